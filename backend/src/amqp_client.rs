@@ -1,5 +1,6 @@
 use lapin::{
     options::*, types::FieldTable, BasicProperties, Channel, Connection, ConnectionProperties,
+    Consumer,
 };
 use serde::{Deserialize, Serialize};
 use tracing::{error, info};
@@ -28,6 +29,70 @@ pub enum JobMessage {
         user_id: String,
         ticket_code: String,
     },
+    AudioWaveform {
+        post_id: String,
+        audio_url: String,
+    },
+    EpisodeAudio {
+        episode_id: String,
+    },
+    EpisodeTranscript {
+        episode_id: String,
+    },
+    PostImport {
+        import_id: String,
+    },
+    ArticleImport {
+        import_id: String,
+    },
+    PodcastImport {
+        import_id: String,
+    },
+    EpisodeMirror {
+        episode_id: String,
+    },
+    PollResultsPublished {
+        poll_id: String,
+        user_id: String,
+        poll_title: String,
+    },
+    DisputeOpened {
+        dispute_id: String,
+        user_id: String,
+        amount_cents: i64,
+        currency: String,
+        reason: Option<String>,
+        evidence_due_by: Option<String>,
+    },
+    DisputeClosed {
+        dispute_id: String,
+        user_id: String,
+        status: String,
+    },
+    RefundIssued {
+        refund_id: String,
+        user_id: String,
+        email: String,
+        amount_cents: i64,
+        currency: String,
+        reason: String,
+    },
+    SendEmail {
+        delivery_id: String,
+    },
+    AnnouncementBatch {
+        announcement_id: String,
+        batch: i32,
+    },
+    PayoutSummary {
+        withdrawal_id: String,
+        user_id: String,
+        email: String,
+        amount_cents: i64,
+        currency: String,
+        arrival_date: Option<String>,
+        transactions: Vec<serde_json::Value>,
+    },
 }
 
 impl AmqpClient {
@@ -69,6 +134,72 @@ impl AmqpClient {
             )
             .await?;
 
+        channel
+            .queue_declare(
+                "media_processing",
+                QueueDeclareOptions {
+                    durable: true,
+                    ..Default::default()
+                },
+                FieldTable::default(),
+            )
+            .await?;
+
+        channel
+            .queue_declare(
+                "poll_notifications",
+                QueueDeclareOptions {
+                    durable: true,
+                    ..Default::default()
+                },
+                FieldTable::default(),
+            )
+            .await?;
+
+        channel
+            .queue_declare(
+                "payment_notifications",
+                QueueDeclareOptions {
+                    durable: true,
+                    ..Default::default()
+                },
+                FieldTable::default(),
+            )
+            .await?;
+
+        channel
+            .queue_declare(
+                "email_notifications",
+                QueueDeclareOptions {
+                    durable: true,
+                    ..Default::default()
+                },
+                FieldTable::default(),
+            )
+            .await?;
+
+        channel
+            .queue_declare(
+                "announcements",
+                QueueDeclareOptions {
+                    durable: true,
+                    ..Default::default()
+                },
+                FieldTable::default(),
+            )
+            .await?;
+
+        channel
+            .queue_declare(
+                "content_imports",
+                QueueDeclareOptions {
+                    durable: true,
+                    ..Default::default()
+                },
+                FieldTable::default(),
+            )
+            .await?;
+
         info!("✅ CloudAMQP connected successfully");
 
         Ok(Self { channel })
@@ -93,7 +224,28 @@ impl AmqpClient {
         Ok(())
     }
 
+    /// Start consuming a queue, one unacknowledged message at a time
+    pub async fn consume(&self, queue: &str, consumer_tag: &str) -> anyhow::Result<Consumer> {
+        self.channel
+            .basic_qos(1, BasicQosOptions::default())
+            .await?;
+
+        let consumer = self
+            .channel
+            .basic_consume(
+                queue,
+                consumer_tag,
+                BasicConsumeOptions::default(),
+                FieldTable::default(),
+            )
+            .await?;
+
+        info!("Consuming queue '{}' as '{}'", queue, consumer_tag);
+        Ok(consumer)
+    }
+
     /// Send event reminder notification
+    #[allow(dead_code)]
     pub async fn send_event_reminder(
         &self,
         event_id: String,
@@ -126,6 +278,7 @@ impl AmqpClient {
     }
 
     /// Send ticket generated notification
+    #[allow(dead_code)]
     pub async fn send_ticket_notification(
         &self,
         event_id: String,
@@ -139,4 +292,159 @@ impl AmqpClient {
         };
         self.publish_job("event_notifications", &message).await
     }
+
+    /// Queue duration and waveform extraction for an audio post
+    pub async fn send_audio_waveform_job(
+        &self,
+        post_id: String,
+        audio_url: String,
+    ) -> anyhow::Result<()> {
+        let message = JobMessage::AudioWaveform { post_id, audio_url };
+        self.publish_job("media_processing", &message).await
+    }
+
+    /// Queue probing, loudness normalization and chapter generation for an uploaded episode
+    pub async fn send_episode_audio_job(&self, episode_id: String) -> anyhow::Result<()> {
+        let message = JobMessage::EpisodeAudio { episode_id };
+        self.publish_job("media_processing", &message).await
+    }
+
+    /// Queue an automatic transcript of an episode
+    pub async fn send_episode_transcript_job(&self, episode_id: String) -> anyhow::Result<()> {
+        let message = JobMessage::EpisodeTranscript { episode_id };
+        self.publish_job("media_processing", &message).await
+    }
+
+    /// Tell a voter that the results of a poll they answered are out
+    pub async fn send_poll_results_notification(
+        &self,
+        poll_id: String,
+        user_id: String,
+        poll_title: String,
+    ) -> anyhow::Result<()> {
+        let message = JobMessage::PollResultsPublished {
+            poll_id,
+            user_id,
+            poll_title,
+        };
+        self.publish_job("poll_notifications", &message).await
+    }
+
+    /// Tell a creator that a buyer disputed one of their payments, and by when to answer
+    pub async fn send_dispute_opened_notification(
+        &self,
+        dispute_id: String,
+        user_id: String,
+        amount_cents: i64,
+        currency: String,
+        reason: Option<String>,
+        evidence_due_by: Option<String>,
+    ) -> anyhow::Result<()> {
+        let message = JobMessage::DisputeOpened {
+            dispute_id,
+            user_id,
+            amount_cents,
+            currency,
+            reason,
+            evidence_due_by,
+        };
+        self.publish_job("payment_notifications", &message).await
+    }
+
+    /// Tell a creator how a dispute on one of their payments ended
+    pub async fn send_dispute_closed_notification(
+        &self,
+        dispute_id: String,
+        user_id: String,
+        status: String,
+    ) -> anyhow::Result<()> {
+        let message = JobMessage::DisputeClosed {
+            dispute_id,
+            user_id,
+            status,
+        };
+        self.publish_job("payment_notifications", &message).await
+    }
+
+    /// Email a buyer that part or all of a payment was refunded
+    pub async fn send_refund_email(
+        &self,
+        refund_id: String,
+        user_id: String,
+        email: String,
+        amount_cents: i64,
+        currency: String,
+        reason: String,
+    ) -> anyhow::Result<()> {
+        let message = JobMessage::RefundIssued {
+            refund_id,
+            user_id,
+            email,
+            amount_cents,
+            currency,
+            reason,
+        };
+        self.publish_job("email_notifications", &message).await
+    }
+
+    /// Email a creator what an automatic payout paid them for
+    #[allow(clippy::too_many_arguments)]
+    pub async fn send_payout_summary_email(
+        &self,
+        withdrawal_id: String,
+        user_id: String,
+        email: String,
+        amount_cents: i64,
+        currency: String,
+        arrival_date: Option<String>,
+        transactions: Vec<serde_json::Value>,
+    ) -> anyhow::Result<()> {
+        let message = JobMessage::PayoutSummary {
+            withdrawal_id,
+            user_id,
+            email,
+            amount_cents,
+            currency,
+            arrival_date,
+            transactions,
+        };
+        self.publish_job("email_notifications", &message).await
+    }
+
+    /// Queue delivery of one batch of a creator announcement's recipients
+    pub async fn send_announcement_batch(
+        &self,
+        announcement_id: String,
+        batch: i32,
+    ) -> anyhow::Result<()> {
+        let message = JobMessage::AnnouncementBatch {
+            announcement_id,
+            batch,
+        };
+        self.publish_job("announcements", &message).await
+    }
+
+    /// Queue processing of an uploaded post import
+    pub async fn send_post_import_job(&self, import_id: String) -> anyhow::Result<()> {
+        let message = JobMessage::PostImport { import_id };
+        self.publish_job("content_imports", &message).await
+    }
+
+    /// Queue processing of an RSS or Medium article import
+    pub async fn send_article_import_job(&self, import_id: String) -> anyhow::Result<()> {
+        let message = JobMessage::ArticleImport { import_id };
+        self.publish_job("content_imports", &message).await
+    }
+
+    /// Queue an import or re-sync of a podcast from its RSS feed
+    pub async fn send_podcast_import_job(&self, import_id: String) -> anyhow::Result<()> {
+        let message = JobMessage::PodcastImport { import_id };
+        self.publish_job("content_imports", &message).await
+    }
+
+    /// Queue copying an imported episode's audio into our storage
+    pub async fn send_episode_mirror_job(&self, episode_id: String) -> anyhow::Result<()> {
+        let message = JobMessage::EpisodeMirror { episode_id };
+        self.publish_job("media_processing", &message).await
+    }
 }
@@ -0,0 +1,184 @@
+use std::{collections::HashMap, sync::OnceLock, time::Duration};
+
+use axum::http::HeaderMap;
+use chrono::{DateTime, Utc};
+use tokio::sync::mpsc;
+use tracing::{error, warn};
+use uuid::Uuid;
+
+use crate::database::Database;
+
+/// Views waiting in memory before the writer drops new ones.
+const BUFFER_CAPACITY: usize = 10_000;
+/// Views written per batch insert.
+const BATCH_SIZE: usize = 500;
+/// Longest a view waits in the buffer before being written.
+const FLUSH_INTERVAL: Duration = Duration::from_secs(10);
+
+static SENDER: OnceLock<mpsc::Sender<ArticleView>> = OnceLock::new();
+
+#[derive(Debug)]
+pub struct ArticleView {
+    pub article_id: Uuid,
+    pub viewer_id: Option<String>,
+    pub referrer: Option<String>,
+    pub country: Option<String>,
+    pub viewed_at: DateTime<Utc>,
+}
+
+impl ArticleView {
+    /// Build a view from the request headers. Only the referrer's host is kept.
+    pub fn from_headers(article_id: Uuid, viewer_id: Option<String>, headers: &HeaderMap) -> Self {
+        let header = |name: &str| {
+            headers
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(str::trim)
+                .filter(|value| !value.is_empty())
+        };
+
+        let referrer = header("referer").and_then(referrer_host);
+        let country = [
+            "cf-ipcountry",
+            "x-vercel-ip-country",
+            "cloudfront-viewer-country",
+            "x-country-code",
+        ]
+        .iter()
+        .find_map(|name| header(name))
+        .filter(|code| code.len() == 2 && code.chars().all(|c| c.is_ascii_alphabetic()))
+        .map(|code| code.to_ascii_uppercase());
+
+        ArticleView {
+            article_id,
+            viewer_id,
+            referrer,
+            country,
+            viewed_at: Utc::now(),
+        }
+    }
+}
+
+/// Start the background writer that batches article views into Postgres.
+pub fn spawn_writer(db: Database) {
+    let (sender, mut receiver) = mpsc::channel::<ArticleView>(BUFFER_CAPACITY);
+    if SENDER.set(sender).is_err() {
+        return;
+    }
+
+    tokio::spawn(async move {
+        let mut buffer = Vec::with_capacity(BATCH_SIZE);
+        let mut interval = tokio::time::interval(FLUSH_INTERVAL);
+
+        loop {
+            tokio::select! {
+                view = receiver.recv() => match view {
+                    Some(view) => {
+                        buffer.push(view);
+                        if buffer.len() >= BATCH_SIZE {
+                            flush(&db, &mut buffer).await;
+                        }
+                    }
+                    None => {
+                        flush(&db, &mut buffer).await;
+                        break;
+                    }
+                },
+                _ = interval.tick() => flush(&db, &mut buffer).await,
+            }
+        }
+    });
+}
+
+/// Queue a view for the writer. Views are dropped rather than blocking the request when the
+/// buffer is full or the writer is not running.
+pub fn record(view: ArticleView) {
+    match SENDER.get() {
+        Some(sender) => {
+            if sender.try_send(view).is_err() {
+                warn!("Article view buffer is full; dropping view");
+            }
+        }
+        None => warn!("Article view writer is not running; dropping view"),
+    }
+}
+
+async fn flush(db: &Database, buffer: &mut Vec<ArticleView>) {
+    if buffer.is_empty() {
+        return;
+    }
+
+    let views = std::mem::take(buffer);
+    if let Err(e) = write_views(db, &views).await {
+        error!("Failed to write {} article views: {:?}", views.len(), e);
+    }
+}
+
+async fn write_views(db: &Database, views: &[ArticleView]) -> anyhow::Result<()> {
+    let article_ids: Vec<Uuid> = views.iter().map(|view| view.article_id).collect();
+    let viewer_ids: Vec<Option<String>> = views.iter().map(|view| view.viewer_id.clone()).collect();
+    let referrers: Vec<Option<String>> = views.iter().map(|view| view.referrer.clone()).collect();
+    let countries: Vec<Option<String>> = views.iter().map(|view| view.country.clone()).collect();
+    let viewed_at: Vec<DateTime<Utc>> = views.iter().map(|view| view.viewed_at).collect();
+
+    let mut per_article: HashMap<Uuid, i64> = HashMap::new();
+    for view in views {
+        *per_article.entry(view.article_id).or_default() += 1;
+    }
+    let (counted_ids, counts): (Vec<Uuid>, Vec<i64>) = per_article.into_iter().unzip();
+
+    let mut tx = db.pool.begin().await?;
+
+    // Views of articles deleted in the meantime are dropped by the join.
+    sqlx::query(
+        r#"
+        INSERT INTO article_views (article_id, viewer_id, referrer, country, viewed_at)
+        SELECT v.article_id, v.viewer_id, v.referrer, v.country, v.viewed_at
+        FROM UNNEST($1::UUID[], $2::TEXT[], $3::TEXT[], $4::TEXT[], $5::TIMESTAMPTZ[])
+            AS v(article_id, viewer_id, referrer, country, viewed_at)
+        JOIN articles a ON a.id = v.article_id
+        "#,
+    )
+    .bind(&article_ids)
+    .bind(&viewer_ids)
+    .bind(&referrers)
+    .bind(&countries)
+    .bind(&viewed_at)
+    .execute(&mut tx)
+    .await?;
+
+    sqlx::query(
+        r#"
+        UPDATE articles a
+        SET view_count = a.view_count + c.views
+        FROM UNNEST($1::UUID[], $2::BIGINT[]) AS c(article_id, views)
+        WHERE a.id = c.article_id
+        "#,
+    )
+    .bind(&counted_ids)
+    .bind(&counts)
+    .execute(&mut tx)
+    .await?;
+
+    tx.commit().await?;
+    Ok(())
+}
+
+fn referrer_host(referrer: &str) -> Option<String> {
+    let without_scheme = referrer.split_once("://").map(|(_, rest)| rest)?;
+    let host = without_scheme
+        .split(['/', '?', '#'])
+        .next()?
+        .rsplit('@')
+        .next()?
+        .split(':')
+        .next()?
+        .trim_start_matches("www.")
+        .to_ascii_lowercase();
+
+    if host.is_empty() {
+        None
+    } else {
+        Some(host)
+    }
+}
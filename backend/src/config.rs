@@ -1,6 +1,11 @@
 use serde::{Deserialize, Serialize};
 use std::env;
 
+/// Signs the webhooks of the sandbox provider when no `STRIPE_WEBHOOK_SECRET` is set.
+pub const SANDBOX_WEBHOOK_SECRET: &str = "whsec_sandbox";
+/// Stands in for `STRIPE_SECRET_KEY` in sandbox mode; the mock provider accepts any key.
+pub const SANDBOX_SECRET_KEY: &str = "sk_test_sandbox";
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Config {
     pub database_url: String,
@@ -17,6 +22,11 @@ pub struct Config {
     pub stripe_publishable_key: String,
     pub stripe_secret_key: String,
     pub stripe_webhook_secret: String,
+    /// Stripe calls go to the in-process mock provider instead of Stripe.
+    pub payments_sandbox: bool,
+    /// Empty disables crypto donations.
+    pub coinbase_commerce_api_key: String,
+    pub coinbase_commerce_webhook_secret: String,
     pub supabase_url: String,
     pub supabase_anon_key: String,
     pub supabase_service_role_key: String,
@@ -51,7 +61,13 @@ impl Config {
             stripe_publishable_key: env::var("STRIPE_PUBLISHABLE_KEY")
                 .unwrap_or_else(|_| "".to_string()),
             stripe_secret_key: env::var("STRIPE_SECRET_KEY").unwrap_or_else(|_| "".to_string()),
-            stripe_webhook_secret: env::var("STRIPE_WEBHOOK_SECRET")
+            stripe_webhook_secret: env::var("STRIPE_WEBHOOK_SECRET").unwrap_or_else(|_| {
+                if payments_sandbox() { SANDBOX_WEBHOOK_SECRET } else { "" }.to_string()
+            }),
+            payments_sandbox: payments_sandbox(),
+            coinbase_commerce_api_key: env::var("COINBASE_COMMERCE_API_KEY")
+                .unwrap_or_else(|_| "".to_string()),
+            coinbase_commerce_webhook_secret: env::var("COINBASE_COMMERCE_WEBHOOK_SECRET")
                 .unwrap_or_else(|_| "".to_string()),
             supabase_url: env::var("SUPABASE_URL").unwrap_or_else(|_| "".to_string()),
             supabase_anon_key: env::var("SUPABASE_ANON_KEY").unwrap_or_else(|_| "".to_string()),
@@ -66,3 +82,113 @@ impl Config {
         })
     }
 }
+
+/// `PAYMENTS_MODE=sandbox` routes every Stripe call to the mock provider served by this process,
+/// so donations, purchases and tickets can be paid in CI and local development without Stripe
+/// keys. The mock is only compiled in with the `payments-sandbox` feature.
+pub fn payments_sandbox() -> bool {
+    env::var("PAYMENTS_MODE").is_ok_and(|mode| mode.trim().eq_ignore_ascii_case("sandbox"))
+}
+
+/// Base URL of the sandbox provider, standing in for `https://api.stripe.com`. It is also where
+/// buyers land for hosted checkout, so it must be reachable from the browser.
+pub fn payments_sandbox_url() -> String {
+    env::var("PAYMENTS_SANDBOX_URL").unwrap_or_else(|_| {
+        let port = env::var("PORT").unwrap_or_else(|_| "4000".to_string());
+        format!("http://localhost:{}/api/sandbox/stripe", port)
+    })
+}
+
+/// How long a checkout session may stay unpaid before the abandoned checkout job expires it,
+/// from `CHECKOUT_EXPIRY_HOURS`. Stripe expires sessions after a day on its own.
+pub fn checkout_expiry_hours() -> i32 {
+    env::var("CHECKOUT_EXPIRY_HOURS")
+        .ok()
+        .and_then(|hours| hours.trim().parse().ok())
+        .filter(|hours| (1..=24).contains(hours))
+        .unwrap_or(24)
+}
+
+/// Minutes after starting checkout that a buyer who has not paid is emailed a link back to it,
+/// from `CHECKOUT_REMINDER_MINUTES`. Reminders are off when it is unset.
+pub fn checkout_reminder_minutes() -> Option<i32> {
+    env::var("CHECKOUT_REMINDER_MINUTES")
+        .ok()
+        .and_then(|minutes| minutes.trim().parse().ok())
+        .filter(|minutes| *minutes > 0)
+}
+
+/// Outgoing email. `EMAIL_PROVIDER` picks the transport: `smtp`, `ses`, `postmark`, or `log`
+/// (the default), which only writes emails to the log.
+#[derive(Debug, Clone)]
+pub struct EmailConfig {
+    pub provider: String,
+    pub from: String,
+    pub smtp_host: String,
+    pub smtp_port: u16,
+    pub smtp_username: String,
+    pub smtp_password: String,
+    /// SES is sent to over SMTP, with `smtp_username` and `smtp_password` as its SMTP
+    /// credentials.
+    pub ses_region: String,
+    pub postmark_server_token: String,
+    /// Shared secret on the bounce webhooks, passed as `?token=`.
+    pub webhook_secret: String,
+    /// Public base URL of this API, for links in emails that are handled here, such as
+    /// unsubscribing.
+    pub api_url: String,
+}
+
+impl EmailConfig {
+    pub fn from_env() -> Self {
+        dotenvy::dotenv().ok();
+        let var = |name: &str| env::var(name).unwrap_or_default();
+
+        EmailConfig {
+            provider: env::var("EMAIL_PROVIDER")
+                .map(|provider| provider.trim().to_ascii_lowercase())
+                .unwrap_or_else(|_| "log".to_string()),
+            from: env::var("EMAIL_FROM")
+                .unwrap_or_else(|_| "Fundify <no-reply@fundify.app>".to_string()),
+            smtp_host: var("SMTP_HOST"),
+            smtp_port: env::var("SMTP_PORT")
+                .ok()
+                .and_then(|port| port.parse().ok())
+                .unwrap_or(587),
+            smtp_username: var("SMTP_USERNAME"),
+            smtp_password: var("SMTP_PASSWORD"),
+            ses_region: env::var("SES_REGION")
+                .or_else(|_| env::var("AWS_REGION"))
+                .unwrap_or_else(|_| "us-east-1".to_string()),
+            postmark_server_token: var("POSTMARK_SERVER_TOKEN"),
+            webhook_secret: var("EMAIL_WEBHOOK_SECRET"),
+            api_url: env::var("API_URL").unwrap_or_else(|_| {
+                let port = env::var("PORT").unwrap_or_else(|_| "4000".to_string());
+                format!("http://localhost:{}", port)
+            }),
+        }
+    }
+}
+
+/// Speech-to-text for podcast transcripts. `TRANSCRIPTION_PROVIDER` picks the service:
+/// `deepgram`, or `none` (the default), which leaves automatic transcripts unavailable.
+#[derive(Debug, Clone)]
+pub struct TranscriptionConfig {
+    pub provider: String,
+    pub deepgram_api_key: String,
+    pub deepgram_model: String,
+}
+
+impl TranscriptionConfig {
+    pub fn from_env() -> Self {
+        dotenvy::dotenv().ok();
+
+        TranscriptionConfig {
+            provider: env::var("TRANSCRIPTION_PROVIDER")
+                .map(|provider| provider.trim().to_ascii_lowercase())
+                .unwrap_or_else(|_| "none".to_string()),
+            deepgram_api_key: env::var("DEEPGRAM_API_KEY").unwrap_or_default(),
+            deepgram_model: env::var("DEEPGRAM_MODEL").unwrap_or_else(|_| "nova-2".to_string()),
+        }
+    }
+}
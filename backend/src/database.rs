@@ -12,6 +12,7 @@ pub struct Database {
 }
 
 impl Database {
+    #[allow(dead_code)]
     pub async fn new(database_url: &str) -> anyhow::Result<Self> {
         let pool = PgPoolOptions::new()
             .max_connections(10)
@@ -22,6 +23,7 @@ impl Database {
         Ok(Database { pool, redis: None, amqp: None })
     }
 
+    #[allow(dead_code)]
     pub async fn with_redis(database_url: &str, redis_url: &str) -> anyhow::Result<Self> {
         let pool = PgPoolOptions::new()
             .max_connections(10)
@@ -760,6 +762,2237 @@ impl Database {
             .execute(&self.pool)
             .await?;
 
+        sqlx::query(
+            "ALTER TABLE post_comments ADD COLUMN IF NOT EXISTS parent_id UUID REFERENCES post_comments(id) ON DELETE CASCADE",
+        )
+        .execute(&self.pool)
+        .await?;
+
+        // Post publishing schedule
+        sqlx::query("ALTER TABLE posts ADD COLUMN IF NOT EXISTS published BOOLEAN DEFAULT TRUE")
+            .execute(&self.pool)
+            .await?;
+
+        sqlx::query("ALTER TABLE posts ADD COLUMN IF NOT EXISTS published_at TIMESTAMP WITH TIME ZONE")
+            .execute(&self.pool)
+            .await?;
+
+        sqlx::query(
+            "CREATE INDEX IF NOT EXISTS idx_posts_user_published_at ON posts(user_id, published_at)",
+        )
+        .execute(&self.pool)
+        .await?;
+
+        // Audio post metadata produced by the media worker
+        sqlx::query("ALTER TABLE posts ADD COLUMN IF NOT EXISTS audio_duration_seconds DOUBLE PRECISION")
+            .execute(&self.pool)
+            .await?;
+
+        sqlx::query("ALTER TABLE posts ADD COLUMN IF NOT EXISTS audio_waveform JSONB")
+            .execute(&self.pool)
+            .await?;
+
+        sqlx::query("ALTER TABLE posts ADD COLUMN IF NOT EXISTS audio_processing_status VARCHAR(20)")
+            .execute(&self.pool)
+            .await?;
+
+        // Campaign updates, optionally cross-posted from a post
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS campaign_updates (
+                id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+                campaign_id UUID NOT NULL REFERENCES campaigns(id) ON DELETE CASCADE,
+                author_id TEXT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+                post_id UUID UNIQUE REFERENCES posts(id) ON DELETE SET NULL,
+                title VARCHAR(255) NOT NULL,
+                content TEXT NOT NULL,
+                published_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+                created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+                updated_at TIMESTAMP WITH TIME ZONE DEFAULT NOW()
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query(
+            "CREATE INDEX IF NOT EXISTS idx_campaign_updates_campaign ON campaign_updates(campaign_id, published_at)",
+        )
+        .execute(&self.pool)
+        .await?;
+
+        // Post attachments
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS post_attachments (
+                id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+                post_id UUID NOT NULL REFERENCES posts(id) ON DELETE CASCADE,
+                user_id TEXT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+                upload_id UUID NOT NULL,
+                filename TEXT NOT NULL,
+                size_bytes BIGINT NOT NULL,
+                mime_type VARCHAR(255) NOT NULL,
+                storage_key TEXT NOT NULL,
+                created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW()
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query(
+            "CREATE INDEX IF NOT EXISTS idx_post_attachments_post ON post_attachments(post_id)",
+        )
+        .execute(&self.pool)
+        .await?;
+
+        // Pay-per-view post unlocks
+        sqlx::query("ALTER TABLE posts ADD COLUMN IF NOT EXISTS unlock_price DOUBLE PRECISION")
+            .execute(&self.pool)
+            .await?;
+
+        sqlx::query(
+            "ALTER TABLE posts ADD COLUMN IF NOT EXISTS unlock_currency VARCHAR(3) DEFAULT 'USD'",
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS post_unlocks (
+                id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+                post_id UUID NOT NULL REFERENCES posts(id) ON DELETE CASCADE,
+                user_id TEXT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+                amount DOUBLE PRECISION NOT NULL,
+                currency VARCHAR(3) DEFAULT 'USD',
+                status VARCHAR(50) NOT NULL,
+                stripe_checkout_session_id VARCHAR(255),
+                stripe_payment_intent_id VARCHAR(255),
+                created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+                updated_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+                UNIQUE(post_id, user_id)
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query("CREATE INDEX IF NOT EXISTS idx_post_unlocks_user ON post_unlocks(user_id)")
+            .execute(&self.pool)
+            .await?;
+
+        sqlx::query(
+            "CREATE INDEX IF NOT EXISTS idx_post_unlocks_session ON post_unlocks(stripe_checkout_session_id)",
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS post_view_stats (
+                post_id UUID NOT NULL REFERENCES posts(id) ON DELETE CASCADE,
+                hour TIMESTAMPTZ NOT NULL,
+                views BIGINT NOT NULL DEFAULT 0,
+                unique_viewers BIGINT NOT NULL DEFAULT 0,
+                subscriber_views BIGINT NOT NULL DEFAULT 0,
+                non_subscriber_views BIGINT NOT NULL DEFAULT 0,
+                PRIMARY KEY (post_id, hour)
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS post_viewers (
+                post_id UUID NOT NULL REFERENCES posts(id) ON DELETE CASCADE,
+                viewer_key TEXT NOT NULL,
+                first_seen_at TIMESTAMPTZ DEFAULT NOW(),
+                PRIMARY KEY (post_id, viewer_key)
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query("ALTER TABLE posts ADD COLUMN IF NOT EXISTS external_id TEXT")
+            .execute(&self.pool)
+            .await?;
+
+        sqlx::query(
+            "CREATE INDEX IF NOT EXISTS idx_posts_user_external_id ON posts(user_id, external_id)",
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS post_imports (
+                id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+                user_id TEXT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+                source VARCHAR(50) NOT NULL DEFAULT 'patreon',
+                filename TEXT,
+                storage_key TEXT NOT NULL,
+                status VARCHAR(20) NOT NULL DEFAULT 'QUEUED',
+                total_rows INTEGER NOT NULL DEFAULT 0,
+                processed_rows INTEGER NOT NULL DEFAULT 0,
+                imported_posts INTEGER NOT NULL DEFAULT 0,
+                skipped_rows INTEGER NOT NULL DEFAULT 0,
+                error TEXT,
+                created_at TIMESTAMPTZ DEFAULT NOW(),
+                updated_at TIMESTAMPTZ DEFAULT NOW(),
+                completed_at TIMESTAMPTZ
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query("CREATE INDEX IF NOT EXISTS idx_post_imports_user ON post_imports(user_id)")
+            .execute(&self.pool)
+            .await?;
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS article_reading_progress (
+                user_id TEXT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+                article_id UUID NOT NULL REFERENCES articles(id) ON DELETE CASCADE,
+                percentage DOUBLE PRECISION NOT NULL DEFAULT 0,
+                paragraph_anchor VARCHAR(255),
+                updated_at TIMESTAMPTZ DEFAULT NOW(),
+                PRIMARY KEY (user_id, article_id)
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query("ALTER TABLE users ADD COLUMN IF NOT EXISTS is_admin BOOLEAN DEFAULT FALSE")
+            .execute(&self.pool)
+            .await?;
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS categories (
+                id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+                name VARCHAR(100) NOT NULL,
+                slug VARCHAR(120) UNIQUE NOT NULL,
+                created_by TEXT REFERENCES users(id) ON DELETE SET NULL,
+                created_at TIMESTAMPTZ DEFAULT NOW(),
+                updated_at TIMESTAMPTZ DEFAULT NOW()
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS tags (
+                id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+                name VARCHAR(100) NOT NULL,
+                slug VARCHAR(120) UNIQUE NOT NULL,
+                created_by TEXT REFERENCES users(id) ON DELETE SET NULL,
+                created_at TIMESTAMPTZ DEFAULT NOW(),
+                updated_at TIMESTAMPTZ DEFAULT NOW()
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query(
+            "ALTER TABLE articles ADD COLUMN IF NOT EXISTS category_id UUID REFERENCES categories(id) ON DELETE SET NULL",
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query("CREATE INDEX IF NOT EXISTS idx_articles_category ON articles(category_id)")
+            .execute(&self.pool)
+            .await?;
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS article_tags (
+                article_id UUID NOT NULL REFERENCES articles(id) ON DELETE CASCADE,
+                tag_id UUID NOT NULL REFERENCES tags(id) ON DELETE CASCADE,
+                PRIMARY KEY (article_id, tag_id)
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query("CREATE INDEX IF NOT EXISTS idx_article_tags_tag ON article_tags(tag_id)")
+            .execute(&self.pool)
+            .await?;
+
+        sqlx::query(
+            "ALTER TABLE article_comments ADD COLUMN IF NOT EXISTS parent_id UUID REFERENCES article_comments(id) ON DELETE CASCADE",
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query(
+            "CREATE INDEX IF NOT EXISTS idx_article_comments_parent ON article_comments(parent_id)",
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS article_comment_likes (
+                comment_id UUID NOT NULL REFERENCES article_comments(id) ON DELETE CASCADE,
+                user_id VARCHAR(255) NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+                created_at TIMESTAMPTZ DEFAULT NOW(),
+                PRIMARY KEY (comment_id, user_id)
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query("ALTER TABLE articles ADD COLUMN IF NOT EXISTS is_premium BOOLEAN NOT NULL DEFAULT FALSE")
+            .execute(&self.pool)
+            .await?;
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS article_series (
+                id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+                author_id VARCHAR(255) NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+                title VARCHAR(255) NOT NULL,
+                slug VARCHAR(255) UNIQUE NOT NULL,
+                description TEXT,
+                created_at TIMESTAMPTZ DEFAULT NOW(),
+                updated_at TIMESTAMPTZ DEFAULT NOW()
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS article_series_items (
+                article_id UUID PRIMARY KEY REFERENCES articles(id) ON DELETE CASCADE,
+                series_id UUID NOT NULL REFERENCES article_series(id) ON DELETE CASCADE,
+                position INTEGER NOT NULL DEFAULT 0
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query(
+            "CREATE INDEX IF NOT EXISTS idx_article_series_items_series ON article_series_items(series_id, position)",
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query("ALTER TABLE articles ADD COLUMN IF NOT EXISTS view_count BIGINT NOT NULL DEFAULT 0")
+            .execute(&self.pool)
+            .await?;
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS article_views (
+                id BIGSERIAL PRIMARY KEY,
+                article_id UUID NOT NULL REFERENCES articles(id) ON DELETE CASCADE,
+                viewer_id TEXT,
+                referrer TEXT,
+                country VARCHAR(2),
+                viewed_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query(
+            "CREATE INDEX IF NOT EXISTS idx_article_views_article_time ON article_views(article_id, viewed_at)",
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query("ALTER TABLE articles ADD COLUMN IF NOT EXISTS external_id TEXT")
+            .execute(&self.pool)
+            .await?;
+
+        sqlx::query(
+            "CREATE INDEX IF NOT EXISTS idx_articles_author_external_id ON articles(author_id, external_id)",
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS article_imports (
+                id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+                user_id TEXT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+                source VARCHAR(20) NOT NULL,
+                feed_url TEXT,
+                filename TEXT,
+                storage_key TEXT,
+                status VARCHAR(20) NOT NULL DEFAULT 'QUEUED',
+                total_items INTEGER NOT NULL DEFAULT 0,
+                processed_items INTEGER NOT NULL DEFAULT 0,
+                imported_articles INTEGER NOT NULL DEFAULT 0,
+                skipped_items INTEGER NOT NULL DEFAULT 0,
+                error TEXT,
+                created_at TIMESTAMPTZ DEFAULT NOW(),
+                updated_at TIMESTAMPTZ DEFAULT NOW(),
+                completed_at TIMESTAMPTZ
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query("CREATE INDEX IF NOT EXISTS idx_article_imports_user ON article_imports(user_id)")
+            .execute(&self.pool)
+            .await?;
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS calendar_feed_tokens (
+                user_id TEXT PRIMARY KEY REFERENCES users(id) ON DELETE CASCADE,
+                token_hash TEXT UNIQUE NOT NULL,
+                created_at TIMESTAMPTZ DEFAULT NOW()
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS event_ticket_types (
+                id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+                event_id TEXT NOT NULL,
+                name VARCHAR(100) NOT NULL,
+                description TEXT,
+                price DOUBLE PRECISION NOT NULL DEFAULT 0.0,
+                quantity INTEGER,
+                sales_start TIMESTAMPTZ,
+                sales_end TIMESTAMPTZ,
+                position INTEGER NOT NULL DEFAULT 0,
+                created_at TIMESTAMPTZ DEFAULT NOW(),
+                updated_at TIMESTAMPTZ DEFAULT NOW()
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query("CREATE INDEX IF NOT EXISTS idx_event_ticket_types_event ON event_ticket_types(event_id)")
+            .execute(&self.pool)
+            .await?;
+
+        sqlx::query("ALTER TABLE event_rsvps ADD COLUMN IF NOT EXISTS ticket_type_id UUID")
+            .execute(&self.pool)
+            .await?;
+
+        sqlx::query("CREATE INDEX IF NOT EXISTS idx_event_rsvps_ticket_type ON event_rsvps(ticket_type_id)")
+            .execute(&self.pool)
+            .await?;
+
+        for statement in [
+            "ALTER TABLE event_rsvps ADD COLUMN IF NOT EXISTS checked_in_at TIMESTAMPTZ",
+            "ALTER TABLE event_rsvps ADD COLUMN IF NOT EXISTS checked_in_by TEXT",
+            "ALTER TABLE event_rsvps ADD COLUMN IF NOT EXISTS payment_intent_id TEXT",
+            "ALTER TABLE event_rsvps ADD COLUMN IF NOT EXISTS amount_paid DOUBLE PRECISION",
+            "ALTER TABLE event_rsvps ADD COLUMN IF NOT EXISTS refunded_at TIMESTAMPTZ",
+            "ALTER TABLE event_rsvps ADD COLUMN IF NOT EXISTS revoked_at TIMESTAMPTZ",
+        ] {
+            sqlx::query(statement).execute(&self.pool).await?;
+        }
+
+        for statement in [
+            "ALTER TABLE events ADD COLUMN IF NOT EXISTS stream_provider VARCHAR(20)",
+            "ALTER TABLE events ADD COLUMN IF NOT EXISTS stream_id TEXT",
+            "ALTER TABLE events ADD COLUMN IF NOT EXISTS stream_playback_url TEXT",
+            "ALTER TABLE events ADD COLUMN IF NOT EXISTS stream_ingest_url TEXT",
+            "ALTER TABLE events ADD COLUMN IF NOT EXISTS stream_key TEXT",
+            "ALTER TABLE events ADD COLUMN IF NOT EXISTS is_live BOOLEAN NOT NULL DEFAULT FALSE",
+            "ALTER TABLE events ADD COLUMN IF NOT EXISTS live_started_at TIMESTAMPTZ",
+            "ALTER TABLE events ADD COLUMN IF NOT EXISTS live_ended_at TIMESTAMPTZ",
+            "CREATE INDEX IF NOT EXISTS idx_events_stream_id ON events(stream_id) WHERE stream_id IS NOT NULL",
+        ] {
+            sqlx::query(statement).execute(&self.pool).await?;
+        }
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS event_cohosts (
+                event_id TEXT NOT NULL,
+                user_id TEXT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+                added_by TEXT,
+                created_at TIMESTAMPTZ DEFAULT NOW(),
+                PRIMARY KEY (event_id, user_id)
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query("CREATE INDEX IF NOT EXISTS idx_event_cohosts_user ON event_cohosts(user_id)")
+            .execute(&self.pool)
+            .await?;
+
+        for statement in [
+            "ALTER TABLE events ADD COLUMN IF NOT EXISTS latitude DOUBLE PRECISION",
+            "ALTER TABLE events ADD COLUMN IF NOT EXISTS longitude DOUBLE PRECISION",
+            "CREATE INDEX IF NOT EXISTS idx_events_coordinates ON events(latitude, longitude) WHERE latitude IS NOT NULL",
+            r#"
+            ALTER TABLE events ADD COLUMN IF NOT EXISTS search_vector tsvector
+            GENERATED ALWAYS AS (
+                setweight(to_tsvector('simple', COALESCE(title, '')), 'A') ||
+                setweight(to_tsvector('simple', COALESCE(description, '')), 'B')
+            ) STORED
+            "#,
+            "CREATE INDEX IF NOT EXISTS idx_events_search_vector ON events USING GIN(search_vector)",
+            "CREATE INDEX IF NOT EXISTS idx_events_tags ON events USING GIN(tags)",
+        ] {
+            sqlx::query(statement).execute(&self.pool).await?;
+        }
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS event_invites (
+                id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+                event_id TEXT NOT NULL,
+                token TEXT NOT NULL UNIQUE,
+                label VARCHAR(100),
+                max_uses INTEGER,
+                use_count INTEGER NOT NULL DEFAULT 0,
+                expires_at TIMESTAMPTZ,
+                revoked_at TIMESTAMPTZ,
+                created_by TEXT,
+                created_at TIMESTAMPTZ DEFAULT NOW()
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        for statement in [
+            "CREATE INDEX IF NOT EXISTS idx_event_invites_event ON event_invites(event_id)",
+            "ALTER TABLE event_rsvps ADD COLUMN IF NOT EXISTS invite_id UUID",
+        ] {
+            sqlx::query(statement).execute(&self.pool).await?;
+        }
+
+        for statement in [
+            "ALTER TABLE events ADD COLUMN IF NOT EXISTS max_guests_per_rsvp INTEGER NOT NULL DEFAULT 0",
+            "ALTER TABLE event_rsvps ADD COLUMN IF NOT EXISTS guest_count INTEGER NOT NULL DEFAULT 0",
+        ] {
+            sqlx::query(statement).execute(&self.pool).await?;
+        }
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS event_feedback (
+                id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+                event_id TEXT NOT NULL,
+                user_id TEXT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+                rating SMALLINT NOT NULL CHECK (rating BETWEEN 1 AND 5),
+                comment TEXT,
+                created_at TIMESTAMPTZ DEFAULT NOW(),
+                UNIQUE (event_id, user_id)
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS event_guest_check_ins (
+                event_id TEXT NOT NULL,
+                user_id TEXT NOT NULL,
+                guest_number INTEGER NOT NULL,
+                checked_in_at TIMESTAMPTZ NOT NULL,
+                checked_in_by TEXT,
+                PRIMARY KEY (event_id, user_id, guest_number)
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        for statement in [
+            r#"
+            CREATE TABLE IF NOT EXISTS polls (
+                id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+                creator_id TEXT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+                title TEXT NOT NULL,
+                description TEXT,
+                is_public BOOLEAN NOT NULL DEFAULT TRUE,
+                closes_at TIMESTAMPTZ,
+                results_published_at TIMESTAMPTZ,
+                created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+                updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+            )
+            "#,
+            r#"
+            CREATE TABLE IF NOT EXISTS poll_questions (
+                id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+                poll_id UUID NOT NULL REFERENCES polls(id) ON DELETE CASCADE,
+                position INTEGER NOT NULL DEFAULT 0,
+                prompt TEXT NOT NULL,
+                allow_multiple BOOLEAN NOT NULL DEFAULT FALSE
+            )
+            "#,
+            r#"
+            CREATE TABLE IF NOT EXISTS poll_options (
+                id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+                question_id UUID NOT NULL REFERENCES poll_questions(id) ON DELETE CASCADE,
+                position INTEGER NOT NULL DEFAULT 0,
+                label TEXT NOT NULL
+            )
+            "#,
+            r#"
+            CREATE TABLE IF NOT EXISTS poll_votes (
+                id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+                poll_id UUID NOT NULL REFERENCES polls(id) ON DELETE CASCADE,
+                question_id UUID NOT NULL REFERENCES poll_questions(id) ON DELETE CASCADE,
+                option_id UUID NOT NULL REFERENCES poll_options(id) ON DELETE CASCADE,
+                user_id TEXT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+                created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+                UNIQUE (option_id, user_id)
+            )
+            "#,
+            "CREATE INDEX IF NOT EXISTS idx_polls_creator ON polls(creator_id, created_at DESC)",
+            "CREATE INDEX IF NOT EXISTS idx_poll_questions_poll ON poll_questions(poll_id, position)",
+            "CREATE INDEX IF NOT EXISTS idx_poll_options_question ON poll_options(question_id, position)",
+            "CREATE INDEX IF NOT EXISTS idx_poll_votes_poll_user ON poll_votes(poll_id, user_id)",
+            "CREATE INDEX IF NOT EXISTS idx_poll_votes_question_user ON poll_votes(question_id, user_id)",
+        ] {
+            sqlx::query(statement).execute(&self.pool).await?;
+        }
+
+        for statement in [
+            "ALTER TABLE polls ADD COLUMN IF NOT EXISTS is_anonymous BOOLEAN NOT NULL DEFAULT FALSE",
+            "ALTER TABLE polls ADD COLUMN IF NOT EXISTS allowed_tier_ids TEXT[] NOT NULL DEFAULT '{}'",
+            "ALTER TABLE poll_votes ALTER COLUMN user_id DROP NOT NULL",
+            "ALTER TABLE poll_votes ADD COLUMN IF NOT EXISTS voter_hash TEXT",
+            "CREATE UNIQUE INDEX IF NOT EXISTS idx_poll_votes_option_voter_hash ON poll_votes(option_id, voter_hash) WHERE voter_hash IS NOT NULL",
+            "CREATE INDEX IF NOT EXISTS idx_poll_votes_question_voter_hash ON poll_votes(question_id, voter_hash) WHERE voter_hash IS NOT NULL",
+            "ALTER TABLE subscriptions ADD COLUMN IF NOT EXISTS tier_id TEXT",
+        ] {
+            sqlx::query(statement).execute(&self.pool).await?;
+        }
+
+        for statement in [
+            "ALTER TABLE poll_questions ADD COLUMN IF NOT EXISTS question_type TEXT NOT NULL DEFAULT 'SINGLE'",
+            "ALTER TABLE poll_questions ADD COLUMN IF NOT EXISTS max_selections INTEGER",
+            "UPDATE poll_questions SET question_type = 'MULTIPLE' WHERE allow_multiple AND question_type = 'SINGLE'",
+            "ALTER TABLE poll_votes ADD COLUMN IF NOT EXISTS rank INTEGER",
+        ] {
+            sqlx::query(statement).execute(&self.pool).await?;
+        }
+
+        for statement in [
+            "ALTER TABLE polls ADD COLUMN IF NOT EXISTS status TEXT NOT NULL DEFAULT 'OPEN'",
+            "ALTER TABLE polls ADD COLUMN IF NOT EXISTS post_id UUID REFERENCES posts(id) ON DELETE CASCADE",
+            "ALTER TABLE polls ADD COLUMN IF NOT EXISTS duration_minutes INTEGER",
+            "CREATE INDEX IF NOT EXISTS idx_polls_post ON polls(post_id) WHERE post_id IS NOT NULL",
+        ] {
+            sqlx::query(statement).execute(&self.pool).await?;
+        }
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS poll_templates (
+                id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+                creator_id TEXT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+                name TEXT NOT NULL,
+                title TEXT,
+                description TEXT,
+                questions JSONB NOT NULL DEFAULT '[]'::jsonb,
+                is_public BOOLEAN NOT NULL DEFAULT TRUE,
+                is_anonymous BOOLEAN NOT NULL DEFAULT FALSE,
+                allowed_tier_ids TEXT[] NOT NULL DEFAULT '{}',
+                duration_minutes INTEGER,
+                use_count INTEGER NOT NULL DEFAULT 0,
+                last_used_at TIMESTAMPTZ,
+                created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+                updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+        sqlx::query("CREATE INDEX IF NOT EXISTS idx_poll_templates_creator ON poll_templates(creator_id)")
+            .execute(&self.pool)
+            .await?;
+
+        for statement in [
+            "ALTER TABLE polls ADD COLUMN IF NOT EXISTS is_quiz BOOLEAN NOT NULL DEFAULT FALSE",
+            "ALTER TABLE poll_options ADD COLUMN IF NOT EXISTS is_correct BOOLEAN NOT NULL DEFAULT FALSE",
+            "ALTER TABLE poll_templates ADD COLUMN IF NOT EXISTS is_quiz BOOLEAN NOT NULL DEFAULT FALSE",
+        ] {
+            sqlx::query(statement).execute(&self.pool).await?;
+        }
+
+        // Stripe Connect accounts of creators, kept in sync by `account.updated` webhooks
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS creator_connect_accounts (
+                user_id TEXT PRIMARY KEY REFERENCES users(id) ON DELETE CASCADE,
+                stripe_account_id TEXT NOT NULL UNIQUE,
+                status TEXT NOT NULL DEFAULT 'PENDING',
+                charges_enabled BOOLEAN NOT NULL DEFAULT FALSE,
+                payouts_enabled BOOLEAN NOT NULL DEFAULT FALSE,
+                details_submitted BOOLEAN NOT NULL DEFAULT FALSE,
+                requirements_due TEXT[] NOT NULL DEFAULT '{}',
+                disabled_reason TEXT,
+                created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+                updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        // Stripe webhook events already seen, so retried deliveries are processed once
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS stripe_events (
+                id TEXT PRIMARY KEY,
+                event_type TEXT NOT NULL,
+                handled BOOLEAN NOT NULL DEFAULT FALSE,
+                received_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+                processed_at TIMESTAMPTZ
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        // Platform fees per product type, and the ledger of what each charge owes creators
+        for statement in [
+            r#"
+            CREATE TABLE IF NOT EXISTS platform_fee_schedules (
+                product_type TEXT PRIMARY KEY,
+                fee_basis_points INTEGER NOT NULL DEFAULT 0,
+                fixed_fee_cents INTEGER NOT NULL DEFAULT 0,
+                updated_by VARCHAR(255) REFERENCES users(id) ON DELETE SET NULL,
+                updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+            )
+            "#,
+            r#"
+            INSERT INTO platform_fee_schedules (product_type, fee_basis_points)
+            VALUES ('DONATION', 500), ('MEMBERSHIP', 800), ('PRODUCT', 500), ('TICKET', 500)
+            ON CONFLICT (product_type) DO NOTHING
+            "#,
+            r#"
+            CREATE TABLE IF NOT EXISTS ledger_entries (
+                id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+                product_type TEXT NOT NULL,
+                source_type TEXT NOT NULL,
+                source_id TEXT NOT NULL,
+                creator_id VARCHAR(255) NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+                payer_id VARCHAR(255) REFERENCES users(id) ON DELETE SET NULL,
+                currency VARCHAR(10) NOT NULL DEFAULT 'USD',
+                gross_cents BIGINT NOT NULL,
+                fee_cents BIGINT NOT NULL DEFAULT 0,
+                net_cents BIGINT NOT NULL,
+                status TEXT NOT NULL DEFAULT 'PENDING',
+                stripe_account_id TEXT,
+                stripe_checkout_session_id TEXT,
+                stripe_payment_intent_id TEXT,
+                created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+                settled_at TIMESTAMPTZ
+            )
+            "#,
+            "CREATE INDEX IF NOT EXISTS idx_ledger_entries_creator ON ledger_entries(creator_id, status)",
+            "CREATE INDEX IF NOT EXISTS idx_ledger_entries_session ON ledger_entries(stripe_checkout_session_id)",
+            "CREATE INDEX IF NOT EXISTS idx_ledger_entries_payment_intent ON ledger_entries(stripe_payment_intent_id)",
+        ] {
+            sqlx::query(statement).execute(&self.pool).await?;
+        }
+
+        // Creator payouts drawn from their settled ledger balance
+        for statement in [
+            r#"
+            CREATE TABLE IF NOT EXISTS withdrawals (
+                id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+                creator_id VARCHAR(255) NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+                amount_cents BIGINT NOT NULL,
+                currency VARCHAR(10) NOT NULL DEFAULT 'USD',
+                status TEXT NOT NULL DEFAULT 'PENDING',
+                stripe_account_id TEXT NOT NULL,
+                transfer_cents BIGINT NOT NULL DEFAULT 0,
+                stripe_transfer_id TEXT,
+                stripe_payout_id TEXT UNIQUE,
+                failure_reason TEXT,
+                arrival_date TIMESTAMPTZ,
+                paid_at TIMESTAMPTZ,
+                created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+                updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+            )
+            "#,
+            "CREATE INDEX IF NOT EXISTS idx_withdrawals_creator ON withdrawals(creator_id, created_at DESC)",
+        ] {
+            sqlx::query(statement).execute(&self.pool).await?;
+        }
+
+        sqlx::query("ALTER TABLE users ADD COLUMN IF NOT EXISTS stripe_customer_id TEXT UNIQUE")
+            .execute(&self.pool)
+            .await?;
+
+        // Campaign donations, counted toward the campaign once their payment succeeds
+        for statement in [
+            r#"
+            CREATE TABLE IF NOT EXISTS donations (
+                id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+                campaign_id UUID NOT NULL REFERENCES campaigns(id) ON DELETE CASCADE,
+                donor_id VARCHAR(255) REFERENCES users(id) ON DELETE SET NULL,
+                amount DOUBLE PRECISION NOT NULL,
+                currency VARCHAR(10) NOT NULL DEFAULT 'USD',
+                status TEXT NOT NULL DEFAULT 'PENDING',
+                message TEXT,
+                is_anonymous BOOLEAN NOT NULL DEFAULT FALSE,
+                stripe_payment_intent_id TEXT UNIQUE,
+                created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+                updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+            )
+            "#,
+            "CREATE INDEX IF NOT EXISTS idx_donations_campaign ON donations(campaign_id, status)",
+        ] {
+            sqlx::query(statement).execute(&self.pool).await?;
+        }
+
+        // Invoices, numbered per creator
+        for statement in [
+            r#"
+            CREATE TABLE IF NOT EXISTS creator_invoice_counters (
+                creator_id VARCHAR(255) PRIMARY KEY REFERENCES users(id) ON DELETE CASCADE,
+                last_number INTEGER NOT NULL DEFAULT 0
+            )
+            "#,
+            r#"
+            CREATE TABLE IF NOT EXISTS invoices (
+                id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+                creator_id VARCHAR(255) NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+                buyer_id VARCHAR(255) REFERENCES users(id) ON DELETE SET NULL,
+                invoice_number TEXT NOT NULL,
+                sequence INTEGER NOT NULL,
+                source_type TEXT NOT NULL,
+                source_id TEXT NOT NULL,
+                subscription_id UUID REFERENCES subscriptions(id) ON DELETE SET NULL,
+                description TEXT NOT NULL,
+                amount_cents BIGINT NOT NULL,
+                currency VARCHAR(10) NOT NULL,
+                storage_key TEXT,
+                issued_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+                UNIQUE (creator_id, sequence),
+                UNIQUE (source_type, source_id)
+            )
+            "#,
+            "CREATE INDEX IF NOT EXISTS idx_invoices_subscription ON invoices(subscription_id)",
+        ] {
+            sqlx::query(statement).execute(&self.pool).await?;
+        }
+
+        // Tax rates on digital goods by buyer location, and the tax charged on each sale
+        for statement in [
+            r#"
+            CREATE TABLE IF NOT EXISTS tax_rates (
+                country_code VARCHAR(2) NOT NULL,
+                region_code VARCHAR(10) NOT NULL DEFAULT '',
+                tax_name TEXT NOT NULL,
+                rate_basis_points INTEGER NOT NULL,
+                updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+                PRIMARY KEY (country_code, region_code)
+            )
+            "#,
+            r#"
+            INSERT INTO tax_rates (country_code, tax_name, rate_basis_points)
+            VALUES
+                ('AT', 'VAT', 2000), ('BE', 'VAT', 2100), ('BG', 'VAT', 2000), ('HR', 'VAT', 2500),
+                ('CY', 'VAT', 1900), ('CZ', 'VAT', 2100), ('DK', 'VAT', 2500), ('EE', 'VAT', 2400),
+                ('FI', 'VAT', 2550), ('FR', 'VAT', 2000), ('DE', 'VAT', 1900), ('GR', 'VAT', 2400),
+                ('HU', 'VAT', 2700), ('IE', 'VAT', 2300), ('IT', 'VAT', 2200), ('LV', 'VAT', 2100),
+                ('LT', 'VAT', 2100), ('LU', 'VAT', 1700), ('MT', 'VAT', 1800), ('NL', 'VAT', 2100),
+                ('PL', 'VAT', 2300), ('PT', 'VAT', 2300), ('RO', 'VAT', 2100), ('SK', 'VAT', 2300),
+                ('SI', 'VAT', 2200), ('ES', 'VAT', 2100), ('SE', 'VAT', 2500),
+                ('GB', 'VAT', 2000), ('NO', 'VAT', 2500), ('CH', 'VAT', 810),
+                ('AU', 'GST', 1000), ('NZ', 'GST', 1500), ('SG', 'GST', 900)
+            ON CONFLICT (country_code, region_code) DO NOTHING
+            "#,
+            r#"
+            CREATE TABLE IF NOT EXISTS tax_lines (
+                id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+                source_type TEXT NOT NULL,
+                source_id TEXT NOT NULL,
+                creator_id VARCHAR(255) NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+                buyer_id VARCHAR(255) REFERENCES users(id) ON DELETE SET NULL,
+                country VARCHAR(2),
+                region VARCHAR(10),
+                tax_name TEXT,
+                rate_basis_points INTEGER NOT NULL DEFAULT 0,
+                taxable_cents BIGINT NOT NULL,
+                tax_cents BIGINT NOT NULL DEFAULT 0,
+                currency VARCHAR(10) NOT NULL,
+                evidence JSONB NOT NULL DEFAULT '{}'::jsonb,
+                status TEXT NOT NULL DEFAULT 'PENDING',
+                stripe_checkout_session_id TEXT,
+                stripe_payment_intent_id TEXT,
+                created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+                settled_at TIMESTAMPTZ
+            )
+            "#,
+            "CREATE INDEX IF NOT EXISTS idx_tax_lines_creator ON tax_lines(creator_id, status, settled_at)",
+            "CREATE INDEX IF NOT EXISTS idx_tax_lines_session ON tax_lines(stripe_checkout_session_id)",
+            "CREATE INDEX IF NOT EXISTS idx_tax_lines_payment_intent ON tax_lines(stripe_payment_intent_id)",
+            "ALTER TABLE ledger_entries ADD COLUMN IF NOT EXISTS tax_cents BIGINT NOT NULL DEFAULT 0",
+        ] {
+            sqlx::query(statement).execute(&self.pool).await?;
+        }
+
+        // Chargebacks, and the ledger entries frozen while they are open
+        for statement in [
+            r#"
+            CREATE TABLE IF NOT EXISTS disputes (
+                id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+                stripe_dispute_id TEXT NOT NULL UNIQUE,
+                stripe_charge_id TEXT,
+                stripe_payment_intent_id TEXT,
+                creator_id VARCHAR(255) REFERENCES users(id) ON DELETE SET NULL,
+                amount_cents BIGINT NOT NULL,
+                currency VARCHAR(10) NOT NULL,
+                reason TEXT,
+                status TEXT NOT NULL DEFAULT 'OPEN',
+                stripe_status TEXT NOT NULL,
+                frozen_cents BIGINT NOT NULL DEFAULT 0,
+                evidence_due_by TIMESTAMPTZ,
+                evidence_submitted_at TIMESTAMPTZ,
+                created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+                updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+                closed_at TIMESTAMPTZ
+            )
+            "#,
+            "CREATE INDEX IF NOT EXISTS idx_disputes_queue ON disputes(status, evidence_due_by)",
+            "CREATE INDEX IF NOT EXISTS idx_disputes_creator ON disputes(creator_id)",
+            "ALTER TABLE ledger_entries ADD COLUMN IF NOT EXISTS dispute_id UUID REFERENCES disputes(id) ON DELETE SET NULL",
+            "CREATE INDEX IF NOT EXISTS idx_ledger_entries_dispute ON ledger_entries(dispute_id)",
+        ] {
+            sqlx::query(statement).execute(&self.pool).await?;
+        }
+
+        // Refunds issued by creators, each offset in the ledger by a negative entry
+        for statement in [
+            r#"
+            CREATE TABLE IF NOT EXISTS refunds (
+                id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+                ledger_entry_id UUID NOT NULL REFERENCES ledger_entries(id) ON DELETE CASCADE,
+                creator_id VARCHAR(255) NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+                buyer_id VARCHAR(255) REFERENCES users(id) ON DELETE SET NULL,
+                amount_cents BIGINT NOT NULL,
+                tax_cents BIGINT NOT NULL DEFAULT 0,
+                fee_cents BIGINT NOT NULL DEFAULT 0,
+                currency VARCHAR(10) NOT NULL,
+                reason TEXT NOT NULL,
+                note TEXT,
+                status TEXT NOT NULL DEFAULT 'PENDING',
+                stripe_refund_id TEXT UNIQUE,
+                failure_reason TEXT,
+                created_by VARCHAR(255) NOT NULL,
+                created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+                updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+            )
+            "#,
+            "CREATE INDEX IF NOT EXISTS idx_refunds_ledger_entry ON refunds(ledger_entry_id)",
+            "CREATE INDEX IF NOT EXISTS idx_refunds_creator ON refunds(creator_id, created_at DESC)",
+        ] {
+            sqlx::query(statement).execute(&self.pool).await?;
+        }
+
+        // Automatic payout preferences
+        for statement in [
+            r#"
+            CREATE TABLE IF NOT EXISTS payout_schedules (
+                creator_id VARCHAR(255) PRIMARY KEY REFERENCES users(id) ON DELETE CASCADE,
+                frequency TEXT NOT NULL DEFAULT 'MANUAL',
+                minimum_cents BIGINT NOT NULL DEFAULT 1000,
+                weekly_day SMALLINT NOT NULL DEFAULT 1,
+                monthly_day SMALLINT NOT NULL DEFAULT 1,
+                next_payout_at TIMESTAMPTZ,
+                last_payout_at TIMESTAMPTZ,
+                updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+            )
+            "#,
+            "CREATE INDEX IF NOT EXISTS idx_payout_schedules_due ON payout_schedules(next_payout_at) WHERE frequency <> 'MANUAL'",
+            "ALTER TABLE withdrawals ADD COLUMN IF NOT EXISTS scheduled BOOLEAN NOT NULL DEFAULT FALSE",
+        ] {
+            sqlx::query(statement).execute(&self.pool).await?;
+        }
+
+        // Double-entry journal of the ledger: each settled or reversed entry gets postings that
+        // sum to zero across the Stripe balance, creator payable, platform fee and tax accounts
+        for statement in [
+            r#"
+            CREATE TABLE IF NOT EXISTS journal_entries (
+                id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+                ledger_entry_id UUID NOT NULL REFERENCES ledger_entries(id) ON DELETE CASCADE,
+                kind TEXT NOT NULL,
+                currency VARCHAR(10) NOT NULL,
+                created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+                UNIQUE (ledger_entry_id, kind)
+            )
+            "#,
+            r#"
+            CREATE TABLE IF NOT EXISTS journal_postings (
+                id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+                journal_entry_id UUID NOT NULL REFERENCES journal_entries(id) ON DELETE CASCADE,
+                account TEXT NOT NULL,
+                creator_id VARCHAR(255),
+                amount_cents BIGINT NOT NULL
+            )
+            "#,
+            "CREATE INDEX IF NOT EXISTS idx_journal_entries_created ON journal_entries(created_at)",
+            "CREATE INDEX IF NOT EXISTS idx_journal_postings_entry ON journal_postings(journal_entry_id)",
+            "CREATE INDEX IF NOT EXISTS idx_journal_postings_account ON journal_postings(account, creator_id)",
+            // Journal the entries settled before the journal existed
+            r#"
+            WITH journaled AS (
+                INSERT INTO journal_entries (ledger_entry_id, kind, currency, created_at)
+                SELECT id, 'SETTLEMENT', currency, settled_at
+                FROM ledger_entries
+                WHERE settled_at IS NOT NULL AND status IN ('SETTLED', 'DISPUTED', 'REVERSED')
+                ON CONFLICT (ledger_entry_id, kind) DO NOTHING
+                RETURNING id, ledger_entry_id
+            )
+            INSERT INTO journal_postings (journal_entry_id, account, creator_id, amount_cents)
+            SELECT j.id, p.account, p.creator_id, p.amount_cents
+            FROM journaled j
+            JOIN ledger_entries l ON l.id = j.ledger_entry_id
+            CROSS JOIN LATERAL (
+                VALUES
+                    ('STRIPE_BALANCE', NULL, l.gross_cents + l.tax_cents),
+                    ('CREATOR_PAYABLE', l.creator_id, -l.net_cents),
+                    ('PLATFORM_FEES', NULL, -l.fee_cents),
+                    ('TAX_PAYABLE', NULL, -l.tax_cents)
+            ) AS p(account, creator_id, amount_cents)
+            WHERE p.amount_cents <> 0
+            "#,
+        ] {
+            sqlx::query(statement).execute(&self.pool).await?;
+        }
+
+        // Carts: several products and a ticket paid in one checkout, fulfilled item by item
+        for statement in [
+            r#"
+            CREATE TABLE IF NOT EXISTS carts (
+                id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+                user_id VARCHAR(255) NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+                status TEXT NOT NULL DEFAULT 'OPEN',
+                stripe_checkout_session_id TEXT,
+                created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+                updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+            )
+            "#,
+            r#"
+            CREATE UNIQUE INDEX IF NOT EXISTS idx_carts_user_open
+            ON carts(user_id) WHERE status IN ('OPEN', 'CHECKED_OUT')
+            "#,
+            "CREATE INDEX IF NOT EXISTS idx_carts_session ON carts(stripe_checkout_session_id)",
+            r#"
+            CREATE TABLE IF NOT EXISTS cart_items (
+                id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+                cart_id UUID NOT NULL REFERENCES carts(id) ON DELETE CASCADE,
+                item_type TEXT NOT NULL,
+                product_id UUID REFERENCES products(id) ON DELETE CASCADE,
+                event_id VARCHAR(255),
+                ticket_type_id UUID,
+                name TEXT NOT NULL,
+                unit_amount_cents BIGINT NOT NULL,
+                tax_cents BIGINT NOT NULL DEFAULT 0,
+                currency VARCHAR(3) NOT NULL DEFAULT 'USD',
+                status TEXT NOT NULL DEFAULT 'PENDING',
+                purchase_id UUID,
+                ledger_entry_id UUID,
+                failure_reason TEXT,
+                fulfilled_at TIMESTAMPTZ,
+                created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+                UNIQUE(cart_id, product_id)
+            )
+            "#,
+            r#"
+            CREATE UNIQUE INDEX IF NOT EXISTS idx_cart_items_one_ticket
+            ON cart_items(cart_id) WHERE item_type = 'EVENT_TICKET'
+            "#,
+        ] {
+            sqlx::query(statement).execute(&self.pool).await?;
+        }
+
+        // Strong Customer Authentication: the latest PaymentIntent status of in-page payments
+        for statement in [
+            "ALTER TABLE donations ADD COLUMN IF NOT EXISTS payment_intent_status VARCHAR(40)",
+            "ALTER TABLE donations ADD COLUMN IF NOT EXISTS payment_error TEXT",
+            "ALTER TABLE purchases ADD COLUMN IF NOT EXISTS payment_intent_status VARCHAR(40)",
+            "ALTER TABLE purchases ADD COLUMN IF NOT EXISTS payment_error TEXT",
+        ] {
+            sqlx::query(statement).execute(&self.pool).await?;
+        }
+
+        // Crypto donations: the provider that took the payment and what it reported about it
+        for statement in [
+            "ALTER TABLE donations ADD COLUMN IF NOT EXISTS provider TEXT NOT NULL DEFAULT 'STRIPE'",
+            "ALTER TABLE donations ADD COLUMN IF NOT EXISTS provider_charge_id TEXT UNIQUE",
+            r#"
+            ALTER TABLE donations
+            ADD COLUMN IF NOT EXISTS provider_metadata JSONB NOT NULL DEFAULT '{}'
+            "#,
+            r#"
+            ALTER TABLE ledger_entries
+            ADD COLUMN IF NOT EXISTS payment_provider TEXT NOT NULL DEFAULT 'STRIPE'
+            "#,
+        ] {
+            sqlx::query(statement).execute(&self.pool).await?;
+        }
+
+        // In-app notifications
+        for statement in [
+            r#"
+            CREATE TABLE IF NOT EXISTS notifications (
+                id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+                user_id VARCHAR(255) NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+                kind TEXT NOT NULL,
+                payload JSONB NOT NULL DEFAULT '{}',
+                read_at TIMESTAMPTZ,
+                created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+            )
+            "#,
+            r#"
+            CREATE INDEX IF NOT EXISTS idx_notifications_user
+            ON notifications(user_id, created_at DESC)
+            "#,
+            r#"
+            CREATE INDEX IF NOT EXISTS idx_notifications_unread
+            ON notifications(user_id) WHERE read_at IS NULL
+            "#,
+        ] {
+            sqlx::query(statement).execute(&self.pool).await?;
+        }
+
+        // Email deliveries, the addresses bounces took off the list, reminders and password resets
+        for statement in [
+            r#"
+            CREATE TABLE IF NOT EXISTS email_deliveries (
+                id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+                recipient TEXT NOT NULL,
+                user_id VARCHAR(255) REFERENCES users(id) ON DELETE SET NULL,
+                template TEXT NOT NULL,
+                data JSONB NOT NULL DEFAULT '{}',
+                status TEXT NOT NULL DEFAULT 'QUEUED',
+                attempts INTEGER NOT NULL DEFAULT 0,
+                last_error TEXT,
+                provider_message_id TEXT,
+                next_attempt_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+                created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+                sent_at TIMESTAMPTZ
+            )
+            "#,
+            r#"
+            CREATE INDEX IF NOT EXISTS idx_email_deliveries_due
+            ON email_deliveries(next_attempt_at)
+            WHERE status IN ('QUEUED', 'RETRYING', 'SENDING')
+            "#,
+            r#"
+            CREATE TABLE IF NOT EXISTS email_suppressions (
+                email TEXT PRIMARY KEY,
+                reason TEXT NOT NULL,
+                source TEXT NOT NULL,
+                created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+            )
+            "#,
+            "ALTER TABLE events ADD COLUMN IF NOT EXISTS reminder_sent_at TIMESTAMPTZ",
+            r#"
+            CREATE TABLE IF NOT EXISTS password_reset_tokens (
+                token_hash TEXT PRIMARY KEY,
+                user_id VARCHAR(255) NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+                expires_at TIMESTAMPTZ NOT NULL,
+                used_at TIMESTAMPTZ,
+                created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+            )
+            "#,
+        ] {
+            sqlx::query(statement).execute(&self.pool).await?;
+        }
+
+        // Digest email cadence per user; users without a row get the weekly digest
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS digest_settings (
+                user_id VARCHAR(255) PRIMARY KEY REFERENCES users(id) ON DELETE CASCADE,
+                frequency TEXT NOT NULL DEFAULT 'WEEKLY',
+                last_sent_at TIMESTAMPTZ,
+                updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        // Notification preferences per category and channel; only choices that differ from the
+        // defaults in `NotificationChannel::enabled_by_default` need a row
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS notification_preferences (
+                user_id VARCHAR(255) NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+                category TEXT NOT NULL,
+                channel TEXT NOT NULL,
+                enabled BOOLEAN NOT NULL,
+                updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+                PRIMARY KEY (user_id, category, channel)
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        // Creator announcements to members; recipients are snapshotted into batches when one is
+        // sent and each batch is delivered by its own job
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS announcements (
+                id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+                creator_id VARCHAR(255) NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+                title TEXT NOT NULL,
+                body TEXT NOT NULL,
+                tier_id TEXT,
+                send_email BOOLEAN NOT NULL DEFAULT TRUE,
+                status TEXT NOT NULL DEFAULT 'SENDING',
+                recipient_count INTEGER NOT NULL DEFAULT 0,
+                created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+                completed_at TIMESTAMPTZ
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query(
+            r#"
+            CREATE INDEX IF NOT EXISTS idx_announcements_creator
+            ON announcements(creator_id, created_at DESC)
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS announcement_recipients (
+                announcement_id UUID NOT NULL REFERENCES announcements(id) ON DELETE CASCADE,
+                user_id VARCHAR(255) NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+                batch INTEGER NOT NULL,
+                delivered_at TIMESTAMPTZ,
+                email_delivery_id UUID,
+                PRIMARY KEY (announcement_id, user_id)
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query(
+            r#"
+            CREATE INDEX IF NOT EXISTS idx_announcement_recipients_batch
+            ON announcement_recipients(announcement_id, batch)
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        // Comments on campaign pages, threaded like article comments
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS campaign_comments (
+                id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+                campaign_id UUID NOT NULL REFERENCES campaigns(id) ON DELETE CASCADE,
+                user_id VARCHAR(255) NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+                content TEXT NOT NULL,
+                parent_id UUID REFERENCES campaign_comments(id) ON DELETE CASCADE,
+                created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query(
+            r#"
+            CREATE INDEX IF NOT EXISTS idx_campaign_comments_campaign
+            ON campaign_comments(campaign_id, created_at)
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        // Direct messages. Receipts are per participant: everything up to `last_delivered_at`
+        // reached them and everything up to `last_read_at` was read
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS conversations (
+                id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+                direct_key TEXT UNIQUE,
+                created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+                last_message_at TIMESTAMPTZ
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS conversation_participants (
+                conversation_id UUID NOT NULL REFERENCES conversations(id) ON DELETE CASCADE,
+                user_id VARCHAR(255) NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+                joined_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+                last_delivered_at TIMESTAMPTZ,
+                last_read_at TIMESTAMPTZ,
+                PRIMARY KEY (conversation_id, user_id)
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query(
+            r#"
+            CREATE INDEX IF NOT EXISTS idx_conversation_participants_user
+            ON conversation_participants(user_id)
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS messages (
+                id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+                conversation_id UUID NOT NULL REFERENCES conversations(id) ON DELETE CASCADE,
+                sender_id VARCHAR(255) NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+                body TEXT NOT NULL,
+                created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query(
+            r#"
+            CREATE INDEX IF NOT EXISTS idx_messages_conversation
+            ON messages(conversation_id, created_at DESC)
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        for statement in [
+            r#"
+            ALTER TABLE messages ADD COLUMN IF NOT EXISTS search_vector tsvector
+            GENERATED ALWAYS AS (to_tsvector('simple', body)) STORED
+            "#,
+            "CREATE INDEX IF NOT EXISTS idx_messages_search_vector ON messages USING GIN(search_vector)",
+        ] {
+            sqlx::query(statement).execute(&self.pool).await?;
+        }
+
+        // Files sent in direct messages. Uploads wait with no message until they are sent with
+        // one; unsent uploads are cleaned up after a day
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS message_attachments (
+                id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+                conversation_id UUID NOT NULL REFERENCES conversations(id) ON DELETE CASCADE,
+                message_id UUID REFERENCES messages(id) ON DELETE CASCADE,
+                uploader_id VARCHAR(255) NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+                file_name TEXT NOT NULL,
+                content_type TEXT NOT NULL,
+                size_bytes BIGINT NOT NULL,
+                storage_key TEXT NOT NULL,
+                thumbnail_key TEXT,
+                width INTEGER,
+                height INTEGER,
+                created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query(
+            r#"
+            CREATE INDEX IF NOT EXISTS idx_message_attachments_message
+            ON message_attachments(message_id)
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        // Who may message each user directly; users without a row can be messaged by anyone.
+        // Messages from anyone else wait in the recipient's message requests, tracked on their
+        // participant row as PENDING until they accept or decline
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS dm_settings (
+                user_id VARCHAR(255) PRIMARY KEY REFERENCES users(id) ON DELETE CASCADE,
+                policy TEXT NOT NULL DEFAULT 'EVERYONE',
+                updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query(
+            "ALTER TABLE conversation_participants ADD COLUMN IF NOT EXISTS request_status TEXT",
+        )
+        .execute(&self.pool)
+        .await?;
+
+        // Group conversations a creator runs for their members, one per tier or one for every
+        // member when `tier_id` is empty. Membership follows active subscriptions; people the
+        // creator removed are banned so syncing doesn't bring them back. Muted members have
+        // `muted_at` set, until `muted_until` or indefinitely when that's empty
+        for statement in [
+            "ALTER TABLE conversations ADD COLUMN IF NOT EXISTS creator_id VARCHAR(255) REFERENCES users(id) ON DELETE CASCADE",
+            "ALTER TABLE conversations ADD COLUMN IF NOT EXISTS title TEXT",
+            "ALTER TABLE conversations ADD COLUMN IF NOT EXISTS tier_id TEXT",
+            "CREATE UNIQUE INDEX IF NOT EXISTS idx_conversations_group_tier ON conversations(creator_id, COALESCE(tier_id, '')) WHERE creator_id IS NOT NULL",
+            "ALTER TABLE conversation_participants ADD COLUMN IF NOT EXISTS muted_at TIMESTAMPTZ",
+            "ALTER TABLE conversation_participants ADD COLUMN IF NOT EXISTS muted_until TIMESTAMPTZ",
+        ] {
+            sqlx::query(statement).execute(&self.pool).await?;
+        }
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS conversation_bans (
+                conversation_id UUID NOT NULL REFERENCES conversations(id) ON DELETE CASCADE,
+                user_id VARCHAR(255) NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+                banned_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+                PRIMARY KEY (conversation_id, user_id)
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        // Paid priority messages: a fan pays the creator's price to message them, the message is
+        // posted once the payment succeeds and refunded if the creator doesn't reply within
+        // `response_hours`. Creators without a price don't take them
+        for statement in [
+            "ALTER TABLE dm_settings ADD COLUMN IF NOT EXISTS priority_price_cents BIGINT",
+            "ALTER TABLE dm_settings ADD COLUMN IF NOT EXISTS priority_currency TEXT NOT NULL DEFAULT 'USD'",
+            "ALTER TABLE dm_settings ADD COLUMN IF NOT EXISTS priority_response_hours INTEGER NOT NULL DEFAULT 48",
+            "INSERT INTO platform_fee_schedules (product_type, fee_basis_points) VALUES ('MESSAGE', 500) ON CONFLICT (product_type) DO NOTHING",
+        ] {
+            sqlx::query(statement).execute(&self.pool).await?;
+        }
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS priority_messages (
+                id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+                conversation_id UUID NOT NULL REFERENCES conversations(id) ON DELETE CASCADE,
+                sender_id VARCHAR(255) NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+                creator_id VARCHAR(255) NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+                body TEXT NOT NULL,
+                amount_cents BIGINT NOT NULL,
+                currency TEXT NOT NULL,
+                response_hours INTEGER NOT NULL,
+                status TEXT NOT NULL DEFAULT 'PENDING',
+                message_id UUID REFERENCES messages(id) ON DELETE SET NULL,
+                stripe_payment_intent_id TEXT UNIQUE,
+                payment_intent_status TEXT,
+                payment_error TEXT,
+                paid_at TIMESTAMPTZ,
+                reply_due_at TIMESTAMPTZ,
+                answered_at TIMESTAMPTZ,
+                created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+                updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        for statement in [
+            "CREATE INDEX IF NOT EXISTS idx_priority_messages_creator ON priority_messages(creator_id, created_at DESC)",
+            "CREATE INDEX IF NOT EXISTS idx_priority_messages_sender ON priority_messages(sender_id, created_at DESC)",
+            "CREATE INDEX IF NOT EXISTS idx_priority_messages_due ON priority_messages(reply_due_at) WHERE status = 'AWAITING_REPLY'",
+        ] {
+            sqlx::query(statement).execute(&self.pool).await?;
+        }
+
+        // Message moderation: reports from participants (or the blocked-words filter, with no
+        // reporter) queue up for admins, who can suspend a sender from messaging
+        for statement in [
+            r#"
+            CREATE TABLE IF NOT EXISTS blocked_words (
+                word TEXT PRIMARY KEY,
+                action TEXT NOT NULL DEFAULT 'BLOCK',
+                created_by VARCHAR(255) REFERENCES users(id) ON DELETE SET NULL,
+                created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+            )
+            "#,
+            r#"
+            CREATE TABLE IF NOT EXISTS message_reports (
+                id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+                conversation_id UUID NOT NULL REFERENCES conversations(id) ON DELETE CASCADE,
+                message_id UUID REFERENCES messages(id) ON DELETE SET NULL,
+                reported_user_id VARCHAR(255) REFERENCES users(id) ON DELETE CASCADE,
+                reporter_id VARCHAR(255) REFERENCES users(id) ON DELETE SET NULL,
+                reason TEXT NOT NULL,
+                details TEXT,
+                status TEXT NOT NULL DEFAULT 'OPEN',
+                resolution_note TEXT,
+                resolved_by VARCHAR(255) REFERENCES users(id) ON DELETE SET NULL,
+                resolved_at TIMESTAMPTZ,
+                created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+            )
+            "#,
+            "CREATE INDEX IF NOT EXISTS idx_message_reports_open ON message_reports(created_at) WHERE status = 'OPEN'",
+            "CREATE INDEX IF NOT EXISTS idx_message_reports_conversation ON message_reports(conversation_id)",
+            "CREATE UNIQUE INDEX IF NOT EXISTS idx_message_reports_reporter ON message_reports(reporter_id, message_id) WHERE message_id IS NOT NULL",
+            "ALTER TABLE users ADD COLUMN IF NOT EXISTS messaging_suspended_at TIMESTAMPTZ",
+            "ALTER TABLE users ADD COLUMN IF NOT EXISTS messaging_suspended_until TIMESTAMPTZ",
+            "ALTER TABLE users ADD COLUMN IF NOT EXISTS messaging_suspension_reason TEXT",
+        ] {
+            sqlx::query(statement).execute(&self.pool).await?;
+        }
+
+        // Automated DM sequences: steps a creator's new members are messaged at, counted from when
+        // their subscription started. Each member's progress is kept on their enrollment, so steps
+        // can be edited and sequences paused while members are part way through
+        for statement in [
+            r#"
+            CREATE TABLE IF NOT EXISTS dm_sequences (
+                id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+                creator_id VARCHAR(255) NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+                name TEXT NOT NULL,
+                tier_id TEXT,
+                status TEXT NOT NULL DEFAULT 'ACTIVE',
+                paused_at TIMESTAMPTZ,
+                created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+                updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+            )
+            "#,
+            r#"
+            CREATE TABLE IF NOT EXISTS dm_sequence_steps (
+                id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+                sequence_id UUID NOT NULL REFERENCES dm_sequences(id) ON DELETE CASCADE,
+                delay_hours INTEGER NOT NULL,
+                body TEXT NOT NULL,
+                created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+                updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+                UNIQUE (sequence_id, delay_hours)
+            )
+            "#,
+            r#"
+            CREATE TABLE IF NOT EXISTS dm_sequence_enrollments (
+                id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+                sequence_id UUID NOT NULL REFERENCES dm_sequences(id) ON DELETE CASCADE,
+                user_id VARCHAR(255) NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+                status TEXT NOT NULL DEFAULT 'ACTIVE',
+                started_at TIMESTAMPTZ NOT NULL,
+                paused_at TIMESTAMPTZ,
+                last_delay_hours INTEGER,
+                last_sent_at TIMESTAMPTZ,
+                steps_sent INTEGER NOT NULL DEFAULT 0,
+                created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+                updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+                UNIQUE (sequence_id, user_id)
+            )
+            "#,
+            "CREATE INDEX IF NOT EXISTS idx_dm_sequences_creator ON dm_sequences(creator_id)",
+            "CREATE INDEX IF NOT EXISTS idx_dm_sequence_enrollments_active ON dm_sequence_enrollments(sequence_id) WHERE status = 'ACTIVE'",
+        ] {
+            sqlx::query(statement).execute(&self.pool).await?;
+        }
+
+        // Editorial pins for the public explore feed; an item is pinned at most once
+        for statement in [
+            r#"
+            CREATE TABLE IF NOT EXISTS explore_pins (
+                id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+                item_type VARCHAR(20) NOT NULL,
+                item_id UUID NOT NULL,
+                position INTEGER NOT NULL DEFAULT 0,
+                pinned_by VARCHAR(255) REFERENCES users(id) ON DELETE SET NULL,
+                expires_at TIMESTAMPTZ,
+                created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+                UNIQUE (item_type, item_id)
+            )
+            "#,
+        ] {
+            sqlx::query(statement).execute(&self.pool).await?;
+        }
+
+        // Per-user feed controls: a muted creator, tag or content type is left out of the feed,
+        // one marked "show fewer" only shows now and then
+        for statement in [
+            r#"
+            CREATE TABLE IF NOT EXISTS feed_mutes (
+                id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+                user_id VARCHAR(255) NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+                kind VARCHAR(20) NOT NULL,
+                value VARCHAR(255) NOT NULL,
+                mode VARCHAR(20) NOT NULL DEFAULT 'MUTE',
+                created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+                UNIQUE (user_id, kind, value)
+            )
+            "#,
+        ] {
+            sqlx::query(statement).execute(&self.pool).await?;
+        }
+
+        // Feed telemetry: raw impressions, clicks and hides with small integer codes (kept for
+        // a few months as ranking training data), rolled up per item and day
+        for statement in [
+            r#"
+            CREATE TABLE IF NOT EXISTS feed_item_events (
+                user_id VARCHAR(255) NOT NULL,
+                item_type SMALLINT NOT NULL,
+                item_id UUID NOT NULL,
+                action SMALLINT NOT NULL,
+                position SMALLINT,
+                occurred_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+            )
+            "#,
+            "CREATE INDEX IF NOT EXISTS idx_feed_item_events_occurred ON feed_item_events USING BRIN (occurred_at)",
+            r#"
+            CREATE TABLE IF NOT EXISTS feed_item_daily_stats (
+                day DATE NOT NULL,
+                item_type SMALLINT NOT NULL,
+                item_id UUID NOT NULL,
+                impressions BIGINT NOT NULL DEFAULT 0,
+                clicks BIGINT NOT NULL DEFAULT 0,
+                hides BIGINT NOT NULL DEFAULT 0,
+                unique_viewers BIGINT NOT NULL DEFAULT 0,
+                PRIMARY KEY (day, item_type, item_id)
+            )
+            "#,
+            r#"
+            CREATE TABLE IF NOT EXISTS feed_hidden_items (
+                user_id VARCHAR(255) NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+                item_type VARCHAR(20) NOT NULL,
+                item_id UUID NOT NULL,
+                created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+                PRIMARY KEY (user_id, item_type, item_id)
+            )
+            "#,
+        ] {
+            sqlx::query(statement).execute(&self.pool).await?;
+        }
+
+        // Bookmarks: a user's read-later list across posts, articles, campaigns, products and
+        // events, keyed by content type and id
+        for statement in [
+            r#"
+            CREATE TABLE IF NOT EXISTS bookmarks (
+                id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+                user_id VARCHAR(255) NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+                content_type VARCHAR(20) NOT NULL,
+                content_id UUID NOT NULL,
+                created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+                UNIQUE (user_id, content_type, content_id)
+            )
+            "#,
+            "CREATE INDEX IF NOT EXISTS idx_bookmarks_user_created ON bookmarks(user_id, created_at DESC, id DESC)",
+        ] {
+            sqlx::query(statement).execute(&self.pool).await?;
+        }
+
+        // When a user last looked at each creator they follow, for new-content badges
+        for statement in [
+            r#"
+            CREATE TABLE IF NOT EXISTS creator_last_seen (
+                user_id VARCHAR(255) NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+                creator_id VARCHAR(255) NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+                seen_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+                PRIMARY KEY (user_id, creator_id)
+            )
+            "#,
+        ] {
+            sqlx::query(statement).execute(&self.pool).await?;
+        }
+
+        // Product variants, e.g. personal and commercial licenses with their own price and file
+        for statement in [
+            r#"
+            CREATE TABLE IF NOT EXISTS product_variants (
+                id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+                product_id UUID NOT NULL REFERENCES products(id) ON DELETE CASCADE,
+                name VARCHAR(255) NOT NULL,
+                description TEXT,
+                price DOUBLE PRECISION NOT NULL,
+                download_url TEXT,
+                position INT NOT NULL DEFAULT 0,
+                is_active BOOLEAN NOT NULL DEFAULT TRUE,
+                created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+                updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+            )
+            "#,
+            "CREATE INDEX IF NOT EXISTS idx_product_variants_product ON product_variants(product_id, position)",
+            "ALTER TABLE purchases ADD COLUMN IF NOT EXISTS variant_id UUID REFERENCES product_variants(id) ON DELETE SET NULL",
+            "ALTER TABLE cart_items ADD COLUMN IF NOT EXISTS variant_id UUID REFERENCES product_variants(id) ON DELETE SET NULL",
+        ] {
+            sqlx::query(statement).execute(&self.pool).await?;
+        }
+
+        // License keys for software products and the machines they are activated on
+        for statement in [
+            "ALTER TABLE products ADD COLUMN IF NOT EXISTS license_keys BOOLEAN NOT NULL DEFAULT FALSE",
+            "ALTER TABLE products ADD COLUMN IF NOT EXISTS license_activation_limit INT NOT NULL DEFAULT 3",
+            "ALTER TABLE product_variants ADD COLUMN IF NOT EXISTS activation_limit INT",
+            r#"
+            CREATE TABLE IF NOT EXISTS license_keys (
+                id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+                license_key VARCHAR(64) NOT NULL UNIQUE,
+                purchase_id UUID NOT NULL UNIQUE REFERENCES purchases(id) ON DELETE CASCADE,
+                product_id UUID NOT NULL REFERENCES products(id) ON DELETE CASCADE,
+                variant_id UUID REFERENCES product_variants(id) ON DELETE SET NULL,
+                user_id VARCHAR(255) NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+                activation_limit INT NOT NULL,
+                revoked_at TIMESTAMPTZ,
+                created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+            )
+            "#,
+            "CREATE INDEX IF NOT EXISTS idx_license_keys_user ON license_keys(user_id, created_at DESC)",
+            r#"
+            CREATE TABLE IF NOT EXISTS license_activations (
+                id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+                license_key_id UUID NOT NULL REFERENCES license_keys(id) ON DELETE CASCADE,
+                instance_id VARCHAR(255) NOT NULL,
+                label VARCHAR(255),
+                activated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+                last_validated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+                UNIQUE (license_key_id, instance_id)
+            )
+            "#,
+        ] {
+            sqlx::query(statement).execute(&self.pool).await?;
+        }
+
+        // Product file downloads through signed links, one row per link used
+        for statement in [
+            "ALTER TABLE products ADD COLUMN IF NOT EXISTS download_limit INT",
+            r#"
+            CREATE TABLE IF NOT EXISTS product_downloads (
+                id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+                product_id UUID NOT NULL REFERENCES products(id) ON DELETE CASCADE,
+                purchase_id UUID REFERENCES purchases(id) ON DELETE CASCADE,
+                variant_id UUID REFERENCES product_variants(id) ON DELETE SET NULL,
+                user_id VARCHAR(255) NOT NULL,
+                link_signature VARCHAR(64) NOT NULL UNIQUE,
+                ip_address VARCHAR(64),
+                user_agent TEXT,
+                created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+            )
+            "#,
+            "CREATE INDEX IF NOT EXISTS idx_product_downloads_purchase ON product_downloads(purchase_id)",
+            "CREATE INDEX IF NOT EXISTS idx_product_downloads_product ON product_downloads(product_id, created_at DESC)",
+        ] {
+            sqlx::query(statement).execute(&self.pool).await?;
+        }
+
+        // Creator discount codes, and flash sales (discounts without a code)
+        for statement in [
+            r#"
+            CREATE TABLE IF NOT EXISTS discount_codes (
+                id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+                creator_id VARCHAR(255) NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+                code VARCHAR(64),
+                discount_type VARCHAR(10) NOT NULL,
+                amount DOUBLE PRECISION NOT NULL,
+                product_id UUID REFERENCES products(id) ON DELETE CASCADE,
+                starts_at TIMESTAMPTZ,
+                expires_at TIMESTAMPTZ,
+                max_uses INT,
+                uses INT NOT NULL DEFAULT 0,
+                is_active BOOLEAN NOT NULL DEFAULT TRUE,
+                created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+                updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+            )
+            "#,
+            r#"
+            CREATE UNIQUE INDEX IF NOT EXISTS idx_discount_codes_creator_code
+            ON discount_codes(creator_id, UPPER(code)) WHERE code IS NOT NULL
+            "#,
+            "CREATE INDEX IF NOT EXISTS idx_discount_codes_sales ON discount_codes(creator_id) WHERE code IS NULL AND is_active",
+            "ALTER TABLE purchases ADD COLUMN IF NOT EXISTS discount_code_id UUID REFERENCES discount_codes(id) ON DELETE SET NULL",
+            "ALTER TABLE purchases ADD COLUMN IF NOT EXISTS discount_amount DOUBLE PRECISION",
+        ] {
+            sqlx::query(statement).execute(&self.pool).await?;
+        }
+
+        // Released versions of product files; buyers keep access to every one
+        for statement in [
+            r#"
+            CREATE TABLE IF NOT EXISTS product_versions (
+                id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+                product_id UUID NOT NULL REFERENCES products(id) ON DELETE CASCADE,
+                variant_id UUID REFERENCES product_variants(id) ON DELETE CASCADE,
+                version_number INT NOT NULL,
+                file_url TEXT NOT NULL,
+                file_name VARCHAR(255),
+                release_notes TEXT,
+                created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+                UNIQUE(product_id, version_number)
+            )
+            "#,
+            "ALTER TABLE product_downloads ADD COLUMN IF NOT EXISTS version_id UUID REFERENCES product_versions(id) ON DELETE SET NULL",
+        ] {
+            sqlx::query(statement).execute(&self.pool).await?;
+        }
+
+        // Pre-orders: products sold before their release date, released by the scheduler
+        for statement in [
+            "ALTER TABLE products ADD COLUMN IF NOT EXISTS release_at TIMESTAMPTZ",
+            "ALTER TABLE products ADD COLUMN IF NOT EXISTS released_at TIMESTAMPTZ",
+            "CREATE INDEX IF NOT EXISTS idx_products_pending_release ON products(release_at) WHERE release_at IS NOT NULL AND released_at IS NULL",
+        ] {
+            sqlx::query(statement).execute(&self.pool).await?;
+        }
+
+        // Limited editions: units left, and units held for buyers in checkout
+        for statement in [
+            "ALTER TABLE products ADD COLUMN IF NOT EXISTS stock INT",
+            r#"
+            CREATE TABLE IF NOT EXISTS stock_holds (
+                id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+                product_id UUID NOT NULL REFERENCES products(id) ON DELETE CASCADE,
+                purchase_id UUID REFERENCES purchases(id) ON DELETE CASCADE,
+                expires_at TIMESTAMPTZ NOT NULL,
+                created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+            )
+            "#,
+            "CREATE INDEX IF NOT EXISTS idx_stock_holds_product ON stock_holds(product_id, expires_at)",
+            "CREATE INDEX IF NOT EXISTS idx_stock_holds_purchase ON stock_holds(purchase_id)",
+        ] {
+            sqlx::query(statement).execute(&self.pool).await?;
+        }
+
+        // Creator storefronts: banner and featured product, product order, and sections
+        for statement in [
+            "ALTER TABLE products ADD COLUMN IF NOT EXISTS storefront_position INT",
+            r#"
+            CREATE TABLE IF NOT EXISTS storefront_settings (
+                creator_id VARCHAR(255) PRIMARY KEY REFERENCES users(id) ON DELETE CASCADE,
+                banner_image_url TEXT,
+                banner_title VARCHAR(200),
+                banner_subtitle TEXT,
+                banner_link_url TEXT,
+                featured_product_id UUID REFERENCES products(id) ON DELETE SET NULL,
+                accent_color VARCHAR(20),
+                updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+            )
+            "#,
+            r#"
+            CREATE TABLE IF NOT EXISTS storefront_sections (
+                id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+                creator_id VARCHAR(255) NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+                title VARCHAR(200) NOT NULL,
+                description TEXT,
+                product_ids UUID[] NOT NULL DEFAULT '{}',
+                position INT NOT NULL DEFAULT 0,
+                created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+                updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+            )
+            "#,
+            "CREATE INDEX IF NOT EXISTS idx_storefront_sections_creator ON storefront_sections(creator_id, position)",
+        ] {
+            sqlx::query(statement).execute(&self.pool).await?;
+        }
+
+        // Products taken off sale without deleting them
+        sqlx::query("ALTER TABLE products ADD COLUMN IF NOT EXISTS is_active BOOLEAN NOT NULL DEFAULT TRUE")
+            .execute(&self.pool)
+            .await?;
+
+        // Unpaid checkout sessions: where to resume them, when they lapse, and the reminder sent
+        for statement in [
+            "ALTER TABLE purchases ADD COLUMN IF NOT EXISTS checkout_url TEXT",
+            "ALTER TABLE purchases ADD COLUMN IF NOT EXISTS checkout_expires_at TIMESTAMPTZ",
+            "ALTER TABLE purchases ADD COLUMN IF NOT EXISTS checkout_reminder_sent_at TIMESTAMPTZ",
+            "CREATE INDEX IF NOT EXISTS idx_purchases_pending_checkout ON purchases(created_at) WHERE status = 'PENDING' AND stripe_checkout_session_id IS NOT NULL",
+        ] {
+            sqlx::query(statement).execute(&self.pool).await?;
+        }
+
+        // Physical products: creators' shipping zones, and the address and delivery of each order
+        for statement in [
+            r#"
+            CREATE TABLE IF NOT EXISTS shipping_zones (
+                id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+                creator_id VARCHAR(255) NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+                name VARCHAR(100) NOT NULL,
+                countries TEXT[] NOT NULL DEFAULT '{}',
+                rate_cents BIGINT NOT NULL DEFAULT 0,
+                free_over_cents BIGINT,
+                created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+                updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+            )
+            "#,
+            "CREATE INDEX IF NOT EXISTS idx_shipping_zones_creator ON shipping_zones(creator_id)",
+            "ALTER TABLE purchases ADD COLUMN IF NOT EXISTS shipping_address JSONB",
+            "ALTER TABLE purchases ADD COLUMN IF NOT EXISTS shipping_zone_id UUID REFERENCES shipping_zones(id) ON DELETE SET NULL",
+            "ALTER TABLE purchases ADD COLUMN IF NOT EXISTS shipping_cents BIGINT NOT NULL DEFAULT 0",
+            "ALTER TABLE purchases ADD COLUMN IF NOT EXISTS fulfillment_status VARCHAR(20)",
+            "ALTER TABLE purchases ADD COLUMN IF NOT EXISTS tracking_carrier VARCHAR(100)",
+            "ALTER TABLE purchases ADD COLUMN IF NOT EXISTS tracking_number VARCHAR(100)",
+            "ALTER TABLE purchases ADD COLUMN IF NOT EXISTS tracking_url TEXT",
+            "ALTER TABLE purchases ADD COLUMN IF NOT EXISTS shipped_at TIMESTAMPTZ",
+            "ALTER TABLE purchases ADD COLUMN IF NOT EXISTS delivered_at TIMESTAMPTZ",
+            "CREATE INDEX IF NOT EXISTS idx_purchases_fulfillment ON purchases(product_id, fulfillment_status) WHERE fulfillment_status IS NOT NULL",
+        ] {
+            sqlx::query(statement).execute(&self.pool).await?;
+        }
+
+        // Country a user signed up from, resolved from their IP, for audience analytics
+        sqlx::query("ALTER TABLE users ADD COLUMN IF NOT EXISTS signup_country VARCHAR(2)")
+            .execute(&self.pool)
+            .await?;
+
+        // Client-side analytics events (page views and clicks on a creator's pages), partitioned
+        // by month so old months can be dropped whole, rolled up per creator and day
+        for statement in [
+            r#"
+            CREATE TABLE IF NOT EXISTS analytics_events (
+                creator_id VARCHAR(255) NOT NULL,
+                event_type SMALLINT NOT NULL,
+                visitor_id VARCHAR(255),
+                path VARCHAR(512),
+                target VARCHAR(128),
+                country VARCHAR(2),
+                occurred_at TIMESTAMPTZ NOT NULL
+            ) PARTITION BY RANGE (occurred_at)
+            "#,
+            "CREATE INDEX IF NOT EXISTS idx_analytics_events_creator_time ON analytics_events(creator_id, occurred_at)",
+            r#"
+            CREATE OR REPLACE FUNCTION create_analytics_event_partitions() RETURNS VOID AS $$
+            DECLARE
+                month_start DATE;
+            BEGIN
+                FOR offset_months IN -1..2 LOOP
+                    month_start := (date_trunc('month', NOW() AT TIME ZONE 'UTC') + make_interval(months => offset_months))::DATE;
+                    EXECUTE format(
+                        'CREATE TABLE IF NOT EXISTS %I PARTITION OF analytics_events FOR VALUES FROM (%L) TO (%L)',
+                        'analytics_events_' || to_char(month_start, 'YYYYMM'),
+                        month_start::TIMESTAMP AT TIME ZONE 'UTC',
+                        (month_start + INTERVAL '1 month')::TIMESTAMP AT TIME ZONE 'UTC'
+                    );
+                END LOOP;
+            END
+            $$ LANGUAGE plpgsql
+            "#,
+            "SELECT create_analytics_event_partitions()",
+            r#"
+            CREATE TABLE IF NOT EXISTS creator_daily_stats (
+                day DATE NOT NULL,
+                creator_id VARCHAR(255) NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+                event_type SMALLINT NOT NULL,
+                events BIGINT NOT NULL DEFAULT 0,
+                unique_visitors BIGINT NOT NULL DEFAULT 0,
+                PRIMARY KEY (creator_id, day, event_type)
+            )
+            "#,
+        ] {
+            sqlx::query(statement).execute(&self.pool).await?;
+        }
+
+        // The campaign or product a tracked event was about, for conversion funnels
+        for statement in [
+            "ALTER TABLE analytics_events ADD COLUMN IF NOT EXISTS subject_type SMALLINT",
+            "ALTER TABLE analytics_events ADD COLUMN IF NOT EXISTS subject_id UUID",
+            "CREATE INDEX IF NOT EXISTS idx_analytics_events_subject ON analytics_events(subject_type, subject_id, occurred_at)",
+        ] {
+            sqlx::query(statement).execute(&self.pool).await?;
+        }
+
+        // Performance report email cadence per creator; creators without a row get none
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS analytics_report_settings (
+                user_id VARCHAR(255) PRIMARY KEY REFERENCES users(id) ON DELETE CASCADE,
+                frequency TEXT NOT NULL DEFAULT 'OFF',
+                last_sent_at TIMESTAMPTZ,
+                updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        // Subscriber retention per creator, join month and months since joining, kept up to date
+        // by a nightly job
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS subscriber_cohorts (
+                creator_id VARCHAR(255) NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+                cohort_month DATE NOT NULL,
+                months_since INTEGER NOT NULL,
+                subscribers BIGINT NOT NULL,
+                retained BIGINT NOT NULL,
+                computed_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+                PRIMARY KEY (creator_id, cohort_month, months_since)
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        // Campaign title and cover experiments, and the variant each visitor was first shown
+        for statement in [
+            r#"
+            CREATE TABLE IF NOT EXISTS campaign_experiments (
+                id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+                campaign_id UUID NOT NULL REFERENCES campaigns(id) ON DELETE CASCADE,
+                title_a VARCHAR(255) NOT NULL,
+                cover_image_a TEXT,
+                title_b VARCHAR(255) NOT NULL,
+                cover_image_b TEXT,
+                status TEXT NOT NULL DEFAULT 'RUNNING',
+                applied_variant VARCHAR(1),
+                created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+                ended_at TIMESTAMPTZ
+            )
+            "#,
+            "CREATE UNIQUE INDEX IF NOT EXISTS idx_campaign_experiments_running ON campaign_experiments(campaign_id) WHERE status = 'RUNNING'",
+            r#"
+            CREATE TABLE IF NOT EXISTS campaign_experiment_exposures (
+                experiment_id UUID NOT NULL REFERENCES campaign_experiments(id) ON DELETE CASCADE,
+                viewer_key TEXT NOT NULL,
+                variant VARCHAR(1) NOT NULL,
+                first_seen_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+                PRIMARY KEY (experiment_id, viewer_key)
+            )
+            "#,
+        ] {
+            sqlx::query(statement).execute(&self.pool).await?;
+        }
+
+        // Referral attribution: who each code brought in, what they went on to pay, and the
+        // reward credited to the code's owner
+        for statement in [
+            "ALTER TABLE referral_codes ADD COLUMN IF NOT EXISTS reward_amount_cents INTEGER",
+            "ALTER TABLE referral_codes ADD COLUMN IF NOT EXISTS reward_months INTEGER NOT NULL DEFAULT 1",
+            "ALTER TABLE referral_codes ADD COLUMN IF NOT EXISTS reward_on TEXT NOT NULL DEFAULT 'FIRST_PAYMENT'",
+            r#"
+            CREATE TABLE IF NOT EXISTS referrals (
+                id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+                code_id UUID NOT NULL REFERENCES referral_codes(id) ON DELETE CASCADE,
+                referrer_id VARCHAR(255) NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+                referred_user_id VARCHAR(255) NOT NULL UNIQUE REFERENCES users(id) ON DELETE CASCADE,
+                source TEXT NOT NULL,
+                created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+            )
+            "#,
+            "CREATE INDEX IF NOT EXISTS idx_referrals_code ON referrals(code_id, created_at)",
+            r#"
+            CREATE TABLE IF NOT EXISTS referral_conversions (
+                id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+                referral_id UUID NOT NULL REFERENCES referrals(id) ON DELETE CASCADE,
+                event_type TEXT NOT NULL,
+                source_id TEXT NOT NULL,
+                amount_cents BIGINT NOT NULL,
+                currency VARCHAR(3) NOT NULL,
+                created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+                UNIQUE (event_type, source_id)
+            )
+            "#,
+            "CREATE INDEX IF NOT EXISTS idx_referral_conversions_referral ON referral_conversions(referral_id)",
+            r#"
+            CREATE TABLE IF NOT EXISTS referral_rewards (
+                id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+                referral_id UUID NOT NULL UNIQUE REFERENCES referrals(id) ON DELETE CASCADE,
+                user_id VARCHAR(255) NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+                reward_type TEXT NOT NULL,
+                amount_cents BIGINT NOT NULL DEFAULT 0,
+                currency VARCHAR(3) NOT NULL DEFAULT 'USD',
+                months INTEGER NOT NULL DEFAULT 0,
+                status TEXT NOT NULL DEFAULT 'CREDITED',
+                created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+            )
+            "#,
+            "CREATE INDEX IF NOT EXISTS idx_referral_rewards_user ON referral_rewards(user_id, created_at DESC)",
+        ] {
+            sqlx::query(statement).execute(&self.pool).await?;
+        }
+
+        // Fans' share links for campaigns, the visitors each brought and the donations they led to
+        for statement in [
+            r#"
+            CREATE TABLE IF NOT EXISTS campaign_share_links (
+                id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+                campaign_id UUID NOT NULL REFERENCES campaigns(id) ON DELETE CASCADE,
+                user_id VARCHAR(255) NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+                token VARCHAR(32) NOT NULL UNIQUE,
+                created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+                UNIQUE (campaign_id, user_id)
+            )
+            "#,
+            r#"
+            CREATE TABLE IF NOT EXISTS campaign_share_visits (
+                link_id UUID NOT NULL REFERENCES campaign_share_links(id) ON DELETE CASCADE,
+                viewer_key TEXT NOT NULL,
+                first_seen_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+                PRIMARY KEY (link_id, viewer_key)
+            )
+            "#,
+            "ALTER TABLE donations ADD COLUMN IF NOT EXISTS share_link_id UUID REFERENCES campaign_share_links(id) ON DELETE SET NULL",
+            "CREATE INDEX IF NOT EXISTS idx_donations_share_link ON donations(share_link_id) WHERE share_link_id IS NOT NULL",
+        ] {
+            sqlx::query(statement).execute(&self.pool).await?;
+        }
+
+        // Referral fraud checks: where accounts signed up from, the cards users paid with, and the
+        // referrals flagged for review
+        for statement in [
+            "ALTER TABLE users ADD COLUMN IF NOT EXISTS signup_ip_hash VARCHAR(64)",
+            "ALTER TABLE referrals ADD COLUMN IF NOT EXISTS ip_hash VARCHAR(64)",
+            "CREATE INDEX IF NOT EXISTS idx_referrals_referrer_ip ON referrals(referrer_id, ip_hash)",
+            r#"
+            CREATE TABLE IF NOT EXISTS payment_card_fingerprints (
+                user_id VARCHAR(255) NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+                fingerprint TEXT NOT NULL,
+                first_seen_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+                PRIMARY KEY (user_id, fingerprint)
+            )
+            "#,
+            "CREATE INDEX IF NOT EXISTS idx_payment_card_fingerprints_fingerprint ON payment_card_fingerprints(fingerprint)",
+            r#"
+            CREATE TABLE IF NOT EXISTS referral_fraud_flags (
+                id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+                referral_id UUID NOT NULL UNIQUE REFERENCES referrals(id) ON DELETE CASCADE,
+                signals TEXT[] NOT NULL,
+                status TEXT NOT NULL DEFAULT 'OPEN',
+                review_note TEXT,
+                reviewed_by VARCHAR(255) REFERENCES users(id) ON DELETE SET NULL,
+                reviewed_at TIMESTAMPTZ,
+                created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+                updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+            )
+            "#,
+            "CREATE INDEX IF NOT EXISTS idx_referral_fraud_flags_status ON referral_fraud_flags(status, created_at)",
+        ] {
+            sqlx::query(statement).execute(&self.pool).await?;
+        }
+
+        // Uploaded podcast episodes are probed, loudness-normalized and chaptered before release
+        for statement in [
+            "ALTER TABLE podcast_episodes ADD COLUMN IF NOT EXISTS original_audio_url TEXT",
+            "ALTER TABLE podcast_episodes ADD COLUMN IF NOT EXISTS processing_status VARCHAR(20)",
+            "ALTER TABLE podcast_episodes ADD COLUMN IF NOT EXISTS processing_error TEXT",
+            "ALTER TABLE podcast_episodes ADD COLUMN IF NOT EXISTS publish_when_ready BOOLEAN NOT NULL DEFAULT FALSE",
+            "ALTER TABLE podcast_episodes ADD COLUMN IF NOT EXISTS bitrate_kbps INTEGER",
+            "ALTER TABLE podcast_episodes ADD COLUMN IF NOT EXISTS loudness_lufs DOUBLE PRECISION",
+            "ALTER TABLE podcast_episodes ADD COLUMN IF NOT EXISTS chapter_markers JSONB",
+            "ALTER TABLE podcast_episodes ADD COLUMN IF NOT EXISTS chapters JSONB",
+        ] {
+            sqlx::query(statement).execute(&self.pool).await?;
+        }
+
+        // Episode transcripts, uploaded by the creator or produced by the speech-to-text provider
+        for statement in [
+            r#"
+            CREATE TABLE IF NOT EXISTS podcast_transcripts (
+                episode_id UUID PRIMARY KEY REFERENCES podcast_episodes(id) ON DELETE CASCADE,
+                source VARCHAR(20) NOT NULL,
+                status VARCHAR(20) NOT NULL DEFAULT 'PENDING',
+                provider TEXT,
+                language TEXT,
+                text TEXT,
+                segments JSONB,
+                error TEXT,
+                created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+                updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+            )
+            "#,
+        ] {
+            sqlx::query(statement).execute(&self.pool).await?;
+        }
+
+        // Podcast playback sessions, kept at the furthest position each one reached
+        for statement in [
+            r#"
+            CREATE TABLE IF NOT EXISTS podcast_listens (
+                session_id UUID NOT NULL,
+                episode_id UUID NOT NULL REFERENCES podcast_episodes(id) ON DELETE CASCADE,
+                listener_key TEXT NOT NULL,
+                started_at TIMESTAMPTZ NOT NULL,
+                last_ping_at TIMESTAMPTZ NOT NULL,
+                max_position DOUBLE PRECISION NOT NULL DEFAULT 0,
+                completed BOOLEAN NOT NULL DEFAULT FALSE,
+                PRIMARY KEY (session_id, episode_id)
+            )
+            "#,
+            "CREATE INDEX IF NOT EXISTS idx_podcast_listens_episode ON podcast_listens(episode_id, started_at)",
+        ] {
+            sqlx::query(statement).execute(&self.pool).await?;
+        }
+
+        // Podcasts imported from an external RSS feed, optionally re-synced and mirrored
+        for statement in [
+            "ALTER TABLE podcasts ADD COLUMN IF NOT EXISTS feed_sync_enabled BOOLEAN NOT NULL DEFAULT FALSE",
+            "ALTER TABLE podcasts ADD COLUMN IF NOT EXISTS mirror_audio BOOLEAN NOT NULL DEFAULT FALSE",
+            "ALTER TABLE podcasts ADD COLUMN IF NOT EXISTS feed_synced_at TIMESTAMPTZ",
+            "ALTER TABLE podcasts ADD COLUMN IF NOT EXISTS feed_sync_error TEXT",
+            "ALTER TABLE podcast_episodes ADD COLUMN IF NOT EXISTS external_guid TEXT",
+            "ALTER TABLE podcast_episodes ADD COLUMN IF NOT EXISTS mirror_status VARCHAR(20)",
+            "CREATE UNIQUE INDEX IF NOT EXISTS idx_podcast_episodes_external_guid ON podcast_episodes(podcast_id, external_guid)",
+            r#"
+            CREATE TABLE IF NOT EXISTS podcast_imports (
+                id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+                user_id TEXT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+                podcast_id UUID REFERENCES podcasts(id) ON DELETE CASCADE,
+                feed_url TEXT NOT NULL,
+                auto_sync BOOLEAN NOT NULL DEFAULT TRUE,
+                mirror_audio BOOLEAN NOT NULL DEFAULT FALSE,
+                status VARCHAR(20) NOT NULL DEFAULT 'QUEUED',
+                total_items INTEGER NOT NULL DEFAULT 0,
+                processed_items INTEGER NOT NULL DEFAULT 0,
+                imported_episodes INTEGER NOT NULL DEFAULT 0,
+                skipped_items INTEGER NOT NULL DEFAULT 0,
+                error TEXT,
+                created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+                updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+                completed_at TIMESTAMPTZ
+            )
+            "#,
+            "CREATE INDEX IF NOT EXISTS idx_podcast_imports_user ON podcast_imports(user_id)",
+        ] {
+            sqlx::query(statement).execute(&self.pool).await?;
+        }
+
+        // Podcast seasons and episodes scheduled for release. Scheduled episodes stay drafts
+        // until the publishing job releases them.
+        for statement in [
+            "ALTER TABLE podcast_episodes ADD COLUMN IF NOT EXISTS season_number INTEGER",
+            "ALTER TABLE podcast_episodes ADD COLUMN IF NOT EXISTS scheduled_for TIMESTAMPTZ",
+            "CREATE INDEX IF NOT EXISTS idx_podcast_episodes_scheduled ON podcast_episodes(scheduled_for) WHERE status = 'DRAFT'",
+            "CREATE INDEX IF NOT EXISTS idx_podcast_episodes_season ON podcast_episodes(podcast_id, season_number, episode_number)",
+            // Episodes created with a future publish date used to be listed right away
+            r#"
+            UPDATE podcast_episodes
+            SET status = 'DRAFT', scheduled_for = published_at, published_at = NULL
+            WHERE status = 'PUBLISHED' AND published_at > NOW() AND external_guid IS NULL
+            "#,
+        ] {
+            sqlx::query(statement).execute(&self.pool).await?;
+        }
+
         println!("✅ Database migrations completed successfully!");
         Ok(())
     }
@@ -0,0 +1,551 @@
+use std::{sync::OnceLock, time::Duration};
+
+use handlebars::Handlebars;
+use serde_json::{json, Map, Value};
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+use crate::{
+    amqp_client::JobMessage,
+    config::{Config, EmailConfig},
+    database::Database,
+    notification_service::{channel_enabled, NotificationChannel, NotificationKind},
+};
+
+mod smtp;
+
+/// Queue the email worker consumes; deliveries are published here as `SendEmail` jobs.
+pub const EMAIL_QUEUE: &str = "email_notifications";
+/// Attempts before a delivery is given up as FAILED.
+const MAX_ATTEMPTS: i32 = 5;
+/// How long a published or sending delivery is left alone before the sweeper picks it up again,
+/// in case its job or its worker got lost.
+const LEASE_MINUTES: i32 = 10;
+/// Deliveries re-dispatched per sweep.
+const SWEEP_BATCH: i64 = 100;
+/// No provider gets longer than this to accept a message.
+pub(super) const SEND_TIMEOUT: Duration = Duration::from_secs(30);
+
+const LAYOUT: &str = include_str!("../../templates/email/layout.html");
+
+/// The emails the platform sends; stored in `email_deliveries.template`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EmailTemplate {
+    Receipt,
+    NewSubscriber,
+    EventReminder,
+    PasswordReset,
+    RefundIssued,
+    PayoutSummary,
+    Digest,
+    Announcement,
+    CheckoutReminder,
+    AnalyticsReport,
+}
+
+impl EmailTemplate {
+    const ALL: [EmailTemplate; 10] = [
+        EmailTemplate::Receipt,
+        EmailTemplate::NewSubscriber,
+        EmailTemplate::EventReminder,
+        EmailTemplate::PasswordReset,
+        EmailTemplate::RefundIssued,
+        EmailTemplate::PayoutSummary,
+        EmailTemplate::Digest,
+        EmailTemplate::Announcement,
+        EmailTemplate::CheckoutReminder,
+        EmailTemplate::AnalyticsReport,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            EmailTemplate::Receipt => "receipt",
+            EmailTemplate::NewSubscriber => "new_subscriber",
+            EmailTemplate::EventReminder => "event_reminder",
+            EmailTemplate::PasswordReset => "password_reset",
+            EmailTemplate::RefundIssued => "refund_issued",
+            EmailTemplate::PayoutSummary => "payout_summary",
+            EmailTemplate::Digest => "digest",
+            EmailTemplate::Announcement => "announcement",
+            EmailTemplate::CheckoutReminder => "checkout_reminder",
+            EmailTemplate::AnalyticsReport => "analytics_report",
+        }
+    }
+
+    fn parse(value: &str) -> Option<Self> {
+        match value {
+            "receipt" => Some(EmailTemplate::Receipt),
+            "new_subscriber" => Some(EmailTemplate::NewSubscriber),
+            "event_reminder" => Some(EmailTemplate::EventReminder),
+            "password_reset" => Some(EmailTemplate::PasswordReset),
+            "refund_issued" => Some(EmailTemplate::RefundIssued),
+            "payout_summary" => Some(EmailTemplate::PayoutSummary),
+            "digest" => Some(EmailTemplate::Digest),
+            "announcement" => Some(EmailTemplate::Announcement),
+            "checkout_reminder" => Some(EmailTemplate::CheckoutReminder),
+            "analytics_report" => Some(EmailTemplate::AnalyticsReport),
+            _ => None,
+        }
+    }
+
+    /// The notification category whose email preference decides whether this email is sent.
+    /// Password resets always are; digests and analytics reports follow their own settings.
+    fn category(self) -> Option<NotificationKind> {
+        match self {
+            EmailTemplate::Receipt | EmailTemplate::RefundIssued | EmailTemplate::CheckoutReminder => {
+                Some(NotificationKind::Payment)
+            }
+            EmailTemplate::NewSubscriber => Some(NotificationKind::Subscription),
+            EmailTemplate::EventReminder => Some(NotificationKind::Event),
+            EmailTemplate::PayoutSummary => Some(NotificationKind::Payout),
+            EmailTemplate::Announcement => Some(NotificationKind::Announcement),
+            EmailTemplate::PasswordReset | EmailTemplate::Digest | EmailTemplate::AnalyticsReport => None,
+        }
+    }
+
+    fn subject(self) -> &'static str {
+        match self {
+            EmailTemplate::Receipt => "Your receipt from {{creator_name}}",
+            EmailTemplate::NewSubscriber => "{{subscriber_name}} joined your membership",
+            EmailTemplate::EventReminder => "Reminder: {{event_title}} starts soon",
+            EmailTemplate::PasswordReset => "Reset your Fundify password",
+            EmailTemplate::RefundIssued => "Your refund of {{amount}}",
+            EmailTemplate::PayoutSummary => "Your payout of {{amount}} is on its way",
+            EmailTemplate::Digest => "Your Fundify digest: {{summary}}",
+            EmailTemplate::Announcement => "{{creator_name}}: {{title}}",
+            EmailTemplate::CheckoutReminder => "You left {{item_name}} at checkout",
+            EmailTemplate::AnalyticsReport => "Your {{cadence}} Fundify report: {{summary}}",
+        }
+    }
+
+    /// The plain text and HTML bodies, from `templates/email/`.
+    fn bodies(self) -> (&'static str, &'static str) {
+        match self {
+            EmailTemplate::Receipt => (
+                include_str!("../../templates/email/receipt.txt"),
+                include_str!("../../templates/email/receipt.html"),
+            ),
+            EmailTemplate::NewSubscriber => (
+                include_str!("../../templates/email/new_subscriber.txt"),
+                include_str!("../../templates/email/new_subscriber.html"),
+            ),
+            EmailTemplate::EventReminder => (
+                include_str!("../../templates/email/event_reminder.txt"),
+                include_str!("../../templates/email/event_reminder.html"),
+            ),
+            EmailTemplate::PasswordReset => (
+                include_str!("../../templates/email/password_reset.txt"),
+                include_str!("../../templates/email/password_reset.html"),
+            ),
+            EmailTemplate::RefundIssued => (
+                include_str!("../../templates/email/refund_issued.txt"),
+                include_str!("../../templates/email/refund_issued.html"),
+            ),
+            EmailTemplate::PayoutSummary => (
+                include_str!("../../templates/email/payout_summary.txt"),
+                include_str!("../../templates/email/payout_summary.html"),
+            ),
+            EmailTemplate::Digest => (
+                include_str!("../../templates/email/digest.txt"),
+                include_str!("../../templates/email/digest.html"),
+            ),
+            EmailTemplate::Announcement => (
+                include_str!("../../templates/email/announcement.txt"),
+                include_str!("../../templates/email/announcement.html"),
+            ),
+            EmailTemplate::CheckoutReminder => (
+                include_str!("../../templates/email/checkout_reminder.txt"),
+                include_str!("../../templates/email/checkout_reminder.html"),
+            ),
+            EmailTemplate::AnalyticsReport => (
+                include_str!("../../templates/email/analytics_report.txt"),
+                include_str!("../../templates/email/analytics_report.html"),
+            ),
+        }
+    }
+}
+
+pub struct RenderedEmail {
+    pub subject: String,
+    pub text: String,
+    pub html: String,
+}
+
+/// Handlebars registries for the templates: one that HTML-escapes `{{name}}` values for the
+/// HTML bodies and layout, one that leaves them as they are for subjects and plain text.
+struct Templates {
+    html: Handlebars<'static>,
+    text: Handlebars<'static>,
+}
+
+fn templates() -> &'static Templates {
+    static TEMPLATES: OnceLock<Templates> = OnceLock::new();
+    TEMPLATES.get_or_init(|| {
+        let mut html = Handlebars::new();
+        let mut text = Handlebars::new();
+        text.register_escape_fn(handlebars::no_escape);
+
+        html.register_template_string("layout", LAYOUT)
+            .expect("email layout is valid Handlebars");
+        for template in EmailTemplate::ALL {
+            let (text_body, html_body) = template.bodies();
+            let name = template.as_str();
+            html.register_template_string(name, html_body)
+                .and_then(|_| text.register_template_string(name, text_body))
+                .and_then(|_| {
+                    text.register_template_string(&format!("{}.subject", name), template.subject())
+                })
+                .unwrap_or_else(|e| panic!("email template '{}' is invalid: {}", name, e));
+        }
+        Templates { html, text }
+    })
+}
+
+/// Fill `template` with `data`. Templates use `{{name}}` for values, which are HTML-escaped in
+/// the HTML body, and `{{{name}}}` for markup that goes in as is. Missing values render empty.
+pub fn render(template: EmailTemplate, data: &Value) -> anyhow::Result<RenderedEmail> {
+    let templates = templates();
+    let name = template.as_str();
+    // The subject is a header; a value with a line break in it must not start a new one
+    let subject = templates
+        .text
+        .render(&format!("{}.subject", name), data)?
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    let mut layout = match data {
+        Value::Object(values) => values.clone(),
+        _ => Map::new(),
+    };
+    layout.insert("subject".to_string(), json!(subject));
+    layout.insert("body".to_string(), json!(templates.html.render(name, data)?));
+    let preferences_link = app_link("/creator-dashboard/settings");
+    layout.insert("preferences_link".to_string(), json!(preferences_link));
+
+    Ok(RenderedEmail {
+        text: templates.text.render(name, data)?,
+        html: templates.html.render("layout", &layout)?,
+        subject,
+    })
+}
+
+pub fn escape_html(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Absolute link to `path` in the web app, for email bodies.
+pub fn app_link(path: &str) -> String {
+    let frontend_url = Config::from_env()
+        .map(|config| config.frontend_url)
+        .unwrap_or_else(|_| "http://localhost:3000".to_string());
+    format!("{}{}", frontend_url.trim_end_matches('/'), path)
+}
+
+/// Absolute link to `path` on this API, for links emails handle without the web app.
+pub fn api_link(path: &str) -> String {
+    format!("{}{}", EmailConfig::from_env().api_url.trim_end_matches('/'), path)
+}
+
+/// Record an email for `to` and hand it to the email worker; returns the delivery's id. Nothing
+/// is sent or recorded when `user_id` turned email off for the template's category; addresses
+/// on the suppression list are recorded as SUPPRESSED and never sent to.
+///
+/// Emails are a side effect of whatever triggered them, so failures are logged rather than
+/// returned.
+pub async fn queue_email(
+    db: &Database,
+    to: &str,
+    user_id: Option<&str>,
+    template: EmailTemplate,
+    data: Value,
+) -> Option<Uuid> {
+    let recipient = to.trim().to_lowercase();
+    if recipient.is_empty() {
+        return None;
+    }
+    if let (Some(kind), Some(user_id)) = (template.category(), user_id) {
+        if !channel_enabled(db, user_id, kind, NotificationChannel::Email).await {
+            return None;
+        }
+    }
+
+    let queued = sqlx::query_as::<_, (Uuid, String)>(
+        r#"
+        INSERT INTO email_deliveries (recipient, user_id, template, data, status, next_attempt_at)
+        SELECT $1, $2, $3, $4,
+               CASE WHEN EXISTS (SELECT 1 FROM email_suppressions WHERE email = $1)
+                    THEN 'SUPPRESSED' ELSE 'QUEUED' END,
+               NOW() + make_interval(mins => $5)
+        RETURNING id, status
+        "#,
+    )
+    .bind(&recipient)
+    .bind(user_id)
+    .bind(template.as_str())
+    .bind(&data)
+    .bind(LEASE_MINUTES)
+    .fetch_one(&db.pool)
+    .await;
+
+    match queued {
+        Ok((id, status)) => {
+            if status == "QUEUED" {
+                dispatch(db, id).await;
+            } else {
+                info!("Suppressed {} email {} to {}", template.as_str(), id, recipient);
+            }
+            Some(id)
+        }
+        Err(e) => {
+            warn!("Failed to queue {} email to {}: {}", template.as_str(), recipient, e);
+            None
+        }
+    }
+}
+
+/// Publish a delivery to the email worker, or send it from here when CloudAMQP is not
+/// configured. Lost jobs are picked up again by `sweep_due_deliveries`.
+async fn dispatch(db: &Database, delivery_id: Uuid) {
+    match &db.amqp {
+        Some(amqp) => {
+            let job = JobMessage::SendEmail {
+                delivery_id: delivery_id.to_string(),
+            };
+            if let Err(e) = amqp.publish_job(EMAIL_QUEUE, &job).await {
+                warn!("Failed to publish email delivery {}: {}", delivery_id, e);
+            }
+        }
+        None => {
+            let db = db.clone();
+            tokio::spawn(async move {
+                if let Err(e) = deliver(&db, &delivery_id.to_string()).await {
+                    error!("Email delivery {} failed: {:?}", delivery_id, e);
+                }
+            });
+        }
+    }
+}
+
+/// Re-dispatch deliveries whose retry is due, or whose job was lost while queued or sending.
+pub async fn sweep_due_deliveries(db: &Database) -> anyhow::Result<()> {
+    let due = sqlx::query_scalar::<_, Uuid>(
+        r#"
+        UPDATE email_deliveries
+        SET status = CASE WHEN status = 'SENDING' THEN 'RETRYING' ELSE status END,
+            next_attempt_at = NOW() + make_interval(mins => $1)
+        WHERE id IN (
+            SELECT id FROM email_deliveries
+            WHERE status IN ('QUEUED', 'RETRYING', 'SENDING') AND next_attempt_at <= NOW()
+            ORDER BY next_attempt_at
+            LIMIT $2
+            FOR UPDATE SKIP LOCKED
+        )
+        RETURNING id
+        "#,
+    )
+    .bind(LEASE_MINUTES)
+    .bind(SWEEP_BATCH)
+    .fetch_all(&db.pool)
+    .await?;
+
+    for delivery_id in due {
+        dispatch(db, delivery_id).await;
+    }
+    Ok(())
+}
+
+/// Send one delivery. The row is claimed first, so a job that arrives twice sends once; a failed
+/// attempt is retried with backoff (1, 4, 16, 64 minutes) until `MAX_ATTEMPTS`.
+pub async fn deliver(db: &Database, delivery_id: &str) -> anyhow::Result<()> {
+    let delivery_id = Uuid::parse_str(delivery_id)?;
+    let claimed = sqlx::query_as::<_, (String, String, Value, i32)>(
+        r#"
+        UPDATE email_deliveries
+        SET status = 'SENDING', attempts = attempts + 1,
+            next_attempt_at = NOW() + make_interval(mins => $2)
+        WHERE id = $1 AND status IN ('QUEUED', 'RETRYING')
+        RETURNING recipient, template, data, attempts
+        "#,
+    )
+    .bind(delivery_id)
+    .bind(LEASE_MINUTES)
+    .fetch_optional(&db.pool)
+    .await?;
+    let Some((recipient, template, data, attempts)) = claimed else {
+        return Ok(());
+    };
+
+    // A bounce may have come in since the email was queued
+    let suppressed = sqlx::query_scalar::<_, bool>(
+        "SELECT EXISTS (SELECT 1 FROM email_suppressions WHERE email = $1)",
+    )
+    .bind(&recipient)
+    .fetch_one(&db.pool)
+    .await?;
+    if suppressed {
+        sqlx::query("UPDATE email_deliveries SET status = 'SUPPRESSED' WHERE id = $1")
+            .bind(delivery_id)
+            .execute(&db.pool)
+            .await?;
+        return Ok(());
+    }
+
+    let Some(template) = EmailTemplate::parse(&template) else {
+        sqlx::query(
+            "UPDATE email_deliveries SET status = 'FAILED', last_error = $2 WHERE id = $1",
+        )
+        .bind(delivery_id)
+        .bind(format!("Unknown template '{}'", template))
+        .execute(&db.pool)
+        .await?;
+        return Ok(());
+    };
+
+    let email = match render(template, &data) {
+        Ok(email) => email,
+        Err(e) => {
+            sqlx::query(
+                "UPDATE email_deliveries SET status = 'FAILED', last_error = $2 WHERE id = $1",
+            )
+            .bind(delivery_id)
+            .bind(format!("Failed to render: {}", e))
+            .execute(&db.pool)
+            .await?;
+            return Ok(());
+        }
+    };
+
+    let config = EmailConfig::from_env();
+    let sent = match tokio::time::timeout(SEND_TIMEOUT, send(&config, &recipient, &email)).await {
+        Ok(sent) => sent,
+        Err(_) => Err(anyhow::anyhow!("{} timed out", config.provider)),
+    };
+
+    match sent {
+        Ok(message_id) => {
+            sqlx::query(
+                r#"
+                UPDATE email_deliveries
+                SET status = 'SENT', sent_at = NOW(), provider_message_id = $2, last_error = NULL
+                WHERE id = $1
+                "#,
+            )
+            .bind(delivery_id)
+            .bind(message_id)
+            .execute(&db.pool)
+            .await?;
+        }
+        Err(e) => {
+            warn!(
+                "Attempt {} of email delivery {} failed: {}",
+                attempts, delivery_id, e
+            );
+            let (status, retry_in_minutes) = if attempts >= MAX_ATTEMPTS {
+                ("FAILED", 0)
+            } else {
+                ("RETRYING", 4i32.pow((attempts - 1).max(0) as u32))
+            };
+            sqlx::query(
+                r#"
+                UPDATE email_deliveries
+                SET status = $2, last_error = $3,
+                    next_attempt_at = NOW() + make_interval(mins => $4)
+                WHERE id = $1
+                "#,
+            )
+            .bind(delivery_id)
+            .bind(status)
+            .bind(e.to_string())
+            .bind(retry_in_minutes)
+            .execute(&db.pool)
+            .await?;
+        }
+    }
+    Ok(())
+}
+
+/// Hand `email` to the configured provider; returns the provider's id for the message.
+async fn send(
+    config: &EmailConfig,
+    to: &str,
+    email: &RenderedEmail,
+) -> anyhow::Result<Option<String>> {
+    match config.provider.as_str() {
+        "smtp" => smtp::send(config, &config.smtp_host, to, email).await,
+        // SES takes mail on its SMTP interface, with the SMTP credentials SES issues
+        "ses" => {
+            let host = format!("email-smtp.{}.amazonaws.com", config.ses_region);
+            smtp::send(config, &host, to, email).await
+        }
+        "postmark" => send_postmark(config, to, email).await,
+        "log" => {
+            info!("📧 Email to {}: {}\n{}", to, email.subject, email.text);
+            Ok(None)
+        }
+        other => anyhow::bail!("Unknown EMAIL_PROVIDER '{}'", other),
+    }
+}
+
+async fn send_postmark(
+    config: &EmailConfig,
+    to: &str,
+    email: &RenderedEmail,
+) -> anyhow::Result<Option<String>> {
+    let response = reqwest::Client::new()
+        .post("https://api.postmarkapp.com/email")
+        .header("Accept", "application/json")
+        .header("X-Postmark-Server-Token", &config.postmark_server_token)
+        .json(&json!({
+            "From": config.from,
+            "To": to,
+            "Subject": email.subject,
+            "TextBody": email.text,
+            "HtmlBody": email.html,
+            "MessageStream": "outbound",
+        }))
+        .send()
+        .await?;
+
+    let status = response.status();
+    let body: Value = response.json().await.unwrap_or(Value::Null);
+    if !status.is_success() || body["ErrorCode"].as_i64().unwrap_or(0) != 0 {
+        anyhow::bail!(
+            "Postmark answered {}: {}",
+            status,
+            body["Message"].as_str().unwrap_or("no message")
+        );
+    }
+    Ok(body["MessageID"].as_str().map(str::to_string))
+}
+
+/// Stop sending to `email`, e.g. after a hard bounce or a spam complaint.
+pub async fn suppress(db: &Database, email: &str, reason: &str, source: &str) {
+    let result = sqlx::query(
+        r#"
+        INSERT INTO email_suppressions (email, reason, source)
+        VALUES ($1, $2, $3)
+        ON CONFLICT (email) DO UPDATE SET reason = EXCLUDED.reason, source = EXCLUDED.source
+        "#,
+    )
+    .bind(email.trim().to_lowercase())
+    .bind(reason)
+    .bind(source)
+    .execute(&db.pool)
+    .await;
+    match result {
+        Ok(_) => info!("Suppressed email to {} ({} from {})", email, reason, source),
+        Err(e) => warn!("Failed to suppress {}: {}", email, e),
+    }
+}
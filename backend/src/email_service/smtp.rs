@@ -0,0 +1,46 @@
+use lettre::{
+    message::{Mailbox, MultiPart},
+    transport::smtp::authentication::Credentials,
+    AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor,
+};
+use uuid::Uuid;
+
+use super::{RenderedEmail, SEND_TIMEOUT};
+use crate::config::EmailConfig;
+
+/// Submit `email` to the SMTP server at `host`. Port 465 uses implicit TLS; any other port must
+/// offer STARTTLS, so credentials never cross in the clear.
+pub async fn send(
+    config: &EmailConfig,
+    host: &str,
+    to: &str,
+    email: &RenderedEmail,
+) -> anyhow::Result<Option<String>> {
+    let from: Mailbox = config.from.parse()?;
+    let message_id = format!("{}@{}", Uuid::new_v4().simple(), from.email.domain());
+    let message = Message::builder()
+        .from(from)
+        .to(to.parse()?)
+        .subject(email.subject.as_str())
+        .message_id(Some(format!("<{}>", message_id)))
+        .multipart(MultiPart::alternative_plain_html(
+            email.text.clone(),
+            email.html.clone(),
+        ))?;
+
+    let transport = if config.smtp_port == 465 {
+        AsyncSmtpTransport::<Tokio1Executor>::relay(host)?
+    } else {
+        AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(host)?
+    };
+    let mut transport = transport.port(config.smtp_port).timeout(Some(SEND_TIMEOUT));
+    if !config.smtp_username.is_empty() {
+        transport = transport.credentials(Credentials::new(
+            config.smtp_username.clone(),
+            config.smtp_password.clone(),
+        ));
+    }
+    transport.build().send(message).await?;
+
+    Ok(Some(message_id))
+}
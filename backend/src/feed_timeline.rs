@@ -0,0 +1,285 @@
+use chrono::{DateTime, Duration, Utc};
+use tracing::error;
+
+use crate::{database::Database, pagination::Cursor};
+
+/// Creators with a bigger audience than this are not fanned out on publish; their content is
+/// merged into each reader's timeline when it is read instead.
+const FANOUT_MAX_AUDIENCE: i64 = 5_000;
+/// Creators whose content is fanned in at read time.
+const FAN_IN_KEY: &str = "feed_timeline:fan_in";
+const TIMELINE_MAX_ITEMS: usize = 500;
+/// Timelines are rebuilt from the database once a day, which also picks up follow and
+/// subscription changes the fan-out never saw.
+const TIMELINE_TTL_SECONDS: usize = 24 * 60 * 60;
+const BACKFILL_DAYS: i64 = 30;
+/// Kept in every timeline so that one without any items still exists.
+const TIMELINE_MARKER: &str = "timeline";
+const SCAN_BATCH: isize = 100;
+
+/// Everyone who sees a creator's content in their feed: followers and active subscribers.
+const AUDIENCE_SQL: &str = r#"
+    SELECT follower_id FROM follows WHERE following_id = $1
+    UNION
+    SELECT user_id FROM subscriptions WHERE creator_id = $1 AND UPPER(status) = 'ACTIVE'
+"#;
+
+/// Creators whose content a user sees in their feed.
+const SOURCES_SQL: &str = r#"
+    SELECT following_id FROM follows WHERE follower_id = $1
+    UNION
+    SELECT creator_id FROM subscriptions WHERE user_id = $1 AND UPPER(status) = 'ACTIVE'
+"#;
+
+/// Feed items of the given creators keyed like the feed (`post-<id>`, ...) at their feed time,
+/// newest first.
+const ITEMS_SQL: &str = r#"
+    SELECT key, at FROM (
+        SELECT 'post-' || p.id::TEXT AS key, p.created_at AS at
+        FROM posts p
+        WHERE p.user_id = ANY($1)
+          AND COALESCE(p.published, TRUE)
+          AND COALESCE(p.published_at, p.created_at) <= NOW()
+        UNION ALL
+        SELECT 'article-' || a.id::TEXT, COALESCE(a.published_at, a.created_at)
+        FROM articles a
+        WHERE a.author_id = ANY($1)
+          AND (a.published_at IS NULL OR a.published_at <= NOW())
+        UNION ALL
+        SELECT 'event-' || e.id::TEXT, COALESCE(e.start_time, e.created_at)
+        FROM events e
+        WHERE e.host_id = ANY($1)
+    ) items
+    WHERE at >= $2
+      AND ($3::TIMESTAMPTZ IS NULL OR (at, key) < ($3, $4))
+      AND ($5::TEXT IS NULL OR key LIKE $5 || '-%')
+    ORDER BY at DESC, key DESC
+    LIMIT $6
+"#;
+
+fn timeline_key(user_id: &str) -> String {
+    format!("feed_timeline:{}", user_id)
+}
+
+// Microseconds since the epoch fit an f64 score exactly
+fn score(at: DateTime<Utc>) -> f64 {
+    at.timestamp_micros() as f64
+}
+
+fn score_time(score: f64) -> Option<DateTime<Utc>> {
+    DateTime::from_timestamp_micros(score as i64)
+}
+
+/// Add a newly published item to the timelines of its creator's audience. Runs in the
+/// background; without Redis there are no timelines to update.
+pub fn publish(db: &Database, creator_id: &str, item_key: String, at: DateTime<Utc>) {
+    if db.redis.is_none() {
+        return;
+    }
+    let db = db.clone();
+    let creator_id = creator_id.to_string();
+    tokio::spawn(async move {
+        if let Err(e) = fan_out(&db, &creator_id, &item_key, at).await {
+            error!("Failed to fan out feed item {}: {:?}", item_key, e);
+        }
+    });
+}
+
+/// Take a deleted or unpublished item back out of its creator's audience's timelines.
+pub fn retract(db: &Database, creator_id: &str, item_key: String) {
+    if db.redis.is_none() {
+        return;
+    }
+    let db = db.clone();
+    let creator_id = creator_id.to_string();
+    tokio::spawn(async move {
+        let result = async {
+            let Some(redis) = &db.redis else {
+                return Ok(());
+            };
+            let keys = audience_timelines(&db, &creator_id, None).await?;
+            redis.clone().zrem_all(&keys, &item_key).await
+        }
+        .await;
+        if let Err(e) = result {
+            error!("Failed to retract feed item {}: {:?}", item_key, e);
+        }
+    });
+}
+
+/// Drop a user's timeline so the next read rebuilds it, e.g. after they follow someone.
+pub async fn reset(db: &Database, user_id: &str) {
+    if let Some(redis) = &db.redis {
+        let _ = redis.clone().del(&timeline_key(user_id)).await;
+    }
+}
+
+async fn audience_timelines(
+    db: &Database,
+    creator_id: &str,
+    limit: Option<i64>,
+) -> anyhow::Result<Vec<String>> {
+    let audience: Vec<String> = sqlx::query_scalar(&format!(
+        "SELECT follower_id FROM ({}) audience(follower_id) LIMIT $2",
+        AUDIENCE_SQL
+    ))
+    .bind(creator_id)
+    .bind(limit)
+    .fetch_all(&db.pool)
+    .await?;
+    Ok(audience.iter().map(|user_id| timeline_key(user_id)).collect())
+}
+
+async fn fan_out(
+    db: &Database,
+    creator_id: &str,
+    item_key: &str,
+    at: DateTime<Utc>,
+) -> anyhow::Result<()> {
+    let Some(redis) = &db.redis else {
+        return Ok(());
+    };
+    let mut redis = redis.clone();
+
+    let keys = audience_timelines(db, creator_id, Some(FANOUT_MAX_AUDIENCE + 1)).await?;
+    if keys.len() as i64 > FANOUT_MAX_AUDIENCE {
+        redis.sadd(FAN_IN_KEY, creator_id).await?;
+        return Ok(());
+    }
+
+    // Timelines that don't exist yet are built with this item when they are first read
+    for chunk in keys.chunks(500) {
+        redis
+            .zadd_existing(chunk, item_key, score(at), TIMELINE_MAX_ITEMS)
+            .await?;
+    }
+    Ok(())
+}
+
+async fn load_items(
+    db: &Database,
+    creators: &[String],
+    since: DateTime<Utc>,
+    cursor: Option<&Cursor>,
+    item_type: Option<&str>,
+    limit: i64,
+) -> Result<Vec<(String, DateTime<Utc>)>, sqlx::Error> {
+    if creators.is_empty() {
+        return Ok(Vec::new());
+    }
+    sqlx::query_as(ITEMS_SQL)
+        .bind(creators)
+        .bind(since)
+        .bind(cursor.map(|cursor| cursor.at))
+        .bind(cursor.map(|cursor| cursor.id.clone()))
+        .bind(item_type)
+        .bind(limit)
+        .fetch_all(&db.pool)
+        .await
+}
+
+/// One page of a user's timeline after `cursor`: up to `limit` item keys and their feed
+/// times, newest first, optionally only of one type (`post`, `article` or `event`).
+///
+/// `None` when there is no Redis or the user follows no one, in which case the feed shows
+/// everyone's content instead.
+pub async fn page(
+    db: &Database,
+    user_id: &str,
+    cursor: Option<&Cursor>,
+    item_type: Option<&str>,
+    limit: usize,
+) -> Option<Vec<(String, DateTime<Utc>)>> {
+    let result = async {
+        let Some(redis) = &db.redis else {
+            return Ok(None);
+        };
+        let mut redis = redis.clone();
+        let key = timeline_key(user_id);
+
+        let sources: Vec<String> = sqlx::query_scalar(SOURCES_SQL)
+            .bind(user_id)
+            .fetch_all(&db.pool)
+            .await?;
+        if sources.is_empty() {
+            return Ok(None);
+        }
+        let fan_in = redis.smembers(FAN_IN_KEY).await?;
+        let (fanned_in, fanned_out): (Vec<String>, Vec<String>) = sources
+            .into_iter()
+            .partition(|creator_id| fan_in.contains(creator_id));
+
+        if !redis.exists(&key).await? {
+            let since = Utc::now() - Duration::days(BACKFILL_DAYS);
+            let mut members: Vec<(f64, String)> =
+                load_items(db, &fanned_out, since, None, None, TIMELINE_MAX_ITEMS as i64)
+                    .await?
+                    .into_iter()
+                    .map(|(key, at)| (score(at), key))
+                    .collect();
+            members.push((0.0, TIMELINE_MARKER.to_string()));
+            redis.zreplace(&key, &members, TIMELINE_TTL_SECONDS).await?;
+        }
+
+        // Scan down from the cursor, skipping what it has already covered and other types
+        let mut items: Vec<(String, DateTime<Utc>)> = Vec::new();
+        let mut offset = 0;
+        while items.len() < limit {
+            let batch = redis
+                .zrevrange_from(&key, cursor.map(|cursor| score(cursor.at)), offset, SCAN_BATCH)
+                .await?;
+            let exhausted = (batch.len() as isize) < SCAN_BATCH;
+            offset += SCAN_BATCH;
+            for (member, member_score) in batch {
+                let Some(at) = score_time(member_score) else {
+                    continue;
+                };
+                let after_cursor = cursor
+                    .map(|cursor| (at, &member) < (cursor.at, &cursor.id))
+                    .unwrap_or(true);
+                let wanted_type = item_type
+                    .map(|item_type| member.starts_with(&format!("{}-", item_type)))
+                    .unwrap_or(true);
+                if member != TIMELINE_MARKER && after_cursor && wanted_type {
+                    items.push((member, at));
+                }
+            }
+            if exhausted {
+                break;
+            }
+        }
+        items.truncate(limit);
+
+        // Big creators' content is read straight from the database
+        let fanned_in_items = load_items(
+            db,
+            &fanned_in,
+            DateTime::UNIX_EPOCH,
+            cursor,
+            item_type,
+            limit as i64,
+        )
+        .await?;
+        if items.is_empty() && fanned_in_items.is_empty() && cursor.is_none() {
+            let timeline_len = redis.zcard(&key).await?;
+            if timeline_len <= 1 && fanned_in.is_empty() {
+                return Ok(None);
+            }
+        }
+        for item in fanned_in_items {
+            if !items.iter().any(|(key, _)| *key == item.0) {
+                items.push(item);
+            }
+        }
+        items.sort_by(|a, b| (b.1, &b.0).cmp(&(a.1, &a.0)));
+        items.truncate(limit);
+
+        anyhow::Ok(Some(items))
+    }
+    .await;
+
+    result.unwrap_or_else(|e| {
+        error!("Failed to read feed timeline for {}: {:?}", user_id, e);
+        None
+    })
+}
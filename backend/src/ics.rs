@@ -0,0 +1,270 @@
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Datelike, Duration, NaiveDate, Offset, TimeZone, Utc};
+use chrono_tz::{OffsetComponents, Tz};
+
+/// Identifies this backend in generated calendars.
+const PRODUCT_ID: &str = "-//Fundify//Events//EN";
+/// Calendar apps poll subscription feeds at roughly this interval.
+const REFRESH_INTERVAL: &str = "PT1H";
+/// RFC 5545 content lines are folded after this many octets.
+const MAX_LINE_OCTETS: usize = 75;
+
+/// One event rendered as a VEVENT.
+#[derive(Debug)]
+pub struct CalendarEvent {
+    pub id: String,
+    pub title: String,
+    pub description: Option<String>,
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    /// IANA zone the host scheduled the event in; unknown zones fall back to UTC times.
+    pub timezone: Option<String>,
+    pub location: Option<String>,
+    pub virtual_link: Option<String>,
+    pub url: Option<String>,
+    pub host_name: Option<String>,
+    /// Event status as stored (`PUBLISHED`, `CANCELLED`, ...).
+    pub status: String,
+    /// Attendance is tentative when the attendee answered "maybe".
+    pub tentative: bool,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Render a VCALENDAR with one VEVENT per event and a VTIMEZONE for every zone they use.
+pub fn render_calendar(name: &str, events: &[CalendarEvent]) -> String {
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        format!("PRODID:{}", PRODUCT_ID),
+        "CALSCALE:GREGORIAN".to_string(),
+        "METHOD:PUBLISH".to_string(),
+        format!("X-WR-CALNAME:{}", escape_text(name)),
+        format!("NAME:{}", escape_text(name)),
+        format!("REFRESH-INTERVAL;VALUE=DURATION:{}", REFRESH_INTERVAL),
+        format!("X-PUBLISHED-TTL:{}", REFRESH_INTERVAL),
+    ];
+
+    // Each zone's VTIMEZONE must cover every event that references it.
+    let mut zones: BTreeMap<String, (Tz, i32, i32)> = BTreeMap::new();
+    for event in events {
+        if let Some(tz) = event_zone(event) {
+            let entry = zones
+                .entry(tz.name().to_string())
+                .or_insert((tz, event.start.year(), event.end.year()));
+            entry.1 = entry.1.min(event.start.year());
+            entry.2 = entry.2.max(event.end.year());
+        }
+    }
+    for (tz, first_year, last_year) in zones.values() {
+        lines.extend(timezone_component(*tz, *first_year, *last_year));
+    }
+
+    let stamp = format_utc(Utc::now());
+    for event in events {
+        lines.extend(event_component(event, &stamp));
+    }
+
+    lines.push("END:VCALENDAR".to_string());
+
+    let mut calendar = String::new();
+    for line in lines {
+        calendar.push_str(&fold_line(&line));
+    }
+    calendar
+}
+
+fn event_component(event: &CalendarEvent, stamp: &str) -> Vec<String> {
+    let mut lines = vec![
+        "BEGIN:VEVENT".to_string(),
+        format!("UID:event-{}@fundify", event.id),
+        format!("DTSTAMP:{}", stamp),
+        format!("LAST-MODIFIED:{}", format_utc(event.updated_at)),
+    ];
+
+    // An event without a duration still needs a valid, non-negative DTEND.
+    let end = event.end.max(event.start);
+    match event_zone(event) {
+        Some(tz) => {
+            lines.push(format!("DTSTART;TZID={}:{}", tz.name(), format_local(tz, event.start)));
+            lines.push(format!("DTEND;TZID={}:{}", tz.name(), format_local(tz, end)));
+        }
+        None => {
+            lines.push(format!("DTSTART:{}", format_utc(event.start)));
+            lines.push(format!("DTEND:{}", format_utc(end)));
+        }
+    }
+
+    lines.push(format!("SUMMARY:{}", escape_text(&event.title)));
+
+    // Google Calendar ignores URL and CONFERENCE, so the join link is repeated in the description.
+    let mut description = event.description.clone().unwrap_or_default();
+    for extra in [
+        event.host_name.as_ref().map(|name| format!("Hosted by {}", name)),
+        event.virtual_link.as_ref().map(|link| format!("Join online: {}", link)),
+    ]
+    .into_iter()
+    .flatten()
+    {
+        if !description.is_empty() {
+            description.push_str("\n\n");
+        }
+        description.push_str(&extra);
+    }
+    if !description.is_empty() {
+        lines.push(format!("DESCRIPTION:{}", escape_text(&description)));
+    }
+
+    match (&event.location, &event.virtual_link) {
+        (Some(location), _) => lines.push(format!("LOCATION:{}", escape_text(location))),
+        (None, Some(link)) => lines.push(format!("LOCATION:{}", escape_text(link))),
+        (None, None) => {}
+    }
+
+    if let Some(link) = &event.virtual_link {
+        lines.push(format!("CONFERENCE;VALUE=URI;FEATURE=VIDEO:{}", link));
+    }
+    if let Some(url) = event.url.as_ref().or(event.virtual_link.as_ref()) {
+        lines.push(format!("URL:{}", url));
+    }
+
+    let status = if event.status.eq_ignore_ascii_case("CANCELLED") {
+        "CANCELLED"
+    } else if event.tentative {
+        "TENTATIVE"
+    } else {
+        "CONFIRMED"
+    };
+    lines.push(format!("STATUS:{}", status));
+    lines.push("TRANSP:OPAQUE".to_string());
+    lines.push("END:VEVENT".to_string());
+    lines
+}
+
+fn event_zone(event: &CalendarEvent) -> Option<Tz> {
+    event
+        .timezone
+        .as_deref()
+        .and_then(|name| name.trim().parse::<Tz>().ok())
+        .filter(|tz| *tz != Tz::UTC)
+}
+
+/// Build a VTIMEZONE whose observances are the zone's actual offset changes between the
+/// first and last year, found by scanning day by day and narrowing down to the second.
+fn timezone_component(tz: Tz, first_year: i32, last_year: i32) -> Vec<String> {
+    let range_start = Utc.from_utc_datetime(
+        &NaiveDate::from_ymd_opt(first_year, 1, 1)
+            .unwrap_or_default()
+            .and_hms_opt(0, 0, 0)
+            .unwrap_or_default(),
+    );
+    let range_end = Utc.from_utc_datetime(
+        &NaiveDate::from_ymd_opt(last_year + 1, 1, 1)
+            .unwrap_or_default()
+            .and_hms_opt(0, 0, 0)
+            .unwrap_or_default(),
+    );
+
+    let offset_at = |instant: DateTime<Utc>| {
+        tz.offset_from_utc_datetime(&instant.naive_utc())
+            .fix()
+            .local_minus_utc()
+    };
+
+    let mut lines = vec!["BEGIN:VTIMEZONE".to_string(), format!("TZID:{}", tz.name())];
+
+    let initial = offset_at(range_start);
+    lines.extend(observance(tz, range_start, initial, initial));
+
+    let mut cursor = range_start;
+    let mut current = initial;
+    while cursor < range_end {
+        let next = cursor + Duration::days(1);
+        let next_offset = offset_at(next);
+        if next_offset != current {
+            let (mut low, mut high) = (cursor, next);
+            while high - low > Duration::seconds(1) {
+                let middle = low + (high - low) / 2;
+                if offset_at(middle) == current {
+                    low = middle;
+                } else {
+                    high = middle;
+                }
+            }
+            lines.extend(observance(tz, high, current, next_offset));
+            current = next_offset;
+        }
+        cursor = next;
+    }
+
+    lines.push("END:VTIMEZONE".to_string());
+    lines
+}
+
+fn observance(tz: Tz, starts_at: DateTime<Utc>, offset_from: i32, offset_to: i32) -> Vec<String> {
+    let local = tz.from_utc_datetime(&starts_at.naive_utc());
+    let kind = if local.offset().dst_offset().num_seconds() != 0 {
+        "DAYLIGHT"
+    } else {
+        "STANDARD"
+    };
+
+    // DTSTART of an observance is expressed in the offset in effect before it begins.
+    let onset = starts_at + Duration::seconds(offset_from as i64);
+
+    vec![
+        format!("BEGIN:{}", kind),
+        format!("DTSTART:{}", onset.format("%Y%m%dT%H%M%S")),
+        format!("TZOFFSETFROM:{}", format_offset(offset_from)),
+        format!("TZOFFSETTO:{}", format_offset(offset_to)),
+        format!("TZNAME:{}", escape_text(&local.format("%Z").to_string())),
+        format!("END:{}", kind),
+    ]
+}
+
+fn format_offset(seconds: i32) -> String {
+    let sign = if seconds < 0 { '-' } else { '+' };
+    let seconds = seconds.abs();
+    let (hours, minutes, rest) = (seconds / 3600, (seconds % 3600) / 60, seconds % 60);
+    if rest == 0 {
+        format!("{}{:02}{:02}", sign, hours, minutes)
+    } else {
+        format!("{}{:02}{:02}{:02}", sign, hours, minutes, rest)
+    }
+}
+
+fn format_utc(instant: DateTime<Utc>) -> String {
+    instant.format("%Y%m%dT%H%M%SZ").to_string()
+}
+
+fn format_local(tz: Tz, instant: DateTime<Utc>) -> String {
+    instant.with_timezone(&tz).format("%Y%m%dT%H%M%S").to_string()
+}
+
+fn escape_text(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace("\r\n", "\\n")
+        .replace(['\n', '\r'], "\\n")
+}
+
+/// Fold a content line into CRLF-terminated chunks of at most 75 octets, never splitting
+/// a UTF-8 character.
+fn fold_line(line: &str) -> String {
+    let mut folded = String::with_capacity(line.len() + 8);
+    let mut width = 0;
+
+    for c in line.chars() {
+        if width + c.len_utf8() > MAX_LINE_OCTETS {
+            folded.push_str("\r\n ");
+            width = 1;
+        }
+        folded.push(c);
+        width += c.len_utf8();
+    }
+
+    folded.push_str("\r\n");
+    folded
+}
@@ -0,0 +1,359 @@
+use std::io::Cursor;
+
+use chrono::{DateTime, Utc};
+use owned_ttf_parser::{Face, GlyphId};
+use printpdf::{lopdf, IndirectFontRef, Line, Mm, PdfDocument, PdfDocumentReference, PdfLayerReference, Point, Pt};
+
+/// A4 portrait, in PDF points.
+const PAGE_WIDTH: f32 = 595.0;
+const PAGE_HEIGHT: f32 = 842.0;
+const MARGIN: f32 = 56.0;
+/// Lowest a line of content may sit; the page footer goes below it.
+const CONTENT_BOTTOM: f32 = MARGIN + 24.0;
+/// Width kept free for the amount column.
+const AMOUNT_COLUMN: f32 = 110.0;
+
+/// DejaVu Sans covers Latin, Greek and Cyrillic, so names and descriptions print as they were
+/// typed. Both faces are embedded in every invoice.
+const REGULAR_FONT: &[u8] = include_bytes!("../assets/fonts/DejaVuSans.ttf");
+const BOLD_FONT: &[u8] = include_bytes!("../assets/fonts/DejaVuSans-Bold.ttf");
+
+/// One invoice, rendered as a PDF with as many pages as its lines need.
+#[derive(Debug)]
+pub struct InvoiceDocument {
+    pub number: String,
+    pub issued_at: DateTime<Utc>,
+    pub seller_name: String,
+    pub seller_email: Option<String>,
+    pub buyer_name: String,
+    pub buyer_email: Option<String>,
+    pub lines: Vec<InvoiceLine>,
+    pub currency: String,
+    /// Free text under the totals, e.g. the payment reference.
+    pub note: Option<String>,
+}
+
+#[derive(Debug)]
+pub struct InvoiceLine {
+    pub description: String,
+    pub amount_cents: i64,
+}
+
+impl InvoiceDocument {
+    pub fn total_cents(&self) -> i64 {
+        self.lines.iter().map(|line| line.amount_cents).sum()
+    }
+}
+
+/// Render the invoice. Long descriptions wrap, and the line table continues on a new page, under
+/// its header again, when it reaches the bottom of one.
+pub fn render_invoice(invoice: &InvoiceDocument) -> anyhow::Result<Vec<u8>> {
+    let mut writer = Writer::new(&format!("Invoice {}", invoice.number))?;
+    let right = PAGE_WIDTH - MARGIN;
+    let column = PAGE_WIDTH / 2.0;
+
+    writer.text(MARGIN, 22.0, true, "INVOICE");
+    writer.text_right(right, 10.0, false, &format!("Invoice no. {}", invoice.number));
+    writer.down(16.0);
+    writer.text_right(right, 10.0, false, &format!("Issued {}", invoice.issued_at.format("%Y-%m-%d")));
+
+    writer.down(40.0);
+    writer.text(MARGIN, 9.0, true, "FROM");
+    writer.text(column, 9.0, true, "BILLED TO");
+    let party_width = column - MARGIN - 12.0;
+    let seller = writer.wrap(&invoice.seller_name, 11.0, false, party_width);
+    let buyer = writer.wrap(&invoice.buyer_name, 11.0, false, party_width);
+    for index in 0..seller.len().max(buyer.len()) {
+        writer.down(if index == 0 { 15.0 } else { 13.0 });
+        if let Some(line) = seller.get(index) {
+            writer.text(MARGIN, 11.0, false, line);
+        }
+        if let Some(line) = buyer.get(index) {
+            writer.text(column, 11.0, false, line);
+        }
+    }
+    writer.down(14.0);
+    if let Some(email) = &invoice.seller_email {
+        writer.text(MARGIN, 10.0, false, email);
+    }
+    if let Some(email) = &invoice.buyer_email {
+        writer.text(column, 10.0, false, email);
+    }
+
+    writer.down(44.0);
+    table_header(&mut writer, "DESCRIPTION");
+    let description_width = right - AMOUNT_COLUMN - MARGIN;
+    for line in &invoice.lines {
+        let wrapped = writer.wrap(&line.description, 10.0, false, description_width);
+        let height = 18.0 + 13.0 * (wrapped.len() - 1) as f32;
+        if writer.y - height < CONTENT_BOTTOM {
+            writer.new_page();
+            table_header(&mut writer, "DESCRIPTION (CONTINUED)");
+        }
+        writer.down(18.0);
+        writer.text_right(right, 10.0, false, &format_amount(line.amount_cents, &invoice.currency));
+        for (index, text) in wrapped.iter().enumerate() {
+            if index > 0 {
+                writer.down(13.0);
+            }
+            writer.text(MARGIN, 10.0, false, text);
+        }
+    }
+
+    if writer.y - 30.0 < CONTENT_BOTTOM {
+        writer.new_page();
+    }
+    writer.down(10.0);
+    writer.rule(MARGIN, right);
+    writer.down(20.0);
+    writer.text_right(right - AMOUNT_COLUMN, 11.0, true, "Total");
+    writer.text_right(right, 11.0, true, &format_amount(invoice.total_cents(), &invoice.currency));
+
+    if let Some(note) = &invoice.note {
+        writer.down(27.0);
+        for line in writer.wrap(note, 9.0, false, right - MARGIN) {
+            writer.down(13.0);
+            if writer.y < CONTENT_BOTTOM {
+                writer.new_page();
+                writer.down(13.0);
+            }
+            writer.text(MARGIN, 9.0, false, &line);
+        }
+    }
+
+    writer.finish(&invoice.number)
+}
+
+fn table_header(writer: &mut Writer, title: &str) {
+    let right = PAGE_WIDTH - MARGIN;
+    writer.text(MARGIN, 9.0, true, title);
+    writer.text_right(right, 9.0, true, "AMOUNT");
+    writer.down(8.0);
+    writer.rule(MARGIN, right);
+}
+
+/// `1234` cents in `usd` becomes `12.34 USD`.
+pub fn format_amount(cents: i64, currency: &str) -> String {
+    let sign = if cents < 0 { "-" } else { "" };
+    let cents = cents.abs();
+    format!("{}{}.{:02} {}", sign, cents / 100, cents % 100, currency.to_uppercase())
+}
+
+/// An embedded font, with its metrics for measuring text.
+struct Font {
+    pdf: IndirectFontRef,
+    face: Face<'static>,
+}
+
+impl Font {
+    fn load(doc: &PdfDocumentReference, data: &'static [u8]) -> anyhow::Result<Self> {
+        Ok(Self {
+            pdf: doc.add_external_font(Cursor::new(data))?,
+            face: Face::parse(data, 0)?,
+        })
+    }
+
+    /// Width of `value` at `size`, in points. Characters the font lacks are drawn, and measured,
+    /// as its missing-glyph box.
+    fn width(&self, value: &str, size: f32) -> f32 {
+        let units: u32 = value
+            .chars()
+            .map(|c| {
+                let glyph = self.face.glyph_index(c).unwrap_or(GlyphId(0));
+                u32::from(self.face.glyph_hor_advance(glyph).unwrap_or(0))
+            })
+            .sum();
+        units as f32 * size / f32::from(self.face.units_per_em())
+    }
+}
+
+/// Lays text out top to bottom, starting a page when asked to.
+struct Writer {
+    doc: PdfDocumentReference,
+    pages: Vec<PdfLayerReference>,
+    regular: Font,
+    bold: Font,
+    /// Baseline of the current line, in points from the bottom of the page.
+    y: f32,
+}
+
+impl Writer {
+    fn new(title: &str) -> anyhow::Result<Self> {
+        let (doc, page, layer) = PdfDocument::new(title, Mm::from(Pt(PAGE_WIDTH)), Mm::from(Pt(PAGE_HEIGHT)), "Invoice");
+        let first = doc.get_page(page).get_layer(layer);
+        Ok(Self {
+            regular: Font::load(&doc, REGULAR_FONT)?,
+            bold: Font::load(&doc, BOLD_FONT)?,
+            doc,
+            pages: vec![first],
+            y: PAGE_HEIGHT - MARGIN,
+        })
+    }
+
+    fn new_page(&mut self) {
+        let (page, layer) = self
+            .doc
+            .add_page(Mm::from(Pt(PAGE_WIDTH)), Mm::from(Pt(PAGE_HEIGHT)), "Invoice");
+        self.pages.push(self.doc.get_page(page).get_layer(layer));
+        self.y = PAGE_HEIGHT - MARGIN;
+    }
+
+    fn down(&mut self, by: f32) {
+        self.y -= by;
+    }
+
+    fn font(&self, bold: bool) -> &Font {
+        if bold {
+            &self.bold
+        } else {
+            &self.regular
+        }
+    }
+
+    fn layer(&self) -> &PdfLayerReference {
+        self.pages.last().expect("the document starts with a page")
+    }
+
+    fn text(&self, x: f32, size: f32, bold: bool, value: &str) {
+        self.layer().use_text(
+            value,
+            size,
+            Mm::from(Pt(x)),
+            Mm::from(Pt(self.y)),
+            &self.font(bold).pdf,
+        );
+    }
+
+    fn text_right(&self, right: f32, size: f32, bold: bool, value: &str) {
+        let width = self.font(bold).width(value, size);
+        self.text(right - width, size, bold, value);
+    }
+
+    fn rule(&self, from: f32, to: f32) {
+        let layer = self.layer();
+        layer.set_outline_thickness(0.5);
+        layer.add_line(Line {
+            points: vec![
+                (Point::new(Mm::from(Pt(from)), Mm::from(Pt(self.y))), false),
+                (Point::new(Mm::from(Pt(to)), Mm::from(Pt(self.y))), false),
+            ],
+            is_closed: false,
+        });
+    }
+
+    /// Break `value` into lines no wider than `width`, between words where possible.
+    fn wrap(&self, value: &str, size: f32, bold: bool, width: f32) -> Vec<String> {
+        let font = self.font(bold);
+        let mut lines = Vec::new();
+        let mut line = String::new();
+        for word in value.split_whitespace() {
+            let candidate = if line.is_empty() {
+                word.to_string()
+            } else {
+                format!("{} {}", line, word)
+            };
+            if font.width(&candidate, size) <= width {
+                line = candidate;
+                continue;
+            }
+            if !line.is_empty() {
+                lines.push(std::mem::take(&mut line));
+            }
+            // A word wider than the line on its own is broken between characters
+            for c in word.chars() {
+                line.push(c);
+                if font.width(&line, size) > width && line.chars().count() > 1 {
+                    line.pop();
+                    lines.push(std::mem::replace(&mut line, c.to_string()));
+                }
+            }
+        }
+        if !line.is_empty() || lines.is_empty() {
+            lines.push(line);
+        }
+        lines
+    }
+
+    /// Number the pages and serialize the document.
+    fn finish(self, number: &str) -> anyhow::Result<Vec<u8>> {
+        let count = self.pages.len();
+        for (index, layer) in self.pages.iter().enumerate() {
+            let footer = format!("Invoice no. {} · Page {} of {}", number, index + 1, count);
+            let width = self.regular.width(&footer, 8.0);
+            layer.use_text(
+                footer,
+                8.0,
+                Mm::from(Pt(PAGE_WIDTH - MARGIN - width)),
+                Mm::from(Pt(MARGIN)),
+                &self.regular.pdf,
+            );
+        }
+        // printpdf leaves embedded fonts uncompressed, which would make up most of every file
+        let mut pdf = lopdf::Document::load_mem(&self.doc.save_to_bytes()?)?;
+        pdf.compress();
+        let mut bytes = Vec::new();
+        pdf.save_to(&mut bytes)?;
+        Ok(bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn invoice(lines: usize, description: &str) -> InvoiceDocument {
+        InvoiceDocument {
+            number: "INV-2026-000042".to_string(),
+            issued_at: Utc::now(),
+            seller_name: "Zoë Ångström".to_string(),
+            seller_email: Some("zoe@example.com".to_string()),
+            buyer_name: "Дмитрий Łukasz".to_string(),
+            buyer_email: None,
+            lines: (0..lines)
+                .map(|_| InvoiceLine {
+                    description: description.to_string(),
+                    amount_cents: 1_999,
+                })
+                .collect(),
+            currency: "eur".to_string(),
+            note: Some("Paid in full.".to_string()),
+        }
+    }
+
+    fn page_count(pdf: &[u8]) -> usize {
+        lopdf::Document::load_mem(pdf).unwrap().get_pages().len()
+    }
+
+    #[test]
+    fn short_invoice_fits_one_page() {
+        let pdf = render_invoice(&invoice(3, "Café membership – année complète")).unwrap();
+        assert!(pdf.starts_with(b"%PDF"));
+        assert_eq!(page_count(&pdf), 1);
+    }
+
+    #[test]
+    fn long_invoice_continues_on_more_pages() {
+        let description = "A description long enough that it has to wrap over several lines of the table ".repeat(2);
+        let pdf = render_invoice(&invoice(60, &description)).unwrap();
+        assert!(page_count(&pdf) > 2);
+    }
+
+    #[test]
+    fn wrap_keeps_lines_within_width() {
+        let writer = Writer::new("test").unwrap();
+        let lines = writer.wrap(&"word ".repeat(50), 10.0, false, 200.0);
+        assert!(lines.len() > 1);
+        for line in &lines {
+            assert!(writer.regular.width(line, 10.0) <= 200.0);
+        }
+        let unbroken = writer.wrap(&"x".repeat(200), 10.0, false, 200.0);
+        assert!(unbroken.len() > 1);
+        assert_eq!(unbroken.concat(), "x".repeat(200));
+    }
+
+    #[test]
+    fn formats_amounts() {
+        assert_eq!(format_amount(1_234, "usd"), "12.34 USD");
+        assert_eq!(format_amount(-5, "eur"), "-0.05 EUR");
+    }
+}
@@ -0,0 +1,170 @@
+use chrono::{DateTime, Duration, Months, Utc};
+use serde_json::json;
+use tracing::info;
+
+use super::digests::section;
+use crate::{
+    database::Database,
+    email_service::{app_link, queue_email, EmailTemplate},
+    invoice_pdf::format_amount,
+    routes::analytics::{performance_summary, ReportFrequency},
+};
+
+/// Creators handled per run; the rest are due on the next one.
+const REPORT_BATCH: i64 = 200;
+/// Pieces of content listed in a report.
+const REPORT_TOP_CONTENT: usize = 5;
+
+/// Email every creator whose performance report is due a summary of the last week or month:
+/// earnings, audience changes and their most viewed content. Nothing is sent when nothing
+/// happened.
+pub async fn send_analytics_reports(db: &Database) -> anyhow::Result<()> {
+    // Due up to an hour early, matching the job interval, so the send time doesn't drift later
+    let due = sqlx::query_as::<_, (String, String, String, String, Option<DateTime<Utc>>)>(
+        r#"
+        SELECT u.id, u.email, COALESCE(u.display_name, u.name), s.frequency, s.last_sent_at
+        FROM analytics_report_settings s
+        JOIN users u ON u.id = s.user_id
+        WHERE u.email IS NOT NULL
+          AND s.frequency IN ('WEEKLY', 'MONTHLY')
+          AND (
+              s.last_sent_at IS NULL
+              OR s.last_sent_at <= NOW() + INTERVAL '1 hour'
+                 - CASE s.frequency WHEN 'MONTHLY' THEN INTERVAL '1 month' ELSE INTERVAL '7 days' END
+          )
+        ORDER BY s.last_sent_at NULLS FIRST
+        LIMIT $1
+        "#,
+    )
+    .bind(REPORT_BATCH)
+    .fetch_all(&db.pool)
+    .await?;
+
+    let mut sent = 0;
+    for (user_id, email, name, frequency, last_sent_at) in due {
+        let Some(frequency) = ReportFrequency::parse(&frequency) else {
+            continue;
+        };
+        // Claiming moves `last_sent_at` on only if no other instance did in the meantime
+        let claimed = sqlx::query(
+            r#"
+            UPDATE analytics_report_settings SET last_sent_at = NOW()
+            WHERE user_id = $1 AND last_sent_at IS NOT DISTINCT FROM $2
+            "#,
+        )
+        .bind(&user_id)
+        .bind(last_sent_at)
+        .execute(&db.pool)
+        .await?
+        .rows_affected();
+        if claimed == 0 {
+            continue;
+        }
+
+        if send_report(db, &user_id, &email, &name, frequency).await? {
+            sent += 1;
+        }
+    }
+
+    if sent > 0 {
+        info!("Queued {} analytics report emails", sent);
+    }
+    Ok(())
+}
+
+async fn send_report(
+    db: &Database,
+    user_id: &str,
+    email: &str,
+    name: &str,
+    frequency: ReportFrequency,
+) -> anyhow::Result<bool> {
+    // Whole days up to yesterday, so the numbers are final
+    let to = Utc::now().date_naive() - Duration::days(1);
+    let (from, period, cadence) = match frequency {
+        ReportFrequency::Monthly => (
+            to.checked_sub_months(Months::new(1)).unwrap_or(to) + Duration::days(1),
+            "the last month",
+            "monthly",
+        ),
+        _ => (to - Duration::days(6), "the last week", "weekly"),
+    };
+    let summary = performance_summary(db, user_id, from, to, REPORT_TOP_CONTENT)
+        .await
+        .map_err(|status| anyhow::anyhow!("Failed to build analytics report of {}: {}", user_id, status))?;
+    if summary.is_empty() {
+        return Ok(false);
+    }
+
+    let analytics_link = app_link("/creator-dashboard/analytics");
+    let earnings_items: Vec<(String, String)> = summary
+        .earnings
+        .iter()
+        .map(|(currency, totals)| {
+            (
+                format!(
+                    "{} earned from {} payments",
+                    format_amount(totals.net_after_refunds_cents, currency),
+                    totals.transactions
+                ),
+                analytics_link.clone(),
+            )
+        })
+        .collect();
+    let audience_items = vec![
+        (format!("{} new subscribers", summary.new_subscribers), analytics_link.clone()),
+        (format!("{} subscriptions ended", summary.ended_subscribers), analytics_link.clone()),
+        (format!("{} new followers", summary.new_followers), analytics_link.clone()),
+    ];
+    let content_items: Vec<(String, String)> = summary
+        .top_content
+        .iter()
+        .map(|row| {
+            let link = match row.content_type.as_str() {
+                "post" => app_link(&format!("/posts/{}", row.id)),
+                _ => analytics_link.clone(),
+            };
+            (format!("{} ({} views)", row.title, row.views), link)
+        })
+        .collect();
+
+    let (earnings_text, earnings_html) =
+        section("Earnings", &earnings_items, earnings_items.len() as i64);
+    let (audience_text, audience_html) =
+        section("Audience", &audience_items, audience_items.len() as i64);
+    let (content_text, content_html) =
+        section("Most viewed content", &content_items, content_items.len() as i64);
+
+    let subscriber_delta = summary.new_subscribers - summary.ended_subscribers;
+    let headline = match summary.earnings.iter().next() {
+        Some((currency, totals)) if summary.earnings.len() == 1 => {
+            format!("{} earned", format_amount(totals.net_after_refunds_cents, currency))
+        }
+        _ => format!("{:+} subscribers", subscriber_delta),
+    };
+
+    queue_email(
+        db,
+        email,
+        Some(user_id),
+        EmailTemplate::AnalyticsReport,
+        json!({
+            "name": name,
+            "summary": headline,
+            "period": period,
+            "cadence": cadence,
+            "from": from.format("%B %-d").to_string(),
+            "to": to.format("%B %-d, %Y").to_string(),
+            "earnings_text": earnings_text,
+            "earnings_html": earnings_html,
+            "audience_text": audience_text,
+            "audience_html": audience_html,
+            "content_text": content_text,
+            "content_html": content_html,
+            "link": analytics_link,
+            "settings_link": app_link("/creator-dashboard/settings"),
+        }),
+    )
+    .await;
+    Ok(true)
+}
@@ -0,0 +1,116 @@
+use serde_json::json;
+use tracing::info;
+use uuid::Uuid;
+
+use crate::{
+    database::Database,
+    email_service::{app_link, queue_email, EmailTemplate},
+    notification_service::{notify, NotificationKind},
+};
+
+/// Deliver one batch of an announcement: an in-app notification to every recipient in it, and
+/// an email too when the creator asked for one. Recipients are claimed before anything is sent,
+/// so a batch job that arrives twice delivers once.
+pub async fn deliver_batch(db: &Database, announcement_id: &str, batch: i32) -> anyhow::Result<()> {
+    let announcement_id = Uuid::parse_str(announcement_id)?;
+    let announcement = sqlx::query_as::<_, (String, String, bool, String, Option<String>)>(
+        r#"
+        SELECT a.title, a.body, a.send_email,
+               COALESCE(u.display_name, u.name), u.username
+        FROM announcements a
+        JOIN users u ON u.id = a.creator_id
+        WHERE a.id = $1
+        "#,
+    )
+    .bind(announcement_id)
+    .fetch_optional(&db.pool)
+    .await?;
+    let Some((title, body, send_email, creator_name, username)) = announcement else {
+        return Ok(());
+    };
+
+    let recipients = sqlx::query_as::<_, (String, Option<String>)>(
+        r#"
+        UPDATE announcement_recipients r
+        SET delivered_at = NOW()
+        FROM users u
+        WHERE r.announcement_id = $1 AND r.batch = $2 AND r.delivered_at IS NULL
+          AND u.id = r.user_id
+        RETURNING r.user_id, u.email
+        "#,
+    )
+    .bind(announcement_id)
+    .bind(batch)
+    .fetch_all(&db.pool)
+    .await?;
+
+    let link = match &username {
+        Some(username) => format!("/creators/{}", username),
+        None => "/feed".to_string(),
+    };
+    for (user_id, email) in &recipients {
+        notify(
+            db,
+            user_id,
+            NotificationKind::Announcement,
+            json!({
+                "message": format!("{}: {}", creator_name, title),
+                "link": link,
+                "announcementId": announcement_id,
+                "title": title,
+                "body": body,
+            }),
+        )
+        .await;
+
+        let Some(email) = email.as_deref().filter(|_| send_email) else {
+            continue;
+        };
+        let delivery_id = queue_email(
+            db,
+            email,
+            Some(user_id),
+            EmailTemplate::Announcement,
+            json!({
+                "creator_name": creator_name,
+                "title": title,
+                "body": body,
+                "link": app_link(&link),
+            }),
+        )
+        .await;
+        if let Some(delivery_id) = delivery_id {
+            sqlx::query(
+                r#"
+                UPDATE announcement_recipients SET email_delivery_id = $3
+                WHERE announcement_id = $1 AND user_id = $2
+                "#,
+            )
+            .bind(announcement_id)
+            .bind(user_id)
+            .bind(delivery_id)
+            .execute(&db.pool)
+            .await?;
+        }
+    }
+
+    // The last batch to finish completes the announcement
+    let completed = sqlx::query(
+        r#"
+        UPDATE announcements SET status = 'SENT', completed_at = NOW()
+        WHERE id = $1 AND status = 'SENDING'
+          AND NOT EXISTS (
+              SELECT 1 FROM announcement_recipients
+              WHERE announcement_id = $1 AND delivered_at IS NULL
+          )
+        "#,
+    )
+    .bind(announcement_id)
+    .execute(&db.pool)
+    .await?
+    .rows_affected();
+    if completed > 0 {
+        info!("Announcement {} delivered", announcement_id);
+    }
+    Ok(())
+}
@@ -0,0 +1,529 @@
+use std::{collections::HashMap, io::Read, time::Duration};
+
+use anyhow::{anyhow, Context};
+use chrono::{DateTime, Utc};
+use quick_xml::{escape::resolve_xml_entity, events::Event, Reader};
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+use crate::{database::Database, remote_fetch, routes::taxonomy::slugify, storage};
+
+/// Largest feed document or remote image fetched during an import.
+const MAX_DOWNLOAD_BYTES: usize = 10 * 1024 * 1024;
+const DOWNLOAD_TIMEOUT: Duration = Duration::from_secs(30);
+/// Rows between progress updates written to `article_imports`.
+const PROGRESS_BATCH: i32 = 10;
+
+/// An article parsed from a feed or Medium export, mapped onto our article columns.
+#[derive(Debug, Default)]
+struct ImportedArticle {
+    external_id: Option<String>,
+    title: String,
+    content: String,
+    published_at: Option<DateTime<Utc>>,
+}
+
+/// Process a queued article import: fetch or unpack the source and create the articles.
+pub async fn run_import(db: &Database, import_id: &str) -> anyhow::Result<()> {
+    let import_id = Uuid::parse_str(import_id).context("invalid import id")?;
+
+    // Claiming the row only from QUEUED keeps redelivered messages from importing twice.
+    let import = sqlx::query_as::<_, (String, String, Option<String>, Option<String>)>(
+        r#"
+        UPDATE article_imports
+        SET status = 'PROCESSING', updated_at = NOW()
+        WHERE id = $1 AND status = 'QUEUED'
+        RETURNING user_id, source, feed_url, storage_key
+        "#,
+    )
+    .bind(import_id)
+    .fetch_optional(&db.pool)
+    .await?;
+
+    let (user_id, source, feed_url, storage_key) = match import {
+        Some(import) => import,
+        None => {
+            info!("Article import {} is not queued; skipping", import_id);
+            return Ok(());
+        }
+    };
+
+    let result = import_articles(db, import_id, &user_id, &source, feed_url, storage_key.as_deref()).await;
+
+    match &result {
+        Ok(()) => {
+            sqlx::query(
+                r#"
+                UPDATE article_imports
+                SET status = 'COMPLETED', updated_at = NOW(), completed_at = NOW()
+                WHERE id = $1
+                "#,
+            )
+            .bind(import_id)
+            .execute(&db.pool)
+            .await?;
+        }
+        Err(e) => {
+            error!("Article import {} failed: {:?}", import_id, e);
+            sqlx::query(
+                r#"
+                UPDATE article_imports
+                SET status = 'FAILED', error = $2, updated_at = NOW(), completed_at = NOW()
+                WHERE id = $1
+                "#,
+            )
+            .bind(import_id)
+            .bind(e.to_string())
+            .execute(&db.pool)
+            .await?;
+        }
+    }
+
+    if let Some(storage_key) = storage_key {
+        if let Err(e) = storage::delete_private_file(&storage_key).await {
+            error!("Failed to remove import file {}: {:?}", storage_key, e);
+        }
+    }
+
+    result
+}
+
+async fn import_articles(
+    db: &Database,
+    import_id: Uuid,
+    user_id: &str,
+    source: &str,
+    feed_url: Option<String>,
+    storage_key: Option<&str>,
+) -> anyhow::Result<()> {
+    let articles = match (source, feed_url, storage_key) {
+        ("rss", Some(feed_url), _) => {
+            let feed = download(&feed_url).await?;
+            tokio::task::spawn_blocking(move || parse_feed(&feed)).await??
+        }
+        ("medium", _, Some(storage_key)) => {
+            let archive = storage::read_private_file(storage_key).await?;
+            tokio::task::spawn_blocking(move || parse_medium_export(&archive)).await??
+        }
+        _ => return Err(anyhow!("Import has no source to read")),
+    };
+
+    sqlx::query("UPDATE article_imports SET total_items = $2, updated_at = NOW() WHERE id = $1")
+        .bind(import_id)
+        .bind(articles.len() as i32)
+        .execute(&db.pool)
+        .await?;
+
+    let mut processed = 0;
+    let mut imported = 0;
+    let mut skipped = 0;
+
+    for mut article in articles {
+        if already_imported(db, user_id, article.external_id.as_deref()).await? {
+            skipped += 1;
+        } else {
+            article.content = rehost_images(&article.content).await;
+            insert_article(db, user_id, &article).await?;
+            imported += 1;
+        }
+        processed += 1;
+
+        if processed % PROGRESS_BATCH == 0 {
+            update_progress(db, import_id, processed, imported, skipped).await?;
+        }
+    }
+
+    update_progress(db, import_id, processed, imported, skipped).await?;
+    info!(
+        "Article import {} finished: {} imported, {} skipped",
+        import_id, imported, skipped
+    );
+    Ok(())
+}
+
+async fn already_imported(
+    db: &Database,
+    user_id: &str,
+    external_id: Option<&str>,
+) -> anyhow::Result<bool> {
+    let external_id = match external_id {
+        Some(external_id) => external_id,
+        None => return Ok(false),
+    };
+
+    Ok(sqlx::query_scalar::<_, bool>(
+        "SELECT EXISTS(SELECT 1 FROM articles WHERE author_id = $1 AND external_id = $2)",
+    )
+    .bind(user_id)
+    .bind(external_id)
+    .fetch_one(&db.pool)
+    .await?)
+}
+
+async fn insert_article(db: &Database, user_id: &str, article: &ImportedArticle) -> anyhow::Result<()> {
+    let base_slug = match slugify(&article.title) {
+        slug if slug.is_empty() => "imported-article".to_string(),
+        slug => slug,
+    };
+
+    // Retry with a suffix when the slug is taken by another article.
+    for attempt in 0..5 {
+        let slug = if attempt == 0 {
+            base_slug.clone()
+        } else {
+            format!("{}-{}", base_slug, &Uuid::new_v4().simple().to_string()[..6])
+        };
+
+        let result = sqlx::query(
+            r#"
+            INSERT INTO articles (title, content, slug, author_id, published_at, external_id, created_at, updated_at)
+            VALUES ($1, $2, $3, $4, COALESCE($5, NOW()), $6, COALESCE($5, NOW()), NOW())
+            "#,
+        )
+        .bind(article.title.chars().take(255).collect::<String>())
+        .bind(&article.content)
+        .bind(&slug)
+        .bind(user_id)
+        .bind(article.published_at)
+        .bind(&article.external_id)
+        .execute(&db.pool)
+        .await;
+
+        match result {
+            Ok(_) => return Ok(()),
+            Err(sqlx::Error::Database(db_err))
+                if db_err.constraint() == Some("articles_slug_key") =>
+            {
+                continue
+            }
+            Err(e) => return Err(e.into()),
+        }
+    }
+
+    Err(anyhow!("Could not find a free slug for '{}'", article.title))
+}
+
+async fn update_progress(
+    db: &Database,
+    import_id: Uuid,
+    processed: i32,
+    imported: i32,
+    skipped: i32,
+) -> anyhow::Result<()> {
+    sqlx::query(
+        r#"
+        UPDATE article_imports
+        SET processed_items = $2, imported_articles = $3, skipped_items = $4, updated_at = NOW()
+        WHERE id = $1
+        "#,
+    )
+    .bind(import_id)
+    .bind(processed)
+    .bind(imported)
+    .bind(skipped)
+    .execute(&db.pool)
+    .await?;
+    Ok(())
+}
+
+async fn download(url: &str) -> anyhow::Result<Vec<u8>> {
+    let response = remote_fetch::get(url, DOWNLOAD_TIMEOUT).await?;
+    remote_fetch::read_body(response, url, MAX_DOWNLOAD_BYTES).await
+}
+
+/// Copy every remote image referenced by the article into our storage and point the
+/// article at the copies. Images that fail to download keep their original URL.
+async fn rehost_images(content: &str) -> String {
+    let mut replacements: HashMap<String, String> = HashMap::new();
+
+    for url in image_sources(content) {
+        if replacements.contains_key(&url) {
+            continue;
+        }
+
+        match rehost_image(&url).await {
+            Ok(stored_url) => {
+                replacements.insert(url, stored_url);
+            }
+            Err(e) => warn!("Keeping remote image {}: {:?}", url, e),
+        }
+    }
+
+    replacements
+        .iter()
+        .fold(content.to_string(), |content, (from, to)| content.replace(from.as_str(), to))
+}
+
+async fn rehost_image(url: &str) -> anyhow::Result<String> {
+    let response = remote_fetch::get(url, DOWNLOAD_TIMEOUT).await?;
+    let content_type = response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or("")
+        .split(';')
+        .next()
+        .unwrap_or("")
+        .trim()
+        .to_string();
+
+    if !content_type.starts_with("image/") {
+        return Err(anyhow!("not an image ({})", content_type));
+    }
+
+    let bytes = remote_fetch::read_body(response, url, MAX_DOWNLOAD_BYTES).await?;
+
+    let extension = match content_type.as_str() {
+        "image/jpeg" => "jpg",
+        "image/png" => "png",
+        "image/gif" => "gif",
+        "image/webp" => "webp",
+        "image/svg+xml" => "svg",
+        _ => "bin",
+    };
+
+    storage::store_public_file("images", extension, &content_type, bytes).await
+}
+
+/// `src` attributes of `<img>` tags pointing at remote images.
+fn image_sources(content: &str) -> Vec<String> {
+    let mut sources = Vec::new();
+    let lower = content.to_ascii_lowercase();
+    let mut offset = 0;
+
+    while let Some(start) = lower[offset..].find("<img") {
+        let tag_start = offset + start;
+        let tag_end = lower[tag_start..]
+            .find('>')
+            .map(|end| tag_start + end)
+            .unwrap_or(lower.len());
+
+        if let Some(src) = attribute_value(&content[tag_start..tag_end], "src") {
+            if src.starts_with("http://") || src.starts_with("https://") {
+                sources.push(src);
+            }
+        }
+        offset = tag_end;
+    }
+
+    sources
+}
+
+/// Value of a quoted attribute inside a single HTML tag.
+fn attribute_value(tag: &str, name: &str) -> Option<String> {
+    let lower = tag.to_ascii_lowercase();
+    let mut search_from = 0;
+
+    while let Some(found) = lower[search_from..].find(&format!("{}=", name)) {
+        let index = search_from + found;
+        search_from = index + name.len() + 1;
+
+        // Skip matches inside longer attribute names such as `data-src=`.
+        let preceded_by_space = index == 0
+            || lower[..index]
+                .chars()
+                .next_back()
+                .map(char::is_whitespace)
+                .unwrap_or(false);
+        if !preceded_by_space {
+            continue;
+        }
+
+        let rest = &tag[search_from..];
+        let quote = rest.chars().next().filter(|c| *c == '"' || *c == '\'')?;
+        let value = &rest[1..];
+        return value.find(quote).map(|end| decode_entities(&value[..end]));
+    }
+
+    None
+}
+
+fn decode_entities(value: &str) -> String {
+    value
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&amp;", "&")
+}
+
+/// Parse an RSS 2.0 or Atom feed into articles.
+fn parse_feed(bytes: &[u8]) -> anyhow::Result<Vec<ImportedArticle>> {
+    let mut reader = Reader::from_reader(bytes);
+    reader.config_mut().trim_text(true);
+
+    let mut articles = Vec::new();
+    let mut current: Option<HashMap<String, String>> = None;
+    let mut field: Option<String> = None;
+    let mut buf = Vec::new();
+
+    loop {
+        match reader.read_event_into(&mut buf)? {
+            Event::Start(tag) => {
+                let name = String::from_utf8_lossy(tag.name().as_ref()).to_string();
+                if name == "item" || name == "entry" {
+                    current = Some(HashMap::new());
+                } else if let Some(fields) = current.as_mut() {
+                    // Atom links carry their target in an attribute.
+                    if name == "link" {
+                        if let Some(href) = link_href(&tag) {
+                            fields.entry("link".to_string()).or_insert(href);
+                        }
+                    }
+                    field = Some(name);
+                }
+            }
+            Event::Empty(tag) => {
+                if let Some(fields) = current.as_mut() {
+                    if tag.name().as_ref() == b"link" {
+                        if let Some(href) = link_href(&tag) {
+                            fields.entry("link".to_string()).or_insert(href);
+                        }
+                    }
+                }
+            }
+            Event::Text(text) => append_field(&mut current, &field, &text.decode()?),
+            Event::CData(text) => append_field(&mut current, &field, &text.decode()?),
+            Event::GeneralRef(reference) => {
+                let resolved = match reference.resolve_char_ref()? {
+                    Some(c) => c.to_string(),
+                    None => resolve_xml_entity(&reference.decode()?)
+                        .unwrap_or_default()
+                        .to_string(),
+                };
+                append_field(&mut current, &field, &resolved);
+            }
+            Event::End(tag) => {
+                let name = tag.name();
+                if name.as_ref() == b"item" || name.as_ref() == b"entry" {
+                    if let Some(fields) = current.take() {
+                        if let Some(article) = feed_article(fields) {
+                            articles.push(article);
+                        }
+                    }
+                }
+                field = None;
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+        buf.clear();
+    }
+
+    if articles.is_empty() {
+        return Err(anyhow!("The feed contains no items"));
+    }
+
+    Ok(articles)
+}
+
+fn link_href(tag: &quick_xml::events::BytesStart) -> Option<String> {
+    tag.attributes()
+        .flatten()
+        .find(|attribute| attribute.key.as_ref() == b"href")
+        .and_then(|attribute| String::from_utf8(attribute.value.to_vec()).ok())
+}
+
+fn append_field(current: &mut Option<HashMap<String, String>>, field: &Option<String>, text: &str) {
+    if let (Some(fields), Some(field)) = (current.as_mut(), field.as_ref()) {
+        fields.entry(field.clone()).or_default().push_str(text);
+    }
+}
+
+fn feed_article(mut fields: HashMap<String, String>) -> Option<ImportedArticle> {
+    let mut take = |names: &[&str]| {
+        names
+            .iter()
+            .find_map(|name| fields.remove(*name).filter(|value| !value.trim().is_empty()))
+    };
+
+    let title = take(&["title"])?.trim().to_string();
+    let content = take(&["content:encoded", "content", "description", "summary"]).unwrap_or_default();
+    let external_id = take(&["guid", "id", "link"]).map(|id| id.trim().to_string());
+    let published_at = take(&["pubDate", "published", "updated", "dc:date"]).and_then(|date| {
+        DateTime::parse_from_rfc2822(date.trim())
+            .or_else(|_| DateTime::parse_from_rfc3339(date.trim()))
+            .ok()
+            .map(|date| date.with_timezone(&Utc))
+    });
+
+    Some(ImportedArticle {
+        external_id,
+        title,
+        content,
+        published_at,
+    })
+}
+
+/// Parse the `posts/*.html` files of a Medium export archive. Drafts are skipped.
+fn parse_medium_export(bytes: &[u8]) -> anyhow::Result<Vec<ImportedArticle>> {
+    let mut archive = zip::ZipArchive::new(std::io::Cursor::new(bytes))?;
+    let names: Vec<String> = archive
+        .file_names()
+        .filter(|name| name.starts_with("posts/") && name.ends_with(".html"))
+        .filter(|name| !name.trim_start_matches("posts/").starts_with("draft_"))
+        .map(|name| name.to_string())
+        .collect();
+
+    let mut articles = Vec::new();
+    for name in names {
+        let mut html = String::new();
+        archive.by_name(&name)?.read_to_string(&mut html)?;
+        if let Some(article) = medium_article(&html) {
+            articles.push(article);
+        }
+    }
+
+    if articles.is_empty() {
+        return Err(anyhow!("No published Medium posts found in the archive"));
+    }
+
+    Ok(articles)
+}
+
+fn medium_article(html: &str) -> Option<ImportedArticle> {
+    let title = element_text(html, "<title>", "</title>")
+        .map(|title| decode_entities(title.trim()))
+        .filter(|title| !title.is_empty())?;
+
+    let content = html
+        .find(r#"data-field="body""#)
+        .and_then(|start| html[start..].find('>').map(|end| start + end + 1))
+        .and_then(|start| {
+            html[start..]
+                .rfind("</section>")
+                .map(|end| html[start..start + end].trim().to_string())
+        })
+        .unwrap_or_default();
+
+    let published_at = html
+        .find(r#"class="dt-published""#)
+        .and_then(|start| html[..start].rfind("<time").map(|tag| (tag, start)))
+        .and_then(|(tag, start)| {
+            let end = html[start..].find('>')? + start;
+            attribute_value(&html[tag..end], "datetime")
+        })
+        .and_then(|date| DateTime::parse_from_rfc3339(&date).ok())
+        .map(|date| date.with_timezone(&Utc));
+
+    let external_id = html
+        .find(r#"class="p-canonical""#)
+        .and_then(|start| html[..start].rfind("<a").map(|tag| (tag, start)))
+        .and_then(|(tag, start)| {
+            let end = html[start..].find('>')? + start;
+            attribute_value(&html[tag..end], "href")
+        });
+
+    Some(ImportedArticle {
+        external_id,
+        title,
+        content,
+        published_at,
+    })
+}
+
+fn element_text<'a>(html: &'a str, open: &str, close: &str) -> Option<&'a str> {
+    let start = html.find(open)? + open.len();
+    let end = html[start..].find(close)? + start;
+    Some(&html[start..end])
+}
@@ -0,0 +1,201 @@
+use std::{
+    env,
+    path::{Component, Path, PathBuf},
+    process::Stdio,
+};
+
+use anyhow::{anyhow, Context};
+use tokio::{fs, io::AsyncWriteExt, process::Command};
+use tracing::{error, info};
+use uuid::Uuid;
+
+use crate::database::Database;
+
+/// Number of peaks stored per track; enough for a full-width scrubber.
+const WAVEFORM_PEAKS: usize = 200;
+/// Sample rate used when decoding audio for peak extraction.
+const WAVEFORM_SAMPLE_RATE: u32 = 8000;
+/// Largest remote file downloaded for processing, the same as the audio upload limit.
+const MAX_DOWNLOAD_BYTES: u64 = 500 * 1024 * 1024;
+
+/// Extract duration and waveform peaks for a post's audio and store them on the post.
+pub async fn process_audio_post(db: &Database, post_id: &str, audio_url: &str) -> anyhow::Result<()> {
+    let post_id = Uuid::parse_str(post_id).context("invalid post id")?;
+
+    match analyze_audio(audio_url).await {
+        Ok((duration, peaks)) => {
+            // Skip the write when the post's audio changed while the job was queued.
+            sqlx::query(
+                r#"
+                UPDATE posts
+                SET audio_duration_seconds = $2,
+                    audio_waveform = $3,
+                    audio_processing_status = 'READY'
+                WHERE id = $1 AND audio_url = $4
+                "#,
+            )
+            .bind(post_id)
+            .bind(duration)
+            .bind(serde_json::json!(peaks))
+            .bind(audio_url)
+            .execute(&db.pool)
+            .await?;
+
+            info!("Generated waveform for post {} ({:.1}s)", post_id, duration);
+            Ok(())
+        }
+        Err(e) => {
+            error!("Audio processing failed for post {}: {:?}", post_id, e);
+            sqlx::query(
+                "UPDATE posts SET audio_processing_status = 'FAILED' WHERE id = $1 AND audio_url = $2",
+            )
+            .bind(post_id)
+            .bind(audio_url)
+            .execute(&db.pool)
+            .await?;
+            Err(e)
+        }
+    }
+}
+
+async fn analyze_audio(audio_url: &str) -> anyhow::Result<(f64, Vec<f64>)> {
+    let (path, is_temporary) = resolve_source(audio_url).await?;
+    let result = async {
+        let duration = probe_duration(&path).await?;
+        let samples = decode_samples(&path).await?;
+        Ok((duration, compute_peaks(&samples, WAVEFORM_PEAKS)))
+    }
+    .await;
+
+    if is_temporary {
+        let _ = fs::remove_file(&path).await;
+    }
+
+    result
+}
+
+/// Local uploads are read in place; remote files are downloaded to a temporary file. The URL
+/// comes from users, so upload paths may not leave the uploads directory and downloads are capped.
+pub(super) async fn resolve_source(audio_url: &str) -> anyhow::Result<(PathBuf, bool)> {
+    if let Some(relative) = audio_url.strip_prefix("/uploads/") {
+        let relative = Path::new(relative);
+        if relative
+            .components()
+            .any(|component| !matches!(component, Component::Normal(_)))
+        {
+            return Err(anyhow!("Invalid upload path: {}", audio_url));
+        }
+        let upload_root = env::var("UPLOAD_DIR").unwrap_or_else(|_| "uploads".to_string());
+        return Ok((PathBuf::from(upload_root).join(relative), false));
+    }
+
+    if !audio_url.starts_with("http://") && !audio_url.starts_with("https://") {
+        return Err(anyhow!("Unsupported audio location: {}", audio_url));
+    }
+
+    let mut response = reqwest::get(audio_url).await?.error_for_status()?;
+    if response.content_length().is_some_and(|length| length > MAX_DOWNLOAD_BYTES) {
+        return Err(anyhow!("{} is too large to process", audio_url));
+    }
+    let path = env::temp_dir().join(format!("funify-audio-{}", Uuid::new_v4()));
+    let mut file = fs::File::create(&path).await?;
+    let copied = async {
+        let mut written = 0u64;
+        while let Some(chunk) = response.chunk().await? {
+            written += chunk.len() as u64;
+            if written > MAX_DOWNLOAD_BYTES {
+                return Err(anyhow!("{} is too large to process", audio_url));
+            }
+            file.write_all(&chunk).await?;
+        }
+        file.flush().await?;
+        Ok(())
+    }
+    .await;
+    if let Err(e) = copied {
+        let _ = fs::remove_file(&path).await;
+        return Err(e);
+    }
+    Ok((path, true))
+}
+
+async fn probe_duration(path: &PathBuf) -> anyhow::Result<f64> {
+    let output = Command::new("ffprobe")
+        .args([
+            "-v",
+            "error",
+            "-show_entries",
+            "format=duration",
+            "-of",
+            "default=noprint_wrappers=1:nokey=1",
+        ])
+        .arg(path)
+        .output()
+        .await
+        .context("failed to run ffprobe")?;
+
+    if !output.status.success() {
+        return Err(anyhow!(
+            "ffprobe failed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        ));
+    }
+
+    String::from_utf8_lossy(&output.stdout)
+        .trim()
+        .parse::<f64>()
+        .context("ffprobe returned an invalid duration")
+}
+
+/// Decode to mono signed 16-bit PCM at a low sample rate.
+async fn decode_samples(path: &PathBuf) -> anyhow::Result<Vec<i16>> {
+    let output = Command::new("ffmpeg")
+        .args(["-v", "error", "-i"])
+        .arg(path)
+        .args([
+            "-ac",
+            "1",
+            "-ar",
+            &WAVEFORM_SAMPLE_RATE.to_string(),
+            "-f",
+            "s16le",
+            "-",
+        ])
+        .stdin(Stdio::null())
+        .output()
+        .await
+        .context("failed to run ffmpeg")?;
+
+    if !output.status.success() {
+        return Err(anyhow!(
+            "ffmpeg failed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        ));
+    }
+
+    Ok(output
+        .stdout
+        .chunks_exact(2)
+        .map(|bytes| i16::from_le_bytes([bytes[0], bytes[1]]))
+        .collect())
+}
+
+/// Split samples into `buckets` windows and keep each window's peak amplitude in 0..=1.
+fn compute_peaks(samples: &[i16], buckets: usize) -> Vec<f64> {
+    if samples.is_empty() || buckets == 0 {
+        return Vec::new();
+    }
+
+    let window = samples.len().div_ceil(buckets);
+    samples
+        .chunks(window)
+        .map(|chunk| {
+            let peak = chunk
+                .iter()
+                .map(|sample| (*sample as i32).unsigned_abs())
+                .max()
+                .unwrap_or(0);
+            ((peak as f64 / i16::MAX as f64).min(1.0) * 1000.0).round() / 1000.0
+        })
+        .collect()
+}
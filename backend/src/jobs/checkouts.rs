@@ -0,0 +1,161 @@
+use chrono::{DateTime, Utc};
+use serde_json::json;
+use tracing::info;
+use uuid::Uuid;
+
+use crate::{
+    config::{checkout_expiry_hours, checkout_reminder_minutes},
+    database::Database,
+    email_service::{queue_email, EmailTemplate},
+    routes::{
+        discounts::release_discount,
+        stripe::{stripe_json, stripe_secret, stripe_url},
+    },
+};
+
+/// Most abandoned sessions expired per run.
+const EXPIRY_BATCH: i64 = 100;
+
+/// Expire checkout sessions left unpaid for longer than `CHECKOUT_EXPIRY_HOURS`. Sessions still
+/// open at Stripe are expired there first, so they can no longer be paid; the pending purchases
+/// then give back the discount uses and stock they held, and carts open up again.
+pub async fn expire_stale_checkouts(db: &Database) -> anyhow::Result<()> {
+    let stale = sqlx::query_as::<_, (String, bool)>(
+        r#"
+        SELECT stripe_checkout_session_id,
+               COALESCE(BOOL_OR(COALESCE(checkout_expires_at, created_at + INTERVAL '24 hours') <= NOW()), FALSE)
+        FROM purchases
+        WHERE status = 'PENDING' AND stripe_checkout_session_id IS NOT NULL
+        GROUP BY stripe_checkout_session_id
+        HAVING MIN(created_at) <= NOW() - make_interval(hours => $1)
+            OR BOOL_OR(checkout_expires_at <= NOW())
+        LIMIT $2
+        "#,
+    )
+    .bind(checkout_expiry_hours())
+    .bind(EXPIRY_BATCH)
+    .fetch_all(&db.pool)
+    .await?;
+
+    for (session_id, lapsed) in stale {
+        // A session Stripe could not expire may have just been paid; the next run looks again
+        if !lapsed && expire_session(&session_id).await.is_err() {
+            continue;
+        }
+        let expired = expire_purchases(db, &session_id).await?;
+        info!("Expired abandoned checkout session {} ({} purchases)", session_id, expired);
+    }
+    Ok(())
+}
+
+async fn expire_session(session_id: &str) -> Result<(), axum::http::StatusCode> {
+    stripe_json(
+        reqwest::Client::new()
+            .post(stripe_url(&format!("/v1/checkout/sessions/{}/expire", session_id)))
+            .bearer_auth(stripe_secret()?)
+            .send()
+            .await,
+        "expire abandoned checkout session",
+    )
+    .await
+    .map(|_| ())
+}
+
+async fn expire_purchases(db: &Database, session_id: &str) -> anyhow::Result<usize> {
+    let mut tx = db.pool.begin().await?;
+    let expired = sqlx::query_as::<_, (Uuid, Option<Uuid>)>(
+        r#"
+        UPDATE purchases SET status = 'EXPIRED'
+        WHERE stripe_checkout_session_id = $1 AND status = 'PENDING'
+        RETURNING id, discount_code_id
+        "#,
+    )
+    .bind(session_id)
+    .fetch_all(&mut tx)
+    .await?;
+    let purchase_ids: Vec<Uuid> = expired.iter().map(|(id, _)| *id).collect();
+
+    for statement in [
+        "DELETE FROM ledger_entries WHERE stripe_checkout_session_id = $1 AND status = 'PENDING'",
+        "DELETE FROM tax_lines WHERE stripe_checkout_session_id = $1 AND status = 'PENDING'",
+        r#"
+        UPDATE carts SET status = 'OPEN', stripe_checkout_session_id = NULL, updated_at = NOW()
+        WHERE stripe_checkout_session_id = $1 AND status = 'CHECKED_OUT'
+        "#,
+    ] {
+        sqlx::query(statement).bind(session_id).execute(&mut tx).await?;
+    }
+    sqlx::query("DELETE FROM stock_holds WHERE purchase_id = ANY($1)")
+        .bind(&purchase_ids)
+        .execute(&mut tx)
+        .await?;
+    tx.commit().await?;
+
+    for discount_id in expired.iter().filter_map(|(_, discount_id)| *discount_id) {
+        release_discount(db, discount_id).await;
+    }
+    Ok(expired.len())
+}
+
+/// Email buyers who started checkout `CHECKOUT_REMINDER_MINUTES` ago and have not paid a link
+/// back to their session, once per session. Sessions about to lapse and products the buyer has
+/// since bought are skipped.
+pub async fn send_checkout_reminders(db: &Database) -> anyhow::Result<()> {
+    let Some(delay_minutes) = checkout_reminder_minutes() else {
+        return Ok(());
+    };
+
+    let due = sqlx::query_as::<_, (String, String, String, String, i64, DateTime<Utc>)>(
+        r#"
+        WITH due AS (
+            UPDATE purchases pu SET checkout_reminder_sent_at = NOW()
+            WHERE pu.status = 'PENDING'
+              AND pu.checkout_url IS NOT NULL
+              AND pu.checkout_reminder_sent_at IS NULL
+              AND pu.created_at <= NOW() - make_interval(mins => $1)
+              AND COALESCE(pu.checkout_expires_at, pu.created_at + INTERVAL '24 hours') > NOW() + INTERVAL '15 minutes'
+              AND NOT EXISTS (
+                  SELECT 1 FROM purchases bought
+                  WHERE bought.user_id = pu.user_id
+                    AND bought.product_id = pu.product_id
+                    AND bought.status = 'COMPLETED'
+              )
+            RETURNING pu.stripe_checkout_session_id, pu.user_id, pu.product_id, pu.checkout_url,
+                      COALESCE(pu.checkout_expires_at, pu.created_at + INTERVAL '24 hours') AS expires_at
+        )
+        SELECT d.user_id, u.email, MIN(d.checkout_url), MIN(p.name), COUNT(*), MIN(d.expires_at)
+        FROM due d
+        JOIN users u ON u.id = d.user_id
+        JOIN products p ON p.id = d.product_id
+        WHERE u.email IS NOT NULL
+        GROUP BY d.stripe_checkout_session_id, d.user_id, u.email
+        "#,
+    )
+    .bind(delay_minutes)
+    .fetch_all(&db.pool)
+    .await?;
+
+    if !due.is_empty() {
+        info!("Sending {} abandoned checkout reminders", due.len());
+    }
+    for (user_id, email, checkout_url, name, items, expires_at) in due {
+        let item_name = match items {
+            1 => name,
+            2 => format!("{} and 1 more item", name),
+            _ => format!("{} and {} more items", name, items - 1),
+        };
+        queue_email(
+            db,
+            &email,
+            Some(&user_id),
+            EmailTemplate::CheckoutReminder,
+            json!({
+                "item_name": item_name,
+                "expires_at": expires_at.format("%B %-d at %H:%M UTC").to_string(),
+                "link": checkout_url,
+            }),
+        )
+        .await;
+    }
+    Ok(())
+}
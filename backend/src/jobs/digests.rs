@@ -0,0 +1,215 @@
+use chrono::{DateTime, Duration, Utc};
+use serde_json::json;
+use tracing::info;
+use uuid::Uuid;
+
+use crate::{
+    database::Database,
+    email_service::{api_link, app_link, escape_html, queue_email, EmailTemplate},
+    notification_service::{digest_unsubscribe_token, DigestFrequency},
+};
+
+/// Users handled per run; the rest are due on the next one.
+const DIGEST_BATCH: i64 = 500;
+/// Items listed per section; the email says how many more there are.
+const DIGEST_ITEM_LIMIT: i64 = 10;
+
+/// Email every user whose digest is due what they missed since the last one: unread
+/// notifications and new posts from creators they follow. Nothing is sent when nothing happened.
+pub async fn send_digests(db: &Database) -> anyhow::Result<()> {
+    // Due up to an hour early, matching the job interval, so the send time doesn't drift later
+    // by up to an hour every period
+    let due = sqlx::query_as::<_, (String, String, String, String, Option<DateTime<Utc>>)>(
+        r#"
+        SELECT u.id, u.email, COALESCE(u.display_name, u.name), COALESCE(s.frequency, 'WEEKLY'),
+               s.last_sent_at
+        FROM users u
+        LEFT JOIN digest_settings s ON s.user_id = u.id
+        WHERE u.email IS NOT NULL
+          AND COALESCE(s.frequency, 'WEEKLY') IN ('DAILY', 'WEEKLY')
+          AND (
+              s.last_sent_at IS NULL
+              OR s.last_sent_at <= NOW() + INTERVAL '1 hour'
+                 - CASE s.frequency WHEN 'DAILY' THEN INTERVAL '1 day' ELSE INTERVAL '7 days' END
+          )
+        ORDER BY s.last_sent_at NULLS FIRST
+        LIMIT $1
+        "#,
+    )
+    .bind(DIGEST_BATCH)
+    .fetch_all(&db.pool)
+    .await?;
+
+    let mut sent = 0;
+    for (user_id, email, name, frequency, last_sent_at) in due {
+        let frequency = DigestFrequency::parse(&frequency).unwrap_or_default();
+        // Claiming moves `last_sent_at` on only if no other instance did in the meantime
+        let claimed = sqlx::query(
+            r#"
+            INSERT INTO digest_settings (user_id, frequency, last_sent_at)
+            VALUES ($1, $2, NOW())
+            ON CONFLICT (user_id) DO UPDATE SET last_sent_at = NOW()
+            WHERE digest_settings.last_sent_at IS NOT DISTINCT FROM $3
+            "#,
+        )
+        .bind(&user_id)
+        .bind(frequency.as_str())
+        .bind(last_sent_at)
+        .execute(&db.pool)
+        .await?
+        .rows_affected();
+        if claimed == 0 {
+            continue;
+        }
+
+        let period = match frequency {
+            DigestFrequency::Daily => Duration::days(1),
+            _ => Duration::days(7),
+        };
+        let since = last_sent_at.unwrap_or_else(|| Utc::now() - period);
+        if send_digest(db, &user_id, &email, &name, frequency, since).await? {
+            sent += 1;
+        }
+    }
+
+    if sent > 0 {
+        info!("Queued {} digest emails", sent);
+    }
+    Ok(())
+}
+
+async fn send_digest(
+    db: &Database,
+    user_id: &str,
+    email: &str,
+    name: &str,
+    frequency: DigestFrequency,
+    since: DateTime<Utc>,
+) -> anyhow::Result<bool> {
+    let notifications = sqlx::query_as::<_, (Option<String>, Option<String>, i64)>(
+        r#"
+        SELECT payload->>'message', payload->>'link', COUNT(*) OVER ()
+        FROM notifications
+        WHERE user_id = $1 AND read_at IS NULL AND created_at > $2
+        ORDER BY created_at DESC
+        LIMIT $3
+        "#,
+    )
+    .bind(user_id)
+    .bind(since)
+    .bind(DIGEST_ITEM_LIMIT)
+    .fetch_all(&db.pool)
+    .await?;
+    let posts = sqlx::query_as::<_, (Uuid, String, String, i64)>(
+        r#"
+        SELECT p.id, p.title, COALESCE(u.display_name, u.username, u.name), COUNT(*) OVER ()
+        FROM posts p
+        JOIN follows f ON f.following_id = p.user_id AND f.follower_id = $1
+        JOIN users u ON u.id = p.user_id
+        WHERE COALESCE(p.published, TRUE)
+          AND COALESCE(p.published_at, p.created_at) > $2
+          AND COALESCE(p.published_at, p.created_at) <= NOW()
+        ORDER BY COALESCE(p.published_at, p.created_at) DESC
+        LIMIT $3
+        "#,
+    )
+    .bind(user_id)
+    .bind(since)
+    .bind(DIGEST_ITEM_LIMIT)
+    .fetch_all(&db.pool)
+    .await?;
+
+    let notification_count = notifications.first().map_or(0, |(_, _, total)| *total);
+    let post_count = posts.first().map_or(0, |(_, _, _, total)| *total);
+    if notification_count == 0 && post_count == 0 {
+        return Ok(false);
+    }
+
+    let notification_items: Vec<(String, String)> = notifications
+        .into_iter()
+        .map(|(message, link, _)| {
+            (
+                message.unwrap_or_else(|| "New notification".to_string()),
+                app_link(link.as_deref().unwrap_or("/dashboard")),
+            )
+        })
+        .collect();
+    let post_items: Vec<(String, String)> = posts
+        .into_iter()
+        .map(|(id, title, creator, _)| {
+            (format!("{}: {}", creator, title), app_link(&format!("/posts/{}", id)))
+        })
+        .collect();
+    let (notifications_text, notifications_html) = section(
+        &format!("{} unread notifications", notification_count),
+        &notification_items,
+        notification_count,
+    );
+    let (posts_text, posts_html) = section(
+        &format!("{} new posts from creators you follow", post_count),
+        &post_items,
+        post_count,
+    );
+
+    let summary = match (notification_count, post_count) {
+        (0, posts) => format!("{} new posts", posts),
+        (notifications, 0) => format!("{} notifications", notifications),
+        (notifications, posts) => {
+            format!("{} notifications and {} new posts", notifications, posts)
+        }
+    };
+    let (period, cadence) = match frequency {
+        DigestFrequency::Daily => ("today", "daily"),
+        _ => ("this week", "weekly"),
+    };
+    let unsubscribe_link = api_link(&format!(
+        "/api/notifications/digest/unsubscribe?token={}",
+        digest_unsubscribe_token(user_id)
+    ));
+
+    queue_email(
+        db,
+        email,
+        Some(user_id),
+        EmailTemplate::Digest,
+        json!({
+            "name": name,
+            "summary": summary,
+            "period": period,
+            "cadence": cadence,
+            "notifications_text": notifications_text,
+            "notifications_html": notifications_html,
+            "posts_text": posts_text,
+            "posts_html": posts_html,
+            "link": app_link("/dashboard"),
+            "unsubscribe_link": unsubscribe_link,
+        }),
+    )
+    .await;
+    Ok(true)
+}
+
+/// A titled list of linked items as plain text and HTML; empty when there is nothing to list.
+pub(super) fn section(title: &str, items: &[(String, String)], total: i64) -> (String, String) {
+    if items.is_empty() {
+        return (String::new(), String::new());
+    }
+    let more = total - items.len() as i64;
+
+    let mut text = format!("{}\n", title);
+    let mut html = format!("<h2 style=\"font-size:16px;\">{}</h2><ul>", escape_html(title));
+    for (label, link) in items {
+        text.push_str(&format!("- {} ({})\n", label, link));
+        html.push_str(&format!(
+            "<li><a href=\"{}\">{}</a></li>",
+            escape_html(link),
+            escape_html(label)
+        ));
+    }
+    if more > 0 {
+        text.push_str(&format!("...and {} more\n", more));
+        html.push_str(&format!("<li>...and {} more</li>", more));
+    }
+    html.push_str("</ul>");
+    (text.trim_end().to_string(), html)
+}
@@ -0,0 +1,143 @@
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
+use serde_json::{json, Value};
+use tracing::info;
+use uuid::Uuid;
+
+use crate::{
+    database::Database,
+    email_service::{app_link, queue_email, EmailTemplate},
+    invoice_pdf::format_amount,
+    notification_service::{notify, NotificationKind},
+    routes::events::parse_timezone,
+};
+
+/// Remind everyone going to a published event that starts within the next 24 hours. Each event
+/// is claimed by setting `reminder_sent_at`, so attendees get one reminder however many
+/// instances tick.
+pub async fn send_event_reminders(db: &Database) -> anyhow::Result<()> {
+    let attendees = sqlx::query_as::<
+        _,
+        (Uuid, String, DateTime<Utc>, Option<String>, String, String),
+    >(
+        r#"
+        WITH due AS (
+            UPDATE events SET reminder_sent_at = NOW()
+            WHERE status = 'PUBLISHED' AND reminder_sent_at IS NULL
+              AND start_time > NOW() AND start_time <= NOW() + INTERVAL '24 hours'
+            RETURNING id, title, start_time, timezone
+        )
+        SELECT d.id, d.title, d.start_time, d.timezone, u.id, u.email
+        FROM due d
+        JOIN event_rsvps r ON r.event_id = d.id::TEXT AND UPPER(TRIM(r.status)) = 'GOING'
+        JOIN users u ON u.id = r.user_id
+        WHERE u.email IS NOT NULL
+        "#,
+    )
+    .fetch_all(&db.pool)
+    .await?;
+
+    if !attendees.is_empty() {
+        info!("Sending {} event reminders", attendees.len());
+    }
+    for (event_id, title, start_time, timezone, user_id, email) in attendees {
+        let tz = timezone.as_deref().and_then(parse_timezone).unwrap_or(Tz::UTC);
+        let start_time = start_time
+            .with_timezone(&tz)
+            .format("%A, %B %-d at %H:%M %Z")
+            .to_string();
+        notify(
+            db,
+            &user_id,
+            NotificationKind::Event,
+            json!({
+                "message": format!("{} starts on {}", title, start_time),
+                "link": format!("/events/{}", event_id),
+                "eventId": event_id,
+            }),
+        )
+        .await;
+        queue_email(
+            db,
+            &email,
+            Some(&user_id),
+            EmailTemplate::EventReminder,
+            json!({
+                "event_title": title,
+                "start_time": start_time,
+                "link": app_link(&format!("/events/{}", event_id)),
+            }),
+        )
+        .await;
+    }
+    Ok(())
+}
+
+pub async fn send_refund_email(
+    db: &Database,
+    user_id: &str,
+    email: &str,
+    amount_cents: i64,
+    currency: &str,
+    reason: &str,
+) -> anyhow::Result<()> {
+    queue_email(
+        db,
+        email,
+        Some(user_id),
+        EmailTemplate::RefundIssued,
+        json!({
+            "amount": format_amount(amount_cents, currency),
+            "reason": reason,
+        }),
+    )
+    .await;
+    Ok(())
+}
+
+pub async fn send_payout_summary(
+    db: &Database,
+    user_id: &str,
+    email: &str,
+    amount_cents: i64,
+    currency: &str,
+    arrival_date: Option<&str>,
+    transactions: &[Value],
+) -> anyhow::Result<()> {
+    let arrival_date = arrival_date
+        .and_then(|date| DateTime::parse_from_rfc3339(date).ok())
+        .map(|date| format!("on {}", date.format("%B %-d, %Y")))
+        .unwrap_or_else(|| "within a few business days".to_string());
+    let transactions = transactions
+        .iter()
+        .map(|transaction| {
+            let settled_on = transaction["settledAt"]
+                .as_str()
+                .and_then(|date| DateTime::parse_from_rfc3339(date).ok())
+                .map(|date| date.format("%Y-%m-%d").to_string())
+                .unwrap_or_default();
+            format!(
+                "{}  {}  {}",
+                settled_on,
+                transaction["sourceType"].as_str().unwrap_or_default(),
+                format_amount(transaction["netCents"].as_i64().unwrap_or(0), currency)
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    queue_email(
+        db,
+        email,
+        Some(user_id),
+        EmailTemplate::PayoutSummary,
+        json!({
+            "amount": format_amount(amount_cents, currency),
+            "arrival_date": arrival_date,
+            "transactions": transactions,
+            "link": app_link("/creator-dashboard"),
+        }),
+    )
+    .await;
+    Ok(())
+}
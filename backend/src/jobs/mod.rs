@@ -0,0 +1,414 @@
+use std::time::Duration;
+
+use futures_util::StreamExt;
+use lapin::options::{BasicAckOptions, BasicNackOptions};
+use tracing::{error, info, warn};
+
+use crate::{
+    amqp_client::{AmqpClient, JobMessage},
+    article_views,
+    database::Database,
+    email_service::{self, EMAIL_QUEUE},
+    messaging, post_views,
+    routes::{cohorts, dm_sequences, feed_telemetry, priority_messages, tracking},
+};
+
+mod analytics_reports;
+pub mod announcements;
+pub mod article_import;
+mod audio;
+mod checkouts;
+mod digests;
+mod emails;
+pub mod patreon_import;
+mod payouts;
+pub mod podcast_audio;
+pub mod podcast_import;
+pub mod podcast_transcript;
+mod preorders;
+mod publishing;
+
+/// How often buffered post view counters are written to Postgres.
+const VIEW_FLUSH_INTERVAL: Duration = Duration::from_secs(60 * 60);
+/// How often content scheduled for publishing is checked.
+const PUBLISHING_INTERVAL: Duration = Duration::from_secs(60);
+/// How often creators' payout schedules are checked for payouts that are due.
+const PAYOUT_INTERVAL: Duration = Duration::from_secs(15 * 60);
+/// How often email retries that are due and upcoming event reminders are checked.
+const EMAIL_INTERVAL: Duration = Duration::from_secs(60);
+/// How often users due for their digest email are looked up.
+const DIGEST_INTERVAL: Duration = Duration::from_secs(60 * 60);
+/// How often message attachments that were uploaded but never sent are cleared out.
+const ATTACHMENT_CLEANUP_INTERVAL: Duration = Duration::from_secs(60 * 60);
+/// How often group conversations are synced with their creators' active members.
+const GROUP_SYNC_INTERVAL: Duration = Duration::from_secs(10 * 60);
+/// How often priority messages past their reply deadline are refunded.
+const PRIORITY_REFUND_INTERVAL: Duration = Duration::from_secs(5 * 60);
+/// How often new members are enrolled in DM sequences and due steps sent.
+const DM_SEQUENCE_INTERVAL: Duration = Duration::from_secs(5 * 60);
+/// How often feed telemetry is rolled up into daily per-item stats.
+const FEED_ROLLUP_INTERVAL: Duration = Duration::from_secs(60 * 60);
+/// How often pre-order products past their release date are released.
+const PREORDER_RELEASE_INTERVAL: Duration = Duration::from_secs(60);
+/// How often tracked analytics events are rolled up into daily per-creator stats.
+const ANALYTICS_ROLLUP_INTERVAL: Duration = Duration::from_secs(15 * 60);
+/// How often creators due for their analytics report email are looked up.
+const ANALYTICS_REPORT_INTERVAL: Duration = Duration::from_secs(60 * 60);
+/// How often subscriber cohort retention is brought up to date.
+const COHORT_REFRESH_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+/// How often unpaid checkout sessions are expired and their buyers reminded.
+const CHECKOUT_RECOVERY_INTERVAL: Duration = Duration::from_secs(5 * 60);
+/// How often imported podcasts are checked for feeds due a re-sync.
+const PODCAST_SYNC_INTERVAL: Duration = Duration::from_secs(30 * 60);
+
+/// Spawn the periodic tasks and the background consumers for CloudAMQP job queues.
+pub fn spawn_workers(db: Database) {
+    article_views::spawn_writer(db.clone());
+
+    let flush_db = db.clone();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(VIEW_FLUSH_INTERVAL);
+        // The first tick completes immediately; skip it so startup doesn't flush a partial hour.
+        interval.tick().await;
+        loop {
+            interval.tick().await;
+            if let Err(e) = post_views::flush_pending(&flush_db).await {
+                error!("Failed to flush post views: {:?}", e);
+            }
+        }
+    });
+
+    let publishing_db = db.clone();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(PUBLISHING_INTERVAL);
+        loop {
+            interval.tick().await;
+            if let Err(e) = publishing::sync_scheduled_polls(&publishing_db).await {
+                error!("Failed to sync scheduled polls: {:?}", e);
+            }
+            if let Err(e) = publishing::publish_scheduled_episodes(&publishing_db).await {
+                error!("Failed to publish scheduled episodes: {:?}", e);
+            }
+        }
+    });
+
+    let payouts_db = db.clone();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(PAYOUT_INTERVAL);
+        loop {
+            interval.tick().await;
+            if let Err(e) = payouts::run_scheduled_payouts(&payouts_db).await {
+                error!("Failed to run scheduled payouts: {:?}", e);
+            }
+        }
+    });
+
+    let email_db = db.clone();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(EMAIL_INTERVAL);
+        loop {
+            interval.tick().await;
+            if let Err(e) = email_service::sweep_due_deliveries(&email_db).await {
+                error!("Failed to sweep email deliveries: {:?}", e);
+            }
+            if let Err(e) = emails::send_event_reminders(&email_db).await {
+                error!("Failed to send event reminders: {:?}", e);
+            }
+        }
+    });
+
+    let digest_db = db.clone();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(DIGEST_INTERVAL);
+        loop {
+            interval.tick().await;
+            if let Err(e) = digests::send_digests(&digest_db).await {
+                error!("Failed to send digests: {:?}", e);
+            }
+        }
+    });
+
+    let attachments_db = db.clone();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(ATTACHMENT_CLEANUP_INTERVAL);
+        loop {
+            interval.tick().await;
+            if let Err(e) = messaging::delete_unsent_attachments(&attachments_db).await {
+                error!("Failed to clean up unsent attachments: {:?}", e);
+            }
+        }
+    });
+
+    let groups_db = db.clone();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(GROUP_SYNC_INTERVAL);
+        loop {
+            interval.tick().await;
+            if let Err(e) = messaging::sync_group_members(&groups_db, None).await {
+                error!("Failed to sync group conversations: {:?}", e);
+            }
+        }
+    });
+
+    let priority_db = db.clone();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(PRIORITY_REFUND_INTERVAL);
+        loop {
+            interval.tick().await;
+            if let Err(e) = priority_messages::refund_overdue_priority_messages(&priority_db).await {
+                error!("Failed to refund overdue priority messages: {:?}", e);
+            }
+        }
+    });
+
+    let sequences_db = db.clone();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(DM_SEQUENCE_INTERVAL);
+        loop {
+            interval.tick().await;
+            if let Err(e) = dm_sequences::enroll_new_members(&sequences_db).await {
+                error!("Failed to enroll members in DM sequences: {:?}", e);
+            }
+            if let Err(e) = dm_sequences::send_due_steps(&sequences_db).await {
+                error!("Failed to send DM sequence steps: {:?}", e);
+            }
+        }
+    });
+
+    let rollup_db = db.clone();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(FEED_ROLLUP_INTERVAL);
+        loop {
+            interval.tick().await;
+            if let Err(e) = feed_telemetry::roll_up_daily_stats(&rollup_db).await {
+                error!("Failed to roll up feed telemetry: {:?}", e);
+            }
+        }
+    });
+
+    let analytics_db = db.clone();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(ANALYTICS_ROLLUP_INTERVAL);
+        loop {
+            interval.tick().await;
+            if let Err(e) = tracking::roll_up_creator_stats(&analytics_db).await {
+                error!("Failed to roll up analytics events: {:?}", e);
+            }
+        }
+    });
+
+    let cohorts_db = db.clone();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(COHORT_REFRESH_INTERVAL);
+        loop {
+            interval.tick().await;
+            if let Err(e) = cohorts::refresh_cohorts(&cohorts_db).await {
+                error!("Failed to refresh subscriber cohorts: {:?}", e);
+            }
+        }
+    });
+
+    let reports_db = db.clone();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(ANALYTICS_REPORT_INTERVAL);
+        loop {
+            interval.tick().await;
+            if let Err(e) = analytics_reports::send_analytics_reports(&reports_db).await {
+                error!("Failed to send analytics reports: {:?}", e);
+            }
+        }
+    });
+
+    let preorders_db = db.clone();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(PREORDER_RELEASE_INTERVAL);
+        loop {
+            interval.tick().await;
+            if let Err(e) = preorders::release_preorders(&preorders_db).await {
+                error!("Failed to release pre-orders: {:?}", e);
+            }
+        }
+    });
+
+    let checkouts_db = db.clone();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(CHECKOUT_RECOVERY_INTERVAL);
+        loop {
+            interval.tick().await;
+            if let Err(e) = checkouts::send_checkout_reminders(&checkouts_db).await {
+                error!("Failed to send checkout reminders: {:?}", e);
+            }
+            if let Err(e) = checkouts::expire_stale_checkouts(&checkouts_db).await {
+                error!("Failed to expire abandoned checkouts: {:?}", e);
+            }
+        }
+    });
+
+    let podcast_sync_db = db.clone();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(PODCAST_SYNC_INTERVAL);
+        loop {
+            interval.tick().await;
+            if let Err(e) = podcast_import::sync_feeds(&podcast_sync_db).await {
+                error!("Failed to sync podcast feeds: {:?}", e);
+            }
+        }
+    });
+
+    let amqp = match db.amqp.clone() {
+        Some(amqp) => amqp,
+        None => {
+            warn!("⚠️  CloudAMQP not configured; background jobs are disabled");
+            return;
+        }
+    };
+
+    let media_db = db.clone();
+    let media_amqp = amqp.clone();
+    tokio::spawn(async move {
+        if let Err(e) =
+            run_queue(media_db, media_amqp, "media_processing", "funify-media-worker").await
+        {
+            error!("Media processing worker stopped: {}", e);
+        }
+    });
+
+    let email_db = db.clone();
+    let email_amqp = amqp.clone();
+    tokio::spawn(async move {
+        if let Err(e) = run_queue(email_db, email_amqp, EMAIL_QUEUE, "funify-email-worker").await {
+            error!("Email worker stopped: {}", e);
+        }
+    });
+
+    let announcement_db = db.clone();
+    let announcement_amqp = amqp.clone();
+    tokio::spawn(async move {
+        if let Err(e) = run_queue(
+            announcement_db,
+            announcement_amqp,
+            "announcements",
+            "funify-announcement-worker",
+        )
+        .await
+        {
+            error!("Announcement worker stopped: {}", e);
+        }
+    });
+
+    tokio::spawn(async move {
+        if let Err(e) = run_queue(db, amqp, "content_imports", "funify-import-worker").await {
+            error!("Content import worker stopped: {}", e);
+        }
+    });
+}
+
+async fn run_queue(
+    db: Database,
+    amqp: AmqpClient,
+    queue: &str,
+    consumer_tag: &str,
+) -> anyhow::Result<()> {
+    let mut consumer = amqp.consume(queue, consumer_tag).await?;
+
+    while let Some(delivery) = consumer.next().await {
+        let delivery = match delivery {
+            Ok(delivery) => delivery,
+            Err(e) => {
+                error!("Failed to receive message from '{}': {}", queue, e);
+                continue;
+            }
+        };
+
+        let job = match serde_json::from_slice::<JobMessage>(&delivery.data) {
+            Ok(job) => job,
+            Err(e) => {
+                error!("Discarding malformed job on '{}': {}", queue, e);
+                delivery
+                    .nack(BasicNackOptions {
+                        requeue: false,
+                        ..Default::default()
+                    })
+                    .await?;
+                continue;
+            }
+        };
+
+        if let Err(e) = handle_job(&db, job).await {
+            error!("Job on '{}' failed: {:?}", queue, e);
+        }
+
+        delivery.ack(BasicAckOptions::default()).await?;
+    }
+
+    info!("Consumer for '{}' closed", queue);
+    Ok(())
+}
+
+async fn handle_job(db: &Database, job: JobMessage) -> anyhow::Result<()> {
+    match job {
+        JobMessage::AudioWaveform { post_id, audio_url } => {
+            audio::process_audio_post(db, &post_id, &audio_url).await
+        }
+        JobMessage::EpisodeAudio { episode_id } => {
+            podcast_audio::process_episode_audio(db, &episode_id).await
+        }
+        JobMessage::EpisodeTranscript { episode_id } => {
+            podcast_transcript::transcribe_episode(db, &episode_id).await
+        }
+        JobMessage::PostImport { import_id } => {
+            patreon_import::run_import(db, &import_id).await
+        }
+        JobMessage::ArticleImport { import_id } => {
+            article_import::run_import(db, &import_id).await
+        }
+        JobMessage::PodcastImport { import_id } => {
+            podcast_import::run_import(db, &import_id).await
+        }
+        JobMessage::EpisodeMirror { episode_id } => {
+            podcast_import::mirror_episode_audio(db, &episode_id).await
+        }
+        JobMessage::AnnouncementBatch {
+            announcement_id,
+            batch,
+        } => announcements::deliver_batch(db, &announcement_id, batch).await,
+        JobMessage::SendEmail { delivery_id } => {
+            email_service::deliver(db, &delivery_id).await
+        }
+        JobMessage::RefundIssued {
+            user_id,
+            email,
+            amount_cents,
+            currency,
+            reason,
+            ..
+        } => {
+            emails::send_refund_email(db, &user_id, &email, amount_cents, &currency, &reason)
+                .await
+        }
+        JobMessage::PayoutSummary {
+            user_id,
+            email,
+            amount_cents,
+            currency,
+            arrival_date,
+            transactions,
+            ..
+        } => {
+            emails::send_payout_summary(
+                db,
+                &user_id,
+                &email,
+                amount_cents,
+                &currency,
+                arrival_date.as_deref(),
+                &transactions,
+            )
+            .await
+        }
+        other => {
+            warn!("No handler registered for job {:?}", other);
+            Ok(())
+        }
+    }
+}
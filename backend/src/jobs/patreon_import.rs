@@ -0,0 +1,310 @@
+use std::{collections::HashMap, io::Read};
+
+use anyhow::{anyhow, Context};
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+use tracing::{error, info};
+use uuid::Uuid;
+
+use crate::{database::Database, storage};
+
+/// Rows between progress updates written to `post_imports`.
+const PROGRESS_BATCH: i32 = 25;
+
+/// A post parsed from a Patreon export, mapped onto our post columns.
+#[derive(Debug)]
+struct ImportedPost {
+    external_id: Option<String>,
+    title: String,
+    content: Option<String>,
+    image_url: Option<String>,
+    is_premium: bool,
+    published_at: Option<DateTime<Utc>>,
+}
+
+/// Process a queued import: parse the stored export and create posts for the importing creator.
+pub async fn run_import(db: &Database, import_id: &str) -> anyhow::Result<()> {
+    let import_id = Uuid::parse_str(import_id).context("invalid import id")?;
+
+    // Claiming the row only from QUEUED keeps redelivered messages from importing twice.
+    let import = sqlx::query_as::<_, (String, String, Option<String>)>(
+        r#"
+        UPDATE post_imports
+        SET status = 'PROCESSING', updated_at = NOW()
+        WHERE id = $1 AND status = 'QUEUED'
+        RETURNING user_id, storage_key, filename
+        "#,
+    )
+    .bind(import_id)
+    .fetch_optional(&db.pool)
+    .await?;
+
+    let (user_id, storage_key, filename) = match import {
+        Some(import) => import,
+        None => {
+            info!("Import {} is not queued; skipping", import_id);
+            return Ok(());
+        }
+    };
+
+    let result = import_posts(db, import_id, &user_id, &storage_key, filename.as_deref()).await;
+
+    match &result {
+        Ok(()) => {
+            sqlx::query(
+                r#"
+                UPDATE post_imports
+                SET status = 'COMPLETED', updated_at = NOW(), completed_at = NOW()
+                WHERE id = $1
+                "#,
+            )
+            .bind(import_id)
+            .execute(&db.pool)
+            .await?;
+        }
+        Err(e) => {
+            error!("Post import {} failed: {:?}", import_id, e);
+            sqlx::query(
+                r#"
+                UPDATE post_imports
+                SET status = 'FAILED', error = $2, updated_at = NOW(), completed_at = NOW()
+                WHERE id = $1
+                "#,
+            )
+            .bind(import_id)
+            .bind(e.to_string())
+            .execute(&db.pool)
+            .await?;
+        }
+    }
+
+    if let Err(e) = storage::delete_private_file(&storage_key).await {
+        error!("Failed to remove import file {}: {:?}", storage_key, e);
+    }
+
+    result
+}
+
+async fn import_posts(
+    db: &Database,
+    import_id: Uuid,
+    user_id: &str,
+    storage_key: &str,
+    filename: Option<&str>,
+) -> anyhow::Result<()> {
+    let bytes = storage::read_private_file(storage_key).await?;
+    let is_zip = filename
+        .map(|name| name.to_ascii_lowercase().ends_with(".zip"))
+        .unwrap_or(false)
+        || bytes.starts_with(b"PK\x03\x04");
+
+    let posts = tokio::task::spawn_blocking(move || {
+        let csv = if is_zip { extract_csv(&bytes)? } else { bytes };
+        parse_posts(&csv)
+    })
+    .await??;
+
+    sqlx::query("UPDATE post_imports SET total_rows = $2, updated_at = NOW() WHERE id = $1")
+        .bind(import_id)
+        .bind(posts.len() as i32)
+        .execute(&db.pool)
+        .await?;
+
+    let mut processed = 0;
+    let mut imported = 0;
+    let mut skipped = 0;
+
+    for post in posts {
+        if insert_post(db, user_id, &post).await? {
+            imported += 1;
+        } else {
+            skipped += 1;
+        }
+        processed += 1;
+
+        if processed % PROGRESS_BATCH == 0 {
+            update_progress(db, import_id, processed, imported, skipped).await?;
+        }
+    }
+
+    update_progress(db, import_id, processed, imported, skipped).await?;
+    info!(
+        "Post import {} finished: {} imported, {} skipped",
+        import_id, imported, skipped
+    );
+    Ok(())
+}
+
+/// Insert one imported post, skipping posts already imported from the same source id.
+async fn insert_post(db: &Database, user_id: &str, post: &ImportedPost) -> anyhow::Result<bool> {
+    if let Some(external_id) = &post.external_id {
+        let exists = sqlx::query_scalar::<_, bool>(
+            "SELECT EXISTS(SELECT 1 FROM posts WHERE user_id = $1 AND external_id = $2)",
+        )
+        .bind(user_id)
+        .bind(external_id)
+        .fetch_one(&db.pool)
+        .await?;
+
+        if exists {
+            return Ok(false);
+        }
+    }
+
+    let image_urls = post.image_url.clone().map(|url| vec![url]);
+    let media_type = post.image_url.as_ref().map(|_| "image".to_string());
+
+    sqlx::query(
+        r#"
+        INSERT INTO posts (user_id, title, content, media_url, media_type, image_urls, is_premium, published, published_at, external_id, created_at)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, TRUE, $8, $9, COALESCE($8, NOW()))
+        "#,
+    )
+    .bind(user_id)
+    .bind(&post.title)
+    .bind(&post.content)
+    .bind(&post.image_url)
+    .bind(media_type)
+    .bind(image_urls)
+    .bind(post.is_premium)
+    .bind(post.published_at)
+    .bind(&post.external_id)
+    .execute(&db.pool)
+    .await?;
+
+    Ok(true)
+}
+
+async fn update_progress(
+    db: &Database,
+    import_id: Uuid,
+    processed: i32,
+    imported: i32,
+    skipped: i32,
+) -> anyhow::Result<()> {
+    sqlx::query(
+        r#"
+        UPDATE post_imports
+        SET processed_rows = $2, imported_posts = $3, skipped_rows = $4, updated_at = NOW()
+        WHERE id = $1
+        "#,
+    )
+    .bind(import_id)
+    .bind(processed)
+    .bind(imported)
+    .bind(skipped)
+    .execute(&db.pool)
+    .await?;
+    Ok(())
+}
+
+/// Pick the posts CSV out of a Patreon ZIP export.
+fn extract_csv(bytes: &[u8]) -> anyhow::Result<Vec<u8>> {
+    let mut archive = zip::ZipArchive::new(std::io::Cursor::new(bytes))?;
+
+    let mut candidates: Vec<String> = archive
+        .file_names()
+        .filter(|name| name.to_ascii_lowercase().ends_with(".csv"))
+        .map(|name| name.to_string())
+        .collect();
+    candidates.sort_by_key(|name| !name.to_ascii_lowercase().contains("post"));
+
+    let name = candidates
+        .into_iter()
+        .next()
+        .ok_or_else(|| anyhow!("No CSV file found in the archive"))?;
+
+    let mut csv = Vec::new();
+    archive.by_name(&name)?.read_to_end(&mut csv)?;
+    Ok(csv)
+}
+
+fn parse_posts(bytes: &[u8]) -> anyhow::Result<Vec<ImportedPost>> {
+    let mut reader = csv::ReaderBuilder::new()
+        .flexible(true)
+        .trim(csv::Trim::All)
+        .from_reader(bytes);
+
+    let columns: HashMap<String, usize> = reader
+        .headers()?
+        .iter()
+        .enumerate()
+        .map(|(index, header)| (normalize_header(header), index))
+        .collect();
+
+    let column = |names: &[&str]| names.iter().find_map(|name| columns.get(*name).copied());
+    let title_column = column(&["title", "posttitle", "name"])
+        .ok_or_else(|| anyhow!("The export has no title column"))?;
+    let content_column = column(&["content", "body", "text", "description", "postcontent"]);
+    let published_column = column(&["publishedat", "publishdate", "published", "date", "createdat"]);
+    let tier_column = column(&["tiers", "tier", "access", "audience", "visibility", "minimumtier"]);
+    let id_column = column(&["id", "postid", "url", "posturl", "link"]);
+    let image_column = column(&["imageurl", "image", "thumbnail", "thumbnailurl"]);
+
+    let mut posts = Vec::new();
+    for record in reader.records() {
+        let record = record?;
+        let value = |index: Option<usize>| {
+            index
+                .and_then(|index| record.get(index))
+                .map(str::trim)
+                .filter(|value| !value.is_empty())
+                .map(str::to_string)
+        };
+
+        let title = match value(Some(title_column)) {
+            Some(title) => title.chars().take(255).collect(),
+            None => continue,
+        };
+
+        posts.push(ImportedPost {
+            external_id: value(id_column),
+            title,
+            content: value(content_column),
+            image_url: value(image_column)
+                .filter(|url| url.starts_with("http://") || url.starts_with("https://")),
+            is_premium: !is_public_audience(value(tier_column).as_deref()),
+            published_at: value(published_column).and_then(|date| parse_date(&date)),
+        });
+    }
+
+    Ok(posts)
+}
+
+fn normalize_header(header: &str) -> String {
+    header
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_lowercase())
+        .collect()
+}
+
+/// Patreon marks public posts as "Public"/"Everyone"; any tier list means patrons only.
+fn is_public_audience(tiers: Option<&str>) -> bool {
+    match tiers {
+        None => true,
+        Some(tiers) => {
+            let tiers = tiers.to_ascii_lowercase();
+            tiers == "public" || tiers == "everyone" || tiers == "all"
+        }
+    }
+}
+
+fn parse_date(value: &str) -> Option<DateTime<Utc>> {
+    if let Ok(date) = DateTime::parse_from_rfc3339(value) {
+        return Some(date.with_timezone(&Utc));
+    }
+
+    for format in ["%Y-%m-%d %H:%M:%S", "%Y-%m-%dT%H:%M:%S", "%m/%d/%Y %H:%M"] {
+        if let Ok(date) = NaiveDateTime::parse_from_str(value, format) {
+            return Some(date.and_utc());
+        }
+    }
+
+    for format in ["%Y-%m-%d", "%m/%d/%Y"] {
+        if let Ok(date) = NaiveDate::parse_from_str(value, format) {
+            return date.and_hms_opt(0, 0, 0).map(|date| date.and_utc());
+        }
+    }
+
+    None
+}
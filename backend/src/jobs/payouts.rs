@@ -0,0 +1,155 @@
+use chrono::{DateTime, Utc};
+use serde_json::json;
+use tracing::{error, info, warn};
+
+use crate::{
+    database::Database,
+    routes::withdrawals::{
+        initiate_withdrawal, load_balances, next_payout_after, Withdrawal, MIN_WITHDRAWAL_CENTS,
+    },
+};
+
+/// Most ledger entries listed in a payout summary email; the dashboard has the full history.
+const SUMMARY_TRANSACTION_LIMIT: i64 = 200;
+
+/// Pay out every creator whose weekly or monthly payout is due, in each currency whose available
+/// balance reaches their threshold, and email them a summary.
+pub async fn run_scheduled_payouts(db: &Database) -> anyhow::Result<()> {
+    let due = sqlx::query_as::<_, (String, String, i64, i16, i16, DateTime<Utc>)>(
+        r#"
+        SELECT creator_id, frequency, minimum_cents, weekly_day, monthly_day, next_payout_at
+        FROM payout_schedules
+        WHERE frequency <> 'MANUAL' AND next_payout_at <= NOW()
+        ORDER BY next_payout_at
+        "#,
+    )
+    .fetch_all(&db.pool)
+    .await?;
+
+    let mut paid_out = 0;
+    for (creator_id, frequency, minimum_cents, weekly_day, monthly_day, due_at) in due {
+        // Moving the schedule on first claims the run, so another instance ticking at the same
+        // time skips it, and a failing payout is retried next period rather than every tick
+        let next_payout_at = next_payout_after(&frequency, weekly_day, monthly_day, Utc::now());
+        let claimed = sqlx::query(
+            r#"
+            UPDATE payout_schedules
+            SET next_payout_at = $2, last_payout_at = NOW()
+            WHERE creator_id = $1 AND next_payout_at = $3
+            "#,
+        )
+        .bind(&creator_id)
+        .bind(next_payout_at)
+        .bind(due_at)
+        .execute(&db.pool)
+        .await?
+        .rows_affected();
+        if claimed == 0 {
+            continue;
+        }
+
+        let balances = match load_balances(db, &creator_id).await {
+            Ok(balances) => balances,
+            Err(status) => {
+                error!("Skipping scheduled payout of {}: balance unavailable ({})", creator_id, status);
+                continue;
+            }
+        };
+        for balance in balances {
+            if balance.available_cents < minimum_cents.max(MIN_WITHDRAWAL_CENTS) {
+                continue;
+            }
+            match initiate_withdrawal(db, &creator_id, &balance.currency, None, minimum_cents, true).await {
+                Ok(Some(withdrawal)) => {
+                    paid_out += 1;
+                    send_summary(db, &withdrawal).await;
+                }
+                Ok(None) => {}
+                Err(status) => error!(
+                    "Scheduled {} payout of {} failed: {}",
+                    balance.currency, creator_id, status
+                ),
+            }
+        }
+    }
+
+    if paid_out > 0 {
+        info!("Started {} scheduled payouts", paid_out);
+    }
+    Ok(())
+}
+
+// The summary lists the earnings settled since the creator's previous payout in this currency
+async fn send_summary(db: &Database, withdrawal: &Withdrawal) {
+    let Some(amqp) = &db.amqp else {
+        return;
+    };
+    let email = match sqlx::query_scalar::<_, Option<String>>("SELECT email FROM users WHERE id = $1")
+        .bind(&withdrawal.creator_id)
+        .fetch_optional(&db.pool)
+        .await
+    {
+        Ok(Some(Some(email))) => email,
+        Ok(_) => return,
+        Err(e) => {
+            warn!("Failed to load email of {}: {}", withdrawal.creator_id, e);
+            return;
+        }
+    };
+
+    let transactions = sqlx::query_as::<_, (String, String, i64, i64, i64, Option<DateTime<Utc>>)>(
+        r#"
+        SELECT l.source_type, l.source_id, l.gross_cents, l.fee_cents, l.net_cents, l.settled_at
+        FROM ledger_entries l
+        WHERE l.creator_id = $1 AND l.currency = $2 AND l.status = 'SETTLED'
+          AND l.settled_at <= $3
+          AND l.settled_at > COALESCE((
+              SELECT MAX(w.created_at) FROM withdrawals w
+              WHERE w.creator_id = $1 AND w.currency = $2 AND w.status <> 'FAILED'
+                AND w.created_at < $3
+          ), '-infinity'::TIMESTAMPTZ)
+        ORDER BY l.settled_at DESC
+        LIMIT $4
+        "#,
+    )
+    .bind(&withdrawal.creator_id)
+    .bind(&withdrawal.currency)
+    .bind(withdrawal.created_at)
+    .bind(SUMMARY_TRANSACTION_LIMIT)
+    .fetch_all(&db.pool)
+    .await;
+    let transactions = match transactions {
+        Ok(rows) => rows
+            .into_iter()
+            .map(|(source_type, source_id, gross, fee, net, settled_at)| {
+                json!({
+                    "sourceType": source_type,
+                    "sourceId": source_id,
+                    "grossCents": gross,
+                    "feeCents": fee,
+                    "netCents": net,
+                    "settledAt": settled_at,
+                })
+            })
+            .collect(),
+        Err(e) => {
+            warn!("Failed to load transactions of withdrawal {}: {}", withdrawal.id, e);
+            Vec::new()
+        }
+    };
+
+    if let Err(e) = amqp
+        .send_payout_summary_email(
+            withdrawal.id.to_string(),
+            withdrawal.creator_id.clone(),
+            email,
+            withdrawal.amount_cents,
+            withdrawal.currency.clone(),
+            withdrawal.arrival_date.map(|date| date.to_rfc3339()),
+            transactions,
+        )
+        .await
+    {
+        warn!("Failed to queue payout summary for {}: {}", withdrawal.creator_id, e);
+    }
+}
@@ -0,0 +1,352 @@
+use std::path::Path;
+
+use anyhow::{anyhow, Context};
+use serde::{Deserialize, Serialize};
+use tokio::{fs, process::Command};
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+use super::audio::resolve_source;
+use crate::{database::Database, storage};
+
+/// Loudness target for spoken-word podcasts, in LUFS, with the true peak and loudness range
+/// allowed around it.
+const TARGET_LOUDNESS: f64 = -16.0;
+const TARGET_TRUE_PEAK: f64 = -1.5;
+const TARGET_LOUDNESS_RANGE: f64 = 11.0;
+/// Normalized episodes are served as MP3 at this bitrate.
+const OUTPUT_BITRATE: &str = "128k";
+const OUTPUT_SAMPLE_RATE: &str = "44100";
+/// Chapters shorter than this are merged into the one before.
+const MIN_CHAPTER_SECONDS: f64 = 5.0;
+
+/// A chapter start the creator marked, or one embedded in the uploaded file.
+#[derive(Debug, Clone, Deserialize)]
+struct Marker {
+    start: f64,
+    title: String,
+}
+
+/// A chapter as served with the episode, in the Podcasting 2.0 JSON chapters shape.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Chapter {
+    start_time: f64,
+    end_time: f64,
+    title: String,
+}
+
+#[derive(Debug)]
+struct Probe {
+    duration: f64,
+    bitrate_kbps: Option<i32>,
+    markers: Vec<Marker>,
+}
+
+/// The loudness ffmpeg measured on the first `loudnorm` pass.
+#[derive(Debug, Deserialize)]
+struct LoudnessMeasurement {
+    input_i: String,
+    input_tp: String,
+    input_lra: String,
+    input_thresh: String,
+    target_offset: String,
+}
+
+/// Probe an uploaded episode, normalize its loudness, build its chapters and store the result.
+/// The episode is published only once all of that succeeded, and only if the creator asked for
+/// it; episodes scheduled for later are left to `jobs::publishing`. On failure it stays a draft with the error recorded so it can be retried.
+pub async fn process_episode_audio(db: &Database, episode_id: &str) -> anyhow::Result<()> {
+    let episode_id = Uuid::parse_str(episode_id).context("invalid episode id")?;
+    let episode = sqlx::query_as::<_, (String, Option<serde_json::Value>)>(
+        r#"
+        UPDATE podcast_episodes
+        SET processing_status = 'PROCESSING', processing_error = NULL, updated_at = NOW()
+        WHERE id = $1 AND processing_status IN ('PENDING', 'PROCESSING')
+        RETURNING COALESCE(original_audio_url, audio_url), chapter_markers
+        "#,
+    )
+    .bind(episode_id)
+    .fetch_optional(&db.pool)
+    .await?;
+    let Some((source_url, markers)) = episode else {
+        // Deleted, or already processed by an earlier delivery of the same job
+        return Ok(());
+    };
+    let markers = markers
+        .map(serde_json::from_value::<Vec<Marker>>)
+        .transpose()
+        .context("invalid chapter markers")?
+        .unwrap_or_default();
+
+    match process(&source_url, markers).await {
+        Ok((audio_url, probe, loudness, chapters)) => {
+            sqlx::query(
+                r#"
+                UPDATE podcast_episodes
+                SET audio_url = $2,
+                    original_audio_url = $3,
+                    duration = $4,
+                    bitrate_kbps = $5,
+                    loudness_lufs = $6,
+                    chapters = $7,
+                    processing_status = 'READY',
+                    status = CASE WHEN publish_when_ready AND COALESCE(scheduled_for <= NOW(), TRUE) THEN 'PUBLISHED' ELSE status END,
+                    published_at = CASE
+                        WHEN publish_when_ready AND COALESCE(scheduled_for <= NOW(), TRUE) THEN COALESCE(scheduled_for, published_at, NOW())
+                        ELSE published_at
+                    END,
+                    scheduled_for = CASE WHEN publish_when_ready AND COALESCE(scheduled_for <= NOW(), TRUE) THEN NULL ELSE scheduled_for END,
+                    updated_at = NOW()
+                WHERE id = $1
+                "#,
+            )
+            .bind(episode_id)
+            .bind(&audio_url)
+            .bind(&source_url)
+            .bind(probe.duration.round() as i32)
+            .bind(probe.bitrate_kbps)
+            .bind(loudness)
+            .bind(serde_json::to_value(&chapters)?)
+            .execute(&db.pool)
+            .await?;
+
+            info!(
+                "Processed episode {} ({:.1}s, {:?} LUFS before normalizing, {} chapters)",
+                episode_id,
+                probe.duration,
+                loudness,
+                chapters.len()
+            );
+            // A transcript requested during upload waits for the normalized audio
+            if let Err(e) = super::podcast_transcript::transcribe_episode(db, &episode_id.to_string()).await {
+                warn!("Transcript of episode {} failed: {:?}", episode_id, e);
+            }
+            Ok(())
+        }
+        Err(e) => {
+            error!("Audio processing failed for episode {}: {:?}", episode_id, e);
+            sqlx::query(
+                r#"
+                UPDATE podcast_episodes
+                SET processing_status = 'FAILED', processing_error = $2, updated_at = NOW()
+                WHERE id = $1
+                "#,
+            )
+            .bind(episode_id)
+            .bind(e.to_string())
+            .execute(&db.pool)
+            .await?;
+            Err(e)
+        }
+    }
+}
+
+async fn process(
+    source_url: &str,
+    markers: Vec<Marker>,
+) -> anyhow::Result<(String, Probe, Option<f64>, Vec<Chapter>)> {
+    let (path, is_temporary) = resolve_source(source_url).await?;
+    let output = std::env::temp_dir().join(format!("funify-episode-{}.mp3", Uuid::new_v4()));
+    let result = async {
+        let probe = probe(&path).await?;
+        if probe.duration <= 0.0 {
+            return Err(anyhow!("the upload has no audio"));
+        }
+        let measurement = measure_loudness(&path).await?;
+        normalize(&path, &output, &measurement).await?;
+        let bytes = fs::read(&output).await?;
+        let audio_url = storage::store_public_file("podcasts", "mp3", "audio/mpeg", bytes).await?;
+
+        let loudness = measurement.input_i.parse::<f64>().ok().filter(|lufs| lufs.is_finite());
+        // Markers the creator gave take precedence over ones embedded in the file
+        let markers = if markers.is_empty() { probe.markers.clone() } else { markers };
+        let chapters = build_chapters(markers, probe.duration);
+        Ok((audio_url, probe, loudness, chapters))
+    }
+    .await;
+
+    let _ = fs::remove_file(&output).await;
+    if is_temporary {
+        let _ = fs::remove_file(&path).await;
+    }
+    result
+}
+
+async fn probe(path: &Path) -> anyhow::Result<Probe> {
+    let output = Command::new("ffprobe")
+        .args([
+            "-v",
+            "error",
+            "-show_entries",
+            "format=duration,bit_rate",
+            "-show_chapters",
+            "-of",
+            "json",
+        ])
+        .arg(path)
+        .output()
+        .await
+        .context("failed to run ffprobe")?;
+
+    if !output.status.success() {
+        return Err(anyhow!(
+            "ffprobe failed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        ));
+    }
+
+    let report: serde_json::Value =
+        serde_json::from_slice(&output.stdout).context("ffprobe returned invalid JSON")?;
+    let number = |value: Option<&serde_json::Value>| {
+        value
+            .and_then(|value| value.as_str())
+            .and_then(|value| value.parse::<f64>().ok())
+    };
+    let duration = number(report.pointer("/format/duration"))
+        .ok_or_else(|| anyhow!("ffprobe reported no duration"))?;
+    let bitrate_kbps = number(report.pointer("/format/bit_rate")).map(|bits| (bits / 1000.0).round() as i32);
+    let markers = report
+        .get("chapters")
+        .and_then(|chapters| chapters.as_array())
+        .map(|chapters| {
+            chapters
+                .iter()
+                .filter_map(|chapter| {
+                    Some(Marker {
+                        start: number(chapter.get("start_time"))?,
+                        title: chapter
+                            .pointer("/tags/title")
+                            .and_then(|title| title.as_str())
+                            .unwrap_or_default()
+                            .to_string(),
+                    })
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    Ok(Probe {
+        duration,
+        bitrate_kbps,
+        markers,
+    })
+}
+
+fn loudnorm_target() -> String {
+    format!(
+        "loudnorm=I={}:TP={}:LRA={}",
+        TARGET_LOUDNESS, TARGET_TRUE_PEAK, TARGET_LOUDNESS_RANGE
+    )
+}
+
+/// First `loudnorm` pass: measure the input without writing anything.
+async fn measure_loudness(path: &Path) -> anyhow::Result<LoudnessMeasurement> {
+    let output = Command::new("ffmpeg")
+        .args(["-hide_banner", "-nostats", "-i"])
+        .arg(path)
+        .args([
+            "-af",
+            &format!("{}:print_format=json", loudnorm_target()),
+            "-f",
+            "null",
+            "-",
+        ])
+        .output()
+        .await
+        .context("failed to run ffmpeg")?;
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    if !output.status.success() {
+        return Err(anyhow!("ffmpeg loudness measurement failed: {}", stderr));
+    }
+    // The measurement is the last JSON object ffmpeg prints
+    let report = stderr
+        .rfind('{')
+        .and_then(|start| stderr[start..].rfind('}').map(|end| &stderr[start..=start + end]))
+        .ok_or_else(|| anyhow!("ffmpeg printed no loudness measurement"))?;
+    serde_json::from_str(report).context("ffmpeg printed an invalid loudness measurement")
+}
+
+/// Second `loudnorm` pass with the measured values, so the gain is applied linearly.
+async fn normalize(input: &Path, output: &Path, measurement: &LoudnessMeasurement) -> anyhow::Result<()> {
+    let filter = format!(
+        "{}:measured_I={}:measured_TP={}:measured_LRA={}:measured_thresh={}:offset={}:linear=true",
+        loudnorm_target(),
+        measurement.input_i,
+        measurement.input_tp,
+        measurement.input_lra,
+        measurement.input_thresh,
+        measurement.target_offset
+    );
+    let result = Command::new("ffmpeg")
+        .args(["-hide_banner", "-nostats", "-y", "-i"])
+        .arg(input)
+        .args([
+            "-af",
+            &filter,
+            "-ar",
+            OUTPUT_SAMPLE_RATE,
+            "-codec:a",
+            "libmp3lame",
+            "-b:a",
+            OUTPUT_BITRATE,
+            "-map_metadata",
+            "0",
+        ])
+        .arg(output)
+        .output()
+        .await
+        .context("failed to run ffmpeg")?;
+
+    if !result.status.success() {
+        return Err(anyhow!(
+            "ffmpeg normalization failed: {}",
+            String::from_utf8_lossy(&result.stderr)
+        ));
+    }
+    Ok(())
+}
+
+/// Turn chapter starts into chapters running up to the next start or the end of the episode.
+/// Starts past the end are dropped, and a first chapter is added at zero when the markers
+/// begin later.
+fn build_chapters(mut markers: Vec<Marker>, duration: f64) -> Vec<Chapter> {
+    markers.retain(|marker| marker.start >= 0.0 && marker.start < duration);
+    if markers.is_empty() {
+        return Vec::new();
+    }
+    markers.sort_by(|a, b| a.start.total_cmp(&b.start));
+    if markers[0].start >= MIN_CHAPTER_SECONDS {
+        markers.insert(
+            0,
+            Marker {
+                start: 0.0,
+                title: "Introduction".to_string(),
+            },
+        );
+    }
+
+    let mut chapters: Vec<Chapter> = Vec::with_capacity(markers.len());
+    for marker in markers {
+        if let Some(previous) = chapters.last() {
+            if marker.start - previous.start_time < MIN_CHAPTER_SECONDS {
+                continue;
+            }
+        }
+        if let Some(previous) = chapters.last_mut() {
+            previous.end_time = marker.start;
+        }
+        let title = marker.title.trim();
+        chapters.push(Chapter {
+            start_time: marker.start,
+            end_time: duration,
+            title: if title.is_empty() {
+                format!("Chapter {}", chapters.len() + 1)
+            } else {
+                title.to_string()
+            },
+        });
+    }
+    chapters
+}
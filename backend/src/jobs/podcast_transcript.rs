@@ -0,0 +1,106 @@
+use anyhow::Context;
+use tokio::fs;
+use tracing::{error, info};
+use uuid::Uuid;
+
+use super::audio::resolve_source;
+use crate::{config::TranscriptionConfig, database::Database, transcription};
+
+/// Transcribe an episode for which an automatic transcript was requested. Episodes whose audio
+/// is still being processed are left alone; processing transcribes them once it is done.
+pub async fn transcribe_episode(db: &Database, episode_id: &str) -> anyhow::Result<()> {
+    let episode_id = Uuid::parse_str(episode_id).context("invalid episode id")?;
+    let audio_url = sqlx::query_scalar::<_, String>(
+        r#"
+        UPDATE podcast_transcripts t
+        SET status = 'PROCESSING', error = NULL, updated_at = NOW()
+        FROM podcast_episodes e
+        WHERE t.episode_id = $1
+          AND e.id = t.episode_id
+          AND t.source = 'AUTO'
+          AND t.status IN ('PENDING', 'PROCESSING')
+          AND COALESCE(e.processing_status, 'READY') = 'READY'
+        RETURNING e.audio_url
+        "#,
+    )
+    .bind(episode_id)
+    .fetch_optional(&db.pool)
+    .await?;
+    let Some(audio_url) = audio_url else {
+        return Ok(());
+    };
+
+    match transcribe(&audio_url).await {
+        Ok((provider, transcript)) => {
+            sqlx::query(
+                r#"
+                UPDATE podcast_transcripts
+                SET status = 'READY',
+                    provider = $2,
+                    language = $3,
+                    text = $4,
+                    segments = $5,
+                    updated_at = NOW()
+                WHERE episode_id = $1 AND source = 'AUTO'
+                "#,
+            )
+            .bind(episode_id)
+            .bind(provider)
+            .bind(&transcript.language)
+            .bind(&transcript.text)
+            .bind(serde_json::to_value(&transcript.segments)?)
+            .execute(&db.pool)
+            .await?;
+
+            info!(
+                "Transcribed episode {} with {} ({} segments)",
+                episode_id,
+                provider,
+                transcript.segments.len()
+            );
+            Ok(())
+        }
+        Err(e) => {
+            error!("Transcription failed for episode {}: {:?}", episode_id, e);
+            sqlx::query(
+                r#"
+                UPDATE podcast_transcripts
+                SET status = 'FAILED', error = $2, updated_at = NOW()
+                WHERE episode_id = $1 AND source = 'AUTO'
+                "#,
+            )
+            .bind(episode_id)
+            .bind(e.to_string())
+            .execute(&db.pool)
+            .await?;
+            Err(e)
+        }
+    }
+}
+
+async fn transcribe(audio_url: &str) -> anyhow::Result<(&'static str, transcription::Transcript)> {
+    let provider = transcription::provider(&TranscriptionConfig::from_env())?
+        .context("no transcription provider is configured")?;
+    let (path, is_temporary) = resolve_source(audio_url).await?;
+    let audio = fs::read(&path).await;
+    if is_temporary {
+        let _ = fs::remove_file(&path).await;
+    }
+
+    // Downloaded copies have no extension, so go by the URL
+    let extension = audio_url
+        .split(['?', '#'])
+        .next()
+        .and_then(|path| path.rsplit_once('.'))
+        .map(|(_, ext)| ext.to_ascii_lowercase());
+    let content_type = match extension.as_deref() {
+        Some("mp3") => "audio/mpeg",
+        Some("m4a") | Some("mp4") => "audio/mp4",
+        Some("wav") => "audio/wav",
+        Some("ogg") => "audio/ogg",
+        Some("flac") => "audio/flac",
+        _ => "application/octet-stream",
+    };
+    let transcript = provider.transcribe(audio?, content_type).await?;
+    Ok((provider.name(), transcript))
+}
@@ -0,0 +1,58 @@
+use serde_json::json;
+use tracing::info;
+use uuid::Uuid;
+
+use crate::{
+    database::Database,
+    notification_service::{notify, NotificationKind},
+};
+
+/// Release pre-order products whose release date has passed: their files open up to buyers on
+/// their own, so this tells everyone who pre-ordered that the download is ready. Each product is
+/// claimed by stamping `released_at` first, so buyers hear about it once.
+pub async fn release_preorders(db: &Database) -> anyhow::Result<()> {
+    let released = sqlx::query_as::<_, (Uuid, String, String)>(
+        r#"
+        UPDATE products
+        SET released_at = NOW()
+        WHERE release_at IS NOT NULL AND release_at <= NOW() AND released_at IS NULL
+        RETURNING id, name, user_id
+        "#,
+    )
+    .fetch_all(&db.pool)
+    .await?;
+
+    for (product_id, name, creator_id) in &released {
+        let buyers = sqlx::query_scalar::<_, String>(
+            r#"
+            SELECT DISTINCT pu.user_id
+            FROM purchases pu
+            JOIN products pr ON pr.id = pu.product_id
+            WHERE pu.product_id = $1
+              AND UPPER(pu.status) = 'COMPLETED'
+              AND pu.created_at < pr.release_at
+              AND pu.user_id <> $2
+            "#,
+        )
+        .bind(product_id)
+        .bind(creator_id)
+        .fetch_all(&db.pool)
+        .await?;
+
+        for user_id in &buyers {
+            notify(
+                db,
+                user_id,
+                NotificationKind::ProductUpdate,
+                json!({
+                    "message": format!("{} is out now and ready to download", name),
+                    "link": format!("/products/{}", product_id),
+                    "productId": product_id,
+                }),
+            )
+            .await;
+        }
+        info!("Released pre-order product {} to {} buyers", product_id, buyers.len());
+    }
+    Ok(())
+}
@@ -0,0 +1,77 @@
+use tracing::info;
+
+use crate::database::Database;
+
+/// Open draft polls whose post has gone live and close polls whose post was taken down again.
+/// Scheduled posts become visible on their own once `published_at` passes; this tick keeps the
+/// polls attached to them in step.
+pub async fn sync_scheduled_polls(db: &Database) -> anyhow::Result<()> {
+    let opened = sqlx::query(
+        r#"
+        UPDATE polls p
+        SET status = 'OPEN',
+            closes_at = CASE
+                WHEN p.duration_minutes IS NOT NULL
+                    THEN NOW() + make_interval(mins => p.duration_minutes)
+                ELSE p.closes_at
+            END,
+            updated_at = NOW()
+        FROM posts po
+        WHERE po.id = p.post_id
+          AND p.status = 'DRAFT'
+          AND COALESCE(po.published, TRUE)
+          AND COALESCE(po.published_at, po.created_at) <= NOW()
+        "#,
+    )
+    .execute(&db.pool)
+    .await?
+    .rows_affected();
+
+    let closed = sqlx::query(
+        r#"
+        UPDATE polls p
+        SET closes_at = NOW(), updated_at = NOW()
+        FROM posts po
+        WHERE po.id = p.post_id
+          AND p.status = 'OPEN'
+          AND (p.closes_at IS NULL OR p.closes_at > NOW())
+          AND NOT (
+              COALESCE(po.published, TRUE)
+              AND COALESCE(po.published_at, po.created_at) <= NOW()
+          )
+        "#,
+    )
+    .execute(&db.pool)
+    .await?
+    .rows_affected();
+
+    if opened > 0 || closed > 0 {
+        info!("Scheduled polls: {} opened, {} closed", opened, closed);
+    }
+    Ok(())
+}
+
+/// Release podcast episodes whose scheduled time has come. Uploaded episodes still being
+/// processed wait for their audio; `jobs::podcast_audio` publishes them once it is ready.
+pub async fn publish_scheduled_episodes(db: &Database) -> anyhow::Result<()> {
+    let published = sqlx::query(
+        r#"
+        UPDATE podcast_episodes
+        SET status = 'PUBLISHED',
+            published_at = scheduled_for,
+            scheduled_for = NULL,
+            updated_at = NOW()
+        WHERE status = 'DRAFT'
+          AND scheduled_for <= NOW()
+          AND COALESCE(processing_status, 'READY') = 'READY'
+        "#,
+    )
+    .execute(&db.pool)
+    .await?
+    .rows_affected();
+
+    if published > 0 {
+        info!("Scheduled podcast episodes: {} published", published);
+    }
+    Ok(())
+}
@@ -18,22 +18,51 @@ use tower_http::{
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
 mod amqp_client;
+mod article_views;
 mod auth;
 mod config;
 mod database;
+mod email_service;
+mod feed_timeline;
+mod ics;
+mod invoice_pdf;
+mod jobs;
+mod messaging;
 mod middleware;
 mod models;
+mod notification_service;
+mod pagination;
+mod payment_providers;
+#[cfg(feature = "payments-sandbox")]
+mod payments_sandbox;
+mod podcast_feed;
+mod post_views;
+mod realtime;
 mod redis_client;
+mod remote_fetch;
 mod routes;
+mod storage;
+mod thumbnails;
+mod transcription;
 
 use config::Config;
 use database::Database;
 use routes::{
     analytics::analytics_routes, articles::articles_routes, auth::auth_routes,
-    campaigns::campaign_routes, creators::creator_routes, events::event_routes, feed::feed_routes,
-    podcasts::podcast_routes, posts::post_routes, products::product_routes,
-    purchases::purchase_routes, referrals::referral_routes, search::search_routes,
-    uploads::upload_routes, users::user_routes,
+    campaigns::campaign_routes, cart::cart_routes, coinbase::coinbase_routes,
+    creators::creator_routes, discounts::discount_routes, disputes::dispute_routes, emails::{email_routes, suppression_routes},
+    events::event_routes, explore::explore_pin_routes, feed::feed_routes,
+    fees::fee_routes, ledger::ledger_routes, licenses::license_routes, message_moderation::message_moderation_routes,
+    metrics::metrics_routes,
+    messages::message_routes,
+    notifications::notification_routes,
+    payments::payment_routes,
+    podcasts::podcast_routes, polls::poll_routes, posts::post_routes, products::product_routes,
+    purchases::purchase_routes, referral_fraud::referral_fraud_routes, referrals::referral_routes, search::search_routes,
+    series::series_routes, shipping::shipping_routes, storefront::storefront_routes, stripe::stripe_routes, subscriptions::subscription_routes,
+    tax::tax_routes, taxonomy::{category_routes, tag_routes},
+    uploads::upload_routes,
+    users::user_routes, withdrawals::withdrawal_routes, ws::ws_routes,
 };
 
 #[tokio::main]
@@ -49,6 +78,9 @@ async fn main() -> anyhow::Result<()> {
 
     // Load configuration
     let config = Config::from_env()?;
+    if config.payments_sandbox && !cfg!(feature = "payments-sandbox") {
+        anyhow::bail!("PAYMENTS_MODE=sandbox needs a build with the payments-sandbox feature");
+    }
 
     // Initialize database with Redis and CloudAMQP
     let db = Database::with_all(&config.database_url, &config.redis_url, &config.cloud_amqp_url).await?;
@@ -58,6 +90,9 @@ async fn main() -> anyhow::Result<()> {
         tracing::error!("Database migrations failed: {}", error);
     }
 
+    // Start background job consumers
+    jobs::spawn_workers(db.clone());
+
     // Prepare upload directories
     let upload_dir = std::env::var("UPLOAD_DIR").unwrap_or_else(|_| "uploads".to_string());
     let upload_path = PathBuf::from(&upload_dir);
@@ -106,16 +141,51 @@ async fn main() -> anyhow::Result<()> {
         .nest("/api/purchases", purchase_routes())
         .nest("/api/analytics", analytics_routes())
         .nest("/api/campaigns", campaign_routes())
+        .nest("/api/cart", cart_routes())
+        .nest("/api/coinbase", coinbase_routes())
+        .nest("/api/emails", email_routes())
         .nest("/api/events", event_routes())
         .nest("/api/feed", feed_routes())
+        .nest("/api/admin/disputes", dispute_routes())
+        .nest("/api/admin/email-suppressions", suppression_routes())
+        .nest("/api/admin/explore", explore_pin_routes())
+        .nest("/api/admin/fees", fee_routes())
+        .nest("/api/admin/ledger", ledger_routes())
+        .nest("/api/admin/messages", message_moderation_routes())
+        .nest("/api/admin/metrics", metrics_routes())
+        .nest("/api/admin/referral-flags", referral_fraud_routes())
+        .nest("/api/licenses", license_routes())
+        .nest("/api/discounts", discount_routes())
+        .nest("/api/storefront", storefront_routes())
+        .nest("/api/shipping", shipping_routes())
         .nest("/api/articles", articles_routes())
+        .nest("/api/categories", category_routes())
+        .nest("/api/tags", tag_routes())
+        .nest("/api/series", series_routes())
         .nest("/api/referrals", referral_routes())
         .nest("/api/podcasts", podcast_routes())
+        .nest("/api/polls", poll_routes())
         .nest("/api/search", search_routes())
+        .nest("/api/stripe", stripe_routes())
+        .nest("/api/tax", tax_routes())
         .nest("/api/upload", upload_routes())
-        .route("/api/notifications", get(get_notifications))
-        .route("/api/subscriptions/my-subscribers", get(get_my_subscribers))
-        .nest_service("/uploads", uploads_service)
+        .nest("/api/withdrawals", withdrawal_routes())
+        .nest("/api/payments", payment_routes())
+        .nest("/api/messages", message_routes())
+        .nest("/api/notifications", notification_routes())
+        .nest("/api/subscriptions", subscription_routes())
+        .merge(ws_routes())
+        .nest_service("/uploads", uploads_service);
+
+    #[cfg(feature = "payments-sandbox")]
+    let app = if config.payments_sandbox {
+        tracing::warn!("Payments sandbox enabled: Stripe calls go to the mock provider");
+        app.nest("/api/sandbox/stripe", payments_sandbox::sandbox_routes())
+    } else {
+        app
+    };
+
+    let app = app
         .layer(
             ServiceBuilder::new()
                 .layer(CompressionLayer::new()) // Compress responses (gzip, br, deflate)
@@ -166,25 +236,3 @@ async fn redis_stats(State(db): State<Database>) -> Result<Json<serde_json::Valu
         })))
     }
 }
-
-async fn get_notifications() -> Result<Json<serde_json::Value>, StatusCode> {
-    // Mock notifications for now
-    let response = serde_json::json!({
-        "success": true,
-        "data": []
-    });
-
-    Ok(Json(response))
-}
-
-async fn get_my_subscribers() -> Result<Json<serde_json::Value>, StatusCode> {
-    // Mock subscribers for now
-    let response = serde_json::json!({
-        "success": true,
-        "data": {
-            "subscriptions": []
-        }
-    });
-
-    Ok(Json(response))
-}
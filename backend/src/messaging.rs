@@ -0,0 +1,685 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tracing::warn;
+use uuid::Uuid;
+
+use crate::{
+    database::Database,
+    notification_service::{notify, NotificationKind},
+    realtime::{self, publish_counters, LiveEvent},
+    storage,
+};
+
+/// Characters of a message quoted in the notification its recipient gets when offline.
+const PREVIEW_CHARS: usize = 80;
+
+/// A direct message as clients see it, live and in listings.
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct DirectMessage {
+    pub id: Uuid,
+    pub conversation_id: Uuid,
+    pub sender_id: String,
+    pub body: String,
+    pub created_at: DateTime<Utc>,
+}
+
+/// A file sent with a message. Files themselves are only reachable through signed URLs handed
+/// to participants.
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct MessageAttachment {
+    pub id: Uuid,
+    #[serde(skip)]
+    pub message_id: Option<Uuid>,
+    pub file_name: String,
+    pub content_type: String,
+    pub size_bytes: i64,
+    pub width: Option<i32>,
+    pub height: Option<i32>,
+    pub has_thumbnail: bool,
+}
+
+/// How far a participant got with a conversation's messages.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReceiptStatus {
+    Delivered,
+    Read,
+}
+
+impl ReceiptStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ReceiptStatus::Delivered => "delivered",
+            ReceiptStatus::Read => "read",
+        }
+    }
+}
+
+/// Who may message a user directly; stored in `dm_settings.policy`. Messages from anyone else go
+/// to the user's message requests.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DmPolicy {
+    #[default]
+    Everyone,
+    /// Followers and members on any tier.
+    Members,
+    /// Members on a paid tier.
+    Paid,
+    Nobody,
+}
+
+impl DmPolicy {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            DmPolicy::Everyone => "EVERYONE",
+            DmPolicy::Members => "MEMBERS",
+            DmPolicy::Paid => "PAID",
+            DmPolicy::Nobody => "NOBODY",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "EVERYONE" => Some(DmPolicy::Everyone),
+            "MEMBERS" => Some(DmPolicy::Members),
+            "PAID" => Some(DmPolicy::Paid),
+            "NOBODY" => Some(DmPolicy::Nobody),
+            _ => None,
+        }
+    }
+}
+
+/// Where a conversation sits for one participant; stored in
+/// `conversation_participants.request_status`, where no value means it was never a request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RequestStatus {
+    Pending,
+    Accepted,
+    Declined,
+}
+
+impl RequestStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            RequestStatus::Pending => "PENDING",
+            RequestStatus::Accepted => "ACCEPTED",
+            RequestStatus::Declined => "DECLINED",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "PENDING" => Some(RequestStatus::Pending),
+            "ACCEPTED" => Some(RequestStatus::Accepted),
+            "DECLINED" => Some(RequestStatus::Declined),
+            _ => None,
+        }
+    }
+}
+
+/// What a client may send up its socket: `{"type": "typing" | "delivered" | "read",
+/// "conversationId": ..., "typing": bool}`. `typing` only matters for typing events and
+/// defaults to true.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ClientEvent {
+    #[serde(rename = "type")]
+    kind: String,
+    conversation_id: Uuid,
+    typing: Option<bool>,
+}
+
+pub async fn is_participant(
+    db: &Database,
+    conversation_id: Uuid,
+    user_id: &str,
+) -> Result<bool, sqlx::Error> {
+    sqlx::query_scalar::<_, bool>(
+        r#"
+        SELECT EXISTS(
+            SELECT 1 FROM conversation_participants WHERE conversation_id = $1 AND user_id = $2
+        )
+        "#,
+    )
+    .bind(conversation_id)
+    .bind(user_id)
+    .fetch_one(&db.pool)
+    .await
+}
+
+/// Everyone else in the conversation who has it in their inbox. Message requests are left out, so
+/// nothing is pushed to someone who hasn't accepted the conversation.
+async fn other_participants(
+    db: &Database,
+    conversation_id: Uuid,
+    user_id: &str,
+) -> Result<Vec<String>, sqlx::Error> {
+    sqlx::query_scalar::<_, String>(
+        r#"
+        SELECT user_id FROM conversation_participants
+        WHERE conversation_id = $1 AND user_id <> $2
+          AND COALESCE(request_status, 'ACCEPTED') = 'ACCEPTED'
+        "#,
+    )
+    .bind(conversation_id)
+    .bind(user_id)
+    .fetch_all(&db.pool)
+    .await
+}
+
+/// What the blocked-words filter does with a message containing a word; stored in
+/// `blocked_words.action`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FilterAction {
+    /// Refuse the message.
+    Block,
+    /// Send it, but report it for review.
+    Flag,
+}
+
+impl FilterAction {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            FilterAction::Block => "BLOCK",
+            FilterAction::Flag => "FLAG",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "BLOCK" => Some(FilterAction::Block),
+            "FLAG" => Some(FilterAction::Flag),
+            _ => None,
+        }
+    }
+}
+
+/// Lowercase words separated by single spaces, padded with a space on each side so whole words
+/// and phrases can be found with `contains`.
+pub fn normalize_words(text: &str) -> String {
+    let mut normalized = String::from(" ");
+    for word in text
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+    {
+        normalized.push_str(&word.to_lowercase());
+        normalized.push(' ');
+    }
+    normalized
+}
+
+/// Run a message body through the blocked-words filter: the strictest action among the words it
+/// contains, with the word that triggered it. Words match whole, ignoring case and punctuation.
+pub async fn screen_message(
+    db: &Database,
+    body: &str,
+) -> Result<Option<(FilterAction, String)>, sqlx::Error> {
+    let words = sqlx::query_as::<_, (String, String)>("SELECT word, action FROM blocked_words")
+        .fetch_all(&db.pool)
+        .await?;
+    let normalized = normalize_words(body);
+    let mut verdict: Option<(FilterAction, String)> = None;
+    for (word, action) in words {
+        let Some(action) = FilterAction::parse(&action) else {
+            continue;
+        };
+        if !normalized.contains(&normalize_words(&word)) {
+            continue;
+        }
+        if action == FilterAction::Block {
+            return Ok(Some((action, word)));
+        }
+        verdict.get_or_insert((action, word));
+    }
+    Ok(verdict)
+}
+
+/// Whether an admin suspended `user_id` from sending messages.
+pub async fn messaging_suspended(db: &Database, user_id: &str) -> Result<bool, sqlx::Error> {
+    sqlx::query_scalar::<_, bool>(
+        r#"
+        SELECT EXISTS(
+            SELECT 1 FROM users
+            WHERE id = $1 AND messaging_suspended_at IS NOT NULL
+              AND COALESCE(messaging_suspended_until > NOW(), TRUE)
+        )
+        "#,
+    )
+    .bind(user_id)
+    .fetch_one(&db.pool)
+    .await
+}
+
+/// Queue a message the filter flagged for admins to review.
+pub async fn flag_message(db: &Database, message: &DirectMessage, word: &str) {
+    let flagged = sqlx::query(
+        r#"
+        INSERT INTO message_reports (conversation_id, message_id, reported_user_id, reason, details)
+        VALUES ($1, $2, $3, 'AUTO_FILTER', $4)
+        "#,
+    )
+    .bind(message.conversation_id)
+    .bind(message.id)
+    .bind(&message.sender_id)
+    .bind(format!("Contains \"{}\"", word))
+    .execute(&db.pool)
+    .await;
+    if let Err(e) = flagged {
+        warn!("Failed to flag message {}: {}", message.id, e);
+    }
+}
+
+/// The direct conversation between two users, created on first use. There is one per pair,
+/// whoever starts it.
+pub async fn open_direct_conversation(
+    db: &Database,
+    user_id: &str,
+    other_id: &str,
+) -> Result<Uuid, sqlx::Error> {
+    let mut pair = [user_id, other_id];
+    pair.sort_unstable();
+    let direct_key = pair.join(":");
+
+    let mut tx = db.pool.begin().await?;
+    let conversation_id = sqlx::query_scalar::<_, Uuid>(
+        r#"
+        INSERT INTO conversations (direct_key) VALUES ($1)
+        ON CONFLICT (direct_key) DO UPDATE SET direct_key = EXCLUDED.direct_key
+        RETURNING id
+        "#,
+    )
+    .bind(&direct_key)
+    .fetch_one(&mut tx)
+    .await?;
+    sqlx::query(
+        r#"
+        INSERT INTO conversation_participants (conversation_id, user_id)
+        SELECT $1, UNNEST($2::TEXT[])
+        ON CONFLICT DO NOTHING
+        "#,
+    )
+    .bind(conversation_id)
+    .bind(&pair[..])
+    .execute(&mut tx)
+    .await?;
+    tx.commit().await?;
+    Ok(conversation_id)
+}
+
+/// Post a message on the sender's behalf from outside a request, e.g. from a scheduled job. The
+/// caller delivers it.
+pub async fn post_message(
+    db: &Database,
+    conversation_id: Uuid,
+    sender_id: &str,
+    body: &str,
+) -> Result<DirectMessage, sqlx::Error> {
+    let mut tx = db.pool.begin().await?;
+    let message = sqlx::query_as::<_, DirectMessage>(
+        r#"
+        INSERT INTO messages (conversation_id, sender_id, body)
+        VALUES ($1, $2, $3)
+        RETURNING id, conversation_id, sender_id, body, created_at
+        "#,
+    )
+    .bind(conversation_id)
+    .bind(sender_id)
+    .bind(body)
+    .fetch_one(&mut tx)
+    .await?;
+    sqlx::query("UPDATE conversations SET last_message_at = $2 WHERE id = $1")
+        .bind(conversation_id)
+        .bind(message.created_at)
+        .execute(&mut tx)
+        .await?;
+    sqlx::query(
+        r#"
+        UPDATE conversation_participants SET last_read_at = $3, last_delivered_at = $3
+        WHERE conversation_id = $1 AND user_id = $2
+        "#,
+    )
+    .bind(conversation_id)
+    .bind(sender_id)
+    .bind(message.created_at)
+    .execute(&mut tx)
+    .await?;
+    tx.commit().await?;
+    Ok(message)
+}
+
+/// The policy `user_id` set for direct messages.
+pub async fn dm_policy(db: &Database, user_id: &str) -> Result<DmPolicy, sqlx::Error> {
+    let policy =
+        sqlx::query_scalar::<_, String>("SELECT policy FROM dm_settings WHERE user_id = $1")
+            .bind(user_id)
+            .fetch_optional(&db.pool)
+            .await?;
+    Ok(policy.and_then(|policy| DmPolicy::parse(&policy)).unwrap_or_default())
+}
+
+/// Whether `recipient_id`'s policy lets `sender_id` message them straight to their inbox.
+pub async fn may_message(
+    db: &Database,
+    sender_id: &str,
+    recipient_id: &str,
+) -> Result<bool, sqlx::Error> {
+    let paid_only = match dm_policy(db, recipient_id).await? {
+        DmPolicy::Everyone => return Ok(true),
+        DmPolicy::Nobody => return Ok(false),
+        DmPolicy::Members => false,
+        DmPolicy::Paid => true,
+    };
+    sqlx::query_scalar::<_, bool>(
+        r#"
+        SELECT EXISTS(
+            SELECT 1 FROM subscriptions
+            WHERE user_id = $1 AND creator_id = $2 AND UPPER(status) = 'ACTIVE'
+              AND (NOT $3 OR stripe_subscription_id IS NOT NULL)
+        ) OR (
+            NOT $3
+            AND EXISTS(SELECT 1 FROM follows WHERE follower_id = $1 AND following_id = $2)
+        )
+        "#,
+    )
+    .bind(sender_id)
+    .bind(recipient_id)
+    .bind(paid_only)
+    .fetch_one(&db.pool)
+    .await
+}
+
+/// Push a message just sent to everyone in its conversation, including the sender's other
+/// sockets. A recipient with a socket open has it delivered there and then; one without gets an
+/// in-app notification instead, one per conversation until they read it.
+pub async fn deliver_message(
+    db: &Database,
+    message: &DirectMessage,
+    attachments: &[MessageAttachment],
+    sender_name: &str,
+) {
+    let mut data = json!(message);
+    data["attachments"] = json!(attachments);
+    data["senderName"] = json!(sender_name);
+
+    let recipients = match other_participants(db, message.conversation_id, &message.sender_id).await
+    {
+        Ok(recipients) => recipients,
+        Err(e) => {
+            warn!("Failed to load participants of {}: {}", message.conversation_id, e);
+            return;
+        }
+    };
+    realtime::publish(db, &message.sender_id, LiveEvent::Message, data.clone()).await;
+    for recipient in recipients {
+        let sockets = realtime::publish(db, &recipient, LiveEvent::Message, data.clone()).await;
+        if sockets > 0 {
+            mark_delivered(db, message.conversation_id, &recipient, message.created_at).await;
+            publish_counters(db, &recipient).await;
+        } else {
+            notify_offline(db, &recipient, message, attachments, sender_name).await;
+        }
+    }
+}
+
+async fn notify_offline(
+    db: &Database,
+    user_id: &str,
+    message: &DirectMessage,
+    attachments: &[MessageAttachment],
+    sender_name: &str,
+) {
+    let pending = sqlx::query_scalar::<_, bool>(
+        r#"
+        SELECT EXISTS(
+            SELECT 1 FROM notifications
+            WHERE user_id = $1 AND kind = $2 AND read_at IS NULL
+              AND payload->>'conversationId' = $3
+        )
+        "#,
+    )
+    .bind(user_id)
+    .bind(NotificationKind::Message.as_str())
+    .bind(message.conversation_id.to_string())
+    .fetch_one(&db.pool)
+    .await
+    .unwrap_or(false);
+    if pending {
+        return;
+    }
+
+    let mut preview: String = message.body.chars().take(PREVIEW_CHARS).collect();
+    if message.body.chars().count() > PREVIEW_CHARS {
+        preview.push('…');
+    }
+    if preview.is_empty() {
+        preview = match attachments.len() {
+            1 => format!("sent {}", attachments[0].file_name),
+            count => format!("sent {} files", count),
+        };
+    }
+    notify(
+        db,
+        user_id,
+        NotificationKind::Message,
+        json!({
+            "message": format!("{}: {}", sender_name, preview),
+            "link": format!("/messages/{}", message.conversation_id),
+            "conversationId": message.conversation_id,
+            "messageId": message.id,
+            "senderId": message.sender_id,
+        }),
+    )
+    .await;
+}
+
+/// Bring group conversations in line with their creator's active members: new members join and
+/// lapsed ones leave. Only `creator_id`'s groups when given. The creator always stays, and people
+/// they removed aren't added back.
+pub async fn sync_group_members(db: &Database, creator_id: Option<&str>) -> anyhow::Result<()> {
+    sqlx::query(
+        r#"
+        INSERT INTO conversation_participants (conversation_id, user_id)
+        SELECT DISTINCT c.id, s.user_id
+        FROM conversations c
+        JOIN subscriptions s
+          ON s.creator_id = c.creator_id AND UPPER(s.status) = 'ACTIVE'
+         AND (c.tier_id IS NULL OR s.tier_id = c.tier_id)
+        WHERE c.creator_id IS NOT NULL AND ($1::TEXT IS NULL OR c.creator_id = $1)
+          AND NOT EXISTS (
+              SELECT 1 FROM conversation_bans b
+              WHERE b.conversation_id = c.id AND b.user_id = s.user_id
+          )
+        ON CONFLICT DO NOTHING
+        "#,
+    )
+    .bind(creator_id)
+    .execute(&db.pool)
+    .await?;
+
+    sqlx::query(
+        r#"
+        DELETE FROM conversation_participants p
+        USING conversations c
+        WHERE p.conversation_id = c.id AND c.creator_id IS NOT NULL
+          AND ($1::TEXT IS NULL OR c.creator_id = $1)
+          AND p.user_id <> c.creator_id
+          AND NOT EXISTS (
+              SELECT 1 FROM subscriptions s
+              WHERE s.user_id = p.user_id AND s.creator_id = c.creator_id
+                AND UPPER(s.status) = 'ACTIVE'
+                AND (c.tier_id IS NULL OR s.tier_id = c.tier_id)
+          )
+        "#,
+    )
+    .bind(creator_id)
+    .execute(&db.pool)
+    .await?;
+    Ok(())
+}
+
+/// Delete attachments uploaded more than a day ago and never sent, files included.
+pub async fn delete_unsent_attachments(db: &Database) -> anyhow::Result<()> {
+    let keys = sqlx::query_as::<_, (String, Option<String>)>(
+        r#"
+        DELETE FROM message_attachments
+        WHERE message_id IS NULL AND created_at < NOW() - INTERVAL '1 day'
+        RETURNING storage_key, thumbnail_key
+        "#,
+    )
+    .fetch_all(&db.pool)
+    .await?;
+
+    for (storage_key, thumbnail_key) in keys {
+        for key in std::iter::once(storage_key).chain(thumbnail_key) {
+            if let Err(e) = storage::delete_private_file(&key).await {
+                warn!("Failed to delete unsent attachment {}: {:?}", key, e);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Record that `user_id` received the conversation's messages up to `up_to`, and tell the
+/// others. Receipts only ever move forward.
+pub async fn mark_delivered(
+    db: &Database,
+    conversation_id: Uuid,
+    user_id: &str,
+    up_to: DateTime<Utc>,
+) {
+    let updated = sqlx::query(
+        r#"
+        UPDATE conversation_participants SET last_delivered_at = $3
+        WHERE conversation_id = $1 AND user_id = $2
+          AND COALESCE(last_delivered_at, '-infinity') < $3
+        "#,
+    )
+    .bind(conversation_id)
+    .bind(user_id)
+    .bind(up_to)
+    .execute(&db.pool)
+    .await;
+    match updated {
+        Ok(result) if result.rows_affected() > 0 => {
+            publish_receipt(db, conversation_id, user_id, ReceiptStatus::Delivered, up_to).await;
+        }
+        Ok(_) => {}
+        Err(e) => warn!("Failed to mark {} delivered for {}: {}", conversation_id, user_id, e),
+    }
+}
+
+/// Mark every message in the conversation read for `user_id`, along with the notifications they
+/// got for it, and tell the others. Returns how far they have read, if that moved.
+pub async fn mark_read(
+    db: &Database,
+    conversation_id: Uuid,
+    user_id: &str,
+) -> Result<Option<DateTime<Utc>>, sqlx::Error> {
+    let read_up_to = sqlx::query_scalar::<_, DateTime<Utc>>(
+        r#"
+        UPDATE conversation_participants p
+        SET last_read_at = latest.created_at,
+            last_delivered_at = GREATEST(p.last_delivered_at, latest.created_at)
+        FROM (SELECT MAX(created_at) AS created_at FROM messages WHERE conversation_id = $1) latest
+        WHERE p.conversation_id = $1 AND p.user_id = $2
+          AND latest.created_at > COALESCE(p.last_read_at, '-infinity')
+        RETURNING p.last_read_at
+        "#,
+    )
+    .bind(conversation_id)
+    .bind(user_id)
+    .fetch_optional(&db.pool)
+    .await?;
+    let Some(read_up_to) = read_up_to else {
+        return Ok(None);
+    };
+
+    sqlx::query(
+        r#"
+        UPDATE notifications SET read_at = NOW()
+        WHERE user_id = $1 AND kind = $2 AND read_at IS NULL
+          AND payload->>'conversationId' = $3
+        "#,
+    )
+    .bind(user_id)
+    .bind(NotificationKind::Message.as_str())
+    .bind(conversation_id.to_string())
+    .execute(&db.pool)
+    .await?;
+
+    publish_receipt(db, conversation_id, user_id, ReceiptStatus::Read, read_up_to).await;
+    publish_counters(db, user_id).await;
+    Ok(Some(read_up_to))
+}
+
+async fn publish_receipt(
+    db: &Database,
+    conversation_id: Uuid,
+    user_id: &str,
+    status: ReceiptStatus,
+    up_to: DateTime<Utc>,
+) {
+    let receipt = json!({
+        "conversationId": conversation_id,
+        "userId": user_id,
+        "status": status.as_str(),
+        "upTo": up_to,
+    });
+    publish_to_others(db, conversation_id, user_id, LiveEvent::Receipt, receipt).await;
+}
+
+async fn publish_to_others(
+    db: &Database,
+    conversation_id: Uuid,
+    user_id: &str,
+    event: LiveEvent,
+    data: Value,
+) {
+    match other_participants(db, conversation_id, user_id).await {
+        Ok(others) => {
+            for other in others {
+                realtime::publish(db, &other, event, data.clone()).await;
+            }
+        }
+        Err(e) => warn!("Failed to load participants of {}: {}", conversation_id, e),
+    }
+}
+
+/// Act on a message a client sent up its socket. Anything malformed, or about a conversation
+/// the user isn't in, is ignored.
+pub async fn handle_client_event(db: &Database, user_id: &str, text: &str) {
+    let Ok(event) = serde_json::from_str::<ClientEvent>(text) else {
+        return;
+    };
+    match is_participant(db, event.conversation_id, user_id).await {
+        Ok(true) => {}
+        Ok(false) => return,
+        Err(e) => {
+            warn!("Failed to check participants of {}: {}", event.conversation_id, e);
+            return;
+        }
+    }
+
+    match event.kind.as_str() {
+        // Typing state isn't stored; clients drop it when no update came for a few seconds
+        "typing" => {
+            let typing = json!({
+                "conversationId": event.conversation_id,
+                "userId": user_id,
+                "typing": event.typing.unwrap_or(true),
+            });
+            publish_to_others(db, event.conversation_id, user_id, LiveEvent::Typing, typing).await;
+        }
+        "delivered" => mark_delivered(db, event.conversation_id, user_id, Utc::now()).await,
+        "read" => {
+            if let Err(e) = mark_read(db, event.conversation_id, user_id).await {
+                warn!("Failed to mark {} read for {}: {}", event.conversation_id, user_id, e);
+            }
+        }
+        _ => {}
+    }
+}
@@ -5,7 +5,10 @@ use axum::{
     response::Response,
 };
 
-use crate::{auth::verify_jwt, config::Config};
+use crate::{
+    auth::verify_jwt,
+    config::{payments_sandbox, Config},
+};
 
 pub async fn auth_middleware(mut request: Request, next: Next) -> Result<Response, StatusCode> {
     let path = request.uri().path().to_owned();
@@ -20,15 +23,40 @@ pub async fn auth_middleware(mut request: Request, next: Next) -> Result<Respons
         || path.starts_with("/api/creators")
         || (path.starts_with("/api/campaigns") && method == Method::GET)
         || (path.starts_with("/api/events") && method == Method::GET)
-        || (path.starts_with("/api/posts") && method == Method::GET && !path.contains("/my-posts"))
+        || (path == "/api/feed/explore" && method == Method::GET)
+        || (path == "/api/analytics/track" && method == Method::POST)
+        || (path == "/api/podcasts/listens" && method == Method::POST)
+        || (path == "/api/users/me/events.ics" && method == Method::GET)
+        || (path == "/api/events/stream/webhook" && method == Method::POST)
+        || (path == "/api/stripe/webhook" && method == Method::POST)
+        || (path == "/api/coinbase/webhook" && method == Method::POST)
+        || (path.starts_with("/api/emails/webhooks/") && method == Method::POST)
+        || path == "/api/notifications/digest/unsubscribe"
+        || (path == "/ws" && method == Method::GET)
+        || (path == "/api/stripe/payment-request-config" && method == Method::GET)
+        || (path.starts_with("/api/sandbox/stripe") && payments_sandbox())
+        || (path.starts_with("/api/posts")
+            && method == Method::GET
+            && !path.contains("/my-posts")
+            && !path.contains("/calendar")
+            && !path.contains("/insights")
+            && !path.contains("/import"))
         || (path.starts_with("/api/products")
             && method == Method::GET
             && !path.contains("/me")
             && !path.contains("/download"))
+        || (matches!(
+            path.as_str(),
+            "/api/licenses/validate" | "/api/licenses/activate" | "/api/licenses/deactivate"
+        ) && method == Method::POST)
         || (path.starts_with("/api/articles") && method == Method::GET)
+        || (path.starts_with("/api/categories") && method == Method::GET)
+        || (path.starts_with("/api/tags") && method == Method::GET)
+        || (path.starts_with("/api/series") && method == Method::GET)
         || (path.starts_with("/api/referrals/validate") && method == Method::GET)
+        || (path.starts_with("/api/upload/private") && method == Method::GET)
         || (path.starts_with("/api/podcasts") && method == Method::GET)
-        || (path.starts_with("/api/notifications") && method == Method::GET)
+        || (path.starts_with("/api/polls") && method == Method::GET)
         || (path.starts_with("/api/subscriptions") && method == Method::GET)
         || (path.starts_with("/api/") && method == Method::OPTIONS);
 
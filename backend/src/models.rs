@@ -17,6 +17,7 @@ pub struct User {
     pub updated_at: DateTime<Utc>,
 }
 
+#[allow(dead_code)]
 #[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
 pub struct Post {
     pub id: Uuid,
@@ -40,11 +41,42 @@ pub struct Product {
     pub currency: String,
     pub image_url: Option<String>,
     pub is_digital: bool,
+    /// Only handed out as signed links; see `routes::product_downloads`.
+    #[serde(skip_serializing)]
     pub download_url: Option<String>,
+    /// Deactivated products can no longer be bought; buyers keep their downloads.
+    pub is_active: bool,
+    /// Whether each purchase comes with a license key.
+    pub license_keys: bool,
+    /// Machines a key may be activated on, unless the variant sets its own limit.
+    pub license_activation_limit: i32,
+    /// Downloads allowed per purchase; unlimited when empty.
+    pub download_limit: Option<i32>,
+    /// Until this passes the product sells as a pre-order and its file cannot be downloaded.
+    pub release_at: Option<DateTime<Utc>>,
+    /// When pre-order buyers were told the product is out.
+    pub released_at: Option<DateTime<Utc>>,
+    /// Units left of a limited edition; unlimited when empty.
+    pub stock: Option<i32>,
+    /// Place in the creator's storefront; unordered products follow, newest first.
+    pub storefront_position: Option<i32>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+    /// Price during a running sale; filled in for listings, not stored.
+    #[sqlx(default)]
+    pub sale_price: Option<f64>,
+    #[sqlx(default)]
+    pub sale_ends_at: Option<DateTime<Utc>>,
+}
+
+impl Product {
+    /// Whether the product is sold ahead of its release date.
+    pub fn is_preorder(&self) -> bool {
+        self.release_at.is_some_and(|release_at| release_at > Utc::now())
+    }
 }
 
+#[allow(dead_code)]
 #[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
 pub struct Subscription {
     pub id: Uuid,
@@ -63,15 +95,21 @@ pub struct Purchase {
     pub id: Uuid,
     pub user_id: String,
     pub product_id: Uuid,
+    /// The variant bought, when the product has variants.
+    pub variant_id: Option<Uuid>,
     pub stripe_payment_intent_id: Option<String>,
     pub stripe_checkout_session_id: Option<String>,
     pub amount: f64,
     pub currency: String,
     pub status: String,
+    /// The discount code or sale applied, and how much it took off.
+    pub discount_code_id: Option<Uuid>,
+    pub discount_amount: Option<f64>,
     pub created_at: DateTime<Utc>,
 }
 
 // Request/Response DTOs
+#[allow(dead_code)]
 #[derive(Debug, Deserialize)]
 pub struct CreateUserRequest {
     pub github_id: i64,
@@ -86,6 +124,7 @@ pub struct CreateUserRequest {
 pub struct CreatePostRequest {
     pub title: String,
     pub content: String,
+    #[allow(dead_code)]
     pub excerpt: Option<String>,
     pub media_url: Option<String>,
     pub media_type: Option<String>,
@@ -98,6 +137,10 @@ pub struct CreatePostRequest {
     pub published: Option<bool>,
     pub published_at: Option<DateTime<Utc>>,
     pub is_premium: Option<bool>,
+    pub unlock_price: Option<f64>,
+    pub unlock_currency: Option<String>,
+    /// Also publish the post as an update on this campaign.
+    pub campaign_id: Option<Uuid>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -114,6 +157,17 @@ pub struct CreateProductRequest {
     #[serde(alias = "fileUrl")]
     pub download_url: Option<String>,
     pub product_type: Option<String>,
+    pub license_keys: Option<bool>,
+    #[serde(alias = "activationLimit")]
+    pub license_activation_limit: Option<i32>,
+    pub download_limit: Option<i32>,
+    /// Sell as a pre-order until this date.
+    #[serde(alias = "releaseDate")]
+    pub release_at: Option<DateTime<Utc>>,
+    /// Units for sale of a limited edition; unlimited when empty.
+    #[serde(alias = "quantity")]
+    pub stock: Option<i32>,
+    pub is_active: Option<bool>,
 }
 
 #[derive(Debug, Serialize)]
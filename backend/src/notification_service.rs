@@ -0,0 +1,408 @@
+use std::collections::{HashMap, HashSet};
+
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sha2::Sha256;
+use tracing::warn;
+use uuid::Uuid;
+
+use crate::{
+    config::Config,
+    database::Database,
+    realtime::{self, LiveEvent},
+};
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// What a notification is about; stored in `notifications.kind`. Kinds are also the categories
+/// users set their preferences by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum NotificationKind {
+    Donation,
+    Subscription,
+    Comment,
+    Rsvp,
+    /// Updates on events the user is going to, such as reminders.
+    Event,
+    Payment,
+    Payout,
+    Dispute,
+    /// A broadcast from a creator the user is a member of.
+    Announcement,
+    /// Someone @mentioned the user in a comment.
+    Mention,
+    /// A direct message that arrived while the user had no socket open.
+    Message,
+    /// A new version of a product the user bought.
+    ProductUpdate,
+    /// A physical order shipped or was delivered.
+    Order,
+}
+
+impl NotificationKind {
+    pub const ALL: [NotificationKind; 13] = [
+        NotificationKind::Donation,
+        NotificationKind::Subscription,
+        NotificationKind::Comment,
+        NotificationKind::Rsvp,
+        NotificationKind::Event,
+        NotificationKind::Payment,
+        NotificationKind::Payout,
+        NotificationKind::Dispute,
+        NotificationKind::Announcement,
+        NotificationKind::Mention,
+        NotificationKind::Message,
+        NotificationKind::ProductUpdate,
+        NotificationKind::Order,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            NotificationKind::Donation => "DONATION",
+            NotificationKind::Subscription => "SUBSCRIPTION",
+            NotificationKind::Comment => "COMMENT",
+            NotificationKind::Rsvp => "RSVP",
+            NotificationKind::Event => "EVENT",
+            NotificationKind::Payment => "PAYMENT",
+            NotificationKind::Payout => "PAYOUT",
+            NotificationKind::Dispute => "DISPUTE",
+            NotificationKind::Announcement => "ANNOUNCEMENT",
+            NotificationKind::Mention => "MENTION",
+            NotificationKind::Message => "MESSAGE",
+            NotificationKind::ProductUpdate => "PRODUCT_UPDATE",
+            NotificationKind::Order => "ORDER",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|kind| kind.as_str() == value)
+    }
+}
+
+/// Where a notification reaches the user.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum NotificationChannel {
+    /// Stored in the notification list.
+    InApp,
+    Email,
+    /// Pushed live to the user's open sockets.
+    Push,
+}
+
+impl NotificationChannel {
+    pub const ALL: [NotificationChannel; 3] = [
+        NotificationChannel::InApp,
+        NotificationChannel::Email,
+        NotificationChannel::Push,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            NotificationChannel::InApp => "IN_APP",
+            NotificationChannel::Email => "EMAIL",
+            NotificationChannel::Push => "PUSH",
+        }
+    }
+
+    /// Key of the channel in the preferences API.
+    pub fn key(self) -> &'static str {
+        match self {
+            NotificationChannel::InApp => "inApp",
+            NotificationChannel::Email => "email",
+            NotificationChannel::Push => "push",
+        }
+    }
+
+    fn parse(value: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|channel| channel.as_str() == value)
+    }
+
+    /// What a user who never changed their preferences gets. Everything shows in the app; email
+    /// is kept for money and membership matters and reminders, which were always emailed.
+    pub fn enabled_by_default(self, kind: NotificationKind) -> bool {
+        match self {
+            NotificationChannel::InApp | NotificationChannel::Push => true,
+            NotificationChannel::Email => !matches!(
+                kind,
+                NotificationKind::Donation
+                    | NotificationKind::Comment
+                    | NotificationKind::Rsvp
+                    | NotificationKind::Mention
+                    | NotificationKind::Message
+            ),
+        }
+    }
+}
+
+/// A user's preferences for every category and channel, with defaults filled in.
+pub async fn load_preferences(
+    db: &Database,
+    user_id: &str,
+) -> Result<HashMap<(NotificationKind, NotificationChannel), bool>, sqlx::Error> {
+    let mut preferences: HashMap<_, _> = NotificationKind::ALL
+        .into_iter()
+        .flat_map(|kind| {
+            NotificationChannel::ALL
+                .into_iter()
+                .map(move |channel| ((kind, channel), channel.enabled_by_default(kind)))
+        })
+        .collect();
+
+    let stored = sqlx::query_as::<_, (String, String, bool)>(
+        "SELECT category, channel, enabled FROM notification_preferences WHERE user_id = $1",
+    )
+    .bind(user_id)
+    .fetch_all(&db.pool)
+    .await?;
+    for (category, channel, enabled) in stored {
+        if let (Some(kind), Some(channel)) =
+            (NotificationKind::parse(&category), NotificationChannel::parse(&channel))
+        {
+            preferences.insert((kind, channel), enabled);
+        }
+    }
+    Ok(preferences)
+}
+
+/// Whether `user_id` wants `kind` notifications on `channel`. When their preferences can't be
+/// read, the default applies.
+pub async fn channel_enabled(
+    db: &Database,
+    user_id: &str,
+    kind: NotificationKind,
+    channel: NotificationChannel,
+) -> bool {
+    let stored = sqlx::query_scalar::<_, bool>(
+        r#"
+        SELECT enabled FROM notification_preferences
+        WHERE user_id = $1 AND category = $2 AND channel = $3
+        "#,
+    )
+    .bind(user_id)
+    .bind(kind.as_str())
+    .bind(channel.as_str())
+    .fetch_optional(&db.pool)
+    .await;
+    match stored {
+        Ok(Some(enabled)) => enabled,
+        Ok(None) => channel.enabled_by_default(kind),
+        Err(e) => {
+            warn!("Failed to read notification preferences of {}: {}", user_id, e);
+            channel.enabled_by_default(kind)
+        }
+    }
+}
+
+/// Store an in-app notification for `user_id` and push it to their open sockets, as far as
+/// their preferences for `kind` allow. `payload` carries a human `message`, a `link` into the
+/// app where relevant, and the ids the client needs to render it.
+///
+/// Notifications are a side effect of whatever triggered them, so failures are logged rather
+/// than returned.
+pub async fn notify(db: &Database, user_id: &str, kind: NotificationKind, payload: Value) {
+    let in_app = channel_enabled(db, user_id, kind, NotificationChannel::InApp).await;
+    let push = channel_enabled(db, user_id, kind, NotificationChannel::Push).await;
+    if !in_app {
+        // Nothing to store; the live toast alone carries no id and changes no counter
+        if push {
+            let notification = json!({
+                "id": null,
+                "kind": kind.as_str(),
+                "payload": payload,
+                "readAt": null,
+                "createdAt": chrono::Utc::now(),
+            });
+            realtime::publish(db, user_id, LiveEvent::Notification, notification).await;
+        }
+        return;
+    }
+
+    let stored = sqlx::query_as::<_, (Uuid, chrono::DateTime<chrono::Utc>)>(
+        r#"
+        INSERT INTO notifications (user_id, kind, payload)
+        VALUES ($1, $2, $3)
+        RETURNING id, created_at
+        "#,
+    )
+    .bind(user_id)
+    .bind(kind.as_str())
+    .bind(&payload)
+    .fetch_one(&db.pool)
+    .await;
+    let (id, created_at) = match stored {
+        Ok(stored) => stored,
+        Err(e) => {
+            warn!("Failed to store {} notification for {}: {}", kind.as_str(), user_id, e);
+            return;
+        }
+    };
+
+    let notification = json!({
+        "id": id,
+        "kind": kind.as_str(),
+        "payload": payload,
+        "readAt": null,
+        "createdAt": created_at,
+    });
+    if push {
+        realtime::publish(db, user_id, LiveEvent::Notification, notification).await;
+    }
+    realtime::publish_counters(db, user_id).await;
+}
+
+/// Mentions resolved per comment; the rest of a longer list is ignored.
+const MAX_MENTIONS: usize = 20;
+
+/// A comment that was just posted, for `notify_comment`.
+pub struct CommentActivity<'a> {
+    pub comment_id: Uuid,
+    pub commenter_id: &'a str,
+    pub commenter_name: &'a str,
+    pub content: &'a str,
+    /// Whoever owns what was commented on: the post's creator, the article's author or the
+    /// campaign's creator.
+    pub owner_id: Option<&'a str>,
+    /// Author of the comment this one replies to.
+    pub parent_author_id: Option<&'a str>,
+    /// The title of what was commented on.
+    pub title: &'a str,
+    pub link: Option<String>,
+    /// Ids the client needs, such as `postId`; merged into every payload.
+    pub ids: Value,
+}
+
+/// Notify everyone a new comment concerns: the author of the comment it replies to, users it
+/// @mentions and the owner of the content. Each of them hears about it once, for the first of
+/// those reasons that applies, and the commenter never hears about their own comment.
+pub async fn notify_comment(db: &Database, activity: CommentActivity<'_>) {
+    let mentioned = mentioned_user_ids(db, activity.content).await;
+
+    let mut recipients: Vec<(&str, NotificationKind, String)> = Vec::new();
+    if let Some(parent_author_id) = activity.parent_author_id {
+        let message =
+            format!("{} replied to your comment on {}", activity.commenter_name, activity.title);
+        recipients.push((parent_author_id, NotificationKind::Comment, message));
+    }
+    for user_id in &mentioned {
+        let message = format!("{} mentioned you on {}", activity.commenter_name, activity.title);
+        recipients.push((user_id.as_str(), NotificationKind::Mention, message));
+    }
+    if let Some(owner_id) = activity.owner_id {
+        let message = format!("{} commented on {}", activity.commenter_name, activity.title);
+        recipients.push((owner_id, NotificationKind::Comment, message));
+    }
+
+    let mut notified = HashSet::from([activity.commenter_id]);
+    for (user_id, kind, message) in recipients {
+        if !notified.insert(user_id) {
+            continue;
+        }
+        let mut payload = json!({
+            "message": message,
+            "link": activity.link,
+            "commentId": activity.comment_id,
+            "commenterId": activity.commenter_id,
+        });
+        if let (Some(payload), Some(ids)) = (payload.as_object_mut(), activity.ids.as_object()) {
+            payload.extend(ids.clone());
+        }
+        notify(db, user_id, kind, payload).await;
+    }
+}
+
+/// Users @mentioned in `content`, looked up by username regardless of case.
+async fn mentioned_user_ids(db: &Database, content: &str) -> Vec<String> {
+    let usernames = mentioned_usernames(content);
+    if usernames.is_empty() {
+        return Vec::new();
+    }
+    sqlx::query_scalar::<_, String>("SELECT id FROM users WHERE LOWER(username) = ANY($1)")
+        .bind(&usernames)
+        .fetch_all(&db.pool)
+        .await
+        .unwrap_or_else(|e| {
+            warn!("Failed to resolve mentions: {}", e);
+            Vec::new()
+        })
+}
+
+/// Lowercased usernames following an `@` that starts a word, so email addresses don't count.
+fn mentioned_usernames(content: &str) -> Vec<String> {
+    let mut usernames: Vec<String> = Vec::new();
+    let mut previous = ' ';
+    for (index, character) in content.char_indices() {
+        let starts_word = !(previous.is_alphanumeric() || previous == '_');
+        previous = character;
+        if character != '@' || !starts_word {
+            continue;
+        }
+        let username: String = content[index + 1..]
+            .chars()
+            .take_while(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '-'))
+            .collect();
+        // A sentence ending right after a mention isn't part of the username
+        let username = username.trim_end_matches(['.', '-']).to_lowercase();
+        if !username.is_empty() && !usernames.contains(&username) {
+            usernames.push(username);
+            if usernames.len() == MAX_MENTIONS {
+                break;
+            }
+        }
+    }
+    usernames
+}
+
+/// How often a user gets the digest email; stored in `digest_settings.frequency`. Users who
+/// never chose get it weekly.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DigestFrequency {
+    Off,
+    Daily,
+    #[default]
+    Weekly,
+}
+
+impl DigestFrequency {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            DigestFrequency::Off => "OFF",
+            DigestFrequency::Daily => "DAILY",
+            DigestFrequency::Weekly => "WEEKLY",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "OFF" => Some(DigestFrequency::Off),
+            "DAILY" => Some(DigestFrequency::Daily),
+            "WEEKLY" => Some(DigestFrequency::Weekly),
+            _ => None,
+        }
+    }
+}
+
+/// Token for the one-click unsubscribe link in digest emails, `<user id>.<signature>`, so the
+/// link works without signing in.
+pub fn digest_unsubscribe_token(user_id: &str) -> String {
+    format!("{}.{}", user_id, hex::encode(sign_unsubscribe(user_id).finalize().into_bytes()))
+}
+
+/// The user an unsubscribe token was issued to, if its signature holds.
+pub fn verify_digest_unsubscribe_token(token: &str) -> Option<String> {
+    let (user_id, signature) = token.trim().rsplit_once('.')?;
+    let signature = hex::decode(signature).ok()?;
+    sign_unsubscribe(user_id)
+        .verify_slice(&signature)
+        .ok()
+        .map(|_| user_id.to_string())
+}
+
+fn sign_unsubscribe(user_id: &str) -> HmacSha256 {
+    let secret = Config::from_env().map(|config| config.jwt_secret).unwrap_or_default();
+    let mut mac =
+        HmacSha256::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(format!("digest-unsubscribe:{}", user_id).as_bytes());
+    mac
+}
@@ -0,0 +1,50 @@
+use axum::http::StatusCode;
+use serde::Deserialize;
+use uuid::Uuid;
+
+use crate::{
+    database::Database,
+    routes::{coinbase::Coinbase, stripe::Stripe},
+};
+
+/// A one-time campaign donation, priced and credited in `currency`.
+pub(crate) struct DonationPayment<'a> {
+    pub campaign_id: Uuid,
+    pub campaign_slug: &'a str,
+    pub title: &'a str,
+    pub creator_id: &'a str,
+    pub donor_id: &'a str,
+    pub amount_cents: i64,
+    pub currency: &'a str,
+    pub message: Option<String>,
+    pub is_anonymous: bool,
+    pub share_link_id: Option<Uuid>,
+}
+
+/// A service that takes payments. Each provider confirms payments through its own webhook.
+#[axum::async_trait]
+pub(crate) trait PaymentProvider: Send + Sync {
+    /// Start paying for `donation`: store it as PENDING and return what the client needs to
+    /// pay, such as a PaymentIntent's client secret or a hosted checkout page.
+    async fn create_donation(
+        &self,
+        db: &Database,
+        donation: DonationPayment<'_>,
+    ) -> Result<serde_json::Value, StatusCode>;
+}
+
+/// Who takes a payment: Stripe in-page, or Coinbase Commerce in crypto.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum ProviderName {
+    #[default]
+    Stripe,
+    Coinbase,
+}
+
+pub(crate) fn provider(name: ProviderName) -> &'static dyn PaymentProvider {
+    match name {
+        ProviderName::Stripe => &Stripe,
+        ProviderName::Coinbase => &Coinbase,
+    }
+}
@@ -0,0 +1,501 @@
+use std::{
+    collections::HashMap,
+    sync::{Mutex, MutexGuard, OnceLock},
+};
+
+use axum::{
+    extract::{Form, Path, Query},
+    http::StatusCode,
+    response::{Json, Redirect},
+    routing::{get, post},
+    Router,
+};
+use chrono::{Duration, Utc};
+use hmac::{Hmac, Mac};
+use serde_json::{json, Value};
+use sha2::Sha256;
+use uuid::Uuid;
+
+use crate::{
+    config::{payments_sandbox_url, Config},
+    database::Database,
+};
+
+type HmacSha256 = Hmac<Sha256>;
+type Params = Vec<(String, String)>;
+
+/// Webhooks trail the call that caused them, as Stripe's do, so callers store ids first.
+const WEBHOOK_DELAY: std::time::Duration = std::time::Duration::from_millis(500);
+
+/// Every object the sandbox has handed out, by id. Restarting the server forgets them.
+static OBJECTS: OnceLock<Mutex<HashMap<String, Value>>> = OnceLock::new();
+
+/// The part of the Stripe API this service calls, served from memory for `PAYMENTS_MODE=sandbox`.
+/// State changes are announced through signed webhooks to `/api/stripe/webhook`, as Stripe would,
+/// so fulfilment runs exactly as in production.
+///
+/// Payment intents are confirmed with Stripe's test payment method ids: `pm_card_visa` succeeds,
+/// `pm_card_authenticationRequired` asks for 3D Secure, which `/authenticate` then passes or
+/// fails, and `pm_card_chargeDeclined` is declined. Hosted checkout is paid by opening the
+/// session's `url`, or abandoned by opening it with `?cancel=true`.
+pub fn sandbox_routes() -> Router<Database> {
+    Router::new()
+        .route("/v1/accounts", post(create_account))
+        .route("/v1/accounts/:id", get(retrieve))
+        .route("/v1/account_links", post(create_account_link))
+        .route("/v1/balance_transactions", get(list_balance_transactions))
+        .route("/v1/billing_portal/sessions", post(create_billing_portal_session))
+        .route("/v1/checkout/sessions", post(create_checkout_session))
+        .route("/v1/checkout/sessions/:id", get(retrieve))
+        .route("/v1/checkout/sessions/:id/expire", post(expire_checkout_session))
+        .route("/v1/customers", post(create_customer))
+        .route("/v1/customers/:id", get(retrieve))
+        .route("/v1/customers/:id/payment_methods", get(list_payment_methods))
+        .route("/v1/disputes/:id", post(update_dispute))
+        .route("/v1/payment_intents", post(create_payment_intent))
+        .route("/v1/payment_intents/:id", get(retrieve))
+        .route("/v1/payment_intents/:id/confirm", post(confirm_payment_intent))
+        .route("/v1/payment_intents/:id/authenticate", post(authenticate_payment_intent))
+        .route("/v1/payment_intents/:id/cancel", post(cancel_payment_intent))
+        .route("/v1/payment_methods/:id", get(retrieve))
+        .route("/v1/payment_methods/:id/detach", post(detach_payment_method))
+        .route("/v1/payouts", post(create_payout))
+        .route("/v1/refunds", post(create_refund))
+        .route("/v1/setup_intents", post(create_setup_intent))
+        .route("/v1/transfers", post(create_transfer))
+        .route("/checkout/:id", get(pay_checkout_session))
+}
+
+async fn retrieve(Path(id): Path<String>) -> Result<Json<Value>, StatusCode> {
+    load(&id).map(Json).ok_or(StatusCode::NOT_FOUND)
+}
+
+async fn create_account(Form(params): Form<Params>) -> Json<Value> {
+    Json(save(json!({
+        "id": new_id("acct"),
+        "object": "account",
+        "type": param(&params, "type").unwrap_or("express"),
+        "email": param(&params, "email"),
+        "metadata": metadata(&params),
+        "charges_enabled": true,
+        "payouts_enabled": true,
+        "details_submitted": true,
+        "requirements": { "currently_due": [], "disabled_reason": null },
+    })))
+}
+
+// Onboarding has nothing to fill in, so the link leads straight back
+async fn create_account_link(Form(params): Form<Params>) -> Json<Value> {
+    Json(json!({
+        "object": "account_link",
+        "url": param(&params, "return_url"),
+        "expires_at": (Utc::now() + Duration::minutes(5)).timestamp(),
+    }))
+}
+
+async fn list_balance_transactions(Query(params): Query<Params>) -> Json<Value> {
+    let bound = |key: &str| param(&params, key).and_then(|value| value.parse::<i64>().ok());
+    let created = bound("created[gte]").unwrap_or(i64::MIN)..bound("created[lt]").unwrap_or(i64::MAX);
+
+    let mut data: Vec<Value> = objects()
+        .values()
+        .filter(|object| object["object"] == "balance_transaction")
+        .filter(|object| object["created"].as_i64().is_some_and(|at| created.contains(&at)))
+        .cloned()
+        .collect();
+    data.sort_by_key(|object| object["created"].as_i64());
+    Json(json!({ "object": "list", "data": data, "has_more": false }))
+}
+
+async fn create_billing_portal_session(Form(params): Form<Params>) -> Json<Value> {
+    Json(json!({
+        "id": new_id("bps"),
+        "object": "billing_portal.session",
+        "customer": param(&params, "customer"),
+        "url": param(&params, "return_url"),
+    }))
+}
+
+async fn create_checkout_session(Form(params): Form<Params>) -> Json<Value> {
+    // Line items are numbered from zero; the total is what Stripe would charge for them
+    let mut amount_total = 0;
+    for index in 0.. {
+        let field = |name: &str| param(&params, &format!("line_items[{}][{}]", index, name));
+        let Some(unit_amount) = field("price_data][unit_amount") else {
+            break;
+        };
+        let quantity = field("quantity").and_then(|value| value.parse::<i64>().ok()).unwrap_or(1);
+        amount_total += unit_amount.parse::<i64>().unwrap_or(0) * quantity;
+    }
+    let currency = param(&params, "line_items[0][price_data][currency]").unwrap_or("usd");
+
+    let id = new_id("cs");
+    Json(save(json!({
+        "id": id,
+        "object": "checkout.session",
+        "mode": param(&params, "mode").unwrap_or("payment"),
+        "status": "open",
+        "payment_status": "unpaid",
+        "amount_total": amount_total,
+        "currency": currency,
+        "client_reference_id": param(&params, "client_reference_id"),
+        "customer": param(&params, "customer"),
+        "metadata": metadata(&params),
+        "payment_intent": null,
+        "success_url": param(&params, "success_url"),
+        "cancel_url": param(&params, "cancel_url"),
+        "url": format!("{}/checkout/{}", payments_sandbox_url(), id),
+    })))
+}
+
+async fn expire_checkout_session(Path(id): Path<String>) -> Result<Json<Value>, StatusCode> {
+    let session = update(&id, |session| {
+        if session["status"] == "open" {
+            session["status"] = json!("expired");
+        }
+    })?;
+    if session["status"] != "expired" {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    Ok(Json(session))
+}
+
+// Stands in for the hosted payment page: opening it pays the session with a test card
+async fn pay_checkout_session(
+    Path(id): Path<String>,
+    Query(params): Query<Params>,
+) -> Result<Redirect, StatusCode> {
+    let session = load(&id).ok_or(StatusCode::NOT_FOUND)?;
+    let url = |key: &str| session[key].as_str().unwrap_or_default().to_string();
+    if param(&params, "cancel") == Some("true") {
+        return Ok(Redirect::to(&url("cancel_url")));
+    }
+    if session["status"] != "open" {
+        return Err(StatusCode::GONE);
+    }
+
+    let amount = session["amount_total"].as_i64().unwrap_or(0);
+    let currency = session["currency"].as_str().unwrap_or("usd");
+    let payment_intent = save(json!({
+        "id": new_id("pi"),
+        "object": "payment_intent",
+        "amount": amount,
+        "currency": currency,
+        "status": "succeeded",
+        "metadata": {},
+        "last_payment_error": null,
+    }));
+    record_balance_transaction("charge", amount, currency, &payment_intent);
+
+    let session = update(&id, |session| {
+        session["status"] = json!("complete");
+        session["payment_status"] = json!("paid");
+        session["payment_intent"] = payment_intent["id"].clone();
+    })?;
+    deliver("payment_intent.succeeded", payment_intent);
+    deliver("checkout.session.completed", session);
+
+    Ok(Redirect::to(&url("success_url").replace("{CHECKOUT_SESSION_ID}", &id)))
+}
+
+async fn create_customer(Form(params): Form<Params>) -> Json<Value> {
+    Json(save(json!({
+        "id": new_id("cus"),
+        "object": "customer",
+        "email": param(&params, "email"),
+        "metadata": metadata(&params),
+        "invoice_settings": { "default_payment_method": null },
+    })))
+}
+
+async fn list_payment_methods(Path(customer_id): Path<String>) -> Json<Value> {
+    let data: Vec<Value> = objects()
+        .values()
+        .filter(|object| object["object"] == "payment_method")
+        .filter(|object| object["customer"] == customer_id.as_str())
+        .cloned()
+        .collect();
+    Json(json!({ "object": "list", "data": data, "has_more": false }))
+}
+
+async fn detach_payment_method(Path(id): Path<String>) -> Result<Json<Value>, StatusCode> {
+    update(&id, |method| method["customer"] = Value::Null).map(Json)
+}
+
+// Disputes only arise from real card networks; evidence for an unknown one is rejected as Stripe would
+async fn update_dispute(Path(id): Path<String>) -> Result<Json<Value>, StatusCode> {
+    load(&id).map(Json).ok_or(StatusCode::NOT_FOUND)
+}
+
+async fn create_payment_intent(Form(params): Form<Params>) -> Result<Json<Value>, StatusCode> {
+    let amount = param(&params, "amount")
+        .and_then(|amount| amount.parse::<i64>().ok())
+        .filter(|amount| *amount > 0)
+        .ok_or(StatusCode::BAD_REQUEST)?;
+    let id = new_id("pi");
+    Ok(Json(save(json!({
+        "id": id,
+        "object": "payment_intent",
+        "amount": amount,
+        "currency": param(&params, "currency").unwrap_or("usd"),
+        "description": param(&params, "description"),
+        "status": "requires_payment_method",
+        "client_secret": format!("{}_secret_{}", id, Uuid::new_v4().simple()),
+        "metadata": metadata(&params),
+        "next_action": null,
+        "last_payment_error": null,
+    }))))
+}
+
+async fn confirm_payment_intent(
+    Path(id): Path<String>,
+    Form(params): Form<Params>,
+) -> Result<Json<Value>, StatusCode> {
+    let payment_method = param(&params, "payment_method").unwrap_or("pm_card_visa").to_string();
+    let payment_intent = load(&id).ok_or(StatusCode::NOT_FOUND)?;
+    if !matches!(
+        payment_intent["status"].as_str(),
+        Some("requires_payment_method" | "requires_confirmation")
+    ) {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    match payment_method.as_str() {
+        "pm_card_authenticationRequired" | "pm_card_threeDSecure2Required" => {
+            let payment_intent = update(&id, |payment_intent| {
+                payment_intent["status"] = json!("requires_action");
+                payment_intent["next_action"] = json!({ "type": "use_stripe_sdk" });
+                payment_intent["last_payment_error"] = Value::Null;
+            })?;
+            deliver("payment_intent.requires_action", payment_intent.clone());
+            Ok(Json(payment_intent))
+        }
+        "pm_card_chargeDeclined" => Ok(Json(decline(&id)?)),
+        _ => Ok(Json(succeed(&id)?)),
+    }
+}
+
+/// Sandbox only: the buyer's answer to the 3D Secure challenge, `success=false` to fail it.
+async fn authenticate_payment_intent(
+    Path(id): Path<String>,
+    Form(params): Form<Params>,
+) -> Result<Json<Value>, StatusCode> {
+    let payment_intent = load(&id).ok_or(StatusCode::NOT_FOUND)?;
+    if payment_intent["status"] != "requires_action" {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    match param(&params, "success") {
+        Some("false") => Ok(Json(decline(&id)?)),
+        _ => Ok(Json(succeed(&id)?)),
+    }
+}
+
+async fn cancel_payment_intent(Path(id): Path<String>) -> Result<Json<Value>, StatusCode> {
+    let payment_intent = update(&id, |payment_intent| {
+        payment_intent["status"] = json!("canceled");
+        payment_intent["next_action"] = Value::Null;
+    })?;
+    deliver("payment_intent.canceled", payment_intent.clone());
+    Ok(Json(payment_intent))
+}
+
+async fn create_refund(Form(params): Form<Params>) -> Result<Json<Value>, StatusCode> {
+    let payment_intent = param(&params, "payment_intent")
+        .and_then(load)
+        .filter(|payment_intent| payment_intent["status"] == "succeeded")
+        .ok_or(StatusCode::BAD_REQUEST)?;
+    let amount = param(&params, "amount")
+        .and_then(|amount| amount.parse::<i64>().ok())
+        .unwrap_or_else(|| payment_intent["amount"].as_i64().unwrap_or(0));
+    let currency = payment_intent["currency"].as_str().unwrap_or("usd");
+
+    let refund = save(json!({
+        "id": new_id("re"),
+        "object": "refund",
+        "amount": amount,
+        "currency": currency,
+        "payment_intent": payment_intent["id"],
+        "reason": param(&params, "reason"),
+        "metadata": metadata(&params),
+        "status": "succeeded",
+    }));
+    record_balance_transaction("refund", -amount, currency, &refund);
+    Ok(Json(refund))
+}
+
+async fn create_setup_intent(Form(params): Form<Params>) -> Json<Value> {
+    let id = new_id("seti");
+    Json(save(json!({
+        "id": id,
+        "object": "setup_intent",
+        "customer": param(&params, "customer"),
+        "status": "requires_payment_method",
+        "client_secret": format!("{}_secret_{}", id, Uuid::new_v4().simple()),
+    })))
+}
+
+async fn create_transfer(Form(params): Form<Params>) -> Json<Value> {
+    Json(save(json!({
+        "id": new_id("tr"),
+        "object": "transfer",
+        "amount": param(&params, "amount").and_then(|amount| amount.parse::<i64>().ok()),
+        "currency": param(&params, "currency"),
+        "destination": param(&params, "destination"),
+        "metadata": metadata(&params),
+    })))
+}
+
+// Sandbox payouts land at once, so the payout.paid webhook follows straight away
+async fn create_payout(Form(params): Form<Params>) -> Json<Value> {
+    let payout = save(json!({
+        "id": new_id("po"),
+        "object": "payout",
+        "amount": param(&params, "amount").and_then(|amount| amount.parse::<i64>().ok()),
+        "currency": param(&params, "currency"),
+        "arrival_date": Utc::now().timestamp(),
+        "metadata": metadata(&params),
+        "status": "paid",
+        "failure_message": null,
+    }));
+    deliver("payout.paid", payout.clone());
+    Json(payout)
+}
+
+fn succeed(id: &str) -> Result<Value, StatusCode> {
+    let payment_intent = update(id, |payment_intent| {
+        payment_intent["status"] = json!("succeeded");
+        payment_intent["next_action"] = Value::Null;
+        payment_intent["last_payment_error"] = Value::Null;
+    })?;
+    let amount = payment_intent["amount"].as_i64().unwrap_or(0);
+    let currency = payment_intent["currency"].as_str().unwrap_or("usd");
+    record_balance_transaction("charge", amount, currency, &payment_intent);
+    deliver("payment_intent.succeeded", payment_intent.clone());
+    Ok(payment_intent)
+}
+
+fn decline(id: &str) -> Result<Value, StatusCode> {
+    let payment_intent = update(id, |payment_intent| {
+        payment_intent["status"] = json!("requires_payment_method");
+        payment_intent["next_action"] = Value::Null;
+        payment_intent["last_payment_error"] = json!({
+            "type": "card_error",
+            "code": "card_declined",
+            "message": "Your card was declined.",
+        });
+    })?;
+    deliver("payment_intent.payment_failed", payment_intent.clone());
+    Ok(payment_intent)
+}
+
+// What the reconciliation report reads back: one movement per charge and refund, no fees
+fn record_balance_transaction(category: &str, amount: i64, currency: &str, source: &Value) {
+    let payment_intent = match source["object"].as_str() {
+        Some("payment_intent") => source["id"].clone(),
+        _ => source["payment_intent"].clone(),
+    };
+    save(json!({
+        "id": new_id("txn"),
+        "object": "balance_transaction",
+        "amount": amount,
+        "currency": currency,
+        "fee": 0,
+        "net": amount,
+        "reporting_category": category,
+        "created": Utc::now().timestamp(),
+        "source": { "id": source["id"], "object": source["object"], "payment_intent": payment_intent },
+    }));
+}
+
+/// Send a signed webhook for `object` in the background, after the response that caused it.
+fn deliver(event_type: &'static str, object: Value) {
+    let event = json!({
+        "id": new_id("evt"),
+        "object": "event",
+        "type": event_type,
+        "created": Utc::now().timestamp(),
+        "livemode": false,
+        "data": { "object": object },
+    });
+
+    tokio::spawn(async move {
+        tokio::time::sleep(WEBHOOK_DELAY).await;
+        let secret = Config::from_env()
+            .map(|config| config.stripe_webhook_secret)
+            .unwrap_or_default();
+        let body = event.to_string();
+        let timestamp = Utc::now().timestamp();
+        let Ok(mut mac) = HmacSha256::new_from_slice(secret.as_bytes()) else {
+            return;
+        };
+        mac.update(format!("{}.{}", timestamp, body).as_bytes());
+        let signature = hex::encode(mac.finalize().into_bytes());
+
+        let delivered = reqwest::Client::new()
+            .post(webhook_url())
+            .header("Content-Type", "application/json")
+            .header("Stripe-Signature", format!("t={},v1={}", timestamp, signature))
+            .body(body)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status());
+        if let Err(e) = delivered {
+            tracing::warn!("Sandbox failed to deliver {} webhook: {}", event_type, e);
+        }
+    });
+}
+
+fn webhook_url() -> String {
+    std::env::var("PAYMENTS_SANDBOX_WEBHOOK_URL").unwrap_or_else(|_| {
+        let port = std::env::var("PORT").unwrap_or_else(|_| "4000".to_string());
+        format!("http://127.0.0.1:{}/api/stripe/webhook", port)
+    })
+}
+
+fn objects() -> MutexGuard<'static, HashMap<String, Value>> {
+    OBJECTS
+        .get_or_init(Default::default)
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+fn new_id(prefix: &str) -> String {
+    format!("{}_sandbox_{}", prefix, Uuid::new_v4().simple())
+}
+
+fn save(object: Value) -> Value {
+    if let Some(id) = object["id"].as_str() {
+        objects().insert(id.to_string(), object.clone());
+    }
+    object
+}
+
+fn load(id: &str) -> Option<Value> {
+    objects().get(id).cloned()
+}
+
+fn update(id: &str, change: impl FnOnce(&mut Value)) -> Result<Value, StatusCode> {
+    let mut objects = objects();
+    let object = objects.get_mut(id).ok_or(StatusCode::NOT_FOUND)?;
+    change(object);
+    Ok(object.clone())
+}
+
+fn param<'a>(params: &'a Params, key: &str) -> Option<&'a str> {
+    params
+        .iter()
+        .find(|(name, _)| name == key)
+        .map(|(_, value)| value.as_str())
+}
+
+/// The `metadata[key]` form fields as a JSON object.
+fn metadata(params: &Params) -> Value {
+    params
+        .iter()
+        .filter_map(|(name, value)| {
+            let key = name.strip_prefix("metadata[")?.strip_suffix(']')?;
+            Some((key.to_string(), Value::String(value.clone())))
+        })
+        .collect::<serde_json::Map<_, _>>()
+        .into()
+}
@@ -0,0 +1,134 @@
+use chrono::{DateTime, Utc};
+
+/// Identifies this backend in generated feeds.
+const GENERATOR: &str = "Fundify";
+
+/// The show a feed is rendered for.
+#[derive(Debug)]
+pub struct FeedShow {
+    pub title: String,
+    pub description: Option<String>,
+    pub link: String,
+    pub feed_url: String,
+    pub author: Option<String>,
+    pub category: Option<String>,
+    pub language: Option<String>,
+    pub cover_image: Option<String>,
+}
+
+/// A published episode rendered as an `<item>`.
+#[derive(Debug)]
+pub struct FeedEpisode {
+    pub id: String,
+    pub title: String,
+    pub description: Option<String>,
+    pub audio_url: String,
+    /// Seconds.
+    pub duration: Option<i32>,
+    pub season_number: Option<i32>,
+    pub episode_number: Option<i32>,
+    pub published_at: DateTime<Utc>,
+    pub transcripts: Vec<FeedTranscript>,
+}
+
+/// One `<podcast:transcript>` of an episode.
+#[derive(Debug)]
+pub struct FeedTranscript {
+    pub url: String,
+    pub mime_type: &'static str,
+    pub language: Option<String>,
+}
+
+/// Render an RSS 2.0 feed with the iTunes and Podcasting 2.0 namespaces.
+pub fn render_feed(show: &FeedShow, episodes: &[FeedEpisode]) -> String {
+    let mut xml = String::from(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <rss version=\"2.0\" xmlns:itunes=\"http://www.itunes.com/dtds/podcast-1.0.dtd\" \
+         xmlns:podcast=\"https://podcastindex.org/namespace/1.0\" \
+         xmlns:atom=\"http://www.w3.org/2005/Atom\">\n<channel>\n",
+    );
+    xml.push_str(&element("title", &show.title));
+    xml.push_str(&element("link", &show.link));
+    xml.push_str(&format!(
+        "<atom:link href=\"{}\" rel=\"self\" type=\"application/rss+xml\"/>\n",
+        escape(&show.feed_url)
+    ));
+    xml.push_str(&element("description", show.description.as_deref().unwrap_or(&show.title)));
+    xml.push_str(&element("generator", GENERATOR));
+    if let Some(language) = &show.language {
+        xml.push_str(&element("language", language));
+    }
+    if let Some(author) = &show.author {
+        xml.push_str(&element("itunes:author", author));
+    }
+    if let Some(category) = &show.category {
+        xml.push_str(&format!("<itunes:category text=\"{}\"/>\n", escape(category)));
+    }
+    if let Some(cover_image) = &show.cover_image {
+        xml.push_str(&format!("<itunes:image href=\"{}\"/>\n", escape(cover_image)));
+    }
+
+    for episode in episodes {
+        xml.push_str("<item>\n");
+        xml.push_str(&element("title", &episode.title));
+        if let Some(description) = &episode.description {
+            xml.push_str(&element("description", description));
+        }
+        xml.push_str(&format!("<guid isPermaLink=\"false\">{}</guid>\n", escape(&episode.id)));
+        xml.push_str(&element("pubDate", &episode.published_at.to_rfc2822()));
+        // The size of externally hosted audio is unknown; 0 is what feeds use for that
+        xml.push_str(&format!(
+            "<enclosure url=\"{}\" length=\"0\" type=\"{}\"/>\n",
+            escape(&episode.audio_url),
+            enclosure_type(&episode.audio_url)
+        ));
+        if let Some(duration) = episode.duration {
+            xml.push_str(&element("itunes:duration", &duration.to_string()));
+        }
+        if let Some(season) = episode.season_number {
+            xml.push_str(&element("itunes:season", &season.to_string()));
+        }
+        if let Some(number) = episode.episode_number {
+            xml.push_str(&element("itunes:episode", &number.to_string()));
+        }
+        for transcript in &episode.transcripts {
+            xml.push_str(&format!(
+                "<podcast:transcript url=\"{}\" type=\"{}\"",
+                escape(&transcript.url),
+                transcript.mime_type
+            ));
+            if let Some(language) = &transcript.language {
+                xml.push_str(&format!(" language=\"{}\"", escape(language)));
+            }
+            xml.push_str("/>\n");
+        }
+        xml.push_str("</item>\n");
+    }
+
+    xml.push_str("</channel>\n</rss>\n");
+    xml
+}
+
+fn element(name: &str, value: &str) -> String {
+    format!("<{}>{}</{}>\n", name, escape(value), name)
+}
+
+fn enclosure_type(audio_url: &str) -> &'static str {
+    let path = audio_url.split(['?', '#']).next().unwrap_or_default();
+    match path.rsplit_once('.').map(|(_, ext)| ext.to_ascii_lowercase()).as_deref() {
+        Some("m4a") | Some("mp4") => "audio/mp4",
+        Some("wav") => "audio/wav",
+        Some("ogg") => "audio/ogg",
+        Some("flac") => "audio/flac",
+        _ => "audio/mpeg",
+    }
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
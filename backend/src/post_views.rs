@@ -0,0 +1,220 @@
+use axum::http::HeaderMap;
+use chrono::{DateTime, DurationRound, Utc};
+use sha2::{Digest, Sha256};
+use tracing::{error, info};
+use uuid::Uuid;
+
+use crate::database::Database;
+
+/// Set of post ids with view counters waiting to be flushed.
+const PENDING_KEY: &str = "post_views:pending";
+
+fn counters_key(post_id: Uuid) -> String {
+    format!("post_views:{}", post_id)
+}
+
+fn viewers_key(post_id: Uuid) -> String {
+    format!("post_viewers:{}", post_id)
+}
+
+/// The client IP as forwarded by the proxy in front of the API.
+fn client_ip(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get("x-forwarded-for")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.split(',').next())
+        .or_else(|| headers.get("x-real-ip").and_then(|value| value.to_str().ok()))
+        .map(str::trim)
+        .filter(|ip| !ip.is_empty())
+}
+
+/// A hash of the client IP, to tell whether two requests came from the same network without
+/// keeping the address.
+pub fn ip_hash(headers: &HeaderMap) -> Option<String> {
+    let digest = Sha256::digest(format!("ip|{}", client_ip(headers)?).as_bytes());
+    Some(hex::encode(&digest[..12]))
+}
+
+/// Identify a viewer: the user id when signed in, otherwise a hash of client IP and user agent.
+pub fn viewer_key(viewer_id: Option<&str>, headers: &HeaderMap) -> String {
+    if let Some(viewer_id) = viewer_id {
+        return format!("user:{}", viewer_id);
+    }
+
+    let ip = client_ip(headers).unwrap_or("unknown");
+    let user_agent = headers
+        .get("user-agent")
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or("");
+
+    let digest = Sha256::digest(format!("{}|{}", ip, user_agent).as_bytes());
+    format!("anon:{}", hex::encode(&digest[..12]))
+}
+
+/// Count a view. Views are buffered in Redis and flushed hourly; without Redis they are
+/// written straight to the stats tables.
+pub async fn record_view(db: &Database, post_id: Uuid, viewer_key: &str, is_subscriber: bool) {
+    let audience_field = if is_subscriber {
+        "subscriber_views"
+    } else {
+        "non_subscriber_views"
+    };
+
+    if let Some(redis) = &db.redis {
+        let mut redis = redis.clone();
+        let counters = counters_key(post_id);
+        let buffered = async {
+            redis.hincr_by(&counters, "views", 1).await?;
+            redis.hincr_by(&counters, audience_field, 1).await?;
+            redis.sadd(&viewers_key(post_id), viewer_key).await?;
+            redis.sadd(PENDING_KEY, &post_id.to_string()).await?;
+            anyhow::Ok(())
+        }
+        .await;
+
+        if buffered.is_ok() {
+            return;
+        }
+    }
+
+    let (subscriber_views, non_subscriber_views) = if is_subscriber { (1, 0) } else { (0, 1) };
+    if let Err(e) = write_stats(
+        db,
+        post_id,
+        current_hour(),
+        1,
+        subscriber_views,
+        non_subscriber_views,
+        &[viewer_key.to_string()],
+    )
+    .await
+    {
+        error!("Failed to record view for post {}: {:?}", post_id, e);
+    }
+}
+
+/// Views buffered in Redis that have not been flushed yet.
+pub async fn pending_counts(db: &Database, post_id: Uuid) -> (i64, i64, i64) {
+    let redis = match &db.redis {
+        Some(redis) => redis,
+        None => return (0, 0, 0),
+    };
+
+    let counters = redis
+        .clone()
+        .hgetall(&counters_key(post_id))
+        .await
+        .unwrap_or_default();
+
+    (
+        counters.get("views").copied().unwrap_or(0),
+        counters.get("subscriber_views").copied().unwrap_or(0),
+        counters.get("non_subscriber_views").copied().unwrap_or(0),
+    )
+}
+
+/// Move buffered view counters from Redis into `post_view_stats` and `post_viewers`.
+pub async fn flush_pending(db: &Database) -> anyhow::Result<usize> {
+    let redis = match &db.redis {
+        Some(redis) => redis,
+        None => return Ok(0),
+    };
+    let mut redis = redis.clone();
+
+    let post_ids = redis.take_set(PENDING_KEY).await?;
+    let hour = current_hour();
+    let mut flushed = 0;
+
+    for post_id in post_ids {
+        let post_id = match Uuid::parse_str(&post_id) {
+            Ok(post_id) => post_id,
+            Err(_) => continue,
+        };
+
+        let counters = redis.take_hash(&counters_key(post_id)).await?;
+        let viewers = redis.take_set(&viewers_key(post_id)).await?;
+
+        let views = counters.get("views").copied().unwrap_or(0);
+        if views == 0 && viewers.is_empty() {
+            continue;
+        }
+
+        write_stats(
+            db,
+            post_id,
+            hour,
+            views,
+            counters.get("subscriber_views").copied().unwrap_or(0),
+            counters.get("non_subscriber_views").copied().unwrap_or(0),
+            &viewers,
+        )
+        .await?;
+        flushed += 1;
+    }
+
+    if flushed > 0 {
+        info!("Flushed view counters for {} posts", flushed);
+    }
+
+    Ok(flushed)
+}
+
+async fn write_stats(
+    db: &Database,
+    post_id: Uuid,
+    hour: DateTime<Utc>,
+    views: i64,
+    subscriber_views: i64,
+    non_subscriber_views: i64,
+    viewers: &[String],
+) -> anyhow::Result<()> {
+    let mut tx = db.pool.begin().await?;
+
+    // Posts deleted since the views were buffered are skipped by the EXISTS guard.
+    let new_viewers = sqlx::query_scalar::<_, i64>(
+        r#"
+        WITH inserted AS (
+            INSERT INTO post_viewers (post_id, viewer_key)
+            SELECT $1, viewer_key
+            FROM UNNEST($2::TEXT[]) AS viewer_key
+            WHERE EXISTS (SELECT 1 FROM posts WHERE id = $1)
+            ON CONFLICT (post_id, viewer_key) DO NOTHING
+            RETURNING 1
+        )
+        SELECT COUNT(*) FROM inserted
+        "#,
+    )
+    .bind(post_id)
+    .bind(viewers)
+    .fetch_one(&mut tx)
+    .await?;
+
+    sqlx::query(
+        r#"
+        INSERT INTO post_view_stats (post_id, hour, views, unique_viewers, subscriber_views, non_subscriber_views)
+        SELECT $1, $2, $3, $4, $5, $6
+        WHERE EXISTS (SELECT 1 FROM posts WHERE id = $1)
+        ON CONFLICT (post_id, hour) DO UPDATE
+        SET views = post_view_stats.views + EXCLUDED.views,
+            unique_viewers = post_view_stats.unique_viewers + EXCLUDED.unique_viewers,
+            subscriber_views = post_view_stats.subscriber_views + EXCLUDED.subscriber_views,
+            non_subscriber_views = post_view_stats.non_subscriber_views + EXCLUDED.non_subscriber_views
+        "#,
+    )
+    .bind(post_id)
+    .bind(hour)
+    .bind(views)
+    .bind(new_viewers)
+    .bind(subscriber_views)
+    .bind(non_subscriber_views)
+    .execute(&mut tx)
+    .await?;
+
+    tx.commit().await?;
+    Ok(())
+}
+
+fn current_hour() -> DateTime<Utc> {
+    let now = Utc::now();
+    now.duration_trunc(chrono::Duration::hours(1)).unwrap_or(now)
+}
@@ -0,0 +1,99 @@
+use serde_json::{json, Value};
+use tracing::warn;
+
+use crate::database::Database;
+
+/// What a live update carries; sent to clients as the message's `type`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LiveEvent {
+    /// A notification just stored for the user.
+    Notification,
+    /// The user's badge counters, sent on connect and whenever one of them changes.
+    Counters,
+    /// A direct message in one of the user's conversations.
+    Message,
+    /// Someone in a conversation received or read its messages.
+    Receipt,
+    /// Someone in a conversation started or stopped typing.
+    Typing,
+}
+
+impl LiveEvent {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            LiveEvent::Notification => "notification",
+            LiveEvent::Counters => "counters",
+            LiveEvent::Message => "message",
+            LiveEvent::Receipt => "receipt",
+            LiveEvent::Typing => "typing",
+        }
+    }
+}
+
+/// Each user has a channel of their own; every API instance holding one of their sockets
+/// subscribes to it, so an update published anywhere reaches all of them.
+pub fn user_channel(user_id: &str) -> String {
+    format!("user:{}:live", user_id)
+}
+
+/// The message a socket receives for `event`.
+pub fn live_message(event: LiveEvent, data: Value) -> String {
+    json!({ "type": event.as_str(), "data": data }).to_string()
+}
+
+/// Push an update to the sockets `user_id` has open, returning how many received it. Without
+/// Redis there are no sockets to push to; failures are logged and never fail the caller.
+pub async fn publish(db: &Database, user_id: &str, event: LiveEvent, data: Value) -> usize {
+    let Some(redis) = &db.redis else {
+        return 0;
+    };
+    let mut redis = redis.clone();
+    match redis.publish(&user_channel(user_id), &live_message(event, data)).await {
+        Ok(receivers) => receivers,
+        Err(e) => {
+            warn!("Failed to publish {} update for {}: {}", event.as_str(), user_id, e);
+            0
+        }
+    }
+}
+
+/// The counters shown on the user's badges.
+pub async fn live_counters(db: &Database, user_id: &str) -> Result<Value, sqlx::Error> {
+    let unread_notifications = sqlx::query_scalar::<_, i64>(
+        "SELECT COUNT(*) FROM notifications WHERE user_id = $1 AND read_at IS NULL",
+    )
+    .bind(user_id)
+    .fetch_one(&db.pool)
+    .await?;
+    let unread_messages = sqlx::query_scalar::<_, i64>(
+        r#"
+        SELECT COUNT(*)
+        FROM conversation_participants p
+        JOIN messages m ON m.conversation_id = p.conversation_id
+        WHERE p.user_id = $1 AND m.sender_id <> p.user_id
+          AND COALESCE(p.request_status, 'ACCEPTED') = 'ACCEPTED'
+          AND m.created_at > COALESCE(p.last_read_at, '-infinity')
+        "#,
+    )
+    .bind(user_id)
+    .fetch_one(&db.pool)
+    .await?;
+
+    Ok(json!({
+        "unreadNotifications": unread_notifications,
+        "unreadMessages": unread_messages,
+    }))
+}
+
+/// Send `user_id` their current counters after something changed them.
+pub async fn publish_counters(db: &Database, user_id: &str) {
+    if db.redis.is_none() {
+        return;
+    }
+    match live_counters(db, user_id).await {
+        Ok(counters) => {
+            publish(db, user_id, LiveEvent::Counters, counters).await;
+        }
+        Err(e) => warn!("Failed to count live counters of {}: {}", user_id, e),
+    }
+}
@@ -1,9 +1,11 @@
-use redis::{aio::MultiplexedConnection, AsyncCommands, Client};
-use std::time::Duration;
+use futures_util::{Stream, StreamExt};
+use redis::{aio::MultiplexedConnection, AsyncCommands, Client, Msg};
+use std::collections::HashMap;
 use tracing::{error, info};
 
 #[derive(Clone)]
 pub struct RedisClient {
+    client: Client,
     connection: MultiplexedConnection,
 }
 
@@ -22,7 +24,7 @@ impl RedisClient {
 
         info!("Successfully connected to Redis");
 
-        Ok(Self { connection })
+        Ok(Self { client, connection })
     }
 
     /// Get a value from Redis
@@ -48,6 +50,7 @@ impl RedisClient {
     }
 
     /// Set a value in Redis without expiration
+    #[allow(dead_code)]
     pub async fn set(&mut self, key: &str, value: &str) -> anyhow::Result<()> {
         match self.connection.set(key, value).await {
             Ok(()) => Ok(()),
@@ -83,6 +86,7 @@ impl RedisClient {
     }
 
     /// Increment a counter in Redis
+    #[allow(dead_code)]
     pub async fn incr(&mut self, key: &str) -> anyhow::Result<i64> {
         match self.connection.incr(key, 1).await {
             Ok(value) => Ok(value),
@@ -94,6 +98,7 @@ impl RedisClient {
     }
 
     /// Check if a key exists
+    #[allow(dead_code)]
     pub async fn exists(&mut self, key: &str) -> anyhow::Result<bool> {
         match self.connection.exists(key).await {
             Ok(exists) => Ok(exists),
@@ -105,6 +110,7 @@ impl RedisClient {
     }
 
     /// Set expiration on a key
+    #[allow(dead_code)]
     pub async fn expire(&mut self, key: &str, seconds: usize) -> anyhow::Result<()> {
         match self.connection.expire(key, seconds).await {
             Ok(()) => Ok(()),
@@ -115,6 +121,229 @@ impl RedisClient {
         }
     }
 
+    /// Increment a field of a hash
+    pub async fn hincr_by(&mut self, key: &str, field: &str, delta: i64) -> anyhow::Result<i64> {
+        match self.connection.hincr(key, field, delta).await {
+            Ok(value) => Ok(value),
+            Err(e) => {
+                error!("Redis HINCRBY error for key '{}': {}", key, e);
+                Err(e.into())
+            }
+        }
+    }
+
+    /// Read all fields of a hash of counters
+    pub async fn hgetall(&mut self, key: &str) -> anyhow::Result<HashMap<String, i64>> {
+        match self.connection.hgetall(key).await {
+            Ok(values) => Ok(values),
+            Err(e) => {
+                error!("Redis HGETALL error for key '{}': {}", key, e);
+                Err(e.into())
+            }
+        }
+    }
+
+    /// Read and delete a hash of counters in one transaction
+    pub async fn take_hash(&mut self, key: &str) -> anyhow::Result<HashMap<String, i64>> {
+        let result: Result<(HashMap<String, i64>,), _> = redis::pipe()
+            .atomic()
+            .hgetall(key)
+            .del(key)
+            .ignore()
+            .query_async(&mut self.connection)
+            .await;
+        match result {
+            Ok((values,)) => Ok(values),
+            Err(e) => {
+                error!("Redis take hash error for key '{}': {}", key, e);
+                Err(e.into())
+            }
+        }
+    }
+
+    /// Add a member to a set, returning whether it was newly added
+    pub async fn sadd(&mut self, key: &str, member: &str) -> anyhow::Result<bool> {
+        match self.connection.sadd(key, member).await {
+            Ok(added) => Ok(added),
+            Err(e) => {
+                error!("Redis SADD error for key '{}': {}", key, e);
+                Err(e.into())
+            }
+        }
+    }
+
+    /// Read and delete a set in one transaction
+    pub async fn take_set(&mut self, key: &str) -> anyhow::Result<Vec<String>> {
+        let result: Result<(Vec<String>,), _> = redis::pipe()
+            .atomic()
+            .smembers(key)
+            .del(key)
+            .ignore()
+            .query_async(&mut self.connection)
+            .await;
+        match result {
+            Ok((members,)) => Ok(members),
+            Err(e) => {
+                error!("Redis take set error for key '{}': {}", key, e);
+                Err(e.into())
+            }
+        }
+    }
+
+    /// Read all members of a set
+    pub async fn smembers(&mut self, key: &str) -> anyhow::Result<Vec<String>> {
+        match self.connection.smembers(key).await {
+            Ok(members) => Ok(members),
+            Err(e) => {
+                error!("Redis SMEMBERS error for key '{}': {}", key, e);
+                Err(e.into())
+            }
+        }
+    }
+
+    /// Replace a sorted set with the given members and set its expiration
+    pub async fn zreplace(
+        &mut self,
+        key: &str,
+        members: &[(f64, String)],
+        seconds: usize,
+    ) -> anyhow::Result<()> {
+        let mut pipe = redis::pipe();
+        pipe.atomic().del(key).ignore();
+        if !members.is_empty() {
+            pipe.zadd_multiple(key, members).ignore();
+        }
+        pipe.expire(key, seconds).ignore();
+        match pipe.query_async::<_, ()>(&mut self.connection).await {
+            Ok(()) => Ok(()),
+            Err(e) => {
+                error!("Redis sorted set replace error for key '{}': {}", key, e);
+                Err(e.into())
+            }
+        }
+    }
+
+    /// Add a member to each of the given sorted sets that already exists, keeping only the
+    /// `max_len` highest scored members of each
+    pub async fn zadd_existing(
+        &mut self,
+        keys: &[String],
+        member: &str,
+        score: f64,
+        max_len: usize,
+    ) -> anyhow::Result<()> {
+        if keys.is_empty() {
+            return Ok(());
+        }
+        let script = redis::Script::new(
+            r#"
+            for _, key in ipairs(KEYS) do
+                if redis.call('EXISTS', key) == 1 then
+                    redis.call('ZADD', key, ARGV[1], ARGV[2])
+                    redis.call('ZREMRANGEBYRANK', key, 0, -tonumber(ARGV[3]) - 1)
+                end
+            end
+            return 0
+            "#,
+        );
+        let mut invocation = script.prepare_invoke();
+        for key in keys {
+            invocation.key(key);
+        }
+        let result: Result<i64, _> = invocation
+            .arg(score)
+            .arg(member)
+            .arg(max_len)
+            .invoke_async(&mut self.connection)
+            .await;
+        match result {
+            Ok(_) => Ok(()),
+            Err(e) => {
+                error!("Redis ZADD error for {} keys: {}", keys.len(), e);
+                Err(e.into())
+            }
+        }
+    }
+
+    /// Remove a member from each of the given sorted sets
+    pub async fn zrem_all(&mut self, keys: &[String], member: &str) -> anyhow::Result<()> {
+        let mut pipe = redis::pipe();
+        for key in keys {
+            pipe.zrem(key, member).ignore();
+        }
+        match pipe.query_async::<_, ()>(&mut self.connection).await {
+            Ok(()) => Ok(()),
+            Err(e) => {
+                error!("Redis ZREM error for {} keys: {}", keys.len(), e);
+                Err(e.into())
+            }
+        }
+    }
+
+    /// Count the members of a sorted set
+    pub async fn zcard(&mut self, key: &str) -> anyhow::Result<usize> {
+        match self.connection.zcard(key).await {
+            Ok(count) => Ok(count),
+            Err(e) => {
+                error!("Redis ZCARD error for key '{}': {}", key, e);
+                Err(e.into())
+            }
+        }
+    }
+
+    /// Read members of a sorted set with their scores, highest first, starting at `max`
+    /// (inclusive; `None` for the top)
+    pub async fn zrevrange_from(
+        &mut self,
+        key: &str,
+        max: Option<f64>,
+        offset: isize,
+        count: isize,
+    ) -> anyhow::Result<Vec<(String, f64)>> {
+        let max = max.map_or_else(|| "+inf".to_string(), |max| max.to_string());
+        match self
+            .connection
+            .zrevrangebyscore_limit_withscores(key, max, "-inf", offset, count)
+            .await
+        {
+            Ok(members) => Ok(members),
+            Err(e) => {
+                error!("Redis ZREVRANGEBYSCORE error for key '{}': {}", key, e);
+                Err(e.into())
+            }
+        }
+    }
+
+    /// Publish a message on a pub/sub channel, returning how many subscribers received it
+    pub async fn publish(&mut self, channel: &str, message: &str) -> anyhow::Result<usize> {
+        match self.connection.publish::<_, _, usize>(channel, message).await {
+            Ok(receivers) => Ok(receivers),
+            Err(e) => {
+                error!("Redis PUBLISH error for channel '{}': {}", channel, e);
+                Err(e.into())
+            }
+        }
+    }
+
+    /// Subscribe to a pub/sub channel. Subscriptions need a connection of their own, which
+    /// is closed when the returned stream is dropped.
+    pub async fn subscribe(&self, channel: &str) -> anyhow::Result<impl Stream<Item = String>> {
+        let mut pubsub = self
+            .client
+            .get_async_connection()
+            .await
+            .map_err(|e| {
+                error!("Failed to open Redis pub/sub connection: {}", e);
+                e
+            })?
+            .into_pubsub();
+        pubsub.subscribe(channel).await?;
+
+        Ok(pubsub
+            .into_on_message()
+            .filter_map(|message: Msg| async move { message.get_payload::<String>().ok() }))
+    }
+
     /// Get Redis statistics
     pub async fn get_stats(&mut self) -> anyhow::Result<serde_json::Value> {
         let info: String = redis::cmd("INFO")
@@ -0,0 +1,163 @@
+use std::{
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+    time::Duration,
+};
+
+use anyhow::{anyhow, bail};
+
+// Fetches of URLs that users hand us: feeds, images in imported articles, product files on a
+// creator's host. Every host, including each redirect's, is resolved first and must only have
+// public addresses; the request then goes to exactly those addresses, so a name that changes
+// its answer in between can't point us at loopback, the private network or cloud metadata.
+
+/// Redirects followed before giving up.
+const MAX_REDIRECTS: usize = 5;
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// GET `url` and return the successful response, after any redirects. `timeout` covers each
+/// request, reading its body included.
+pub async fn get(url: &str, timeout: Duration) -> anyhow::Result<reqwest::Response> {
+    let mut url = reqwest::Url::parse(url.trim()).map_err(|_| anyhow!("Unsupported URL: {}", url))?;
+    for _ in 0..=MAX_REDIRECTS {
+        let response = client_for(&url, timeout).await?.get(url.clone()).send().await?;
+        if !response.status().is_redirection() {
+            return Ok(response.error_for_status()?);
+        }
+        let location = response
+            .headers()
+            .get(reqwest::header::LOCATION)
+            .and_then(|location| location.to_str().ok())
+            .ok_or_else(|| anyhow!("{} redirected without a location", url))?;
+        url = url.join(location)?;
+    }
+    bail!("{} redirected too many times", url)
+}
+
+/// Read a downloaded body, giving up once it grows past `max_bytes`; hosts that send no
+/// Content-Length are not trusted to stop.
+pub async fn read_body(
+    mut response: reqwest::Response,
+    url: &str,
+    max_bytes: usize,
+) -> anyhow::Result<Vec<u8>> {
+    if response
+        .content_length()
+        .is_some_and(|length| length > max_bytes as u64)
+    {
+        bail!("{} is too large to import", url);
+    }
+    let mut bytes = Vec::new();
+    while let Some(chunk) = response.chunk().await? {
+        if bytes.len() + chunk.len() > max_bytes {
+            bail!("{} is too large to import", url);
+        }
+        bytes.extend_from_slice(&chunk);
+    }
+    Ok(bytes)
+}
+
+/// A client that only connects to the public addresses `url`'s host resolves to.
+async fn client_for(url: &reqwest::Url, timeout: Duration) -> anyhow::Result<reqwest::Client> {
+    if !matches!(url.scheme(), "http" | "https") {
+        bail!("Unsupported URL: {}", url);
+    }
+    let host = url.host_str().ok_or_else(|| anyhow!("Unsupported URL: {}", url))?;
+    let port = url
+        .port_or_known_default()
+        .ok_or_else(|| anyhow!("Unsupported URL: {}", url))?;
+
+    let literal = host.trim_start_matches('[').trim_end_matches(']').parse::<IpAddr>();
+    let addresses: Vec<SocketAddr> = match literal {
+        Ok(ip) => vec![SocketAddr::new(ip, port)],
+        Err(_) => tokio::net::lookup_host((host, port)).await?.collect(),
+    };
+    if addresses.is_empty() || !addresses.iter().all(|address| is_public(address.ip())) {
+        bail!("{} does not resolve to a public address", host);
+    }
+
+    let mut builder = reqwest::Client::builder()
+        .timeout(timeout)
+        .connect_timeout(CONNECT_TIMEOUT)
+        .redirect(reqwest::redirect::Policy::none())
+        .no_proxy();
+    if literal.is_err() {
+        builder = builder.resolve_to_addrs(host, &addresses);
+    }
+    Ok(builder.build()?)
+}
+
+/// Whether `ip` is reachable on the public internet, rather than loopback, a private or
+/// link-local network, or a reserved range.
+fn is_public(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => is_public_v4(ip),
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(ip) => is_public_v4(ip),
+            None => is_public_v6(ip),
+        },
+    }
+}
+
+fn is_public_v4(ip: Ipv4Addr) -> bool {
+    let [a, b, c, _] = ip.octets();
+    !(ip.is_unspecified()
+        || ip.is_loopback()
+        || ip.is_private()
+        || ip.is_link_local()
+        || ip.is_broadcast()
+        || ip.is_documentation()
+        || ip.is_multicast()
+        || a == 0
+        // Carrier-grade NAT
+        || (a == 100 && (64..128).contains(&b))
+        // IETF protocol assignments
+        || (a == 192 && b == 0 && c == 0)
+        // Benchmarking
+        || (a == 198 && (18..20).contains(&b))
+        // Reserved
+        || a >= 240)
+}
+
+fn is_public_v6(ip: Ipv6Addr) -> bool {
+    let first = ip.segments()[0];
+    !(ip.is_unspecified()
+        || ip.is_loopback()
+        || ip.is_multicast()
+        // Unique local
+        || (first & 0xfe00) == 0xfc00
+        // Link-local
+        || (first & 0xffc0) == 0xfe80
+        // Documentation
+        || (first == 0x2001 && ip.segments()[1] == 0x0db8))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rejects_internal_addresses() {
+        for ip in [
+            "127.0.0.1",
+            "10.1.2.3",
+            "172.16.0.1",
+            "192.168.1.1",
+            "169.254.169.254",
+            "100.64.0.1",
+            "0.0.0.0",
+            "::1",
+            "fd00::1",
+            "fe80::1",
+            "::ffff:127.0.0.1",
+        ] {
+            assert!(!is_public(ip.parse().unwrap()), "{} should not be public", ip);
+        }
+    }
+
+    #[test]
+    fn accepts_public_addresses() {
+        for ip in ["93.184.216.34", "8.8.8.8", "2606:4700:4700::1111"] {
+            assert!(is_public(ip.parse().unwrap()), "{} should be public", ip);
+        }
+    }
+}
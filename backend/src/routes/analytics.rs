@@ -1,25 +1,340 @@
+use std::collections::BTreeMap;
+
 use axum::{
-    extract::{Query, State},
-    http::StatusCode,
-    response::Json,
-    routing::get,
+    extract::{Path, Query, State},
+    http::{header, StatusCode},
+    response::{IntoResponse, Json, Response},
+    routing::{get, post},
     Router,
 };
-use chrono::{Duration, Utc};
-use serde::Deserialize;
+
+use chrono::{DateTime, Datelike, Duration, Months, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
 use serde_json::json;
 use sqlx::Row;
 use uuid::Uuid;
 
-use crate::{auth::Claims, database::Database};
+use crate::{
+    auth::Claims,
+    database::Database,
+    routes::cohorts::get_cohorts,
+    routes::tracking::{daily_traffic, subject_code, track_events, DailyTraffic, TrackedEvent, DONATE_TARGET},
+};
 
 #[derive(Debug, Deserialize)]
 pub struct AnalyticsQuery {
     pub period: Option<String>,
 }
 
+/// A date range split into days or months. `to` is inclusive.
+#[derive(Debug, Deserialize)]
+pub struct RangeQuery {
+    /// `day` (the default) or `month`.
+    pub interval: Option<String>,
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+    /// `csv` to download instead of JSON, where supported.
+    pub format: Option<String>,
+}
+
+impl RangeQuery {
+    /// The interval and range asked for: the last 30 days or 12 months unless given, and at most
+    /// a year of days or five years of months.
+    pub(crate) fn resolve(&self) -> Result<(Interval, NaiveDate, NaiveDate), StatusCode> {
+        let interval = Interval::parse(self.interval.as_deref()).ok_or(StatusCode::BAD_REQUEST)?;
+        let to = self.to.unwrap_or_else(|| Utc::now().date_naive());
+        let from = self.from.unwrap_or(match interval {
+            Interval::Day => to - Duration::days(29),
+            Interval::Month => to.checked_sub_months(Months::new(11)).unwrap_or(to),
+        });
+        let max_days = match interval {
+            Interval::Day => 366,
+            Interval::Month => 366 * 5,
+        };
+        if from > to || (to - from).num_days() > max_days {
+            return Err(StatusCode::BAD_REQUEST);
+        }
+        Ok((interval, from, to))
+    }
+
+    fn wants_csv(&self) -> bool {
+        self.format.as_deref().is_some_and(|format| format.eq_ignore_ascii_case("csv"))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Interval {
+    Day,
+    Month,
+}
+
+impl Interval {
+    fn parse(value: Option<&str>) -> Option<Self> {
+        match value.map(|value| value.trim().to_ascii_lowercase()).as_deref() {
+            None | Some("day") | Some("daily") => Some(Interval::Day),
+            Some("month") | Some("monthly") => Some(Interval::Month),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn as_str(self) -> &'static str {
+        match self {
+            Interval::Day => "day",
+            Interval::Month => "month",
+        }
+    }
+
+    /// The start of the period `date` falls in.
+    pub(crate) fn truncate(self, date: NaiveDate) -> NaiveDate {
+        match self {
+            Interval::Day => date,
+            Interval::Month => date.with_day(1).unwrap_or(date),
+        }
+    }
+
+    fn next(self, period: NaiveDate) -> Option<NaiveDate> {
+        match self {
+            Interval::Day => period.succ_opt(),
+            Interval::Month => period.checked_add_months(Months::new(1)),
+        }
+    }
+}
+
+/// How often a creator gets the performance report email; stored in
+/// `analytics_report_settings.frequency`. Creators opt in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum ReportFrequency {
+    #[default]
+    Off,
+    Weekly,
+    Monthly,
+}
+
+impl ReportFrequency {
+    pub(crate) fn as_str(self) -> &'static str {
+        match self {
+            ReportFrequency::Off => "OFF",
+            ReportFrequency::Weekly => "WEEKLY",
+            ReportFrequency::Monthly => "MONTHLY",
+        }
+    }
+
+    pub(crate) fn parse(value: &str) -> Option<Self> {
+        match value {
+            "OFF" => Some(ReportFrequency::Off),
+            "WEEKLY" => Some(ReportFrequency::Weekly),
+            "MONTHLY" => Some(ReportFrequency::Monthly),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct ReportSettingsRequest {
+    frequency: ReportFrequency,
+}
+
+/// Money moved in one slice of a creator's earnings, in minor units.
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct EarningsTotals {
+    pub(crate) transactions: i64,
+    gross_cents: i64,
+    fee_cents: i64,
+    net_cents: i64,
+    refunded_cents: i64,
+    /// Net less what refunds took back from the creator.
+    pub(crate) net_after_refunds_cents: i64,
+}
+
+impl EarningsTotals {
+    fn add(&mut self, other: &EarningsTotals) {
+        self.transactions += other.transactions;
+        self.gross_cents += other.gross_cents;
+        self.fee_cents += other.fee_cents;
+        self.net_cents += other.net_cents;
+        self.refunded_cents += other.refunded_cents;
+        self.net_after_refunds_cents += other.net_after_refunds_cents;
+    }
+}
+
+/// Earnings of one source in one currency and period.
+struct EarningsRow {
+    period: NaiveDate,
+    currency: String,
+    product_type: String,
+    totals: EarningsTotals,
+}
+
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct EarningsPoint {
+    period: NaiveDate,
+    #[serde(flatten)]
+    totals: EarningsTotals,
+    /// Net after refunds of each source.
+    by_source: BTreeMap<String, i64>,
+}
+
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct CurrencyEarnings {
+    currency: String,
+    totals: EarningsTotals,
+    by_source: BTreeMap<String, EarningsTotals>,
+    series: Vec<EarningsPoint>,
+}
+
 pub fn analytics_routes() -> Router<Database> {
-    Router::new().route("/", get(get_dashboard))
+    Router::new()
+        .route("/", get(get_dashboard))
+        .route("/earnings", get(get_earnings))
+        .route("/audience", get(get_audience))
+        .route("/reports", get(get_report_settings).put(update_report_settings))
+        .route("/track", post(track_events))
+        .route("/content", get(get_content_performance))
+        .route("/cohorts", get(get_cohorts))
+        .route("/funnels/campaigns/:id", get(get_campaign_funnel))
+        .route("/funnels/products/:id", get(get_product_funnel))
+}
+
+/// A creator's settled earnings from every source, from the ledger, with refunds counted in the
+/// period they were made. `to` is inclusive.
+async fn earnings_rows(
+    db: &Database,
+    creator_id: &str,
+    interval: Interval,
+    from: NaiveDate,
+    to: NaiveDate,
+) -> Result<Vec<EarningsRow>, StatusCode> {
+    let until = to.succ_opt().unwrap_or(to);
+    let charges = sqlx::query_as::<_, (NaiveDate, String, String, i64, i64, i64, i64)>(
+        r#"
+        SELECT date_trunc($2, COALESCE(settled_at, created_at))::DATE, currency, product_type,
+               COUNT(*)::BIGINT, SUM(gross_cents)::BIGINT, SUM(fee_cents)::BIGINT, SUM(net_cents)::BIGINT
+        FROM ledger_entries
+        WHERE creator_id = $1
+          AND status IN ('SETTLED', 'DISPUTED')
+          AND COALESCE(settled_at, created_at) >= $3::DATE
+          AND COALESCE(settled_at, created_at) < $4::DATE
+        GROUP BY 1, 2, 3
+        "#,
+    )
+    .bind(creator_id)
+    .bind(interval.as_str())
+    .bind(from)
+    .bind(until)
+    .fetch_all(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to load earnings of {}: {}", creator_id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    let refunds = sqlx::query_as::<_, (NaiveDate, String, String, i64, i64)>(
+        r#"
+        SELECT date_trunc($2, r.created_at)::DATE, r.currency, l.product_type,
+               SUM(r.amount_cents)::BIGINT, SUM(r.amount_cents - r.fee_cents)::BIGINT
+        FROM refunds r
+        JOIN ledger_entries l ON l.id = r.ledger_entry_id
+        WHERE r.creator_id = $1
+          AND r.status <> 'FAILED'
+          AND r.created_at >= $3::DATE
+          AND r.created_at < $4::DATE
+        GROUP BY 1, 2, 3
+        "#,
+    )
+    .bind(creator_id)
+    .bind(interval.as_str())
+    .bind(from)
+    .bind(until)
+    .fetch_all(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to load refunds of {}: {}", creator_id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    let charges = charges.into_iter().map(|(period, currency, product_type, transactions, gross, fee, net)| {
+        EarningsRow {
+            period,
+            currency: currency.to_ascii_uppercase(),
+            product_type,
+            totals: EarningsTotals {
+                transactions,
+                gross_cents: gross,
+                fee_cents: fee,
+                net_cents: net,
+                refunded_cents: 0,
+                net_after_refunds_cents: net,
+            },
+        }
+    });
+    let refunds = refunds.into_iter().map(|(period, currency, product_type, refunded, refunded_net)| {
+        EarningsRow {
+            period,
+            currency: currency.to_ascii_uppercase(),
+            product_type,
+            totals: EarningsTotals {
+                refunded_cents: refunded,
+                net_after_refunds_cents: -refunded_net,
+                ..Default::default()
+            },
+        }
+    });
+    Ok(charges.chain(refunds).collect())
+}
+
+// Daily or monthly earnings per currency, broken down by donations, memberships, products,
+// tickets and paid messages. Periods without earnings are filled in with zeros.
+async fn get_earnings(
+    State(db): State<Database>,
+    claims: Claims,
+    Query(query): Query<RangeQuery>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let (interval, from, to) = query.resolve()?;
+
+    let mut periods = Vec::new();
+    let mut period = interval.truncate(from);
+    while period <= to {
+        periods.push(period);
+        let Some(next) = interval.next(period) else {
+            break;
+        };
+        period = next;
+    }
+
+    let mut currencies = BTreeMap::<String, CurrencyEarnings>::new();
+    for row in earnings_rows(&db, &claims.sub, interval, from, to).await? {
+        let earnings = currencies.entry(row.currency.clone()).or_insert_with(|| CurrencyEarnings {
+            currency: row.currency.clone(),
+            series: periods
+                .iter()
+                .map(|&period| EarningsPoint {
+                    period,
+                    ..Default::default()
+                })
+                .collect(),
+            ..Default::default()
+        });
+        earnings.totals.add(&row.totals);
+        earnings.by_source.entry(row.product_type.clone()).or_default().add(&row.totals);
+        if let Some(point) = earnings.series.iter_mut().find(|point| point.period == row.period) {
+            point.totals.add(&row.totals);
+            *point.by_source.entry(row.product_type).or_default() += row.totals.net_after_refunds_cents;
+        }
+    }
+
+    Ok(Json(json!({
+        "success": true,
+        "data": {
+            "interval": interval.as_str(),
+            "from": from,
+            "to": to,
+            "currencies": currencies.into_values().collect::<Vec<_>>(),
+        }
+    })))
 }
 
 async fn get_dashboard(
@@ -83,20 +398,68 @@ async fn get_dashboard(
         })
         .collect::<Vec<_>>();
 
+    let today = Utc::now().date_naive();
+    let mut daily_revenue = BTreeMap::<NaiveDate, i64>::new();
+    for row in earnings_rows(&db, &claims.sub, Interval::Day, today - Duration::days(days.max(30) - 1), today).await? {
+        *daily_revenue.entry(row.period).or_default() += row.totals.net_after_refunds_cents;
+    }
+    let monthly_revenue_cents: i64 = daily_revenue
+        .range(today - Duration::days(29)..)
+        .map(|(_, cents)| cents)
+        .sum();
+
+    let traffic: BTreeMap<NaiveDate, DailyTraffic> =
+        daily_traffic(&db, &claims.sub, today - Duration::days(days - 1), today)
+            .await
+            .map_err(|e| {
+                tracing::error!("Failed to load traffic of {}: {}", claims.sub, e);
+                StatusCode::INTERNAL_SERVER_ERROR
+            })?
+            .into_iter()
+            .collect();
+    let page_views: i64 = traffic.values().map(|day| day.page_views).sum();
+    let clicks: i64 = traffic.values().map(|day| day.clicks).sum();
+
+    let step = (days.max(1) / 10).max(1);
     let trend_points = (0..days)
         .rev()
-        .step_by((days.max(1) / 10).max(1) as usize)
+        .step_by(step as usize)
         .map(|offset| {
-            let date = Utc::now() - Duration::days(offset as i64);
+            let date = today - Duration::days(offset);
+            // Each point covers the days since the previous one
+            let revenue_cents: i64 = daily_revenue
+                .range(date - Duration::days(step - 1)..=date)
+                .map(|(_, cents)| cents)
+                .sum();
             json!({
-                "date": date.date_naive().to_string(),
-                "revenue": 0,
+                "date": date.to_string(),
+                "revenue": revenue_cents as f64 / 100.0,
                 "subscribers": 0
             })
         })
         .collect::<Vec<_>>();
     let revenue_trend = trend_points.clone();
     let subscriber_trend = trend_points;
+    let traffic_trend = (0..days)
+        .rev()
+        .step_by(step as usize)
+        .map(|offset| {
+            let date = today - Duration::days(offset);
+            let mut point = DailyTraffic::default();
+            for day in traffic.range(date - Duration::days(step - 1)..=date).map(|(_, day)| day) {
+                point.page_views += day.page_views;
+                point.unique_visitors += day.unique_visitors;
+                point.clicks += day.clicks;
+            }
+            // Visitors are unique per day, so a point spanning several days can count one twice
+            json!({
+                "date": date.to_string(),
+                "pageViews": point.page_views,
+                "visitors": point.unique_visitors,
+                "clicks": point.clicks
+            })
+        })
+        .collect::<Vec<_>>();
 
     let response = json!({
         "success": true,
@@ -105,7 +468,7 @@ async fn get_dashboard(
                 "activeSubscribers": 0,
                 "newSubscribers": 0,
                 "canceledSubscribers": 0,
-                "monthlyRevenue": 0,
+                "monthlyRevenue": monthly_revenue_cents as f64 / 100.0,
                 "totalPosts": total_posts,
                 "totalPolls": 0,
                 "totalEvents": total_events,
@@ -114,11 +477,14 @@ async fn get_dashboard(
                 "completedGoals": 0,
                 "totalLikes": 0,
                 "totalComments": 0,
-                "totalDownloads": 0
+                "totalDownloads": 0,
+                "pageViews": page_views,
+                "clicks": clicks
             },
             "trends": {
                 "revenue": revenue_trend,
-                "subscribers": subscriber_trend
+                "subscribers": subscriber_trend,
+                "traffic": traffic_trend
             },
             "content": {
                 "postsInPeriod": total_posts,
@@ -130,3 +496,703 @@ async fn get_dashboard(
 
     Ok(Json(response))
 }
+
+/// New and total followers and subscribers in one period.
+#[derive(Debug, Serialize, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+struct AudiencePoint {
+    period: NaiveDate,
+    new_followers: i64,
+    total_followers: i64,
+    new_subscribers: i64,
+    ended_subscribers: i64,
+    total_subscribers: i64,
+}
+
+// Growth of a creator's followers and subscribers, the tiers subscribers are on, and where the
+// audience signed up from. Unfollows remove the follow, so follower totals count current
+// followers; a subscription that stopped being active counts until it was last updated.
+async fn get_audience(
+    State(db): State<Database>,
+    claims: Claims,
+    Query(query): Query<RangeQuery>,
+) -> Result<Response, StatusCode> {
+    let (interval, from, to) = query.resolve()?;
+    let step = format!("1 {}", interval.as_str());
+    let growth = sqlx::query_as::<_, AudiencePoint>(
+        r#"
+        SELECT
+            p.period::DATE AS period,
+            (SELECT COUNT(*) FROM follows f
+             WHERE f.following_id = $1 AND f.created_at >= p.period AND f.created_at < p.period + $4::INTERVAL) AS new_followers,
+            (SELECT COUNT(*) FROM follows f
+             WHERE f.following_id = $1 AND f.created_at < p.period + $4::INTERVAL) AS total_followers,
+            (SELECT COUNT(*) FROM subscriptions s
+             WHERE s.creator_id = $1 AND s.created_at >= p.period AND s.created_at < p.period + $4::INTERVAL) AS new_subscribers,
+            (SELECT COUNT(*) FROM subscriptions s
+             WHERE s.creator_id = $1 AND UPPER(s.status) <> 'ACTIVE'
+               AND s.updated_at >= p.period AND s.updated_at < p.period + $4::INTERVAL) AS ended_subscribers,
+            (SELECT COUNT(*) FROM subscriptions s
+             WHERE s.creator_id = $1 AND s.created_at < p.period + $4::INTERVAL
+               AND (UPPER(s.status) = 'ACTIVE' OR s.updated_at >= p.period + $4::INTERVAL)) AS total_subscribers
+        FROM generate_series($2::DATE, $3::DATE, $4::INTERVAL) AS p(period)
+        ORDER BY p.period
+        "#,
+    )
+    .bind(&claims.sub)
+    .bind(interval.truncate(from))
+    .bind(to)
+    .bind(&step)
+    .fetch_all(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to load audience growth of {}: {}", claims.sub, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    if query.wants_csv() {
+        return audience_csv(&growth);
+    }
+
+    let tiers = sqlx::query_as::<_, (Option<String>, i64)>(
+        r#"
+        SELECT tier_id, COUNT(*) FROM subscriptions
+        WHERE creator_id = $1 AND UPPER(status) = 'ACTIVE'
+        GROUP BY tier_id
+        ORDER BY 2 DESC
+        "#,
+    )
+    .bind(&claims.sub)
+    .fetch_all(&db.pool)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let countries = sqlx::query_as::<_, (Option<String>, i64, i64)>(
+        r#"
+        SELECT country, SUM(followers)::BIGINT, SUM(subscribers)::BIGINT
+        FROM (
+            SELECT u.signup_country AS country, 1 AS followers, 0 AS subscribers
+            FROM follows f JOIN users u ON u.id = f.follower_id
+            WHERE f.following_id = $1
+            UNION ALL
+            SELECT u.signup_country, 0, 1
+            FROM subscriptions s JOIN users u ON u.id = s.user_id
+            WHERE s.creator_id = $1 AND UPPER(s.status) = 'ACTIVE'
+        ) audience
+        GROUP BY country
+        ORDER BY SUM(followers) + SUM(subscribers) DESC
+        "#,
+    )
+    .bind(&claims.sub)
+    .fetch_all(&db.pool)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(json!({
+        "success": true,
+        "data": {
+            "interval": interval.as_str(),
+            "from": from,
+            "to": to,
+            "growth": growth,
+            "tiers": tiers
+                .into_iter()
+                .map(|(tier_id, subscribers)| json!({ "tierId": tier_id, "subscribers": subscribers }))
+                .collect::<Vec<_>>(),
+            // Users who signed up before countries were recorded have none
+            "countries": countries
+                .into_iter()
+                .map(|(country, followers, subscribers)| json!({
+                    "country": country,
+                    "followers": followers,
+                    "subscribers": subscribers,
+                }))
+                .collect::<Vec<_>>(),
+        }
+    }))
+    .into_response())
+}
+
+fn audience_csv(growth: &[AudiencePoint]) -> Result<Response, StatusCode> {
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    let write_error = |e: csv::Error| {
+        tracing::error!("Failed to write audience CSV: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    };
+    writer
+        .write_record([
+            "period",
+            "new_followers",
+            "total_followers",
+            "new_subscribers",
+            "ended_subscribers",
+            "total_subscribers",
+        ])
+        .map_err(write_error)?;
+    for point in growth {
+        writer
+            .write_record([
+                point.period.to_string(),
+                point.new_followers.to_string(),
+                point.total_followers.to_string(),
+                point.new_subscribers.to_string(),
+                point.ended_subscribers.to_string(),
+                point.total_subscribers.to_string(),
+            ])
+            .map_err(write_error)?;
+    }
+    let body = writer.into_inner().map_err(|e| {
+        tracing::error!("Failed to finish audience CSV: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, "text/csv; charset=utf-8".to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                "attachment; filename=\"audience.csv\"".to_string(),
+            ),
+        ],
+        body,
+    )
+        .into_response())
+}
+
+/// Page views, distinct visitors and clicks on one target of a campaign or product page, from
+/// the raw tracked events of the range.
+async fn subject_traffic(
+    db: &Database,
+    subject_type: &str,
+    subject_id: Uuid,
+    from: NaiveDate,
+    to: NaiveDate,
+    click_target: &str,
+) -> Result<(i64, i64, i64), StatusCode> {
+    sqlx::query_as::<_, (i64, i64, i64)>(
+        r#"
+        SELECT
+            COUNT(*) FILTER (WHERE event_type = $5),
+            COUNT(DISTINCT visitor_id) FILTER (WHERE event_type = $5),
+            COUNT(*) FILTER (WHERE event_type = $6 AND target = $7)
+        FROM analytics_events
+        WHERE subject_type = $1 AND subject_id = $2
+          AND occurred_at >= $3::DATE::TIMESTAMP AT TIME ZONE 'UTC'
+          AND occurred_at < ($4::DATE + 1)::TIMESTAMP AT TIME ZONE 'UTC'
+        "#,
+    )
+    .bind(subject_code(subject_type))
+    .bind(subject_id)
+    .bind(from)
+    .bind(to)
+    .bind(TrackedEvent::PageView.code())
+    .bind(TrackedEvent::Click.code())
+    .bind(click_target)
+    .fetch_one(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to load tracked events of {} {}: {}", subject_type, subject_id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })
+}
+
+/// Funnel steps with the share of the previous step that made it to each one.
+fn funnel_steps(steps: &[(&str, i64)]) -> Vec<serde_json::Value> {
+    let rate = |count: i64, of: i64| if of > 0 { count as f64 / of as f64 } else { 0.0 };
+    steps
+        .iter()
+        .enumerate()
+        .map(|(index, (step, count))| {
+            let previous = if index == 0 { *count } else { steps[index - 1].1 };
+            json!({
+                "step": step,
+                "count": count,
+                "conversionRate": rate(*count, previous),
+                "overallRate": rate(*count, steps[0].1),
+            })
+        })
+        .collect()
+}
+
+// Campaign view -> donate click -> payment started -> completed. Views and clicks come from
+// tracked events; payments are the donations started in the range, which the server knows for
+// certain, so signed-out donors and blocked trackers still count.
+async fn get_campaign_funnel(
+    State(db): State<Database>,
+    claims: Claims,
+    Path(campaign_id): Path<Uuid>,
+    Query(query): Query<RangeQuery>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let (_, from, to) = query.resolve()?;
+    sqlx::query_scalar::<_, Uuid>("SELECT id FROM campaigns WHERE id = $1 AND creator_id = $2")
+        .bind(campaign_id)
+        .bind(&claims.sub)
+        .fetch_optional(&db.pool)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    let (views, visitors, donate_clicks) =
+        subject_traffic(&db, "campaign", campaign_id, from, to, DONATE_TARGET).await?;
+    let (started, completed) = sqlx::query_as::<_, (i64, i64)>(
+        r#"
+        SELECT COUNT(*), COUNT(*) FILTER (WHERE status = 'COMPLETED')
+        FROM donations
+        WHERE campaign_id = $1
+          AND created_at >= $2::DATE::TIMESTAMP AT TIME ZONE 'UTC'
+          AND created_at < ($3::DATE + 1)::TIMESTAMP AT TIME ZONE 'UTC'
+        "#,
+    )
+    .bind(campaign_id)
+    .bind(from)
+    .bind(to)
+    .fetch_one(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to load donations of campaign {}: {}", campaign_id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    Ok(Json(json!({
+        "success": true,
+        "data": {
+            "campaignId": campaign_id,
+            "from": from,
+            "to": to,
+            "uniqueVisitors": visitors,
+            "steps": funnel_steps(&[
+                ("view", views),
+                ("donate_click", donate_clicks),
+                ("payment_started", started),
+                ("completed", completed),
+            ]),
+        }
+    })))
+}
+
+// Product view -> purchase, with purchases completed in the range counted by the server.
+async fn get_product_funnel(
+    State(db): State<Database>,
+    claims: Claims,
+    Path(product_id): Path<Uuid>,
+    Query(query): Query<RangeQuery>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let (_, from, to) = query.resolve()?;
+    sqlx::query_scalar::<_, Uuid>("SELECT id FROM products WHERE id = $1 AND user_id = $2")
+        .bind(product_id)
+        .bind(&claims.sub)
+        .fetch_optional(&db.pool)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    let (views, visitors, _) = subject_traffic(&db, "product", product_id, from, to, "").await?;
+    let purchases = sqlx::query_scalar::<_, i64>(
+        r#"
+        SELECT COUNT(*) FROM purchases
+        WHERE product_id = $1 AND status = 'COMPLETED'
+          AND created_at >= $2::DATE::TIMESTAMP AT TIME ZONE 'UTC'
+          AND created_at < ($3::DATE + 1)::TIMESTAMP AT TIME ZONE 'UTC'
+        "#,
+    )
+    .bind(product_id)
+    .bind(from)
+    .bind(to)
+    .fetch_one(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to load purchases of product {}: {}", product_id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    Ok(Json(json!({
+        "success": true,
+        "data": {
+            "productId": product_id,
+            "from": from,
+            "to": to,
+            "uniqueVisitors": visitors,
+            "steps": funnel_steps(&[("view", views), ("purchase", purchases)]),
+        }
+    })))
+}
+
+/// Payments made this long after viewing a piece of content are attributed to it.
+const ATTRIBUTION_WINDOW_HOURS: i32 = 48;
+const MAX_CONTENT_RESULTS: usize = 100;
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ContentQuery {
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+    /// `post`, `article` or `episode`; all three unless given.
+    #[serde(rename = "type")]
+    pub content_type: Option<String>,
+    /// `views` (the default), `engagement` or `revenue`.
+    pub sort: Option<String>,
+    /// Revenue in other currencies is left out. USD unless given.
+    pub currency: Option<String>,
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, sqlx::FromRow)]
+pub(crate) struct ContentRow {
+    pub(crate) content_type: String,
+    pub(crate) id: Uuid,
+    pub(crate) title: String,
+    created_at: Option<DateTime<Utc>>,
+    pub(crate) views: i64,
+    engagements: i64,
+    unlock_cents: i64,
+    conversions: i64,
+    conversion_cents: i64,
+}
+
+impl ContentRow {
+    fn engagement_rate(&self) -> f64 {
+        if self.views > 0 {
+            self.engagements as f64 / self.views as f64
+        } else {
+            0.0
+        }
+    }
+
+    fn revenue_cents(&self) -> i64 {
+        self.unlock_cents + self.conversion_cents
+    }
+}
+
+// Each query yields one row per piece of content: views and engagements (likes and comments,
+// or clicks for episodes) in the range, and the revenue attributed to it. Attributed revenue is
+// what the content itself sold (post unlocks) plus the creator's settled payments made by a
+// viewer within the attribution window after viewing, so one payment can count for several
+// pieces of content. Only signed-in viewers can be matched to their payments.
+const CONTENT_POSTS_SQL: &str = r#"
+    WITH bounds AS (
+        SELECT $2::DATE::TIMESTAMP AT TIME ZONE 'UTC' AS starts,
+               ($3::DATE + 1)::TIMESTAMP AT TIME ZONE 'UTC' AS ends
+    )
+    SELECT 'post' AS content_type, p.id, p.title, p.created_at,
+        COALESCE((SELECT SUM(s.views) FROM post_view_stats s
+                  WHERE s.post_id = p.id AND s.hour >= b.starts AND s.hour < b.ends), 0)::BIGINT AS views,
+        (SELECT COUNT(*) FROM post_likes l
+         WHERE l.post_id = p.id AND l.created_at >= b.starts AND l.created_at < b.ends)
+        + (SELECT COUNT(*) FROM post_comments c
+           WHERE c.post_id = p.id AND c.created_at >= b.starts AND c.created_at < b.ends) AS engagements,
+        COALESCE((SELECT SUM(ROUND(u.amount * 100)) FROM post_unlocks u
+                  WHERE u.post_id = p.id AND u.status = 'COMPLETED' AND UPPER(COALESCE(u.currency, 'USD')) = $4
+                    AND u.created_at >= b.starts AND u.created_at < b.ends), 0)::BIGINT AS unlock_cents,
+        conversion.count AS conversions,
+        conversion.cents AS conversion_cents
+    FROM posts p
+    CROSS JOIN bounds b
+    CROSS JOIN LATERAL (
+        SELECT COUNT(*) AS count, COALESCE(SUM(le.gross_cents), 0)::BIGINT AS cents
+        FROM ledger_entries le
+        WHERE le.creator_id = $1 AND le.status IN ('SETTLED', 'DISPUTED') AND UPPER(le.currency) = $4
+          AND le.created_at >= b.starts AND le.created_at < b.ends
+          AND EXISTS (
+              SELECT 1 FROM post_viewers v
+              WHERE v.post_id = p.id AND v.viewer_key = 'user:' || le.payer_id
+                AND v.first_seen_at <= le.created_at
+                AND v.first_seen_at > le.created_at - make_interval(hours => $5)
+          )
+    ) conversion
+    WHERE p.user_id = $1
+"#;
+
+const CONTENT_ARTICLES_SQL: &str = r#"
+    WITH bounds AS (
+        SELECT $2::DATE::TIMESTAMP AT TIME ZONE 'UTC' AS starts,
+               ($3::DATE + 1)::TIMESTAMP AT TIME ZONE 'UTC' AS ends
+    )
+    SELECT 'article' AS content_type, a.id, a.title, COALESCE(a.published_at, a.created_at) AS created_at,
+        (SELECT COUNT(*) FROM article_views v
+         WHERE v.article_id = a.id AND v.viewed_at >= b.starts AND v.viewed_at < b.ends) AS views,
+        (SELECT COUNT(*) FROM article_likes l
+         WHERE l.article_id = a.id AND l.created_at >= b.starts AND l.created_at < b.ends)
+        + (SELECT COUNT(*) FROM article_comments c
+           WHERE c.article_id = a.id AND c.created_at >= b.starts AND c.created_at < b.ends) AS engagements,
+        0::BIGINT AS unlock_cents,
+        conversion.count AS conversions,
+        conversion.cents AS conversion_cents
+    FROM articles a
+    CROSS JOIN bounds b
+    CROSS JOIN LATERAL (
+        SELECT COUNT(*) AS count, COALESCE(SUM(le.gross_cents), 0)::BIGINT AS cents
+        FROM ledger_entries le
+        WHERE le.creator_id = $1 AND le.status IN ('SETTLED', 'DISPUTED') AND UPPER(le.currency) = $4
+          AND le.created_at >= b.starts AND le.created_at < b.ends
+          AND EXISTS (
+              SELECT 1 FROM article_views v
+              WHERE v.article_id = a.id AND v.viewer_id = le.payer_id
+                AND v.viewed_at <= le.created_at
+                AND v.viewed_at > le.created_at - make_interval(hours => $5)
+          )
+    ) conversion
+    WHERE a.author_id = $1
+"#;
+
+const CONTENT_EPISODES_SQL: &str = r#"
+    WITH bounds AS (
+        SELECT $2::DATE::TIMESTAMP AT TIME ZONE 'UTC' AS starts,
+               ($3::DATE + 1)::TIMESTAMP AT TIME ZONE 'UTC' AS ends
+    )
+    SELECT 'episode' AS content_type, e.id, e.title, COALESCE(e.published_at, e.created_at) AS created_at,
+        traffic.views,
+        traffic.clicks AS engagements,
+        0::BIGINT AS unlock_cents,
+        conversion.count AS conversions,
+        conversion.cents AS conversion_cents
+    FROM podcast_episodes e
+    JOIN podcasts pc ON pc.id = e.podcast_id
+    CROSS JOIN bounds b
+    CROSS JOIN LATERAL (
+        SELECT COUNT(*) FILTER (WHERE ae.event_type = $7) AS views,
+               COUNT(*) FILTER (WHERE ae.event_type = $8) AS clicks
+        FROM analytics_events ae
+        WHERE ae.subject_type = $6 AND ae.subject_id = e.id
+          AND ae.occurred_at >= b.starts AND ae.occurred_at < b.ends
+    ) traffic
+    CROSS JOIN LATERAL (
+        SELECT COUNT(*) AS count, COALESCE(SUM(le.gross_cents), 0)::BIGINT AS cents
+        FROM ledger_entries le
+        WHERE le.creator_id = $1 AND le.status IN ('SETTLED', 'DISPUTED') AND UPPER(le.currency) = $4
+          AND le.created_at >= b.starts AND le.created_at < b.ends
+          AND EXISTS (
+              SELECT 1 FROM analytics_events ae
+              WHERE ae.subject_type = $6 AND ae.subject_id = e.id AND ae.visitor_id = le.payer_id
+                AND ae.occurred_at <= le.created_at
+                AND ae.occurred_at > le.created_at - make_interval(hours => $5)
+          )
+    ) conversion
+    WHERE pc.creator_id = $1
+"#;
+
+/// Performance of a creator's content of one type, or of every type, in the range.
+async fn content_rows(
+    db: &Database,
+    creator_id: &str,
+    from: NaiveDate,
+    to: NaiveDate,
+    currency: &str,
+    content_type: Option<&str>,
+) -> Result<Vec<ContentRow>, StatusCode> {
+    let sources = [
+        ("post", CONTENT_POSTS_SQL),
+        ("article", CONTENT_ARTICLES_SQL),
+        ("episode", CONTENT_EPISODES_SQL),
+    ];
+    if let Some(content_type) = content_type {
+        if !sources.iter().any(|(name, _)| *name == content_type) {
+            return Err(StatusCode::BAD_REQUEST);
+        }
+    }
+
+    let mut rows = Vec::new();
+    for (source_type, sql) in sources {
+        if content_type.is_some_and(|wanted| wanted != source_type) {
+            continue;
+        }
+        let mut statement = sqlx::query_as::<_, ContentRow>(sql)
+            .bind(creator_id)
+            .bind(from)
+            .bind(to)
+            .bind(currency)
+            .bind(ATTRIBUTION_WINDOW_HOURS);
+        if source_type == "episode" {
+            statement = statement
+                .bind(subject_code("episode"))
+                .bind(TrackedEvent::PageView.code())
+                .bind(TrackedEvent::Click.code());
+        }
+        rows.extend(statement.fetch_all(&db.pool).await.map_err(|e| {
+            tracing::error!("Failed to load content performance of {}: {}", creator_id, e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?);
+    }
+    Ok(rows)
+}
+
+// A creator's posts, articles and podcast episodes ranked by views, engagement rate or
+// attributed revenue over a date range.
+async fn get_content_performance(
+    State(db): State<Database>,
+    claims: Claims,
+    Query(query): Query<ContentQuery>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let range = RangeQuery {
+        interval: None,
+        from: query.from,
+        to: query.to,
+        format: None,
+    };
+    let (_, from, to) = range.resolve()?;
+    let currency = query.currency.as_deref().unwrap_or("USD").to_ascii_uppercase();
+    let limit = query.limit.unwrap_or(20).clamp(1, MAX_CONTENT_RESULTS);
+    let mut rows = content_rows(&db, &claims.sub, from, to, &currency, query.content_type.as_deref()).await?;
+
+    match query.sort.as_deref().unwrap_or("views") {
+        "views" => rows.sort_by_key(|row| std::cmp::Reverse(row.views)),
+        "engagement" => rows.sort_by(|a, b| b.engagement_rate().total_cmp(&a.engagement_rate())),
+        "revenue" => rows.sort_by_key(|row| std::cmp::Reverse(row.revenue_cents())),
+        _ => return Err(StatusCode::BAD_REQUEST),
+    }
+    rows.truncate(limit);
+
+    let items = rows
+        .iter()
+        .map(|row| {
+            json!({
+                "type": row.content_type,
+                "id": row.id,
+                "title": row.title,
+                "createdAt": row.created_at,
+                "views": row.views,
+                "engagements": row.engagements,
+                "engagementRate": row.engagement_rate(),
+                "unlockCents": row.unlock_cents,
+                "conversions": row.conversions,
+                "conversionCents": row.conversion_cents,
+                "revenueCents": row.revenue_cents(),
+            })
+        })
+        .collect::<Vec<_>>();
+
+    Ok(Json(json!({
+        "success": true,
+        "data": {
+            "from": from,
+            "to": to,
+            "currency": currency,
+            "attributionWindowHours": ATTRIBUTION_WINDOW_HOURS,
+            "items": items,
+        }
+    })))
+}
+
+/// What a creator's performance report covers: earnings per currency, the most viewed content
+/// and how the audience changed.
+pub(crate) struct PerformanceSummary {
+    pub(crate) earnings: BTreeMap<String, EarningsTotals>,
+    pub(crate) top_content: Vec<ContentRow>,
+    pub(crate) new_subscribers: i64,
+    pub(crate) ended_subscribers: i64,
+    pub(crate) new_followers: i64,
+}
+
+impl PerformanceSummary {
+    pub(crate) fn is_empty(&self) -> bool {
+        self.earnings.is_empty()
+            && self.top_content.is_empty()
+            && self.new_subscribers == 0
+            && self.ended_subscribers == 0
+            && self.new_followers == 0
+    }
+}
+
+/// Sum up a creator's performance between two dates, inclusive, for the report email.
+pub(crate) async fn performance_summary(
+    db: &Database,
+    creator_id: &str,
+    from: NaiveDate,
+    to: NaiveDate,
+    top_content: usize,
+) -> Result<PerformanceSummary, StatusCode> {
+    let mut earnings = BTreeMap::<String, EarningsTotals>::new();
+    for row in earnings_rows(db, creator_id, Interval::Month, from, to).await? {
+        earnings.entry(row.currency).or_default().add(&row.totals);
+    }
+
+    let mut content = content_rows(db, creator_id, from, to, "USD", None).await?;
+    content.retain(|row| row.views > 0);
+    content.sort_by_key(|row| std::cmp::Reverse(row.views));
+    content.truncate(top_content);
+
+    let (new_subscribers, ended_subscribers, new_followers) = sqlx::query_as::<_, (i64, i64, i64)>(
+        r#"
+        WITH bounds AS (
+            SELECT $2::DATE::TIMESTAMP AT TIME ZONE 'UTC' AS starts,
+                   ($3::DATE + 1)::TIMESTAMP AT TIME ZONE 'UTC' AS ends
+        )
+        SELECT
+            (SELECT COUNT(*) FROM subscriptions s
+             WHERE s.creator_id = $1 AND s.created_at >= b.starts AND s.created_at < b.ends),
+            (SELECT COUNT(*) FROM subscriptions s
+             WHERE s.creator_id = $1 AND UPPER(s.status) <> 'ACTIVE'
+               AND s.updated_at >= b.starts AND s.updated_at < b.ends),
+            (SELECT COUNT(*) FROM follows f
+             WHERE f.following_id = $1 AND f.created_at >= b.starts AND f.created_at < b.ends)
+        FROM bounds b
+        "#,
+    )
+    .bind(creator_id)
+    .bind(from)
+    .bind(to)
+    .fetch_one(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to load audience changes of {}: {}", creator_id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    Ok(PerformanceSummary {
+        earnings,
+        top_content: content,
+        new_subscribers,
+        ended_subscribers,
+        new_followers,
+    })
+}
+
+async fn get_report_settings(
+    State(db): State<Database>,
+    claims: Claims,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let settings = sqlx::query_as::<_, (String, Option<DateTime<Utc>>)>(
+        "SELECT frequency, last_sent_at FROM analytics_report_settings WHERE user_id = $1",
+    )
+    .bind(&claims.sub)
+    .fetch_optional(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to load report settings of {}: {}", claims.sub, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    let (frequency, last_sent_at) = match settings {
+        Some((frequency, last_sent_at)) => {
+            (ReportFrequency::parse(&frequency).unwrap_or_default(), last_sent_at)
+        }
+        None => (ReportFrequency::default(), None),
+    };
+
+    Ok(Json(json!({
+        "success": true,
+        "data": { "frequency": frequency, "lastSentAt": last_sent_at }
+    })))
+}
+
+async fn update_report_settings(
+    State(db): State<Database>,
+    claims: Claims,
+    Json(payload): Json<ReportSettingsRequest>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    sqlx::query(
+        r#"
+        INSERT INTO analytics_report_settings (user_id, frequency)
+        VALUES ($1, $2)
+        ON CONFLICT (user_id) DO UPDATE SET frequency = EXCLUDED.frequency, updated_at = NOW()
+        "#,
+    )
+    .bind(&claims.sub)
+    .bind(payload.frequency.as_str())
+    .execute(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to update report settings of {}: {}", claims.sub, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    Ok(Json(json!({ "success": true, "data": { "frequency": payload.frequency } })))
+}
@@ -0,0 +1,311 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::Json,
+    routing::get,
+    Router,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use uuid::Uuid;
+
+use crate::{auth::Claims, database::Database, jobs::announcements, routes::polls::ensure_creator};
+
+/// Recipients per fan-out job.
+const ANNOUNCEMENT_BATCH_SIZE: i64 = 500;
+const MAX_TITLE_CHARS: usize = 200;
+const MAX_BODY_CHARS: usize = 10_000;
+
+/// A one-off message from a creator to their members, delivered in batches.
+#[derive(Debug, Serialize, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+struct Announcement {
+    id: Uuid,
+    title: String,
+    body: String,
+    /// `None` when sent to every member.
+    tier_id: Option<String>,
+    send_email: bool,
+    /// `SENDING` until every batch is delivered, then `SENT`.
+    status: String,
+    recipient_count: i32,
+    created_at: DateTime<Utc>,
+    completed_at: Option<DateTime<Utc>>,
+}
+
+/// How far delivery got; emails count from the email deliveries the batches queued.
+#[derive(Debug, Serialize, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+struct DeliveryStats {
+    announcement_id: Uuid,
+    delivered: i64,
+    emails_queued: i64,
+    emails_sent: i64,
+    emails_failed: i64,
+    emails_suppressed: i64,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CreateAnnouncementRequest {
+    title: String,
+    body: String,
+    /// Only members of this tier; everyone when left out.
+    tier_id: Option<String>,
+    /// Email members as well as notifying them in the app; defaults to true.
+    send_email: Option<bool>,
+}
+
+#[derive(Debug, Deserialize)]
+struct AnnouncementQuery {
+    page: Option<u32>,
+    limit: Option<u32>,
+}
+
+pub fn announcement_routes() -> Router<Database> {
+    Router::new()
+        .route("/", get(list_announcements).post(create_announcement))
+        .route("/:id", get(get_announcement))
+}
+
+async fn create_announcement(
+    State(db): State<Database>,
+    claims: Claims,
+    Json(payload): Json<CreateAnnouncementRequest>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    ensure_creator(&db, &claims.sub).await?;
+
+    let title = payload.title.trim();
+    let body = payload.body.trim();
+    if title.is_empty()
+        || body.is_empty()
+        || title.chars().count() > MAX_TITLE_CHARS
+        || body.chars().count() > MAX_BODY_CHARS
+    {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let tier_id = payload
+        .tier_id
+        .as_deref()
+        .map(str::trim)
+        .filter(|tier_id| !tier_id.is_empty());
+
+    let mut tx = db.pool.begin().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let announcement_id = sqlx::query_scalar::<_, Uuid>(
+        r#"
+        INSERT INTO announcements (creator_id, title, body, tier_id, send_email)
+        VALUES ($1, $2, $3, $4, $5)
+        RETURNING id
+        "#,
+    )
+    .bind(&claims.sub)
+    .bind(title)
+    .bind(body)
+    .bind(tier_id)
+    .bind(payload.send_email.unwrap_or(true))
+    .fetch_one(&mut tx)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to create announcement for {}: {}", claims.sub, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    // Recipients are fixed now, so members who join while it goes out don't get half of it
+    let recipient_count = sqlx::query(
+        r#"
+        INSERT INTO announcement_recipients (announcement_id, user_id, batch)
+        SELECT $1, m.user_id, (ROW_NUMBER() OVER (ORDER BY m.user_id) - 1) / $3
+        FROM (
+            SELECT DISTINCT user_id FROM subscriptions
+            WHERE creator_id = $2 AND UPPER(status) = 'ACTIVE' AND user_id <> $2
+              AND ($4::TEXT IS NULL OR tier_id = $4)
+        ) m
+        "#,
+    )
+    .bind(announcement_id)
+    .bind(&claims.sub)
+    .bind(ANNOUNCEMENT_BATCH_SIZE)
+    .bind(tier_id)
+    .execute(&mut tx)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to select recipients of announcement {}: {}", announcement_id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?
+    .rows_affected() as i64;
+    if recipient_count == 0 {
+        return Err(StatusCode::UNPROCESSABLE_ENTITY);
+    }
+
+    let announcement = sqlx::query_as::<_, Announcement>(
+        r#"
+        UPDATE announcements SET recipient_count = $2 WHERE id = $1
+        RETURNING id, title, body, tier_id, send_email, status, recipient_count, created_at,
+                  completed_at
+        "#,
+    )
+    .bind(announcement_id)
+    .bind(recipient_count as i32)
+    .fetch_one(&mut tx)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    tx.commit().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let batches =
+        ((recipient_count + ANNOUNCEMENT_BATCH_SIZE - 1) / ANNOUNCEMENT_BATCH_SIZE) as i32;
+    dispatch_batches(&db, announcement_id, batches).await;
+
+    let stats = delivery_stats(&db, &[announcement_id]).await?;
+    Ok(Json(json!({
+        "success": true,
+        "data": { "announcement": announcement, "stats": stats.first() }
+    })))
+}
+
+/// Publish a fan-out job per batch, or deliver the batches from here when CloudAMQP is not
+/// configured.
+async fn dispatch_batches(db: &Database, announcement_id: Uuid, batches: i32) {
+    match &db.amqp {
+        Some(amqp) => {
+            for batch in 0..batches {
+                if let Err(e) = amqp
+                    .send_announcement_batch(announcement_id.to_string(), batch)
+                    .await
+                {
+                    tracing::error!(
+                        "Failed to queue batch {} of announcement {}: {}",
+                        batch,
+                        announcement_id,
+                        e
+                    );
+                }
+            }
+        }
+        None => {
+            let db = db.clone();
+            tokio::spawn(async move {
+                for batch in 0..batches {
+                    let id = announcement_id.to_string();
+                    if let Err(e) = announcements::deliver_batch(&db, &id, batch).await {
+                        tracing::error!(
+                            "Batch {} of announcement {} failed: {:?}",
+                            batch,
+                            announcement_id,
+                            e
+                        );
+                    }
+                }
+            });
+        }
+    }
+}
+
+async fn list_announcements(
+    State(db): State<Database>,
+    Query(params): Query<AnnouncementQuery>,
+    claims: Claims,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let page = params.page.unwrap_or(1).max(1);
+    let limit = params.limit.unwrap_or(20).clamp(1, 100);
+    let offset = ((page - 1) * limit) as i64;
+
+    let announcements = sqlx::query_as::<_, Announcement>(
+        r#"
+        SELECT id, title, body, tier_id, send_email, status, recipient_count, created_at,
+               completed_at
+        FROM announcements
+        WHERE creator_id = $1
+        ORDER BY created_at DESC
+        LIMIT $2 OFFSET $3
+        "#,
+    )
+    .bind(&claims.sub)
+    .bind(limit as i64)
+    .bind(offset)
+    .fetch_all(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to list announcements of {}: {}", claims.sub, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    let total =
+        sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM announcements WHERE creator_id = $1")
+            .bind(&claims.sub)
+            .fetch_one(&db.pool)
+            .await
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let ids: Vec<Uuid> = announcements.iter().map(|announcement| announcement.id).collect();
+    let mut stats = delivery_stats(&db, &ids).await?;
+    let announcements: Vec<serde_json::Value> = announcements
+        .into_iter()
+        .map(|announcement| {
+            let position = stats.iter().position(|stats| stats.announcement_id == announcement.id);
+            let stats = position.map(|position| stats.swap_remove(position));
+            json!({ "announcement": announcement, "stats": stats })
+        })
+        .collect();
+
+    Ok(Json(json!({
+        "success": true,
+        "data": announcements,
+        "pagination": {
+            "page": page,
+            "limit": limit,
+            "total": total,
+            "pages": ((total as f64) / (limit as f64)).ceil() as u32,
+        }
+    })))
+}
+
+async fn get_announcement(
+    State(db): State<Database>,
+    Path(id): Path<Uuid>,
+    claims: Claims,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let announcement = sqlx::query_as::<_, Announcement>(
+        r#"
+        SELECT id, title, body, tier_id, send_email, status, recipient_count, created_at,
+               completed_at
+        FROM announcements
+        WHERE id = $1 AND creator_id = $2
+        "#,
+    )
+    .bind(id)
+    .bind(&claims.sub)
+    .fetch_optional(&db.pool)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    .ok_or(StatusCode::NOT_FOUND)?;
+
+    let stats = delivery_stats(&db, &[id]).await?;
+    Ok(Json(json!({
+        "success": true,
+        "data": { "announcement": announcement, "stats": stats.first() }
+    })))
+}
+
+async fn delivery_stats(db: &Database, ids: &[Uuid]) -> Result<Vec<DeliveryStats>, StatusCode> {
+    sqlx::query_as::<_, DeliveryStats>(
+        r#"
+        SELECT r.announcement_id,
+               COUNT(*) FILTER (WHERE r.delivered_at IS NOT NULL) AS delivered,
+               COUNT(d.id) AS emails_queued,
+               COUNT(*) FILTER (WHERE d.status = 'SENT') AS emails_sent,
+               COUNT(*) FILTER (WHERE d.status = 'FAILED') AS emails_failed,
+               COUNT(*) FILTER (WHERE d.status = 'SUPPRESSED') AS emails_suppressed
+        FROM announcement_recipients r
+        LEFT JOIN email_deliveries d ON d.id = r.email_delivery_id
+        WHERE r.announcement_id = ANY($1)
+        GROUP BY r.announcement_id
+        "#,
+    )
+    .bind(ids)
+    .fetch_all(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to count announcement deliveries: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })
+}
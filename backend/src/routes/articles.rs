@@ -1,6 +1,6 @@
 use axum::{
-    extract::{Json, Path, Query, State},
-    http::StatusCode,
+    extract::{Json, Multipart, Path, Query, State},
+    http::{HeaderMap, StatusCode},
     response::Json as ResponseJson,
     routing::{get, post},
     Router,
@@ -8,9 +8,27 @@ use axum::{
 use serde::{Deserialize, Serialize};
 use serde_json::json;
 use sqlx::Row;
+use std::collections::{HashMap, HashSet};
 use uuid::Uuid;
 
-use crate::{auth::Claims, database::Database, middleware::optional_auth::MaybeClaims};
+use crate::{
+    article_views::{self, ArticleView},
+    auth::Claims,
+    database::Database,
+    feed_timeline, jobs,
+    middleware::optional_auth::MaybeClaims,
+    notification_service::{notify_comment, CommentActivity},
+    routes::series::load_article_series,
+    storage,
+};
+use tracing::error;
+
+/// Paragraphs shown to readers without access when the author placed no paywall marker.
+const PREVIEW_PARAGRAPHS: usize = 3;
+/// Authors end the free part of a premium article explicitly with this marker.
+const PAYWALL_MARKER: &str = "<!-- paywall -->";
+/// Largest Medium export archive accepted by the import endpoint.
+const IMPORT_MAX_BYTES: usize = 200 * 1024 * 1024;
 
 #[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
 pub struct Article {
@@ -20,8 +38,29 @@ pub struct Article {
     pub slug: String,
     pub author_id: String,
     pub published_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub category_id: Option<Uuid>,
+    #[serde(rename = "isPremium")]
+    pub is_premium: bool,
+    #[serde(rename = "viewCount")]
+    pub view_count: i64,
     pub created_at: chrono::DateTime<chrono::Utc>,
     pub updated_at: chrono::DateTime<chrono::Utc>,
+    #[sqlx(default)]
+    #[serde(rename = "isLocked")]
+    pub is_locked: bool,
+    /// Character offset in the full article where the locked part starts.
+    #[sqlx(default)]
+    #[serde(rename = "lockedAt", skip_serializing_if = "Option::is_none")]
+    pub locked_at: Option<i64>,
+}
+
+impl Article {
+    fn apply_access(&mut self, has_access: bool) {
+        let (content, locked_at) = gate_content(self.content.take(), self.is_premium, has_access);
+        self.content = content;
+        self.is_locked = locked_at.is_some();
+        self.locked_at = locked_at;
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -65,9 +104,27 @@ struct ArticleCommentUser {
 struct ArticleComment {
     id: Uuid,
     content: String,
+    #[serde(rename = "parentId")]
+    parent_id: Option<Uuid>,
     #[serde(rename = "createdAt")]
     created_at: chrono::DateTime<chrono::Utc>,
     user: ArticleCommentUser,
+    #[serde(rename = "likeCount")]
+    like_count: i64,
+    #[serde(rename = "hasLiked")]
+    has_liked: bool,
+    /// Comment written by the article's author.
+    #[serde(rename = "isAuthor")]
+    is_author: bool,
+    /// Comment liked by the article's author.
+    #[serde(rename = "authorLiked")]
+    author_liked: bool,
+    replies: Vec<ArticleComment>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CommentQuery {
+    sort: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -75,11 +132,33 @@ struct CreateArticleRequest {
     pub title: String,
     pub content: String,
     pub slug: Option<String>,
+    #[serde(alias = "publishedAt")]
+    pub published_at: Option<chrono::DateTime<chrono::Utc>>,
+    #[serde(alias = "categoryId")]
+    pub category_id: Option<Uuid>,
+    #[serde(alias = "tagIds")]
+    pub tag_ids: Option<Vec<Uuid>>,
+    #[serde(alias = "isPremium")]
+    pub is_premium: Option<bool>,
 }
 
 #[derive(Debug, Deserialize)]
 struct CreateCommentRequest {
     content: String,
+    #[serde(alias = "parentId")]
+    parent_id: Option<Uuid>,
+}
+
+#[derive(Debug, Deserialize)]
+struct AnalyticsQuery {
+    days: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ReadingProgressRequest {
+    percentage: f64,
+    #[serde(alias = "paragraphAnchor")]
+    anchor: Option<String>,
 }
 
 pub fn articles_routes() -> Router<Database> {
@@ -91,24 +170,36 @@ pub fn articles_routes() -> Router<Database> {
             "/:id/comments",
             get(get_article_comments).post(create_article_comment),
         )
+        .route(
+            "/:id/comments/:comment_id/like",
+            post(toggle_article_comment_like),
+        )
+        .route(
+            "/:id/progress",
+            get(get_reading_progress).put(save_reading_progress),
+        )
+        .route("/:id/analytics", get(get_article_analytics))
+        .route("/import", post(import_articles))
+        .route("/import/:import_id", get(get_article_import))
 }
 
 async fn get_articles(
     State(db): State<Database>,
     Query(params): Query<ArticleQuery>,
+    MaybeClaims(maybe_claims): MaybeClaims,
 ) -> Result<ResponseJson<ArticlesResponse>, StatusCode> {
     let page = params.page.unwrap_or(1);
     let limit = params.limit.unwrap_or(20);
     let offset = (page - 1) * limit;
 
     let total_count = if let Some(author_id) = &params.author_id {
-        sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM articles WHERE author_id = $1")
+        sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM articles WHERE author_id = $1 AND (published_at IS NULL OR published_at <= NOW())")
             .bind(author_id)
             .fetch_one(&db.pool)
             .await
             .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
     } else {
-        sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM articles")
+        sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM articles WHERE published_at IS NULL OR published_at <= NOW()")
             .fetch_one(&db.pool)
             .await
             .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
@@ -116,7 +207,7 @@ async fn get_articles(
 
     let articles = if let Some(author_id) = &params.author_id {
         sqlx::query_as::<_, Article>(
-            "SELECT * FROM articles WHERE author_id = $1 ORDER BY created_at DESC LIMIT $2 OFFSET $3",
+            "SELECT * FROM articles WHERE author_id = $1 AND (published_at IS NULL OR published_at <= NOW()) ORDER BY created_at DESC LIMIT $2 OFFSET $3",
         )
         .bind(author_id)
         .bind(limit as i64)
@@ -126,7 +217,7 @@ async fn get_articles(
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
     } else {
         sqlx::query_as::<_, Article>(
-            "SELECT * FROM articles ORDER BY created_at DESC LIMIT $1 OFFSET $2",
+            "SELECT * FROM articles WHERE published_at IS NULL OR published_at <= NOW() ORDER BY created_at DESC LIMIT $1 OFFSET $2",
         )
         .bind(limit as i64)
         .bind(offset as i64)
@@ -135,6 +226,22 @@ async fn get_articles(
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
     };
 
+    let mut articles = articles;
+    let premium_authors: Vec<String> = articles
+        .iter()
+        .filter(|article| article.is_premium)
+        .map(|article| article.author_id.clone())
+        .collect::<HashSet<_>>()
+        .into_iter()
+        .collect();
+    let viewer_id = maybe_claims.map(|claims| claims.sub);
+    let readable_authors =
+        load_subscribed_authors(&db, viewer_id.as_deref(), &premium_authors).await?;
+    for article in articles.iter_mut() {
+        let has_access = readable_authors.contains(&article.author_id);
+        article.apply_access(has_access);
+    }
+
     let total = total_count as usize;
     let response = ArticlesResponse {
         success: true,
@@ -154,6 +261,7 @@ async fn get_article_by_slug(
     State(db): State<Database>,
     Path(slug): Path<String>,
     MaybeClaims(maybe_claims): MaybeClaims,
+    headers: HeaderMap,
 ) -> Result<ResponseJson<serde_json::Value>, StatusCode> {
     let row = sqlx::query(
         r#"
@@ -163,9 +271,14 @@ async fn get_article_by_slug(
             a.content,
             a.slug,
             a.author_id,
+            a.is_premium,
+            a.view_count,
             a.published_at,
             a.created_at,
             a.updated_at,
+            cat.id AS category_id,
+            cat.name AS category_name,
+            cat.slug AS category_slug,
             COALESCE(l.like_count, 0) AS like_count,
             COALESCE(c.comment_count, 0) AS comment_count,
             COALESCE(u.display_name, u.name, u.username) AS author_name,
@@ -173,6 +286,7 @@ async fn get_article_by_slug(
             u.avatar_url AS author_avatar
         FROM articles a
         LEFT JOIN users u ON u.id = a.author_id
+        LEFT JOIN categories cat ON cat.id = a.category_id
         LEFT JOIN (
             SELECT article_id, COUNT(*) AS like_count
             FROM article_likes
@@ -192,6 +306,28 @@ async fn get_article_by_slug(
     .map_err(|_| StatusCode::NOT_FOUND)?;
 
     let article_id = row.get::<Uuid, _>("id");
+    let author_id = row.get::<String, _>("author_id");
+    let viewer_id = maybe_claims.as_ref().map(|claims| claims.sub.clone());
+    let is_premium = row.get::<bool, _>("is_premium");
+    let has_access = !is_premium
+        || load_subscribed_authors(&db, viewer_id.as_deref(), std::slice::from_ref(&author_id))
+            .await?
+            .contains(&author_id);
+
+    let (content, locked_at) =
+        gate_content(row.get::<Option<String>, _>("content"), is_premium, has_access);
+
+    let is_author = viewer_id.as_deref() == Some(author_id.as_str());
+    let series = load_article_series(&db, article_id, is_author).await?;
+
+    if !is_author {
+        article_views::record(ArticleView::from_headers(
+            article_id,
+            viewer_id.clone(),
+            &headers,
+        ));
+    }
+
     let has_liked = if let Some(claims) = maybe_claims {
         sqlx::query_scalar::<_, bool>(
             "SELECT EXISTS(SELECT 1 FROM article_likes WHERE article_id = $1 AND user_id = $2)",
@@ -205,11 +341,48 @@ async fn get_article_by_slug(
         false
     };
 
+    let tags = sqlx::query(
+        r#"
+        SELECT t.id, t.name, t.slug
+        FROM article_tags at
+        JOIN tags t ON t.id = at.tag_id
+        WHERE at.article_id = $1
+        ORDER BY t.name ASC
+        "#,
+    )
+    .bind(article_id)
+    .fetch_all(&db.pool)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    .into_iter()
+    .map(|tag| {
+        json!({
+            "id": tag.get::<Uuid, _>("id"),
+            "name": tag.get::<String, _>("name"),
+            "slug": tag.get::<String, _>("slug"),
+        })
+    })
+    .collect::<Vec<_>>();
+
+    let category = row
+        .get::<Option<Uuid>, _>("category_id")
+        .map(|category_id| {
+            json!({
+                "id": category_id,
+                "name": row.get::<Option<String>, _>("category_name"),
+                "slug": row.get::<Option<String>, _>("category_slug"),
+            })
+        });
+
     Ok(ResponseJson(json!({
         "id": article_id,
         "title": row.get::<String, _>("title"),
-        "content": row.get::<Option<String>, _>("content"),
+        "content": content,
         "slug": row.get::<String, _>("slug"),
+        "isPremium": is_premium,
+        "isLocked": locked_at.is_some(),
+        "viewCount": row.get::<i64, _>("view_count"),
+        "lockedAt": locked_at,
         "author_id": row.get::<String, _>("author_id"),
         "authorName": row.get::<Option<String>, _>("author_name"),
         "authorUsername": row.get::<Option<String>, _>("author_username"),
@@ -227,6 +400,9 @@ async fn get_article_by_slug(
             likes: row.get::<i64, _>("like_count"),
             comments: row.get::<i64, _>("comment_count")
         },
+        "category": category,
+        "tags": tags,
+        "series": series,
         "hasLiked": has_liked
     })))
 }
@@ -257,9 +433,15 @@ async fn create_article(
             .replace(|c: char| !c.is_alphanumeric() && c != '-', "")
     });
 
+    let mut tx = db
+        .pool
+        .begin()
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
     let article = sqlx::query_as::<_, Article>(
-        "INSERT INTO articles (id, title, content, slug, author_id, published_at, created_at, updated_at) 
-         VALUES ($1, $2, $3, $4, $5, NOW(), NOW(), NOW())
+        "INSERT INTO articles (id, title, content, slug, author_id, published_at, category_id, is_premium, created_at, updated_at) 
+         VALUES ($1, $2, $3, $4, $5, COALESCE($6, NOW()), $7, $8, NOW(), NOW())
          RETURNING *",
     )
     .bind(article_id)
@@ -267,15 +449,45 @@ async fn create_article(
     .bind(&payload.content)
     .bind(&slug)
     .bind(&author_id)
-    .fetch_one(&db.pool)
+    .bind(payload.published_at)
+    .bind(payload.category_id)
+    .bind(payload.is_premium.unwrap_or(false))
+    .fetch_one(&mut tx)
     .await
     .map_err(|e| match &e {
         sqlx::Error::Database(db_err) if db_err.constraint() == Some("articles_slug_key") => {
             StatusCode::CONFLICT
         }
+        sqlx::Error::Database(db_err)
+            if db_err.constraint() == Some("articles_category_id_fkey") =>
+        {
+            StatusCode::BAD_REQUEST
+        }
         _ => StatusCode::INTERNAL_SERVER_ERROR,
     })?;
 
+    if let Some(tag_ids) = payload.tag_ids.filter(|ids| !ids.is_empty()) {
+        sqlx::query(
+            "INSERT INTO article_tags (article_id, tag_id) SELECT $1, id FROM tags WHERE id = ANY($2) ON CONFLICT DO NOTHING",
+        )
+        .bind(article_id)
+        .bind(&tag_ids)
+        .execute(&mut tx)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    }
+
+    tx.commit()
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    feed_timeline::publish(
+        &db,
+        &article.author_id,
+        format!("article-{}", article.id),
+        article.published_at.unwrap_or(article.created_at),
+    );
+
     Ok(ResponseJson(json!({
         "success": true,
         "data": article
@@ -332,33 +544,48 @@ async fn toggle_article_like(
 async fn get_article_comments(
     State(db): State<Database>,
     Path(id_or_slug): Path<String>,
+    Query(params): Query<CommentQuery>,
+    MaybeClaims(maybe_claims): MaybeClaims,
 ) -> Result<ResponseJson<serde_json::Value>, StatusCode> {
-    let article_id = match Uuid::parse_str(&id_or_slug) {
-        Ok(uuid) => uuid,
-        Err(_) => sqlx::query_scalar::<_, Uuid>("SELECT id FROM articles WHERE slug = $1")
-            .bind(&id_or_slug)
-            .fetch_one(&db.pool)
-            .await
-            .map_err(|_| StatusCode::NOT_FOUND)?,
-    };
+    let article_id = resolve_article_id(&db, &id_or_slug).await?;
+    let viewer_id = maybe_claims.map(|claims| claims.sub);
+    let sort_top = params.sort.as_deref() == Some("top");
 
     let comments = sqlx::query(
         r#"
         SELECT 
             c.id,
             c.content,
+            c.parent_id,
             c.created_at,
             u.id AS user_id,
             u.name AS user_name,
             u.username AS user_username,
-            u.avatar AS user_avatar
+            u.avatar AS user_avatar,
+            c.user_id = a.author_id AS is_author,
+            COALESCE(l.like_count, 0) AS like_count,
+            EXISTS(
+                SELECT 1 FROM article_comment_likes acl
+                WHERE acl.comment_id = c.id AND acl.user_id = $2
+            ) AS has_liked,
+            EXISTS(
+                SELECT 1 FROM article_comment_likes acl
+                WHERE acl.comment_id = c.id AND acl.user_id = a.author_id
+            ) AS author_liked
         FROM article_comments c
         JOIN users u ON c.user_id = u.id
+        JOIN articles a ON a.id = c.article_id
+        LEFT JOIN (
+            SELECT comment_id, COUNT(*) AS like_count
+            FROM article_comment_likes
+            GROUP BY comment_id
+        ) l ON l.comment_id = c.id
         WHERE c.article_id = $1
-        ORDER BY c.created_at DESC
+        ORDER BY c.created_at ASC
         "#,
     )
     .bind(article_id)
+    .bind(&viewer_id)
     .fetch_all(&db.pool)
     .await
     .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
@@ -366,6 +593,7 @@ async fn get_article_comments(
     .map(|row| ArticleComment {
         id: row.get("id"),
         content: row.get("content"),
+        parent_id: row.get("parent_id"),
         created_at: row.get("created_at"),
         user: ArticleCommentUser {
             id: row.get("user_id"),
@@ -373,15 +601,64 @@ async fn get_article_comments(
             username: row.get("user_username"),
             avatar: row.get("user_avatar"),
         },
+        like_count: row.get("like_count"),
+        has_liked: row.get("has_liked"),
+        is_author: row.get("is_author"),
+        author_liked: row.get("author_liked"),
+        replies: Vec::new(),
     })
     .collect::<Vec<_>>();
 
+    let total = comments.len();
+    let mut threads = build_comment_tree(comments);
+    if sort_top {
+        threads.sort_by(|a, b| {
+            b.like_count
+                .cmp(&a.like_count)
+                .then(b.created_at.cmp(&a.created_at))
+        });
+    } else {
+        threads.sort_by_key(|comment| std::cmp::Reverse(comment.created_at));
+    }
+
     Ok(ResponseJson(json!({
         "success": true,
-        "data": comments
+        "data": threads,
+        "total": total,
+        "sort": if sort_top { "top" } else { "newest" }
     })))
 }
 
+/// Nest replies under their parents. Replies stay in chronological order; comments whose
+/// parent is missing are shown at the top level.
+fn build_comment_tree(comments: Vec<ArticleComment>) -> Vec<ArticleComment> {
+    let ids: HashSet<Uuid> = comments.iter().map(|comment| comment.id).collect();
+    let mut children: HashMap<Uuid, Vec<ArticleComment>> = HashMap::new();
+    let mut roots = Vec::new();
+
+    for comment in comments {
+        match comment.parent_id.filter(|parent_id| ids.contains(parent_id)) {
+            Some(parent_id) => children.entry(parent_id).or_default().push(comment),
+            None => roots.push(comment),
+        }
+    }
+
+    fn attach(comment: &mut ArticleComment, children: &mut HashMap<Uuid, Vec<ArticleComment>>) {
+        if let Some(mut replies) = children.remove(&comment.id) {
+            for reply in replies.iter_mut() {
+                attach(reply, children);
+            }
+            comment.replies = replies;
+        }
+    }
+
+    for root in roots.iter_mut() {
+        attach(root, &mut children);
+    }
+
+    roots
+}
+
 async fn create_article_comment(
     State(db): State<Database>,
     Path(id): Path<String>,
@@ -394,30 +671,607 @@ async fn create_article_comment(
 
     let article_id = Uuid::parse_str(&id).map_err(|_| StatusCode::BAD_REQUEST)?;
 
+    if let Some(parent_id) = payload.parent_id {
+        let parent_in_article = sqlx::query_scalar::<_, bool>(
+            "SELECT EXISTS(SELECT 1 FROM article_comments WHERE id = $1 AND article_id = $2)",
+        )
+        .bind(parent_id)
+        .bind(article_id)
+        .fetch_one(&db.pool)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+        if !parent_in_article {
+            return Err(StatusCode::BAD_REQUEST);
+        }
+    }
+
     let comment = sqlx::query(
         r#"
-        INSERT INTO article_comments (article_id, user_id, content)
-        VALUES ($1, $2, $3)
-        RETURNING id, created_at
+        INSERT INTO article_comments (article_id, user_id, content, parent_id)
+        VALUES ($1, $2, $3, $4)
+        RETURNING id, created_at,
+            user_id = (SELECT author_id FROM articles WHERE id = $1) AS is_author,
+            (SELECT author_id FROM articles WHERE id = $1) AS author_id,
+            (SELECT title FROM articles WHERE id = $1) AS article_title,
+            (SELECT slug FROM articles WHERE id = $1) AS article_slug,
+            (SELECT user_id FROM article_comments WHERE id = $4) AS parent_author_id,
+            (SELECT COALESCE(display_name, name, username) FROM users WHERE id = $2)
+                AS commenter_name
         "#,
     )
     .bind(article_id)
     .bind(&claims.sub)
     .bind(&payload.content)
+    .bind(payload.parent_id)
     .fetch_one(&db.pool)
     .await
     .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
+    let comment_id = comment.get::<Uuid, _>("id");
+    let title = comment.get::<Option<String>, _>("article_title").unwrap_or_default();
+    let author_id = comment.get::<Option<String>, _>("author_id");
+    let parent_author_id = comment.get::<Option<String>, _>("parent_author_id");
+    let commenter_name = comment
+        .get::<Option<String>, _>("commenter_name")
+        .unwrap_or_else(|| "Someone".to_string());
+    notify_comment(
+        &db,
+        CommentActivity {
+            comment_id,
+            commenter_id: &claims.sub,
+            commenter_name: &commenter_name,
+            content: &payload.content,
+            owner_id: author_id.as_deref(),
+            parent_author_id: parent_author_id.as_deref(),
+            title: &title,
+            link: comment
+                .get::<Option<String>, _>("article_slug")
+                .map(|slug| format!("/blog/{}", slug)),
+            ids: json!({ "articleId": article_id, "parentId": payload.parent_id }),
+        },
+    )
+    .await;
+
     Ok(ResponseJson(json!({
         "success": true,
         "data": {
-            "id": comment.get::<Uuid, _>("id"),
+            "id": comment_id,
             "content": payload.content,
+            "parentId": payload.parent_id,
+            "isAuthor": comment.get::<Option<bool>, _>("is_author").unwrap_or(false),
+            "likeCount": 0,
+            "replies": [],
             "createdAt": comment.get::<chrono::DateTime<chrono::Utc>, _>("created_at")
         }
     })))
 }
 
+async fn toggle_article_comment_like(
+    State(db): State<Database>,
+    Path((id, comment_id)): Path<(String, Uuid)>,
+    claims: Claims,
+) -> Result<ResponseJson<serde_json::Value>, StatusCode> {
+    let article_id = Uuid::parse_str(&id).map_err(|_| StatusCode::BAD_REQUEST)?;
+
+    let comment_exists = sqlx::query_scalar::<_, bool>(
+        "SELECT EXISTS(SELECT 1 FROM article_comments WHERE id = $1 AND article_id = $2)",
+    )
+    .bind(comment_id)
+    .bind(article_id)
+    .fetch_one(&db.pool)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    if !comment_exists {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    let inserted = sqlx::query(
+        r#"
+        INSERT INTO article_comment_likes (comment_id, user_id)
+        VALUES ($1, $2)
+        ON CONFLICT (comment_id, user_id) DO NOTHING
+        "#,
+    )
+    .bind(comment_id)
+    .bind(&claims.sub)
+    .execute(&db.pool)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    .rows_affected()
+        > 0;
+
+    if !inserted {
+        sqlx::query("DELETE FROM article_comment_likes WHERE comment_id = $1 AND user_id = $2")
+            .bind(comment_id)
+            .bind(&claims.sub)
+            .execute(&db.pool)
+            .await
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    }
+
+    let like_count = sqlx::query_scalar::<_, i64>(
+        "SELECT COUNT(*) FROM article_comment_likes WHERE comment_id = $1",
+    )
+    .bind(comment_id)
+    .fetch_one(&db.pool)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(ResponseJson(json!({
+        "success": true,
+        "data": {
+            "liked": inserted,
+            "likeCount": like_count
+        }
+    })))
+}
+
+async fn get_reading_progress(
+    State(db): State<Database>,
+    Path(id_or_slug): Path<String>,
+    claims: Claims,
+) -> Result<ResponseJson<serde_json::Value>, StatusCode> {
+    let article_id = resolve_article_id(&db, &id_or_slug).await?;
+
+    let progress = sqlx::query(
+        r#"
+        SELECT percentage, paragraph_anchor, updated_at
+        FROM article_reading_progress
+        WHERE user_id = $1 AND article_id = $2
+        "#,
+    )
+    .bind(&claims.sub)
+    .bind(article_id)
+    .fetch_optional(&db.pool)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(ResponseJson(json!({
+        "success": true,
+        "data": progress.map(|row| json!({
+            "articleId": article_id,
+            "percentage": row.get::<f64, _>("percentage"),
+            "anchor": row.get::<Option<String>, _>("paragraph_anchor"),
+            "updatedAt": row.get::<chrono::DateTime<chrono::Utc>, _>("updated_at")
+        }))
+    })))
+}
+
+async fn save_reading_progress(
+    State(db): State<Database>,
+    Path(id_or_slug): Path<String>,
+    claims: Claims,
+    Json(payload): Json<ReadingProgressRequest>,
+) -> Result<ResponseJson<serde_json::Value>, StatusCode> {
+    if !payload.percentage.is_finite() || !(0.0..=100.0).contains(&payload.percentage) {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let anchor = payload
+        .anchor
+        .map(|anchor| anchor.trim().to_string())
+        .filter(|anchor| !anchor.is_empty());
+    if anchor.as_ref().map(|anchor| anchor.len() > 255).unwrap_or(false) {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let article_id = resolve_article_id(&db, &id_or_slug).await?;
+
+    let updated_at = sqlx::query_scalar::<_, chrono::DateTime<chrono::Utc>>(
+        r#"
+        INSERT INTO article_reading_progress (user_id, article_id, percentage, paragraph_anchor, updated_at)
+        VALUES ($1, $2, $3, $4, NOW())
+        ON CONFLICT (user_id, article_id) DO UPDATE
+        SET percentage = EXCLUDED.percentage,
+            paragraph_anchor = EXCLUDED.paragraph_anchor,
+            updated_at = NOW()
+        RETURNING updated_at
+        "#,
+    )
+    .bind(&claims.sub)
+    .bind(article_id)
+    .bind(payload.percentage)
+    .bind(&anchor)
+    .fetch_one(&db.pool)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(ResponseJson(json!({
+        "success": true,
+        "data": {
+            "articleId": article_id,
+            "percentage": payload.percentage,
+            "anchor": anchor,
+            "updatedAt": updated_at
+        }
+    })))
+}
+
+// Daily views, top referrers and countries for one of the author's articles
+async fn get_article_analytics(
+    State(db): State<Database>,
+    Path(id_or_slug): Path<String>,
+    Query(params): Query<AnalyticsQuery>,
+    claims: Claims,
+) -> Result<ResponseJson<serde_json::Value>, StatusCode> {
+    let article_id = resolve_article_id(&db, &id_or_slug).await?;
+    let days = params.days.unwrap_or(30).clamp(1, 365);
+
+    let article = sqlx::query("SELECT author_id, view_count FROM articles WHERE id = $1")
+        .bind(article_id)
+        .fetch_one(&db.pool)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    if article.get::<String, _>("author_id") != claims.sub {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    let daily = sqlx::query(
+        r#"
+        SELECT d.day::DATE AS day,
+               COUNT(v.id) AS views,
+               COUNT(DISTINCT v.viewer_id) AS readers
+        FROM generate_series(
+            date_trunc('day', NOW()) - ($2 - 1) * INTERVAL '1 day',
+            date_trunc('day', NOW()),
+            INTERVAL '1 day'
+        ) AS d(day)
+        LEFT JOIN article_views v
+            ON v.article_id = $1
+           AND v.viewed_at >= d.day
+           AND v.viewed_at < d.day + INTERVAL '1 day'
+        GROUP BY d.day
+        ORDER BY d.day ASC
+        "#,
+    )
+    .bind(article_id)
+    .bind(days as i32)
+    .fetch_all(&db.pool)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    .iter()
+    .map(|row| {
+        json!({
+            "date": row.get::<chrono::NaiveDate, _>("day"),
+            "views": row.get::<i64, _>("views"),
+            "signedInReaders": row.get::<i64, _>("readers"),
+        })
+    })
+    .collect::<Vec<_>>();
+
+    let top_referrers = top_breakdown(&db, article_id, days, "referrer").await?;
+    let top_countries = top_breakdown(&db, article_id, days, "country").await?;
+
+    let period_views: i64 = daily
+        .iter()
+        .map(|day| day["views"].as_i64().unwrap_or(0))
+        .sum();
+
+    Ok(ResponseJson(json!({
+        "success": true,
+        "data": {
+            "articleId": article_id,
+            "days": days,
+            "totalViews": article.get::<i64, _>("view_count"),
+            "periodViews": period_views,
+            "daily": daily,
+            "topReferrers": top_referrers,
+            "topCountries": top_countries,
+        }
+    })))
+}
+
+// Queue an import from an RSS/Atom feed URL (`feedUrl`) or a Medium export archive (`file`)
+async fn import_articles(
+    State(db): State<Database>,
+    claims: Claims,
+    mut multipart: Multipart,
+) -> Result<ResponseJson<serde_json::Value>, StatusCode> {
+    let is_creator = sqlx::query_scalar::<_, bool>("SELECT is_creator FROM users WHERE id = $1")
+        .bind(&claims.sub)
+        .fetch_one(&db.pool)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    if !is_creator {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    let mut feed_url: Option<String> = None;
+    let mut upload: Option<(String, Vec<u8>)> = None;
+
+    while let Some(mut field) = multipart
+        .next_field()
+        .await
+        .map_err(|_| StatusCode::BAD_REQUEST)?
+    {
+        match field.name() {
+            Some("feedUrl") => {
+                let value = field.text().await.map_err(|_| StatusCode::BAD_REQUEST)?;
+                feed_url = Some(value.trim().to_string()).filter(|value| !value.is_empty());
+            }
+            Some("file") => {
+                let filename = field
+                    .file_name()
+                    .and_then(|name| std::path::Path::new(name).file_name())
+                    .and_then(|name| name.to_str())
+                    .unwrap_or("medium-export.zip")
+                    .to_string();
+
+                let mut bytes: Vec<u8> = Vec::new();
+                while let Some(chunk) = field.chunk().await.map_err(|_| StatusCode::BAD_REQUEST)? {
+                    if bytes.len() + chunk.len() > IMPORT_MAX_BYTES {
+                        return Err(StatusCode::PAYLOAD_TOO_LARGE);
+                    }
+                    bytes.extend_from_slice(&chunk);
+                }
+
+                upload = Some((filename, bytes));
+            }
+            _ => continue,
+        }
+    }
+
+    let (source, filename, storage_key) = match (feed_url.as_deref(), upload) {
+        (Some(url), None) => {
+            if !url.starts_with("http://") && !url.starts_with("https://") {
+                return Err(StatusCode::BAD_REQUEST);
+            }
+            ("rss", None, None)
+        }
+        (None, Some((filename, bytes))) => {
+            if bytes.is_empty() || !filename.to_ascii_lowercase().ends_with(".zip") {
+                return Err(StatusCode::BAD_REQUEST);
+            }
+            let stored =
+                storage::store_private_file("imports", Some(&filename), "application/zip", bytes)
+                    .await
+                    .map_err(|e| {
+                        error!("Failed to store article import for {}: {:?}", claims.sub, e);
+                        StatusCode::INTERNAL_SERVER_ERROR
+                    })?;
+            ("medium", Some(filename), Some(stored.storage_key))
+        }
+        _ => return Err(StatusCode::BAD_REQUEST),
+    };
+
+    let import_id = sqlx::query_scalar::<_, Uuid>(
+        r#"
+        INSERT INTO article_imports (user_id, source, feed_url, filename, storage_key)
+        VALUES ($1, $2, $3, $4, $5)
+        RETURNING id
+        "#,
+    )
+    .bind(&claims.sub)
+    .bind(source)
+    .bind(&feed_url)
+    .bind(&filename)
+    .bind(&storage_key)
+    .fetch_one(&db.pool)
+    .await
+    .map_err(|e| {
+        error!("Failed to create article import for {}: {:?}", claims.sub, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    match &db.amqp {
+        Some(amqp) => {
+            if let Err(e) = amqp.send_article_import_job(import_id.to_string()).await {
+                error!("Failed to queue article import {}: {:?}", import_id, e);
+                let _ = sqlx::query(
+                    "UPDATE article_imports SET status = 'FAILED', error = 'Could not queue import', updated_at = NOW() WHERE id = $1",
+                )
+                .bind(import_id)
+                .execute(&db.pool)
+                .await;
+                return Err(StatusCode::SERVICE_UNAVAILABLE);
+            }
+        }
+        None => {
+            // Without CloudAMQP the import still runs in the background of this process.
+            let db = db.clone();
+            tokio::spawn(async move {
+                let _ = jobs::article_import::run_import(&db, &import_id.to_string()).await;
+            });
+        }
+    }
+
+    Ok(ResponseJson(json!({
+        "success": true,
+        "data": {
+            "id": import_id,
+            "source": source,
+            "status": "QUEUED"
+        }
+    })))
+}
+
+// Progress of one of the creator's article imports
+async fn get_article_import(
+    State(db): State<Database>,
+    Path(import_id): Path<Uuid>,
+    claims: Claims,
+) -> Result<ResponseJson<serde_json::Value>, StatusCode> {
+    let row = sqlx::query(
+        r#"
+        SELECT id, source, feed_url, filename, status, total_items, processed_items,
+               imported_articles, skipped_items, error, created_at, completed_at
+        FROM article_imports
+        WHERE id = $1 AND user_id = $2
+        "#,
+    )
+    .bind(import_id)
+    .bind(&claims.sub)
+    .fetch_optional(&db.pool)
+    .await
+    .map_err(|e| {
+        error!("Failed to load article import {}: {:?}", import_id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?
+    .ok_or(StatusCode::NOT_FOUND)?;
+
+    let total_items: i32 = row.get("total_items");
+    let processed_items: i32 = row.get("processed_items");
+    let status: String = row.get("status");
+    let progress = if status == "COMPLETED" {
+        100.0
+    } else if total_items > 0 {
+        (processed_items as f64 / total_items as f64 * 1000.0).round() / 10.0
+    } else {
+        0.0
+    };
+
+    Ok(ResponseJson(json!({
+        "success": true,
+        "data": {
+            "id": row.get::<Uuid, _>("id"),
+            "source": row.get::<String, _>("source"),
+            "feedUrl": row.get::<Option<String>, _>("feed_url"),
+            "filename": row.get::<Option<String>, _>("filename"),
+            "status": status,
+            "totalItems": total_items,
+            "processedItems": processed_items,
+            "importedArticles": row.get::<i32, _>("imported_articles"),
+            "skippedItems": row.get::<i32, _>("skipped_items"),
+            "progress": progress,
+            "error": row.get::<Option<String>, _>("error"),
+            "createdAt": row.get::<chrono::DateTime<chrono::Utc>, _>("created_at"),
+            "completedAt": row.get::<Option<chrono::DateTime<chrono::Utc>>, _>("completed_at"),
+        }
+    })))
+}
+
+/// Ten most common values of `column` among the article's views in the period;
+/// views without a value are grouped as "direct"/"unknown".
+async fn top_breakdown(
+    db: &Database,
+    article_id: Uuid,
+    days: i64,
+    column: &str,
+) -> Result<Vec<serde_json::Value>, StatusCode> {
+    let fallback = if column == "referrer" { "direct" } else { "unknown" };
+
+    let rows = sqlx::query(&format!(
+        r#"
+        SELECT COALESCE({column}, '{fallback}') AS value, COUNT(*) AS views
+        FROM article_views
+        WHERE article_id = $1 AND viewed_at >= NOW() - $2 * INTERVAL '1 day'
+        GROUP BY 1
+        ORDER BY views DESC
+        LIMIT 10
+        "#,
+    ))
+    .bind(article_id)
+    .bind(days as i32)
+    .fetch_all(&db.pool)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(rows
+        .iter()
+        .map(|row| {
+            json!({
+                "value": row.get::<String, _>("value"),
+                "views": row.get::<i64, _>("views"),
+            })
+        })
+        .collect())
+}
+
+/// Authors whose premium articles the viewer may read in full: their own and those of
+/// creators they actively subscribe to.
+async fn load_subscribed_authors(
+    db: &Database,
+    viewer_id: Option<&str>,
+    author_ids: &[String],
+) -> Result<HashSet<String>, StatusCode> {
+    let viewer_id = match viewer_id {
+        Some(viewer_id) if !author_ids.is_empty() => viewer_id,
+        _ => return Ok(HashSet::new()),
+    };
+
+    let mut readable: HashSet<String> = sqlx::query_scalar::<_, String>(
+        r#"
+        SELECT creator_id
+        FROM subscriptions
+        WHERE user_id = $1 AND UPPER(status) = 'ACTIVE' AND creator_id = ANY($2)
+        "#,
+    )
+    .bind(viewer_id)
+    .bind(author_ids)
+    .fetch_all(&db.pool)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    .into_iter()
+    .collect();
+
+    if author_ids.iter().any(|author_id| author_id == viewer_id) {
+        readable.insert(viewer_id.to_string());
+    }
+
+    Ok(readable)
+}
+
+/// Cut premium content down to its preview for readers without access (returning where the
+/// locked part starts), or strip the paywall marker for everyone else.
+fn gate_content(
+    content: Option<String>,
+    is_premium: bool,
+    has_access: bool,
+) -> (Option<String>, Option<i64>) {
+    match content {
+        Some(content) if is_premium && !has_access => {
+            let (preview, locked_at) = article_preview(&content);
+            (Some(preview), Some(locked_at as i64))
+        }
+        content => (content.map(|content| content.replacen(PAYWALL_MARKER, "", 1)), None),
+    }
+}
+
+/// Free part of a premium article and the character offset where the locked part begins.
+/// An explicit paywall marker wins; otherwise the first few paragraphs are shown.
+pub(crate) fn article_preview(content: &str) -> (String, usize) {
+    let cut = content.find(PAYWALL_MARKER).or_else(|| {
+        let (separator, keep_separator) = if content.contains("</p>") {
+            ("</p>", true)
+        } else {
+            ("\n\n", false)
+        };
+
+        content
+            .match_indices(separator)
+            .nth(PREVIEW_PARAGRAPHS - 1)
+            .map(|(index, _)| if keep_separator { index + separator.len() } else { index })
+    });
+
+    let preview = match cut {
+        Some(index) => content[..index].trim_end().to_string(),
+        None => content.to_string(),
+    };
+    let locked_at = content[..cut.unwrap_or(content.len())].chars().count();
+
+    (preview, locked_at)
+}
+
+/// Articles are addressed by id or slug.
+async fn resolve_article_id(db: &Database, id_or_slug: &str) -> Result<Uuid, StatusCode> {
+    let query = match Uuid::parse_str(id_or_slug) {
+        Ok(uuid) => sqlx::query_scalar::<_, Uuid>("SELECT id FROM articles WHERE id = $1").bind(uuid),
+        Err(_) => {
+            sqlx::query_scalar::<_, Uuid>("SELECT id FROM articles WHERE slug = $1").bind(id_or_slug)
+        }
+    };
+
+    query
+        .fetch_optional(&db.pool)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::NOT_FOUND)
+}
+
 fn calculate_total_pages(total: usize, limit: u32) -> u32 {
     if limit == 0 {
         0
@@ -1,6 +1,6 @@
 use axum::{
     extract::{Query, State},
-    http::StatusCode,
+    http::{HeaderMap, StatusCode},
     response::{IntoResponse, Response},
     routing::{get, post},
     Json, Router,
@@ -11,16 +11,24 @@ use oauth2::{
     ClientSecret, CsrfToken, RedirectUrl, Scope, TokenResponse, TokenUrl,
 };
 use serde::Deserialize;
+use sha2::{Digest, Sha256};
 
 use crate::{
     config::Config,
     database::Database,
+    email_service::{app_link, queue_email, EmailTemplate},
     models::{AuthResponse, GitHubUser, User},
+    post_views::ip_hash,
+    routes::{referrals::attribute_referral, tax::ip_country},
 };
 
+/// How long a password reset link works.
+const PASSWORD_RESET_TTL_MINUTES: i64 = 60;
+
 #[derive(Debug, Deserialize)]
 pub struct AuthCallbackQuery {
     pub code: String,
+    #[allow(dead_code)]
     pub state: String,
 }
 
@@ -36,6 +44,20 @@ pub struct RegisterRequest {
     pub password: String,
     pub name: String,
     pub username: Option<String>,
+    /// A referral code the visitor arrived with.
+    #[serde(rename = "referralCode")]
+    pub referral_code: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ForgotPasswordRequest {
+    pub email: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ResetPasswordRequest {
+    pub token: String,
+    pub password: String,
 }
 
 pub fn auth_routes() -> Router<Database> {
@@ -44,6 +66,8 @@ pub fn auth_routes() -> Router<Database> {
         .route("/github/callback", get(github_callback))
         .route("/login", post(login))
         .route("/register", post(register))
+        .route("/password/forgot", post(forgot_password))
+        .route("/password/reset", post(reset_password))
         .route("/me", get(get_current_user))
 }
 
@@ -69,6 +93,7 @@ async fn github_auth() -> impl IntoResponse {
 async fn github_callback(
     State(db): State<Database>,
     Query(params): Query<AuthCallbackQuery>,
+    headers: HeaderMap,
 ) -> Result<Json<AuthResponse>, AppError> {
     let config = Config::from_env().unwrap();
 
@@ -87,10 +112,10 @@ async fn github_callback(
         .map_err(|_| AppError::AuthError("Failed to exchange code for token".to_string()))?;
 
     // Get user info from GitHub
-    let github_user = get_github_user(&token.access_token().secret()).await?;
+    let github_user = get_github_user(token.access_token().secret()).await?;
 
     // Find or create user
-    let user = find_or_create_user(&db, &github_user).await?;
+    let user = find_or_create_user(&db, &github_user, &headers).await?;
 
     // Generate JWT token
     let token = generate_jwt(&user, &config.jwt_secret)?;
@@ -120,7 +145,11 @@ async fn get_github_user(access_token: &str) -> Result<GitHubUser, AppError> {
     Ok(github_user)
 }
 
-async fn find_or_create_user(db: &Database, github_user: &GitHubUser) -> Result<User, AppError> {
+async fn find_or_create_user(
+    db: &Database,
+    github_user: &GitHubUser,
+    headers: &HeaderMap,
+) -> Result<User, AppError> {
     // Try to find existing user by GitHub ID
     let existing_user = sqlx::query_as::<_, User>("SELECT * FROM users WHERE github_id = $1")
         .bind(github_user.id)
@@ -135,8 +164,10 @@ async fn find_or_create_user(db: &Database, github_user: &GitHubUser) -> Result<
     // Create new user
     let user = sqlx::query_as::<_, User>(
         r#"
-        INSERT INTO users (github_id, username, email, display_name, avatar_url, bio)
-        VALUES ($1, $2, $3, $4, $5, $6)
+        INSERT INTO users (
+            github_id, username, email, display_name, avatar_url, bio, signup_country, signup_ip_hash
+        )
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
         RETURNING *
         "#,
     )
@@ -146,6 +177,8 @@ async fn find_or_create_user(db: &Database, github_user: &GitHubUser) -> Result<
     .bind(&github_user.name)
     .bind(&github_user.avatar_url)
     .bind(&github_user.bio)
+    .bind(ip_country(headers))
+    .bind(ip_hash(headers))
     .fetch_one(&db.pool)
     .await
     .map_err(|_| AppError::DatabaseError("Failed to create user".to_string()))?;
@@ -202,6 +235,7 @@ async fn login(
 
 async fn register(
     State(db): State<Database>,
+    headers: HeaderMap,
     Json(payload): Json<RegisterRequest>,
 ) -> Result<Json<AuthResponse>, AppError> {
     let config = Config::from_env().unwrap();
@@ -231,8 +265,10 @@ async fn register(
     // Create new user
     let user = sqlx::query_as::<_, User>(
         r#"
-        INSERT INTO users (id, email, name, username, password_hash, is_creator)
-        VALUES ($1, $2, $3, $4, $5, $6)
+        INSERT INTO users (
+            id, email, name, username, password_hash, is_creator, signup_country, signup_ip_hash
+        )
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
         RETURNING *
         "#,
     )
@@ -242,9 +278,14 @@ async fn register(
     .bind(&payload.username)
     .bind(&password_hash)
     .bind(false)
+    .bind(ip_country(&headers))
+    .bind(ip_hash(&headers))
     .fetch_one(&db.pool)
     .await
     .map_err(|_| AppError::DatabaseError("Failed to create user".to_string()))?;
+    if let Some(code) = &payload.referral_code {
+        attribute_referral(&db, code, &user.id, "SIGNUP", ip_hash(&headers).as_deref()).await;
+    }
 
     // Generate JWT token
     let token = generate_jwt(&user, &config.jwt_secret)?;
@@ -252,6 +293,122 @@ async fn register(
     Ok(Json(AuthResponse { user, token }))
 }
 
+// Answers the same whether or not the address has an account, so it can't be used to find out
+// which emails are registered.
+async fn forgot_password(
+    State(db): State<Database>,
+    Json(payload): Json<ForgotPasswordRequest>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    let response = Json(serde_json::json!({
+        "success": true,
+        "message": "If an account exists for this email, a reset link is on its way"
+    }));
+
+    let user = sqlx::query_as::<_, (String, String)>(
+        "SELECT id, email FROM users WHERE LOWER(email) = LOWER($1)",
+    )
+    .bind(payload.email.trim())
+    .fetch_optional(&db.pool)
+    .await
+    .map_err(|_| AppError::DatabaseError("Failed to query user".to_string()))?;
+    let Some((user_id, email)) = user else {
+        return Ok(response);
+    };
+
+    // Only the hash is stored; the token itself exists in the email alone
+    let token = format!("{}{}", uuid::Uuid::new_v4().simple(), uuid::Uuid::new_v4().simple());
+    let mut tx = db
+        .pool
+        .begin()
+        .await
+        .map_err(|_| AppError::DatabaseError("Failed to create reset token".to_string()))?;
+    // A new link replaces any earlier one
+    sqlx::query("DELETE FROM password_reset_tokens WHERE user_id = $1 AND used_at IS NULL")
+        .bind(&user_id)
+        .execute(&mut tx)
+        .await
+        .map_err(|_| AppError::DatabaseError("Failed to create reset token".to_string()))?;
+    sqlx::query(
+        r#"
+        INSERT INTO password_reset_tokens (token_hash, user_id, expires_at)
+        VALUES ($1, $2, NOW() + make_interval(mins => $3))
+        "#,
+    )
+    .bind(hash_reset_token(&token))
+    .bind(&user_id)
+    .bind(PASSWORD_RESET_TTL_MINUTES as i32)
+    .execute(&mut tx)
+    .await
+    .map_err(|_| AppError::DatabaseError("Failed to create reset token".to_string()))?;
+    tx.commit()
+        .await
+        .map_err(|_| AppError::DatabaseError("Failed to create reset token".to_string()))?;
+
+    queue_email(
+        &db,
+        &email,
+        Some(&user_id),
+        EmailTemplate::PasswordReset,
+        serde_json::json!({
+            "link": app_link(&format!("/reset-password?token={}", token)),
+            "expires_in": format!("{} minutes", PASSWORD_RESET_TTL_MINUTES),
+        }),
+    )
+    .await;
+
+    Ok(response)
+}
+
+async fn reset_password(
+    State(db): State<Database>,
+    Json(payload): Json<ResetPasswordRequest>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    if payload.password.trim().len() < 8 {
+        return Err(AppError::ValidationError(
+            "Password must be at least 8 characters long".to_string(),
+        ));
+    }
+    let password_hash = hash(payload.password.trim(), DEFAULT_COST)
+        .map_err(|_| AppError::AuthError("Failed to hash password".to_string()))?;
+
+    let mut tx = db
+        .pool
+        .begin()
+        .await
+        .map_err(|_| AppError::DatabaseError("Failed to reset password".to_string()))?;
+    let user_id = sqlx::query_scalar::<_, String>(
+        r#"
+        UPDATE password_reset_tokens SET used_at = NOW()
+        WHERE token_hash = $1 AND used_at IS NULL AND expires_at > NOW()
+        RETURNING user_id
+        "#,
+    )
+    .bind(hash_reset_token(payload.token.trim()))
+    .fetch_optional(&mut tx)
+    .await
+    .map_err(|_| AppError::DatabaseError("Failed to reset password".to_string()))?
+    .ok_or_else(|| AppError::ValidationError("Reset link is invalid or has expired".to_string()))?;
+
+    sqlx::query("UPDATE users SET password_hash = $2, updated_at = NOW() WHERE id = $1")
+        .bind(&user_id)
+        .bind(&password_hash)
+        .execute(&mut tx)
+        .await
+        .map_err(|_| AppError::DatabaseError("Failed to reset password".to_string()))?;
+    tx.commit()
+        .await
+        .map_err(|_| AppError::DatabaseError("Failed to reset password".to_string()))?;
+
+    Ok(Json(serde_json::json!({
+        "success": true,
+        "message": "Password updated; sign in with the new one"
+    })))
+}
+
+fn hash_reset_token(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
 fn generate_jwt(user: &User, secret: &str) -> Result<String, AppError> {
     let now = chrono::Utc::now();
     let exp = now + chrono::Duration::days(7);
@@ -275,6 +432,7 @@ fn generate_jwt(user: &User, secret: &str) -> Result<String, AppError> {
     Ok(token)
 }
 
+#[allow(clippy::enum_variant_names)]
 #[derive(Debug, thiserror::Error)]
 pub enum AppError {
     #[error("Authentication error: {0}")]
@@ -0,0 +1,345 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::Json,
+    routing::{delete, get},
+    Router,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use uuid::Uuid;
+
+use crate::{
+    auth::Claims,
+    database::Database,
+    pagination::{parse_cursor, Cursor},
+    routes::feed_mutes::invalidate_feed_cache,
+};
+
+/// What a bookmark points at.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub(crate) enum BookmarkType {
+    Post,
+    Article,
+    Campaign,
+    Product,
+    Event,
+}
+
+impl BookmarkType {
+    pub(crate) fn as_str(self) -> &'static str {
+        match self {
+            BookmarkType::Post => "POST",
+            BookmarkType::Article => "ARTICLE",
+            BookmarkType::Campaign => "CAMPAIGN",
+            BookmarkType::Product => "PRODUCT",
+            BookmarkType::Event => "EVENT",
+        }
+    }
+
+    fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_uppercase().as_str() {
+            "POST" => Some(BookmarkType::Post),
+            "ARTICLE" => Some(BookmarkType::Article),
+            "CAMPAIGN" => Some(BookmarkType::Campaign),
+            "PRODUCT" => Some(BookmarkType::Product),
+            "EVENT" => Some(BookmarkType::Event),
+            _ => None,
+        }
+    }
+
+    fn table(self) -> &'static str {
+        match self {
+            BookmarkType::Post => "posts",
+            BookmarkType::Article => "articles",
+            BookmarkType::Campaign => "campaigns",
+            BookmarkType::Product => "products",
+            BookmarkType::Event => "events",
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BookmarkPayload {
+    pub content_type: BookmarkType,
+    pub content_id: Uuid,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct BookmarkQuery {
+    /// One type, or several separated by commas, e.g. `post,article`.
+    #[serde(rename = "type")]
+    pub content_type: Option<String>,
+    /// `nextCursor` of the previous page.
+    pub cursor: Option<String>,
+    pub limit: Option<u32>,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+struct Bookmark {
+    id: Uuid,
+    user_id: String,
+    content_type: String,
+    content_id: Uuid,
+    created_at: DateTime<Utc>,
+}
+
+/// A bookmark with enough of the saved content to list it; the content fields are empty when
+/// it has been deleted since.
+#[derive(Debug, Serialize, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+struct SavedItem {
+    id: Uuid,
+    content_type: String,
+    content_id: Uuid,
+    created_at: DateTime<Utc>,
+    title: Option<String>,
+    image: Option<String>,
+    /// Article and campaign slug, used for links.
+    #[serde(skip)]
+    slug: Option<String>,
+    creator_id: Option<String>,
+    creator_name: Option<String>,
+    creator_username: Option<String>,
+    #[sqlx(default)]
+    link: Option<String>,
+}
+
+pub fn bookmark_routes() -> Router<Database> {
+    Router::new()
+        .route("/", get(list_saved_items).post(add_bookmark))
+        .route("/:id", delete(delete_bookmark))
+}
+
+/// Content ids of the given type among `content_ids` that the user has bookmarked.
+pub(crate) async fn bookmarked_ids(
+    db: &Database,
+    user_id: &str,
+    content_type: BookmarkType,
+    content_ids: &[Uuid],
+) -> Result<Vec<Uuid>, StatusCode> {
+    if content_ids.is_empty() {
+        return Ok(Vec::new());
+    }
+    sqlx::query_scalar(
+        "SELECT content_id FROM bookmarks WHERE user_id = $1 AND content_type = $2 AND content_id = ANY($3)",
+    )
+    .bind(user_id)
+    .bind(content_type.as_str())
+    .bind(content_ids)
+    .fetch_all(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to load bookmarks for {}: {}", user_id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })
+}
+
+// The feed's bookmark list: every bookmark, newest first
+pub(crate) async fn list_bookmarks(
+    State(db): State<Database>,
+    claims: Claims,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let bookmarks = sqlx::query_as::<_, Bookmark>(
+        "SELECT * FROM bookmarks WHERE user_id = $1 ORDER BY created_at DESC, id DESC",
+    )
+    .bind(&claims.sub)
+    .fetch_all(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to list bookmarks: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    Ok(Json(json!({ "success": true, "data": bookmarks })))
+}
+
+/// Saving something twice keeps the original bookmark.
+pub(crate) async fn add_bookmark(
+    State(db): State<Database>,
+    claims: Claims,
+    Json(payload): Json<BookmarkPayload>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let exists = sqlx::query_scalar::<_, bool>(&format!(
+        "SELECT EXISTS(SELECT 1 FROM {} WHERE id = $1)",
+        payload.content_type.table()
+    ))
+    .bind(payload.content_id)
+    .fetch_one(&db.pool)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    if !exists {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    let bookmark = sqlx::query_as::<_, Bookmark>(
+        r#"
+        WITH inserted AS (
+            INSERT INTO bookmarks (user_id, content_type, content_id) VALUES ($1, $2, $3)
+            ON CONFLICT (user_id, content_type, content_id) DO NOTHING
+            RETURNING *
+        )
+        SELECT * FROM inserted
+        UNION ALL
+        SELECT * FROM bookmarks WHERE user_id = $1 AND content_type = $2 AND content_id = $3
+        LIMIT 1
+        "#,
+    )
+    .bind(&claims.sub)
+    .bind(payload.content_type.as_str())
+    .bind(payload.content_id)
+    .fetch_one(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to add bookmark: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    invalidate_feed_cache(&db, &claims.sub).await;
+    Ok(Json(json!({ "success": true, "data": bookmark })))
+}
+
+// The feed removes by content rather than bookmark id
+pub(crate) async fn remove_bookmark(
+    State(db): State<Database>,
+    claims: Claims,
+    Json(payload): Json<BookmarkPayload>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let removed = sqlx::query(
+        "DELETE FROM bookmarks WHERE user_id = $1 AND content_type = $2 AND content_id = $3",
+    )
+    .bind(&claims.sub)
+    .bind(payload.content_type.as_str())
+    .bind(payload.content_id)
+    .execute(&db.pool)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    .rows_affected();
+    if removed == 0 {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    invalidate_feed_cache(&db, &claims.sub).await;
+    Ok(Json(json!({ "success": true })))
+}
+
+async fn delete_bookmark(
+    State(db): State<Database>,
+    Path(id): Path<Uuid>,
+    claims: Claims,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let removed = sqlx::query("DELETE FROM bookmarks WHERE id = $1 AND user_id = $2")
+        .bind(id)
+        .bind(&claims.sub)
+        .execute(&db.pool)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .rows_affected();
+    if removed == 0 {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    invalidate_feed_cache(&db, &claims.sub).await;
+    Ok(Json(json!({ "success": true, "data": { "id": id } })))
+}
+
+// The read-later list, newest first, with the saved content filled in
+async fn list_saved_items(
+    State(db): State<Database>,
+    Query(params): Query<BookmarkQuery>,
+    claims: Claims,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let limit = params.limit.unwrap_or(20).clamp(1, 100) as i64;
+    let cursor = parse_cursor(params.cursor.as_deref())?;
+    let cursor_id = cursor.as_ref().map(Cursor::uuid).transpose()?;
+    let types: Option<Vec<&'static str>> = params
+        .content_type
+        .as_deref()
+        .map(|types| {
+            types
+                .split(',')
+                .filter(|value| !value.trim().is_empty())
+                .map(|value| BookmarkType::parse(value).map(BookmarkType::as_str))
+                .collect::<Option<Vec<_>>>()
+                .ok_or(StatusCode::BAD_REQUEST)
+        })
+        .transpose()?
+        .filter(|types| !types.is_empty());
+
+    let mut items = sqlx::query_as::<_, SavedItem>(
+        r#"
+        SELECT
+            b.id,
+            b.content_type,
+            b.content_id,
+            b.created_at,
+            COALESCE(p.title, a.title, c.title, pr.name, e.title) AS title,
+            COALESCE(p.image_urls[1], p.media_url, c.cover_image, pr.image_url, e.cover_image) AS image,
+            COALESCE(a.slug, c.slug) AS slug,
+            u.id AS creator_id,
+            COALESCE(u.display_name, u.username) AS creator_name,
+            u.username AS creator_username
+        FROM bookmarks b
+        LEFT JOIN posts p ON b.content_type = 'POST' AND p.id = b.content_id
+        LEFT JOIN articles a ON b.content_type = 'ARTICLE' AND a.id = b.content_id
+        LEFT JOIN campaigns c ON b.content_type = 'CAMPAIGN' AND c.id = b.content_id
+        LEFT JOIN products pr ON b.content_type = 'PRODUCT' AND pr.id = b.content_id
+        LEFT JOIN events e ON b.content_type = 'EVENT' AND e.id = b.content_id
+        LEFT JOIN users u ON u.id = COALESCE(p.user_id, a.author_id, c.creator_id, pr.user_id, e.host_id)
+        WHERE b.user_id = $1
+          AND ($2::TEXT[] IS NULL OR b.content_type = ANY($2))
+          AND ($3::TIMESTAMPTZ IS NULL OR (b.created_at, b.id) < ($3, $4))
+        ORDER BY b.created_at DESC, b.id DESC
+        LIMIT $5
+        "#,
+    )
+    .bind(&claims.sub)
+    .bind(types.as_ref())
+    .bind(cursor.as_ref().map(|cursor| cursor.at))
+    .bind(cursor_id)
+    .bind(limit + 1)
+    .fetch_all(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to list saved items: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    let has_more = items.len() > limit as usize;
+    items.truncate(limit as usize);
+    let next_cursor = if has_more {
+        items
+            .last()
+            .map(|item| Cursor::new(item.created_at, item.id).encode())
+    } else {
+        None
+    };
+
+    for item in items.iter_mut().filter(|item| item.title.is_some()) {
+        let creator = item
+            .creator_username
+            .clone()
+            .or_else(|| item.creator_id.clone())
+            .unwrap_or_default();
+        item.link = Some(match item.content_type.as_str() {
+            "ARTICLE" => format!("/blog/{}", item.slug.clone().unwrap_or_default()),
+            "CAMPAIGN" => format!("/campaigns/{}", item.slug.clone().unwrap_or_default()),
+            "PRODUCT" => format!("/products/{}", item.content_id),
+            "EVENT" => format!("/events/{}", item.content_id),
+            _ => format!("/creators/{}", creator),
+        });
+    }
+
+    Ok(Json(json!({
+        "success": true,
+        "data": items,
+        "pagination": {
+            "limit": limit,
+            "hasMore": has_more,
+            "nextCursor": next_cursor
+        }
+    })))
+}
@@ -0,0 +1,421 @@
+use std::collections::HashMap;
+
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::Json,
+};
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+use crate::{auth::Claims, database::Database};
+
+/// Results with a two-sided p-value below this are reported as significant.
+const SIGNIFICANCE_LEVEL: f64 = 0.05;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum Variant {
+    A,
+    B,
+}
+
+impl Variant {
+    pub(crate) fn as_str(self) -> &'static str {
+        match self {
+            Variant::A => "a",
+            Variant::B => "b",
+        }
+    }
+}
+
+/// A running title and cover test of one campaign.
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub(crate) struct CampaignExperiment {
+    pub id: Uuid,
+    pub campaign_id: Uuid,
+    title_a: String,
+    cover_image_a: Option<String>,
+    title_b: String,
+    cover_image_b: Option<String>,
+}
+
+impl CampaignExperiment {
+    /// The variant a visitor sees, the same on every visit: the experiment id and viewer key
+    /// hashed together, so assignments do not carry over between experiments.
+    pub(crate) fn variant_for(&self, viewer_key: &str) -> Variant {
+        let digest = Sha256::digest(format!("{}|{}", self.id, viewer_key).as_bytes());
+        if digest[0] & 1 == 0 {
+            Variant::A
+        } else {
+            Variant::B
+        }
+    }
+
+    /// Title and cover image of a variant; a variant without its own cover keeps the campaign's.
+    pub(crate) fn content(&self, variant: Variant) -> (&str, Option<&str>) {
+        match variant {
+            Variant::A => (&self.title_a, self.cover_image_a.as_deref()),
+            Variant::B => (&self.title_b, self.cover_image_b.as_deref()),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct StartExperimentRequest {
+    /// Variant A defaults to the campaign's current title and cover.
+    title_a: Option<String>,
+    cover_image_a: Option<String>,
+    title_b: Option<String>,
+    cover_image_b: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct StopExperimentQuery {
+    /// Make this variant's title and cover the campaign's own.
+    apply: Option<Variant>,
+}
+
+/// Running experiments of the given campaigns.
+pub(crate) async fn running_experiments(
+    db: &Database,
+    campaign_ids: &[Uuid],
+) -> Result<HashMap<Uuid, CampaignExperiment>, StatusCode> {
+    let experiments = sqlx::query_as::<_, CampaignExperiment>(
+        r#"
+        SELECT id, campaign_id, title_a, cover_image_a, title_b, cover_image_b
+        FROM campaign_experiments
+        WHERE campaign_id = ANY($1) AND status = 'RUNNING'
+        "#,
+    )
+    .bind(campaign_ids)
+    .fetch_all(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to load campaign experiments: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    Ok(experiments
+        .into_iter()
+        .map(|experiment| (experiment.campaign_id, experiment))
+        .collect())
+}
+
+/// Record that a visitor saw a variant; only the first exposure counts. Failures are logged
+/// rather than failing the page.
+pub(crate) async fn record_exposure(db: &Database, experiment: &CampaignExperiment, viewer_key: &str) {
+    let variant = experiment.variant_for(viewer_key);
+    let recorded = sqlx::query(
+        r#"
+        INSERT INTO campaign_experiment_exposures (experiment_id, viewer_key, variant)
+        VALUES ($1, $2, $3)
+        ON CONFLICT (experiment_id, viewer_key) DO NOTHING
+        "#,
+    )
+    .bind(experiment.id)
+    .bind(viewer_key)
+    .bind(variant.as_str())
+    .execute(&db.pool)
+    .await;
+    if let Err(e) = recorded {
+        tracing::warn!("Failed to record exposure to experiment {}: {}", experiment.id, e);
+    }
+}
+
+/// Put the variant each visitor is assigned into campaign listings, by campaign id.
+pub(crate) async fn apply_variants(
+    db: &Database,
+    viewer_key: &str,
+    campaigns: &mut [serde_json::Value],
+) -> Result<(), StatusCode> {
+    let ids: Vec<Uuid> = campaigns
+        .iter()
+        .filter_map(|campaign| campaign.get("id")?.as_str()?.parse().ok())
+        .collect();
+    if ids.is_empty() {
+        return Ok(());
+    }
+    let experiments = running_experiments(db, &ids).await?;
+    for campaign in campaigns.iter_mut() {
+        let Some(experiment) = campaign
+            .get("id")
+            .and_then(|id| id.as_str())
+            .and_then(|id| id.parse::<Uuid>().ok())
+            .and_then(|id| experiments.get(&id))
+        else {
+            continue;
+        };
+        let variant = experiment.variant_for(viewer_key);
+        let (title, cover_image) = experiment.content(variant);
+        campaign["title"] = json!(title);
+        if let Some(cover_image) = cover_image {
+            campaign["imageUrl"] = json!(cover_image);
+        }
+        campaign["variant"] = json!(variant.as_str());
+    }
+    Ok(())
+}
+
+async fn owned_campaign(db: &Database, slug: &str, user_id: &str) -> Result<(Uuid, String, Option<String>), StatusCode> {
+    let (campaign_id, title, cover_image, creator_id) =
+        sqlx::query_as::<_, (Uuid, String, Option<String>, String)>(
+            "SELECT id, title, cover_image, creator_id FROM campaigns WHERE slug = $1",
+        )
+        .bind(slug)
+        .fetch_optional(&db.pool)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+    if creator_id != user_id {
+        return Err(StatusCode::FORBIDDEN);
+    }
+    Ok((campaign_id, title, cover_image))
+}
+
+fn clean(value: Option<String>) -> Option<String> {
+    value
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty())
+}
+
+/// Start testing two titles and covers of a campaign. A campaign runs one experiment at a time.
+pub(crate) async fn start_experiment(
+    State(db): State<Database>,
+    claims: Claims,
+    Path(slug): Path<String>,
+    Json(payload): Json<StartExperimentRequest>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let (campaign_id, title, cover_image) = owned_campaign(&db, &slug, &claims.sub).await?;
+    let title_a = clean(payload.title_a).unwrap_or(title);
+    let cover_image_a = clean(payload.cover_image_a).or(cover_image);
+    let title_b = clean(payload.title_b).unwrap_or_else(|| title_a.clone());
+    let cover_image_b = clean(payload.cover_image_b).or_else(|| cover_image_a.clone());
+    if title_a.chars().count() > 255 || title_b.chars().count() > 255 {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    if title_a == title_b && cover_image_a == cover_image_b {
+        return Err(StatusCode::UNPROCESSABLE_ENTITY);
+    }
+
+    let experiment_id = sqlx::query_scalar::<_, Uuid>(
+        r#"
+        INSERT INTO campaign_experiments (campaign_id, title_a, cover_image_a, title_b, cover_image_b)
+        VALUES ($1, $2, $3, $4, $5)
+        RETURNING id
+        "#,
+    )
+    .bind(campaign_id)
+    .bind(&title_a)
+    .bind(&cover_image_a)
+    .bind(&title_b)
+    .bind(&cover_image_b)
+    .fetch_one(&db.pool)
+    .await
+    .map_err(|e| match e {
+        sqlx::Error::Database(ref db_error) if db_error.code().as_deref() == Some("23505") => {
+            StatusCode::CONFLICT
+        }
+        _ => {
+            tracing::error!("Failed to start experiment on campaign {}: {}", campaign_id, e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        }
+    })?;
+
+    Ok(Json(json!({
+        "success": true,
+        "data": {
+            "id": experiment_id,
+            "campaignId": campaign_id,
+            "status": "RUNNING",
+            "variants": {
+                "a": { "title": title_a, "coverImage": cover_image_a },
+                "b": { "title": title_b, "coverImage": cover_image_b },
+            }
+        }
+    })))
+}
+
+/// Stop the running experiment, optionally keeping the winning variant as the campaign's own.
+pub(crate) async fn stop_experiment(
+    State(db): State<Database>,
+    claims: Claims,
+    Path(slug): Path<String>,
+    Query(query): Query<StopExperimentQuery>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let (campaign_id, _, _) = owned_campaign(&db, &slug, &claims.sub).await?;
+    let experiment = sqlx::query_as::<_, CampaignExperiment>(
+        r#"
+        UPDATE campaign_experiments
+        SET status = 'STOPPED', ended_at = NOW(), applied_variant = $2
+        WHERE campaign_id = $1 AND status = 'RUNNING'
+        RETURNING id, campaign_id, title_a, cover_image_a, title_b, cover_image_b
+        "#,
+    )
+    .bind(campaign_id)
+    .bind(query.apply.map(Variant::as_str))
+    .fetch_optional(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to stop experiment on campaign {}: {}", campaign_id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?
+    .ok_or(StatusCode::NOT_FOUND)?;
+
+    if let Some(variant) = query.apply {
+        let (title, cover_image) = experiment.content(variant);
+        sqlx::query(
+            r#"
+            UPDATE campaigns
+            SET title = $2, cover_image = COALESCE($3, cover_image), updated_at = NOW()
+            WHERE id = $1
+            "#,
+        )
+        .bind(campaign_id)
+        .bind(title)
+        .bind(cover_image)
+        .execute(&db.pool)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to apply variant to campaign {}: {}", campaign_id, e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+    }
+
+    Ok(Json(json!({
+        "success": true,
+        "data": {
+            "id": experiment.id,
+            "status": "STOPPED",
+            "appliedVariant": query.apply.map(Variant::as_str),
+        }
+    })))
+}
+
+/// Two-sided p-value of a standard normal z score, using the Abramowitz and Stegun
+/// approximation of the error function.
+fn two_sided_p_value(z: f64) -> f64 {
+    let x = z.abs() / std::f64::consts::SQRT_2;
+    let t = 1.0 / (1.0 + 0.3275911 * x);
+    let poly = t * (0.254829592 + t * (-0.284496736 + t * (1.421413741 + t * (-1.453152027 + t * 1.061405429))));
+    (poly * (-x * x).exp()).clamp(0.0, 1.0)
+}
+
+// Visitors and conversions per variant of the campaign's latest experiment, with a two-proportion
+// z-test of the conversion rates. A visitor converts with a completed donation made after first
+// seeing the campaign; each visitor counts once however often they donate.
+pub(crate) async fn get_experiment_results(
+    State(db): State<Database>,
+    claims: Claims,
+    Path(slug): Path<String>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let (campaign_id, _, _) = owned_campaign(&db, &slug, &claims.sub).await?;
+    let (experiment_id, status, created_at, ended_at, applied_variant) =
+        sqlx::query_as::<_, (Uuid, String, DateTime<Utc>, Option<DateTime<Utc>>, Option<String>)>(
+            r#"
+            SELECT id, status, created_at, ended_at, applied_variant
+            FROM campaign_experiments
+            WHERE campaign_id = $1
+            ORDER BY created_at DESC
+            LIMIT 1
+            "#,
+        )
+        .bind(campaign_id)
+        .fetch_optional(&db.pool)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    let counts = sqlx::query_as::<_, (String, i64, i64, f64)>(
+        r#"
+        SELECT e.variant,
+               COUNT(*),
+               COUNT(*) FILTER (WHERE d.donated),
+               COALESCE(SUM(d.amount), 0)::FLOAT8
+        FROM campaign_experiment_exposures e
+        LEFT JOIN LATERAL (
+            SELECT TRUE AS donated, SUM(dn.amount) AS amount
+            FROM donations dn
+            WHERE dn.campaign_id = $2
+              AND 'user:' || dn.donor_id = e.viewer_key
+              AND dn.status = 'COMPLETED'
+              AND dn.created_at >= e.first_seen_at
+              AND ($3::TIMESTAMPTZ IS NULL OR dn.created_at < $3)
+            HAVING COUNT(*) > 0
+        ) d ON TRUE
+        WHERE e.experiment_id = $1
+        GROUP BY e.variant
+        "#,
+    )
+    .bind(experiment_id)
+    .bind(campaign_id)
+    .bind(ended_at)
+    .fetch_all(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to load results of experiment {}: {}", experiment_id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    let totals = |variant: Variant| {
+        counts
+            .iter()
+            .find(|(name, _, _, _)| name == variant.as_str())
+            .map(|(_, visitors, conversions, amount)| (*visitors, *conversions, *amount))
+            .unwrap_or((0, 0, 0.0))
+    };
+    let (visitors_a, conversions_a, amount_a) = totals(Variant::A);
+    let (visitors_b, conversions_b, amount_b) = totals(Variant::B);
+    let rate = |conversions: i64, visitors: i64| {
+        if visitors > 0 {
+            conversions as f64 / visitors as f64
+        } else {
+            0.0
+        }
+    };
+    let (rate_a, rate_b) = (rate(conversions_a, visitors_a), rate(conversions_b, visitors_b));
+
+    let pooled = rate(conversions_a + conversions_b, visitors_a + visitors_b);
+    let standard_error = if visitors_a > 0 && visitors_b > 0 {
+        (pooled * (1.0 - pooled) * (1.0 / visitors_a as f64 + 1.0 / visitors_b as f64)).sqrt()
+    } else {
+        0.0
+    };
+    let (z_score, p_value) = if standard_error > 0.0 {
+        let z = (rate_b - rate_a) / standard_error;
+        (Some(z), Some(two_sided_p_value(z)))
+    } else {
+        (None, None)
+    };
+    let significant = p_value.is_some_and(|p| p < SIGNIFICANCE_LEVEL);
+    let leader = if rate_b > rate_a {
+        Some(Variant::B)
+    } else if rate_a > rate_b {
+        Some(Variant::A)
+    } else {
+        None
+    };
+
+    Ok(Json(json!({
+        "success": true,
+        "data": {
+            "id": experiment_id,
+            "status": status,
+            "startedAt": created_at,
+            "endedAt": ended_at,
+            "appliedVariant": applied_variant,
+            "variants": {
+                "a": { "visitors": visitors_a, "conversions": conversions_a, "conversionRate": rate_a, "amount": amount_a },
+                "b": { "visitors": visitors_b, "conversions": conversions_b, "conversionRate": rate_b, "amount": amount_b },
+            },
+            "lift": if rate_a > 0.0 { Some((rate_b - rate_a) / rate_a) } else { None },
+            "zScore": z_score,
+            "pValue": p_value,
+            "significant": significant,
+            "winner": if significant { leader.map(Variant::as_str) } else { None },
+        }
+    })))
+}
@@ -0,0 +1,231 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::Json,
+};
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use serde_json::json;
+use uuid::Uuid;
+
+use crate::{auth::Claims, database::Database, email_service::app_link};
+
+const DEFAULT_LEADERBOARD_SIZE: i64 = 10;
+const MAX_LEADERBOARD_SIZE: i64 = 50;
+const SHARE_TOKEN_LEN: usize = 10;
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct LeaderboardQuery {
+    pub limit: Option<i64>,
+}
+
+/// A fan's share link for one campaign, with how it has done so far.
+#[derive(Debug, sqlx::FromRow)]
+struct ShareLink {
+    token: String,
+    created_at: DateTime<Utc>,
+    visits: i64,
+    donations: i64,
+    raised: f64,
+}
+
+/// The share link a token stands for, if it is for this campaign and not the visitor's own.
+pub(crate) async fn share_link_id(
+    db: &Database,
+    campaign_id: Uuid,
+    token: &str,
+    visitor_id: Option<&str>,
+) -> Result<Option<Uuid>, StatusCode> {
+    sqlx::query_scalar::<_, Uuid>(
+        r#"
+        SELECT id FROM campaign_share_links
+        WHERE campaign_id = $1 AND token = $2 AND ($3::TEXT IS NULL OR user_id <> $3)
+        "#,
+    )
+    .bind(campaign_id)
+    .bind(token.trim())
+    .bind(visitor_id)
+    .fetch_optional(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to look up share link of campaign {}: {}", campaign_id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })
+}
+
+/// Count a visitor arriving through a share link, once per link.
+pub(crate) async fn record_share_visit(db: &Database, link_id: Uuid, viewer_key: &str) {
+    let recorded = sqlx::query(
+        r#"
+        INSERT INTO campaign_share_visits (link_id, viewer_key)
+        VALUES ($1, $2)
+        ON CONFLICT (link_id, viewer_key) DO NOTHING
+        "#,
+    )
+    .bind(link_id)
+    .bind(viewer_key)
+    .execute(&db.pool)
+    .await;
+    if let Err(e) = recorded {
+        tracing::warn!("Failed to record visit through share link {}: {}", link_id, e);
+    }
+}
+
+async fn campaign_id(db: &Database, slug: &str) -> Result<Uuid, StatusCode> {
+    sqlx::query_scalar::<_, Uuid>("SELECT id FROM campaigns WHERE slug = $1")
+        .bind(slug)
+        .fetch_optional(&db.pool)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to load campaign {}: {}", slug, e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?
+        .ok_or(StatusCode::NOT_FOUND)
+}
+
+async fn load_share_link(db: &Database, campaign_id: Uuid, user_id: &str) -> Result<Option<ShareLink>, StatusCode> {
+    sqlx::query_as::<_, ShareLink>(
+        r#"
+        SELECT l.token, l.created_at,
+               (SELECT COUNT(*) FROM campaign_share_visits v WHERE v.link_id = l.id) AS visits,
+               COUNT(d.id) AS donations,
+               COALESCE(SUM(d.amount), 0)::FLOAT8 AS raised
+        FROM campaign_share_links l
+        LEFT JOIN donations d ON d.share_link_id = l.id AND d.status = 'COMPLETED'
+        WHERE l.campaign_id = $1 AND l.user_id = $2
+        GROUP BY l.id
+        "#,
+    )
+    .bind(campaign_id)
+    .bind(user_id)
+    .fetch_optional(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to load share link of {} for campaign {}: {}", user_id, campaign_id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })
+}
+
+fn share_link_json(slug: &str, link: &ShareLink) -> serde_json::Value {
+    json!({
+        "token": link.token,
+        "url": app_link(&format!("/campaigns/{}?ref={}", slug, link.token)),
+        "visits": link.visits,
+        "donations": link.donations,
+        "raised": link.raised,
+        "createdAt": link.created_at,
+    })
+}
+
+/// The signed-in user's share link for a campaign, made on first request.
+pub(crate) async fn create_share_link(
+    State(db): State<Database>,
+    claims: Claims,
+    Path(slug): Path<String>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let campaign_id = campaign_id(&db, &slug).await?;
+    let token = Uuid::new_v4().simple().to_string()[..SHARE_TOKEN_LEN].to_string();
+    sqlx::query(
+        r#"
+        INSERT INTO campaign_share_links (campaign_id, user_id, token)
+        VALUES ($1, $2, $3)
+        ON CONFLICT (campaign_id, user_id) DO NOTHING
+        "#,
+    )
+    .bind(campaign_id)
+    .bind(&claims.sub)
+    .bind(&token)
+    .execute(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to create share link of {} for campaign {}: {}", claims.sub, campaign_id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    let link = load_share_link(&db, campaign_id, &claims.sub)
+        .await?
+        .ok_or(StatusCode::INTERNAL_SERVER_ERROR)?;
+    Ok(Json(json!({
+        "success": true,
+        "data": share_link_json(&slug, &link)
+    })))
+}
+
+pub(crate) async fn get_share_link(
+    State(db): State<Database>,
+    claims: Claims,
+    Path(slug): Path<String>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let campaign_id = campaign_id(&db, &slug).await?;
+    let link = load_share_link(&db, campaign_id, &claims.sub)
+        .await?
+        .ok_or(StatusCode::NOT_FOUND)?;
+    Ok(Json(json!({
+        "success": true,
+        "data": share_link_json(&slug, &link)
+    })))
+}
+
+// Fans ranked by what the donations through their share links raised, then by donations and
+// visits. Only completed donations count, and donors who asked to stay anonymous still count
+// towards the fan who brought them.
+pub(crate) async fn get_leaderboard(
+    State(db): State<Database>,
+    Path(slug): Path<String>,
+    Query(query): Query<LeaderboardQuery>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let campaign_id = campaign_id(&db, &slug).await?;
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_LEADERBOARD_SIZE)
+        .clamp(1, MAX_LEADERBOARD_SIZE);
+
+    let rows = sqlx::query_as::<_, (String, String, Option<String>, Option<String>, i64, i64, f64)>(
+        r#"
+        SELECT u.id, u.username, u.display_name, u.avatar_url,
+               (SELECT COUNT(*) FROM campaign_share_visits v WHERE v.link_id = l.id) AS visits,
+               COUNT(d.id) AS donations,
+               COALESCE(SUM(d.amount), 0)::FLOAT8 AS raised
+        FROM campaign_share_links l
+        JOIN users u ON u.id = l.user_id
+        LEFT JOIN donations d ON d.share_link_id = l.id AND d.status = 'COMPLETED'
+        WHERE l.campaign_id = $1
+        GROUP BY l.id, u.id
+        HAVING COUNT(d.id) > 0 OR EXISTS (SELECT 1 FROM campaign_share_visits v WHERE v.link_id = l.id)
+        ORDER BY raised DESC, donations DESC, visits DESC, l.created_at
+        LIMIT $2
+        "#,
+    )
+    .bind(campaign_id)
+    .bind(limit)
+    .fetch_all(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to load share leaderboard of campaign {}: {}", campaign_id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    let leaderboard = rows
+        .into_iter()
+        .enumerate()
+        .map(|(index, (id, username, display_name, avatar_url, visits, donations, raised))| {
+            json!({
+                "rank": index + 1,
+                "user": {
+                    "id": id,
+                    "username": username,
+                    "displayName": display_name,
+                    "avatarUrl": avatar_url,
+                },
+                "visits": visits,
+                "donations": donations,
+                "raised": raised,
+            })
+        })
+        .collect::<Vec<_>>();
+
+    Ok(Json(json!({
+        "success": true,
+        "data": leaderboard
+    })))
+}
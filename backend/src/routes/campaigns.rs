@@ -1,6 +1,6 @@
 use axum::{
     extract::{Path, Query, State},
-    http::StatusCode,
+    http::{HeaderMap, StatusCode},
     response::Json,
     routing::{get, post},
     Router,
@@ -10,7 +10,28 @@ use serde::{Deserialize, Serialize};
 use sqlx::Row;
 use uuid::Uuid;
 
-use crate::database::Database;
+use crate::{
+    database::Database,
+    middleware::optional_auth::MaybeClaims,
+    notification_service::{notify, notify_comment, CommentActivity, NotificationKind},
+    pagination::{parse_cursor, Cursor},
+    payment_providers::{provider, DonationPayment, ProviderName},
+    post_views::{ip_hash, viewer_key},
+    routes::{
+        campaign_experiments::{
+            apply_variants, get_experiment_results, record_exposure, running_experiments,
+            start_experiment, stop_experiment,
+        },
+        campaign_referrals::{
+            create_share_link, get_leaderboard, get_share_link, record_share_visit, share_link_id,
+        },
+        referrals::attribute_referral,
+    },
+};
+
+/// Donations are taken in whole cents between these amounts, in dollars.
+const MIN_DONATION: f64 = 1.0;
+const MAX_DONATION: f64 = 100_000.0;
 
 const DEFAULT_COVER_IMAGE: &str =
     "https://images.unsplash.com/photo-1488521787991-ed7bbaae773c?w=1200&q=80";
@@ -62,6 +83,9 @@ struct CampaignResponse {
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
     pub creator: Option<CampaignCreator>,
+    /// The experiment variant shown, while the title and cover are being tested.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub variant: Option<&'static str>,
 }
 
 impl CampaignResponse {
@@ -139,6 +163,7 @@ impl CampaignResponse {
             created_at,
             updated_at,
             creator,
+            variant: None,
         }
     }
 }
@@ -150,6 +175,30 @@ pub struct CampaignQuery {
     pub limit: Option<u32>,
 }
 
+/// A completed donation as shown on its campaign; anonymous donors stay unnamed.
+#[derive(Debug, Serialize, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+struct DonationListing {
+    id: Uuid,
+    amount: f64,
+    currency: String,
+    message: Option<String>,
+    is_anonymous: bool,
+    donor_id: Option<String>,
+    donor_name: Option<String>,
+    donor_username: Option<String>,
+    donor_avatar: Option<String>,
+    created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+struct DonationQuery {
+    page: Option<u32>,
+    limit: Option<u32>,
+    /// `nextCursor` of the previous page; takes the place of `page`.
+    cursor: Option<String>,
+}
+
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
 struct CreateCampaignPayload {
@@ -167,17 +216,62 @@ struct CreateCampaignPayload {
     pub end_date: Option<String>,
 }
 
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DonationRequest {
+    amount: f64,
+    message: Option<String>,
+    #[serde(default)]
+    is_anonymous: bool,
+    #[serde(default)]
+    provider: ProviderName,
+    /// Attributes the donor to this code's owner, unless they were referred before.
+    #[serde(rename = "referralCode")]
+    referral_code: Option<String>,
+    /// Token of the fan share link the donor came through, from its `?ref=`.
+    #[serde(rename = "ref")]
+    share_token: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CampaignViewQuery {
+    /// Token of the fan share link the visitor came through.
+    #[serde(rename = "ref")]
+    share_token: Option<String>,
+}
+
 pub fn campaign_routes() -> Router<Database> {
     Router::new()
         .route("/", get(get_campaigns))
         .route("/", post(create_campaign))
         .route("/:slug", get(get_campaign_by_slug))
+        .route("/:slug/updates", get(get_campaign_updates))
+        .route("/:slug/comments", get(get_campaign_comments).post(create_campaign_comment))
+        .route("/:slug/donations", get(get_campaign_donations).post(create_donation))
+        .route("/:slug/share-link", get(get_share_link).post(create_share_link))
+        .route("/:slug/leaderboard", get(get_leaderboard))
+        .route(
+            "/:slug/experiment",
+            get(get_experiment_results).post(start_experiment).delete(stop_experiment),
+        )
 }
 
+// Listings are cached for everyone; experiment variants are put in per visitor afterwards
 async fn get_campaigns(
     State(db): State<Database>,
+    MaybeClaims(claims): MaybeClaims,
+    headers: HeaderMap,
     Query(params): Query<CampaignQuery>,
 ) -> Result<Json<serde_json::Value>, StatusCode> {
+    let mut response = campaign_listing(&db, params).await?;
+    if let Some(campaigns) = response.get_mut("data").and_then(|data| data.as_array_mut()) {
+        let viewer = viewer_key(claims.as_ref().map(|claims| claims.sub.as_str()), &headers);
+        apply_variants(&db, &viewer, campaigns).await?;
+    }
+    Ok(Json(response))
+}
+
+async fn campaign_listing(db: &Database, params: CampaignQuery) -> Result<serde_json::Value, StatusCode> {
     let page = params.page.unwrap_or(1).max(1);
     let limit = params.limit.unwrap_or(12).max(1);
     let offset = (page - 1) * limit;
@@ -189,7 +283,7 @@ async fn get_campaigns(
         if let Ok(Some(cached)) = redis_clone.get(&cache_key).await {
             tracing::debug!("Cache HIT for campaigns list: {}", cache_key);
             if let Ok(cached_value) = serde_json::from_str::<serde_json::Value>(&cached) {
-                return Ok(Json(cached_value));
+                return Ok(cached_value);
             }
         }
         tracing::debug!("Cache MISS for campaigns list: {}", cache_key);
@@ -265,7 +359,7 @@ async fn get_campaigns(
                 }
             }
 
-            Ok(Json(response))
+            Ok(response)
         }
         Err(e) => {
             tracing::error!("Failed to fetch campaigns: {}", e);
@@ -431,7 +525,10 @@ async fn create_campaign(
 
 async fn get_campaign_by_slug(
     State(db): State<Database>,
+    MaybeClaims(claims): MaybeClaims,
+    headers: HeaderMap,
     Path(slug): Path<String>,
+    Query(params): Query<CampaignViewQuery>,
 ) -> Result<Json<serde_json::Value>, StatusCode> {
     let query = r#"
         SELECT
@@ -465,7 +562,24 @@ async fn get_campaign_by_slug(
         .await
     {
         Ok(Some(row)) => {
-            let campaign = CampaignResponse::from_row(&row);
+            let mut campaign = CampaignResponse::from_row(&row);
+            let user_id = claims.as_ref().map(|claims| claims.sub.as_str());
+            let viewer = viewer_key(user_id, &headers);
+            if let Some(token) = &params.share_token {
+                if let Some(link_id) = share_link_id(&db, campaign.id, token, user_id).await? {
+                    record_share_visit(&db, link_id, &viewer).await;
+                }
+            }
+            if let Some(experiment) = running_experiments(&db, &[campaign.id]).await?.remove(&campaign.id) {
+                let variant = experiment.variant_for(&viewer);
+                let (title, cover_image) = experiment.content(variant);
+                campaign.title = title.to_string();
+                if let Some(cover_image) = cover_image {
+                    campaign.image_url = cover_image.to_string();
+                }
+                campaign.variant = Some(variant.as_str());
+                record_exposure(&db, &experiment, &viewer).await;
+            }
             let response = serde_json::json!({
                 "success": true,
                 "data": campaign
@@ -480,3 +594,398 @@ async fn get_campaign_by_slug(
         }
     }
 }
+
+async fn get_campaign_updates(
+    State(db): State<Database>,
+    Path(slug): Path<String>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let campaign_id = sqlx::query_scalar::<_, Uuid>("SELECT id FROM campaigns WHERE slug = $1")
+        .bind(&slug)
+        .fetch_optional(&db.pool)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to fetch campaign by slug: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    let rows = sqlx::query(
+        r#"
+        SELECT
+            cu.id,
+            cu.post_id,
+            cu.title,
+            cu.content,
+            cu.published_at,
+            cu.created_at,
+            cu.updated_at,
+            u.id AS author_id,
+            u.display_name AS author_name,
+            u.username AS author_username,
+            u.avatar_url AS author_avatar
+        FROM campaign_updates cu
+        LEFT JOIN users u ON cu.author_id = u.id
+        WHERE cu.campaign_id = $1 AND cu.published_at <= NOW()
+        ORDER BY cu.published_at DESC
+        "#,
+    )
+    .bind(campaign_id)
+    .fetch_all(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to fetch campaign updates: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    let updates: Vec<serde_json::Value> = rows
+        .iter()
+        .map(|row| {
+            serde_json::json!({
+                "id": row.get::<Uuid, _>("id"),
+                "campaignId": campaign_id,
+                "postId": row.get::<Option<Uuid>, _>("post_id"),
+                "title": row.get::<String, _>("title"),
+                "content": row.get::<String, _>("content"),
+                "publishedAt": row.get::<Option<DateTime<Utc>>, _>("published_at"),
+                "createdAt": row.get::<Option<DateTime<Utc>>, _>("created_at"),
+                "updatedAt": row.get::<Option<DateTime<Utc>>, _>("updated_at"),
+                "author": {
+                    "id": row.get::<Option<String>, _>("author_id"),
+                    "name": row.get::<Option<String>, _>("author_name"),
+                    "username": row.get::<Option<String>, _>("author_username"),
+                    "avatar": row.get::<Option<String>, _>("author_avatar")
+                }
+            })
+        })
+        .collect();
+
+    Ok(Json(serde_json::json!({
+        "success": true,
+        "data": updates
+    })))
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CampaignCommentRequest {
+    pub content: String,
+    pub parent_id: Option<Uuid>,
+}
+
+async fn get_campaign_comments(
+    State(db): State<Database>,
+    Path(slug): Path<String>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let campaign_id = sqlx::query_scalar::<_, Uuid>("SELECT id FROM campaigns WHERE slug = $1")
+        .bind(&slug)
+        .fetch_optional(&db.pool)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    let rows = sqlx::query(
+        r#"
+        SELECT cc.id, cc.user_id, cc.content, cc.parent_id, cc.created_at,
+               u.display_name, u.username, u.avatar_url
+        FROM campaign_comments cc
+        LEFT JOIN users u ON u.id = cc.user_id
+        WHERE cc.campaign_id = $1
+        ORDER BY cc.created_at ASC
+        "#,
+    )
+    .bind(campaign_id)
+    .fetch_all(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to fetch campaign comments: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    let comments: Vec<serde_json::Value> = rows
+        .iter()
+        .map(|row| {
+            serde_json::json!({
+                "id": row.get::<Uuid, _>("id"),
+                "userId": row.get::<String, _>("user_id"),
+                "content": row.get::<String, _>("content"),
+                "parentId": row.get::<Option<Uuid>, _>("parent_id"),
+                "createdAt": row.get::<DateTime<Utc>, _>("created_at"),
+                "user": {
+                    "name": row.get::<Option<String>, _>("display_name"),
+                    "username": row.get::<Option<String>, _>("username"),
+                    "avatar": row.get::<Option<String>, _>("avatar_url")
+                }
+            })
+        })
+        .collect();
+
+    Ok(Json(serde_json::json!({
+        "success": true,
+        "data": comments
+    })))
+}
+
+/// Completed donations to a campaign, newest first.
+async fn get_campaign_donations(
+    State(db): State<Database>,
+    Path(slug): Path<String>,
+    Query(params): Query<DonationQuery>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let cursor = parse_cursor(params.cursor.as_deref())?;
+    let cursor_id = cursor.as_ref().map(Cursor::uuid).transpose()?;
+    let page = params.page.unwrap_or(1).max(1);
+    let limit = params.limit.unwrap_or(20).clamp(1, 100);
+    let offset = if cursor.is_some() { 0 } else { ((page - 1) * limit) as i64 };
+
+    let campaign_id = sqlx::query_scalar::<_, Uuid>("SELECT id FROM campaigns WHERE slug = $1")
+        .bind(&slug)
+        .fetch_optional(&db.pool)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    // One extra row tells whether there is a next page
+    let mut donations = sqlx::query_as::<_, DonationListing>(
+        r#"
+        SELECT d.id, d.amount, d.currency, d.message, d.is_anonymous, d.created_at,
+               CASE WHEN d.is_anonymous THEN NULL ELSE d.donor_id END AS donor_id,
+               CASE WHEN d.is_anonymous THEN NULL ELSE COALESCE(u.display_name, u.username) END AS donor_name,
+               CASE WHEN d.is_anonymous THEN NULL ELSE u.username END AS donor_username,
+               CASE WHEN d.is_anonymous THEN NULL ELSE u.avatar_url END AS donor_avatar
+        FROM donations d
+        LEFT JOIN users u ON u.id = d.donor_id
+        WHERE d.campaign_id = $1 AND d.status = 'COMPLETED'
+          AND ($4::TIMESTAMPTZ IS NULL OR (d.created_at, d.id) < ($4, $5))
+        ORDER BY d.created_at DESC, d.id DESC
+        LIMIT $2 OFFSET $3
+        "#,
+    )
+    .bind(campaign_id)
+    .bind(limit as i64 + 1)
+    .bind(offset)
+    .bind(cursor.as_ref().map(|cursor| cursor.at))
+    .bind(cursor_id)
+    .fetch_all(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to list donations of campaign {}: {}", campaign_id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    let has_more = donations.len() > limit as usize;
+    donations.truncate(limit as usize);
+    let next_cursor = donations
+        .last()
+        .filter(|_| has_more)
+        .map(|donation| Cursor::new(donation.created_at, donation.id).encode());
+    let total = sqlx::query_scalar::<_, i64>(
+        "SELECT COUNT(*) FROM donations WHERE campaign_id = $1 AND status = 'COMPLETED'",
+    )
+    .bind(campaign_id)
+    .fetch_one(&db.pool)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(serde_json::json!({
+        "success": true,
+        "data": donations,
+        "pagination": {
+            "page": page,
+            "limit": limit,
+            "total": total,
+            "pages": ((total as f64) / (limit as f64)).ceil() as u32,
+            "hasMore": has_more,
+            "nextCursor": next_cursor,
+        }
+    })))
+}
+
+async fn create_campaign_comment(
+    State(db): State<Database>,
+    Path(slug): Path<String>,
+    claims: crate::auth::Claims,
+    Json(payload): Json<CampaignCommentRequest>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let content = payload.content.trim();
+    if content.is_empty() {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let (campaign_id, creator_id, title) = sqlx::query_as::<_, (Uuid, String, String)>(
+        "SELECT id, creator_id, title FROM campaigns WHERE slug = $1",
+    )
+    .bind(&slug)
+    .fetch_optional(&db.pool)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    .ok_or(StatusCode::NOT_FOUND)?;
+
+    let parent_author_id = match payload.parent_id {
+        Some(parent_id) => Some(
+            sqlx::query_scalar::<_, String>(
+                "SELECT user_id FROM campaign_comments WHERE id = $1 AND campaign_id = $2",
+            )
+            .bind(parent_id)
+            .bind(campaign_id)
+            .fetch_optional(&db.pool)
+            .await
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+            .ok_or(StatusCode::BAD_REQUEST)?,
+        ),
+        None => None,
+    };
+
+    let (comment_id, created_at, commenter_name) =
+        sqlx::query_as::<_, (Uuid, DateTime<Utc>, Option<String>)>(
+            r#"
+            INSERT INTO campaign_comments (campaign_id, user_id, content, parent_id)
+            VALUES ($1, $2, $3, $4)
+            RETURNING id, created_at,
+                (SELECT COALESCE(display_name, name, username) FROM users WHERE id = $2)
+            "#,
+        )
+        .bind(campaign_id)
+        .bind(&claims.sub)
+        .bind(content)
+        .bind(payload.parent_id)
+        .fetch_one(&db.pool)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to create campaign comment: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    notify_comment(
+        &db,
+        CommentActivity {
+            comment_id,
+            commenter_id: &claims.sub,
+            commenter_name: commenter_name.as_deref().unwrap_or("Someone"),
+            content,
+            owner_id: Some(&creator_id),
+            parent_author_id: parent_author_id.as_deref(),
+            title: &title,
+            link: Some(format!("/campaigns/{}", slug)),
+            ids: serde_json::json!({ "campaignId": campaign_id, "parentId": payload.parent_id }),
+        },
+    )
+    .await;
+
+    Ok(Json(serde_json::json!({
+        "success": true,
+        "data": {
+            "id": comment_id,
+            "userId": claims.sub,
+            "content": content,
+            "parentId": payload.parent_id,
+            "createdAt": created_at
+        }
+    })))
+}
+
+// Starts the donation's payment with the donor's chosen provider; the donation counts toward the
+// campaign once that provider's webhook confirms the payment.
+async fn create_donation(
+    State(db): State<Database>,
+    Path(slug): Path<String>,
+    claims: crate::auth::Claims,
+    headers: HeaderMap,
+    Json(payload): Json<DonationRequest>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    if !(MIN_DONATION..=MAX_DONATION).contains(&payload.amount) {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let amount_cents = (payload.amount * 100.0).round() as i64;
+    let message = payload
+        .message
+        .map(|message| message.trim().chars().take(500).collect::<String>())
+        .filter(|message| !message.is_empty());
+
+    let campaign = sqlx::query(
+        "SELECT id, title, creator_id, end_date FROM campaigns WHERE slug = $1 LIMIT 1",
+    )
+    .bind(&slug)
+    .fetch_optional(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to load campaign {}: {}", slug, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?
+    .ok_or(StatusCode::NOT_FOUND)?;
+    let campaign_id: Uuid = campaign.get("id");
+    let title: String = campaign.get("title");
+    let creator_id: String = campaign.get("creator_id");
+    let end_date: Option<DateTime<Utc>> = campaign.try_get("end_date").unwrap_or(None);
+    if end_date.is_some_and(|end_date| end_date < Utc::now()) {
+        return Err(StatusCode::CONFLICT);
+    }
+    // Donors count as seeing the variant they are assigned, in case they viewed signed out
+    if let Some(experiment) = running_experiments(&db, &[campaign_id]).await?.remove(&campaign_id) {
+        record_exposure(&db, &experiment, &viewer_key(Some(&claims.sub), &HeaderMap::new())).await;
+    }
+    if let Some(code) = &payload.referral_code {
+        attribute_referral(&db, code, &claims.sub, "DONATION", ip_hash(&headers).as_deref()).await;
+    }
+    let share_link_id = match &payload.share_token {
+        Some(token) => share_link_id(&db, campaign_id, token, Some(&claims.sub)).await?,
+        None => None,
+    };
+
+    let donation = provider(payload.provider)
+        .create_donation(
+            &db,
+            DonationPayment {
+                campaign_id,
+                campaign_slug: &slug,
+                title: &title,
+                creator_id: &creator_id,
+                donor_id: &claims.sub,
+                amount_cents,
+                currency: "USD",
+                message,
+                is_anonymous: payload.is_anonymous,
+                share_link_id,
+            },
+        )
+        .await?;
+
+    Ok(Json(serde_json::json!({ "success": true, "data": donation })))
+}
+
+/// Tell the campaign's creator about a completed donation; anonymous donors stay unnamed.
+pub(crate) async fn notify_donation(db: &Database, donation_id: Uuid) {
+    let donation = sqlx::query_as::<_, (String, String, String, f64, String, Option<String>)>(
+        r#"
+        SELECT c.creator_id, c.title, c.slug, d.amount, d.currency,
+               CASE WHEN d.is_anonymous THEN NULL ELSE d.donor_id END
+        FROM donations d
+        JOIN campaigns c ON c.id = d.campaign_id
+        WHERE d.id = $1
+        "#,
+    )
+    .bind(donation_id)
+    .fetch_optional(&db.pool)
+    .await;
+    let (creator_id, title, slug, amount, currency, donor_id) = match donation {
+        Ok(Some(donation)) => donation,
+        Ok(None) => return,
+        Err(e) => {
+            tracing::warn!("Failed to load donation {} to notify about: {}", donation_id, e);
+            return;
+        }
+    };
+
+    notify(
+        db,
+        &creator_id,
+        NotificationKind::Donation,
+        serde_json::json!({
+            "message": format!("New {:.2} {} donation to {}", amount, currency, title),
+            "link": format!("/campaigns/{}", slug),
+            "donationId": donation_id,
+            "donorId": donor_id,
+            "amount": amount,
+            "currency": currency,
+        }),
+    )
+    .await;
+}
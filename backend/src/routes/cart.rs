@@ -0,0 +1,862 @@
+use axum::{
+    extract::{Path, State},
+    http::{HeaderMap, StatusCode},
+    response::Json,
+    routing::{get, post, put},
+    Router,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use uuid::Uuid;
+
+use crate::{
+    auth::Claims,
+    database::Database,
+    models::Product,
+    routes::{
+        discounts::{claim_discount, code_entered, find_discount, release_discount, AppliedDiscount},
+        events::{fulfill_ticket, quote_ticket},
+        fees::{quote_platform_fee, LedgerSource, PlatformFee, ProductType},
+        inventory::{attach_hold, hold_stock, CHECKOUT_SESSION_MINUTES},
+        payments::{refund_ledger_entry, RefundReason},
+        product_variants::{apply_variant, select_variant},
+        stripe::{stripe_json, stripe_secret, stripe_url, track_checkout},
+        tax::{quote_tax, resolve_buyer_location, BuyerLocation, TaxQuote},
+    },
+};
+
+/// Most items one checkout may hold.
+const MAX_CART_ITEMS: i64 = 20;
+
+/// A buyer's basket of digital products and at most one event ticket, paid in one checkout.
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct Cart {
+    pub id: Uuid,
+    pub user_id: String,
+    /// `OPEN`, `CHECKED_OUT` while the buyer pays, then `COMPLETED` or `PARTIALLY_FULFILLED`.
+    pub status: String,
+    pub stripe_checkout_session_id: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct CartItem {
+    pub id: Uuid,
+    pub cart_id: Uuid,
+    /// `PRODUCT` or `EVENT_TICKET`.
+    pub item_type: String,
+    pub product_id: Option<Uuid>,
+    /// The product variant, when the product has variants.
+    pub variant_id: Option<Uuid>,
+    pub event_id: Option<String>,
+    pub ticket_type_id: Option<Uuid>,
+    pub name: String,
+    pub unit_amount_cents: i64,
+    pub tax_cents: i64,
+    pub currency: String,
+    /// `PENDING` until paid, then `FULFILLED`, or `REFUNDED` / `FAILED` when it could not be
+    /// delivered and the refund went through or did not.
+    pub status: String,
+    pub purchase_id: Option<Uuid>,
+    pub ledger_entry_id: Option<Uuid>,
+    pub failure_reason: Option<String>,
+    pub fulfilled_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AddCartItemRequest {
+    product_id: Option<Uuid>,
+    variant_id: Option<Uuid>,
+    event_id: Option<String>,
+    ticket_type_id: Option<Uuid>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct UpdateCartItemRequest {
+    variant_id: Option<Uuid>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CartCheckoutRequest {
+    billing_country: Option<String>,
+    billing_region: Option<String>,
+    /// A discount code, applied to every item of the creator it belongs to.
+    discount_code: Option<String>,
+}
+
+/// A product as it goes in a cart, priced in its chosen variant.
+struct CartProduct {
+    name: String,
+    amount_cents: i64,
+    currency: String,
+    creator_id: String,
+    variant_id: Option<Uuid>,
+    /// Already taken off `amount_cents`.
+    discount: Option<AppliedDiscount>,
+    code_matched: bool,
+}
+
+/// One cart item priced for checkout.
+struct PricedItem {
+    item: CartItem,
+    creator_id: String,
+    tax: TaxQuote,
+    platform_fee: PlatformFee,
+    discount: Option<AppliedDiscount>,
+    /// The unit held for a limited product.
+    hold_id: Option<Uuid>,
+}
+
+pub fn cart_routes() -> Router<Database> {
+    Router::new()
+        .route("/", get(get_cart).delete(clear_cart))
+        .route("/items", post(add_cart_item))
+        .route("/items/:item_id", put(update_cart_item).delete(remove_cart_item))
+        .route("/checkout", post(checkout_cart))
+        .route("/checkout/:session_id", get(get_checkout_result))
+}
+
+async fn get_cart(
+    State(db): State<Database>,
+    claims: Claims,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let cart = open_cart(&db, &claims.sub).await?;
+    // A cart being paid for keeps the items and prices of its checkout
+    let (removed, repriced) = if cart.status == "OPEN" {
+        (prune_unavailable(&db, &cart).await?, refresh_prices(&db, cart.id).await?)
+    } else {
+        (Vec::new(), Vec::new())
+    };
+    let items = load_items(&db, cart.id).await?;
+    let mut data = cart_json(&cart, &items);
+    data["removedItems"] = json!(removed);
+    data["repricedItems"] = json!(repriced);
+    Ok(Json(json!({
+        "success": true,
+        "data": data
+    })))
+}
+
+async fn clear_cart(
+    State(db): State<Database>,
+    claims: Claims,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let cart = open_cart(&db, &claims.sub).await?;
+    sqlx::query("DELETE FROM cart_items WHERE cart_id = $1")
+        .bind(cart.id)
+        .execute(&db.pool)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    reopen(&db, &cart).await?;
+
+    Ok(Json(json!({
+        "success": true,
+        "data": cart_json(&cart, &[])
+    })))
+}
+
+async fn add_cart_item(
+    State(db): State<Database>,
+    claims: Claims,
+    Json(payload): Json<AddCartItemRequest>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let cart = open_cart(&db, &claims.sub).await?;
+    let count = sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM cart_items WHERE cart_id = $1")
+        .bind(cart.id)
+        .fetch_one(&db.pool)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    if count >= MAX_CART_ITEMS {
+        return Err(StatusCode::UNPROCESSABLE_ENTITY);
+    }
+
+    let requested = (&payload.product_id, &payload.event_id);
+    let (item_type, name, amount_cents, currency, variant_id, ticket_type_id) = match requested {
+        (Some(product_id), None) => {
+            let product = load_cart_product(&db, *product_id, payload.variant_id, None).await?;
+            // Owning one license does not stop the buyer from adding another
+            let owned = sqlx::query_scalar::<_, bool>(
+                r#"
+                SELECT EXISTS(
+                    SELECT 1 FROM purchases
+                    WHERE user_id = $1 AND product_id = $2 AND variant_id IS NOT DISTINCT FROM $3
+                      AND status = 'COMPLETED'
+                )
+                "#,
+            )
+            .bind(&claims.sub)
+            .bind(product_id)
+            .bind(product.variant_id)
+            .fetch_one(&db.pool)
+            .await
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+            if owned {
+                return Err(StatusCode::CONFLICT);
+            }
+            ("PRODUCT", product.name, product.amount_cents, product.currency, product.variant_id, None)
+        }
+        (None, Some(event_id)) => {
+            let ticket = quote_ticket(&db, event_id, &claims.sub, payload.ticket_type_id).await?;
+            let currency = "USD".to_string();
+            ("EVENT_TICKET", ticket.title, ticket.amount_cents, currency, None, ticket.ticket_type_id)
+        }
+        _ => return Err(StatusCode::BAD_REQUEST),
+    };
+
+    // A cart holds each product once and a single ticket; the unique indexes say which
+    let item = sqlx::query_as::<_, CartItem>(
+        r#"
+        INSERT INTO cart_items (
+            cart_id, item_type, product_id, variant_id, event_id, ticket_type_id, name, unit_amount_cents, currency
+        )
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+        ON CONFLICT DO NOTHING
+        RETURNING *
+        "#,
+    )
+    .bind(cart.id)
+    .bind(item_type)
+    .bind(payload.product_id)
+    .bind(variant_id)
+    .bind(&payload.event_id)
+    .bind(ticket_type_id)
+    .bind(&name)
+    .bind(amount_cents)
+    .bind(&currency)
+    .fetch_optional(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to add item to cart {}: {}", cart.id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?
+    .ok_or(StatusCode::CONFLICT)?;
+    reopen(&db, &cart).await?;
+
+    Ok(Json(json!({
+        "success": true,
+        "data": item
+    })))
+}
+
+// Switches a product to another of its variants, priced as it is now
+async fn update_cart_item(
+    State(db): State<Database>,
+    Path(item_id): Path<Uuid>,
+    claims: Claims,
+    Json(payload): Json<UpdateCartItemRequest>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let cart = open_cart(&db, &claims.sub).await?;
+    let item = sqlx::query_as::<_, CartItem>("SELECT * FROM cart_items WHERE id = $1 AND cart_id = $2")
+        .bind(item_id)
+        .bind(cart.id)
+        .fetch_optional(&db.pool)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+    let Some(product_id) = item.product_id else {
+        return Err(StatusCode::BAD_REQUEST);
+    };
+
+    let product = load_cart_product(&db, product_id, payload.variant_id, None).await?;
+    let item = sqlx::query_as::<_, CartItem>(
+        r#"
+        UPDATE cart_items SET variant_id = $2, name = $3, unit_amount_cents = $4, currency = $5
+        WHERE id = $1
+        RETURNING *
+        "#,
+    )
+    .bind(item_id)
+    .bind(product.variant_id)
+    .bind(&product.name)
+    .bind(product.amount_cents)
+    .bind(&product.currency)
+    .fetch_one(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to update item {} of cart {}: {}", item_id, cart.id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    reopen(&db, &cart).await?;
+
+    Ok(Json(json!({
+        "success": true,
+        "data": item
+    })))
+}
+
+async fn remove_cart_item(
+    State(db): State<Database>,
+    Path(item_id): Path<Uuid>,
+    claims: Claims,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let cart = open_cart(&db, &claims.sub).await?;
+    let removed = sqlx::query("DELETE FROM cart_items WHERE id = $1 AND cart_id = $2")
+        .bind(item_id)
+        .bind(cart.id)
+        .execute(&db.pool)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .rows_affected();
+    if removed == 0 {
+        return Err(StatusCode::NOT_FOUND);
+    }
+    reopen(&db, &cart).await?;
+
+    let items = load_items(&db, cart.id).await?;
+    Ok(Json(json!({
+        "success": true,
+        "data": cart_json(&cart, &items)
+    })))
+}
+
+// Prices, tax and availability are checked again here: the cart may have sat for days. The charge
+// lands on the platform account because one session can only pay out to a single creator; each
+// creator's share is tracked in the ledger and paid out with their withdrawals.
+async fn checkout_cart(
+    State(db): State<Database>,
+    claims: Claims,
+    headers: HeaderMap,
+    payload: Option<Json<CartCheckoutRequest>>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let Json(payload) = payload.unwrap_or_default();
+    let cart = open_cart(&db, &claims.sub).await?;
+    let removed = prune_unavailable(&db, &cart).await?;
+    let items = load_items(&db, cart.id).await?;
+    let Some(currency) = items.first().map(|item| item.currency.clone()) else {
+        return Err(StatusCode::BAD_REQUEST);
+    };
+    if items.iter().any(|item| item.currency != currency) {
+        return Err(StatusCode::UNPROCESSABLE_ENTITY);
+    }
+
+    let location = resolve_buyer_location(
+        payload.billing_country.as_deref(),
+        payload.billing_region.as_deref(),
+        &headers,
+    );
+    let code = payload.discount_code.as_deref();
+    let mut code_matched = false;
+    let mut priced = Vec::with_capacity(items.len());
+    for mut item in items {
+        let mut discount = None;
+        let (creator_id, product_type, tax) = match (item.item_type.as_str(), item.product_id) {
+            ("PRODUCT", Some(product_id)) => {
+                let product = load_cart_product(&db, product_id, item.variant_id, code).await?;
+                item.name = product.name;
+                item.unit_amount_cents = product.amount_cents;
+                item.variant_id = product.variant_id;
+                code_matched |= product.code_matched;
+                discount = product.discount;
+                let tax = quote_tax(&db, location.clone(), product.amount_cents).await?;
+                (product.creator_id, ProductType::Product, tax)
+            }
+            ("EVENT_TICKET", _) => {
+                let event_id = item.event_id.clone().ok_or(StatusCode::INTERNAL_SERVER_ERROR)?;
+                let ticket = quote_ticket(&db, &event_id, &claims.sub, item.ticket_type_id).await?;
+                item.name = ticket.title;
+                item.unit_amount_cents = ticket.amount_cents;
+                let tax = TaxQuote::none(BuyerLocation::default(), ticket.amount_cents);
+                (ticket.host_id, ProductType::Ticket, tax)
+            }
+            _ => return Err(StatusCode::INTERNAL_SERVER_ERROR),
+        };
+        item.tax_cents = tax.tax_cents;
+        let platform_fee = quote_platform_fee(&db, product_type, &creator_id, item.unit_amount_cents)
+            .await?
+            .with_tax(tax.tax_cents)
+            .held_by_platform();
+        priced.push(PricedItem {
+            item,
+            creator_id,
+            tax,
+            platform_fee,
+            discount,
+            hold_id: None,
+        });
+    }
+    if code_entered(code) && !code_matched {
+        return Err(StatusCode::UNPROCESSABLE_ENTITY);
+    }
+
+    if let Some(previous_session_id) = &cart.stripe_checkout_session_id {
+        discard_previous_checkout(&db, previous_session_id).await;
+    }
+    for priced in &mut priced {
+        if let Some(product_id) = priced.item.product_id {
+            priced.hold_id = hold_stock(&db, product_id).await?;
+        }
+    }
+
+    let frontend_url =
+        std::env::var("FRONTEND_URL").unwrap_or_else(|_| "http://localhost:3000".to_string());
+    let mut form_data = vec![
+        ("mode".to_string(), "payment".to_string()),
+        (
+            "success_url".to_string(),
+            format!("{}/cart?session_id={{CHECKOUT_SESSION_ID}}", frontend_url),
+        ),
+        ("cancel_url".to_string(), format!("{}/cart?cancelled=true", frontend_url)),
+        ("client_reference_id".to_string(), cart.id.to_string()),
+        ("metadata[cart_id]".to_string(), cart.id.to_string()),
+        ("metadata[user_id]".to_string(), claims.sub.clone()),
+    ];
+    if priced.iter().any(|priced| priced.hold_id.is_some()) {
+        let expires_at = Utc::now() + chrono::Duration::minutes(CHECKOUT_SESSION_MINUTES);
+        form_data.push(("expires_at".to_string(), expires_at.timestamp().to_string()));
+    }
+    for (index, priced) in priced.iter().enumerate() {
+        form_data.extend(line_item(index, &priced.item.name, priced.item.unit_amount_cents, &currency));
+    }
+    // Digital items are all taxed where the buyer is, so one line carries the tax of the cart
+    let tax_cents: i64 = priced.iter().map(|priced| priced.tax.tax_cents).sum();
+    if let Some(taxed) = priced.iter().find(|priced| priced.tax.tax_cents > 0) {
+        form_data.extend(line_item(priced.len(), &taxed.tax.label(), tax_cents, &currency));
+    }
+
+    let session = stripe_json(
+        reqwest::Client::new()
+            .post(stripe_url("/v1/checkout/sessions"))
+            .bearer_auth(stripe_secret()?)
+            .form(&form_data)
+            .send()
+            .await,
+        "create cart checkout session",
+    )
+    .await?;
+    let session_id = session
+        .get("id")
+        .and_then(|id| id.as_str())
+        .ok_or(StatusCode::BAD_GATEWAY)?
+        .to_string();
+    let checkout_url = session.get("url").and_then(|url| url.as_str()).map(str::to_string);
+
+    // Uses are counted once the session exists; if one ran out meanwhile, the others are given back
+    let mut claimed = Vec::new();
+    for discount_id in priced.iter().filter_map(|priced| priced.discount.as_ref().map(|discount| discount.id)) {
+        if let Err(status) = claim_discount(&db, discount_id).await {
+            for discount_id in claimed {
+                release_discount(&db, discount_id).await;
+            }
+            return Err(status);
+        }
+        claimed.push(discount_id);
+    }
+
+    for priced in &mut priced {
+        let (source_type, source_id) = match priced.item.product_id {
+            Some(product_id) => {
+                let purchase_id = sqlx::query_scalar::<_, Uuid>(
+                    r#"
+                    INSERT INTO purchases (
+                        user_id, product_id, variant_id, stripe_checkout_session_id, amount, currency, status,
+                        discount_code_id, discount_amount
+                    )
+                    VALUES ($1, $2, $3, $4, $5, $6, 'PENDING', $7, $8)
+                    RETURNING id
+                    "#,
+                )
+                .bind(&claims.sub)
+                .bind(product_id)
+                .bind(priced.item.variant_id)
+                .bind(&session_id)
+                .bind(priced.item.unit_amount_cents as f64 / 100.0)
+                .bind(&currency)
+                .bind(priced.discount.as_ref().map(|discount| discount.id))
+                .bind(priced.discount.as_ref().map(|discount| discount.discount_cents as f64 / 100.0))
+                .fetch_one(&db.pool)
+                .await
+                .map_err(|e| {
+                    tracing::error!("Failed to create purchase for cart {}: {}", cart.id, e);
+                    StatusCode::INTERNAL_SERVER_ERROR
+                })?;
+                priced.item.purchase_id = Some(purchase_id);
+                attach_hold(&db, priced.hold_id, purchase_id).await?;
+                ("PURCHASE", purchase_id.to_string())
+            }
+            None => ("EVENT_TICKET", priced.item.event_id.clone().unwrap_or_default()),
+        };
+
+        let source = LedgerSource {
+            source_type,
+            source_id,
+            payer_id: &claims.sub,
+            currency: &currency,
+            stripe_checkout_session_id: Some(&session_id),
+            stripe_payment_intent_id: None,
+        };
+        priced.tax.record(&db, &priced.creator_id, &source).await?;
+        priced.item.ledger_entry_id = Some(priced.platform_fee.record(&db, source).await?);
+
+        sqlx::query(
+            r#"
+            UPDATE cart_items
+            SET name = $2, unit_amount_cents = $3, tax_cents = $4, purchase_id = $5,
+                ledger_entry_id = $6, variant_id = $7, status = 'PENDING', failure_reason = NULL
+            WHERE id = $1
+            "#,
+        )
+        .bind(priced.item.id)
+        .bind(&priced.item.name)
+        .bind(priced.item.unit_amount_cents)
+        .bind(priced.item.tax_cents)
+        .bind(priced.item.purchase_id)
+        .bind(priced.item.ledger_entry_id)
+        .bind(priced.item.variant_id)
+        .execute(&db.pool)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    }
+
+    sqlx::query(
+        "UPDATE carts SET status = 'CHECKED_OUT', stripe_checkout_session_id = $2, updated_at = NOW() WHERE id = $1",
+    )
+    .bind(cart.id)
+    .bind(&session_id)
+    .execute(&db.pool)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    track_checkout(&db, &session).await;
+
+    let subtotal_cents: i64 = priced.iter().map(|priced| priced.item.unit_amount_cents).sum();
+    let discount_cents: i64 = priced
+        .iter()
+        .filter_map(|priced| priced.discount.as_ref())
+        .map(|discount| discount.discount_cents)
+        .sum();
+    Ok(Json(json!({
+        "success": true,
+        "data": {
+            "cartId": cart.id,
+            "sessionId": session_id,
+            "checkoutUrl": checkout_url,
+            "currency": currency,
+            "subtotalCents": subtotal_cents,
+            "discountCents": discount_cents,
+            "taxCents": tax_cents,
+            "totalCents": subtotal_cents + tax_cents,
+            "removedItems": removed,
+        }
+    })))
+}
+
+// What the success page shows once the webhook has fulfilled the cart
+async fn get_checkout_result(
+    State(db): State<Database>,
+    Path(session_id): Path<String>,
+    claims: Claims,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let cart = sqlx::query_as::<_, Cart>(
+        "SELECT * FROM carts WHERE stripe_checkout_session_id = $1 AND user_id = $2",
+    )
+    .bind(&session_id)
+    .bind(&claims.sub)
+    .fetch_optional(&db.pool)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    .ok_or(StatusCode::NOT_FOUND)?;
+    let items = load_items(&db, cart.id).await?;
+
+    Ok(Json(json!({
+        "success": true,
+        "data": cart_json(&cart, &items)
+    })))
+}
+
+/// Deliver each item of a paid cart. Products were completed with the session's purchases; tickets
+/// are seated here, and a ticket that can no longer be seated is refunded on its own so the rest
+/// of the order stands.
+pub(crate) async fn fulfill_cart(
+    db: &Database,
+    session_id: &str,
+    payment_intent_id: Option<&str>,
+) -> Result<(), StatusCode> {
+    // Claiming the cart first keeps a repeated completion event from seating or refunding twice
+    let Some(cart) = sqlx::query_as::<_, Cart>(
+        r#"
+        UPDATE carts SET status = 'FULFILLING', updated_at = NOW()
+        WHERE stripe_checkout_session_id = $1 AND status = 'CHECKED_OUT'
+        RETURNING *
+        "#,
+    )
+    .bind(session_id)
+    .fetch_optional(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to claim cart of session {}: {}", session_id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?
+    else {
+        return Ok(());
+    };
+
+    let mut partial = false;
+    for item in load_items(db, cart.id).await? {
+        if item.status != "PENDING" {
+            continue;
+        }
+        let seated = match (item.item_type.as_str(), &item.event_id) {
+            ("EVENT_TICKET", Some(event_id)) => fulfill_ticket(
+                db,
+                event_id,
+                &cart.user_id,
+                item.ticket_type_id,
+                payment_intent_id,
+                item.unit_amount_cents,
+            )
+            .await
+            .map_err(|status| match status {
+                StatusCode::CONFLICT => "The event sold out before payment completed",
+                _ => "The ticket could not be issued",
+            }),
+            _ => Ok(()),
+        };
+
+        let (status, failure_reason) = match seated {
+            Ok(()) => ("FULFILLED", None),
+            Err(reason) => {
+                partial = true;
+                let refunded = match item.ledger_entry_id {
+                    Some(ledger_entry_id) => refund_ledger_entry(
+                        db,
+                        ledger_entry_id,
+                        None,
+                        RefundReason::FulfillmentFailed,
+                        Some(reason.to_string()),
+                        "SYSTEM",
+                    )
+                    .await
+                    .map_err(|status| {
+                        tracing::error!("Failed to refund cart item {}: {}", item.id, status);
+                    })
+                    .is_ok(),
+                    None => false,
+                };
+                (if refunded { "REFUNDED" } else { "FAILED" }, Some(reason))
+            }
+        };
+        sqlx::query(
+            r#"
+            UPDATE cart_items
+            SET status = $2, failure_reason = $3,
+                fulfilled_at = CASE WHEN $2 = 'FULFILLED' THEN NOW() ELSE NULL END
+            WHERE id = $1
+            "#,
+        )
+        .bind(item.id)
+        .bind(status)
+        .bind(failure_reason)
+        .execute(&db.pool)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    }
+
+    sqlx::query("UPDATE carts SET status = $2, updated_at = NOW() WHERE id = $1")
+        .bind(cart.id)
+        .bind(if partial { "PARTIALLY_FULFILLED" } else { "COMPLETED" })
+        .execute(&db.pool)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    Ok(())
+}
+
+/// The buyer's cart that has not been paid yet, created on first use.
+async fn open_cart(db: &Database, user_id: &str) -> Result<Cart, StatusCode> {
+    sqlx::query(
+        r#"
+        INSERT INTO carts (user_id) VALUES ($1)
+        ON CONFLICT (user_id) WHERE status IN ('OPEN', 'CHECKED_OUT') DO NOTHING
+        "#,
+    )
+    .bind(user_id)
+    .execute(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to create cart for {}: {}", user_id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    sqlx::query_as::<_, Cart>(
+        "SELECT * FROM carts WHERE user_id = $1 AND status IN ('OPEN', 'CHECKED_OUT')",
+    )
+    .bind(user_id)
+    .fetch_one(&db.pool)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
+}
+
+// A cart edited after checkout started needs a new checkout session
+async fn reopen(db: &Database, cart: &Cart) -> Result<(), StatusCode> {
+    sqlx::query("UPDATE carts SET status = 'OPEN', updated_at = NOW() WHERE id = $1")
+        .bind(cart.id)
+        .execute(&db.pool)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    Ok(())
+}
+
+/// Take deactivated products and retired variants out of a cart, returning their names so the
+/// buyer can be told what went.
+async fn prune_unavailable(db: &Database, cart: &Cart) -> Result<Vec<String>, StatusCode> {
+    let removed = sqlx::query_scalar::<_, String>(
+        r#"
+        DELETE FROM cart_items ci
+        USING products p
+        WHERE ci.cart_id = $1
+          AND ci.item_type = 'PRODUCT'
+          AND ci.status = 'PENDING'
+          AND p.id = ci.product_id
+          AND (
+              NOT p.is_active
+              OR EXISTS (
+                  SELECT 1 FROM product_variants v
+                  WHERE v.id = ci.variant_id AND NOT v.is_active
+              )
+          )
+        RETURNING ci.name
+        "#,
+    )
+    .bind(cart.id)
+    .fetch_all(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to prune cart {}: {}", cart.id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    if !removed.is_empty() {
+        reopen(db, cart).await?;
+    }
+    Ok(removed)
+}
+
+/// Bring the prices of a cart's products up to date, returning the items whose price changed.
+/// Products that can no longer be bought keep their last price; checkout rejects them.
+async fn refresh_prices(db: &Database, cart_id: Uuid) -> Result<Vec<Uuid>, StatusCode> {
+    let mut repriced = Vec::new();
+    for item in load_items(db, cart_id).await? {
+        let Some(product_id) = item.product_id else {
+            continue;
+        };
+        let Ok(product) = load_cart_product(db, product_id, item.variant_id, None).await else {
+            continue;
+        };
+        if product.amount_cents == item.unit_amount_cents {
+            continue;
+        }
+        sqlx::query("UPDATE cart_items SET unit_amount_cents = $2, name = $3 WHERE id = $1")
+            .bind(item.id)
+            .bind(product.amount_cents)
+            .bind(&product.name)
+            .execute(&db.pool)
+            .await
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        repriced.push(item.id);
+    }
+    Ok(repriced)
+}
+
+async fn load_items(db: &Database, cart_id: Uuid) -> Result<Vec<CartItem>, StatusCode> {
+    sqlx::query_as::<_, CartItem>("SELECT * FROM cart_items WHERE cart_id = $1 ORDER BY created_at")
+        .bind(cart_id)
+        .fetch_all(&db.pool)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to load items of cart {}: {}", cart_id, e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })
+}
+
+/// A product that can go in a cart, paid and digital, priced in the chosen variant or the
+/// product's first when it has variants and none was chosen, less any running sale or the
+/// buyer's code.
+async fn load_cart_product(
+    db: &Database,
+    product_id: Uuid,
+    variant_id: Option<Uuid>,
+    code: Option<&str>,
+) -> Result<CartProduct, StatusCode> {
+    let mut product = sqlx::query_as::<_, Product>("SELECT * FROM products WHERE id = $1 AND is_active")
+        .bind(product_id)
+        .fetch_optional(&db.pool)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+    let variant = select_variant(db, product_id, variant_id).await?;
+    if let Some(variant) = &variant {
+        apply_variant(&mut product, variant);
+    }
+
+    let amount_cents = (product.price * 100.0).round() as i64;
+    if !product.is_digital || amount_cents <= 0 {
+        return Err(StatusCode::UNPROCESSABLE_ENTITY);
+    }
+    if product.stock == Some(0) {
+        return Err(StatusCode::CONFLICT);
+    }
+    let lookup = find_discount(db, &product.user_id, product_id, amount_cents, code).await?;
+    let discount_cents = lookup.best.as_ref().map(|discount| discount.discount_cents).unwrap_or(0);
+    Ok(CartProduct {
+        name: product.name,
+        amount_cents: amount_cents - discount_cents,
+        currency: product.currency.to_ascii_uppercase(),
+        creator_id: product.user_id,
+        variant_id: variant.map(|variant| variant.id),
+        discount: lookup.best,
+        code_matched: lookup.code_matched,
+    })
+}
+
+// The abandoned session is expired before its pending records go, so it can no longer be paid
+async fn discard_previous_checkout(db: &Database, session_id: &str) {
+    let Ok(secret) = stripe_secret() else {
+        return;
+    };
+    let expired = stripe_json(
+        reqwest::Client::new()
+            .post(stripe_url(&format!("/v1/checkout/sessions/{}/expire", session_id)))
+            .bearer_auth(secret)
+            .send()
+            .await,
+        "expire cart checkout session",
+    )
+    .await;
+    if expired.is_err() {
+        return;
+    }
+
+    for statement in [
+        "DELETE FROM ledger_entries WHERE stripe_checkout_session_id = $1 AND status = 'PENDING'",
+        "DELETE FROM tax_lines WHERE stripe_checkout_session_id = $1 AND status = 'PENDING'",
+        "DELETE FROM purchases WHERE stripe_checkout_session_id = $1 AND status = 'PENDING'",
+    ] {
+        if let Err(e) = sqlx::query(statement).bind(session_id).execute(&db.pool).await {
+            tracing::warn!("Failed to discard checkout session {}: {}", session_id, e);
+        }
+    }
+}
+
+fn line_item(index: usize, name: &str, amount_cents: i64, currency: &str) -> Vec<(String, String)> {
+    vec![
+        (format!("line_items[{}][price_data][currency]", index), currency.to_lowercase()),
+        (format!("line_items[{}][price_data][product_data][name]", index), name.to_string()),
+        (format!("line_items[{}][price_data][unit_amount]", index), amount_cents.to_string()),
+        (format!("line_items[{}][quantity]", index), "1".to_string()),
+    ]
+}
+
+fn cart_json(cart: &Cart, items: &[CartItem]) -> serde_json::Value {
+    let subtotal_cents: i64 = items.iter().map(|item| item.unit_amount_cents).sum();
+    json!({
+        "cart": cart,
+        "items": items,
+        "subtotalCents": subtotal_cents,
+        "currency": items.first().map(|item| item.currency.clone()),
+    })
+}
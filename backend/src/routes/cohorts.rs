@@ -0,0 +1,155 @@
+use std::collections::BTreeMap;
+
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::Json,
+};
+use chrono::NaiveDate;
+use serde::Deserialize;
+use serde_json::json;
+use tracing::info;
+
+use crate::{auth::Claims, database::Database, routes::fees::ensure_admin};
+
+const DEFAULT_COHORT_MONTHS: i32 = 12;
+const MAX_COHORT_MONTHS: i32 = 36;
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CohortQuery {
+    /// Join months to show, counting back from this one.
+    pub months: Option<i32>,
+    /// Admins only: another creator's cohorts.
+    pub creator_id: Option<String>,
+    /// Admins only: every creator's subscribers together.
+    pub platform: Option<bool>,
+}
+
+/// Recompute the cohort cells that can still change: those of the current month, and on the
+/// first run after a month ends those of the month just ended. Earlier cells are final, since
+/// a subscription that ends later does not change whether it was active back then. Cells never
+/// computed before, e.g. on the first run, are filled in as well.
+pub async fn refresh_cohorts(db: &Database) -> anyhow::Result<()> {
+    let refreshed = sqlx::query(
+        r#"
+        WITH cohorts AS (
+            SELECT creator_id, date_trunc('month', created_at AT TIME ZONE 'UTC')::DATE AS cohort_month,
+                   COUNT(*) AS subscribers
+            FROM subscriptions
+            WHERE created_at IS NOT NULL
+            GROUP BY 1, 2
+        ),
+        cells AS (
+            SELECT c.creator_id, c.cohort_month, c.subscribers, m.months_since,
+                   (c.cohort_month + make_interval(months => m.months_since))::DATE AS month_start,
+                   (c.cohort_month + make_interval(months => m.months_since + 1))::TIMESTAMP AT TIME ZONE 'UTC' AS month_end
+            FROM cohorts c
+            CROSS JOIN LATERAL generate_series(
+                0,
+                ((EXTRACT(YEAR FROM AGE(date_trunc('month', NOW() AT TIME ZONE 'UTC'), c.cohort_month)) * 12)
+                 + EXTRACT(MONTH FROM AGE(date_trunc('month', NOW() AT TIME ZONE 'UTC'), c.cohort_month)))::INT
+            ) AS m(months_since)
+        )
+        INSERT INTO subscriber_cohorts (creator_id, cohort_month, months_since, subscribers, retained, computed_at)
+        SELECT cell.creator_id, cell.cohort_month, cell.months_since, cell.subscribers,
+            (SELECT COUNT(*) FROM subscriptions s
+             WHERE s.creator_id = cell.creator_id
+               AND date_trunc('month', s.created_at AT TIME ZONE 'UTC')::DATE = cell.cohort_month
+               AND (UPPER(s.status) = 'ACTIVE' OR s.updated_at >= cell.month_end)),
+            NOW()
+        FROM cells cell
+        WHERE cell.month_start >= date_trunc('month', (NOW() - INTERVAL '1 day') AT TIME ZONE 'UTC')::DATE
+           OR NOT EXISTS (
+               SELECT 1 FROM subscriber_cohorts sc
+               WHERE sc.creator_id = cell.creator_id
+                 AND sc.cohort_month = cell.cohort_month
+                 AND sc.months_since = cell.months_since
+           )
+        ON CONFLICT (creator_id, cohort_month, months_since) DO UPDATE
+        SET subscribers = EXCLUDED.subscribers,
+            retained = EXCLUDED.retained,
+            computed_at = EXCLUDED.computed_at
+        "#,
+    )
+    .execute(&db.pool)
+    .await?
+    .rows_affected();
+
+    if refreshed > 0 {
+        info!("Subscriber cohorts: {} cells refreshed", refreshed);
+    }
+    Ok(())
+}
+
+// Subscribers grouped by the month they joined, with the share still subscribed at the end of
+// each month since. The current month counts who is subscribed now. Figures are as of the last
+// nightly refresh.
+pub(crate) async fn get_cohorts(
+    State(db): State<Database>,
+    claims: Claims,
+    Query(query): Query<CohortQuery>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let months = query.months.unwrap_or(DEFAULT_COHORT_MONTHS).clamp(1, MAX_COHORT_MONTHS);
+    let platform = query.platform.unwrap_or(false);
+    let creator_id = match &query.creator_id {
+        Some(creator_id) if *creator_id != claims.sub => Some(creator_id.clone()),
+        _ => None,
+    };
+    if platform || creator_id.is_some() {
+        ensure_admin(&db, &claims.sub).await?;
+    }
+    let creator_id = creator_id.unwrap_or_else(|| claims.sub.clone());
+
+    let cells = sqlx::query_as::<_, (NaiveDate, i32, i64, i64, Option<chrono::DateTime<chrono::Utc>>)>(
+        r#"
+        SELECT cohort_month, months_since, SUM(subscribers)::BIGINT, SUM(retained)::BIGINT, MAX(computed_at)
+        FROM subscriber_cohorts
+        WHERE ($1 OR creator_id = $2)
+          AND cohort_month >= (date_trunc('month', NOW() AT TIME ZONE 'UTC') - make_interval(months => $3 - 1))::DATE
+        GROUP BY cohort_month, months_since
+        ORDER BY cohort_month, months_since
+        "#,
+    )
+    .bind(platform)
+    .bind(&creator_id)
+    .bind(months)
+    .fetch_all(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to load subscriber cohorts of {}: {}", creator_id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    let computed_at = cells.iter().filter_map(|(_, _, _, _, computed_at)| *computed_at).max();
+    let mut cohorts = BTreeMap::<NaiveDate, (i64, Vec<serde_json::Value>)>::new();
+    for (cohort_month, months_since, subscribers, retained, _) in cells {
+        let cohort = cohorts.entry(cohort_month).or_insert((subscribers, Vec::new()));
+        let rate = if subscribers > 0 {
+            retained as f64 / subscribers as f64
+        } else {
+            0.0
+        };
+        cohort.1.push(json!({
+            "month": months_since,
+            "retained": retained,
+            "rate": rate,
+        }));
+    }
+
+    Ok(Json(json!({
+        "success": true,
+        "data": {
+            "creatorId": if platform { None } else { Some(creator_id) },
+            "computedAt": computed_at,
+            "cohorts": cohorts
+                .into_iter()
+                .map(|(cohort_month, (subscribers, retention))| json!({
+                    "cohort": cohort_month,
+                    "subscribers": subscribers,
+                    "retention": retention,
+                }))
+                .collect::<Vec<_>>(),
+        }
+    })))
+}
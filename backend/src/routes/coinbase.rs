@@ -0,0 +1,452 @@
+use axum::{
+    body::Bytes,
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    response::Json,
+    routing::post,
+    Router,
+};
+use hmac::{Hmac, Mac};
+use serde::Deserialize;
+use serde_json::json;
+use sha2::Sha256;
+use uuid::Uuid;
+
+use crate::{
+    config::Config,
+    database::Database,
+    payment_providers::{DonationPayment, PaymentProvider},
+    routes::{
+        campaigns::notify_donation,
+        fees::{quote_platform_fee, LedgerSource, ProductType},
+        ledger::{post_journal, JournalKind},
+        referrals::record_donation,
+    },
+};
+
+type HmacSha256 = Hmac<Sha256>;
+
+const COINBASE_COMMERCE_API: &str = "https://api.commerce.coinbase.com";
+/// API version the charge and webhook payloads below are shaped after.
+const COINBASE_COMMERCE_VERSION: &str = "2018-03-22";
+
+/// Coinbase Commerce: payments in crypto, priced and credited in the campaign's currency.
+pub(crate) struct Coinbase;
+
+#[derive(Debug, Deserialize)]
+struct WebhookPayload {
+    event: CommerceEvent,
+}
+
+#[derive(Debug, Deserialize)]
+struct CommerceEvent {
+    id: String,
+    #[serde(rename = "type")]
+    event_type: String,
+    data: Charge,
+}
+
+#[derive(Debug, Deserialize)]
+struct Charge {
+    id: String,
+    code: Option<String>,
+    pricing: Option<ChargePricing>,
+    #[serde(default)]
+    payments: Vec<ChargePayment>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChargePricing {
+    local: Money,
+}
+
+/// One on-chain payment towards a charge.
+#[derive(Debug, Deserialize)]
+struct ChargePayment {
+    network: String,
+    transaction_id: String,
+    /// `PENDING` until the network confirms it, then `CONFIRMED`.
+    status: String,
+    value: PaymentValue,
+}
+
+#[derive(Debug, Deserialize)]
+struct PaymentValue {
+    /// What the payment was worth in the charge's currency when it was made.
+    local: Money,
+    crypto: Money,
+}
+
+#[derive(Debug, Deserialize)]
+struct Money {
+    /// Decimal string, e.g. `"25.00"` or `"0.00041000"`.
+    amount: String,
+    currency: String,
+}
+
+pub fn coinbase_routes() -> Router<Database> {
+    Router::new().route("/webhook", post(coinbase_webhook))
+}
+
+#[axum::async_trait]
+impl PaymentProvider for Coinbase {
+    /// Create a Coinbase Commerce charge for the donation and store the donation as PENDING; it
+    /// counts toward the campaign once the charge is confirmed on-chain.
+    async fn create_donation(
+        &self,
+        db: &Database,
+        donation: DonationPayment<'_>,
+    ) -> Result<serde_json::Value, StatusCode> {
+        create_charge_donation(db, donation).await
+    }
+}
+
+async fn create_charge_donation(
+    db: &Database,
+    donation: DonationPayment<'_>,
+) -> Result<serde_json::Value, StatusCode> {
+    let config = Config::from_env().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    if config.coinbase_commerce_api_key.trim().is_empty() {
+        return Err(StatusCode::SERVICE_UNAVAILABLE);
+    }
+    let donation_id = Uuid::new_v4();
+    let campaign_url = format!(
+        "{}/campaigns/{}",
+        config.frontend_url.trim_end_matches('/'),
+        donation.campaign_slug
+    );
+
+    let response = reqwest::Client::new()
+        .post(format!("{}/charges", COINBASE_COMMERCE_API))
+        .header("X-CC-Api-Key", &config.coinbase_commerce_api_key)
+        .header("X-CC-Version", COINBASE_COMMERCE_VERSION)
+        .json(&json!({
+            "name": donation.title.chars().take(100).collect::<String>(),
+            "description": "Campaign donation",
+            "pricing_type": "fixed_price",
+            "local_price": {
+                "amount": format!("{:.2}", donation.amount_cents as f64 / 100.0),
+                "currency": donation.currency,
+            },
+            "metadata": {
+                "donation_id": donation_id,
+                "campaign_id": donation.campaign_id,
+                "user_id": donation.donor_id,
+            },
+            "redirect_url": format!("{}?donation={}", campaign_url, donation_id),
+            "cancel_url": campaign_url,
+        }))
+        .send()
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to create Coinbase Commerce charge: {}", e);
+            StatusCode::BAD_GATEWAY
+        })?;
+    if !response.status().is_success() {
+        let body = response.text().await.unwrap_or_default();
+        tracing::error!("Coinbase Commerce returned error for create charge: {}", body);
+        return Err(StatusCode::BAD_GATEWAY);
+    }
+    let charge: serde_json::Value = response.json().await.map_err(|e| {
+        tracing::error!("Failed to parse Coinbase Commerce charge: {}", e);
+        StatusCode::BAD_GATEWAY
+    })?;
+    let charge = charge.get("data").cloned().unwrap_or_default();
+    let charge_id = charge
+        .get("id")
+        .and_then(|id| id.as_str())
+        .ok_or(StatusCode::BAD_GATEWAY)?;
+    let provider_metadata = json!({
+        "chargeCode": charge.get("code"),
+        "hostedUrl": charge.get("hosted_url"),
+        "expiresAt": charge.get("expires_at"),
+        "chargeStatus": "NEW",
+    });
+
+    sqlx::query(
+        r#"
+        INSERT INTO donations (
+            id, campaign_id, donor_id, amount, currency, message, is_anonymous,
+            provider, provider_charge_id, provider_metadata, share_link_id
+        )
+        VALUES ($1, $2, $3, $4, $5, $6, $7, 'COINBASE', $8, $9, $10)
+        "#,
+    )
+    .bind(donation_id)
+    .bind(donation.campaign_id)
+    .bind(donation.donor_id)
+    .bind(donation.amount_cents as f64 / 100.0)
+    .bind(donation.currency)
+    .bind(&donation.message)
+    .bind(donation.is_anonymous)
+    .bind(charge_id)
+    .bind(&provider_metadata)
+    .bind(donation.share_link_id)
+    .execute(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!(
+            "Failed to store crypto donation to campaign {}: {}",
+            donation.campaign_id,
+            e
+        );
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    Ok(json!({
+        "donationId": donation_id,
+        "campaignId": donation.campaign_id,
+        "amount": donation.amount_cents as f64 / 100.0,
+        "currency": donation.currency,
+        "status": "PENDING",
+        "provider": "COINBASE",
+        "hostedUrl": provider_metadata["hostedUrl"],
+        "chargeCode": provider_metadata["chargeCode"],
+        "expiresAt": provider_metadata["expiresAt"],
+    }))
+}
+
+/// Coinbase Commerce webhook. Confirmation is idempotent on the donation's status, so retried
+/// deliveries are harmless.
+async fn coinbase_webhook(
+    State(db): State<Database>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let secret = Config::from_env()
+        .map(|config| config.coinbase_commerce_webhook_secret)
+        .unwrap_or_default();
+    if secret.trim().is_empty() {
+        tracing::error!("COINBASE_COMMERCE_WEBHOOK_SECRET is not configured");
+        return Err(StatusCode::SERVICE_UNAVAILABLE);
+    }
+
+    let signature = headers
+        .get("x-cc-webhook-signature")
+        .and_then(|value| value.to_str().ok())
+        .ok_or(StatusCode::UNAUTHORIZED)?;
+    if !verify_signature(&secret, signature, &body) {
+        return Err(StatusCode::UNAUTHORIZED);
+    }
+
+    let WebhookPayload { event } = serde_json::from_slice(&body).map_err(|e| {
+        tracing::error!("Malformed Coinbase Commerce event: {}", e);
+        StatusCode::BAD_REQUEST
+    })?;
+    let handled = match event.event_type.as_str() {
+        "charge:confirmed" | "charge:resolved" => confirm_crypto_donation(&db, &event.data).await?,
+        "charge:pending" => record_charge_status(&db, &event.data, "PENDING").await?,
+        // Paid after the charge expired; Coinbase confirms or fails it later
+        "charge:delayed" => record_charge_status(&db, &event.data, "DELAYED").await?,
+        // Expired unpaid or underpaid; an admin can still resolve it on Coinbase's side
+        "charge:failed" => record_charge_status(&db, &event.data, "FAILED").await?,
+        _ => false,
+    };
+
+    Ok(Json(json!({
+        "success": true,
+        "data": { "eventId": event.id, "handled": handled }
+    })))
+}
+
+/// `X-CC-Webhook-Signature` is the hex HMAC-SHA256 of the raw body under the shared secret.
+fn verify_signature(secret: &str, signature: &str, body: &[u8]) -> bool {
+    let Ok(signature) = hex::decode(signature.trim()) else {
+        return false;
+    };
+    let Ok(mut mac) = HmacSha256::new_from_slice(secret.as_bytes()) else {
+        return false;
+    };
+    mac.update(body);
+    mac.verify_slice(&signature).is_ok()
+}
+
+async fn record_charge_status(
+    db: &Database,
+    charge: &Charge,
+    status: &str,
+) -> Result<bool, StatusCode> {
+    let updated = sqlx::query(
+        r#"
+        UPDATE donations
+        SET provider_metadata = provider_metadata || $2,
+            status = CASE WHEN $3 = 'FAILED' THEN 'FAILED' ELSE status END,
+            updated_at = NOW()
+        WHERE provider = 'COINBASE' AND provider_charge_id = $1
+          AND status IN ('PENDING', 'FAILED')
+        "#,
+    )
+    .bind(&charge.id)
+    .bind(json!({ "chargeStatus": status, "payments": payment_details(charge) }))
+    .bind(status)
+    .execute(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to update crypto donation for charge {}: {}", charge.id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?
+    .rows_affected();
+    Ok(updated > 0)
+}
+
+// The donor may have sent more or less than asked, and the coin's price moves while the payment
+// confirms, so the campaign is credited with what the confirmed payments were worth in its
+// currency rather than with the amount the charge was created for.
+async fn confirm_crypto_donation(db: &Database, charge: &Charge) -> Result<bool, StatusCode> {
+    let donation = sqlx::query_as::<_, (Uuid, Uuid, Option<String>, String, String)>(
+        r#"
+        SELECT d.id, d.campaign_id, d.donor_id, d.currency, c.creator_id
+        FROM donations d
+        JOIN campaigns c ON c.id = d.campaign_id
+        WHERE d.provider = 'COINBASE' AND d.provider_charge_id = $1
+        "#,
+    )
+    .bind(&charge.id)
+    .fetch_optional(&db.pool)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let Some((donation_id, campaign_id, donor_id, currency, creator_id)) = donation else {
+        tracing::warn!("Coinbase Commerce charge {} has no donation", charge.id);
+        return Ok(false);
+    };
+
+    let received_cents = match received_cents(charge, &currency) {
+        Some(cents) if cents > 0 => cents,
+        _ => {
+            tracing::error!("Confirmed charge {} carries no payment in {}", charge.id, currency);
+            return Ok(false);
+        }
+    };
+    // The coins land on the platform's Coinbase account, so the creator's share is owed to them
+    // through the ledger and paid out with their withdrawals.
+    let platform_fee = quote_platform_fee(db, ProductType::Donation, &creator_id, received_cents)
+        .await?
+        .held_by_platform();
+
+    // Completing the donation, crediting the campaign and booking the ledger entry commit
+    // together, so a failed confirmation is retried in full rather than leaving the creator
+    // unpaid.
+    let db_error = |e: sqlx::Error| {
+        tracing::error!("Failed to complete crypto donation {}: {}", donation_id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    };
+    let mut tx = db.pool.begin().await.map_err(db_error)?;
+    let completed = sqlx::query(
+        r#"
+        UPDATE donations
+        SET status = 'COMPLETED', amount = $2, provider_metadata = provider_metadata || $3,
+            updated_at = NOW()
+        WHERE id = $1 AND status <> 'COMPLETED'
+        "#,
+    )
+    .bind(donation_id)
+    .bind(received_cents as f64 / 100.0)
+    .bind(json!({
+        "chargeStatus": "CONFIRMED",
+        "chargeCode": charge.code,
+        "payments": payment_details(charge),
+    }))
+    .execute(&mut tx)
+    .await
+    .map_err(db_error)?
+    .rows_affected();
+    if completed == 0 {
+        return Ok(true);
+    }
+    sqlx::query(
+        r#"
+        UPDATE campaigns
+        SET current_amount = COALESCE(current_amount, 0) + $2, updated_at = NOW()
+        WHERE id = $1
+        "#,
+    )
+    .bind(campaign_id)
+    .bind(received_cents as f64 / 100.0)
+    .execute(&mut tx)
+    .await
+    .map_err(db_error)?;
+
+    let ledger_entry_id = platform_fee
+        .record_on(
+            &mut tx,
+            LedgerSource {
+                source_type: "DONATION",
+                source_id: donation_id.to_string(),
+                payer_id: donor_id.as_deref().unwrap_or_default(),
+                currency: &currency,
+                stripe_checkout_session_id: None,
+                stripe_payment_intent_id: None,
+            },
+        )
+        .await?;
+    sqlx::query(
+        r#"
+        UPDATE ledger_entries
+        SET status = 'SETTLED', payment_provider = 'COINBASE', settled_at = NOW()
+        WHERE id = $1 AND status = 'PENDING'
+        "#,
+    )
+    .bind(ledger_entry_id)
+    .execute(&mut tx)
+    .await
+    .map_err(db_error)?;
+    post_journal(&mut tx, &[ledger_entry_id], JournalKind::Settlement)
+        .await
+        .map_err(db_error)?;
+    tx.commit().await.map_err(db_error)?;
+
+    notify_donation(db, donation_id).await;
+    record_donation(db, donation_id).await;
+    Ok(true)
+}
+
+/// Confirmed payments in `currency`, in cents; a charge resolved without payments by Coinbase
+/// support counts at its price.
+fn received_cents(charge: &Charge, currency: &str) -> Option<i64> {
+    let to_cents = |money: &Money| {
+        money
+            .currency
+            .eq_ignore_ascii_case(currency)
+            .then(|| money.amount.parse::<f64>().ok())
+            .flatten()
+            .map(|amount| (amount * 100.0).round() as i64)
+    };
+    let confirmed: Vec<&ChargePayment> = charge
+        .payments
+        .iter()
+        .filter(|payment| payment.status.eq_ignore_ascii_case("CONFIRMED"))
+        .collect();
+    if confirmed.is_empty() {
+        return charge.pricing.as_ref().and_then(|pricing| to_cents(&pricing.local));
+    }
+    confirmed
+        .iter()
+        .map(|payment| to_cents(&payment.value.local))
+        .sum()
+}
+
+/// What was paid on-chain and at which rate it converted into the charge's currency.
+fn payment_details(charge: &Charge) -> serde_json::Value {
+    charge
+        .payments
+        .iter()
+        .map(|payment| {
+            let local = payment.value.local.amount.parse::<f64>().ok();
+            let crypto = payment.value.crypto.amount.parse::<f64>().ok();
+            let exchange_rate = local
+                .zip(crypto)
+                .filter(|(_, crypto)| *crypto > 0.0)
+                .map(|(local, crypto)| local / crypto);
+            json!({
+                "network": payment.network,
+                "transactionId": payment.transaction_id,
+                "status": payment.status,
+                "cryptoAmount": payment.value.crypto.amount,
+                "cryptoCurrency": payment.value.crypto.currency,
+                "localAmount": payment.value.local.amount,
+                "localCurrency": payment.value.local.currency,
+                "exchangeRate": exchange_rate,
+            })
+        })
+        .collect()
+}
@@ -8,7 +8,13 @@ use axum::{
 use serde::Deserialize;
 use serde_json::json;
 
-use crate::{database::Database, middleware::optional_auth::MaybeClaims, models::User};
+use crate::{
+    database::Database, middleware::optional_auth::MaybeClaims, models::User,
+    routes::{
+        announcements::announcement_routes, event_feedback::host_rating_summary,
+        following::mark_seen, storefront::get_storefront,
+    },
+};
 
 #[derive(Debug, Deserialize)]
 pub struct CreatorQuery {
@@ -20,6 +26,8 @@ pub fn creator_routes() -> Router<Database> {
     Router::new()
         .route("/", get(get_creators))
         .route("/:username", get(get_creator_by_username))
+        .route("/:username/storefront", get(get_storefront))
+        .nest("/me/announcements", announcement_routes())
 }
 
 async fn get_creators(
@@ -116,7 +124,9 @@ async fn get_creator_by_username(
                 StatusCode::INTERNAL_SERVER_ERROR
             })?;
 
-    let is_following = if let Some(claims) = maybe_claims {
+    let event_rating = host_rating_summary(&db, &creator.id).await?;
+
+    let is_following = if let Some(claims) = &maybe_claims {
         sqlx::query_scalar::<_, bool>(
             "SELECT EXISTS(SELECT 1 FROM follows WHERE follower_id = $1 AND following_id = $2)",
         )
@@ -129,6 +139,13 @@ async fn get_creator_by_username(
         false
     };
 
+    // Visiting a followed creator clears their new-content badge
+    if let Some(claims) = maybe_claims.filter(|_| is_following) {
+        if let Err(e) = mark_seen(&db, &claims.sub, &creator.id).await {
+            tracing::warn!("Failed to mark creator {} seen: {}", creator.id, e);
+        }
+    }
+
     Ok(Json(json!({
         "id": creator.id,
         "email": creator.email,
@@ -141,6 +158,7 @@ async fn get_creator_by_username(
         "updatedAt": creator.updated_at,
         "followerCount": follower_count,
         "followingCount": following_count,
+        "eventRating": event_rating,
         "isFollowing": is_following
     })))
 }
@@ -0,0 +1,470 @@
+use std::collections::HashMap;
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::Json,
+    routing::{get, post, put},
+    Router,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use uuid::Uuid;
+
+use crate::{
+    auth::Claims,
+    database::Database,
+    models::Product,
+    routes::product_variants::{apply_variant, select_variant},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum DiscountType {
+    /// `amount` is a percentage off.
+    Percent,
+    /// `amount` is taken off the price, in the product's currency.
+    Fixed,
+}
+
+impl DiscountType {
+    fn as_str(self) -> &'static str {
+        match self {
+            DiscountType::Percent => "PERCENT",
+            DiscountType::Fixed => "FIXED",
+        }
+    }
+
+    fn parse(value: &str) -> Option<Self> {
+        match value {
+            "PERCENT" => Some(DiscountType::Percent),
+            "FIXED" => Some(DiscountType::Fixed),
+            _ => None,
+        }
+    }
+}
+
+/// A creator's discount. Without a code it is a sale, applied to everyone while it runs.
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct DiscountCode {
+    pub id: Uuid,
+    pub creator_id: String,
+    pub code: Option<String>,
+    pub discount_type: String,
+    pub amount: f64,
+    /// The one product it applies to; every product of the creator when empty.
+    pub product_id: Option<Uuid>,
+    pub starts_at: Option<DateTime<Utc>>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub max_uses: Option<i32>,
+    pub uses: i32,
+    pub is_active: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl DiscountCode {
+    /// Cents taken off a price, never more than the price itself.
+    fn discount_cents(&self, amount_cents: i64) -> i64 {
+        let off = match DiscountType::parse(&self.discount_type) {
+            Some(DiscountType::Percent) => (amount_cents as f64 * self.amount / 100.0).round() as i64,
+            Some(DiscountType::Fixed) => (self.amount * 100.0).round() as i64,
+            None => 0,
+        };
+        off.clamp(0, amount_cents)
+    }
+}
+
+/// A discount as applied to one purchase.
+#[derive(Debug, Clone)]
+pub(crate) struct AppliedDiscount {
+    pub id: Uuid,
+    /// None for a sale.
+    pub code: Option<String>,
+    pub discount_cents: i64,
+}
+
+/// The discounts found for one purchase.
+#[derive(Debug, Default)]
+pub(crate) struct DiscountLookup {
+    pub best: Option<AppliedDiscount>,
+    /// Whether the buyer's code is running and covers the product, even if a sale beat it.
+    pub code_matched: bool,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DiscountRequest {
+    /// Leave empty for a sale.
+    code: Option<String>,
+    discount_type: DiscountType,
+    amount: f64,
+    product_id: Option<Uuid>,
+    starts_at: Option<DateTime<Utc>>,
+    expires_at: Option<DateTime<Utc>>,
+    max_uses: Option<i32>,
+    is_active: Option<bool>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PreviewDiscountRequest {
+    code: Option<String>,
+    product_id: Uuid,
+    variant_id: Option<Uuid>,
+}
+
+pub fn discount_routes() -> Router<Database> {
+    Router::new()
+        .route("/", get(list_discounts).post(create_discount))
+        .route("/preview", post(preview_discount))
+        .route("/:id", put(update_discount).delete(deactivate_discount))
+}
+
+const RUNNING: &str = r#"
+    is_active
+    AND (starts_at IS NULL OR starts_at <= NOW())
+    AND (expires_at IS NULL OR expires_at > NOW())
+    AND (max_uses IS NULL OR uses < max_uses)
+"#;
+
+/// The best discount on one product priced at `amount_cents`: its running sales, and the code
+/// the buyer entered if it is one of the creator's and covers the product. Discounts do not
+/// stack. A code that does not apply is ignored here; callers decide whether that is an error.
+pub(crate) async fn find_discount(
+    db: &Database,
+    creator_id: &str,
+    product_id: Uuid,
+    amount_cents: i64,
+    code: Option<&str>,
+) -> Result<DiscountLookup, StatusCode> {
+    let code = code.map(str::trim).filter(|code| !code.is_empty());
+    let candidates = sqlx::query_as::<_, DiscountCode>(&format!(
+        r#"
+        SELECT * FROM discount_codes
+        WHERE creator_id = $1
+          AND (product_id IS NULL OR product_id = $2)
+          AND (code IS NULL OR UPPER(code) = UPPER($3))
+          AND {}
+        "#,
+        RUNNING
+    ))
+    .bind(creator_id)
+    .bind(product_id)
+    .bind(code)
+    .fetch_all(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to load discounts of {}: {}", creator_id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    let code_matched = candidates.iter().any(|discount| discount.code.is_some());
+    let best = candidates
+        .into_iter()
+        .map(|discount| AppliedDiscount {
+            id: discount.id,
+            discount_cents: discount.discount_cents(amount_cents),
+            code: discount.code,
+        })
+        .filter(|discount| discount.discount_cents > 0)
+        .max_by_key(|discount| discount.discount_cents);
+    Ok(DiscountLookup { best, code_matched })
+}
+
+/// Whether the buyer entered a code at all.
+pub(crate) fn code_entered(code: Option<&str>) -> bool {
+    code.is_some_and(|code| !code.trim().is_empty())
+}
+
+/// Take the best discount off a product's price before checkout. A code that does not apply
+/// to the product is rejected rather than silently charging full price.
+pub(crate) async fn discount_product(
+    db: &Database,
+    product: &mut Product,
+    code: Option<&str>,
+) -> Result<Option<AppliedDiscount>, StatusCode> {
+    let amount_cents = (product.price * 100.0).round() as i64;
+    let lookup = find_discount(db, &product.user_id, product.id, amount_cents, code).await?;
+    if code_entered(code) && !lookup.code_matched {
+        return Err(StatusCode::UNPROCESSABLE_ENTITY);
+    }
+    if let Some(discount) = &lookup.best {
+        product.price = (amount_cents - discount.discount_cents) as f64 / 100.0;
+    }
+    Ok(lookup.best)
+}
+
+/// Count a use of a discount, failing when its last use was taken in the meantime.
+pub(crate) async fn claim_discount(db: &Database, discount_id: Uuid) -> Result<(), StatusCode> {
+    let claimed = sqlx::query(
+        r#"
+        UPDATE discount_codes SET uses = uses + 1
+        WHERE id = $1 AND (max_uses IS NULL OR uses < max_uses)
+        "#,
+    )
+    .bind(discount_id)
+    .execute(&db.pool)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    .rows_affected();
+    if claimed == 0 {
+        return Err(StatusCode::CONFLICT);
+    }
+    Ok(())
+}
+
+/// Give back a use claimed for a checkout that could not be created.
+pub(crate) async fn release_discount(db: &Database, discount_id: Uuid) {
+    if let Err(e) = sqlx::query("UPDATE discount_codes SET uses = GREATEST(uses - 1, 0) WHERE id = $1")
+        .bind(discount_id)
+        .execute(&db.pool)
+        .await
+    {
+        tracing::warn!("Failed to release discount {}: {}", discount_id, e);
+    }
+}
+
+/// Fill in `sale_price` for products on sale, for display in listings. Codes are left out:
+/// they only apply once the buyer enters one.
+pub(crate) async fn apply_sale_prices(db: &Database, products: &mut [Product]) -> Result<(), StatusCode> {
+    let creator_ids: Vec<String> = products.iter().map(|product| product.user_id.clone()).collect();
+    if creator_ids.is_empty() {
+        return Ok(());
+    }
+    let sales = sqlx::query_as::<_, DiscountCode>(&format!(
+        "SELECT * FROM discount_codes WHERE creator_id = ANY($1) AND code IS NULL AND {}",
+        RUNNING
+    ))
+    .bind(&creator_ids)
+    .fetch_all(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to load running sales: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    if sales.is_empty() {
+        return Ok(());
+    }
+
+    let mut by_creator: HashMap<&str, Vec<&DiscountCode>> = HashMap::new();
+    for sale in &sales {
+        by_creator.entry(sale.creator_id.as_str()).or_default().push(sale);
+    }
+    for product in products.iter_mut() {
+        let amount_cents = (product.price * 100.0).round() as i64;
+        let best = by_creator
+            .get(product.user_id.as_str())
+            .into_iter()
+            .flatten()
+            .filter(|sale| sale.product_id.is_none_or(|id| id == product.id))
+            .map(|sale| (sale.discount_cents(amount_cents), sale.expires_at))
+            .filter(|(off, _)| *off > 0)
+            .max_by_key(|(off, _)| *off);
+        if let Some((off, ends_at)) = best {
+            product.sale_price = Some((amount_cents - off) as f64 / 100.0);
+            product.sale_ends_at = ends_at;
+        }
+    }
+    Ok(())
+}
+
+fn validate(payload: &DiscountRequest) -> Result<Option<String>, StatusCode> {
+    let valid_amount = payload.amount.is_finite()
+        && payload.amount > 0.0
+        && (payload.discount_type == DiscountType::Fixed || payload.amount <= 100.0);
+    let valid_window = match (payload.starts_at, payload.expires_at) {
+        (Some(starts_at), Some(expires_at)) => starts_at < expires_at,
+        _ => true,
+    };
+    if !valid_amount || !valid_window || payload.max_uses.is_some_and(|max| max < 1) {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let code = payload
+        .code
+        .as_deref()
+        .map(str::trim)
+        .filter(|code| !code.is_empty())
+        .map(str::to_string);
+    if let Some(code) = &code {
+        let well_formed = code.len() <= 64
+            && code.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+        if !well_formed {
+            return Err(StatusCode::BAD_REQUEST);
+        }
+    }
+    Ok(code)
+}
+
+async fn ensure_own_product(db: &Database, product_id: Option<Uuid>, creator_id: &str) -> Result<(), StatusCode> {
+    let Some(product_id) = product_id else {
+        return Ok(());
+    };
+    let owns = sqlx::query_scalar::<_, bool>(
+        "SELECT EXISTS(SELECT 1 FROM products WHERE id = $1 AND user_id = $2)",
+    )
+    .bind(product_id)
+    .bind(creator_id)
+    .fetch_one(&db.pool)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    if !owns {
+        return Err(StatusCode::NOT_FOUND);
+    }
+    Ok(())
+}
+
+fn save_error(e: sqlx::Error) -> StatusCode {
+    match &e {
+        sqlx::Error::Database(db_error) if db_error.code().as_deref() == Some("23505") => {
+            StatusCode::CONFLICT
+        }
+        _ => {
+            tracing::error!("Failed to save discount: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        }
+    }
+}
+
+async fn list_discounts(
+    State(db): State<Database>,
+    claims: Claims,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let discounts = sqlx::query_as::<_, DiscountCode>(
+        "SELECT * FROM discount_codes WHERE creator_id = $1 ORDER BY created_at DESC",
+    )
+    .bind(&claims.sub)
+    .fetch_all(&db.pool)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(json!({ "success": true, "data": discounts })))
+}
+
+async fn create_discount(
+    State(db): State<Database>,
+    claims: Claims,
+    Json(payload): Json<DiscountRequest>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let code = validate(&payload)?;
+    ensure_own_product(&db, payload.product_id, &claims.sub).await?;
+
+    let discount = sqlx::query_as::<_, DiscountCode>(
+        r#"
+        INSERT INTO discount_codes (
+            creator_id, code, discount_type, amount, product_id, starts_at, expires_at, max_uses, is_active
+        )
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, COALESCE($9, TRUE))
+        RETURNING *
+        "#,
+    )
+    .bind(&claims.sub)
+    .bind(code)
+    .bind(payload.discount_type.as_str())
+    .bind(payload.amount)
+    .bind(payload.product_id)
+    .bind(payload.starts_at)
+    .bind(payload.expires_at)
+    .bind(payload.max_uses)
+    .bind(payload.is_active)
+    .fetch_one(&db.pool)
+    .await
+    .map_err(save_error)?;
+
+    Ok(Json(json!({ "success": true, "data": discount })))
+}
+
+async fn update_discount(
+    State(db): State<Database>,
+    Path(id): Path<Uuid>,
+    claims: Claims,
+    Json(payload): Json<DiscountRequest>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let code = validate(&payload)?;
+    ensure_own_product(&db, payload.product_id, &claims.sub).await?;
+
+    let discount = sqlx::query_as::<_, DiscountCode>(
+        r#"
+        UPDATE discount_codes
+        SET code = $3, discount_type = $4, amount = $5, product_id = $6, starts_at = $7,
+            expires_at = $8, max_uses = $9, is_active = COALESCE($10, is_active), updated_at = NOW()
+        WHERE id = $1 AND creator_id = $2
+        RETURNING *
+        "#,
+    )
+    .bind(id)
+    .bind(&claims.sub)
+    .bind(code)
+    .bind(payload.discount_type.as_str())
+    .bind(payload.amount)
+    .bind(payload.product_id)
+    .bind(payload.starts_at)
+    .bind(payload.expires_at)
+    .bind(payload.max_uses)
+    .bind(payload.is_active)
+    .fetch_optional(&db.pool)
+    .await
+    .map_err(save_error)?
+    .ok_or(StatusCode::NOT_FOUND)?;
+
+    Ok(Json(json!({ "success": true, "data": discount })))
+}
+
+// Purchases keep pointing at the discount they used, so it is switched off rather than deleted
+async fn deactivate_discount(
+    State(db): State<Database>,
+    Path(id): Path<Uuid>,
+    claims: Claims,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let discount = sqlx::query_as::<_, DiscountCode>(
+        r#"
+        UPDATE discount_codes SET is_active = FALSE, updated_at = NOW()
+        WHERE id = $1 AND creator_id = $2
+        RETURNING *
+        "#,
+    )
+    .bind(id)
+    .bind(&claims.sub)
+    .fetch_optional(&db.pool)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    .ok_or(StatusCode::NOT_FOUND)?;
+
+    Ok(Json(json!({ "success": true, "data": discount })))
+}
+
+// What the buyer would pay with a code, before starting checkout
+async fn preview_discount(
+    State(db): State<Database>,
+    _claims: Claims,
+    Json(payload): Json<PreviewDiscountRequest>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let mut product = sqlx::query_as::<_, Product>("SELECT * FROM products WHERE id = $1 AND is_active")
+        .bind(payload.product_id)
+        .fetch_optional(&db.pool)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+    if let Some(variant) = select_variant(&db, product.id, payload.variant_id).await? {
+        apply_variant(&mut product, &variant);
+    }
+
+    let price = product.price;
+    let discount = discount_product(&db, &mut product, payload.code.as_deref()).await?;
+    let discount_cents = discount.as_ref().map(|discount| discount.discount_cents).unwrap_or(0);
+
+    Ok(Json(json!({
+        "success": true,
+        "data": {
+            "productId": product.id,
+            "currency": product.currency,
+            "price": price,
+            "discount": discount_cents as f64 / 100.0,
+            "finalPrice": product.price,
+            "code": discount.and_then(|discount| discount.code)
+        }
+    })))
+}
@@ -0,0 +1,481 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::Json,
+    routing::{get, post},
+    Router,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use uuid::Uuid;
+
+use crate::{
+    auth::Claims,
+    database::Database,
+    notification_service::{notify, NotificationKind},
+    routes::{
+        fees::ensure_admin,
+        ledger::{post_journal, JournalKind},
+        stripe::{stripe_json, stripe_secret, stripe_url},
+    },
+};
+
+/// A buyer's chargeback on a payment, and the ledger earnings held back while it is open.
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct Dispute {
+    pub id: Uuid,
+    pub stripe_dispute_id: String,
+    pub stripe_charge_id: Option<String>,
+    pub stripe_payment_intent_id: Option<String>,
+    /// `None` when the disputed payment cannot be matched to a ledger entry.
+    pub creator_id: Option<String>,
+    pub amount_cents: i64,
+    pub currency: String,
+    pub reason: Option<String>,
+    /// `OPEN` until Stripe closes the dispute as `WON` or `LOST`.
+    pub status: String,
+    /// Stripe's own status, e.g. `needs_response` or `under_review`.
+    pub stripe_status: String,
+    /// Creator earnings taken out of the available balance when the dispute opened.
+    pub frozen_cents: i64,
+    pub evidence_due_by: Option<DateTime<Utc>>,
+    pub evidence_submitted_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub closed_at: Option<DateTime<Utc>>,
+}
+
+/// A dispute as reported by a `charge.dispute.*` webhook.
+pub(crate) struct DisputeUpdate<'a> {
+    pub stripe_dispute_id: &'a str,
+    pub stripe_charge_id: Option<&'a str>,
+    pub stripe_payment_intent_id: Option<&'a str>,
+    pub amount_cents: i64,
+    pub currency: &'a str,
+    pub reason: Option<&'a str>,
+    pub stripe_status: &'a str,
+    pub evidence_due_by: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct DisputeQueueQuery {
+    status: Option<String>,
+    page: Option<u32>,
+    limit: Option<u32>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DisputeEvidenceRequest {
+    product_description: Option<String>,
+    customer_communication: Option<String>,
+    uncategorized_text: Option<String>,
+    /// Send the evidence to the card issuer; otherwise it is only staged on Stripe.
+    #[serde(default)]
+    submit: bool,
+}
+
+pub fn dispute_routes() -> Router<Database> {
+    Router::new()
+        .route("/", get(list_disputes))
+        .route("/:id", get(get_dispute))
+        .route("/:id/evidence", post(submit_dispute_evidence))
+}
+
+// Open disputes come first, the closest evidence deadline at the top
+async fn list_disputes(
+    State(db): State<Database>,
+    Query(params): Query<DisputeQueueQuery>,
+    claims: Claims,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    ensure_admin(&db, &claims.sub).await?;
+    let page = params.page.unwrap_or(1).max(1);
+    let limit = params.limit.unwrap_or(20).clamp(1, 100);
+    let offset = ((page - 1) * limit) as i64;
+    let status = params.status.map(|status| status.trim().to_ascii_uppercase());
+
+    let disputes = sqlx::query_as::<_, Dispute>(
+        r#"
+        SELECT * FROM disputes
+        WHERE ($1::TEXT IS NULL OR status = $1)
+        ORDER BY status = 'OPEN' DESC, evidence_due_by ASC NULLS LAST, created_at DESC
+        LIMIT $2 OFFSET $3
+        "#,
+    )
+    .bind(&status)
+    .bind(limit as i64)
+    .bind(offset)
+    .fetch_all(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to list disputes: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    let total = sqlx::query_scalar::<_, i64>(
+        "SELECT COUNT(*) FROM disputes WHERE ($1::TEXT IS NULL OR status = $1)",
+    )
+    .bind(&status)
+    .fetch_one(&db.pool)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(json!({
+        "success": true,
+        "data": disputes,
+        "pagination": {
+            "page": page,
+            "limit": limit,
+            "total": total,
+            "pages": ((total as f64) / (limit as f64)).ceil() as u32,
+        }
+    })))
+}
+
+async fn get_dispute(
+    State(db): State<Database>,
+    Path(id): Path<Uuid>,
+    claims: Claims,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    ensure_admin(&db, &claims.sub).await?;
+    let dispute = load_dispute(&db, id).await?;
+
+    let ledger_entries = sqlx::query_as::<_, (Uuid, String, String, String, i64, i64, String)>(
+        r#"
+        SELECT id, product_type, source_type, source_id, gross_cents, net_cents, status
+        FROM ledger_entries
+        WHERE dispute_id = $1
+        ORDER BY created_at
+        "#,
+    )
+    .bind(id)
+    .fetch_all(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to load ledger entries of dispute {}: {}", id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?
+    .into_iter()
+    .map(|(id, product_type, source_type, source_id, gross, net, status)| {
+        json!({
+            "id": id,
+            "productType": product_type,
+            "sourceType": source_type,
+            "sourceId": source_id,
+            "grossCents": gross,
+            "netCents": net,
+            "status": status,
+        })
+    })
+    .collect::<Vec<_>>();
+
+    Ok(Json(json!({
+        "success": true,
+        "data": {
+            "dispute": dispute,
+            "ledgerEntries": ledger_entries,
+        }
+    })))
+}
+
+async fn submit_dispute_evidence(
+    State(db): State<Database>,
+    Path(id): Path<Uuid>,
+    claims: Claims,
+    Json(payload): Json<DisputeEvidenceRequest>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    ensure_admin(&db, &claims.sub).await?;
+    let dispute = load_dispute(&db, id).await?;
+    if dispute.status != "OPEN" {
+        return Err(StatusCode::CONFLICT);
+    }
+
+    let mut form_data = vec![("submit".to_string(), payload.submit.to_string())];
+    for (field, value) in [
+        ("product_description", payload.product_description),
+        ("customer_communication", payload.customer_communication),
+        ("uncategorized_text", payload.uncategorized_text),
+    ] {
+        if let Some(value) = value.filter(|value| !value.trim().is_empty()) {
+            form_data.push((format!("evidence[{}]", field), value));
+        }
+    }
+
+    let stripe_dispute = stripe_json(
+        reqwest::Client::new()
+            .post(stripe_url(&format!("/v1/disputes/{}", dispute.stripe_dispute_id)))
+            .bearer_auth(stripe_secret()?)
+            .form(&form_data)
+            .send()
+            .await,
+        "update dispute evidence",
+    )
+    .await?;
+    let stripe_status = stripe_dispute
+        .get("status")
+        .and_then(|value| value.as_str())
+        .unwrap_or(&dispute.stripe_status)
+        .to_string();
+
+    let dispute = sqlx::query_as::<_, Dispute>(
+        r#"
+        UPDATE disputes
+        SET stripe_status = $2,
+            evidence_submitted_at = CASE WHEN $3 THEN NOW() ELSE evidence_submitted_at END,
+            updated_at = NOW()
+        WHERE id = $1
+        RETURNING *
+        "#,
+    )
+    .bind(id)
+    .bind(&stripe_status)
+    .bind(payload.submit)
+    .fetch_one(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to update dispute {}: {}", id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    Ok(Json(json!({
+        "success": true,
+        "data": dispute
+    })))
+}
+
+async fn load_dispute(db: &Database, id: Uuid) -> Result<Dispute, StatusCode> {
+    sqlx::query_as::<_, Dispute>("SELECT * FROM disputes WHERE id = $1")
+        .bind(id)
+        .fetch_optional(&db.pool)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::NOT_FOUND)
+}
+
+/// Record a dispute reported by Stripe. A new dispute freezes the creator earnings of the
+/// disputed payment; closing it releases them when won and reverses them when lost.
+pub(crate) async fn apply_dispute_update(db: &Database, update: &DisputeUpdate<'_>) -> Result<(), StatusCode> {
+    let db_error = |e: sqlx::Error| {
+        tracing::error!("Failed to apply dispute {}: {}", update.stripe_dispute_id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    };
+    let mut tx = db.pool.begin().await.map_err(db_error)?;
+
+    let creator_id = match update.stripe_payment_intent_id {
+        Some(payment_intent_id) => sqlx::query_scalar::<_, String>(
+            "SELECT creator_id FROM ledger_entries WHERE stripe_payment_intent_id = $1 LIMIT 1",
+        )
+        .bind(payment_intent_id)
+        .fetch_optional(&mut tx)
+        .await
+        .map_err(db_error)?,
+        None => None,
+    };
+
+    let opened = sqlx::query_scalar::<_, Uuid>(
+        r#"
+        INSERT INTO disputes (
+            stripe_dispute_id, stripe_charge_id, stripe_payment_intent_id, creator_id,
+            amount_cents, currency, reason, stripe_status, evidence_due_by
+        )
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+        ON CONFLICT (stripe_dispute_id) DO NOTHING
+        RETURNING id
+        "#,
+    )
+    .bind(update.stripe_dispute_id)
+    .bind(update.stripe_charge_id)
+    .bind(update.stripe_payment_intent_id)
+    .bind(&creator_id)
+    .bind(update.amount_cents)
+    .bind(update.currency.to_uppercase())
+    .bind(update.reason)
+    .bind(update.stripe_status)
+    .bind(update.evidence_due_by)
+    .fetch_optional(&mut tx)
+    .await
+    .map_err(db_error)?;
+
+    let mut dispute = sqlx::query_as::<_, Dispute>(
+        r#"
+        UPDATE disputes
+        SET stripe_status = $2,
+            amount_cents = $3,
+            reason = COALESCE($4, reason),
+            evidence_due_by = COALESCE($5, evidence_due_by),
+            updated_at = NOW()
+        WHERE stripe_dispute_id = $1
+        RETURNING *
+        "#,
+    )
+    .bind(update.stripe_dispute_id)
+    .bind(update.stripe_status)
+    .bind(update.amount_cents)
+    .bind(update.reason)
+    .bind(update.evidence_due_by)
+    .fetch_one(&mut tx)
+    .await
+    .map_err(db_error)?;
+
+    if opened.is_some() {
+        // Pending entries are frozen too: a disputed charge went through even if its
+        // confirmation never reached us
+        dispute.frozen_cents = sqlx::query_scalar::<_, i64>(
+            r#"
+            WITH frozen AS (
+                UPDATE ledger_entries
+                SET status = 'DISPUTED', dispute_id = $1
+                WHERE stripe_payment_intent_id = $2 AND status IN ('PENDING', 'SETTLED')
+                RETURNING net_cents
+            )
+            UPDATE disputes
+            SET frozen_cents = (SELECT COALESCE(SUM(net_cents), 0) FROM frozen)
+            WHERE id = $1
+            RETURNING frozen_cents
+            "#,
+        )
+        .bind(dispute.id)
+        .bind(update.stripe_payment_intent_id)
+        .fetch_one(&mut tx)
+        .await
+        .map_err(db_error)?;
+    }
+
+    let outcome = match update.stripe_status {
+        "won" | "warning_closed" => Some("WON"),
+        "lost" => Some("LOST"),
+        _ => None,
+    };
+    let closed = match outcome {
+        Some(outcome) if dispute.status == "OPEN" => {
+            resolve_dispute(&mut tx, &dispute, outcome).await.map_err(db_error)?;
+            dispute.status = outcome.to_string();
+            true
+        }
+        _ => false,
+    };
+
+    tx.commit().await.map_err(db_error)?;
+
+    if opened.is_some() {
+        notify_dispute_opened(db, &dispute).await;
+    } else if closed {
+        notify_dispute_closed(db, &dispute).await;
+    }
+    Ok(())
+}
+
+// Won disputes hand the frozen earnings back; lost ones reverse them together with the tax
+// collected on the sale, since the buyer got the money back. Either way the payment did reach
+// the platform, so entries frozen before they settled are journaled as settled first.
+async fn resolve_dispute(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    dispute: &Dispute,
+    outcome: &str,
+) -> Result<(), sqlx::Error> {
+    let ledger_status = if outcome == "WON" { "SETTLED" } else { "REVERSED" };
+    let resolved = sqlx::query_scalar::<_, Uuid>(
+        r#"
+        UPDATE ledger_entries
+        SET status = $2, settled_at = COALESCE(settled_at, NOW())
+        WHERE dispute_id = $1 AND status = 'DISPUTED'
+        RETURNING id
+        "#,
+    )
+    .bind(dispute.id)
+    .bind(ledger_status)
+    .fetch_all(&mut *tx)
+    .await?;
+    post_journal(&mut *tx, &resolved, JournalKind::Settlement).await?;
+    if outcome == "LOST" {
+        post_journal(&mut *tx, &resolved, JournalKind::Reversal).await?;
+    }
+
+    if outcome == "LOST" {
+        sqlx::query("UPDATE tax_lines SET status = 'REVERSED' WHERE stripe_payment_intent_id = $1")
+            .bind(&dispute.stripe_payment_intent_id)
+            .execute(&mut *tx)
+            .await?;
+    }
+
+    sqlx::query("UPDATE disputes SET status = $2, closed_at = NOW(), updated_at = NOW() WHERE id = $1")
+        .bind(dispute.id)
+        .bind(outcome)
+        .execute(&mut *tx)
+        .await?;
+
+    Ok(())
+}
+
+async fn notify_dispute_opened(db: &Database, dispute: &Dispute) {
+    let Some(creator_id) = &dispute.creator_id else {
+        return;
+    };
+    notify(
+        db,
+        creator_id,
+        NotificationKind::Dispute,
+        json!({
+            "message": format!(
+                "A buyer disputed a {:.2} {} payment",
+                dispute.amount_cents as f64 / 100.0,
+                dispute.currency.to_uppercase()
+            ),
+            "disputeId": dispute.id,
+            "status": dispute.status,
+            "amountCents": dispute.amount_cents,
+            "currency": dispute.currency,
+            "evidenceDueBy": dispute.evidence_due_by,
+        }),
+    )
+    .await;
+    let Some(amqp) = &db.amqp else {
+        return;
+    };
+    if let Err(e) = amqp
+        .send_dispute_opened_notification(
+            dispute.id.to_string(),
+            creator_id.clone(),
+            dispute.amount_cents,
+            dispute.currency.clone(),
+            dispute.reason.clone(),
+            dispute.evidence_due_by.map(|due_by| due_by.to_rfc3339()),
+        )
+        .await
+    {
+        tracing::warn!("Failed to notify {} about dispute {}: {}", creator_id, dispute.id, e);
+    }
+}
+
+async fn notify_dispute_closed(db: &Database, dispute: &Dispute) {
+    let Some(creator_id) = &dispute.creator_id else {
+        return;
+    };
+    notify(
+        db,
+        creator_id,
+        NotificationKind::Dispute,
+        json!({
+            "message": if dispute.status == "WON" {
+                "A disputed payment was decided in your favour"
+            } else {
+                "A disputed payment was decided against you"
+            },
+            "disputeId": dispute.id,
+            "status": dispute.status,
+            "amountCents": dispute.amount_cents,
+            "currency": dispute.currency,
+        }),
+    )
+    .await;
+    let Some(amqp) = &db.amqp else {
+        return;
+    };
+    if let Err(e) = amqp
+        .send_dispute_closed_notification(dispute.id.to_string(), creator_id.clone(), dispute.status.clone())
+        .await
+    {
+        tracing::warn!("Failed to notify {} about dispute {}: {}", creator_id, dispute.id, e);
+    }
+}
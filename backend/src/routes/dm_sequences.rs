@@ -0,0 +1,716 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::Json,
+    routing::{get, post, put},
+    Router,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use uuid::Uuid;
+
+use crate::{
+    auth::Claims,
+    database::Database,
+    messaging::{self, RequestStatus},
+    routes::{messages::MAX_MESSAGE_CHARS, polls::ensure_creator},
+};
+
+const MAX_NAME_CHARS: usize = 100;
+const MAX_STEPS: usize = 20;
+/// Latest a step can be sent, a year after the subscription started.
+const MAX_DELAY_HOURS: i32 = 365 * 24;
+/// Steps sent per scheduler run, so one busy run can't hold up the next.
+const SEND_BATCH: i64 = 200;
+
+/// A creator's automated sequence of messages to new members.
+#[derive(Debug, Serialize, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+struct Sequence {
+    id: Uuid,
+    name: String,
+    /// `None` when every new member is enrolled.
+    tier_id: Option<String>,
+    /// `ACTIVE` or `PAUSED`.
+    status: String,
+    step_count: i64,
+    active_enrollments: i64,
+    created_at: DateTime<Utc>,
+    updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+struct SequenceStep {
+    id: Uuid,
+    /// Hours after the subscription started; 0 sends straight away.
+    delay_hours: i32,
+    /// `{name}` is replaced with the member's name.
+    body: String,
+    updated_at: DateTime<Utc>,
+}
+
+/// A member's progress through a sequence.
+#[derive(Debug, Serialize, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+struct Enrollment {
+    user_id: String,
+    name: Option<String>,
+    username: Option<String>,
+    /// `ACTIVE`, `PAUSED` or `CANCELLED`.
+    status: String,
+    started_at: DateTime<Utc>,
+    steps_sent: i32,
+    last_sent_at: Option<DateTime<Utc>>,
+    next_send_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct StepRequest {
+    delay_hours: i32,
+    body: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CreateSequenceRequest {
+    name: String,
+    /// Only members of this tier; every member when left out.
+    tier_id: Option<String>,
+    #[serde(default)]
+    steps: Vec<StepRequest>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RenameSequenceRequest {
+    name: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct EnrollmentQuery {
+    status: Option<String>,
+    page: Option<u32>,
+    limit: Option<u32>,
+}
+
+/// Due steps as the scheduler sends them.
+#[derive(Debug, sqlx::FromRow)]
+struct DueStep {
+    enrollment_id: Uuid,
+    user_id: String,
+    creator_id: String,
+    delay_hours: i32,
+    body: String,
+    member_name: Option<String>,
+    creator_name: Option<String>,
+}
+
+pub fn sequence_routes() -> Router<Database> {
+    Router::new()
+        .route("/", get(list_sequences).post(create_sequence))
+        .route("/:id", get(get_sequence).put(rename_sequence).delete(delete_sequence))
+        .route("/:id/pause", post(pause_sequence))
+        .route("/:id/resume", post(resume_sequence))
+        .route("/:id/steps", post(add_step))
+        .route("/:id/steps/:step_id", put(update_step).delete(delete_step))
+        .route("/:id/enrollments", get(list_enrollments))
+        .route("/:id/enrollments/:user_id/pause", post(pause_enrollment))
+        .route("/:id/enrollments/:user_id/resume", post(resume_enrollment))
+        .route("/:id/enrollments/:user_id/cancel", post(cancel_enrollment))
+}
+
+fn validate_step(step: &StepRequest) -> Result<(), StatusCode> {
+    let body = step.body.trim();
+    if body.is_empty()
+        || body.chars().count() > MAX_MESSAGE_CHARS
+        || !(0..=MAX_DELAY_HOURS).contains(&step.delay_hours)
+    {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    Ok(())
+}
+
+fn step_conflict(e: sqlx::Error) -> StatusCode {
+    match e {
+        // Another step is already sent at that delay
+        sqlx::Error::Database(e) if e.code().as_deref() == Some("23505") => StatusCode::CONFLICT,
+        e => {
+            tracing::error!("Failed to save sequence step: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        }
+    }
+}
+
+async fn load_sequence(db: &Database, id: Uuid, creator_id: &str) -> Result<Sequence, StatusCode> {
+    sqlx::query_as::<_, Sequence>(
+        r#"
+        SELECT q.id, q.name, q.tier_id, q.status, q.created_at, q.updated_at,
+               (SELECT COUNT(*) FROM dm_sequence_steps st WHERE st.sequence_id = q.id) AS step_count,
+               (SELECT COUNT(*) FROM dm_sequence_enrollments e
+                WHERE e.sequence_id = q.id AND e.status = 'ACTIVE') AS active_enrollments
+        FROM dm_sequences q
+        WHERE q.id = $1 AND q.creator_id = $2
+        "#,
+    )
+    .bind(id)
+    .bind(creator_id)
+    .fetch_optional(&db.pool)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    .ok_or(StatusCode::NOT_FOUND)
+}
+
+async fn load_steps(db: &Database, id: Uuid) -> Result<Vec<SequenceStep>, StatusCode> {
+    sqlx::query_as::<_, SequenceStep>(
+        r#"
+        SELECT id, delay_hours, body, updated_at FROM dm_sequence_steps
+        WHERE sequence_id = $1
+        ORDER BY delay_hours
+        "#,
+    )
+    .bind(id)
+    .fetch_all(&db.pool)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
+}
+
+async fn sequence_response(
+    db: &Database,
+    id: Uuid,
+    creator_id: &str,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let sequence = load_sequence(db, id, creator_id).await?;
+    let steps = load_steps(db, id).await?;
+    let mut data = json!(sequence);
+    data["steps"] = json!(steps);
+    Ok(Json(json!({ "success": true, "data": data })))
+}
+
+async fn list_sequences(
+    State(db): State<Database>,
+    claims: Claims,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    ensure_creator(&db, &claims.sub).await?;
+    let sequences = sqlx::query_as::<_, Sequence>(
+        r#"
+        SELECT q.id, q.name, q.tier_id, q.status, q.created_at, q.updated_at,
+               (SELECT COUNT(*) FROM dm_sequence_steps st WHERE st.sequence_id = q.id) AS step_count,
+               (SELECT COUNT(*) FROM dm_sequence_enrollments e
+                WHERE e.sequence_id = q.id AND e.status = 'ACTIVE') AS active_enrollments
+        FROM dm_sequences q
+        WHERE q.creator_id = $1
+        ORDER BY q.created_at DESC
+        "#,
+    )
+    .bind(&claims.sub)
+    .fetch_all(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to list sequences of {}: {}", claims.sub, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    Ok(Json(json!({ "success": true, "data": sequences })))
+}
+
+/// Members who subscribe from now on are enrolled; existing members aren't.
+async fn create_sequence(
+    State(db): State<Database>,
+    claims: Claims,
+    Json(payload): Json<CreateSequenceRequest>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    ensure_creator(&db, &claims.sub).await?;
+    let name = payload.name.trim();
+    if name.is_empty() || name.chars().count() > MAX_NAME_CHARS || payload.steps.len() > MAX_STEPS {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    for step in &payload.steps {
+        validate_step(step)?;
+    }
+    let tier_id = payload
+        .tier_id
+        .as_deref()
+        .map(str::trim)
+        .filter(|tier_id| !tier_id.is_empty());
+
+    let mut tx = db.pool.begin().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let id = sqlx::query_scalar::<_, Uuid>(
+        "INSERT INTO dm_sequences (creator_id, name, tier_id) VALUES ($1, $2, $3) RETURNING id",
+    )
+    .bind(&claims.sub)
+    .bind(name)
+    .bind(tier_id)
+    .fetch_one(&mut tx)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to create sequence for {}: {}", claims.sub, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    for step in &payload.steps {
+        sqlx::query("INSERT INTO dm_sequence_steps (sequence_id, delay_hours, body) VALUES ($1, $2, $3)")
+            .bind(id)
+            .bind(step.delay_hours)
+            .bind(step.body.trim())
+            .execute(&mut tx)
+            .await
+            .map_err(step_conflict)?;
+    }
+    tx.commit().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    sequence_response(&db, id, &claims.sub).await
+}
+
+async fn get_sequence(
+    State(db): State<Database>,
+    Path(id): Path<Uuid>,
+    claims: Claims,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    sequence_response(&db, id, &claims.sub).await
+}
+
+async fn rename_sequence(
+    State(db): State<Database>,
+    Path(id): Path<Uuid>,
+    claims: Claims,
+    Json(payload): Json<RenameSequenceRequest>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let name = payload.name.trim();
+    if name.is_empty() || name.chars().count() > MAX_NAME_CHARS {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let renamed = sqlx::query(
+        "UPDATE dm_sequences SET name = $3, updated_at = NOW() WHERE id = $1 AND creator_id = $2",
+    )
+    .bind(id)
+    .bind(&claims.sub)
+    .bind(name)
+    .execute(&db.pool)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    .rows_affected();
+    if renamed == 0 {
+        return Err(StatusCode::NOT_FOUND);
+    }
+    sequence_response(&db, id, &claims.sub).await
+}
+
+/// Delete the sequence and everyone's progress through it. Messages already sent stay.
+async fn delete_sequence(
+    State(db): State<Database>,
+    Path(id): Path<Uuid>,
+    claims: Claims,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let deleted = sqlx::query("DELETE FROM dm_sequences WHERE id = $1 AND creator_id = $2")
+        .bind(id)
+        .bind(&claims.sub)
+        .execute(&db.pool)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .rows_affected();
+    if deleted == 0 {
+        return Err(StatusCode::NOT_FOUND);
+    }
+    Ok(Json(json!({ "success": true, "message": "Sequence deleted" })))
+}
+
+/// Stop sending the sequence's steps. New members are still enrolled while it is paused.
+async fn pause_sequence(
+    State(db): State<Database>,
+    Path(id): Path<Uuid>,
+    claims: Claims,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    sqlx::query(
+        r#"
+        UPDATE dm_sequences SET status = 'PAUSED', paused_at = NOW(), updated_at = NOW()
+        WHERE id = $1 AND creator_id = $2 AND status = 'ACTIVE'
+        "#,
+    )
+    .bind(id)
+    .bind(&claims.sub)
+    .execute(&db.pool)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    sequence_response(&db, id, &claims.sub).await
+}
+
+/// Pick up where the sequence left off: members' schedules move on by the time it was paused,
+/// so nothing piles up to be sent at once.
+async fn resume_sequence(
+    State(db): State<Database>,
+    Path(id): Path<Uuid>,
+    claims: Claims,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let mut tx = db.pool.begin().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let paused_at = sqlx::query_scalar::<_, Option<DateTime<Utc>>>(
+        r#"
+        UPDATE dm_sequences q SET status = 'ACTIVE', paused_at = NULL, updated_at = NOW()
+        FROM dm_sequences old
+        WHERE q.id = old.id AND q.id = $1 AND q.creator_id = $2 AND q.status = 'PAUSED'
+        RETURNING old.paused_at
+        "#,
+    )
+    .bind(id)
+    .bind(&claims.sub)
+    .fetch_optional(&mut tx)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    .flatten();
+    if let Some(paused_at) = paused_at {
+        // Members who joined during the pause start their schedule now
+        sqlx::query(
+            r#"
+            UPDATE dm_sequence_enrollments
+            SET started_at = started_at + (NOW() - GREATEST($2, started_at)), updated_at = NOW()
+            WHERE sequence_id = $1 AND status IN ('ACTIVE', 'PAUSED')
+            "#,
+        )
+        .bind(id)
+        .bind(paused_at)
+        .execute(&mut tx)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    }
+    tx.commit().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    sequence_response(&db, id, &claims.sub).await
+}
+
+/// Add a step. Members already past its delay skip it; the rest get it in turn.
+async fn add_step(
+    State(db): State<Database>,
+    Path(id): Path<Uuid>,
+    claims: Claims,
+    Json(payload): Json<StepRequest>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    validate_step(&payload)?;
+    let sequence = load_sequence(&db, id, &claims.sub).await?;
+    if sequence.step_count as usize >= MAX_STEPS {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    sqlx::query("INSERT INTO dm_sequence_steps (sequence_id, delay_hours, body) VALUES ($1, $2, $3)")
+        .bind(id)
+        .bind(payload.delay_hours)
+        .bind(payload.body.trim())
+        .execute(&db.pool)
+        .await
+        .map_err(step_conflict)?;
+    sequence_response(&db, id, &claims.sub).await
+}
+
+/// Edit a step. Members who haven't reached it yet get the new version.
+async fn update_step(
+    State(db): State<Database>,
+    Path((id, step_id)): Path<(Uuid, Uuid)>,
+    claims: Claims,
+    Json(payload): Json<StepRequest>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    validate_step(&payload)?;
+    load_sequence(&db, id, &claims.sub).await?;
+    let updated = sqlx::query(
+        r#"
+        UPDATE dm_sequence_steps SET delay_hours = $3, body = $4, updated_at = NOW()
+        WHERE id = $2 AND sequence_id = $1
+        "#,
+    )
+    .bind(id)
+    .bind(step_id)
+    .bind(payload.delay_hours)
+    .bind(payload.body.trim())
+    .execute(&db.pool)
+    .await
+    .map_err(step_conflict)?
+    .rows_affected();
+    if updated == 0 {
+        return Err(StatusCode::NOT_FOUND);
+    }
+    sequence_response(&db, id, &claims.sub).await
+}
+
+async fn delete_step(
+    State(db): State<Database>,
+    Path((id, step_id)): Path<(Uuid, Uuid)>,
+    claims: Claims,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    load_sequence(&db, id, &claims.sub).await?;
+    let deleted = sqlx::query("DELETE FROM dm_sequence_steps WHERE id = $2 AND sequence_id = $1")
+        .bind(id)
+        .bind(step_id)
+        .execute(&db.pool)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .rows_affected();
+    if deleted == 0 {
+        return Err(StatusCode::NOT_FOUND);
+    }
+    sequence_response(&db, id, &claims.sub).await
+}
+
+async fn list_enrollments(
+    State(db): State<Database>,
+    Path(id): Path<Uuid>,
+    Query(params): Query<EnrollmentQuery>,
+    claims: Claims,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    load_sequence(&db, id, &claims.sub).await?;
+    let page = params.page.unwrap_or(1).max(1);
+    let limit = params.limit.unwrap_or(20).clamp(1, 100);
+    let offset = ((page - 1) * limit) as i64;
+    let status = params.status.map(|status| status.trim().to_ascii_uppercase());
+
+    let enrollments = sqlx::query_as::<_, Enrollment>(
+        r#"
+        SELECT e.user_id, COALESCE(u.display_name, u.name) AS name, u.username, e.status,
+               e.started_at, e.steps_sent, e.last_sent_at,
+               (SELECT e.started_at + make_interval(hours => st.delay_hours)
+                FROM dm_sequence_steps st
+                WHERE st.sequence_id = e.sequence_id
+                  AND st.delay_hours > COALESCE(e.last_delay_hours, -1)
+                ORDER BY st.delay_hours
+                LIMIT 1) AS next_send_at
+        FROM dm_sequence_enrollments e
+        JOIN users u ON u.id = e.user_id
+        WHERE e.sequence_id = $1 AND ($2::TEXT IS NULL OR e.status = $2)
+        ORDER BY e.started_at DESC
+        LIMIT $3 OFFSET $4
+        "#,
+    )
+    .bind(id)
+    .bind(&status)
+    .bind(limit as i64)
+    .bind(offset)
+    .fetch_all(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to list enrollments of {}: {}", id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    let total = sqlx::query_scalar::<_, i64>(
+        r#"
+        SELECT COUNT(*) FROM dm_sequence_enrollments
+        WHERE sequence_id = $1 AND ($2::TEXT IS NULL OR status = $2)
+        "#,
+    )
+    .bind(id)
+    .bind(&status)
+    .fetch_one(&db.pool)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(json!({
+        "success": true,
+        "data": enrollments,
+        "pagination": {
+            "page": page,
+            "limit": limit,
+            "total": total,
+            "pages": ((total as f64) / (limit as f64)).ceil() as u32,
+        }
+    })))
+}
+
+async fn pause_enrollment(
+    State(db): State<Database>,
+    Path((id, user_id)): Path<(Uuid, String)>,
+    claims: Claims,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    update_enrollment(
+        &db,
+        id,
+        &user_id,
+        &claims.sub,
+        r#"
+        UPDATE dm_sequence_enrollments SET status = 'PAUSED', paused_at = NOW(), updated_at = NOW()
+        WHERE sequence_id = $1 AND user_id = $2 AND status = 'ACTIVE'
+        "#,
+    )
+    .await
+}
+
+/// Resume a member's schedule where it was paused.
+async fn resume_enrollment(
+    State(db): State<Database>,
+    Path((id, user_id)): Path<(Uuid, String)>,
+    claims: Claims,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    update_enrollment(
+        &db,
+        id,
+        &user_id,
+        &claims.sub,
+        r#"
+        UPDATE dm_sequence_enrollments
+        SET status = 'ACTIVE', started_at = started_at + (NOW() - paused_at), paused_at = NULL,
+            updated_at = NOW()
+        WHERE sequence_id = $1 AND user_id = $2 AND status = 'PAUSED'
+        "#,
+    )
+    .await
+}
+
+/// Take a member out of the sequence for good.
+async fn cancel_enrollment(
+    State(db): State<Database>,
+    Path((id, user_id)): Path<(Uuid, String)>,
+    claims: Claims,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    update_enrollment(
+        &db,
+        id,
+        &user_id,
+        &claims.sub,
+        r#"
+        UPDATE dm_sequence_enrollments SET status = 'CANCELLED', updated_at = NOW()
+        WHERE sequence_id = $1 AND user_id = $2 AND status IN ('ACTIVE', 'PAUSED')
+        "#,
+    )
+    .await
+}
+
+async fn update_enrollment(
+    db: &Database,
+    id: Uuid,
+    user_id: &str,
+    creator_id: &str,
+    statement: &str,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    load_sequence(db, id, creator_id).await?;
+    let status = sqlx::query_scalar::<_, String>(&format!("{} RETURNING status", statement))
+        .bind(id)
+        .bind(user_id)
+        .fetch_optional(&db.pool)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to update enrollment of {} in {}: {}", user_id, id, e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?
+        .ok_or(StatusCode::CONFLICT)?;
+    Ok(Json(json!({
+        "success": true,
+        "data": { "userId": user_id, "status": status }
+    })))
+}
+
+/// Enroll members whose subscription started since a sequence was created, and cancel those whose
+/// membership lapsed.
+pub(crate) async fn enroll_new_members(db: &Database) -> anyhow::Result<()> {
+    sqlx::query(
+        r#"
+        INSERT INTO dm_sequence_enrollments (sequence_id, user_id, started_at)
+        SELECT q.id, s.user_id, MIN(s.created_at)
+        FROM dm_sequences q
+        JOIN subscriptions s
+          ON s.creator_id = q.creator_id AND UPPER(s.status) = 'ACTIVE'
+         AND (q.tier_id IS NULL OR s.tier_id = q.tier_id)
+         AND s.created_at >= q.created_at
+        GROUP BY q.id, s.user_id
+        ON CONFLICT (sequence_id, user_id) DO NOTHING
+        "#,
+    )
+    .execute(&db.pool)
+    .await?;
+
+    sqlx::query(
+        r#"
+        UPDATE dm_sequence_enrollments e SET status = 'CANCELLED', updated_at = NOW()
+        FROM dm_sequences q
+        WHERE e.sequence_id = q.id AND e.status IN ('ACTIVE', 'PAUSED')
+          AND NOT EXISTS (
+              SELECT 1 FROM subscriptions s
+              WHERE s.user_id = e.user_id AND s.creator_id = q.creator_id
+                AND UPPER(s.status) = 'ACTIVE'
+                AND (q.tier_id IS NULL OR s.tier_id = q.tier_id)
+          )
+        "#,
+    )
+    .execute(&db.pool)
+    .await?;
+    Ok(())
+}
+
+/// Send every enrollment's next step that has come due, one step per member per run. A step
+/// is claimed before it is sent, so a crash skips it rather than sending it twice.
+pub(crate) async fn send_due_steps(db: &Database) -> anyhow::Result<()> {
+    let due = sqlx::query_as::<_, DueStep>(
+        r#"
+        SELECT e.id AS enrollment_id, e.user_id, q.creator_id, st.delay_hours, st.body,
+               COALESCE(m.display_name, m.name, m.username) AS member_name,
+               COALESCE(c.display_name, c.name, c.username) AS creator_name
+        FROM dm_sequence_enrollments e
+        JOIN dm_sequences q ON q.id = e.sequence_id AND q.status = 'ACTIVE'
+        JOIN LATERAL (
+            SELECT delay_hours, body FROM dm_sequence_steps
+            WHERE sequence_id = q.id AND delay_hours > COALESCE(e.last_delay_hours, -1)
+            ORDER BY delay_hours
+            LIMIT 1
+        ) st ON TRUE
+        JOIN users m ON m.id = e.user_id
+        JOIN users c ON c.id = q.creator_id
+        WHERE e.status = 'ACTIVE'
+          AND e.started_at + make_interval(hours => st.delay_hours) <= NOW()
+        ORDER BY e.started_at
+        LIMIT $1
+        "#,
+    )
+    .bind(SEND_BATCH)
+    .fetch_all(&db.pool)
+    .await?;
+
+    for step in due {
+        let claimed = sqlx::query(
+            r#"
+            UPDATE dm_sequence_enrollments
+            SET last_delay_hours = $2, last_sent_at = NOW(), steps_sent = steps_sent + 1,
+                updated_at = NOW()
+            WHERE id = $1 AND status = 'ACTIVE' AND COALESCE(last_delay_hours, -1) < $2
+            "#,
+        )
+        .bind(step.enrollment_id)
+        .bind(step.delay_hours)
+        .execute(&db.pool)
+        .await?
+        .rows_affected();
+        if claimed == 0 {
+            continue;
+        }
+        if let Err(e) = send_step(db, &step).await {
+            tracing::warn!("Failed to send sequence step to {}: {:?}", step.user_id, e);
+        }
+    }
+    Ok(())
+}
+
+async fn send_step(db: &Database, step: &DueStep) -> anyhow::Result<()> {
+    if messaging::messaging_suspended(db, &step.creator_id).await? {
+        return Ok(());
+    }
+    let conversation_id =
+        messaging::open_direct_conversation(db, &step.creator_id, &step.user_id).await?;
+    // Members who declined the creator's messages leave the sequence
+    let declined = sqlx::query_scalar::<_, bool>(
+        r#"
+        SELECT EXISTS(
+            SELECT 1 FROM conversation_participants
+            WHERE conversation_id = $1 AND user_id = $2 AND request_status = $3
+        )
+        "#,
+    )
+    .bind(conversation_id)
+    .bind(&step.user_id)
+    .bind(RequestStatus::Declined.as_str())
+    .fetch_one(&db.pool)
+    .await?;
+    if declined {
+        sqlx::query("UPDATE dm_sequence_enrollments SET status = 'CANCELLED', updated_at = NOW() WHERE id = $1")
+            .bind(step.enrollment_id)
+            .execute(&db.pool)
+            .await?;
+        return Ok(());
+    }
+
+    let name = step.member_name.as_deref().unwrap_or("there");
+    let body = step.body.replace("{name}", name);
+    let message = messaging::post_message(db, conversation_id, &step.creator_id, &body).await?;
+    let sender_name = step.creator_name.as_deref().unwrap_or("Someone");
+    messaging::deliver_message(db, &message, &[], sender_name).await;
+    Ok(())
+}
@@ -0,0 +1,243 @@
+use axum::{
+    body::Bytes,
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::Json,
+    routing::{delete, get, post},
+    Router,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::{
+    auth::Claims,
+    config::EmailConfig,
+    database::Database,
+    email_service::suppress,
+    routes::fees::ensure_admin,
+};
+
+/// An address no email is sent to any more.
+#[derive(Debug, Serialize, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+struct Suppression {
+    email: String,
+    /// `HARD_BOUNCE` or `COMPLAINT`.
+    reason: String,
+    /// The provider that reported it, e.g. `POSTMARK` or `SES`.
+    source: String,
+    created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+struct WebhookQuery {
+    token: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SuppressionQuery {
+    page: Option<u32>,
+    limit: Option<u32>,
+}
+
+/// Bounce and complaint webhooks, public but guarded by `?token=`.
+pub fn email_routes() -> Router<Database> {
+    Router::new()
+        .route("/webhooks/postmark", post(postmark_webhook))
+        .route("/webhooks/ses", post(ses_webhook))
+}
+
+pub fn suppression_routes() -> Router<Database> {
+    Router::new()
+        .route("/", get(list_suppressions))
+        .route("/:email", delete(remove_suppression))
+}
+
+/// Neither provider signs its webhooks in a way worth checking here, so the webhook URLs carry
+/// `EMAIL_WEBHOOK_SECRET` as `?token=`.
+fn verify_token(query: &WebhookQuery) -> Result<(), StatusCode> {
+    let secret = EmailConfig::from_env().webhook_secret;
+    if secret.trim().is_empty() {
+        tracing::error!("EMAIL_WEBHOOK_SECRET is not configured");
+        return Err(StatusCode::SERVICE_UNAVAILABLE);
+    }
+    match query.token.as_deref() {
+        Some(token) if token == secret => Ok(()),
+        _ => Err(StatusCode::UNAUTHORIZED),
+    }
+}
+
+/// Postmark bounce and spam complaint webhook. Soft bounces are left to the retries; bounces
+/// that deactivate the address at Postmark suppress it here too.
+async fn postmark_webhook(
+    State(db): State<Database>,
+    Query(query): Query<WebhookQuery>,
+    Json(event): Json<Value>,
+) -> Result<Json<Value>, StatusCode> {
+    verify_token(&query)?;
+
+    let email = event["Email"].as_str().unwrap_or_default();
+    let reason = match event["RecordType"].as_str() {
+        Some("SpamComplaint") => Some("COMPLAINT"),
+        Some("Bounce")
+            if event["Inactive"].as_bool() == Some(true)
+                || event["Type"].as_str() == Some("HardBounce") =>
+        {
+            Some("HARD_BOUNCE")
+        }
+        _ => None,
+    };
+    let handled = match reason {
+        Some(reason) if !email.is_empty() => {
+            suppress(&db, email, reason, "POSTMARK").await;
+            true
+        }
+        _ => false,
+    };
+
+    Ok(Json(json!({ "success": true, "data": { "handled": handled } })))
+}
+
+/// Amazon SES bounce and complaint notifications, delivered by an SNS subscription. SNS posts
+/// them as `text/plain`, hence the raw body.
+async fn ses_webhook(
+    State(db): State<Database>,
+    Query(query): Query<WebhookQuery>,
+    body: Bytes,
+) -> Result<Json<Value>, StatusCode> {
+    verify_token(&query)?;
+
+    let envelope: Value = serde_json::from_slice(&body).map_err(|e| {
+        tracing::error!("Malformed SNS message: {}", e);
+        StatusCode::BAD_REQUEST
+    })?;
+    match envelope["Type"].as_str() {
+        Some("SubscriptionConfirmation") => {
+            confirm_sns_subscription(envelope["SubscribeURL"].as_str().unwrap_or_default())
+                .await?;
+            return Ok(Json(json!({ "success": true, "data": { "handled": true } })));
+        }
+        Some("Notification") => {}
+        _ => return Ok(Json(json!({ "success": true, "data": { "handled": false } }))),
+    }
+
+    let message: Value = envelope["Message"]
+        .as_str()
+        .and_then(|message| serde_json::from_str(message).ok())
+        .ok_or(StatusCode::BAD_REQUEST)?;
+    // Notifications use `notificationType`, configuration set events `eventType`
+    let kind = message["notificationType"]
+        .as_str()
+        .or_else(|| message["eventType"].as_str());
+    let (reason, recipients) = match kind {
+        Some("Bounce") if message["bounce"]["bounceType"].as_str() == Some("Permanent") => {
+            ("HARD_BOUNCE", &message["bounce"]["bouncedRecipients"])
+        }
+        Some("Complaint") => ("COMPLAINT", &message["complaint"]["complainedRecipients"]),
+        _ => return Ok(Json(json!({ "success": true, "data": { "handled": false } }))),
+    };
+
+    let mut suppressed = 0;
+    for recipient in recipients.as_array().into_iter().flatten() {
+        if let Some(email) = recipient["emailAddress"].as_str() {
+            suppress(&db, email, reason, "SES").await;
+            suppressed += 1;
+        }
+    }
+
+    Ok(Json(json!({
+        "success": true,
+        "data": { "handled": true, "suppressed": suppressed }
+    })))
+}
+
+/// SNS asks every new subscriber to confirm by visiting a link on its own domain.
+async fn confirm_sns_subscription(subscribe_url: &str) -> Result<(), StatusCode> {
+    let url = reqwest::Url::parse(subscribe_url).map_err(|_| StatusCode::BAD_REQUEST)?;
+    let from_sns = url.scheme() == "https"
+        && url
+            .host_str()
+            .is_some_and(|host| host.starts_with("sns.") && host.ends_with(".amazonaws.com"));
+    if !from_sns {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let response = reqwest::get(url).await.map_err(|e| {
+        tracing::error!("Failed to confirm SNS subscription: {}", e);
+        StatusCode::BAD_GATEWAY
+    })?;
+    if !response.status().is_success() {
+        tracing::error!("SNS refused the subscription confirmation: {}", response.status());
+        return Err(StatusCode::BAD_GATEWAY);
+    }
+    Ok(())
+}
+
+async fn list_suppressions(
+    State(db): State<Database>,
+    Query(params): Query<SuppressionQuery>,
+    claims: Claims,
+) -> Result<Json<Value>, StatusCode> {
+    ensure_admin(&db, &claims.sub).await?;
+
+    let page = params.page.unwrap_or(1).max(1);
+    let limit = params.limit.unwrap_or(50).clamp(1, 200);
+    let offset = ((page - 1) * limit) as i64;
+
+    let suppressions = sqlx::query_as::<_, Suppression>(
+        r#"
+        SELECT email, reason, source, created_at FROM email_suppressions
+        ORDER BY created_at DESC
+        LIMIT $1 OFFSET $2
+        "#,
+    )
+    .bind(limit as i64)
+    .bind(offset)
+    .fetch_all(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to list email suppressions: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    let total = sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM email_suppressions")
+        .fetch_one(&db.pool)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(json!({
+        "success": true,
+        "data": suppressions,
+        "pagination": {
+            "page": page,
+            "limit": limit,
+            "total": total,
+            "pages": ((total as f64) / (limit as f64)).ceil() as u32,
+        }
+    })))
+}
+
+/// Lift a suppression, e.g. once the recipient fixed their mailbox.
+async fn remove_suppression(
+    State(db): State<Database>,
+    Path(email): Path<String>,
+    claims: Claims,
+) -> Result<Json<Value>, StatusCode> {
+    ensure_admin(&db, &claims.sub).await?;
+
+    let email = email.trim().to_lowercase();
+    let removed = sqlx::query("DELETE FROM email_suppressions WHERE email = $1")
+        .bind(&email)
+        .execute(&db.pool)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to remove email suppression of {}: {}", email, e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?
+        .rows_affected();
+    if removed == 0 {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    Ok(Json(json!({ "success": true, "data": { "email": email } })))
+}
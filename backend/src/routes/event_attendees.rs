@@ -0,0 +1,712 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::{header, StatusCode},
+    response::{IntoResponse, Json, Response},
+    routing::{delete, get, post},
+    Router,
+};
+use std::collections::HashMap;
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sqlx::{postgres::PgRow, Row};
+use uuid::Uuid;
+
+use crate::{
+    auth::Claims,
+    database::Database,
+    routes::events::{
+        ensure_event_host, ensure_event_manager, invalidate_event_cache, ticket_code,
+    },
+    routes::stripe::{stripe_secret, stripe_url},
+};
+
+/// Upper bound on attendees in a single CSV export.
+const EXPORT_LIMIT: i64 = 10_000;
+/// Upper bound on scans uploaded by a scanner in one sync request.
+const MAX_SYNC_SCANS: usize = 500;
+/// Scanner clocks drift; scans this far ahead of the server are still accepted.
+const SCAN_CLOCK_SKEW_MINUTES: i64 = 5;
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AttendeeQuery {
+    search: Option<String>,
+    status: Option<String>,
+    checked_in: Option<bool>,
+    page: Option<u32>,
+    limit: Option<u32>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CheckInSyncRequest {
+    scans: Vec<TicketScan>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TicketScan {
+    ticket_code: String,
+    scanned_at: String,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct RevokeQuery {
+    refund: Option<bool>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Attendee {
+    user_id: String,
+    name: Option<String>,
+    username: Option<String>,
+    email: Option<String>,
+    avatar: Option<String>,
+    status: String,
+    is_paid: bool,
+    amount_paid: Option<f64>,
+    ticket_type_id: Option<Uuid>,
+    ticket_type_name: Option<String>,
+    guest_count: i32,
+    checked_in: bool,
+    checked_in_at: Option<DateTime<Utc>>,
+    refunded_at: Option<DateTime<Utc>>,
+    revoked_at: Option<DateTime<Utc>>,
+    rsvp_at: Option<DateTime<Utc>>,
+}
+
+impl Attendee {
+    fn from_row(row: &PgRow) -> Self {
+        let checked_in_at: Option<DateTime<Utc>> = row.try_get("checked_in_at").unwrap_or(None);
+        Attendee {
+            user_id: row.get("user_id"),
+            name: row.try_get("display_name").unwrap_or(None),
+            username: row.try_get("username").unwrap_or(None),
+            email: row.try_get("email").unwrap_or(None),
+            avatar: row.try_get("avatar_url").unwrap_or(None),
+            status: row.get("status"),
+            is_paid: row
+                .try_get::<Option<bool>, _>("is_paid")
+                .unwrap_or(None)
+                .unwrap_or(false),
+            amount_paid: row.try_get("amount_paid").unwrap_or(None),
+            ticket_type_id: row.try_get("ticket_type_id").unwrap_or(None),
+            ticket_type_name: row.try_get("ticket_type_name").unwrap_or(None),
+            guest_count: row
+                .try_get::<Option<i32>, _>("guest_count")
+                .unwrap_or(None)
+                .unwrap_or(0),
+            checked_in: checked_in_at.is_some(),
+            checked_in_at,
+            refunded_at: row.try_get("refunded_at").unwrap_or(None),
+            revoked_at: row.try_get("revoked_at").unwrap_or(None),
+            rsvp_at: row.try_get("created_at").unwrap_or(None),
+        }
+    }
+
+    fn payment_state(&self) -> &'static str {
+        if self.refunded_at.is_some() {
+            "REFUNDED"
+        } else if self.is_paid {
+            "PAID"
+        } else {
+            "UNPAID"
+        }
+    }
+}
+
+pub fn attendee_routes() -> Router<Database> {
+    Router::new()
+        .route("/:id/attendees", get(list_attendees))
+        .route("/:id/attendees/export", get(export_attendees))
+        .route(
+            "/:id/attendees/:user_id/check-in",
+            post(check_in_attendee).delete(undo_check_in),
+        )
+        .route("/:id/attendees/:user_id", delete(revoke_attendee))
+        .route("/:id/check-ins/sync", post(sync_check_ins))
+}
+
+const ATTENDEE_SELECT: &str = r#"
+    SELECT r.user_id, r.status, r.is_paid, r.amount_paid, r.ticket_type_id, r.guest_count,
+           r.checked_in_at, r.refunded_at, r.revoked_at, r.created_at,
+           u.display_name, u.username, u.email, u.avatar_url,
+           t.name AS ticket_type_name
+    FROM event_rsvps r
+    LEFT JOIN users u ON u.id = r.user_id
+    LEFT JOIN event_ticket_types t ON t.id = r.ticket_type_id
+    WHERE r.event_id = $1
+      AND ($2::TEXT IS NULL
+           OR u.display_name ILIKE $2 OR u.username ILIKE $2 OR u.email ILIKE $2)
+      AND ($3::TEXT IS NULL OR UPPER(TRIM(r.status)) = $3)
+      AND ($4::BOOLEAN IS NULL OR (r.checked_in_at IS NOT NULL) = $4)
+"#;
+
+async fn list_attendees(
+    State(db): State<Database>,
+    Path(event_id): Path<String>,
+    Query(params): Query<AttendeeQuery>,
+    claims: Claims,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    ensure_event_manager(&db, &event_id, &claims.sub).await?;
+
+    let page = params.page.unwrap_or(1).max(1);
+    let limit = params.limit.unwrap_or(50).clamp(1, 200);
+    let offset = ((page - 1) * limit) as i64;
+    let search = search_pattern(params.search.as_deref());
+    let status = params.status.as_deref().map(|s| s.trim().to_uppercase());
+
+    let rows = sqlx::query(&format!(
+        "{} ORDER BY r.created_at ASC LIMIT $5 OFFSET $6",
+        ATTENDEE_SELECT
+    ))
+    .bind(&event_id)
+    .bind(&search)
+    .bind(&status)
+    .bind(params.checked_in)
+    .bind(limit as i64)
+    .bind(offset)
+    .fetch_all(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to list attendees of event {}: {}", event_id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    let total = sqlx::query_scalar::<_, i64>(&format!(
+        "SELECT COUNT(*)::BIGINT FROM ({}) filtered",
+        ATTENDEE_SELECT
+    ))
+    .bind(&event_id)
+    .bind(&search)
+    .bind(&status)
+    .bind(params.checked_in)
+    .fetch_one(&db.pool)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let summary = sqlx::query(
+        r#"
+        SELECT
+            COUNT(*) FILTER (WHERE UPPER(TRIM(status)) = 'GOING')::BIGINT AS going,
+            COALESCE(SUM(guest_count) FILTER (WHERE UPPER(TRIM(status)) = 'GOING'), 0)::BIGINT AS guests,
+            COUNT(*) FILTER (WHERE UPPER(TRIM(status)) = 'MAYBE')::BIGINT AS maybe,
+            COUNT(*) FILTER (WHERE checked_in_at IS NOT NULL)::BIGINT AS checked_in,
+            COUNT(*) FILTER (WHERE is_paid AND refunded_at IS NULL)::BIGINT AS paid,
+            COUNT(*) FILTER (WHERE revoked_at IS NOT NULL)::BIGINT AS revoked
+        FROM event_rsvps
+        WHERE event_id = $1
+        "#,
+    )
+    .bind(&event_id)
+    .fetch_one(&db.pool)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let attendees: Vec<serde_json::Value> = rows
+        .iter()
+        .map(|row| {
+            let attendee = Attendee::from_row(row);
+            let payment_state = attendee.payment_state();
+            let mut value = serde_json::to_value(attendee).unwrap_or_default();
+            value["paymentState"] = json!(payment_state);
+            value
+        })
+        .collect();
+
+    Ok(Json(json!({
+        "success": true,
+        "data": attendees,
+        "summary": {
+            "going": summary.get::<i64, _>("going"),
+            "guests": summary.get::<i64, _>("guests"),
+            "seats": summary.get::<i64, _>("going") + summary.get::<i64, _>("guests"),
+            "maybe": summary.get::<i64, _>("maybe"),
+            "checkedIn": summary.get::<i64, _>("checked_in"),
+            "paid": summary.get::<i64, _>("paid"),
+            "revoked": summary.get::<i64, _>("revoked"),
+        },
+        "pagination": {
+            "page": page,
+            "limit": limit,
+            "total": total,
+            "pages": ((total as f64) / (limit as f64)).ceil() as u32,
+        }
+    })))
+}
+
+async fn export_attendees(
+    State(db): State<Database>,
+    Path(event_id): Path<String>,
+    Query(params): Query<AttendeeQuery>,
+    claims: Claims,
+) -> Result<Response, StatusCode> {
+    ensure_event_manager(&db, &event_id, &claims.sub).await?;
+
+    let rows = sqlx::query(&format!(
+        "{} ORDER BY r.created_at ASC LIMIT $5",
+        ATTENDEE_SELECT
+    ))
+    .bind(&event_id)
+    .bind(search_pattern(params.search.as_deref()))
+    .bind(params.status.as_deref().map(|s| s.trim().to_uppercase()))
+    .bind(params.checked_in)
+    .bind(EXPORT_LIMIT)
+    .fetch_all(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to export attendees of event {}: {}", event_id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    let write_error = |e: csv::Error| {
+        tracing::error!("Failed to write attendee CSV: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    };
+
+    writer
+        .write_record([
+            "User ID",
+            "Name",
+            "Username",
+            "Email",
+            "RSVP status",
+            "Ticket type",
+            "Guests",
+            "Payment",
+            "Amount paid",
+            "Checked in at",
+            "RSVP at",
+        ])
+        .map_err(write_error)?;
+
+    let format_time = |time: Option<DateTime<Utc>>| time.map(|t| t.to_rfc3339()).unwrap_or_default();
+    for row in &rows {
+        let attendee = Attendee::from_row(row);
+        writer
+            .write_record([
+                attendee.user_id.clone(),
+                attendee.name.clone().unwrap_or_default(),
+                attendee.username.clone().unwrap_or_default(),
+                attendee.email.clone().unwrap_or_default(),
+                attendee.status.clone(),
+                attendee.ticket_type_name.clone().unwrap_or_default(),
+                attendee.guest_count.to_string(),
+                attendee.payment_state().to_string(),
+                attendee
+                    .amount_paid
+                    .map(|amount| format!("{:.2}", amount))
+                    .unwrap_or_default(),
+                format_time(attendee.checked_in_at),
+                format_time(attendee.rsvp_at),
+            ])
+            .map_err(write_error)?;
+    }
+
+    let body = writer.into_inner().map_err(|e| {
+        tracing::error!("Failed to finish attendee CSV: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, "text/csv; charset=utf-8".to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"event-{}-attendees.csv\"", event_id),
+            ),
+        ],
+        body,
+    )
+        .into_response())
+}
+
+async fn check_in_attendee(
+    State(db): State<Database>,
+    Path((event_id, user_id)): Path<(String, String)>,
+    claims: Claims,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    ensure_event_manager(&db, &event_id, &claims.sub).await?;
+
+    // Only confirmed attendees can be checked in; re-checking keeps the first timestamp.
+    let checked_in_at = sqlx::query_scalar::<_, Option<DateTime<Utc>>>(
+        r#"
+        UPDATE event_rsvps
+        SET checked_in_at = COALESCE(checked_in_at, NOW()),
+            checked_in_by = COALESCE(checked_in_by, $3),
+            updated_at = NOW()
+        WHERE event_id = $1 AND user_id = $2 AND UPPER(TRIM(status)) = 'GOING'
+        RETURNING checked_in_at
+        "#,
+    )
+    .bind(&event_id)
+    .bind(&user_id)
+    .bind(&claims.sub)
+    .fetch_optional(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to check in {} at event {}: {}", user_id, event_id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?
+    .ok_or(StatusCode::NOT_FOUND)?;
+
+    Ok(Json(json!({
+        "success": true,
+        "data": {
+            "userId": user_id,
+            "checkedIn": true,
+            "checkedInAt": checked_in_at
+        }
+    })))
+}
+
+async fn undo_check_in(
+    State(db): State<Database>,
+    Path((event_id, user_id)): Path<(String, String)>,
+    claims: Claims,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    ensure_event_manager(&db, &event_id, &claims.sub).await?;
+
+    let result = sqlx::query(
+        r#"
+        UPDATE event_rsvps
+        SET checked_in_at = NULL, checked_in_by = NULL, updated_at = NOW()
+        WHERE event_id = $1 AND user_id = $2
+        "#,
+    )
+    .bind(&event_id)
+    .bind(&user_id)
+    .execute(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to undo check-in of {} at event {}: {}", user_id, event_id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    if result.rows_affected() == 0 {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    Ok(Json(json!({
+        "success": true,
+        "data": {
+            "userId": user_id,
+            "checkedIn": false,
+            "checkedInAt": serde_json::Value::Null
+        }
+    })))
+}
+
+/// Revoke an attendee's RSVP. With `?refund=true` a paid ticket is refunded through Stripe
+/// first; the RSVP is only revoked once the refund went through.
+async fn revoke_attendee(
+    State(db): State<Database>,
+    Path((event_id, user_id)): Path<(String, String)>,
+    Query(params): Query<RevokeQuery>,
+    claims: Claims,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    ensure_event_host(&db, &event_id, &claims.sub).await?;
+
+    let rsvp = sqlx::query(
+        r#"
+        SELECT is_paid, payment_intent_id, refunded_at
+        FROM event_rsvps
+        WHERE event_id = $1 AND user_id = $2
+        "#,
+    )
+    .bind(&event_id)
+    .bind(&user_id)
+    .fetch_optional(&db.pool)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    .ok_or(StatusCode::NOT_FOUND)?;
+
+    let is_paid: bool = rsvp
+        .try_get::<Option<bool>, _>("is_paid")
+        .unwrap_or(None)
+        .unwrap_or(false);
+    let payment_intent_id: Option<String> = rsvp.try_get("payment_intent_id").unwrap_or(None);
+    let already_refunded = rsvp
+        .try_get::<Option<DateTime<Utc>>, _>("refunded_at")
+        .unwrap_or(None)
+        .is_some();
+
+    let mut refund_id = None;
+    if params.refund.unwrap_or(false) && is_paid && !already_refunded {
+        // Tickets paid before payment intents were recorded cannot be refunded from here.
+        let payment_intent_id = payment_intent_id.ok_or(StatusCode::UNPROCESSABLE_ENTITY)?;
+        refund_id = Some(refund_payment_intent(&payment_intent_id).await?);
+    }
+
+    sqlx::query(
+        r#"
+        UPDATE event_rsvps
+        SET status = 'REVOKED',
+            revoked_at = NOW(),
+            checked_in_at = NULL,
+            refunded_at = CASE WHEN $3 THEN NOW() ELSE refunded_at END,
+            updated_at = NOW()
+        WHERE event_id = $1 AND user_id = $2
+        "#,
+    )
+    .bind(&event_id)
+    .bind(&user_id)
+    .bind(refund_id.is_some())
+    .execute(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to revoke RSVP of {} at event {}: {}", user_id, event_id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    invalidate_event_cache(&db, &event_id).await;
+
+    Ok(Json(json!({
+        "success": true,
+        "data": {
+            "userId": user_id,
+            "status": "REVOKED",
+            "refunded": refund_id.is_some() || already_refunded,
+            "refundId": refund_id
+        }
+    })))
+}
+
+async fn refund_payment_intent(payment_intent_id: &str) -> Result<String, StatusCode> {
+    let stripe_secret = stripe_secret()?;
+
+    let response = reqwest::Client::new()
+        .post(stripe_url("/v1/refunds"))
+        .header("Authorization", format!("Bearer {}", stripe_secret))
+        .form(&[("payment_intent", payment_intent_id)])
+        .send()
+        .await
+        .map_err(|err| {
+            tracing::error!("Failed to create Stripe refund: {:?}", err);
+            StatusCode::BAD_GATEWAY
+        })?;
+
+    if !response.status().is_success() {
+        let body = response.text().await.unwrap_or_default();
+        tracing::error!("Stripe refund failed: {}", body);
+        return Err(StatusCode::BAD_GATEWAY);
+    }
+
+    let refund: serde_json::Value = response.json().await.map_err(|err| {
+        tracing::error!("Failed to parse Stripe refund: {:?}", err);
+        StatusCode::BAD_GATEWAY
+    })?;
+
+    refund
+        .get("id")
+        .and_then(|v| v.as_str())
+        .map(str::to_string)
+        .ok_or(StatusCode::BAD_GATEWAY)
+}
+
+fn search_pattern(search: Option<&str>) -> Option<String> {
+    search
+        .map(str::trim)
+        .filter(|term| !term.is_empty())
+        .map(|term| format!("%{}%", term.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_")))
+}
+
+struct SyncAttendee {
+    user_id: String,
+    going: bool,
+    revoked: bool,
+    guest_count: i32,
+}
+
+/// Replay scans collected by scanner apps while offline. The earliest scan of a ticket wins,
+/// so uploading the same batch again, or overlapping batches from several devices, gives the
+/// same result. Each scan gets its own outcome instead of failing the whole batch.
+async fn sync_check_ins(
+    State(db): State<Database>,
+    Path(event_id): Path<String>,
+    claims: Claims,
+    Json(payload): Json<CheckInSyncRequest>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    ensure_event_manager(&db, &event_id, &claims.sub).await?;
+
+    if payload.scans.len() > MAX_SYNC_SCANS {
+        return Err(StatusCode::PAYLOAD_TOO_LARGE);
+    }
+
+    let rows = sqlx::query(
+        r#"
+        SELECT user_id, status, revoked_at, checked_in_at, guest_count
+        FROM event_rsvps
+        WHERE event_id = $1
+        "#,
+    )
+    .bind(&event_id)
+    .fetch_all(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to load attendees of event {} for sync: {}", event_id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    // Ticket codes only carry short id prefixes, so one code can match several attendees.
+    let mut by_code: HashMap<String, Vec<SyncAttendee>> = HashMap::new();
+    let mut checked_in: HashMap<(String, i32), DateTime<Utc>> = HashMap::new();
+    for row in &rows {
+        let user_id: String = row.get("user_id");
+        let status: String = row.get("status");
+        if let Some(at) = row.try_get::<Option<DateTime<Utc>>, _>("checked_in_at").unwrap_or(None) {
+            checked_in.insert((user_id.clone(), 0), at);
+        }
+        by_code
+            .entry(ticket_code(&event_id, &user_id))
+            .or_default()
+            .push(SyncAttendee {
+                user_id,
+                going: status.trim().eq_ignore_ascii_case("GOING"),
+                revoked: row
+                    .try_get::<Option<DateTime<Utc>>, _>("revoked_at")
+                    .unwrap_or(None)
+                    .is_some(),
+                guest_count: row
+                    .try_get::<Option<i32>, _>("guest_count")
+                    .unwrap_or(None)
+                    .unwrap_or(0),
+            });
+    }
+
+    let guest_rows = sqlx::query(
+        "SELECT user_id, guest_number, checked_in_at FROM event_guest_check_ins WHERE event_id = $1",
+    )
+    .bind(&event_id)
+    .fetch_all(&db.pool)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    for row in &guest_rows {
+        checked_in.insert(
+            (row.get("user_id"), row.get("guest_number")),
+            row.get("checked_in_at"),
+        );
+    }
+
+    let latest_accepted = Utc::now() + Duration::minutes(SCAN_CLOCK_SKEW_MINUTES);
+    let mut results = Vec::with_capacity(payload.scans.len());
+    let mut changed = 0;
+
+    for scan in &payload.scans {
+        let code = scan.ticket_code.trim().to_uppercase();
+        let outcome = |status: &str| json!({ "ticketCode": code, "status": status });
+
+        let Some(scanned_at) = DateTime::parse_from_rfc3339(scan.scanned_at.trim())
+            .ok()
+            .map(|at| at.with_timezone(&Utc))
+            .filter(|at| *at <= latest_accepted)
+        else {
+            results.push(outcome("INVALID_TIMESTAMP"));
+            continue;
+        };
+
+        let (base_code, guest_number) = match code.rsplit_once("-G") {
+            Some((base, number)) if base.matches('-').count() == 2 => match number.parse::<i32>() {
+                Ok(number) if number > 0 => (base, number),
+                _ => {
+                    results.push(outcome("NOT_FOUND"));
+                    continue;
+                }
+            },
+            _ => (code.as_str(), 0),
+        };
+
+        let attendee = match by_code.get(base_code).map(Vec::as_slice) {
+            Some([attendee]) => attendee,
+            Some([_, _, ..]) => {
+                results.push(outcome("AMBIGUOUS"));
+                continue;
+            }
+            _ => {
+                results.push(outcome("NOT_FOUND"));
+                continue;
+            }
+        };
+        if attendee.revoked {
+            results.push(outcome("REVOKED"));
+            continue;
+        }
+        if !attendee.going || guest_number > attendee.guest_count {
+            results.push(outcome("NOT_ATTENDING"));
+            continue;
+        }
+
+        let key = (attendee.user_id.clone(), guest_number);
+        let previous = checked_in.get(&key).copied();
+        let resolved = match previous {
+            Some(previous) if previous <= scanned_at => previous,
+            _ => {
+                let stored = if guest_number == 0 {
+                    sqlx::query_scalar::<_, Option<DateTime<Utc>>>(
+                        r#"
+                        UPDATE event_rsvps
+                        SET checked_in_at = LEAST(COALESCE(checked_in_at, $3), $3),
+                            checked_in_by = COALESCE(checked_in_by, $4),
+                            updated_at = NOW()
+                        WHERE event_id = $1 AND user_id = $2
+                        RETURNING checked_in_at
+                        "#,
+                    )
+                    .bind(&event_id)
+                    .bind(&attendee.user_id)
+                    .bind(scanned_at)
+                    .bind(&claims.sub)
+                    .fetch_one(&db.pool)
+                    .await
+                    .map(|at| at.unwrap_or(scanned_at))
+                } else {
+                    sqlx::query_scalar::<_, DateTime<Utc>>(
+                        r#"
+                        INSERT INTO event_guest_check_ins (event_id, user_id, guest_number, checked_in_at, checked_in_by)
+                        VALUES ($1, $2, $3, $4, $5)
+                        ON CONFLICT (event_id, user_id, guest_number)
+                        DO UPDATE SET checked_in_at = LEAST(event_guest_check_ins.checked_in_at, EXCLUDED.checked_in_at)
+                        RETURNING checked_in_at
+                        "#,
+                    )
+                    .bind(&event_id)
+                    .bind(&attendee.user_id)
+                    .bind(guest_number)
+                    .bind(scanned_at)
+                    .bind(&claims.sub)
+                    .fetch_one(&db.pool)
+                    .await
+                };
+                let stored = stored.map_err(|e| {
+                    tracing::error!("Failed to sync check-in {} at event {}: {}", code, event_id, e);
+                    StatusCode::INTERNAL_SERVER_ERROR
+                })?;
+                checked_in.insert(key, stored);
+                changed += 1;
+                stored
+            }
+        };
+
+        results.push(json!({
+            "ticketCode": code,
+            "status": if previous.is_some() { "ALREADY_CHECKED_IN" } else { "CHECKED_IN" },
+            "userId": attendee.user_id,
+            "guestNumber": (guest_number > 0).then_some(guest_number),
+            "checkedInAt": resolved,
+        }));
+    }
+
+    if changed > 0 {
+        invalidate_event_cache(&db, &event_id).await;
+    }
+
+    Ok(Json(json!({
+        "success": true,
+        "data": results,
+        "syncedAt": Utc::now(),
+    })))
+}
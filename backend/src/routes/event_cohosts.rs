@@ -0,0 +1,156 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::Json,
+    routing::{delete, get},
+    Router,
+};
+use serde::Deserialize;
+use serde_json::json;
+use sqlx::Row;
+
+use crate::{
+    auth::Claims,
+    database::Database,
+    routes::events::{ensure_event_host, ensure_event_manager},
+};
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AddCohostRequest {
+    user_id: Option<String>,
+    username: Option<String>,
+}
+
+pub fn cohost_routes() -> Router<Database> {
+    Router::new()
+        .route("/:id/cohosts", get(list_cohosts).post(add_cohost))
+        .route("/:id/cohosts/:user_id", delete(remove_cohost))
+}
+
+async fn list_cohosts(
+    State(db): State<Database>,
+    Path(event_id): Path<String>,
+    claims: Claims,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    ensure_event_manager(&db, &event_id, &claims.sub).await?;
+
+    let rows = sqlx::query(
+        r#"
+        SELECT c.user_id, c.created_at, u.display_name, u.username, u.avatar_url
+        FROM event_cohosts c
+        JOIN users u ON u.id = c.user_id
+        WHERE c.event_id = $1
+        ORDER BY c.created_at ASC
+        "#,
+    )
+    .bind(&event_id)
+    .fetch_all(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to list co-hosts of event {}: {}", event_id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    let cohosts: Vec<serde_json::Value> = rows
+        .iter()
+        .map(|row| {
+            json!({
+                "id": row.get::<String, _>("user_id"),
+                "name": row.try_get::<Option<String>, _>("display_name").unwrap_or(None),
+                "username": row.try_get::<Option<String>, _>("username").unwrap_or(None),
+                "avatar": row.try_get::<Option<String>, _>("avatar_url").unwrap_or(None),
+                "addedAt": row.try_get::<Option<chrono::DateTime<chrono::Utc>>, _>("created_at").unwrap_or(None),
+            })
+        })
+        .collect();
+
+    Ok(Json(json!({
+        "success": true,
+        "data": cohosts
+    })))
+}
+
+// Only the host can add co-hosts, by user id or username
+async fn add_cohost(
+    State(db): State<Database>,
+    Path(event_id): Path<String>,
+    claims: Claims,
+    Json(payload): Json<AddCohostRequest>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    ensure_event_host(&db, &event_id, &claims.sub).await?;
+
+    let user = sqlx::query(
+        r#"
+        SELECT id, display_name, username, avatar_url
+        FROM users
+        WHERE ($1::TEXT IS NOT NULL AND id = $1) OR ($2::TEXT IS NOT NULL AND username = $2)
+        LIMIT 1
+        "#,
+    )
+    .bind(payload.user_id.as_deref().map(str::trim))
+    .bind(payload.username.as_deref().map(|name| name.trim().trim_start_matches('@')))
+    .fetch_optional(&db.pool)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    .ok_or(StatusCode::NOT_FOUND)?;
+
+    let user_id: String = user.get("id");
+    if user_id == claims.sub {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    sqlx::query(
+        r#"
+        INSERT INTO event_cohosts (event_id, user_id, added_by)
+        VALUES ($1, $2, $3)
+        ON CONFLICT (event_id, user_id) DO NOTHING
+        "#,
+    )
+    .bind(&event_id)
+    .bind(&user_id)
+    .bind(&claims.sub)
+    .execute(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to add co-host to event {}: {}", event_id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    Ok(Json(json!({
+        "success": true,
+        "data": {
+            "id": user_id,
+            "name": user.try_get::<Option<String>, _>("display_name").unwrap_or(None),
+            "username": user.try_get::<Option<String>, _>("username").unwrap_or(None),
+            "avatar": user.try_get::<Option<String>, _>("avatar_url").unwrap_or(None),
+        }
+    })))
+}
+
+// The host can remove any co-host; co-hosts can step down themselves
+async fn remove_cohost(
+    State(db): State<Database>,
+    Path((event_id, user_id)): Path<(String, String)>,
+    claims: Claims,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    if user_id != claims.sub {
+        ensure_event_host(&db, &event_id, &claims.sub).await?;
+    }
+
+    let result = sqlx::query("DELETE FROM event_cohosts WHERE event_id = $1 AND user_id = $2")
+        .bind(&event_id)
+        .bind(&user_id)
+        .execute(&db.pool)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    if result.rows_affected() == 0 {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    Ok(Json(json!({
+        "success": true,
+        "message": "Co-host removed"
+    })))
+}
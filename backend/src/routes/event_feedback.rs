@@ -0,0 +1,253 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::Json,
+    routing::get,
+    Router,
+};
+use serde::Deserialize;
+use serde_json::json;
+use sqlx::Row;
+
+use crate::{auth::Claims, database::Database, routes::events::ensure_event_host};
+
+/// Longest comment accepted with a rating.
+const MAX_COMMENT_LENGTH: usize = 2000;
+
+#[derive(Debug, Deserialize)]
+struct FeedbackRequest {
+    rating: i16,
+    comment: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct FeedbackQuery {
+    page: Option<u32>,
+    limit: Option<u32>,
+}
+
+pub fn feedback_routes() -> Router<Database> {
+    Router::new().route("/:id/feedback", get(list_feedback).post(submit_feedback))
+}
+
+// Attendees can rate an event once it has ended, one submission each
+async fn submit_feedback(
+    State(db): State<Database>,
+    Path(event_id): Path<String>,
+    claims: Claims,
+    Json(payload): Json<FeedbackRequest>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    if !(1..=5).contains(&payload.rating) {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let comment = payload
+        .comment
+        .map(|comment| comment.trim().to_string())
+        .filter(|comment| !comment.is_empty());
+    if comment.as_ref().is_some_and(|comment| comment.chars().count() > MAX_COMMENT_LENGTH) {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let row = sqlx::query(
+        r#"
+        SELECT
+            e.host_id,
+            COALESCE(e.end_time, e.start_time) < NOW() AS has_ended,
+            EXISTS(
+                SELECT 1 FROM event_rsvps r
+                WHERE r.event_id = e.id::TEXT AND r.user_id = $2 AND r.revoked_at IS NULL
+                  AND (UPPER(TRIM(r.status)) = 'GOING' OR r.checked_in_at IS NOT NULL)
+            ) AS attended
+        FROM events e
+        WHERE e.id::TEXT = $1
+        LIMIT 1
+        "#,
+    )
+    .bind(&event_id)
+    .bind(&claims.sub)
+    .fetch_optional(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to load event {} for feedback: {}", event_id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?
+    .ok_or(StatusCode::NOT_FOUND)?;
+
+    let host_id: String = row.get("host_id");
+    let has_ended: bool = row
+        .try_get::<Option<bool>, _>("has_ended")
+        .unwrap_or(None)
+        .unwrap_or(false);
+    let attended: bool = row.get("attended");
+    if host_id == claims.sub || !attended {
+        return Err(StatusCode::FORBIDDEN);
+    }
+    if !has_ended {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let feedback = sqlx::query(
+        r#"
+        INSERT INTO event_feedback (event_id, user_id, rating, comment)
+        VALUES ($1, $2, $3, $4)
+        ON CONFLICT (event_id, user_id) DO NOTHING
+        RETURNING id, rating, comment, created_at
+        "#,
+    )
+    .bind(&event_id)
+    .bind(&claims.sub)
+    .bind(payload.rating)
+    .bind(&comment)
+    .fetch_optional(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to save feedback for event {}: {}", event_id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?
+    .ok_or(StatusCode::CONFLICT)?;
+
+    Ok(Json(json!({
+        "success": true,
+        "data": {
+            "id": feedback.get::<uuid::Uuid, _>("id"),
+            "rating": feedback.get::<i16, _>("rating"),
+            "comment": feedback.try_get::<Option<String>, _>("comment").unwrap_or(None),
+            "createdAt": feedback.try_get::<Option<chrono::DateTime<chrono::Utc>>, _>("created_at").unwrap_or(None),
+        }
+    })))
+}
+
+// Only the host reads individual feedback; everyone else sees the aggregate on the profile
+async fn list_feedback(
+    State(db): State<Database>,
+    Path(event_id): Path<String>,
+    Query(params): Query<FeedbackQuery>,
+    claims: Claims,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    ensure_event_host(&db, &event_id, &claims.sub).await?;
+
+    let page = params.page.unwrap_or(1).max(1);
+    let limit = params.limit.unwrap_or(20).clamp(1, 100);
+    let offset = ((page - 1) * limit) as i64;
+
+    let rows = sqlx::query(
+        r#"
+        SELECT f.id, f.user_id, f.rating, f.comment, f.created_at,
+               u.display_name, u.username, u.avatar_url
+        FROM event_feedback f
+        LEFT JOIN users u ON u.id = f.user_id
+        WHERE f.event_id = $1
+        ORDER BY f.created_at DESC
+        LIMIT $2 OFFSET $3
+        "#,
+    )
+    .bind(&event_id)
+    .bind(limit as i64)
+    .bind(offset)
+    .fetch_all(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to list feedback of event {}: {}", event_id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    let summary = sqlx::query(
+        r#"
+        SELECT
+            COUNT(*)::BIGINT AS count,
+            AVG(rating)::DOUBLE PRECISION AS average,
+            COUNT(*) FILTER (WHERE rating = 1)::BIGINT AS one,
+            COUNT(*) FILTER (WHERE rating = 2)::BIGINT AS two,
+            COUNT(*) FILTER (WHERE rating = 3)::BIGINT AS three,
+            COUNT(*) FILTER (WHERE rating = 4)::BIGINT AS four,
+            COUNT(*) FILTER (WHERE rating = 5)::BIGINT AS five
+        FROM event_feedback
+        WHERE event_id = $1
+        "#,
+    )
+    .bind(&event_id)
+    .fetch_one(&db.pool)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let total: i64 = summary.get("count");
+    let feedback: Vec<serde_json::Value> = rows
+        .iter()
+        .map(|row| {
+            json!({
+                "id": row.get::<uuid::Uuid, _>("id"),
+                "rating": row.get::<i16, _>("rating"),
+                "comment": row.try_get::<Option<String>, _>("comment").unwrap_or(None),
+                "createdAt": row.try_get::<Option<chrono::DateTime<chrono::Utc>>, _>("created_at").unwrap_or(None),
+                "user": {
+                    "id": row.get::<String, _>("user_id"),
+                    "name": row.try_get::<Option<String>, _>("display_name").unwrap_or(None),
+                    "username": row.try_get::<Option<String>, _>("username").unwrap_or(None),
+                    "avatar": row.try_get::<Option<String>, _>("avatar_url").unwrap_or(None),
+                },
+            })
+        })
+        .collect();
+
+    Ok(Json(json!({
+        "success": true,
+        "data": feedback,
+        "summary": {
+            "count": total,
+            "averageRating": summary
+                .try_get::<Option<f64>, _>("average")
+                .unwrap_or(None)
+                .map(round_rating),
+            "distribution": {
+                "1": summary.get::<i64, _>("one"),
+                "2": summary.get::<i64, _>("two"),
+                "3": summary.get::<i64, _>("three"),
+                "4": summary.get::<i64, _>("four"),
+                "5": summary.get::<i64, _>("five"),
+            },
+        },
+        "pagination": {
+            "page": page,
+            "limit": limit,
+            "total": total,
+            "pages": ((total as f64) / (limit as f64)).ceil() as u32,
+        }
+    })))
+}
+
+/// Average rating and number of ratings across every event a user has hosted.
+pub(crate) async fn host_rating_summary(
+    db: &Database,
+    host_id: &str,
+) -> Result<serde_json::Value, StatusCode> {
+    let row = sqlx::query(
+        r#"
+        SELECT COUNT(f.id)::BIGINT AS count,
+               AVG(f.rating)::DOUBLE PRECISION AS average,
+               COUNT(DISTINCT f.event_id)::BIGINT AS events
+        FROM event_feedback f
+        JOIN events e ON e.id::TEXT = f.event_id
+        WHERE e.host_id = $1
+        "#,
+    )
+    .bind(host_id)
+    .fetch_one(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to aggregate event ratings for {}: {}", host_id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    Ok(json!({
+        "averageRating": row
+            .try_get::<Option<f64>, _>("average")
+            .unwrap_or(None)
+            .map(round_rating),
+        "ratingCount": row.get::<i64, _>("count"),
+        "ratedEvents": row.get::<i64, _>("events"),
+    }))
+}
+
+fn round_rating(average: f64) -> f64 {
+    (average * 100.0).round() / 100.0
+}
@@ -0,0 +1,285 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::Json,
+    routing::{delete, get},
+    Router,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sqlx::{Postgres, Row, Transaction};
+use uuid::Uuid;
+
+use crate::{auth::Claims, database::Database, routes::events::ensure_event_manager};
+
+/// An invite link to a non-public event. The token is what the link carries.
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct EventInvite {
+    pub id: Uuid,
+    pub event_id: String,
+    pub token: String,
+    pub label: Option<String>,
+    /// `None` means the link can be used any number of times.
+    pub max_uses: Option<i32>,
+    pub use_count: i32,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub revoked_at: Option<DateTime<Utc>>,
+    pub created_by: Option<String>,
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+impl EventInvite {
+    fn is_usable(&self, now: DateTime<Utc>) -> bool {
+        self.revoked_at.is_none()
+            && self.expires_at.is_none_or(|expires_at| expires_at > now)
+            && self.max_uses.is_none_or(|max_uses| self.use_count < max_uses)
+    }
+
+    fn to_json(&self) -> serde_json::Value {
+        let mut value = serde_json::to_value(self).unwrap_or_default();
+        value["remainingUses"] = json!(self
+            .max_uses
+            .map(|max_uses| (max_uses - self.use_count).max(0)));
+        value["isActive"] = json!(self.is_usable(Utc::now()));
+        value
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CreateInviteRequest {
+    label: Option<String>,
+    max_uses: Option<i32>,
+    expires_at: Option<String>,
+}
+
+pub fn invite_routes() -> Router<Database> {
+    Router::new()
+        .route("/:id/invites", get(list_invites).post(create_invite))
+        .route("/:id/invites/:invite_id", delete(revoke_invite))
+}
+
+async fn list_invites(
+    State(db): State<Database>,
+    Path(event_id): Path<String>,
+    claims: Claims,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    ensure_event_manager(&db, &event_id, &claims.sub).await?;
+
+    let invites = sqlx::query_as::<_, EventInvite>(
+        "SELECT * FROM event_invites WHERE event_id = $1 ORDER BY created_at DESC",
+    )
+    .bind(&event_id)
+    .fetch_all(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to list invites of event {}: {}", event_id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    Ok(Json(json!({
+        "success": true,
+        "data": invites.iter().map(EventInvite::to_json).collect::<Vec<_>>()
+    })))
+}
+
+// Invite links only exist for events that are not public
+async fn create_invite(
+    State(db): State<Database>,
+    Path(event_id): Path<String>,
+    claims: Claims,
+    payload: Option<Json<CreateInviteRequest>>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let Json(payload) = payload.unwrap_or_default();
+    ensure_event_manager(&db, &event_id, &claims.sub).await?;
+
+    let is_public = sqlx::query_scalar::<_, Option<bool>>(
+        "SELECT is_public FROM events WHERE id::TEXT = $1 LIMIT 1",
+    )
+    .bind(&event_id)
+    .fetch_one(&db.pool)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    .unwrap_or(true);
+    if is_public {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    if payload.max_uses.is_some_and(|max_uses| max_uses < 1) {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let expires_at = match payload.expires_at.as_deref() {
+        Some(raw) => {
+            let expires_at = DateTime::parse_from_rfc3339(raw)
+                .map_err(|_| StatusCode::BAD_REQUEST)?
+                .with_timezone(&Utc);
+            if expires_at <= Utc::now() {
+                return Err(StatusCode::BAD_REQUEST);
+            }
+            Some(expires_at)
+        }
+        None => None,
+    };
+    let label = payload
+        .label
+        .map(|label| label.trim().to_string())
+        .filter(|label| !label.is_empty());
+
+    let token = format!("{}{}", Uuid::new_v4().simple(), Uuid::new_v4().simple());
+
+    let invite = sqlx::query_as::<_, EventInvite>(
+        r#"
+        INSERT INTO event_invites (event_id, token, label, max_uses, expires_at, created_by)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        RETURNING *
+        "#,
+    )
+    .bind(&event_id)
+    .bind(&token)
+    .bind(&label)
+    .bind(payload.max_uses)
+    .bind(expires_at)
+    .bind(&claims.sub)
+    .fetch_one(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to create invite for event {}: {}", event_id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    Ok(Json(json!({
+        "success": true,
+        "data": invite.to_json()
+    })))
+}
+
+// Revoked links stay listed so hosts can see who they were handed to
+async fn revoke_invite(
+    State(db): State<Database>,
+    Path((event_id, invite_id)): Path<(String, Uuid)>,
+    claims: Claims,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    ensure_event_manager(&db, &event_id, &claims.sub).await?;
+
+    let invite = sqlx::query_as::<_, EventInvite>(
+        r#"
+        UPDATE event_invites
+        SET revoked_at = COALESCE(revoked_at, NOW())
+        WHERE id = $1 AND event_id = $2
+        RETURNING *
+        "#,
+    )
+    .bind(invite_id)
+    .bind(&event_id)
+    .fetch_optional(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to revoke invite {}: {}", invite_id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?
+    .ok_or(StatusCode::NOT_FOUND)?;
+
+    Ok(Json(json!({
+        "success": true,
+        "data": invite.to_json()
+    })))
+}
+
+/// Decide whether a user may RSVP to an event. Public events, managers and users who already
+/// hold an RSVP are let through; anyone else needs a usable invite token, whose id is returned
+/// so it can be redeemed together with the RSVP.
+pub(crate) async fn check_invite_access(
+    db: &Database,
+    event_id: &str,
+    user_id: &str,
+    token: Option<&str>,
+) -> Result<Option<Uuid>, StatusCode> {
+    let row = sqlx::query(
+        r#"
+        SELECT
+            COALESCE(e.is_public, TRUE) AS is_public,
+            e.host_id = $2
+                OR EXISTS(SELECT 1 FROM event_cohosts c WHERE c.event_id = e.id::TEXT AND c.user_id = $2)
+                OR EXISTS(SELECT 1 FROM event_rsvps r WHERE r.event_id = e.id::TEXT AND r.user_id = $2)
+                AS admitted
+        FROM events e
+        WHERE e.id::TEXT = $1
+        LIMIT 1
+        "#,
+    )
+    .bind(event_id)
+    .bind(user_id)
+    .fetch_optional(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to check invite access for event {}: {}", event_id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?
+    .ok_or(StatusCode::NOT_FOUND)?;
+
+    let is_public: bool = row.get("is_public");
+    let admitted: Option<bool> = row.try_get("admitted").unwrap_or(None);
+    if is_public || admitted == Some(true) {
+        return Ok(None);
+    }
+
+    let token = token
+        .map(str::trim)
+        .filter(|token| !token.is_empty())
+        .ok_or(StatusCode::FORBIDDEN)?;
+    let invite = sqlx::query_as::<_, EventInvite>(
+        "SELECT * FROM event_invites WHERE token = $1 AND event_id = $2 LIMIT 1",
+    )
+    .bind(token)
+    .bind(event_id)
+    .fetch_optional(&db.pool)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    .ok_or(StatusCode::FORBIDDEN)?;
+
+    if !invite.is_usable(Utc::now()) {
+        return Err(StatusCode::GONE);
+    }
+
+    Ok(Some(invite.id))
+}
+
+/// Count one use of an invite. With `enforce_limit` the use is refused once the link is
+/// revoked, expired or used up; paid RSVPs skip that because the attendee has already paid.
+pub(crate) async fn redeem_invite(
+    tx: &mut Transaction<'_, Postgres>,
+    invite_id: Uuid,
+    enforce_limit: bool,
+) -> Result<(), StatusCode> {
+    let result = sqlx::query(
+        r#"
+        UPDATE event_invites
+        SET use_count = use_count + 1
+        WHERE id = $1
+          AND (
+              NOT $2
+              OR (
+                  revoked_at IS NULL
+                  AND (expires_at IS NULL OR expires_at > NOW())
+                  AND (max_uses IS NULL OR use_count < max_uses)
+              )
+          )
+        "#,
+    )
+    .bind(invite_id)
+    .bind(enforce_limit)
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to redeem invite {}: {}", invite_id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    if result.rows_affected() == 0 && enforce_limit {
+        return Err(StatusCode::GONE);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,398 @@
+use axum::{
+    body::Bytes,
+    extract::{Path, State},
+    http::{HeaderMap, StatusCode},
+    response::Json,
+    routing::{get, post},
+    Router,
+};
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use serde::Deserialize;
+use serde_json::json;
+use sha2::Sha256;
+use sqlx::Row;
+
+use crate::{
+    auth::Claims,
+    database::Database,
+    routes::events::{ensure_event_host, invalidate_event_cache},
+};
+
+type HmacSha256 = Hmac<Sha256>;
+
+const STREAM_PROVIDERS: [&str; 3] = ["MUX", "YOUTUBE", "RTMP"];
+/// Mux webhooks older than this are rejected to limit replays.
+const WEBHOOK_TOLERANCE_SECONDS: i64 = 300;
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct StreamRequest {
+    provider: String,
+    /// Provider-side live stream id; Mux webhooks are matched on it.
+    stream_id: Option<String>,
+    playback_url: Option<String>,
+    /// Mux playback id, used to build the playback URL when none is given.
+    playback_id: Option<String>,
+    ingest_url: Option<String>,
+    stream_key: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct LiveRequest {
+    is_live: bool,
+}
+
+pub fn stream_routes() -> Router<Database> {
+    Router::new()
+        .route(
+            "/:id/stream",
+            get(get_stream).put(attach_stream).delete(detach_stream),
+        )
+        .route("/:id/stream/live", post(set_live))
+        .route("/stream/webhook", post(stream_webhook))
+}
+
+/// Playback details for the host and for attendees holding a valid ticket. Only the host
+/// sees the ingest URL and stream key.
+async fn get_stream(
+    State(db): State<Database>,
+    Path(event_id): Path<String>,
+    claims: Claims,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let row = sqlx::query(
+        r#"
+        SELECT e.host_id, e.price, e.stream_provider, e.stream_id, e.stream_playback_url,
+               e.stream_ingest_url, e.stream_key, e.is_live, e.live_started_at, e.live_ended_at,
+               r.status AS rsvp_status, r.is_paid, r.refunded_at, r.revoked_at,
+               t.price AS ticket_type_price,
+               EXISTS(
+                   SELECT 1 FROM event_cohosts c WHERE c.event_id = e.id::TEXT AND c.user_id = $2
+               ) AS is_cohost
+        FROM events e
+        LEFT JOIN event_rsvps r ON r.event_id = e.id::TEXT AND r.user_id = $2
+        LEFT JOIN event_ticket_types t ON t.id = r.ticket_type_id
+        WHERE e.id::TEXT = $1
+        "#,
+    )
+    .bind(&event_id)
+    .bind(&claims.sub)
+    .fetch_optional(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to load stream of event {}: {}", event_id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?
+    .ok_or(StatusCode::NOT_FOUND)?;
+
+    let host_id: String = row.get("host_id");
+    let is_host = host_id == claims.sub;
+    let is_cohost: bool = row.try_get("is_cohost").unwrap_or(false);
+
+    // Co-hosts watch like attendees but never see the ingest credentials.
+    if !is_host && !is_cohost {
+        let going = row
+            .try_get::<Option<String>, _>("rsvp_status")
+            .unwrap_or(None)
+            .map(|status| status.trim().eq_ignore_ascii_case("GOING"))
+            .unwrap_or(false);
+        let revoked = row
+            .try_get::<Option<DateTime<Utc>>, _>("revoked_at")
+            .unwrap_or(None)
+            .is_some();
+        if !going || revoked {
+            return Err(StatusCode::FORBIDDEN);
+        }
+
+        let price = row
+            .try_get::<Option<f64>, _>("ticket_type_price")
+            .unwrap_or(None)
+            .or_else(|| row.try_get::<Option<f64>, _>("price").unwrap_or(None))
+            .unwrap_or(0.0);
+        let is_paid = row
+            .try_get::<Option<bool>, _>("is_paid")
+            .unwrap_or(None)
+            .unwrap_or(false);
+        let refunded = row
+            .try_get::<Option<DateTime<Utc>>, _>("refunded_at")
+            .unwrap_or(None)
+            .is_some();
+        if price > 0.0 && (!is_paid || refunded) {
+            return Err(StatusCode::PAYMENT_REQUIRED);
+        }
+    }
+
+    let provider: Option<String> = row.try_get("stream_provider").unwrap_or(None);
+    if provider.is_none() {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    let mut data = json!({
+        "provider": provider,
+        "playbackUrl": row.try_get::<Option<String>, _>("stream_playback_url").unwrap_or(None),
+        "isLive": row.try_get::<Option<bool>, _>("is_live").unwrap_or(None).unwrap_or(false),
+        "liveStartedAt": row.try_get::<Option<DateTime<Utc>>, _>("live_started_at").unwrap_or(None),
+        "liveEndedAt": row.try_get::<Option<DateTime<Utc>>, _>("live_ended_at").unwrap_or(None),
+    });
+
+    if is_host {
+        data["streamId"] = json!(row.try_get::<Option<String>, _>("stream_id").unwrap_or(None));
+        data["ingestUrl"] =
+            json!(row.try_get::<Option<String>, _>("stream_ingest_url").unwrap_or(None));
+        data["streamKey"] = json!(row.try_get::<Option<String>, _>("stream_key").unwrap_or(None));
+    }
+
+    Ok(Json(json!({
+        "success": true,
+        "data": data
+    })))
+}
+
+async fn attach_stream(
+    State(db): State<Database>,
+    Path(event_id): Path<String>,
+    claims: Claims,
+    Json(payload): Json<StreamRequest>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    ensure_event_host(&db, &event_id, &claims.sub).await?;
+
+    let provider = payload.provider.trim().to_uppercase();
+    if !STREAM_PROVIDERS.contains(&provider.as_str()) {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let event_type = sqlx::query_scalar::<_, Option<String>>(
+        "SELECT event_type FROM events WHERE id::TEXT = $1",
+    )
+    .bind(&event_id)
+    .fetch_one(&db.pool)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    if event_type.as_deref().map(str::to_uppercase).as_deref() == Some("IN_PERSON") {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let non_empty = |value: &Option<String>| {
+        value
+            .as_deref()
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+            .map(str::to_string)
+    };
+    let playback_url = non_empty(&payload.playback_url).or_else(|| {
+        non_empty(&payload.playback_id)
+            .filter(|_| provider == "MUX")
+            .map(|playback_id| format!("https://stream.mux.com/{}.m3u8", playback_id))
+    });
+    let stream_id = non_empty(&payload.stream_id);
+
+    // Mux flips the live flag through webhooks, which are matched on the live stream id.
+    if playback_url.is_none() || (provider == "MUX" && stream_id.is_none()) {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    sqlx::query(
+        r#"
+        UPDATE events
+        SET stream_provider = $2, stream_id = $3, stream_playback_url = $4,
+            stream_ingest_url = $5, stream_key = $6, updated_at = NOW()
+        WHERE id::TEXT = $1
+        "#,
+    )
+    .bind(&event_id)
+    .bind(&provider)
+    .bind(&stream_id)
+    .bind(&playback_url)
+    .bind(non_empty(&payload.ingest_url))
+    .bind(non_empty(&payload.stream_key))
+    .execute(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to attach stream to event {}: {}", event_id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    invalidate_event_cache(&db, &event_id).await;
+
+    Ok(Json(json!({
+        "success": true,
+        "data": {
+            "provider": provider,
+            "streamId": stream_id,
+            "playbackUrl": playback_url
+        }
+    })))
+}
+
+async fn detach_stream(
+    State(db): State<Database>,
+    Path(event_id): Path<String>,
+    claims: Claims,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    ensure_event_host(&db, &event_id, &claims.sub).await?;
+
+    sqlx::query(
+        r#"
+        UPDATE events
+        SET stream_provider = NULL, stream_id = NULL, stream_playback_url = NULL,
+            stream_ingest_url = NULL, stream_key = NULL, is_live = FALSE, updated_at = NOW()
+        WHERE id::TEXT = $1
+        "#,
+    )
+    .bind(&event_id)
+    .execute(&db.pool)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    invalidate_event_cache(&db, &event_id).await;
+
+    Ok(Json(json!({
+        "success": true,
+        "message": "Stream removed"
+    })))
+}
+
+// Manual live toggle for providers without webhooks (YouTube, custom RTMP)
+async fn set_live(
+    State(db): State<Database>,
+    Path(event_id): Path<String>,
+    claims: Claims,
+    Json(payload): Json<LiveRequest>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    ensure_event_host(&db, &event_id, &claims.sub).await?;
+
+    let updated = update_live_state(&db, "id::TEXT", &event_id, payload.is_live).await?;
+    if updated.is_empty() {
+        // The event exists (host check passed) but has no stream attached.
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    Ok(Json(json!({
+        "success": true,
+        "data": {
+            "isLive": payload.is_live
+        }
+    })))
+}
+
+/// Mux webhook: `video.live_stream.active` marks the matching event live and
+/// `video.live_stream.idle` / `disconnected` ends it.
+async fn stream_webhook(
+    State(db): State<Database>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let secret = std::env::var("MUX_WEBHOOK_SECRET").unwrap_or_default();
+    if secret.trim().is_empty() {
+        tracing::error!("MUX_WEBHOOK_SECRET is not configured");
+        return Err(StatusCode::SERVICE_UNAVAILABLE);
+    }
+
+    let signature = headers
+        .get("mux-signature")
+        .and_then(|value| value.to_str().ok())
+        .ok_or(StatusCode::UNAUTHORIZED)?;
+    if !verify_mux_signature(&secret, signature, &body) {
+        return Err(StatusCode::UNAUTHORIZED);
+    }
+
+    let event: serde_json::Value =
+        serde_json::from_slice(&body).map_err(|_| StatusCode::BAD_REQUEST)?;
+    let event_type = event.get("type").and_then(|v| v.as_str()).unwrap_or("");
+    let is_live = match event_type {
+        "video.live_stream.active" => true,
+        "video.live_stream.idle" | "video.live_stream.disconnected" => false,
+        _ => {
+            return Ok(Json(json!({ "success": true, "ignored": true })));
+        }
+    };
+
+    let stream_id = event
+        .pointer("/data/id")
+        .and_then(|v| v.as_str())
+        .ok_or(StatusCode::BAD_REQUEST)?;
+
+    let updated = update_live_state(&db, "stream_id", stream_id, is_live).await?;
+    tracing::info!(
+        "Mux {} for stream {} updated {} event(s)",
+        event_type,
+        stream_id,
+        updated.len()
+    );
+
+    Ok(Json(json!({
+        "success": true,
+        "data": {
+            "events": updated,
+            "isLive": is_live
+        }
+    })))
+}
+
+/// Set the live flag on events with a stream, matched on `column`; returns the event ids.
+async fn update_live_state(
+    db: &Database,
+    column: &str,
+    value: &str,
+    is_live: bool,
+) -> Result<Vec<String>, StatusCode> {
+    let updated = sqlx::query_scalar::<_, String>(&format!(
+        r#"
+        UPDATE events
+        SET is_live = $2,
+            live_started_at = CASE WHEN $2 AND NOT is_live THEN NOW() ELSE live_started_at END,
+            live_ended_at = CASE WHEN NOT $2 AND is_live THEN NOW() ELSE live_ended_at END,
+            updated_at = NOW()
+        WHERE {} = $1 AND stream_provider IS NOT NULL
+        RETURNING id::TEXT
+        "#,
+        column
+    ))
+    .bind(value)
+    .bind(is_live)
+    .fetch_all(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to update live state for {} {}: {}", column, value, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    for event_id in &updated {
+        invalidate_event_cache(db, event_id).await;
+    }
+
+    Ok(updated)
+}
+
+/// `Mux-Signature: t=<unix>,v1=<hex hmac-sha256 of "<t>.<body>">`
+fn verify_mux_signature(secret: &str, header: &str, body: &[u8]) -> bool {
+    let mut timestamp = None;
+    let mut signatures = Vec::new();
+    for part in header.split(',') {
+        match part.trim().split_once('=') {
+            Some(("t", value)) => timestamp = value.parse::<i64>().ok(),
+            Some(("v1", value)) => signatures.push(value.to_string()),
+            _ => {}
+        }
+    }
+
+    let Some(timestamp) = timestamp else {
+        return false;
+    };
+    if (Utc::now().timestamp() - timestamp).abs() > WEBHOOK_TOLERANCE_SECONDS {
+        return false;
+    }
+
+    signatures.iter().any(|signature| {
+        let Ok(expected) = hex::decode(signature) else {
+            return false;
+        };
+        let mut mac =
+            HmacSha256::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+        mac.update(timestamp.to_string().as_bytes());
+        mac.update(b".");
+        mac.update(body);
+        mac.verify_slice(&expected).is_ok()
+    })
+}
@@ -0,0 +1,381 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::Json,
+    routing::{get, put},
+    Router,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sqlx::{Postgres, Transaction};
+use uuid::Uuid;
+
+use crate::{auth::Claims, database::Database, routes::events::ensure_event_host};
+
+/// A ticket tier of an event, with how many GOING RSVPs hold it.
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct TicketType {
+    pub id: Uuid,
+    pub event_id: String,
+    pub name: String,
+    pub description: Option<String>,
+    pub price: f64,
+    /// `None` means unlimited.
+    pub quantity: Option<i32>,
+    pub sales_start: Option<DateTime<Utc>>,
+    pub sales_end: Option<DateTime<Utc>>,
+    pub position: i32,
+    pub sold: i64,
+}
+
+impl TicketType {
+    pub fn available(&self) -> Option<i64> {
+        self.quantity
+            .map(|quantity| (quantity as i64 - self.sold).max(0))
+    }
+
+    pub fn on_sale(&self, now: DateTime<Utc>) -> bool {
+        self.sales_start.map(|start| start <= now).unwrap_or(true)
+            && self.sales_end.map(|end| now < end).unwrap_or(true)
+    }
+
+    pub fn to_json(&self) -> serde_json::Value {
+        json!({
+            "id": self.id,
+            "name": self.name,
+            "description": self.description,
+            "price": self.price,
+            "quantity": self.quantity,
+            "sold": self.sold,
+            "available": self.available(),
+            "salesStart": self.sales_start,
+            "salesEnd": self.sales_end,
+            "onSale": self.on_sale(Utc::now()),
+            "soldOut": self.available() == Some(0),
+            "position": self.position,
+        })
+    }
+}
+
+const TICKET_TYPE_COLUMNS: &str = r#"
+    t.id, t.event_id, t.name, t.description, t.price, t.quantity, t.sales_start, t.sales_end,
+    t.position,
+    (
+        SELECT COALESCE(SUM(1 + COALESCE(r.guest_count, 0)), 0)::BIGINT FROM event_rsvps r
+        WHERE r.ticket_type_id = t.id AND UPPER(TRIM(r.status)) = 'GOING'
+    ) AS sold
+"#;
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TicketTypeRequest {
+    name: Option<String>,
+    description: Option<String>,
+    price: Option<f64>,
+    quantity: Option<i32>,
+    sales_start: Option<DateTime<Utc>>,
+    sales_end: Option<DateTime<Utc>>,
+    position: Option<i32>,
+}
+
+pub fn ticket_type_routes() -> Router<Database> {
+    Router::new()
+        .route(
+            "/:id/ticket-types",
+            get(list_ticket_types).post(create_ticket_type),
+        )
+        .route(
+            "/:id/ticket-types/:ticket_type_id",
+            put(update_ticket_type).delete(delete_ticket_type),
+        )
+}
+
+/// All tiers of an event in display order.
+pub(crate) async fn load_ticket_types(
+    db: &Database,
+    event_id: &str,
+) -> Result<Vec<TicketType>, StatusCode> {
+    sqlx::query_as::<_, TicketType>(&format!(
+        "SELECT {} FROM event_ticket_types t WHERE t.event_id = $1 ORDER BY t.position, t.price, t.created_at",
+        TICKET_TYPE_COLUMNS
+    ))
+    .bind(event_id)
+    .fetch_all(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to load ticket types for event {}: {}", event_id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })
+}
+
+/// Load a tier of the event and check that it can be bought right now.
+pub(crate) async fn find_purchasable_ticket_type(
+    db: &Database,
+    event_id: &str,
+    ticket_type_id: Uuid,
+) -> Result<TicketType, StatusCode> {
+    let ticket_type = sqlx::query_as::<_, TicketType>(&format!(
+        "SELECT {} FROM event_ticket_types t WHERE t.id = $1 AND t.event_id = $2",
+        TICKET_TYPE_COLUMNS
+    ))
+    .bind(ticket_type_id)
+    .bind(event_id)
+    .fetch_optional(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to load ticket type {}: {}", ticket_type_id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?
+    .ok_or(StatusCode::NOT_FOUND)?;
+
+    if !ticket_type.on_sale(Utc::now()) {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    if ticket_type.available() == Some(0) {
+        return Err(StatusCode::CONFLICT);
+    }
+
+    Ok(ticket_type)
+}
+
+/// Lock a tier inside the caller's transaction and check there are still `seats` tickets left
+/// for `user_id` and their guests. Holding the row lock until commit keeps concurrent RSVPs from overselling it.
+pub(crate) async fn reserve_ticket_type(
+    tx: &mut Transaction<'_, Postgres>,
+    event_id: &str,
+    ticket_type_id: Uuid,
+    user_id: &str,
+    seats: i64,
+) -> Result<TicketType, StatusCode> {
+    sqlx::query("SELECT id FROM event_ticket_types WHERE id = $1 AND event_id = $2 FOR UPDATE")
+        .bind(ticket_type_id)
+        .bind(event_id)
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to lock ticket type {}: {}", ticket_type_id, e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    // The user's own ticket does not count against them when switching or re-confirming.
+    let ticket_type = sqlx::query_as::<_, TicketType>(
+        r#"
+        SELECT t.id, t.event_id, t.name, t.description, t.price, t.quantity, t.sales_start,
+               t.sales_end, t.position,
+               (
+                   SELECT COALESCE(SUM(1 + COALESCE(r.guest_count, 0)), 0)::BIGINT
+                   FROM event_rsvps r
+                   WHERE r.ticket_type_id = t.id AND UPPER(TRIM(r.status)) = 'GOING'
+                     AND r.user_id <> $2
+               ) AS sold
+        FROM event_ticket_types t
+        WHERE t.id = $1
+        "#,
+    )
+    .bind(ticket_type_id)
+    .bind(user_id)
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to count tickets for {}: {}", ticket_type_id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    if ticket_type.available().is_some_and(|available| available < seats) {
+        return Err(StatusCode::CONFLICT);
+    }
+
+    Ok(ticket_type)
+}
+
+async fn list_ticket_types(
+    State(db): State<Database>,
+    Path(event_id): Path<String>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let ticket_types = load_ticket_types(&db, &event_id).await?;
+
+    Ok(Json(json!({
+        "success": true,
+        "data": ticket_types.iter().map(TicketType::to_json).collect::<Vec<_>>()
+    })))
+}
+
+async fn create_ticket_type(
+    State(db): State<Database>,
+    Path(event_id): Path<String>,
+    claims: Claims,
+    Json(payload): Json<TicketTypeRequest>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    ensure_event_host(&db, &event_id, &claims.sub).await?;
+
+    let name = payload
+        .name
+        .as_deref()
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .ok_or(StatusCode::BAD_REQUEST)?
+        .to_string();
+    validate_ticket_type(
+        payload.price.unwrap_or(0.0),
+        payload.quantity,
+        payload.sales_start,
+        payload.sales_end,
+    )?;
+
+    let ticket_type_id = sqlx::query_scalar::<_, Uuid>(
+        r#"
+        INSERT INTO event_ticket_types
+            (event_id, name, description, price, quantity, sales_start, sales_end, position)
+        VALUES ($1, $2, $3, $4, $5, $6, $7,
+                COALESCE($8, (SELECT COUNT(*)::INTEGER FROM event_ticket_types WHERE event_id = $1)))
+        RETURNING id
+        "#,
+    )
+    .bind(&event_id)
+    .bind(&name)
+    .bind(&payload.description)
+    .bind(payload.price.unwrap_or(0.0))
+    .bind(payload.quantity)
+    .bind(payload.sales_start)
+    .bind(payload.sales_end)
+    .bind(payload.position)
+    .fetch_one(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to create ticket type for event {}: {}", event_id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    ticket_type_response(&db, &event_id, ticket_type_id).await
+}
+
+async fn update_ticket_type(
+    State(db): State<Database>,
+    Path((event_id, ticket_type_id)): Path<(String, Uuid)>,
+    claims: Claims,
+    Json(payload): Json<TicketTypeRequest>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    ensure_event_host(&db, &event_id, &claims.sub).await?;
+
+    let current = load_ticket_types(&db, &event_id)
+        .await?
+        .into_iter()
+        .find(|ticket_type| ticket_type.id == ticket_type_id)
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    let name = match payload.name.as_deref().map(str::trim) {
+        Some("") => return Err(StatusCode::BAD_REQUEST),
+        Some(name) => name.to_string(),
+        None => current.name.clone(),
+    };
+    let price = payload.price.unwrap_or(current.price);
+    let quantity = payload.quantity.or(current.quantity);
+    let sales_start = payload.sales_start.or(current.sales_start);
+    let sales_end = payload.sales_end.or(current.sales_end);
+    validate_ticket_type(price, quantity, sales_start, sales_end)?;
+
+    // Capacity cannot drop below the tickets already issued.
+    if quantity.map(|quantity| (quantity as i64) < current.sold).unwrap_or(false) {
+        return Err(StatusCode::CONFLICT);
+    }
+
+    sqlx::query(
+        r#"
+        UPDATE event_ticket_types
+        SET name = $2, description = $3, price = $4, quantity = $5, sales_start = $6,
+            sales_end = $7, position = $8, updated_at = NOW()
+        WHERE id = $1
+        "#,
+    )
+    .bind(ticket_type_id)
+    .bind(&name)
+    .bind(payload.description.or(current.description))
+    .bind(price)
+    .bind(quantity)
+    .bind(sales_start)
+    .bind(sales_end)
+    .bind(payload.position.unwrap_or(current.position))
+    .execute(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to update ticket type {}: {}", ticket_type_id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    ticket_type_response(&db, &event_id, ticket_type_id).await
+}
+
+async fn delete_ticket_type(
+    State(db): State<Database>,
+    Path((event_id, ticket_type_id)): Path<(String, Uuid)>,
+    claims: Claims,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    ensure_event_host(&db, &event_id, &claims.sub).await?;
+
+    let holders = sqlx::query_scalar::<_, i64>(
+        "SELECT COUNT(*)::BIGINT FROM event_rsvps WHERE ticket_type_id = $1",
+    )
+    .bind(ticket_type_id)
+    .fetch_one(&db.pool)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    // Tiers with issued tickets stay so the tickets keep their tier; end the sales window instead.
+    if holders > 0 {
+        return Err(StatusCode::CONFLICT);
+    }
+
+    let result = sqlx::query("DELETE FROM event_ticket_types WHERE id = $1 AND event_id = $2")
+        .bind(ticket_type_id)
+        .bind(&event_id)
+        .execute(&db.pool)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    if result.rows_affected() == 0 {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    Ok(Json(json!({
+        "success": true,
+        "message": "Ticket type deleted"
+    })))
+}
+
+async fn ticket_type_response(
+    db: &Database,
+    event_id: &str,
+    ticket_type_id: Uuid,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let ticket_type = load_ticket_types(db, event_id)
+        .await?
+        .into_iter()
+        .find(|ticket_type| ticket_type.id == ticket_type_id)
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    Ok(Json(json!({
+        "success": true,
+        "data": ticket_type.to_json()
+    })))
+}
+
+fn validate_ticket_type(
+    price: f64,
+    quantity: Option<i32>,
+    sales_start: Option<DateTime<Utc>>,
+    sales_end: Option<DateTime<Utc>>,
+) -> Result<(), StatusCode> {
+    let valid_window = match (sales_start, sales_end) {
+        (Some(start), Some(end)) => start < end,
+        _ => true,
+    };
+
+    if !price.is_finite() || price < 0.0 || quantity.map(|q| q < 0).unwrap_or(false) || !valid_window {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    Ok(())
+}
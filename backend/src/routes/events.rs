@@ -1,31 +1,56 @@
 use axum::{
     extract::{Path, Query, State},
-    http::StatusCode,
-    response::Json,
+    http::{header, StatusCode},
+    response::{IntoResponse, Json, Response},
     routing::{get, post},
     Router,
 };
+use std::collections::HashMap;
+
+use chrono::{DateTime, Datelike, Duration, NaiveDate, TimeZone, Utc};
+use chrono_tz::Tz;
 use serde::{Deserialize, Serialize};
 use serde_json::json;
-use sqlx::{postgres::PgRow, Postgres, QueryBuilder, Row};
+use sqlx::{postgres::PgRow, Postgres, QueryBuilder, Row, Transaction};
 use uuid::Uuid;
 
-use crate::{auth::Claims, database::Database, middleware::optional_auth::MaybeClaims};
+use crate::{
+    auth::Claims,
+    database::Database,
+    feed_timeline,
+    ics::{self, CalendarEvent},
+    middleware::optional_auth::MaybeClaims,
+    notification_service::{notify, NotificationKind},
+    routes::event_attendees::attendee_routes,
+    routes::fees::{quote_platform_fee, LedgerSource, ProductType},
+    routes::stripe::{create_payment_intent, fetch_payment_intent, PaymentIntentSpec},
+    routes::event_cohosts::cohost_routes,
+    routes::event_feedback::feedback_routes,
+    routes::event_invites::{check_invite_access, invite_routes, redeem_invite},
+    routes::event_stream::stream_routes,
+    routes::event_tickets::{
+        find_purchasable_ticket_type, load_ticket_types, reserve_ticket_type, ticket_type_routes,
+    },
+};
 
 // Redis cache keys
 const CACHE_TTL_EVENT_LIST: usize = 60; // 1 minute for list
 const CACHE_TTL_EVENT_DETAIL: usize = 300; // 5 minutes for detail
+#[allow(dead_code)]
 const CACHE_TTL_RSVP_COUNT: usize = 30; // 30 seconds for RSVP count
 
-fn event_list_cache_key(page: u32, limit: u32, upcoming: bool, past: bool, status: &Option<String>, host_id: &Option<String>) -> String {
+fn event_list_cache_key(page: u32, limit: u32, upcoming: bool, past: bool, status: &Option<String>, host_id: &Option<String>, window: Option<DateWindow>) -> String {
     format!(
-        "events:list:{}:{}:{}:{}:{}:{}",
+        "events:list:{}:{}:{}:{}:{}:{}:{}",
         page,
         limit,
         upcoming,
         past,
         status.as_deref().unwrap_or("all"),
-        host_id.as_deref().unwrap_or("all")
+        host_id.as_deref().unwrap_or("all"),
+        window
+            .map(|(start, end)| format!("{}-{}", start.timestamp(), end.timestamp()))
+            .unwrap_or_else(|| "any".to_string())
     )
 }
 
@@ -37,7 +62,7 @@ fn event_rsvp_count_cache_key(event_id: &str) -> String {
     format!("event:rsvp_count:{}", event_id)
 }
 
-async fn invalidate_event_cache(db: &Database, event_id: &str) {
+pub(crate) async fn invalidate_event_cache(db: &Database, event_id: &str) {
     if let Some(redis) = &db.redis {
         let mut redis_clone = redis.clone();
         // Invalidate event detail cache
@@ -65,6 +90,251 @@ pub struct EventQuery {
     pub host_id: Option<String>,
     #[serde(alias = "hostUsername")]
     pub host_username: Option<String>,
+    /// Viewer's IANA timezone; local times and date ranges are computed in it.
+    #[serde(alias = "tz")]
+    pub timezone: Option<String>,
+    /// Named local date range: today, tomorrow, this_weekend, this_week, next_week, this_month.
+    pub range: Option<String>,
+    /// First local date (YYYY-MM-DD) to include.
+    pub from: Option<String>,
+    /// Last local date (YYYY-MM-DD) to include.
+    pub to: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct EventSearchQuery {
+    q: Option<String>,
+    #[serde(rename = "type")]
+    event_type: Option<String>,
+    /// `free` or `paid`.
+    price: Option<String>,
+    /// Comma-separated; events matching any tag are returned.
+    tags: Option<String>,
+    range: Option<String>,
+    from: Option<String>,
+    to: Option<String>,
+    #[serde(alias = "tz")]
+    timezone: Option<String>,
+    lat: Option<f64>,
+    lng: Option<f64>,
+    radius_km: Option<f64>,
+    page: Option<u32>,
+    limit: Option<u32>,
+}
+
+/// Radius used for geo search when the client sends coordinates without one.
+const DEFAULT_SEARCH_RADIUS_KM: f64 = 50.0;
+const MAX_SEARCH_RADIUS_KM: f64 = 500.0;
+
+/// Upper bound hosts can set for plus-ones on a single RSVP.
+const MAX_GUESTS_PER_RSVP_LIMIT: i32 = 20;
+
+#[derive(Debug, Default, Deserialize)]
+struct ViewerTimezoneQuery {
+    #[serde(alias = "tz")]
+    timezone: Option<String>,
+}
+
+/// Parse an IANA zone name such as `Europe/Istanbul`.
+pub(crate) fn parse_timezone(name: &str) -> Option<Tz> {
+    name.trim().parse::<Tz>().ok()
+}
+
+/// Validate an event's `timezone` field and normalise it to the canonical zone name.
+fn validate_event_timezone(timezone: &Option<String>) -> Result<Option<String>, StatusCode> {
+    match timezone.as_deref().map(str::trim) {
+        None | Some("") => Ok(None),
+        Some(name) => parse_timezone(name)
+            .map(|tz| Some(tz.name().to_string()))
+            .ok_or(StatusCode::BAD_REQUEST),
+    }
+}
+
+fn validate_max_guests(max_guests: Option<i32>) -> Result<Option<i32>, StatusCode> {
+    match max_guests {
+        Some(max_guests) if !(0..=MAX_GUESTS_PER_RSVP_LIMIT).contains(&max_guests) => {
+            Err(StatusCode::BAD_REQUEST)
+        }
+        _ => Ok(max_guests),
+    }
+}
+
+/// Guests requested on an RSVP, checked against the host's per-RSVP limit.
+fn requested_guests(guests: Option<i32>, max_guests: i32) -> Result<i32, StatusCode> {
+    let guests = guests.unwrap_or(0);
+    if guests < 0 || guests > max_guests {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    Ok(guests)
+}
+
+/// Lock the event row and check `seats` more people fit under `max_attendees`. Every GOING
+/// RSVP takes one seat plus one per guest; the user's own RSVP is left out so changing the
+/// guest count does not count them twice.
+async fn reserve_event_capacity(
+    tx: &mut Transaction<'_, Postgres>,
+    event_id: &str,
+    user_id: &str,
+    seats: i64,
+) -> Result<(), StatusCode> {
+    let max_attendees = sqlx::query_scalar::<_, Option<i32>>(
+        "SELECT max_attendees FROM events WHERE id::TEXT = $1 FOR UPDATE",
+    )
+    .bind(event_id)
+    .fetch_optional(&mut *tx)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to lock event {}: {}", event_id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?
+    .ok_or(StatusCode::NOT_FOUND)?;
+
+    let Some(max_attendees) = max_attendees.filter(|max| *max > 0) else {
+        return Ok(());
+    };
+
+    let taken = sqlx::query_scalar::<_, i64>(
+        r#"
+        SELECT COALESCE(SUM(1 + COALESCE(guest_count, 0)), 0)::BIGINT
+        FROM event_rsvps
+        WHERE event_id = $1 AND user_id <> $2 AND UPPER(TRIM(status)) = 'GOING'
+        "#,
+    )
+    .bind(event_id)
+    .bind(user_id)
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to count seats of event {}: {}", event_id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    if taken + seats > max_attendees as i64 {
+        return Err(StatusCode::CONFLICT);
+    }
+
+    Ok(())
+}
+
+/// Coordinates must come as a pair and lie on the globe.
+fn validate_coordinates(
+    latitude: Option<f64>,
+    longitude: Option<f64>,
+) -> Result<(Option<f64>, Option<f64>), StatusCode> {
+    match (latitude, longitude) {
+        (None, None) => Ok((None, None)),
+        (Some(lat), Some(lng))
+            if (-90.0..=90.0).contains(&lat) && (-180.0..=180.0).contains(&lng) =>
+        {
+            Ok((Some(lat), Some(lng)))
+        }
+        _ => Err(StatusCode::BAD_REQUEST),
+    }
+}
+
+/// First instant of a local date. Days starting inside a DST gap begin at the gap's end.
+fn local_midnight(tz: Tz, date: NaiveDate) -> DateTime<Utc> {
+    let midnight = date.and_hms_opt(0, 0, 0).unwrap_or_default();
+    (0..4)
+        .find_map(|hours| {
+            tz.from_local_datetime(&(midnight + Duration::hours(hours)))
+                .earliest()
+        })
+        .map(|local| local.with_timezone(&Utc))
+        .unwrap_or_else(|| Utc.from_utc_datetime(&midnight))
+}
+
+/// Half-open `[start, end)` window of instants.
+type DateWindow = (DateTime<Utc>, DateTime<Utc>);
+
+/// Resolve `range` or `from`/`to` into a half-open UTC window of local days in `tz`.
+fn local_date_window(
+    tz: Tz,
+    range: Option<&str>,
+    from: Option<&str>,
+    to: Option<&str>,
+) -> Result<Option<DateWindow>, StatusCode> {
+    let today = Utc::now().with_timezone(&tz).date_naive();
+    let days_from_monday = today.weekday().num_days_from_monday() as i64;
+
+    let dates = match range.map(|range| range.trim().to_ascii_lowercase()) {
+        Some(range) => {
+            let (first, days) = match range.as_str() {
+                "today" => (today, 1),
+                "tomorrow" => (today + Duration::days(1), 1),
+                // Saturday and Sunday of the current week; on Sunday that is yesterday and today.
+                "this_weekend" | "weekend" => (today + Duration::days(5 - days_from_monday), 2),
+                "this_week" | "week" => (today - Duration::days(days_from_monday), 7),
+                "next_week" => (today + Duration::days(7 - days_from_monday), 7),
+                "this_month" | "month" => {
+                    let first = today.with_day(1).unwrap_or(today);
+                    let next = if first.month() == 12 {
+                        NaiveDate::from_ymd_opt(first.year() + 1, 1, 1)
+                    } else {
+                        NaiveDate::from_ymd_opt(first.year(), first.month() + 1, 1)
+                    }
+                    .unwrap_or(first);
+                    (first, (next - first).num_days())
+                }
+                _ => return Err(StatusCode::BAD_REQUEST),
+            };
+            Some((first, first + Duration::days(days)))
+        }
+        None => {
+            let parse = |raw: &str| {
+                NaiveDate::parse_from_str(raw.trim(), "%Y-%m-%d").map_err(|_| StatusCode::BAD_REQUEST)
+            };
+            match (from.map(parse).transpose()?, to.map(parse).transpose()?) {
+                (None, None) => None,
+                (first, last) => {
+                    let first = first.unwrap_or(today);
+                    let end = last
+                        .map(|last| last + Duration::days(1))
+                        .unwrap_or(first + Duration::days(3650));
+                    if end <= first {
+                        return Err(StatusCode::BAD_REQUEST);
+                    }
+                    Some((first, end))
+                }
+            }
+        }
+    };
+
+    Ok(dates.map(|(first, end)| (local_midnight(tz, first), local_midnight(tz, end))))
+}
+
+/// Add the event's times as seen from the viewer's timezone to a serialized event.
+fn localize_event_json(event: &mut serde_json::Value, tz: Tz) {
+    let local = |key: &str| {
+        event
+            .get(key)
+            .and_then(|value| value.as_str())
+            .and_then(|raw| DateTime::parse_from_rfc3339(raw).ok())
+            .map(|time| time.with_timezone(&tz))
+    };
+    let start = local("startTime");
+    let end = local("endTime");
+
+    if let Some(object) = event.as_object_mut() {
+        object.insert("viewerTimezone".to_string(), json!(tz.name()));
+        object.insert(
+            "localStartTime".to_string(),
+            json!(start.map(|time| time.to_rfc3339())),
+        );
+        object.insert(
+            "localEndTime".to_string(),
+            json!(end.map(|time| time.to_rfc3339())),
+        );
+        object.insert(
+            "localDate".to_string(),
+            json!(start.map(|time| time.format("%Y-%m-%d").to_string())),
+        );
+        object.insert(
+            "utcOffset".to_string(),
+            json!(start.map(|time| time.format("%:z").to_string())),
+        );
+    }
 }
 
 #[derive(Debug, Serialize)]
@@ -95,9 +365,16 @@ struct EventResponse {
     pub end_time: chrono::DateTime<chrono::Utc>,
     pub timezone: Option<String>,
     pub location: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub latitude: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub longitude: Option<f64>,
     pub virtual_link: Option<String>,
+    pub is_live: bool,
     pub cover_image: String,
     pub max_attendees: Option<i32>,
+    /// How many guests one RSVP may bring along; 0 disables plus-ones.
+    pub max_guests_per_rsvp: i32,
     pub is_public: bool,
     pub is_premium: bool,
     pub price: f64,
@@ -116,6 +393,8 @@ struct EventResponse {
     pub user_rsvp_status: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub user_rsvp_is_paid: Option<bool>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub ticket_types: Vec<serde_json::Value>,
 }
 
 impl EventResponse {
@@ -134,13 +413,23 @@ impl EventResponse {
             .unwrap_or(start_time);
         let timezone: Option<String> = row.try_get("timezone").unwrap_or(None);
         let location: Option<String> = row.try_get("location").unwrap_or(None);
+        let latitude: Option<f64> = row.try_get("latitude").unwrap_or(None);
+        let longitude: Option<f64> = row.try_get("longitude").unwrap_or(None);
         let virtual_link: Option<String> = row.try_get("virtual_link").unwrap_or(None);
+        let is_live: bool = row
+            .try_get::<Option<bool>, _>("is_live")
+            .unwrap_or(None)
+            .unwrap_or(false);
         let cover_image = row
             .try_get::<Option<String>, _>("cover_image")
             .unwrap_or(None)
             .filter(|url| !url.trim().is_empty())
             .unwrap_or_else(|| DEFAULT_EVENT_COVER.to_string());
         let max_attendees: Option<i32> = row.try_get("max_attendees").unwrap_or(None);
+        let max_guests_per_rsvp: i32 = row
+            .try_get::<Option<i32>, _>("max_guests_per_rsvp")
+            .unwrap_or(None)
+            .unwrap_or(0);
         let is_public: bool = row
             .try_get::<Option<bool>, _>("is_public")
             .unwrap_or(Some(true))
@@ -202,9 +491,13 @@ impl EventResponse {
             end_time,
             timezone,
             location,
+            latitude,
+            longitude,
             virtual_link,
+            is_live,
             cover_image,
             max_attendees,
+            max_guests_per_rsvp,
             is_public,
             is_premium,
             price,
@@ -221,6 +514,7 @@ impl EventResponse {
             _count: EventCounts { rsvps: rsvp_count },
             user_rsvp_status,
             user_rsvp_is_paid,
+            ticket_types: Vec::new(),
         }
     }
 }
@@ -245,6 +539,9 @@ struct CreateEventRequest {
     pub cover_image: Option<String>,
     pub agenda: Option<String>,
     pub tags: Option<Vec<String>>,
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
+    pub max_guests_per_rsvp: Option<i32>,
 }
 
 impl CreateEventRequest {
@@ -255,12 +552,150 @@ impl CreateEventRequest {
     }
 }
 
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct UpdateEventRequest {
+    pub title: Option<String>,
+    pub description: Option<String>,
+    #[serde(default, rename = "type")]
+    pub type_field: Option<String>,
+    pub status: Option<String>,
+    pub start_time: Option<String>,
+    pub end_time: Option<String>,
+    pub timezone: Option<String>,
+    pub location: Option<String>,
+    pub virtual_link: Option<String>,
+    pub max_attendees: Option<i32>,
+    pub is_public: Option<bool>,
+    pub is_premium: Option<bool>,
+    pub price: Option<f64>,
+    pub cover_image: Option<String>,
+    pub agenda: Option<String>,
+    pub tags: Option<Vec<String>>,
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
+    pub max_guests_per_rsvp: Option<i32>,
+}
+
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
 struct RsvpRequest {
     status: String,
     #[serde(default)]
     is_paid: Option<bool>,
+    #[serde(default)]
+    ticket_type_id: Option<Uuid>,
+    /// Required for non-public events the user has not been admitted to yet.
+    #[serde(default)]
+    invite_token: Option<String>,
+    /// Plus-ones coming with the user, up to the event's `maxGuestsPerRsvp`.
+    #[serde(default)]
+    guests: Option<i32>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PaymentIntentRequest {
+    #[serde(default)]
+    ticket_type_id: Option<Uuid>,
+    #[serde(default)]
+    invite_token: Option<String>,
+    #[serde(default)]
+    guests: Option<i32>,
+}
+
+/// The code printed on an attendee's ticket; guests append `-G<n>` to it.
+pub(crate) fn ticket_code(event_id: &str, user_id: &str) -> String {
+    let short = |id: &str| {
+        id.chars()
+            .filter(|c| c.is_ascii_alphanumeric())
+            .take(6)
+            .collect::<String>()
+            .to_uppercase()
+    };
+    format!("TCK-{}-{}", short(event_id), short(user_id))
+}
+
+/// Attendees whose RSVP the host revoked cannot RSVP or buy a ticket again.
+async fn ensure_not_revoked(db: &Database, event_id: &str, user_id: &str) -> Result<(), StatusCode> {
+    let revoked = sqlx::query_scalar::<_, bool>(
+        "SELECT EXISTS(SELECT 1 FROM event_rsvps WHERE event_id = $1 AND user_id = $2 AND revoked_at IS NOT NULL)",
+    )
+    .bind(event_id)
+    .bind(user_id)
+    .fetch_one(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to check RSVP revocation for event {}: {}", event_id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    if revoked {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    Ok(())
+}
+
+/// The host or a co-host may edit the event, view attendees and check people in.
+pub(crate) async fn ensure_event_manager(
+    db: &Database,
+    event_id: &str,
+    user_id: &str,
+) -> Result<(), StatusCode> {
+    let row = sqlx::query(
+        r#"
+        SELECT e.host_id,
+               EXISTS(
+                   SELECT 1 FROM event_cohosts c WHERE c.event_id = e.id::TEXT AND c.user_id = $2
+               ) AS is_cohost
+        FROM events e
+        WHERE e.id::TEXT = $1
+        LIMIT 1
+        "#,
+    )
+    .bind(event_id)
+    .bind(user_id)
+    .fetch_optional(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to load managers of event {}: {}", event_id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?
+    .ok_or(StatusCode::NOT_FOUND)?;
+
+    let host_id: String = row.get("host_id");
+    let is_cohost: bool = row.get("is_cohost");
+    if host_id != user_id && !is_cohost {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    Ok(())
+}
+
+/// Only the host may manage an event. Missing events are reported as 404.
+pub(crate) async fn ensure_event_host(
+    db: &Database,
+    event_id: &str,
+    user_id: &str,
+) -> Result<(), StatusCode> {
+    let host_id = sqlx::query_scalar::<_, String>(
+        "SELECT host_id FROM events WHERE id::TEXT = $1 LIMIT 1",
+    )
+    .bind(event_id)
+    .fetch_optional(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to load host of event {}: {}", event_id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?
+    .ok_or(StatusCode::NOT_FOUND)?;
+
+    if host_id != user_id {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    Ok(())
 }
 
 async fn ensure_event_rsvps_table(db: &Database) -> Result<(), StatusCode> {
@@ -364,8 +799,8 @@ async fn handle_rsvp(
         return Err(StatusCode::BAD_REQUEST);
     }
 
-    let event_exists = sqlx::query_scalar::<_, Option<String>>(
-        "SELECT id::TEXT FROM events WHERE id::TEXT = $1 LIMIT 1",
+    let max_guests = sqlx::query_scalar::<_, Option<i32>>(
+        "SELECT max_guests_per_rsvp FROM events WHERE id::TEXT = $1 LIMIT 1",
     )
     .bind(&event_id)
     .fetch_optional(&db.pool)
@@ -373,11 +808,19 @@ async fn handle_rsvp(
     .map_err(|e| {
         tracing::error!("Failed to verify event {}: {}", id, e);
         StatusCode::INTERNAL_SERVER_ERROR
-    })?;
+    })?
+    .ok_or(StatusCode::NOT_FOUND)?
+    .unwrap_or(0);
+    let guests = if normalized_status == "NOT_GOING" {
+        0
+    } else {
+        requested_guests(payload.guests, max_guests)?
+    };
 
-    if event_exists.is_none() {
-        return Err(StatusCode::NOT_FOUND);
-    }
+    ensure_not_revoked(&db, &event_id, &claims.sub).await?;
+
+    let ticket_types = load_ticket_types(&db, &event_id).await?;
+    let mut is_paid = payload.is_paid.unwrap_or(false);
 
     if normalized_status == "NOT_GOING" {
         sqlx::query("DELETE FROM event_rsvps WHERE event_id = $1 AND user_id = $2")
@@ -390,27 +833,103 @@ async fn handle_rsvp(
                 StatusCode::INTERNAL_SERVER_ERROR
             })?;
     } else {
-        sqlx::query(
+        let invite_id =
+            check_invite_access(&db, &event_id, &claims.sub, payload.invite_token.as_deref()).await?;
+
+        // Events with ticket tiers need a tier for GOING; paid tiers are only confirmed by
+        // the payment_intent.succeeded webhook.
+        let ticket_type_id = if normalized_status == "GOING" && !ticket_types.is_empty() {
+            let ticket_type_id = payload.ticket_type_id.ok_or(StatusCode::BAD_REQUEST)?;
+            let ticket_type = ticket_types
+                .iter()
+                .find(|ticket_type| ticket_type.id == ticket_type_id)
+                .ok_or(StatusCode::NOT_FOUND)?;
+
+            let existing = sqlx::query_as::<_, (Option<Uuid>, Option<bool>, Option<i32>)>(
+                "SELECT ticket_type_id, is_paid, guest_count FROM event_rsvps WHERE event_id = $1 AND user_id = $2",
+            )
+            .bind(&event_id)
+            .bind(&claims.sub)
+            .fetch_optional(&db.pool)
+            .await
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+            let (holds_paid_ticket, paid_guests) = match existing {
+                Some((Some(held), Some(true), paid_guests)) if held == ticket_type_id => {
+                    (true, paid_guests.unwrap_or(0))
+                }
+                _ => (false, 0),
+            };
+
+            // Each guest needs a ticket of their own, so extra guests have to be paid for.
+            if ticket_type.price > 0.0 && (!holds_paid_ticket || guests > paid_guests) {
+                return Err(StatusCode::PAYMENT_REQUIRED);
+            }
+            if !holds_paid_ticket && !ticket_type.on_sale(chrono::Utc::now()) {
+                return Err(StatusCode::BAD_REQUEST);
+            }
+
+            is_paid = holds_paid_ticket;
+            Some(ticket_type_id)
+        } else {
+            None
+        };
+
+        let mut tx = db.pool.begin().await.map_err(|e| {
+            tracing::error!("Failed to start RSVP transaction: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+        if normalized_status == "GOING" {
+            reserve_event_capacity(&mut tx, &event_id, &claims.sub, 1 + guests as i64).await?;
+        }
+        if let Some(ticket_type_id) = ticket_type_id {
+            reserve_ticket_type(&mut tx, &event_id, ticket_type_id, &claims.sub, 1 + guests as i64)
+                .await?;
+        }
+        if let Some(invite_id) = invite_id {
+            redeem_invite(&mut tx, invite_id, true).await?;
+        }
+
+        let is_new_rsvp = sqlx::query_scalar::<_, bool>(
             r#"
-            INSERT INTO event_rsvps (event_id, user_id, status, is_paid, created_at, updated_at)
-            VALUES ($1, $2, $3, $4, NOW(), NOW())
+            INSERT INTO event_rsvps (
+                event_id, user_id, status, is_paid, ticket_type_id, invite_id, guest_count,
+                created_at, updated_at
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, NOW(), NOW())
             ON CONFLICT (event_id, user_id)
             DO UPDATE SET
                 status = EXCLUDED.status,
                 is_paid = EXCLUDED.is_paid,
+                guest_count = EXCLUDED.guest_count,
+                ticket_type_id = COALESCE(EXCLUDED.ticket_type_id, event_rsvps.ticket_type_id),
+                invite_id = COALESCE(event_rsvps.invite_id, EXCLUDED.invite_id),
                 updated_at = NOW()
+            RETURNING xmax = 0
             "#,
         )
         .bind(&event_id)
         .bind(&claims.sub)
         .bind(&normalized_status)
-        .bind(payload.is_paid.unwrap_or(false))
-        .execute(&db.pool)
+        .bind(is_paid)
+        .bind(ticket_type_id)
+        .bind(invite_id)
+        .bind(guests)
+        .fetch_one(&mut tx)
         .await
         .map_err(|e| {
             tracing::error!("Failed to upsert RSVP for event {}: {}", id, e);
             StatusCode::INTERNAL_SERVER_ERROR
         })?;
+
+        tx.commit().await.map_err(|e| {
+            tracing::error!("Failed to commit RSVP for event {}: {}", id, e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+        if is_new_rsvp {
+            notify_host_of_rsvp(&db, &event_id, &claims.sub, &normalized_status, guests).await;
+        }
     }
 
     let rsvp_count = sqlx::query_scalar::<_, i64>(
@@ -424,13 +943,10 @@ async fn handle_rsvp(
         StatusCode::INTERNAL_SERVER_ERROR
     })?;
 
-    let (user_status, user_is_paid) = if normalized_status == "NOT_GOING" {
-        (None, None)
+    let (user_status, user_is_paid, user_guests) = if normalized_status == "NOT_GOING" {
+        (None, None, None)
     } else {
-        (
-            Some(normalized_status.clone()),
-            Some(payload.is_paid.unwrap_or(false)),
-        )
+        (Some(normalized_status.clone()), Some(is_paid), Some(guests))
     };
 
     // Ensure we hold the normalized status text back in the row for future queries
@@ -455,19 +971,75 @@ async fn handle_rsvp(
         "data": {
             "status": user_status,
             "isPaid": user_is_paid,
+            "guestCount": user_guests,
             "rsvpCount": rsvp_count
         }
     })))
 }
 
+/// Tell the host about a new attendee; hosts RSVPing to their own event are skipped.
+async fn notify_host_of_rsvp(
+    db: &Database,
+    event_id: &str,
+    attendee_id: &str,
+    status: &str,
+    guests: i32,
+) {
+    let event = sqlx::query_as::<_, (String, String)>(
+        "SELECT host_id, title FROM events WHERE id::TEXT = $1",
+    )
+    .bind(event_id)
+    .fetch_optional(&db.pool)
+    .await;
+    let (host_id, title) = match event {
+        Ok(Some(event)) => event,
+        Ok(None) => return,
+        Err(e) => {
+            tracing::warn!("Failed to load event {} to notify its host: {}", event_id, e);
+            return;
+        }
+    };
+    if host_id == attendee_id {
+        return;
+    }
+
+    let message = match (status, guests) {
+        ("GOING", 0) => format!("Someone is going to {}", title),
+        ("GOING", guests) => format!("Someone is going to {} with {} guest(s)", title, guests),
+        _ => format!("Someone might go to {}", title),
+    };
+    notify(
+        db,
+        &host_id,
+        NotificationKind::Rsvp,
+        json!({
+            "message": message,
+            "link": format!("/events/{}", event_id),
+            "eventId": event_id,
+            "attendeeId": attendee_id,
+            "status": status,
+            "guestCount": guests,
+        }),
+    )
+    .await;
+}
+
 pub fn event_routes() -> Router<Database> {
     Router::new()
         .route("/", get(get_events).post(create_event))
-        .route("/:id", get(get_event_by_id))
+        .route("/search", get(search_events))
+        .route("/:id", get(get_event_by_id).put(update_event))
         .route("/:id/ticket", get(get_event_ticket))
+        .route("/:id/ics", get(get_event_ics))
         .route("/:id/rsvp", post(handle_rsvp))
         .route("/:id/payment-intent", post(create_event_payment_intent))
         .route("/:id/complete-rsvp", post(complete_event_rsvp))
+        .merge(ticket_type_routes())
+        .merge(attendee_routes())
+        .merge(stream_routes())
+        .merge(cohost_routes())
+        .merge(invite_routes())
+        .merge(feedback_routes())
 }
 
 async fn get_events(
@@ -481,6 +1053,17 @@ async fn get_events(
     let past = params.past.unwrap_or(false);
     let status = params.status.clone();
     let host_id_param = params.host_id.clone();
+    let viewer_tz = match params.timezone.as_deref().filter(|tz| !tz.trim().is_empty()) {
+        Some(name) => Some(parse_timezone(name).ok_or(StatusCode::BAD_REQUEST)?),
+        None => None,
+    };
+    // Date ranges are local to the viewer; without a timezone they are taken as UTC days.
+    let window = local_date_window(
+        viewer_tz.unwrap_or(Tz::UTC),
+        params.range.as_deref(),
+        params.from.as_deref(),
+        params.to.as_deref(),
+    )?;
     let mut host_username_param = params.host_username.clone();
     if let (Some(ref host_id), Some(ref host_username)) = (&host_id_param, &host_username_param) {
         if host_id == host_username {
@@ -489,12 +1072,15 @@ async fn get_events(
     }
 
     // Try to get from cache first
-    let cache_key = event_list_cache_key(page, limit, upcoming, past, &status, &host_id_param);
+    let cache_key = event_list_cache_key(page, limit, upcoming, past, &status, &host_id_param, window);
     if let Some(redis) = &db.redis {
         let mut redis_clone = redis.clone();
         if let Ok(Some(cached)) = redis_clone.get(&cache_key).await {
             tracing::debug!("Cache HIT for events list: {}", cache_key);
-            if let Ok(cached_value) = serde_json::from_str::<serde_json::Value>(&cached) {
+            if let Ok(mut cached_value) = serde_json::from_str::<serde_json::Value>(&cached) {
+                if let Some(tz) = viewer_tz {
+                    localize_event_list(&mut cached_value, tz);
+                }
                 return Ok(Json(cached_value));
             }
         } else {
@@ -537,12 +1123,20 @@ async fn get_events(
             .push("e.start_time < NOW()");
         has_count_filter = true;
     }
+    if let Some((window_start, window_end)) = window {
+        count_builder
+            .push(if has_count_filter { " AND " } else { " WHERE " })
+            .push("e.start_time < ")
+            .push_bind(window_end)
+            .push(" AND COALESCE(e.end_time, e.start_time) >= ")
+            .push_bind(window_start);
+        has_count_filter = true;
+    }
     if let Some(ref status) = status {
         count_builder
             .push(if has_count_filter { " AND " } else { " WHERE " })
             .push("e.status = ")
             .push_bind(status);
-        has_count_filter = true;
     }
 
     let total_row = count_builder
@@ -569,7 +1163,11 @@ async fn get_events(
             e.timezone,
             e.location,
             e.virtual_link,
+            e.is_live,
+            e.latitude,
+            e.longitude,
             e.max_attendees,
+            e.max_guests_per_rsvp,
             e.is_public,
             e.is_premium,
             e.price,
@@ -626,16 +1224,25 @@ async fn get_events(
             .push("e.start_time < NOW()");
         has_list_filter = true;
     }
+    if let Some((window_start, window_end)) = window {
+        list_builder
+            .push(if has_list_filter { " AND " } else { " WHERE " })
+            .push("e.start_time < ")
+            .push_bind(window_end)
+            .push(" AND COALESCE(e.end_time, e.start_time) >= ")
+            .push_bind(window_start);
+        has_list_filter = true;
+    }
     if let Some(ref status) = status {
         list_builder
             .push(if has_list_filter { " AND " } else { " WHERE " })
             .push("e.status = ")
             .push_bind(status);
-        has_list_filter = true;
     }
 
+    // A date range reads like a schedule, so it is listed chronologically.
     list_builder.push(" ORDER BY e.start_time ");
-    if upcoming {
+    if upcoming || window.is_some() {
         list_builder.push("ASC");
     } else {
         list_builder.push("DESC");
@@ -678,35 +1285,72 @@ async fn get_events(
         }
     }
 
+    // Localized after caching so one cached page serves every viewer timezone.
+    let mut response = response;
+    if let Some(tz) = viewer_tz {
+        localize_event_list(&mut response, tz);
+    }
+
     Ok(Json(response))
 }
 
-async fn get_event_by_id(
+fn localize_event_list(response: &mut serde_json::Value, tz: Tz) {
+    if let Some(events) = response.get_mut("data").and_then(|data| data.as_array_mut()) {
+        for event in events {
+            localize_event_json(event, tz);
+        }
+    }
+}
+
+// Public search over published, public events: full-text, filters and geo radius
+async fn search_events(
     State(db): State<Database>,
-    Path(id): Path<String>,
-    MaybeClaims(maybe_claims): MaybeClaims,
+    Query(params): Query<EventSearchQuery>,
 ) -> Result<Json<serde_json::Value>, StatusCode> {
-    let event_identifier = id.clone();
+    let page = params.page.unwrap_or(1).max(1);
+    let limit = params.limit.unwrap_or(12).clamp(1, 50);
+    let offset = (page - 1) * limit;
 
-    // Try cache first (only for non-authenticated requests to avoid leaking user data)
-    if maybe_claims.is_none() {
-        let cache_key = event_detail_cache_key(&event_identifier);
-        if let Some(redis) = &db.redis {
-            let mut redis_clone = redis.clone();
-            if let Ok(Some(cached)) = redis_clone.get(&cache_key).await {
-                tracing::debug!("Cache HIT for event detail: {}", cache_key);
-                if let Ok(cached_value) = serde_json::from_str::<serde_json::Value>(&cached) {
-                    return Ok(Json(cached_value));
-                }
-            } else {
-                tracing::debug!("Cache MISS for event detail: {}", cache_key);
-            }
+    let viewer_tz = match params.timezone.as_deref().filter(|tz| !tz.trim().is_empty()) {
+        Some(name) => Some(parse_timezone(name).ok_or(StatusCode::BAD_REQUEST)?),
+        None => None,
+    };
+    let window = local_date_window(
+        viewer_tz.unwrap_or(Tz::UTC),
+        params.range.as_deref(),
+        params.from.as_deref(),
+        params.to.as_deref(),
+    )?;
+
+    let text = params
+        .q
+        .as_deref()
+        .map(str::trim)
+        .filter(|q| !q.is_empty())
+        .map(str::to_string);
+    let tags: Vec<String> = params
+        .tags
+        .as_deref()
+        .unwrap_or("")
+        .split(',')
+        .map(|tag| tag.trim().to_string())
+        .filter(|tag| !tag.is_empty())
+        .collect();
+    let geo = match (params.lat, params.lng) {
+        (Some(lat), Some(lng)) => {
+            validate_coordinates(Some(lat), Some(lng))?;
+            let radius = params
+                .radius_km
+                .unwrap_or(DEFAULT_SEARCH_RADIUS_KM)
+                .clamp(0.1, MAX_SEARCH_RADIUS_KM);
+            Some((lat, lng, radius))
         }
-    }
-
-    ensure_event_rsvps_table(&db).await?;
+        (None, None) => None,
+        _ => return Err(StatusCode::BAD_REQUEST),
+    };
 
-    let query = r#"
+    let mut builder = QueryBuilder::<Postgres>::new(
+        r#"
         SELECT
             e.id,
             e.title,
@@ -718,8 +1362,12 @@ async fn get_event_by_id(
             e.end_time,
             e.timezone,
             e.location,
+            e.latitude,
+            e.longitude,
             e.virtual_link,
+            e.is_live,
             e.max_attendees,
+            e.max_guests_per_rsvp,
             e.is_public,
             e.is_premium,
             e.price,
@@ -731,19 +1379,237 @@ async fn get_event_by_id(
             u.display_name AS host_name,
             u.username AS host_username,
             u.avatar_url AS host_avatar,
-            COALESCE(rsvp_counts.count, 0) AS rsvp_count,
+            (
+                SELECT COUNT(*)::BIGINT FROM event_rsvps r
+                WHERE r.event_id = e.id::TEXT AND UPPER(TRIM(r.status)) = 'GOING'
+            ) AS rsvp_count,
             NULL::TEXT AS user_rsvp_status,
-            NULL::BOOLEAN AS user_rsvp_is_paid
+            NULL::BOOLEAN AS user_rsvp_is_paid,
+            COUNT(*) OVER() AS total_count,
+        "#,
+    );
+
+    match &text {
+        Some(q) => builder
+            .push("ts_rank(e.search_vector, websearch_to_tsquery('simple', ")
+            .push_bind(q.clone())
+            .push(")) AS rank, "),
+        None => builder.push("0::REAL AS rank, "),
+    };
+    match geo {
+        Some((lat, lng, _)) => push_distance_km(&mut builder, lat, lng).push(" AS distance_km"),
+        None => builder.push("NULL::DOUBLE PRECISION AS distance_km"),
+    };
+
+    builder.push(
+        r#"
         FROM events e
         LEFT JOIN users u ON e.host_id = u.id
-        LEFT JOIN (
-            SELECT event_id, COUNT(*)::BIGINT AS count
-            FROM event_rsvps
-            WHERE UPPER(TRIM(status)) = 'GOING'
-            GROUP BY event_id
-        ) rsvp_counts ON rsvp_counts.event_id = e.id::TEXT
-        WHERE e.id::TEXT = $1
-        LIMIT 1
+        WHERE COALESCE(e.is_public, TRUE)
+          AND UPPER(COALESCE(e.status, 'PUBLISHED')) NOT IN ('DRAFT', 'CANCELLED')
+        "#,
+    );
+
+    if let Some(q) = &text {
+        // Prefix matches on the title catch partially typed words the tsquery misses.
+        builder
+            .push(" AND (e.search_vector @@ websearch_to_tsquery('simple', ")
+            .push_bind(q.clone())
+            .push(") OR e.title ILIKE ")
+            .push_bind(format!("%{}%", q.replace('%', "\\%").replace('_', "\\_")))
+            .push(")");
+    }
+    if let Some(event_type) = params.event_type.as_deref().filter(|t| !t.trim().is_empty()) {
+        builder
+            .push(" AND UPPER(COALESCE(e.event_type, 'VIRTUAL')) = ")
+            .push_bind(event_type.trim().to_uppercase());
+    }
+    match params.price.as_deref().map(|p| p.trim().to_ascii_lowercase()) {
+        Some(price) if price == "free" => {
+            builder.push(" AND COALESCE(e.price, 0) <= 0");
+        }
+        Some(price) if price == "paid" => {
+            builder.push(" AND COALESCE(e.price, 0) > 0");
+        }
+        Some(price) if !price.is_empty() => return Err(StatusCode::BAD_REQUEST),
+        _ => {}
+    }
+    if !tags.is_empty() {
+        builder.push(" AND e.tags && ").push_bind(tags.clone());
+    }
+    match window {
+        Some((window_start, window_end)) => {
+            builder
+                .push(" AND e.start_time < ")
+                .push_bind(window_end)
+                .push(" AND COALESCE(e.end_time, e.start_time) >= ")
+                .push_bind(window_start);
+        }
+        // Discovery shows what is still ahead unless a date range is asked for.
+        None => {
+            builder.push(" AND COALESCE(e.end_time, e.start_time) >= NOW()");
+        }
+    }
+    if let Some((lat, lng, radius)) = geo {
+        // Bounding box first so the coordinate index narrows the set before the distance math.
+        let lat_delta = radius / 111.0;
+        let lng_delta = radius / (111.0 * lat.to_radians().cos().abs().max(0.01));
+        builder
+            .push(" AND e.latitude BETWEEN ")
+            .push_bind(lat - lat_delta)
+            .push(" AND ")
+            .push_bind(lat + lat_delta)
+            .push(" AND e.longitude BETWEEN ")
+            .push_bind(lng - lng_delta)
+            .push(" AND ")
+            .push_bind(lng + lng_delta)
+            .push(" AND ");
+        push_distance_km(&mut builder, lat, lng)
+            .push(" <= ")
+            .push_bind(radius);
+    }
+
+    builder.push(if geo.is_some() {
+        " ORDER BY distance_km ASC, e.start_time ASC"
+    } else if text.is_some() {
+        " ORDER BY rank DESC, e.start_time ASC"
+    } else {
+        " ORDER BY e.start_time ASC"
+    });
+    builder
+        .push(" LIMIT ")
+        .push_bind(limit as i64)
+        .push(" OFFSET ")
+        .push_bind(offset as i64);
+
+    let rows = builder.build().fetch_all(&db.pool).await.map_err(|e| {
+        tracing::error!("Failed to search events: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    let total_items: i64 = rows
+        .first()
+        .and_then(|row| row.try_get::<i64, _>("total_count").ok())
+        .unwrap_or(0);
+    let total_pages = ((total_items as f64) / (limit as f64)).ceil() as i64;
+
+    let events: Vec<serde_json::Value> = rows
+        .iter()
+        .map(|row| {
+            let mut event = serde_json::to_value(EventResponse::from_row(row)).unwrap_or_default();
+            if let Some(distance) = row.try_get::<Option<f64>, _>("distance_km").unwrap_or(None) {
+                event["distanceKm"] = json!((distance * 10.0).round() / 10.0);
+            }
+            if let Some(tz) = viewer_tz {
+                localize_event_json(&mut event, tz);
+            }
+            event
+        })
+        .collect();
+
+    Ok(Json(json!({
+        "success": true,
+        "data": events,
+        "pagination": {
+            "page": page,
+            "pageSize": limit,
+            "totalItems": total_items,
+            "totalPages": total_pages.max(1)
+        }
+    })))
+}
+
+/// Great-circle distance in kilometres from the given point to the event's stored coordinates.
+fn push_distance_km<'a, 'args>(
+    builder: &'a mut QueryBuilder<'args, Postgres>,
+    lat: f64,
+    lng: f64,
+) -> &'a mut QueryBuilder<'args, Postgres> {
+    builder
+        .push("(6371 * ACOS(LEAST(1, COS(RADIANS(")
+        .push_bind(lat)
+        .push(")) * COS(RADIANS(e.latitude)) * COS(RADIANS(e.longitude) - RADIANS(")
+        .push_bind(lng)
+        .push(")) + SIN(RADIANS(")
+        .push_bind(lat)
+        .push(")) * SIN(RADIANS(e.latitude)))))")
+}
+
+async fn get_event_by_id(
+    State(db): State<Database>,
+    Path(id): Path<String>,
+    Query(viewer): Query<ViewerTimezoneQuery>,
+    MaybeClaims(maybe_claims): MaybeClaims,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let event_identifier = id.clone();
+    let viewer_tz = match viewer.timezone.as_deref().filter(|tz| !tz.trim().is_empty()) {
+        Some(name) => Some(parse_timezone(name).ok_or(StatusCode::BAD_REQUEST)?),
+        None => None,
+    };
+
+    // Try cache first (only for non-authenticated requests to avoid leaking user data)
+    if maybe_claims.is_none() {
+        let cache_key = event_detail_cache_key(&event_identifier);
+        if let Some(redis) = &db.redis {
+            let mut redis_clone = redis.clone();
+            if let Ok(Some(cached)) = redis_clone.get(&cache_key).await {
+                tracing::debug!("Cache HIT for event detail: {}", cache_key);
+                if let Ok(mut cached_value) = serde_json::from_str::<serde_json::Value>(&cached) {
+                    if let Some(tz) = viewer_tz {
+                        localize_event_json(&mut cached_value["data"], tz);
+                    }
+                    return Ok(Json(cached_value));
+                }
+            } else {
+                tracing::debug!("Cache MISS for event detail: {}", cache_key);
+            }
+        }
+    }
+
+    ensure_event_rsvps_table(&db).await?;
+
+    let query = r#"
+        SELECT
+            e.id,
+            e.title,
+            e.description,
+            e.status,
+            e.event_type,
+            e.cover_image,
+            e.start_time,
+            e.end_time,
+            e.timezone,
+            e.location,
+            e.virtual_link,
+            e.is_live,
+            e.latitude,
+            e.longitude,
+            e.max_attendees,
+            e.max_guests_per_rsvp,
+            e.is_public,
+            e.is_premium,
+            e.price,
+            e.agenda,
+            e.tags,
+            e.created_at,
+            e.updated_at,
+            e.host_id,
+            u.display_name AS host_name,
+            u.username AS host_username,
+            u.avatar_url AS host_avatar,
+            COALESCE(rsvp_counts.count, 0) AS rsvp_count,
+            NULL::TEXT AS user_rsvp_status,
+            NULL::BOOLEAN AS user_rsvp_is_paid
+        FROM events e
+        LEFT JOIN users u ON e.host_id = u.id
+        LEFT JOIN (
+            SELECT event_id, COUNT(*)::BIGINT AS count
+            FROM event_rsvps
+            WHERE UPPER(TRIM(status)) = 'GOING'
+            GROUP BY event_id
+        ) rsvp_counts ON rsvp_counts.event_id = e.id::TEXT
+        WHERE e.id::TEXT = $1
+        LIMIT 1
     "#;
 
     match sqlx::query(query)
@@ -753,6 +1619,11 @@ async fn get_event_by_id(
     {
         Ok(Some(row)) => {
             let mut event = EventResponse::from_row(&row);
+            event.ticket_types = load_ticket_types(&db, &event_identifier)
+                .await?
+                .iter()
+                .map(|ticket_type| ticket_type.to_json())
+                .collect();
 
             let has_user_data = if let Some(claims) = maybe_claims {
                 if let Ok(Some(rsvp_row)) = sqlx::query(
@@ -796,6 +1667,11 @@ async fn get_event_by_id(
                 }
             }
 
+            let mut response = response;
+            if let Some(tz) = viewer_tz {
+                localize_event_json(&mut response["data"], tz);
+            }
+
             Ok(Json(response))
         }
         Ok(None) => Err(StatusCode::NOT_FOUND),
@@ -806,6 +1682,141 @@ async fn get_event_by_id(
     }
 }
 
+// Single event as an .ics file for "Add to calendar"
+async fn get_event_ics(
+    State(db): State<Database>,
+    Path(id): Path<String>,
+    MaybeClaims(maybe_claims): MaybeClaims,
+) -> Result<Response, StatusCode> {
+    let row = sqlx::query(
+        r#"
+        SELECT e.id, e.title, e.description, e.status, e.start_time, e.end_time, e.timezone,
+               e.location, e.virtual_link, e.host_id, e.updated_at,
+               COALESCE(u.display_name, u.username) AS host_name,
+               NULL::TEXT AS rsvp_status
+        FROM events e
+        LEFT JOIN users u ON e.host_id = u.id
+        WHERE e.id::TEXT = $1
+        "#,
+    )
+    .bind(&id)
+    .fetch_optional(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to load event {} for calendar export: {}", id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?
+    .ok_or(StatusCode::NOT_FOUND)?;
+
+    // Drafts are only exportable by their host.
+    let host_id: String = row.get("host_id");
+    let status: Option<String> = row.try_get("status").unwrap_or(None);
+    let is_host = maybe_claims
+        .as_ref()
+        .map(|claims| claims.sub == host_id)
+        .unwrap_or(false);
+    if status.as_deref() == Some("DRAFT") && !is_host {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    let event = calendar_event_from_row(&row);
+    let calendar = ics::render_calendar(&event.title.clone(), &[event]);
+    Ok(calendar_response(&format!("event-{}.ics", id), calendar))
+}
+
+/// Events the user hosts or has RSVP'd to (going or maybe), from 30 days ago onwards.
+pub(crate) async fn load_calendar_events(
+    db: &Database,
+    user_id: &str,
+) -> Result<Vec<CalendarEvent>, StatusCode> {
+    ensure_event_rsvps_table(db).await?;
+
+    let rows = sqlx::query(
+        r#"
+        SELECT e.id, e.title, e.description, e.status, e.start_time, e.end_time, e.timezone,
+               e.location, e.virtual_link, e.host_id, e.updated_at,
+               COALESCE(u.display_name, u.username) AS host_name,
+               r.status AS rsvp_status
+        FROM events e
+        LEFT JOIN users u ON e.host_id = u.id
+        LEFT JOIN event_rsvps r ON r.event_id = e.id::TEXT AND r.user_id = $1
+        WHERE COALESCE(e.end_time, e.start_time) >= NOW() - INTERVAL '30 days'
+          AND (
+              e.host_id = $1
+              OR EXISTS(SELECT 1 FROM event_cohosts c WHERE c.event_id = e.id::TEXT AND c.user_id = $1)
+              OR (UPPER(TRIM(r.status)) IN ('GOING', 'MAYBE') AND COALESCE(e.status, '') <> 'DRAFT')
+          )
+        ORDER BY e.start_time ASC
+        LIMIT 500
+        "#,
+    )
+    .bind(user_id)
+    .fetch_all(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to load calendar events for {}: {}", user_id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    Ok(rows.iter().map(calendar_event_from_row).collect())
+}
+
+fn calendar_event_from_row(row: &PgRow) -> CalendarEvent {
+    let id: String = row
+        .try_get::<Uuid, _>("id")
+        .map(|uuid| uuid.to_string())
+        .unwrap_or_else(|_| row.get::<String, _>("id"));
+    let start: chrono::DateTime<chrono::Utc> = row.get("start_time");
+    let frontend_url =
+        std::env::var("FRONTEND_URL").unwrap_or_else(|_| "http://localhost:3000".to_string());
+    let rsvp_status: Option<String> = row.try_get("rsvp_status").unwrap_or(None);
+
+    CalendarEvent {
+        url: Some(format!("{}/events/{}", frontend_url.trim_end_matches('/'), id)),
+        id,
+        title: row.get("title"),
+        description: row.try_get("description").unwrap_or(None),
+        start,
+        end: row
+            .try_get::<Option<chrono::DateTime<chrono::Utc>>, _>("end_time")
+            .unwrap_or(None)
+            .unwrap_or(start),
+        timezone: row.try_get("timezone").unwrap_or(None),
+        location: row
+            .try_get::<Option<String>, _>("location")
+            .unwrap_or(None)
+            .filter(|location| !location.trim().is_empty()),
+        virtual_link: row
+            .try_get::<Option<String>, _>("virtual_link")
+            .unwrap_or(None)
+            .filter(|link| !link.trim().is_empty()),
+        host_name: row.try_get("host_name").unwrap_or(None),
+        status: row
+            .try_get::<Option<String>, _>("status")
+            .unwrap_or(None)
+            .unwrap_or_default(),
+        tentative: rsvp_status
+            .map(|status| status.trim().eq_ignore_ascii_case("MAYBE"))
+            .unwrap_or(false),
+        updated_at: row.get("updated_at"),
+    }
+}
+
+pub(crate) fn calendar_response(filename: &str, calendar: String) -> Response {
+    (
+        [
+            (header::CONTENT_TYPE, "text/calendar; charset=utf-8".to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("inline; filename=\"{}\"", filename),
+            ),
+            (header::CACHE_CONTROL, "private, max-age=300".to_string()),
+        ],
+        calendar,
+    )
+        .into_response()
+}
+
 async fn get_event_ticket(
     State(db): State<Database>,
     Path(id): Path<String>,
@@ -830,6 +1841,7 @@ async fn get_event_ticket(
             e.location,
             e.virtual_link,
             e.max_attendees,
+            e.max_guests_per_rsvp,
             e.is_public,
             e.is_premium,
             e.price,
@@ -873,9 +1885,11 @@ async fn get_event_ticket(
 
     let rsvp_row = sqlx::query(
         r#"
-        SELECT status, is_paid
-        FROM event_rsvps
-        WHERE event_id = $1 AND user_id = $2
+        SELECT r.status, r.is_paid, r.checked_in_at, r.guest_count, t.id AS ticket_type_id, t.name AS ticket_type_name,
+               t.price AS ticket_type_price
+        FROM event_rsvps r
+        LEFT JOIN event_ticket_types t ON t.id = r.ticket_type_id
+        WHERE r.event_id = $1 AND r.user_id = $2
         "#,
     )
     .bind(&event_identifier)
@@ -901,7 +1915,19 @@ async fn get_event_ticket(
         return Err(StatusCode::FORBIDDEN);
     }
 
-    if event.price > 0.0 && !is_paid {
+    let checked_in_at: Option<chrono::DateTime<chrono::Utc>> =
+        rsvp_row.try_get("checked_in_at").unwrap_or(None);
+    let ticket_type_id: Option<Uuid> = rsvp_row.try_get("ticket_type_id").unwrap_or(None);
+    let ticket_type_price: Option<f64> = rsvp_row.try_get("ticket_type_price").unwrap_or(None);
+    let ticket_type = ticket_type_id.map(|ticket_type_id| {
+        json!({
+            "id": ticket_type_id,
+            "name": rsvp_row.try_get::<Option<String>, _>("ticket_type_name").unwrap_or(None),
+            "price": ticket_type_price,
+        })
+    });
+
+    if ticket_type_price.unwrap_or(event.price) > 0.0 && !is_paid {
         return Err(StatusCode::FORBIDDEN);
     }
 
@@ -928,21 +1954,23 @@ async fn get_event_ticket(
         .or(claims.username.clone())
         .unwrap_or_else(|| "Guest Attendee".to_string());
 
-    let attendee_email = claims.email.clone().unwrap_or_else(|| "".to_string());
-
-    let short_event = event_identifier
-        .chars()
-        .filter(|c| c.is_ascii_alphanumeric())
-        .take(6)
-        .collect::<String>()
-        .to_uppercase();
-    let short_user = user_id
-        .chars()
-        .filter(|c| c.is_ascii_alphanumeric())
-        .take(6)
-        .collect::<String>()
-        .to_uppercase();
-    let ticket_code = format!("TCK-{}-{}", short_event, short_user);
+    let attendee_email = claims.email.clone().unwrap_or_default();
+
+    let ticket_code = ticket_code(&event_identifier, &user_id);
+
+    // Guests get their own codes derived from the attendee's, so each can be scanned at the door.
+    let guest_count: i32 = rsvp_row
+        .try_get::<Option<i32>, _>("guest_count")
+        .unwrap_or(None)
+        .unwrap_or(0);
+    let guest_tickets: Vec<serde_json::Value> = (1..=guest_count)
+        .map(|number| {
+            json!({
+                "guestNumber": number,
+                "ticketCode": format!("{}-G{}", ticket_code, number),
+            })
+        })
+        .collect();
 
     let event_json = json!({
         "id": event.id,
@@ -963,9 +1991,12 @@ async fn get_event_ticket(
         "id": format!("{}:{}", event_identifier, user_id.clone()),
         "ticketCode": ticket_code,
         "status": "GOING",
-        "checkedIn": false,
-        "checkedInAt": serde_json::Value::Null,
+        "checkedIn": checked_in_at.is_some(),
+        "checkedInAt": checked_in_at,
         "isPaid": is_paid,
+        "ticketType": ticket_type,
+        "guestCount": guest_count,
+        "guestTickets": guest_tickets,
         "event": event_json,
         "user": {
             "id": user_id,
@@ -985,13 +2016,18 @@ async fn create_event_payment_intent(
     State(db): State<Database>,
     Path(id): Path<String>,
     claims: Claims,
+    payload: Option<Json<PaymentIntentRequest>>,
 ) -> Result<Json<serde_json::Value>, StatusCode> {
+    let Json(payload) = payload.unwrap_or_default();
+    ensure_not_revoked(&db, &id, &claims.sub).await?;
+    let invite_id =
+        check_invite_access(&db, &id, &claims.sub, payload.invite_token.as_deref()).await?;
     let event_identifier = id.clone();
 
     // Get the event to check price
     let event_row = sqlx::query(
         r#"
-        SELECT id, title, price, is_premium
+        SELECT id, title, price, is_premium, max_guests_per_rsvp, host_id
         FROM events
         WHERE id::TEXT = $1
         LIMIT 1
@@ -1009,55 +2045,67 @@ async fn create_event_payment_intent(
         return Err(StatusCode::NOT_FOUND);
     };
 
-    let price: f64 = row.try_get("price").unwrap_or(0.0);
-    let is_premium: bool = row.try_get("is_premium").unwrap_or(false);
+    let mut price: f64 = row.try_get("price").unwrap_or(0.0);
+
+    // Events with ticket tiers are paid per tier; the event price only applies without tiers.
+    let has_ticket_types = !load_ticket_types(&db, &event_identifier).await?.is_empty();
+    let ticket_type = match (has_ticket_types, payload.ticket_type_id) {
+        (true, Some(ticket_type_id)) => Some(
+            find_purchasable_ticket_type(&db, &event_identifier, ticket_type_id).await?,
+        ),
+        (true, None) => return Err(StatusCode::BAD_REQUEST),
+        (false, _) => None,
+    };
+    if let Some(ticket_type) = &ticket_type {
+        price = ticket_type.price;
+    }
 
     if price <= 0.0 {
         return Err(StatusCode::BAD_REQUEST);
     }
 
-    // Get Stripe secret key
-    let stripe_secret = std::env::var("STRIPE_SECRET_KEY")
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
-
-    if stripe_secret.trim().is_empty() {
-        tracing::error!("Stripe secret key not configured");
-        return Err(StatusCode::INTERNAL_SERVER_ERROR);
+    let max_guests: i32 = row
+        .try_get::<Option<i32>, _>("max_guests_per_rsvp")
+        .unwrap_or(None)
+        .unwrap_or(0);
+    let guests = requested_guests(payload.guests, max_guests)?;
+    let seats = 1 + guests as i64;
+
+    // Checked before charging; the seats are only held once the payment succeeds.
+    let mut tx = db.pool.begin().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    reserve_event_capacity(&mut tx, &event_identifier, &claims.sub, seats).await?;
+    if let Some(ticket_type) = &ticket_type {
+        reserve_ticket_type(&mut tx, &event_identifier, ticket_type.id, &claims.sub, seats).await?;
     }
+    tx.rollback().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let price = price * seats as f64;
 
     // Create payment intent via Stripe API
     let amount_cents = (price * 100.0) as i64;
-    let client = reqwest::Client::new();
-
-    let params = [
-        ("amount", amount_cents.to_string()),
-        ("currency", "usd".to_string()),
-        ("metadata[event_id]", event_identifier.clone()),
-        ("metadata[user_id]", claims.sub.clone()),
-        ("automatic_payment_methods[enabled]", "true".to_string()),
+    let host_id: String = row.try_get("host_id").map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let title: String = row.try_get("title").unwrap_or_default();
+    let platform_fee = quote_platform_fee(&db, ProductType::Ticket, &host_id, amount_cents).await?;
+
+    let mut metadata = vec![
+        ("event_id", event_identifier.clone()),
+        ("user_id", claims.sub.clone()),
+        ("guest_count", guests.to_string()),
     ];
-
-    let response = client
-        .post("https://api.stripe.com/v1/payment_intents")
-        .header("Authorization", format!("Bearer {}", stripe_secret))
-        .form(&params)
-        .send()
-        .await
-        .map_err(|err| {
-            tracing::error!("Failed to create Stripe payment intent: {:?}", err);
-            StatusCode::BAD_GATEWAY
-        })?;
-
-    if !response.status().is_success() {
-        let body = response.text().await.unwrap_or_default();
-        tracing::error!("Stripe returned error: {}", body);
-        return Err(StatusCode::BAD_GATEWAY);
+    if let Some(ticket_type) = &ticket_type {
+        metadata.push(("ticket_type_id", ticket_type.id.to_string()));
+    }
+    if let Some(invite_id) = invite_id {
+        metadata.push(("invite_id", invite_id.to_string()));
     }
 
-    let payment_intent: serde_json::Value = response.json().await.map_err(|err| {
-        tracing::error!("Failed to parse Stripe response: {:?}", err);
-        StatusCode::BAD_GATEWAY
-    })?;
+    let payment_intent = create_payment_intent(PaymentIntentSpec {
+        amount_cents,
+        currency: "usd",
+        description: &title,
+        metadata,
+        platform_fee: &platform_fee,
+    })
+    .await?;
 
     let client_secret = payment_intent
         .get("client_secret")
@@ -1066,11 +2114,33 @@ async fn create_event_payment_intent(
             tracing::error!("No client_secret in Stripe response");
             StatusCode::INTERNAL_SERVER_ERROR
         })?;
+    let payment_intent_id = payment_intent
+        .get("id")
+        .and_then(|v| v.as_str())
+        .ok_or(StatusCode::BAD_GATEWAY)?;
+
+    let payment_id = platform_fee
+        .record(
+            &db,
+            LedgerSource {
+                source_type: "EVENT_TICKET",
+                source_id: event_identifier.clone(),
+                payer_id: &claims.sub,
+                currency: "usd",
+                stripe_checkout_session_id: None,
+                stripe_payment_intent_id: Some(payment_intent_id),
+            },
+        )
+        .await?;
 
     Ok(Json(json!({
         "success": true,
         "data": {
-            "clientSecret": client_secret
+            "clientSecret": client_secret,
+            "paymentId": payment_id,
+            "amount": price,
+            "guestCount": guests,
+            "ticketTypeId": ticket_type.map(|ticket_type| ticket_type.id)
         }
     })))
 }
@@ -1081,6 +2151,9 @@ struct CompleteRsvpRequest {
     payment_intent_id: String,
 }
 
+// Reports where the attendee's payment stands once Stripe.js returns, including after a 3D Secure
+// challenge. The seat itself is only taken by the payment_intent.succeeded webhook, so calling
+// this cannot claim one.
 async fn complete_event_rsvp(
     State(db): State<Database>,
     Path(id): Path<String>,
@@ -1089,115 +2162,287 @@ async fn complete_event_rsvp(
 ) -> Result<Json<serde_json::Value>, StatusCode> {
     ensure_event_rsvps_table(&db).await?;
 
-    let event_identifier = id.clone();
-    let user_id = claims.sub.clone();
+    let payment_intent = fetch_payment_intent(&payload.payment_intent_id).await?;
+    let metadata = |key: &str| {
+        payment_intent
+            .pointer(&format!("/metadata/{}", key))
+            .and_then(|v| v.as_str())
+    };
+    if metadata("event_id") != Some(id.as_str()) || metadata("user_id") != Some(claims.sub.as_str()) {
+        return Err(StatusCode::FORBIDDEN);
+    }
+    let payment_status = payment_intent
+        .get("status")
+        .and_then(|v| v.as_str())
+        .unwrap_or_default();
+    if !matches!(payment_status, "succeeded" | "processing") {
+        tracing::error!("Payment not succeeded, status: {}", payment_status);
+        return Err(StatusCode::PAYMENT_REQUIRED);
+    }
 
-    // Verify the payment with Stripe
-    let stripe_secret = std::env::var("STRIPE_SECRET_KEY")
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let rsvp = sqlx::query_as::<_, (Option<i32>, Option<Uuid>)>(
+        r#"
+        SELECT guest_count, ticket_type_id FROM event_rsvps
+        WHERE event_id = $1 AND user_id = $2 AND payment_intent_id = $3 AND is_paid = true
+        "#,
+    )
+    .bind(&id)
+    .bind(&claims.sub)
+    .bind(&payload.payment_intent_id)
+    .fetch_optional(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to load paid RSVP for event {}: {}", id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
 
-    if stripe_secret.trim().is_empty() {
-        return Err(StatusCode::INTERNAL_SERVER_ERROR);
-    }
+    let rsvp_count = sqlx::query_scalar::<_, i64>(
+        "SELECT COUNT(*)::BIGINT FROM event_rsvps WHERE event_id = $1 AND UPPER(TRIM(status)) = 'GOING'",
+    )
+    .bind(&id)
+    .fetch_one(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to count RSVPs: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
 
-    let client = reqwest::Client::new();
-    let response = client
-        .get(format!(
-            "https://api.stripe.com/v1/payment_intents/{}",
-            payload.payment_intent_id
-        ))
-        .header("Authorization", format!("Bearer {}", stripe_secret))
-        .send()
-        .await
-        .map_err(|err| {
-            tracing::error!("Failed to verify payment intent: {:?}", err);
-            StatusCode::BAD_GATEWAY
-        })?;
+    Ok(Json(json!({
+        "success": true,
+        "data": {
+            "status": if rsvp.is_some() { "GOING" } else { "PENDING" },
+            "isPaid": rsvp.is_some(),
+            "paymentStatus": payment_status,
+            "guestCount": rsvp.and_then(|(guests, _)| guests),
+            "ticketTypeId": rsvp.and_then(|(_, ticket_type_id)| ticket_type_id),
+            "rsvpCount": rsvp_count
+        }
+    })))
+}
 
-    if !response.status().is_success() {
-        let body = response.text().await.unwrap_or_default();
-        tracing::error!("Stripe returned error: {}", body);
-        return Err(StatusCode::BAD_GATEWAY);
+/// Seat an attendee whose ticket PaymentIntent succeeded. The tier, guests and invite come from
+/// the metadata set when the intent was created, so they cannot be swapped after paying; an
+/// attendee already seated with this payment is left as is.
+pub(crate) async fn seat_paid_attendee(
+    db: &Database,
+    payment_intent_id: &str,
+    metadata: &HashMap<String, String>,
+    amount_cents: i64,
+) -> Result<(), StatusCode> {
+    let (Some(event_id), Some(user_id)) = (metadata.get("event_id"), metadata.get("user_id")) else {
+        return Err(StatusCode::UNPROCESSABLE_ENTITY);
+    };
+    let ticket_type_id = metadata
+        .get("ticket_type_id")
+        .and_then(|v| Uuid::parse_str(v).ok());
+    let invite_id = metadata.get("invite_id").and_then(|v| Uuid::parse_str(v).ok());
+    let guests = metadata
+        .get("guest_count")
+        .and_then(|v| v.parse::<i32>().ok())
+        .unwrap_or(0)
+        .max(0);
+
+    ensure_event_rsvps_table(db).await?;
+    let seated = sqlx::query_scalar::<_, bool>(
+        r#"
+        SELECT EXISTS(
+            SELECT 1 FROM event_rsvps
+            WHERE event_id = $1 AND user_id = $2 AND payment_intent_id = $3 AND is_paid = true
+        )
+        "#,
+    )
+    .bind(event_id)
+    .bind(user_id)
+    .bind(payment_intent_id)
+    .fetch_one(&db.pool)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    if seated {
+        return Ok(());
     }
+    ensure_not_revoked(db, event_id, user_id).await?;
 
-    let payment_intent: serde_json::Value = response.json().await.map_err(|err| {
-        tracing::error!("Failed to parse Stripe response: {:?}", err);
-        StatusCode::BAD_GATEWAY
+    let mut tx = db.pool.begin().await.map_err(|e| {
+        tracing::error!("Failed to start RSVP transaction: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
     })?;
 
-    let payment_status = payment_intent
-        .get("status")
-        .and_then(|v| v.as_str())
-        .unwrap_or("")
-        .to_lowercase();
-
-    if payment_status != "succeeded" {
-        tracing::error!("Payment not succeeded, status: {}", payment_status);
-        return Err(StatusCode::PAYMENT_REQUIRED);
+    reserve_event_capacity(&mut tx, event_id, user_id, 1 + guests as i64).await?;
+    if let Some(ticket_type_id) = ticket_type_id {
+        reserve_ticket_type(&mut tx, event_id, ticket_type_id, user_id, 1 + guests as i64).await?;
+    }
+    // The invite was checked when the payment intent was created; the attendee has paid since.
+    if let Some(invite_id) = invite_id {
+        redeem_invite(&mut tx, invite_id, false).await?;
     }
 
-    // Update or create RSVP with is_paid=true
     sqlx::query(
         r#"
-        INSERT INTO event_rsvps (event_id, user_id, status, is_paid, created_at, updated_at)
-        VALUES ($1, $2, 'GOING', true, NOW(), NOW())
+        INSERT INTO event_rsvps (
+            event_id, user_id, status, is_paid, ticket_type_id, payment_intent_id, amount_paid,
+            invite_id, guest_count, created_at, updated_at
+        )
+        VALUES ($1, $2, 'GOING', true, $3, $4, $5, $6, $7, NOW(), NOW())
         ON CONFLICT (event_id, user_id)
         DO UPDATE SET
             status = 'GOING',
             is_paid = true,
+            ticket_type_id = COALESCE(EXCLUDED.ticket_type_id, event_rsvps.ticket_type_id),
+            payment_intent_id = EXCLUDED.payment_intent_id,
+            amount_paid = EXCLUDED.amount_paid,
+            invite_id = COALESCE(event_rsvps.invite_id, EXCLUDED.invite_id),
+            guest_count = EXCLUDED.guest_count,
+            refunded_at = NULL,
             updated_at = NOW()
         "#,
     )
-    .bind(&event_identifier)
-    .bind(&user_id)
-    .execute(&db.pool)
+    .bind(event_id)
+    .bind(user_id)
+    .bind(ticket_type_id)
+    .bind(payment_intent_id)
+    .bind(amount_cents as f64 / 100.0)
+    .bind(invite_id)
+    .bind(guests)
+    .execute(&mut tx)
     .await
     .map_err(|e| {
         tracing::error!("Failed to update RSVP after payment: {}", e);
         StatusCode::INTERNAL_SERVER_ERROR
     })?;
 
-    // Get updated RSVP count
-    let rsvp_count = sqlx::query_scalar::<_, i64>(
-        "SELECT COUNT(*)::BIGINT FROM event_rsvps WHERE event_id = $1 AND UPPER(TRIM(status)) = 'GOING'",
+    tx.commit().await.map_err(|e| {
+        tracing::error!("Failed to commit RSVP after payment: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    invalidate_event_cache(db, event_id).await;
+    notify_host_of_rsvp(db, event_id, user_id, "GOING", guests).await;
+    // Report what was charged, which differs from the event price for ticket tiers.
+    if let Some(amqp) = &db.amqp {
+        if let Err(e) = amqp
+            .send_payment_confirmation(event_id.clone(), user_id.clone(), amount_cents as f64 / 100.0)
+            .await
+        {
+            tracing::warn!("Failed to send payment confirmation notification: {}", e);
+        }
+    }
+    Ok(())
+}
+
+/// A single paid seat at an event, priced for a cart checkout.
+pub(crate) struct TicketQuote {
+    pub host_id: String,
+    pub title: String,
+    pub ticket_type_id: Option<Uuid>,
+    pub amount_cents: i64,
+}
+
+/// Price one seat for `user_id` and check it is still available. Carts carry no invite token or
+/// guests, so invite-only events and plus-ones go through the event page instead.
+pub(crate) async fn quote_ticket(
+    db: &Database,
+    event_id: &str,
+    user_id: &str,
+    ticket_type_id: Option<Uuid>,
+) -> Result<TicketQuote, StatusCode> {
+    ensure_not_revoked(db, event_id, user_id).await?;
+    check_invite_access(db, event_id, user_id, None).await?;
+
+    let row = sqlx::query("SELECT title, price, host_id FROM events WHERE id::TEXT = $1 LIMIT 1")
+        .bind(event_id)
+        .fetch_optional(&db.pool)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to load event {}: {}", event_id, e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    let has_ticket_types = !load_ticket_types(db, event_id).await?.is_empty();
+    let price = match (has_ticket_types, ticket_type_id) {
+        (true, Some(ticket_type_id)) => find_purchasable_ticket_type(db, event_id, ticket_type_id).await?.price,
+        (true, None) => return Err(StatusCode::BAD_REQUEST),
+        (false, _) => row.try_get::<Option<f64>, _>("price").ok().flatten().unwrap_or(0.0),
+    };
+    let amount_cents = (price * 100.0).round() as i64;
+    if amount_cents <= 0 {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let mut tx = db.pool.begin().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    reserve_event_capacity(&mut tx, event_id, user_id, 1).await?;
+    let ticket_type_id = ticket_type_id.filter(|_| has_ticket_types);
+    if let Some(ticket_type_id) = ticket_type_id {
+        reserve_ticket_type(&mut tx, event_id, ticket_type_id, user_id, 1).await?;
+    }
+    tx.rollback().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(TicketQuote {
+        host_id: row.try_get("host_id").map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?,
+        title: row.try_get("title").unwrap_or_default(),
+        ticket_type_id,
+        amount_cents,
+    })
+}
+
+/// Seat a buyer whose ticket was paid for in a cart checkout. Fails when the event filled up
+/// between checkout and payment; the caller refunds the ticket then.
+pub(crate) async fn fulfill_ticket(
+    db: &Database,
+    event_id: &str,
+    user_id: &str,
+    ticket_type_id: Option<Uuid>,
+    payment_intent_id: Option<&str>,
+    amount_cents: i64,
+) -> Result<(), StatusCode> {
+    ensure_event_rsvps_table(db).await?;
+    let mut tx = db.pool.begin().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    reserve_event_capacity(&mut tx, event_id, user_id, 1).await?;
+    if let Some(ticket_type_id) = ticket_type_id {
+        reserve_ticket_type(&mut tx, event_id, ticket_type_id, user_id, 1).await?;
+    }
+    sqlx::query(
+        r#"
+        INSERT INTO event_rsvps (
+            event_id, user_id, status, is_paid, ticket_type_id, payment_intent_id, amount_paid,
+            guest_count, created_at, updated_at
+        )
+        VALUES ($1, $2, 'GOING', true, $3, $4, $5, 0, NOW(), NOW())
+        ON CONFLICT (event_id, user_id)
+        DO UPDATE SET
+            status = 'GOING',
+            is_paid = true,
+            ticket_type_id = COALESCE(EXCLUDED.ticket_type_id, event_rsvps.ticket_type_id),
+            payment_intent_id = EXCLUDED.payment_intent_id,
+            amount_paid = EXCLUDED.amount_paid,
+            refunded_at = NULL,
+            updated_at = NOW()
+        "#,
     )
-    .bind(&event_identifier)
-    .fetch_one(&db.pool)
+    .bind(event_id)
+    .bind(user_id)
+    .bind(ticket_type_id)
+    .bind(payment_intent_id)
+    .bind(amount_cents as f64 / 100.0)
+    .execute(&mut tx)
     .await
     .map_err(|e| {
-        tracing::error!("Failed to count RSVPs: {}", e);
+        tracing::error!("Failed to seat cart buyer {} at event {}: {}", user_id, event_id, e);
         StatusCode::INTERNAL_SERVER_ERROR
     })?;
+    tx.commit().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
-    // Invalidate cache after payment completion
-    invalidate_event_cache(&db, &event_identifier).await;
-
-    // Send payment confirmation notification via AMQP
+    invalidate_event_cache(db, event_id).await;
     if let Some(amqp) = &db.amqp {
-        let price: f64 = sqlx::query_scalar("SELECT price FROM events WHERE id::TEXT = $1")
-            .bind(&event_identifier)
-            .fetch_optional(&db.pool)
+        if let Err(e) = amqp
+            .send_payment_confirmation(event_id.to_string(), user_id.to_string(), amount_cents as f64 / 100.0)
             .await
-            .unwrap_or(None)
-            .unwrap_or(0.0);
-
-        if let Err(e) = amqp.send_payment_confirmation(
-            event_identifier.clone(),
-            user_id.clone(),
-            price,
-        ).await {
+        {
             tracing::warn!("Failed to send payment confirmation notification: {}", e);
         }
     }
-
-    Ok(Json(json!({
-        "success": true,
-        "data": {
-            "status": "GOING",
-            "isPaid": true,
-            "rsvpCount": rsvp_count
-        }
-    })))
+    Ok(())
 }
 
 async fn create_event(
@@ -1205,6 +2450,9 @@ async fn create_event(
     claims: Claims,
     Json(payload): Json<CreateEventRequest>,
 ) -> Result<Json<serde_json::Value>, StatusCode> {
+    let timezone = validate_event_timezone(&payload.timezone)?;
+    let (latitude, longitude) = validate_coordinates(payload.latitude, payload.longitude)?;
+    let max_guests = validate_max_guests(payload.max_guests_per_rsvp)?;
     let start_time = chrono::DateTime::parse_from_rfc3339(&payload.start_time)
         .map_err(|_| StatusCode::BAD_REQUEST)?
         .with_timezone(&chrono::Utc);
@@ -1239,6 +2487,9 @@ async fn create_event(
                 price,
                 agenda,
                 tags,
+                latitude,
+                longitude,
+                max_guests_per_rsvp,
                 created_at,
                 updated_at
             )
@@ -1247,7 +2498,7 @@ async fn create_event(
                 $1, $2, $3, $4, $5,
                 $6, $7, $8, $9, $10,
                 $11, $12, $13, $14, $15,
-                $16, $17, NOW(), NOW()
+                $16, $17, $18, $19, $20, NOW(), NOW()
             )
             RETURNING
                 id,
@@ -1261,7 +2512,10 @@ async fn create_event(
                 timezone,
                 location,
                 virtual_link,
+                latitude,
+                longitude,
                 max_attendees,
+                max_guests_per_rsvp,
                 is_public,
                 is_premium,
                 price,
@@ -1283,7 +2537,10 @@ async fn create_event(
             inserted.timezone,
             inserted.location,
             inserted.virtual_link,
+            inserted.latitude,
+            inserted.longitude,
             inserted.max_attendees,
+            inserted.max_guests_per_rsvp,
             inserted.is_public,
             inserted.is_premium,
             inserted.price,
@@ -1316,7 +2573,7 @@ async fn create_event(
         .bind(payload.cover_image.clone())
         .bind(start_time)
         .bind(end_time)
-        .bind(payload.timezone.clone())
+        .bind(timezone)
         .bind(payload.location.clone())
         .bind(payload.virtual_link.clone())
         .bind(payload.max_attendees)
@@ -1325,6 +2582,9 @@ async fn create_event(
         .bind(payload.price.unwrap_or(0.0))
         .bind(payload.agenda.clone())
         .bind(payload.tags.clone())
+        .bind(latitude)
+        .bind(longitude)
+        .bind(max_guests.unwrap_or(0))
         .fetch_one(&db.pool)
         .await
         .map_err(|e| {
@@ -1332,6 +2592,154 @@ async fn create_event(
             StatusCode::INTERNAL_SERVER_ERROR
         })?;
 
+    publish_to_feed(&db, &row);
+
+    Ok(Json(json!({
+        "success": true,
+        "data": EventResponse::from_row(&row)
+    })))
+}
+
+// Events sit in feeds at their start time
+fn publish_to_feed(db: &Database, row: &PgRow) {
+    let (Ok(id), Ok(host_id), Ok(start_time)) = (
+        row.try_get::<Uuid, _>("id"),
+        row.try_get::<String, _>("host_id"),
+        row.try_get::<chrono::DateTime<chrono::Utc>, _>("start_time"),
+    ) else {
+        return;
+    };
+    feed_timeline::publish(db, &host_id, format!("event-{}", id), start_time);
+}
+
+// Host and co-hosts can edit an event; omitted fields keep their current value
+async fn update_event(
+    State(db): State<Database>,
+    Path(id): Path<String>,
+    claims: Claims,
+    Json(payload): Json<UpdateEventRequest>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    ensure_event_manager(&db, &id, &claims.sub).await?;
+
+    let parse_time = |raw: &Option<String>| -> Result<Option<chrono::DateTime<chrono::Utc>>, StatusCode> {
+        raw.as_deref()
+            .map(|raw| {
+                chrono::DateTime::parse_from_rfc3339(raw)
+                    .map(|time| time.with_timezone(&chrono::Utc))
+                    .map_err(|_| StatusCode::BAD_REQUEST)
+            })
+            .transpose()
+    };
+    let start_time = parse_time(&payload.start_time)?;
+    let end_time = parse_time(&payload.end_time)?;
+    let timezone = validate_event_timezone(&payload.timezone)?;
+    let (latitude, longitude) = validate_coordinates(payload.latitude, payload.longitude)?;
+    let max_guests = validate_max_guests(payload.max_guests_per_rsvp)?;
+
+    if payload.title.as_deref().map(|title| title.trim().is_empty()).unwrap_or(false)
+        || payload.price.map(|price| !price.is_finite() || price < 0.0).unwrap_or(false)
+    {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let query = r#"
+        WITH updated AS (
+            UPDATE events
+            SET title = COALESCE($2, title),
+                description = COALESCE($3, description),
+                event_type = COALESCE($4, event_type),
+                status = COALESCE($5, status),
+                start_time = COALESCE($6, start_time),
+                end_time = COALESCE($7, end_time),
+                timezone = COALESCE($8, timezone),
+                location = COALESCE($9, location),
+                virtual_link = COALESCE($10, virtual_link),
+                max_attendees = COALESCE($11, max_attendees),
+                is_public = COALESCE($12, is_public),
+                is_premium = COALESCE($13, is_premium),
+                price = COALESCE($14, price),
+                cover_image = COALESCE($15, cover_image),
+                agenda = COALESCE($16, agenda),
+                tags = COALESCE($17, tags),
+                latitude = COALESCE($18, latitude),
+                longitude = COALESCE($19, longitude),
+                max_guests_per_rsvp = COALESCE($20, max_guests_per_rsvp),
+                updated_at = NOW()
+            WHERE id::TEXT = $1
+            RETURNING
+                id,
+                title,
+                description,
+                status,
+                event_type,
+                cover_image,
+                start_time,
+                end_time,
+                timezone,
+                location,
+                virtual_link,
+                is_live,
+                latitude,
+                longitude,
+                max_attendees,
+                max_guests_per_rsvp,
+                is_public,
+                is_premium,
+                price,
+                agenda,
+                tags,
+                created_at,
+                updated_at,
+                host_id
+        )
+        SELECT
+            updated.*,
+            u.display_name AS host_name,
+            u.username AS host_username,
+            u.avatar_url AS host_avatar,
+            (
+                SELECT COUNT(*)::BIGINT FROM event_rsvps r
+                WHERE r.event_id = updated.id::TEXT AND UPPER(TRIM(r.status)) = 'GOING'
+            ) AS rsvp_count,
+            NULL::TEXT AS user_rsvp_status,
+            NULL::BOOLEAN AS user_rsvp_is_paid
+        FROM updated
+        LEFT JOIN users u ON updated.host_id = u.id
+    "#;
+
+    let row = sqlx::query(query)
+        .bind(&id)
+        .bind(payload.title.as_deref().map(str::trim))
+        .bind(&payload.description)
+        .bind(&payload.type_field)
+        .bind(&payload.status)
+        .bind(start_time)
+        .bind(end_time)
+        .bind(&timezone)
+        .bind(&payload.location)
+        .bind(&payload.virtual_link)
+        .bind(payload.max_attendees)
+        .bind(payload.is_public)
+        .bind(payload.is_premium)
+        .bind(payload.price)
+        .bind(&payload.cover_image)
+        .bind(&payload.agenda)
+        .bind(&payload.tags)
+        .bind(latitude)
+        .bind(longitude)
+        .bind(max_guests)
+        .fetch_optional(&db.pool)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to update event {}: {}", id, e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    invalidate_event_cache(&db, &id).await;
+    // Re-adding moves the event in timelines when its start time changed
+    publish_to_feed(&db, &row);
+
     Ok(Json(json!({
         "success": true,
         "data": EventResponse::from_row(&row)
@@ -0,0 +1,446 @@
+use axum::{
+    extract::{Path, State},
+    http::{header, StatusCode},
+    response::{IntoResponse, Json, Response},
+    routing::{delete, get},
+    Router,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sqlx::Row;
+use uuid::Uuid;
+
+use crate::{auth::Claims, database::Database, routes::fees::ensure_admin};
+
+const EXPLORE_CACHE_KEY: &str = "feed:explore";
+const EXPLORE_CACHE_SECONDS: usize = 60;
+/// The explore feed is the same for everyone, so CDNs may serve it for a minute and keep
+/// serving a stale copy while they refetch.
+const EXPLORE_CACHE_CONTROL: &str = "public, max-age=60, s-maxage=60, stale-while-revalidate=120";
+const TRENDING_POSTS: i64 = 12;
+const CAMPAIGNS: i64 = 6;
+const UPCOMING_EVENTS: i64 = 6;
+/// Posts older than this only show when pinned.
+const TRENDING_DAYS: i32 = 7;
+
+/// What an editorial pin points at.
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum PinType {
+    Post,
+    Campaign,
+    Event,
+}
+
+impl PinType {
+    fn as_str(self) -> &'static str {
+        match self {
+            PinType::Post => "POST",
+            PinType::Campaign => "CAMPAIGN",
+            PinType::Event => "EVENT",
+        }
+    }
+
+    fn table(self) -> &'static str {
+        match self {
+            PinType::Post => "posts",
+            PinType::Campaign => "campaigns",
+            PinType::Event => "events",
+        }
+    }
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+struct ExplorePin {
+    id: Uuid,
+    /// `POST`, `CAMPAIGN` or `EVENT`.
+    item_type: String,
+    item_id: Uuid,
+    /// Pins sort by ascending position ahead of everything else in their section.
+    position: i32,
+    pinned_by: Option<String>,
+    expires_at: Option<DateTime<Utc>>,
+    created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PinRequest {
+    item_type: PinType,
+    item_id: Uuid,
+    position: Option<i32>,
+    expires_at: Option<DateTime<Utc>>,
+}
+
+pub fn explore_pin_routes() -> Router<Database> {
+    Router::new()
+        .route("/pins", get(list_pins).post(pin_item))
+        .route("/pins/:id", delete(unpin_item))
+}
+
+/// Trending public posts, campaigns nearing their goal and upcoming public events, with pinned
+/// items first. Public and the same for every visitor.
+pub(crate) async fn get_explore(State(db): State<Database>) -> Result<Response, StatusCode> {
+    if let Some(redis) = &db.redis {
+        let mut redis_clone = redis.clone();
+        if let Ok(Some(cached)) = redis_clone.get(EXPLORE_CACHE_KEY).await {
+            if let Ok(cached_value) = serde_json::from_str::<serde_json::Value>(&cached) {
+                return Ok(explore_response(cached_value));
+            }
+        }
+    }
+
+    // Engagement counts for the whole life of the post, decayed by its age in hours
+    let post_rows = sqlx::query(
+        r#"
+        WITH pins AS (
+            SELECT item_id, position FROM explore_pins
+            WHERE item_type = 'POST' AND (expires_at IS NULL OR expires_at > NOW())
+        ),
+        candidates AS (
+            SELECT
+                p.id,
+                p.title,
+                p.content,
+                p.media_url,
+                p.image_urls,
+                p.video_url,
+                p.created_at,
+                u.id AS creator_id,
+                COALESCE(u.display_name, u.username) AS creator_name,
+                u.username,
+                u.avatar_url,
+                pins.position AS pin_position,
+                (SELECT COUNT(*) FROM post_likes l WHERE l.post_id = p.id) AS likes,
+                (SELECT COUNT(*) FROM post_comments c WHERE c.post_id = p.id) AS comments,
+                (SELECT COALESCE(SUM(v.views), 0) FROM post_view_stats v WHERE v.post_id = p.id)::BIGINT AS views
+            FROM posts p
+            JOIN users u ON p.user_id = u.id
+            LEFT JOIN pins ON pins.item_id = p.id
+            WHERE NOT COALESCE(p.is_premium, FALSE)
+              AND COALESCE(p.published, TRUE)
+              AND COALESCE(p.published_at, p.created_at) <= NOW()
+              AND (pins.item_id IS NOT NULL OR p.created_at >= NOW() - make_interval(days => $1))
+        )
+        SELECT *,
+            (likes * 2 + comments * 3 + views / 10.0)
+                / POWER(EXTRACT(EPOCH FROM NOW() - created_at) / 3600 + 2, 1.5) AS trending_score
+        FROM candidates
+        ORDER BY pin_position ASC NULLS LAST, trending_score DESC, created_at DESC
+        LIMIT $2
+        "#,
+    )
+    .bind(TRENDING_DAYS)
+    .bind(TRENDING_POSTS)
+    .fetch_all(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to load trending posts for explore: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    let trending_posts: Vec<serde_json::Value> = post_rows
+        .into_iter()
+        .map(|row| {
+            let id: Uuid = row.get("id");
+            let creator_id: String = row.try_get("creator_id").unwrap_or_default();
+            let creator_username: Option<String> = row.try_get("username").ok();
+            let content: Option<String> = row.try_get("content").ok();
+            let image_urls: Option<Vec<String>> = row.try_get("image_urls").ok();
+            let cover_image = image_urls
+                .as_ref()
+                .and_then(|imgs| imgs.first().cloned())
+                .or_else(|| row.try_get::<Option<String>, _>("video_url").ok().flatten())
+                .or_else(|| row.try_get::<Option<String>, _>("media_url").ok().flatten());
+            json!({
+                "id": format!("post-{}", id),
+                "sourceId": id.to_string(),
+                "type": "post",
+                "title": row.try_get::<String, _>("title").unwrap_or_else(|_| "New post".to_string()),
+                "summary": content.as_ref().map(|c| c.trim().chars().take(160).collect::<String>()),
+                "coverImage": cover_image,
+                "publishedAt": row.get::<DateTime<Utc>, _>("created_at"),
+                "link": format!("/creators/{}", creator_username.clone().unwrap_or_else(|| creator_id.clone())),
+                "creator": {
+                    "id": creator_id,
+                    "name": row.try_get::<Option<String>, _>("creator_name").ok().flatten().unwrap_or_else(|| "Creator".to_string()),
+                    "username": creator_username,
+                    "avatar": row.try_get::<Option<String>, _>("avatar_url").ok().flatten(),
+                },
+                "isPinned": row.try_get::<Option<i32>, _>("pin_position").ok().flatten().is_some(),
+                "meta": {
+                    "likes": row.try_get::<i64, _>("likes").unwrap_or(0),
+                    "comments": row.try_get::<i64, _>("comments").unwrap_or(0),
+                    "views": row.try_get::<i64, _>("views").unwrap_or(0),
+                },
+            })
+        })
+        .collect();
+
+    // Campaigns still short of their goal, the closest first
+    let campaign_rows = sqlx::query(
+        r#"
+        WITH pins AS (
+            SELECT item_id, position FROM explore_pins
+            WHERE item_type = 'CAMPAIGN' AND (expires_at IS NULL OR expires_at > NOW())
+        )
+        SELECT
+            c.id,
+            c.title,
+            c.description,
+            c.slug,
+            c.cover_image,
+            c.goal_amount,
+            COALESCE(c.current_amount, 0) AS current_amount,
+            c.end_date,
+            c.created_at,
+            u.id AS creator_id,
+            COALESCE(u.display_name, u.username) AS creator_name,
+            u.username,
+            u.avatar_url,
+            pins.position AS pin_position
+        FROM campaigns c
+        JOIN users u ON c.creator_id = u.id
+        LEFT JOIN pins ON pins.item_id = c.id
+        WHERE UPPER(COALESCE(c.status, '')) = 'ACTIVE'
+          AND c.goal_amount > 0
+          AND (c.end_date IS NULL OR c.end_date > NOW())
+          AND (pins.item_id IS NOT NULL OR COALESCE(c.current_amount, 0) < c.goal_amount)
+        ORDER BY pins.position ASC NULLS LAST,
+                 COALESCE(c.current_amount, 0) / c.goal_amount DESC,
+                 c.created_at DESC
+        LIMIT $1
+        "#,
+    )
+    .bind(CAMPAIGNS)
+    .fetch_all(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to load campaigns for explore: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    let campaigns: Vec<serde_json::Value> = campaign_rows
+        .into_iter()
+        .map(|row| {
+            let id: Uuid = row.get("id");
+            let creator_id: String = row.try_get("creator_id").unwrap_or_default();
+            let goal_amount: f64 = row.try_get("goal_amount").unwrap_or(0.0);
+            let current_amount: f64 = row.try_get("current_amount").unwrap_or(0.0);
+            json!({
+                "id": format!("campaign-{}", id),
+                "sourceId": id.to_string(),
+                "type": "campaign",
+                "title": row.try_get::<String, _>("title").unwrap_or_default(),
+                "summary": row.try_get::<Option<String>, _>("description").ok().flatten(),
+                "coverImage": row.try_get::<Option<String>, _>("cover_image").ok().flatten(),
+                "publishedAt": row.get::<DateTime<Utc>, _>("created_at"),
+                "link": format!("/campaigns/{}", row.try_get::<String, _>("slug").unwrap_or_default()),
+                "creator": {
+                    "id": creator_id,
+                    "name": row.try_get::<Option<String>, _>("creator_name").ok().flatten().unwrap_or_else(|| "Creator".to_string()),
+                    "username": row.try_get::<Option<String>, _>("username").ok().flatten(),
+                    "avatar": row.try_get::<Option<String>, _>("avatar_url").ok().flatten(),
+                },
+                "isPinned": row.try_get::<Option<i32>, _>("pin_position").ok().flatten().is_some(),
+                "meta": {
+                    "goalAmount": goal_amount,
+                    "currentAmount": current_amount,
+                    "progress": if goal_amount > 0.0 { current_amount / goal_amount } else { 0.0 },
+                    "endDate": row.try_get::<Option<DateTime<Utc>>, _>("end_date").ok().flatten(),
+                },
+            })
+        })
+        .collect();
+
+    let event_rows = sqlx::query(
+        r#"
+        WITH pins AS (
+            SELECT item_id, position FROM explore_pins
+            WHERE item_type = 'EVENT' AND (expires_at IS NULL OR expires_at > NOW())
+        )
+        SELECT
+            e.id,
+            e.title,
+            e.description,
+            e.cover_image,
+            e.start_time,
+            e.end_time,
+            e.location,
+            e.price,
+            u.id AS creator_id,
+            COALESCE(u.display_name, u.username) AS creator_name,
+            u.username,
+            u.avatar_url,
+            pins.position AS pin_position
+        FROM events e
+        JOIN users u ON e.host_id = u.id
+        LEFT JOIN pins ON pins.item_id = e.id
+        WHERE COALESCE(e.is_public, TRUE)
+          AND UPPER(COALESCE(e.status, 'PUBLISHED')) NOT IN ('DRAFT', 'CANCELLED')
+          AND e.start_time > NOW()
+        ORDER BY pins.position ASC NULLS LAST, e.start_time ASC
+        LIMIT $1
+        "#,
+    )
+    .bind(UPCOMING_EVENTS)
+    .fetch_all(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to load events for explore: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    let upcoming_events: Vec<serde_json::Value> = event_rows
+        .into_iter()
+        .map(|row| {
+            let id: Uuid = row.get("id");
+            let creator_id: String = row.try_get("creator_id").unwrap_or_default();
+            let creator_username: Option<String> = row.try_get("username").ok();
+            let start_time: DateTime<Utc> = row.get("start_time");
+            json!({
+                "id": format!("event-{}", id),
+                "sourceId": id.to_string(),
+                "type": "event",
+                "title": row.try_get::<String, _>("title").unwrap_or_else(|_| "Upcoming event".to_string()),
+                "summary": row.try_get::<Option<String>, _>("description").ok().flatten(),
+                "coverImage": row.try_get::<Option<String>, _>("cover_image").ok().flatten(),
+                "publishedAt": start_time,
+                "link": format!("/creators/{}?tab=events", creator_username.clone().unwrap_or_else(|| creator_id.clone())),
+                "creator": {
+                    "id": creator_id,
+                    "name": row.try_get::<Option<String>, _>("creator_name").ok().flatten().unwrap_or_else(|| "Creator".to_string()),
+                    "username": creator_username,
+                    "avatar": row.try_get::<Option<String>, _>("avatar_url").ok().flatten(),
+                },
+                "isPinned": row.try_get::<Option<i32>, _>("pin_position").ok().flatten().is_some(),
+                "meta": {
+                    "startTime": start_time,
+                    "endTime": row.try_get::<Option<DateTime<Utc>>, _>("end_time").ok().flatten(),
+                    "location": row.try_get::<Option<String>, _>("location").ok().flatten(),
+                    "price": row.try_get::<Option<f64>, _>("price").ok().flatten(),
+                },
+            })
+        })
+        .collect();
+
+    let response = json!({
+        "success": true,
+        "data": {
+            "trendingPosts": trending_posts,
+            "campaigns": campaigns,
+            "upcomingEvents": upcoming_events,
+            "generatedAt": Utc::now(),
+        }
+    });
+
+    if let Some(redis) = &db.redis {
+        let mut redis_clone = redis.clone();
+        if let Ok(response_str) = serde_json::to_string(&response) {
+            let _ = redis_clone
+                .set_ex(EXPLORE_CACHE_KEY, &response_str, EXPLORE_CACHE_SECONDS)
+                .await;
+        }
+    }
+
+    Ok(explore_response(response))
+}
+
+fn explore_response(value: serde_json::Value) -> Response {
+    ([(header::CACHE_CONTROL, EXPLORE_CACHE_CONTROL)], Json(value)).into_response()
+}
+
+async fn invalidate_explore_cache(db: &Database) {
+    if let Some(redis) = &db.redis {
+        let _ = redis.clone().del(EXPLORE_CACHE_KEY).await;
+    }
+}
+
+async fn list_pins(
+    State(db): State<Database>,
+    claims: Claims,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    ensure_admin(&db, &claims.sub).await?;
+    let pins = sqlx::query_as::<_, ExplorePin>(
+        "SELECT * FROM explore_pins ORDER BY item_type, position, created_at",
+    )
+    .fetch_all(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to list explore pins: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    Ok(Json(json!({ "success": true, "data": pins })))
+}
+
+// Pinning an item again moves it or changes its expiry
+async fn pin_item(
+    State(db): State<Database>,
+    claims: Claims,
+    Json(payload): Json<PinRequest>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    ensure_admin(&db, &claims.sub).await?;
+    if payload.expires_at.is_some_and(|expires_at| expires_at <= Utc::now()) {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let exists = sqlx::query_scalar::<_, bool>(&format!(
+        "SELECT EXISTS(SELECT 1 FROM {} WHERE id = $1)",
+        payload.item_type.table()
+    ))
+    .bind(payload.item_id)
+    .fetch_one(&db.pool)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    if !exists {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    let pin = sqlx::query_as::<_, ExplorePin>(
+        r#"
+        INSERT INTO explore_pins (item_type, item_id, position, pinned_by, expires_at)
+        VALUES ($1, $2, $3, $4, $5)
+        ON CONFLICT (item_type, item_id) DO UPDATE
+        SET position = EXCLUDED.position,
+            pinned_by = EXCLUDED.pinned_by,
+            expires_at = EXCLUDED.expires_at
+        RETURNING *
+        "#,
+    )
+    .bind(payload.item_type.as_str())
+    .bind(payload.item_id)
+    .bind(payload.position.unwrap_or(0))
+    .bind(&claims.sub)
+    .bind(payload.expires_at)
+    .fetch_one(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to pin explore item: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    invalidate_explore_cache(&db).await;
+    Ok(Json(json!({ "success": true, "data": pin })))
+}
+
+async fn unpin_item(
+    State(db): State<Database>,
+    Path(id): Path<Uuid>,
+    claims: Claims,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    ensure_admin(&db, &claims.sub).await?;
+    let removed = sqlx::query("DELETE FROM explore_pins WHERE id = $1")
+        .bind(id)
+        .execute(&db.pool)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .rows_affected();
+    if removed == 0 {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    invalidate_explore_cache(&db).await;
+    Ok(Json(json!({ "success": true, "data": { "id": id } })))
+}
@@ -2,20 +2,33 @@ use axum::{
     extract::{Query, State},
     http::StatusCode,
     response::Json,
-    routing::{delete, get, post},
+    routing::{get, post},
     Router,
 };
-use chrono::{Duration, Utc};
+use chrono::{DateTime, Duration, Utc};
 use serde::Deserialize;
 use serde_json::json;
 use sqlx::Row;
 use uuid::Uuid;
 
-use crate::{auth::Claims, database::Database};
+use crate::{
+    auth::Claims,
+    database::Database,
+    feed_timeline,
+    pagination::{parse_cursor, Cursor},
+    routes::{
+        articles::article_preview,
+        bookmarks::{add_bookmark, bookmarked_ids, list_bookmarks, remove_bookmark, BookmarkType},
+        explore::get_explore,
+        feed_mutes::{hidden_item_routes, mute_routes, FeedMutes},
+        feed_telemetry::record_feed_events,
+    },
+};
 
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct FeedQuery {
+    /// `nextCursor` of the previous page.
     pub cursor: Option<String>,
     pub limit: Option<u32>,
     #[serde(rename = "type")]
@@ -24,20 +37,19 @@ pub struct FeedQuery {
     pub period: Option<String>,
 }
 
-#[derive(Debug, Deserialize)]
-#[serde(rename_all = "camelCase")]
-pub struct BookmarkPayload {
-    pub content_type: Option<String>,
-    pub content_id: Option<String>,
-}
-
 pub fn feed_routes() -> Router<Database> {
-    Router::new().route("/", get(get_feed)).route(
-        "/bookmarks",
-        get(get_bookmarks)
-            .post(add_bookmark)
-            .delete(remove_bookmark),
-    )
+    Router::new()
+        .route("/", get(get_feed))
+        .route("/explore", get(get_explore))
+        .nest("/mutes", mute_routes())
+        .nest("/hidden", hidden_item_routes())
+        .route("/events", post(record_feed_events))
+        .route(
+            "/bookmarks",
+            get(list_bookmarks)
+                .post(add_bookmark)
+                .delete(remove_bookmark),
+        )
 }
 
 async fn get_feed(
@@ -52,12 +64,24 @@ async fn get_feed(
         .trim_end_matches(|c: char| !c.is_ascii_digit())
         .parse::<i64>()
         .unwrap_or(72);
-    let limit = params.limit.unwrap_or(20).min(50) as i64;
-    let per_type_limit = (limit.max(6) / 3).max(3);
+    let limit = params.limit.unwrap_or(20).clamp(1, 50) as i64;
+    // Each type fetches a full page plus one, so the merged page is exact and tells if there is more
+    let per_type_limit = limit + 1;
     let cutoff = Utc::now() - Duration::hours(period_value.max(1));
+    let cursor = parse_cursor(params.cursor.as_deref())?;
+    let cursor_at = cursor.as_ref().map(|cursor| cursor.at);
+    let cursor_key = cursor.as_ref().map(|cursor| cursor.id.clone());
 
     // Try cache first
-    let cache_key = format!("feed:{}:{}:{}:{}:{}", claims.sub, filter, sort, period_str, limit);
+    let cache_key = format!(
+        "feed:{}:{}:{}:{}:{}:{}",
+        claims.sub,
+        filter,
+        sort,
+        period_str,
+        limit,
+        params.cursor.as_deref().unwrap_or("")
+    );
     if let Some(redis) = &db.redis {
         let mut redis_clone = redis.clone();
         if let Ok(Some(cached)) = redis_clone.get(&cache_key).await {
@@ -69,9 +93,52 @@ async fn get_feed(
         tracing::debug!("Cache MISS for feed: {}", cache_key);
     }
 
+    let mutes = FeedMutes::load(&db, &claims.sub).await?;
+
+    // Users who follow someone read their precomputed timeline, and the queries below only
+    // load that page's items by id
+    let mut timeline = if filter == "highlights" {
+        None
+    } else {
+        let item_type = match filter.as_str() {
+            "posts" => Some("post"),
+            "articles" => Some("article"),
+            "events" => Some("event"),
+            _ => None,
+        };
+        feed_timeline::page(&db, &claims.sub, cursor.as_ref(), item_type, per_type_limit as usize)
+            .await
+    };
+    let mut timeline_cursor = None;
+    if let Some(page) = timeline.as_mut() {
+        if page.len() > limit as usize {
+            timeline_cursor = page
+                .get(limit as usize - 1)
+                .map(|(key, at)| Cursor::new(*at, key).encode());
+            page.truncate(limit as usize);
+        }
+    }
+    let timeline_ids = |prefix: &str| -> Option<Vec<Uuid>> {
+        timeline.as_ref().map(|page| {
+            page.iter()
+                .filter_map(|(key, _)| key.strip_prefix(prefix))
+                .filter_map(|id| Uuid::parse_str(id).ok())
+                .collect()
+        })
+    };
+    let (since, cursor_at, cursor_key) = if timeline.is_some() {
+        (DateTime::UNIX_EPOCH, None, None)
+    } else {
+        (cutoff, cursor_at, cursor_key)
+    };
+
+    // Entries are ordered by `published_at` then `key`, the item id, which the cursor carries
     struct FeedEntry {
         published_at: chrono::DateTime<chrono::Utc>,
+        key: String,
         item_type: String,
+        creator_id: String,
+        tags: Vec<String>,
         value: serde_json::Value,
     }
 
@@ -97,12 +164,25 @@ async fn get_feed(
         FROM posts p
         JOIN users u ON p.user_id = u.id
         WHERE p.created_at >= $1
-        ORDER BY p.created_at DESC
+          AND COALESCE(p.published, TRUE)
+          AND COALESCE(p.published_at, p.created_at) <= NOW()
+          AND ($3::TIMESTAMPTZ IS NULL OR (p.created_at, 'post-' || p.id::TEXT) < ($3, $4))
+          AND ($5::UUID[] IS NULL OR p.id = ANY($5))
+          AND NOT $7
+          AND u.id <> ALL($6)
+          AND p.id <> ALL($8)
+        ORDER BY p.created_at DESC, p.id DESC
         LIMIT $2
         "#,
     )
-    .bind(cutoff)
+    .bind(since)
     .bind(per_type_limit)
+    .bind(cursor_at)
+    .bind(&cursor_key)
+    .bind(timeline_ids("post-"))
+    .bind(&mutes.creators)
+    .bind(mutes.hides_type("post"))
+    .bind(mutes.hidden("post"))
     .fetch_all(&db.pool)
     .await
     .map_err(|e| {
@@ -154,7 +234,10 @@ async fn get_feed(
 
         entries.push(FeedEntry {
             published_at: created_at,
+            key: format!("post-{}", id),
             item_type: "posts".to_string(),
+            creator_id: creator_id.clone(),
+            tags: Vec::new(),
             value: json!({
                 "id": format!("post-{}", id),
                 "sourceId": id.to_string(),
@@ -189,8 +272,14 @@ async fn get_feed(
             a.slug,
             a.title,
             a.content,
+            a.is_premium,
             a.published_at,
             a.created_at,
+            ARRAY(
+                SELECT t.slug FROM article_tags art
+                JOIN tags t ON t.id = art.tag_id
+                WHERE art.article_id = a.id
+            ) AS tag_slugs,
             u.id AS creator_id,
             COALESCE(u.display_name, u.username) AS creator_name,
             u.username,
@@ -198,12 +287,31 @@ async fn get_feed(
         FROM articles a
         JOIN users u ON a.author_id = u.id
         WHERE a.created_at >= $1
-        ORDER BY a.created_at DESC
+          AND (a.published_at IS NULL OR a.published_at <= NOW())
+          AND ($3::TIMESTAMPTZ IS NULL
+               OR (COALESCE(a.published_at, a.created_at), 'article-' || a.id::TEXT) < ($3, $4))
+          AND ($5::UUID[] IS NULL OR a.id = ANY($5))
+          AND NOT $7
+          AND u.id <> ALL($6)
+          AND a.id <> ALL($9)
+          AND NOT EXISTS (
+              SELECT 1 FROM article_tags art
+              JOIN tags t ON t.id = art.tag_id
+              WHERE art.article_id = a.id AND t.slug = ANY($8)
+          )
+        ORDER BY COALESCE(a.published_at, a.created_at) DESC, a.id DESC
         LIMIT $2
         "#,
     )
-    .bind(cutoff)
+    .bind(since)
     .bind(per_type_limit)
+    .bind(cursor_at)
+    .bind(&cursor_key)
+    .bind(timeline_ids("article-"))
+    .bind(&mutes.creators)
+    .bind(mutes.hides_type("article"))
+    .bind(&mutes.tags)
+    .bind(mutes.hidden("article"))
     .fetch_all(&db.pool)
     .await
     .map_err(|e| {
@@ -221,14 +329,29 @@ async fn get_feed(
         let creator_name: Option<String> = row.try_get("creator_name").ok();
         let creator_username: Option<String> = row.try_get("username").ok();
         let creator_avatar: Option<String> = row.try_get("avatar_url").ok();
-        let content: Option<String> = row.try_get("content").ok();
+        let is_premium: bool = row.try_get("is_premium").unwrap_or(false);
+        // Premium articles only ever expose their free preview in the feed.
+        let content: Option<String> = row
+            .try_get::<Option<String>, _>("content")
+            .ok()
+            .flatten()
+            .map(|content| {
+                if is_premium {
+                    article_preview(&content).0
+                } else {
+                    content
+                }
+            });
         let summary = content
             .as_ref()
             .map(|c| c.trim().chars().take(200).collect::<String>());
 
         entries.push(FeedEntry {
             published_at: published_at.unwrap_or(created_at),
+            key: format!("article-{}", id),
             item_type: "articles".to_string(),
+            creator_id: creator_id.clone(),
+            tags: row.try_get("tag_slugs").unwrap_or_default(),
             value: json!({
                 "id": format!("article-{}", id),
                 "sourceId": id.to_string(),
@@ -236,6 +359,7 @@ async fn get_feed(
                 "title": row.try_get::<String, _>("title").unwrap_or_else(|_| "New article".to_string()),
                 "summary": summary,
                 "preview": content,
+                "isPremium": is_premium,
                 "coverImage": null,
                 "publishedAt": published_at.unwrap_or(created_at),
                 "link": format!("/blog/{}", slug),
@@ -272,6 +396,7 @@ async fn get_feed(
             e.location,
             e.price,
             e.created_at,
+            e.tags,
             u.id AS creator_id,
             COALESCE(u.display_name, u.username) AS creator_name,
             u.username,
@@ -279,12 +404,26 @@ async fn get_feed(
         FROM events e
         JOIN users u ON e.host_id = u.id
         WHERE e.created_at >= $1
-        ORDER BY e.start_time ASC
+          AND ($3::TIMESTAMPTZ IS NULL
+               OR (COALESCE(e.start_time, e.created_at), 'event-' || e.id::TEXT) < ($3, $4))
+          AND ($5::UUID[] IS NULL OR e.id = ANY($5))
+          AND NOT $7
+          AND u.id <> ALL($6)
+          AND e.id <> ALL($9)
+          AND NOT EXISTS (SELECT 1 FROM UNNEST(e.tags) tag WHERE LOWER(tag) = ANY($8))
+        ORDER BY COALESCE(e.start_time, e.created_at) DESC, e.id DESC
         LIMIT $2
         "#,
     )
-    .bind(cutoff)
+    .bind(since)
     .bind(per_type_limit)
+    .bind(cursor_at)
+    .bind(&cursor_key)
+    .bind(timeline_ids("event-"))
+    .bind(&mutes.creators)
+    .bind(mutes.hides_type("event"))
+    .bind(&mutes.tags)
+    .bind(mutes.hidden("event"))
     .fetch_all(&db.pool)
     .await
     .map_err(|e| {
@@ -307,7 +446,17 @@ async fn get_feed(
 
         entries.push(FeedEntry {
             published_at: start_time.unwrap_or(created_at),
+            key: format!("event-{}", id),
             item_type: "events".to_string(),
+            creator_id: creator_id.clone(),
+            tags: row
+                .try_get::<Option<Vec<String>>, _>("tags")
+                .ok()
+                .flatten()
+                .unwrap_or_default()
+                .iter()
+                .map(|tag| tag.to_lowercase())
+                .collect(),
             value: json!({
                 "id": format!("event-{}", id),
                 "sourceId": id.to_string(),
@@ -342,7 +491,7 @@ async fn get_feed(
     }
 
     // Sort all entries by published date descending
-    entries.sort_by(|a, b| b.published_at.cmp(&a.published_at));
+    entries.sort_by(|a, b| (b.published_at, &b.key).cmp(&(a.published_at, &a.key)));
 
     // Filter by requested filter
     let mut filtered_entries: Vec<FeedEntry> = entries
@@ -356,11 +505,50 @@ async fn get_feed(
         })
         .collect();
 
+    let mut next_cursor = timeline_cursor;
     if filter == "highlights" {
         filtered_entries.truncate(5);
     } else {
+        if filtered_entries.len() > limit as usize {
+            next_cursor = filtered_entries
+                .get(limit as usize - 1)
+                .map(|entry| Cursor::new(entry.published_at, &entry.key).encode());
+        }
         filtered_entries.truncate(limit as usize);
     }
+    // Thinned out only once the page is cut, so the cursor still follows every item
+    filtered_entries.retain(|entry| {
+        !mutes.shows_fewer(
+            &entry.key,
+            entry.item_type.trim_end_matches('s'),
+            &entry.creator_id,
+            &entry.tags,
+        )
+    });
+
+    // Mark what the viewer has saved
+    let mut saved: Vec<String> = Vec::new();
+    for (item_type, bookmark_type) in [
+        ("posts", BookmarkType::Post),
+        ("articles", BookmarkType::Article),
+        ("events", BookmarkType::Event),
+    ] {
+        let prefix = format!("{}-", item_type.trim_end_matches('s'));
+        let ids: Vec<Uuid> = filtered_entries
+            .iter()
+            .filter(|entry| entry.item_type == item_type)
+            .filter_map(|entry| entry.key.strip_prefix(&prefix))
+            .filter_map(|id| Uuid::parse_str(id).ok())
+            .collect();
+        for id in bookmarked_ids(&db, &claims.sub, bookmark_type, &ids).await? {
+            saved.push(format!("{}{}", prefix, id));
+        }
+    }
+    for entry in filtered_entries.iter_mut().filter(|entry| saved.contains(&entry.key)) {
+        if let Some(object) = entry.value.as_object_mut() {
+            object.insert("isSaved".to_string(), json!(true));
+        }
+    }
 
     let mut items: Vec<serde_json::Value> = Vec::new();
     let mut highlights: Vec<serde_json::Value> = Vec::new();
@@ -449,8 +637,8 @@ async fn get_feed(
                 "highlightCount": highlights.len(),
                 "recommendationsCount": recommended_content.len()
             },
-            "nextCursor": null,
-            "hasMore": false
+            "hasMore": next_cursor.is_some(),
+            "nextCursor": next_cursor
         }
     });
 
@@ -464,39 +652,3 @@ async fn get_feed(
 
     Ok(Json(response))
 }
-
-async fn get_bookmarks(
-    _state: State<Database>,
-    _claims: Claims,
-) -> Result<Json<serde_json::Value>, StatusCode> {
-    let response = serde_json::json!({
-        "success": true,
-        "data": []
-    });
-
-    Ok(Json(response))
-}
-
-async fn add_bookmark(
-    _state: State<Database>,
-    _claims: Claims,
-    Json(_payload): Json<BookmarkPayload>,
-) -> Result<Json<serde_json::Value>, StatusCode> {
-    let response = serde_json::json!({
-        "success": true
-    });
-
-    Ok(Json(response))
-}
-
-async fn remove_bookmark(
-    _state: State<Database>,
-    _claims: Claims,
-    Json(_payload): Json<BookmarkPayload>,
-) -> Result<Json<serde_json::Value>, StatusCode> {
-    let response = serde_json::json!({
-        "success": true
-    });
-
-    Ok(Json(response))
-}
@@ -0,0 +1,384 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::Json,
+    routing::{delete, get},
+    Router,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::{
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+};
+use uuid::Uuid;
+
+use crate::{
+    auth::Claims,
+    database::Database,
+    routes::feed_telemetry::{parse_item_key, record_event, FeedAction},
+};
+
+/// Items matching a "show fewer" control still show one time in this many.
+const SHOW_FEWER_RATE: u64 = 3;
+const MAX_VALUE_CHARS: usize = 100;
+const FEED_TYPES: [&str; 3] = ["post", "article", "event"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum MuteKind {
+    Creator,
+    Tag,
+    Type,
+}
+
+impl MuteKind {
+    fn as_str(self) -> &'static str {
+        match self {
+            MuteKind::Creator => "CREATOR",
+            MuteKind::Tag => "TAG",
+            MuteKind::Type => "TYPE",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum MuteMode {
+    #[default]
+    Mute,
+    ShowFewer,
+}
+
+impl MuteMode {
+    fn as_str(self) -> &'static str {
+        match self {
+            MuteMode::Mute => "MUTE",
+            MuteMode::ShowFewer => "SHOW_FEWER",
+        }
+    }
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+struct FeedMute {
+    id: Uuid,
+    /// `CREATOR`, `TAG` or `TYPE`.
+    kind: String,
+    /// A creator id, a tag slug, or `post`, `article` or `event`.
+    value: String,
+    /// `MUTE` or `SHOW_FEWER`.
+    mode: String,
+    created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct MuteRequest {
+    kind: MuteKind,
+    value: String,
+    #[serde(default)]
+    mode: MuteMode,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+struct HiddenItem {
+    item_type: String,
+    item_id: Uuid,
+    created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct HideRequest {
+    /// The feed item id, e.g. `post-<uuid>`.
+    item_id: String,
+}
+
+pub fn mute_routes() -> Router<Database> {
+    Router::new()
+        .route("/", get(list_mutes).post(add_mute))
+        .route("/:id", delete(remove_mute))
+}
+
+pub fn hidden_item_routes() -> Router<Database> {
+    Router::new()
+        .route("/", get(list_hidden_items).post(hide_item))
+        .route("/:item_id", delete(unhide_item))
+}
+
+/// A user's feed controls, as the feed applies them.
+#[derive(Debug, Default)]
+pub(crate) struct FeedMutes {
+    pub creators: Vec<String>,
+    pub tags: Vec<String>,
+    types: Vec<String>,
+    fewer_creators: Vec<String>,
+    fewer_tags: Vec<String>,
+    fewer_types: Vec<String>,
+    /// Single items hidden from the feed, by type.
+    hidden: Vec<(String, Uuid)>,
+}
+
+impl FeedMutes {
+    pub(crate) async fn load(db: &Database, user_id: &str) -> Result<Self, StatusCode> {
+        let rows = sqlx::query_as::<_, (String, String, String)>(
+            "SELECT kind, value, mode FROM feed_mutes WHERE user_id = $1",
+        )
+        .bind(user_id)
+        .fetch_all(&db.pool)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to load feed mutes for {}: {}", user_id, e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+        let mut mutes = FeedMutes::default();
+        for (kind, value, mode) in rows {
+            let list = match (kind.as_str(), mode == "SHOW_FEWER") {
+                ("CREATOR", false) => &mut mutes.creators,
+                ("TAG", false) => &mut mutes.tags,
+                ("TYPE", false) => &mut mutes.types,
+                ("CREATOR", true) => &mut mutes.fewer_creators,
+                ("TAG", true) => &mut mutes.fewer_tags,
+                ("TYPE", true) => &mut mutes.fewer_types,
+                _ => continue,
+            };
+            list.push(value);
+        }
+
+        mutes.hidden = sqlx::query_as::<_, (String, Uuid)>(
+            "SELECT item_type, item_id FROM feed_hidden_items WHERE user_id = $1",
+        )
+        .bind(user_id)
+        .fetch_all(&db.pool)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to load hidden feed items for {}: {}", user_id, e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+        Ok(mutes)
+    }
+
+    /// Ids of the items of one type (`post`, `article` or `event`) hidden from the feed.
+    pub(crate) fn hidden(&self, item_type: &str) -> Vec<Uuid> {
+        self.hidden
+            .iter()
+            .filter(|(hidden_type, _)| hidden_type == item_type)
+            .map(|(_, item_id)| *item_id)
+            .collect()
+    }
+
+    /// Whether a whole content type (`post`, `article` or `event`) is muted.
+    pub(crate) fn hides_type(&self, item_type: &str) -> bool {
+        self.types.iter().any(|muted| muted == item_type)
+    }
+
+    /// Whether an item matching a "show fewer" control is left out of this page. The choice
+    /// follows from the item key alone, so it is the same on every load of the page.
+    pub(crate) fn shows_fewer(
+        &self,
+        item_key: &str,
+        item_type: &str,
+        creator_id: &str,
+        tags: &[String],
+    ) -> bool {
+        let matches = self.fewer_types.iter().any(|value| value == item_type)
+            || self.fewer_creators.iter().any(|value| value == creator_id)
+            || tags.iter().any(|tag| self.fewer_tags.contains(tag));
+        if !matches {
+            return false;
+        }
+        let mut hasher = DefaultHasher::new();
+        item_key.hash(&mut hasher);
+        !hasher.finish().is_multiple_of(SHOW_FEWER_RATE)
+    }
+}
+
+/// Cached feed pages are per user, so they are dropped when the controls change.
+pub(crate) async fn invalidate_feed_cache(db: &Database, user_id: &str) {
+    if let Some(redis) = &db.redis {
+        let _ = redis
+            .clone()
+            .del_pattern(&format!("feed:{}:*", user_id))
+            .await;
+    }
+}
+
+async fn list_mutes(
+    State(db): State<Database>,
+    claims: Claims,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let mutes = sqlx::query_as::<_, FeedMute>(
+        "SELECT id, kind, value, mode, created_at FROM feed_mutes WHERE user_id = $1 ORDER BY created_at DESC",
+    )
+    .bind(&claims.sub)
+    .fetch_all(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to list feed mutes: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    Ok(Json(json!({ "success": true, "data": mutes })))
+}
+
+// Adding a control that exists already switches its mode
+async fn add_mute(
+    State(db): State<Database>,
+    claims: Claims,
+    Json(payload): Json<MuteRequest>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let value = match payload.kind {
+        MuteKind::Creator => payload.value.trim().to_string(),
+        MuteKind::Tag | MuteKind::Type => payload.value.trim().to_lowercase(),
+    };
+    if value.is_empty() || value.chars().count() > MAX_VALUE_CHARS {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    match payload.kind {
+        MuteKind::Type if !FEED_TYPES.contains(&value.as_str()) => {
+            return Err(StatusCode::BAD_REQUEST);
+        }
+        MuteKind::Creator => {
+            if value == claims.sub {
+                return Err(StatusCode::BAD_REQUEST);
+            }
+            let exists = sqlx::query_scalar::<_, bool>(
+                "SELECT EXISTS(SELECT 1 FROM users WHERE id = $1)",
+            )
+            .bind(&value)
+            .fetch_one(&db.pool)
+            .await
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+            if !exists {
+                return Err(StatusCode::NOT_FOUND);
+            }
+        }
+        _ => {}
+    }
+
+    let mute = sqlx::query_as::<_, FeedMute>(
+        r#"
+        INSERT INTO feed_mutes (user_id, kind, value, mode) VALUES ($1, $2, $3, $4)
+        ON CONFLICT (user_id, kind, value) DO UPDATE SET mode = EXCLUDED.mode
+        RETURNING id, kind, value, mode, created_at
+        "#,
+    )
+    .bind(&claims.sub)
+    .bind(payload.kind.as_str())
+    .bind(&value)
+    .bind(payload.mode.as_str())
+    .fetch_one(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to add feed mute: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    invalidate_feed_cache(&db, &claims.sub).await;
+    Ok(Json(json!({ "success": true, "data": mute })))
+}
+
+async fn remove_mute(
+    State(db): State<Database>,
+    Path(id): Path<Uuid>,
+    claims: Claims,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let removed = sqlx::query("DELETE FROM feed_mutes WHERE id = $1 AND user_id = $2")
+        .bind(id)
+        .bind(&claims.sub)
+        .execute(&db.pool)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .rows_affected();
+    if removed == 0 {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    invalidate_feed_cache(&db, &claims.sub).await;
+    Ok(Json(json!({ "success": true, "data": { "id": id } })))
+}
+
+async fn list_hidden_items(
+    State(db): State<Database>,
+    claims: Claims,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let items = sqlx::query_as::<_, HiddenItem>(
+        "SELECT item_type, item_id, created_at FROM feed_hidden_items WHERE user_id = $1 ORDER BY created_at DESC",
+    )
+    .bind(&claims.sub)
+    .fetch_all(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to list hidden feed items: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    Ok(Json(json!({ "success": true, "data": items })))
+}
+
+// Hiding is also a training signal, so it is recorded with the feed telemetry
+async fn hide_item(
+    State(db): State<Database>,
+    claims: Claims,
+    Json(payload): Json<HideRequest>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let (item_type, item_id) =
+        parse_item_key(payload.item_id.trim()).ok_or(StatusCode::BAD_REQUEST)?;
+
+    let inserted = sqlx::query(
+        r#"
+        INSERT INTO feed_hidden_items (user_id, item_type, item_id) VALUES ($1, $2, $3)
+        ON CONFLICT (user_id, item_type, item_id) DO NOTHING
+        "#,
+    )
+    .bind(&claims.sub)
+    .bind(item_type)
+    .bind(item_id)
+    .execute(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to hide feed item: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?
+    .rows_affected();
+
+    if inserted > 0 {
+        if let Err(e) = record_event(&db, &claims.sub, item_type, item_id, FeedAction::Hide).await {
+            tracing::error!("Failed to record feed hide: {}", e);
+        }
+        invalidate_feed_cache(&db, &claims.sub).await;
+    }
+
+    Ok(Json(json!({
+        "success": true,
+        "data": { "itemId": format!("{}-{}", item_type, item_id), "hidden": true }
+    })))
+}
+
+async fn unhide_item(
+    State(db): State<Database>,
+    Path(item_id): Path<String>,
+    claims: Claims,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let (item_type, source_id) =
+        parse_item_key(item_id.trim()).ok_or(StatusCode::BAD_REQUEST)?;
+    let removed = sqlx::query(
+        "DELETE FROM feed_hidden_items WHERE user_id = $1 AND item_type = $2 AND item_id = $3",
+    )
+    .bind(&claims.sub)
+    .bind(item_type)
+    .bind(source_id)
+    .execute(&db.pool)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    .rows_affected();
+    if removed == 0 {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    invalidate_feed_cache(&db, &claims.sub).await;
+    Ok(Json(json!({ "success": true, "data": { "itemId": item_id, "hidden": false } })))
+}
@@ -0,0 +1,211 @@
+use axum::{extract::State, http::StatusCode, response::Json};
+use chrono::{DateTime, Duration, Utc};
+use serde::Deserialize;
+use serde_json::json;
+use tracing::info;
+use uuid::Uuid;
+
+use crate::{auth::Claims, database::Database};
+
+const MAX_BATCH_EVENTS: usize = 100;
+/// Events reported later than this after they happened are dropped.
+const MAX_EVENT_AGE_HOURS: i64 = 24;
+/// Raw events are kept this long; the daily rollups are kept for good.
+const RAW_RETENTION_DAYS: i32 = 90;
+
+/// Feed item types as stored in the telemetry tables.
+pub(crate) const FEED_ITEM_TYPES: [(&str, i16); 3] = [("post", 1), ("article", 2), ("event", 3)];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum FeedAction {
+    Impression,
+    Click,
+    Hide,
+}
+
+impl FeedAction {
+    fn code(self) -> i16 {
+        match self {
+            FeedAction::Impression => 1,
+            FeedAction::Click => 2,
+            FeedAction::Hide => 3,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct FeedEvent {
+    /// The feed item id, e.g. `post-<uuid>`.
+    item_id: String,
+    action: FeedAction,
+    /// Zero-based position of the item in the feed when it was seen.
+    position: Option<i16>,
+    occurred_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct FeedEventBatch {
+    events: Vec<FeedEvent>,
+}
+
+/// Split a feed item id such as `post-<uuid>` into its type and source id.
+pub(crate) fn parse_item_key(key: &str) -> Option<(&'static str, Uuid)> {
+    let (item_type, id) = key.split_once('-')?;
+    let (item_type, _) = FEED_ITEM_TYPES
+        .iter()
+        .find(|(name, _)| *name == item_type)?;
+    Some((item_type, Uuid::parse_str(id).ok()?))
+}
+
+fn type_code(item_type: &str) -> i16 {
+    FEED_ITEM_TYPES
+        .iter()
+        .find(|(name, _)| *name == item_type)
+        .map(|(_, code)| *code)
+        .unwrap_or(0)
+}
+
+struct EventRow {
+    item_type: i16,
+    item_id: Uuid,
+    action: i16,
+    position: Option<i16>,
+    occurred_at: DateTime<Utc>,
+}
+
+async fn insert_events(db: &Database, user_id: &str, events: &[EventRow]) -> Result<(), sqlx::Error> {
+    let item_types: Vec<i16> = events.iter().map(|event| event.item_type).collect();
+    let item_ids: Vec<Uuid> = events.iter().map(|event| event.item_id).collect();
+    let actions: Vec<i16> = events.iter().map(|event| event.action).collect();
+    let positions: Vec<Option<i16>> = events.iter().map(|event| event.position).collect();
+    let occurred_at: Vec<DateTime<Utc>> = events.iter().map(|event| event.occurred_at).collect();
+
+    sqlx::query(
+        r#"
+        INSERT INTO feed_item_events (user_id, item_type, item_id, action, position, occurred_at)
+        SELECT $1, e.item_type, e.item_id, e.action, e.position, e.occurred_at
+        FROM UNNEST($2::SMALLINT[], $3::UUID[], $4::SMALLINT[], $5::SMALLINT[], $6::TIMESTAMPTZ[])
+            AS e(item_type, item_id, action, position, occurred_at)
+        "#,
+    )
+    .bind(user_id)
+    .bind(&item_types)
+    .bind(&item_ids)
+    .bind(&actions)
+    .bind(&positions)
+    .bind(&occurred_at)
+    .execute(&db.pool)
+    .await?;
+    Ok(())
+}
+
+/// Record one event outside a batch, e.g. the hide behind the hide endpoint.
+pub(crate) async fn record_event(
+    db: &Database,
+    user_id: &str,
+    item_type: &str,
+    item_id: Uuid,
+    action: FeedAction,
+) -> Result<(), sqlx::Error> {
+    let event = EventRow {
+        item_type: type_code(item_type),
+        item_id,
+        action: action.code(),
+        position: None,
+        occurred_at: Utc::now(),
+    };
+    insert_events(db, user_id, &[event]).await
+}
+
+/// Batched impressions and clicks from the feed. Events for unknown item ids or from too long
+/// ago are skipped; hides go through the hide endpoint so the item leaves the feed.
+pub(crate) async fn record_feed_events(
+    State(db): State<Database>,
+    claims: Claims,
+    Json(payload): Json<FeedEventBatch>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    if payload.events.len() > MAX_BATCH_EVENTS {
+        return Err(StatusCode::PAYLOAD_TOO_LARGE);
+    }
+
+    let now = Utc::now();
+    let oldest = now - Duration::hours(MAX_EVENT_AGE_HOURS);
+    let events: Vec<EventRow> = payload
+        .events
+        .iter()
+        .filter(|event| event.action != FeedAction::Hide)
+        .filter_map(|event| {
+            let (item_type, item_id) = parse_item_key(&event.item_id)?;
+            let occurred_at = event.occurred_at.unwrap_or(now).min(now);
+            (occurred_at >= oldest).then(|| EventRow {
+                item_type: type_code(item_type),
+                item_id,
+                action: event.action.code(),
+                position: event.position.filter(|position| *position >= 0),
+                occurred_at,
+            })
+        })
+        .collect();
+
+    if !events.is_empty() {
+        insert_events(&db, &claims.sub, &events).await.map_err(|e| {
+            tracing::error!("Failed to record feed events: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+    }
+
+    Ok(Json(json!({
+        "success": true,
+        "data": {
+            "accepted": events.len(),
+            "skipped": payload.events.len() - events.len()
+        }
+    })))
+}
+
+/// Recount yesterday's and today's per-item totals from the raw events, then drop raw events
+/// past retention. Recounting whole days keeps the rollup right however often it runs.
+pub async fn roll_up_daily_stats(db: &Database) -> anyhow::Result<()> {
+    let rolled_up = sqlx::query(
+        r#"
+        INSERT INTO feed_item_daily_stats (day, item_type, item_id, impressions, clicks, hides, unique_viewers)
+        SELECT
+            (occurred_at AT TIME ZONE 'UTC')::DATE AS day,
+            item_type,
+            item_id,
+            COUNT(*) FILTER (WHERE action = 1),
+            COUNT(*) FILTER (WHERE action = 2),
+            COUNT(*) FILTER (WHERE action = 3),
+            COUNT(DISTINCT user_id)
+        FROM feed_item_events
+        WHERE occurred_at >= date_trunc('day', NOW() AT TIME ZONE 'UTC') AT TIME ZONE 'UTC' - INTERVAL '1 day'
+        GROUP BY 1, 2, 3
+        ON CONFLICT (day, item_type, item_id) DO UPDATE
+        SET impressions = EXCLUDED.impressions,
+            clicks = EXCLUDED.clicks,
+            hides = EXCLUDED.hides,
+            unique_viewers = EXCLUDED.unique_viewers
+        "#,
+    )
+    .execute(&db.pool)
+    .await?
+    .rows_affected();
+
+    let pruned = sqlx::query(
+        "DELETE FROM feed_item_events WHERE occurred_at < NOW() - make_interval(days => $1)",
+    )
+    .bind(RAW_RETENTION_DAYS)
+    .execute(&db.pool)
+    .await?
+    .rows_affected();
+
+    if rolled_up > 0 || pruned > 0 {
+        info!(
+            "Feed telemetry: {} daily rows rolled up, {} raw events pruned",
+            rolled_up, pruned
+        );
+    }
+    Ok(())
+}
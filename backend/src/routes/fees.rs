@@ -0,0 +1,414 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::Json,
+    routing::{get, put},
+    Router,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::{
+    auth::Claims,
+    database::Database,
+    routes::ledger::{post_journal, JournalKind},
+};
+
+/// Fee schedules are capped so a typo cannot take a creator's whole payment.
+const MAX_FEE_BASIS_POINTS: i32 = 5_000;
+
+/// What a buyer paid for; each kind has its own platform fee schedule.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ProductType {
+    Donation,
+    Membership,
+    Product,
+    Ticket,
+    /// Paid priority messages to a creator.
+    Message,
+}
+
+impl ProductType {
+    fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_uppercase().trim_end_matches('S') {
+            "DONATION" => Some(Self::Donation),
+            "MEMBERSHIP" => Some(Self::Membership),
+            "PRODUCT" => Some(Self::Product),
+            "TICKET" => Some(Self::Ticket),
+            "MESSAGE" => Some(Self::Message),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn as_str(self) -> &'static str {
+        match self {
+            Self::Donation => "DONATION",
+            Self::Membership => "MEMBERSHIP",
+            Self::Product => "PRODUCT",
+            Self::Ticket => "TICKET",
+            Self::Message => "MESSAGE",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct FeeSchedule {
+    pub product_type: String,
+    /// Percentage of the charge, in hundredths of a percent.
+    pub fee_basis_points: i32,
+    /// Flat amount added per charge, in the charge currency's minor unit.
+    pub fixed_fee_cents: i32,
+    pub updated_by: Option<String>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct UpdateFeeScheduleRequest {
+    fee_basis_points: i32,
+    #[serde(default)]
+    fixed_fee_cents: i32,
+}
+
+/// The platform's share of one charge, and where the rest of it goes.
+#[derive(Debug, Clone)]
+pub(crate) struct PlatformFee {
+    pub product_type: ProductType,
+    pub creator_id: String,
+    pub gross_cents: i64,
+    pub fee_cents: i64,
+    /// Tax charged on top of `gross_cents`; it stays with the platform, which remits it.
+    pub tax_cents: i64,
+    /// The creator's Connect account when it can accept charges; without one the whole charge
+    /// lands on the platform account and the creator's share is only tracked in the ledger.
+    pub destination: Option<String>,
+}
+
+/// Where a ledger entry's money came from.
+pub(crate) struct LedgerSource<'a> {
+    pub source_type: &'a str,
+    pub source_id: String,
+    pub payer_id: &'a str,
+    pub currency: &'a str,
+    pub stripe_checkout_session_id: Option<&'a str>,
+    pub stripe_payment_intent_id: Option<&'a str>,
+}
+
+pub fn fee_routes() -> Router<Database> {
+    Router::new()
+        .route("/schedules", get(list_fee_schedules))
+        .route("/schedules/:product_type", put(update_fee_schedule))
+}
+
+async fn list_fee_schedules(
+    State(db): State<Database>,
+    claims: Claims,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    ensure_admin(&db, &claims.sub).await?;
+
+    let schedules =
+        sqlx::query_as::<_, FeeSchedule>("SELECT * FROM platform_fee_schedules ORDER BY product_type")
+            .fetch_all(&db.pool)
+            .await
+            .map_err(|e| {
+                tracing::error!("Failed to list fee schedules: {}", e);
+                StatusCode::INTERNAL_SERVER_ERROR
+            })?;
+
+    Ok(Json(json!({
+        "success": true,
+        "data": schedules
+    })))
+}
+
+// Changes apply to charges created afterwards; existing ledger entries keep the fee they were charged
+async fn update_fee_schedule(
+    State(db): State<Database>,
+    Path(product_type): Path<String>,
+    claims: Claims,
+    Json(payload): Json<UpdateFeeScheduleRequest>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    ensure_admin(&db, &claims.sub).await?;
+    let product_type = ProductType::parse(&product_type).ok_or(StatusCode::NOT_FOUND)?;
+    if !(0..=MAX_FEE_BASIS_POINTS).contains(&payload.fee_basis_points) || payload.fixed_fee_cents < 0 {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let schedule = sqlx::query_as::<_, FeeSchedule>(
+        r#"
+        INSERT INTO platform_fee_schedules (product_type, fee_basis_points, fixed_fee_cents, updated_by)
+        VALUES ($1, $2, $3, $4)
+        ON CONFLICT (product_type) DO UPDATE
+        SET fee_basis_points = EXCLUDED.fee_basis_points,
+            fixed_fee_cents = EXCLUDED.fixed_fee_cents,
+            updated_by = EXCLUDED.updated_by,
+            updated_at = NOW()
+        RETURNING *
+        "#,
+    )
+    .bind(product_type.as_str())
+    .bind(payload.fee_basis_points)
+    .bind(payload.fixed_fee_cents)
+    .bind(&claims.sub)
+    .fetch_one(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to update {} fee schedule: {}", product_type.as_str(), e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    Ok(Json(json!({
+        "success": true,
+        "data": schedule
+    })))
+}
+
+pub(crate) async fn ensure_admin(db: &Database, user_id: &str) -> Result<(), StatusCode> {
+    let is_admin = sqlx::query_scalar::<_, Option<bool>>("SELECT is_admin FROM users WHERE id = $1")
+        .bind(user_id)
+        .fetch_optional(&db.pool)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::UNAUTHORIZED)?
+        .unwrap_or(false);
+
+    if is_admin {
+        Ok(())
+    } else {
+        Err(StatusCode::FORBIDDEN)
+    }
+}
+
+/// Work out the platform fee on a charge to one of `creator_id`'s offerings.
+pub(crate) async fn quote_platform_fee(
+    db: &Database,
+    product_type: ProductType,
+    creator_id: &str,
+    gross_cents: i64,
+) -> Result<PlatformFee, StatusCode> {
+    let schedule = sqlx::query_as::<_, (i32, i32)>(
+        "SELECT fee_basis_points, fixed_fee_cents FROM platform_fee_schedules WHERE product_type = $1",
+    )
+    .bind(product_type.as_str())
+    .fetch_optional(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to load {} fee schedule: {}", product_type.as_str(), e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    let (basis_points, fixed_cents) = schedule.unwrap_or((0, 0));
+    let fee_cents = fee_cents(gross_cents, basis_points, fixed_cents);
+
+    let destination = sqlx::query_scalar::<_, String>(
+        r#"
+        SELECT stripe_account_id FROM creator_connect_accounts
+        WHERE user_id = $1 AND status = 'ENABLED' AND charges_enabled
+        "#,
+    )
+    .bind(creator_id)
+    .fetch_optional(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to load Connect account of {}: {}", creator_id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    Ok(PlatformFee {
+        product_type,
+        creator_id: creator_id.to_string(),
+        gross_cents,
+        fee_cents,
+        tax_cents: 0,
+        destination,
+    })
+}
+
+/// A percentage of `gross_cents` rounded to the nearest cent, plus a fixed part; never negative
+/// and never more than the payment itself.
+fn fee_cents(gross_cents: i64, basis_points: i32, fixed_cents: i32) -> i64 {
+    ((gross_cents * basis_points as i64 + 5_000) / 10_000 + fixed_cents as i64).clamp(0, gross_cents.max(0))
+}
+
+impl PlatformFee {
+    pub(crate) fn net_cents(&self) -> i64 {
+        self.gross_cents - self.fee_cents
+    }
+
+    pub(crate) fn with_tax(mut self, tax_cents: i64) -> Self {
+        self.tax_cents = tax_cents.max(0);
+        self
+    }
+
+    /// Collect the charge on the platform account even when the creator could take it directly,
+    /// for charges that pay several creators at once.
+    pub(crate) fn held_by_platform(mut self) -> Self {
+        self.destination = None;
+        self
+    }
+
+    /// Form fields routing a Checkout Session's payment to the creator minus the fee.
+    pub(crate) fn checkout_params(&self) -> Vec<(String, String)> {
+        self.charge_params(Some("payment_intent_data"))
+    }
+
+    /// Form fields routing a PaymentIntent to the creator minus the fee.
+    pub(crate) fn payment_intent_params(&self) -> Vec<(String, String)> {
+        self.charge_params(None)
+    }
+
+    // Stripe only takes an application fee on charges destined for a connected account
+    fn charge_params(&self, scope: Option<&str>) -> Vec<(String, String)> {
+        let Some(destination) = &self.destination else {
+            return Vec::new();
+        };
+        let key = |path: &[&str]| {
+            let (head, rest) = match scope {
+                Some(scope) => (scope, path),
+                None => (path[0], &path[1..]),
+            };
+            rest.iter().fold(head.to_string(), |key, part| format!("{key}[{part}]"))
+        };
+
+        let mut params = vec![(key(&["transfer_data", "destination"]), destination.clone())];
+        let platform_share = self.fee_cents + self.tax_cents;
+        if platform_share > 0 {
+            params.push((key(&["application_fee_amount"]), platform_share.to_string()));
+        }
+        params
+    }
+
+    /// Record the charge as a pending ledger entry; it settles once payment is confirmed.
+    pub(crate) async fn record(&self, db: &Database, source: LedgerSource<'_>) -> Result<uuid::Uuid, StatusCode> {
+        self.record_on(&db.pool, source).await
+    }
+
+    /// `record` on `executor`, e.g. inside the transaction that confirms the payment.
+    pub(crate) async fn record_on<'e, E>(
+        &self,
+        executor: E,
+        source: LedgerSource<'_>,
+    ) -> Result<uuid::Uuid, StatusCode>
+    where
+        E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+    {
+        sqlx::query_scalar::<_, uuid::Uuid>(
+            r#"
+            INSERT INTO ledger_entries (
+                product_type, source_type, source_id, creator_id, payer_id, currency,
+                gross_cents, fee_cents, net_cents, stripe_account_id,
+                stripe_checkout_session_id, stripe_payment_intent_id, tax_cents
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13)
+            RETURNING id
+            "#,
+        )
+        .bind(self.product_type.as_str())
+        .bind(source.source_type)
+        .bind(&source.source_id)
+        .bind(&self.creator_id)
+        .bind(source.payer_id)
+        .bind(source.currency.to_uppercase())
+        .bind(self.gross_cents)
+        .bind(self.fee_cents)
+        .bind(self.net_cents())
+        .bind(&self.destination)
+        .bind(source.stripe_checkout_session_id)
+        .bind(source.stripe_payment_intent_id)
+        .bind(self.tax_cents)
+        .fetch_one(executor)
+        .await
+        .map_err(|e| {
+            tracing::error!(
+                "Failed to record ledger entry for {} {}: {}",
+                source.source_type,
+                source.source_id,
+                e
+            );
+            StatusCode::INTERNAL_SERVER_ERROR
+        })
+    }
+}
+
+/// Settle the pending ledger entries and tax lines of a paid checkout session or payment intent,
+/// and journal the settled entries.
+pub(crate) async fn settle_ledger_entries(
+    db: &Database,
+    stripe_reference: &str,
+    payment_intent_id: Option<&str>,
+) -> Result<(), StatusCode> {
+    let db_error = |e: sqlx::Error| {
+        tracing::error!("Failed to settle ledger entries for {}: {}", stripe_reference, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    };
+    let mut tx = db.pool.begin().await.map_err(db_error)?;
+    settle_ledger_entries_on(&mut tx, stripe_reference, payment_intent_id).await?;
+    tx.commit().await.map_err(db_error)
+}
+
+/// `settle_ledger_entries` inside `tx`, so settling commits together with what confirmed the
+/// payment.
+pub(crate) async fn settle_ledger_entries_on(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    stripe_reference: &str,
+    payment_intent_id: Option<&str>,
+) -> Result<(), StatusCode> {
+    let db_error = |e: sqlx::Error| {
+        tracing::error!("Failed to settle ledger entries for {}: {}", stripe_reference, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    };
+
+    let settle = |table: &str| {
+        format!(
+            r#"
+            UPDATE {}
+            SET status = 'SETTLED',
+                stripe_payment_intent_id = COALESCE($2, stripe_payment_intent_id),
+                settled_at = NOW()
+            WHERE status = 'PENDING'
+              AND (stripe_checkout_session_id = $1 OR stripe_payment_intent_id = $1)
+            RETURNING id
+            "#,
+            table
+        )
+    };
+    let settled = sqlx::query_scalar::<_, uuid::Uuid>(&settle("ledger_entries"))
+        .bind(stripe_reference)
+        .bind(payment_intent_id)
+        .fetch_all(&mut *tx)
+        .await
+        .map_err(db_error)?;
+    post_journal(&mut *tx, &settled, JournalKind::Settlement)
+        .await
+        .map_err(db_error)?;
+    sqlx::query(&settle("tax_lines"))
+        .bind(stripe_reference)
+        .bind(payment_intent_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(db_error)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fee_rounds_to_the_nearest_cent() {
+        // 5% of $10.10 is 50.5 cents
+        assert_eq!(fee_cents(1_010, 500, 0), 51);
+        assert_eq!(fee_cents(1_009, 500, 0), 50);
+        assert_eq!(fee_cents(1_000, 290, 30), 59);
+        assert_eq!(fee_cents(1_000, 0, 0), 0);
+    }
+
+    #[test]
+    fn fee_never_exceeds_the_payment() {
+        assert_eq!(fee_cents(20, 500, 30), 20);
+        assert_eq!(fee_cents(0, 500, 30), 0);
+        assert_eq!(fee_cents(-100, 500, 30), 0);
+        assert_eq!(fee_cents(1_000, 0, -50), 0);
+    }
+}
@@ -0,0 +1,155 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::Json,
+    routing::{get, post},
+    Router,
+};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use serde_json::json;
+
+use crate::{auth::Claims, database::Database};
+
+/// Content a followed creator published since the viewer last looked at them.
+#[derive(Debug, Serialize, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+struct NewContentCount {
+    creator_id: String,
+    name: Option<String>,
+    username: Option<String>,
+    avatar: Option<String>,
+    /// The last visit, or when the viewer followed the creator if they haven't visited since.
+    since: DateTime<Utc>,
+    new_posts: i64,
+    new_articles: i64,
+    new_events: i64,
+    total: i64,
+}
+
+pub fn following_routes() -> Router<Database> {
+    Router::new()
+        .route("/new-content", get(get_new_content_counts))
+        .route("/:creator_id/seen", post(mark_creator_seen))
+}
+
+/// Move the viewer's last-seen cursor for a creator up to now.
+pub(crate) async fn mark_seen(
+    db: &Database,
+    user_id: &str,
+    creator_id: &str,
+) -> Result<DateTime<Utc>, sqlx::Error> {
+    sqlx::query_scalar(
+        r#"
+        INSERT INTO creator_last_seen (user_id, creator_id, seen_at) VALUES ($1, $2, NOW())
+        ON CONFLICT (user_id, creator_id) DO UPDATE SET seen_at = EXCLUDED.seen_at
+        RETURNING seen_at
+        "#,
+    )
+    .bind(user_id)
+    .bind(creator_id)
+    .fetch_one(&db.pool)
+    .await
+}
+
+// Followed creators with something new first
+async fn get_new_content_counts(
+    State(db): State<Database>,
+    claims: Claims,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let counts = sqlx::query_as::<_, NewContentCount>(
+        r#"
+        WITH followed AS (
+            SELECT
+                f.following_id AS creator_id,
+                GREATEST(COALESCE(f.created_at, NOW()), COALESCE(s.seen_at, '-infinity')) AS since
+            FROM follows f
+            LEFT JOIN creator_last_seen s
+                ON s.user_id = f.follower_id AND s.creator_id = f.following_id
+            WHERE f.follower_id = $1
+        ),
+        counted AS (
+            SELECT
+                fo.creator_id,
+                fo.since,
+                (
+                    SELECT COUNT(*) FROM posts p
+                    WHERE p.user_id = fo.creator_id
+                      AND COALESCE(p.published, TRUE)
+                      AND COALESCE(p.published_at, p.created_at) > fo.since
+                      AND COALESCE(p.published_at, p.created_at) <= NOW()
+                ) AS new_posts,
+                (
+                    SELECT COUNT(*) FROM articles a
+                    WHERE a.author_id = fo.creator_id
+                      AND COALESCE(a.published_at, a.created_at) > fo.since
+                      AND COALESCE(a.published_at, a.created_at) <= NOW()
+                ) AS new_articles,
+                (
+                    SELECT COUNT(*) FROM events e
+                    WHERE e.host_id = fo.creator_id
+                      AND e.created_at > fo.since
+                      AND COALESCE(e.is_public, TRUE)
+                      AND UPPER(COALESCE(e.status, 'PUBLISHED')) NOT IN ('DRAFT', 'CANCELLED')
+                ) AS new_events
+            FROM followed fo
+        )
+        SELECT
+            c.creator_id,
+            COALESCE(u.display_name, u.name) AS name,
+            u.username,
+            u.avatar_url AS avatar,
+            c.since,
+            c.new_posts,
+            c.new_articles,
+            c.new_events,
+            c.new_posts + c.new_articles + c.new_events AS total
+        FROM counted c
+        JOIN users u ON u.id = c.creator_id
+        ORDER BY total DESC, c.since ASC
+        "#,
+    )
+    .bind(&claims.sub)
+    .fetch_all(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to count new content for {}: {}", claims.sub, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    let total: i64 = counts.iter().map(|count| count.total).sum();
+    Ok(Json(json!({
+        "success": true,
+        "data": counts,
+        "total": total
+    })))
+}
+
+async fn mark_creator_seen(
+    State(db): State<Database>,
+    Path(creator_id): Path<String>,
+    claims: Claims,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let follows = sqlx::query_scalar::<_, bool>(
+        "SELECT EXISTS(SELECT 1 FROM follows WHERE follower_id = $1 AND following_id = $2)",
+    )
+    .bind(&claims.sub)
+    .bind(&creator_id)
+    .fetch_one(&db.pool)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    if !follows {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    let seen_at = mark_seen(&db, &claims.sub, &creator_id)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to mark creator {} seen: {}", creator_id, e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+    Ok(Json(json!({
+        "success": true,
+        "data": { "creatorId": creator_id, "seenAt": seen_at }
+    })))
+}
@@ -0,0 +1,183 @@
+use axum::http::StatusCode;
+use uuid::Uuid;
+
+use crate::{database::Database, routes::payments::refund_unfulfilled};
+
+/// How long the Checkout session of a limited product stays open. Stripe rejects an
+/// `expires_at` less than 30 minutes after the session is created, so this leaves a margin.
+pub(crate) const CHECKOUT_SESSION_MINUTES: i64 = 32;
+
+/// How long a unit stays held for a buyer who started checkout. It outlasts the Checkout
+/// session, so a session can never be paid for after its hold lapsed. In-page PaymentIntents
+/// have no expiry, so `consume_stock` checks their unit again once they are paid.
+const STOCK_HOLD_MINUTES: i64 = CHECKOUT_SESSION_MINUTES + 5;
+
+/// Hold one unit of a limited product for a checkout, or fail with 409 when every unit left is
+/// sold or held. The product row is locked while counting, so parallel checkouts cannot hold the
+/// last unit twice. Products without a stock count need no hold.
+pub(crate) async fn hold_stock(db: &Database, product_id: Uuid) -> Result<Option<Uuid>, StatusCode> {
+    let mut tx = db.pool.begin().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let stock = sqlx::query_scalar::<_, Option<i32>>("SELECT stock FROM products WHERE id = $1 FOR UPDATE")
+        .bind(product_id)
+        .fetch_optional(&mut tx)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+    let Some(stock) = stock else {
+        return Ok(None);
+    };
+
+    let held = sqlx::query_scalar::<_, i64>(
+        "SELECT COUNT(*) FROM stock_holds WHERE product_id = $1 AND expires_at > NOW()",
+    )
+    .bind(product_id)
+    .fetch_one(&mut tx)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    if i64::from(stock) - held <= 0 {
+        return Err(StatusCode::CONFLICT);
+    }
+
+    let hold_id = sqlx::query_scalar::<_, Uuid>(
+        r#"
+        INSERT INTO stock_holds (product_id, expires_at)
+        VALUES ($1, NOW() + make_interval(mins => $2))
+        RETURNING id
+        "#,
+    )
+    .bind(product_id)
+    .bind(STOCK_HOLD_MINUTES as i32)
+    .fetch_one(&mut tx)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to hold stock of product {}: {}", product_id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    tx.commit().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    Ok(Some(hold_id))
+}
+
+/// Tie a hold to the purchase it was taken for, so completing the purchase sells the unit.
+pub(crate) async fn attach_hold(db: &Database, hold_id: Option<Uuid>, purchase_id: Uuid) -> Result<(), StatusCode> {
+    let Some(hold_id) = hold_id else {
+        return Ok(());
+    };
+    sqlx::query("UPDATE stock_holds SET purchase_id = $2 WHERE id = $1")
+        .bind(hold_id)
+        .bind(purchase_id)
+        .execute(&db.pool)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    Ok(())
+}
+
+/// Give a hold back when the checkout it was taken for could not be created.
+pub(crate) async fn release_hold(db: &Database, hold_id: Option<Uuid>) {
+    let Some(hold_id) = hold_id else {
+        return;
+    };
+    if let Err(e) = sqlx::query("DELETE FROM stock_holds WHERE id = $1")
+        .bind(hold_id)
+        .execute(&db.pool)
+        .await
+    {
+        tracing::warn!("Failed to release stock hold {}: {}", hold_id, e);
+    }
+}
+
+/// Turn the holds of completed purchases into sales, taking the units off the stock count. The
+/// reference is a checkout session, payment intent or purchase id. A hold is consumed with its
+/// sale, so repeated completion events count a unit once.
+///
+/// An in-page PaymentIntent can still be paid after its hold lapsed, by which time the unit may
+/// have gone to another buyer. Each unit is therefore counted again under the product's row
+/// lock, and a purchase whose unit is gone is refunded in full instead of overselling.
+pub(crate) async fn consume_stock(db: &Database, reference: &str) -> Result<(), StatusCode> {
+    let db_error = |e: sqlx::Error| {
+        tracing::error!("Failed to take sold units of {} off stock: {}", reference, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    };
+    let mut tx = db.pool.begin().await.map_err(db_error)?;
+    // A purchase already being refunded as sold out stays that way, even if a unit came free
+    let holds = sqlx::query_as::<_, (Uuid, Uuid, Uuid, bool)>(
+        r#"
+        SELECT h.id, h.product_id, p.id,
+               EXISTS (
+                   SELECT 1 FROM refunds r
+                   JOIN ledger_entries l ON l.id = r.ledger_entry_id
+                   WHERE l.source_type = 'PURCHASE' AND l.source_id = p.id::TEXT
+                     AND r.reason = 'FULFILLMENT_FAILED' AND r.status <> 'FAILED'
+               )
+        FROM stock_holds h
+        JOIN purchases p ON p.id = h.purchase_id
+        WHERE p.status = 'COMPLETED'
+          AND (p.stripe_checkout_session_id = $1 OR p.stripe_payment_intent_id = $1 OR p.id::TEXT = $1)
+        ORDER BY h.product_id
+        FOR UPDATE OF h
+        "#,
+    )
+    .bind(reference)
+    .fetch_all(&mut tx)
+    .await
+    .map_err(db_error)?;
+
+    let mut sold_out = Vec::new();
+    for (hold_id, product_id, purchase_id, refunding) in holds {
+        if refunding {
+            sold_out.push((hold_id, purchase_id));
+            continue;
+        }
+        let stock = sqlx::query_scalar::<_, Option<i32>>("SELECT stock FROM products WHERE id = $1 FOR UPDATE")
+            .bind(product_id)
+            .fetch_optional(&mut tx)
+            .await
+            .map_err(db_error)?
+            .flatten();
+        if let Some(stock) = stock {
+            // Units other buyers still hold are theirs; a lapsed hold of this purchase is not
+            let held = sqlx::query_scalar::<_, i64>(
+                "SELECT COUNT(*) FROM stock_holds WHERE product_id = $1 AND id <> $2 AND expires_at > NOW()",
+            )
+            .bind(product_id)
+            .bind(hold_id)
+            .fetch_one(&mut tx)
+            .await
+            .map_err(db_error)?;
+            if i64::from(stock) - held <= 0 {
+                sold_out.push((hold_id, purchase_id));
+                continue;
+            }
+            sqlx::query("UPDATE products SET stock = stock - 1, updated_at = NOW() WHERE id = $1")
+                .bind(product_id)
+                .execute(&mut tx)
+                .await
+                .map_err(db_error)?;
+        }
+        sqlx::query("DELETE FROM stock_holds WHERE id = $1")
+            .bind(hold_id)
+            .execute(&mut tx)
+            .await
+            .map_err(db_error)?;
+    }
+    tx.commit().await.map_err(db_error)?;
+
+    // Sold-out holds stay until their refund is through, so a retry finds and finishes it
+    for (hold_id, purchase_id) in sold_out {
+        tracing::warn!("Purchase {} was paid after its product sold out; refunding it", purchase_id);
+        let ledger_entry_id = sqlx::query_scalar::<_, Uuid>(
+            "SELECT id FROM ledger_entries WHERE source_type = 'PURCHASE' AND source_id = $1",
+        )
+        .bind(purchase_id.to_string())
+        .fetch_optional(&db.pool)
+        .await
+        .map_err(db_error)?;
+        match ledger_entry_id {
+            Some(ledger_entry_id) => {
+                refund_unfulfilled(db, ledger_entry_id, "The product sold out before payment completed").await?
+            }
+            None => tracing::error!("Sold-out purchase {} has no ledger entry to refund", purchase_id),
+        }
+        release_hold(db, Some(hold_id)).await;
+    }
+    Ok(())
+}
@@ -0,0 +1,472 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::{header, StatusCode},
+    response::{IntoResponse, Json, Response},
+    routing::get,
+    Router,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sqlx::Row;
+use uuid::Uuid;
+
+use crate::{
+    auth::Claims,
+    database::Database,
+    email_service::{app_link, queue_email, EmailTemplate},
+    invoice_pdf::{format_amount, render_invoice, InvoiceDocument, InvoiceLine},
+    storage,
+};
+
+/// Invoice download links are meant to be forwarded, e.g. to an accountant, so they last a day.
+const INVOICE_URL_TTL_SECONDS: i64 = 24 * 60 * 60;
+
+/// A numbered invoice for one purchase or subscription payment. Numbers run per creator
+/// without gaps, as most tax authorities require.
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct Invoice {
+    pub id: Uuid,
+    pub creator_id: String,
+    pub buyer_id: Option<String>,
+    pub invoice_number: String,
+    pub sequence: i32,
+    /// `PURCHASE` or `SUBSCRIPTION`.
+    pub source_type: String,
+    /// Purchase id, or the Stripe invoice id of a subscription payment.
+    pub source_id: String,
+    pub subscription_id: Option<Uuid>,
+    pub description: String,
+    pub amount_cents: i64,
+    pub currency: String,
+    #[serde(skip)]
+    pub storage_key: Option<String>,
+    pub issued_at: DateTime<Utc>,
+}
+
+/// What an invoice is issued for.
+pub(crate) struct InvoiceSource {
+    pub source_type: &'static str,
+    pub source_id: String,
+    pub creator_id: String,
+    pub buyer_id: Option<String>,
+    pub subscription_id: Option<Uuid>,
+    pub description: String,
+    pub amount_cents: i64,
+    pub currency: String,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct InvoiceQuery {
+    /// `pdf` streams the document instead of returning its metadata.
+    format: Option<String>,
+}
+
+pub fn purchase_invoice_routes() -> Router<Database> {
+    Router::new().route("/:id/invoice", get(get_purchase_invoice))
+}
+
+pub fn subscription_invoice_routes() -> Router<Database> {
+    Router::new().route("/:id/invoices", get(list_subscription_invoices))
+}
+
+// Buyers and the selling creator can fetch it; purchases made before invoicing existed get
+// their invoice on first request
+async fn get_purchase_invoice(
+    State(db): State<Database>,
+    Path(purchase_id): Path<Uuid>,
+    Query(params): Query<InvoiceQuery>,
+    claims: Claims,
+) -> Result<Response, StatusCode> {
+    let row = sqlx::query(
+        r#"
+        SELECT p.user_id, p.status, pr.user_id AS creator_id
+        FROM purchases p
+        JOIN products pr ON pr.id = p.product_id
+        WHERE p.id = $1
+        "#,
+    )
+    .bind(purchase_id)
+    .fetch_optional(&db.pool)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    .ok_or(StatusCode::NOT_FOUND)?;
+    let buyer_id: String = row.get("user_id");
+    let creator_id: String = row.get("creator_id");
+    if claims.sub != buyer_id && claims.sub != creator_id {
+        return Err(StatusCode::NOT_FOUND);
+    }
+    if row.get::<String, _>("status") != "COMPLETED" {
+        return Err(StatusCode::CONFLICT);
+    }
+
+    let invoice = issue_purchase_invoice(&db, purchase_id)
+        .await?
+        .ok_or(StatusCode::CONFLICT)?;
+
+    if params.format.as_deref() == Some("pdf") {
+        return invoice_pdf_response(&db, &invoice).await;
+    }
+    Ok(Json(json!({
+        "success": true,
+        "data": invoice_json(&db, invoice).await?
+    }))
+    .into_response())
+}
+
+async fn list_subscription_invoices(
+    State(db): State<Database>,
+    Path(subscription_id): Path<Uuid>,
+    claims: Claims,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let (user_id, creator_id) = sqlx::query_as::<_, (String, String)>(
+        "SELECT user_id, creator_id FROM subscriptions WHERE id = $1",
+    )
+    .bind(subscription_id)
+    .fetch_optional(&db.pool)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    .ok_or(StatusCode::NOT_FOUND)?;
+    if claims.sub != user_id && claims.sub != creator_id {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    let invoices = sqlx::query_as::<_, Invoice>(
+        "SELECT * FROM invoices WHERE subscription_id = $1 ORDER BY issued_at DESC",
+    )
+    .bind(subscription_id)
+    .fetch_all(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to list invoices of subscription {}: {}", subscription_id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    let mut data = Vec::with_capacity(invoices.len());
+    for invoice in invoices {
+        data.push(invoice_json(&db, invoice).await?);
+    }
+
+    Ok(Json(json!({
+        "success": true,
+        "data": data
+    })))
+}
+
+/// Issue the invoice of a completed purchase, or return the one already issued.
+pub(crate) async fn issue_purchase_invoice(
+    db: &Database,
+    purchase_id: Uuid,
+) -> Result<Option<Invoice>, StatusCode> {
+    let row = sqlx::query(
+        r#"
+        SELECT p.user_id, p.amount, p.currency, pr.user_id AS creator_id, pr.name
+        FROM purchases p
+        JOIN products pr ON pr.id = p.product_id
+        WHERE p.id = $1 AND p.status = 'COMPLETED'
+        "#,
+    )
+    .bind(purchase_id)
+    .fetch_optional(&db.pool)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let Some(row) = row else {
+        return Ok(None);
+    };
+
+    issue_invoice(
+        db,
+        InvoiceSource {
+            source_type: "PURCHASE",
+            source_id: purchase_id.to_string(),
+            creator_id: row.get("creator_id"),
+            buyer_id: Some(row.get("user_id")),
+            subscription_id: None,
+            description: row.get("name"),
+            amount_cents: (row.get::<f64, _>("amount") * 100.0).round() as i64,
+            currency: row.get("currency"),
+        },
+    )
+    .await
+    .map(Some)
+}
+
+/// Invoice every purchase completed by a checkout session or payment intent. Failures are only
+/// logged: the invoice endpoint issues anything missed here on first request.
+pub(crate) async fn issue_purchase_invoices(db: &Database, stripe_reference: &str) {
+    let purchase_ids = match sqlx::query_scalar::<_, Uuid>(
+        r#"
+        SELECT id FROM purchases
+        WHERE status = 'COMPLETED'
+          AND (stripe_checkout_session_id = $1 OR stripe_payment_intent_id = $1)
+        "#,
+    )
+    .bind(stripe_reference)
+    .fetch_all(&db.pool)
+    .await
+    {
+        Ok(ids) => ids,
+        Err(e) => {
+            tracing::warn!("Failed to load purchases paid by {}: {}", stripe_reference, e);
+            return;
+        }
+    };
+
+    for purchase_id in purchase_ids {
+        if issue_purchase_invoice(db, purchase_id).await.is_err() {
+            tracing::warn!("Failed to invoice purchase {}", purchase_id);
+        }
+    }
+}
+
+/// Issue an invoice, once per source. The number comes from the creator's counter inside the
+/// same transaction, so a lost race rolls the counter back instead of leaving a gap.
+pub(crate) async fn issue_invoice(db: &Database, source: InvoiceSource) -> Result<Invoice, StatusCode> {
+    if let Some(invoice) = find_invoice(db, source.source_type, &source.source_id).await? {
+        return Ok(invoice);
+    }
+
+    let mut tx = db.pool.begin().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let sequence = sqlx::query_scalar::<_, i32>(
+        r#"
+        INSERT INTO creator_invoice_counters (creator_id, last_number)
+        VALUES ($1, 1)
+        ON CONFLICT (creator_id) DO UPDATE
+        SET last_number = creator_invoice_counters.last_number + 1
+        RETURNING last_number
+        "#,
+    )
+    .bind(&source.creator_id)
+    .fetch_one(&mut tx)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to number invoice for {}: {}", source.creator_id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    let prefix = sqlx::query_scalar::<_, String>("SELECT username FROM users WHERE id = $1")
+        .bind(&source.creator_id)
+        .fetch_optional(&mut tx)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .map(|username| invoice_prefix(&username))
+        .unwrap_or_else(|| "INV".to_string());
+
+    let invoice = sqlx::query_as::<_, Invoice>(
+        r#"
+        INSERT INTO invoices (
+            creator_id, buyer_id, invoice_number, sequence, source_type, source_id,
+            subscription_id, description, amount_cents, currency
+        )
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+        ON CONFLICT (source_type, source_id) DO NOTHING
+        RETURNING *
+        "#,
+    )
+    .bind(&source.creator_id)
+    .bind(&source.buyer_id)
+    .bind(format!("{}-{:06}", prefix, sequence))
+    .bind(sequence)
+    .bind(source.source_type)
+    .bind(&source.source_id)
+    .bind(source.subscription_id)
+    .bind(&source.description)
+    .bind(source.amount_cents)
+    .bind(source.currency.to_uppercase())
+    .fetch_optional(&mut tx)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to issue invoice for {} {}: {}", source.source_type, source.source_id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    let Some(invoice) = invoice else {
+        tx.rollback().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        return find_invoice(db, source.source_type, &source.source_id)
+            .await?
+            .ok_or(StatusCode::INTERNAL_SERVER_ERROR);
+    };
+    tx.commit().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    email_receipt(db, &invoice).await;
+
+    // The PDF is rendered again on download if storing it fails here
+    match store_invoice_pdf(db, &invoice).await {
+        Ok(stored) => Ok(stored),
+        Err(_) => Ok(invoice),
+    }
+}
+
+/// Email the buyer a receipt for a freshly issued invoice.
+async fn email_receipt(db: &Database, invoice: &Invoice) {
+    let Some(buyer_id) = &invoice.buyer_id else {
+        return;
+    };
+    let recipients = sqlx::query_as::<_, (Option<String>, String)>(
+        r#"
+        SELECT buyer.email, creator.name
+        FROM users buyer, users creator
+        WHERE buyer.id = $1 AND creator.id = $2
+        "#,
+    )
+    .bind(buyer_id)
+    .bind(&invoice.creator_id)
+    .fetch_optional(&db.pool)
+    .await;
+    let (email, creator_name) = match recipients {
+        Ok(Some((Some(email), creator_name))) => (email, creator_name),
+        Ok(_) => return,
+        Err(e) => {
+            tracing::warn!("Failed to look up receipt recipient of {}: {}", invoice.id, e);
+            return;
+        }
+    };
+
+    queue_email(
+        db,
+        &email,
+        Some(buyer_id),
+        EmailTemplate::Receipt,
+        json!({
+            "creator_name": creator_name,
+            "description": invoice.description,
+            "amount": format_amount(invoice.amount_cents, &invoice.currency),
+            "invoice_number": invoice.invoice_number,
+            "link": app_link("/purchases"),
+        }),
+    )
+    .await;
+}
+
+async fn find_invoice(
+    db: &Database,
+    source_type: &str,
+    source_id: &str,
+) -> Result<Option<Invoice>, StatusCode> {
+    sqlx::query_as::<_, Invoice>("SELECT * FROM invoices WHERE source_type = $1 AND source_id = $2")
+        .bind(source_type)
+        .bind(source_id)
+        .fetch_optional(&db.pool)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
+}
+
+/// Up to eight letters and digits of the creator's username, so numbers read like `JANE-000042`.
+fn invoice_prefix(username: &str) -> String {
+    let prefix: String = username
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .take(8)
+        .collect::<String>()
+        .to_ascii_uppercase();
+    if prefix.is_empty() {
+        "INV".to_string()
+    } else {
+        prefix
+    }
+}
+
+async fn load_document(db: &Database, invoice: &Invoice) -> Result<InvoiceDocument, StatusCode> {
+    let party = |user_id: Option<String>| async move {
+        let Some(user_id) = user_id else {
+            return Ok::<_, StatusCode>(("Customer".to_string(), None));
+        };
+        let row = sqlx::query("SELECT display_name, username, email FROM users WHERE id = $1")
+            .bind(&user_id)
+            .fetch_optional(&db.pool)
+            .await
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        Ok(match row {
+            Some(row) => (
+                row.get::<Option<String>, _>("display_name")
+                    .filter(|name| !name.trim().is_empty())
+                    .unwrap_or_else(|| row.get("username")),
+                row.get::<Option<String>, _>("email"),
+            ),
+            None => ("Customer".to_string(), None),
+        })
+    };
+    let (seller_name, seller_email) = party(Some(invoice.creator_id.clone())).await?;
+    let (buyer_name, buyer_email) = party(invoice.buyer_id.clone()).await?;
+
+    Ok(InvoiceDocument {
+        number: invoice.invoice_number.clone(),
+        issued_at: invoice.issued_at,
+        seller_name,
+        seller_email,
+        buyer_name,
+        buyer_email,
+        lines: vec![InvoiceLine {
+            description: invoice.description.clone(),
+            amount_cents: invoice.amount_cents,
+        }],
+        currency: invoice.currency.clone(),
+        note: Some(format!("Paid in full. Reference {}.", invoice.source_id)),
+    })
+}
+
+fn render_pdf(document: &InvoiceDocument) -> Result<Vec<u8>, StatusCode> {
+    render_invoice(document).map_err(|e| {
+        tracing::error!("Failed to render invoice {}: {}", document.number, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })
+}
+
+async fn store_invoice_pdf(db: &Database, invoice: &Invoice) -> Result<Invoice, StatusCode> {
+    let pdf = render_pdf(&load_document(db, invoice).await?)?;
+    let stored = storage::store_private_file("invoices", Some("invoice.pdf"), "application/pdf", pdf)
+        .await
+        .map_err(|e| {
+            tracing::warn!("Failed to store invoice {}: {}", invoice.invoice_number, e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    sqlx::query_as::<_, Invoice>("UPDATE invoices SET storage_key = $2 WHERE id = $1 RETURNING *")
+        .bind(invoice.id)
+        .bind(&stored.storage_key)
+        .fetch_one(&db.pool)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
+}
+
+async fn invoice_json(db: &Database, invoice: Invoice) -> Result<serde_json::Value, StatusCode> {
+    let invoice = match invoice.storage_key {
+        Some(_) => invoice,
+        None => store_invoice_pdf(db, &invoice).await.unwrap_or(invoice),
+    };
+    let download = match &invoice.storage_key {
+        Some(storage_key) => storage::create_signed_url(storage_key, INVOICE_URL_TTL_SECONDS)
+            .await
+            .map_err(|e| tracing::warn!("Failed to sign invoice {}: {}", invoice.invoice_number, e))
+            .ok(),
+        None => None,
+    };
+
+    let mut data = json!(invoice);
+    data["downloadUrl"] = json!(download.as_ref().map(|(url, _)| url));
+    data["downloadExpiresAt"] = json!(download
+        .and_then(|(_, expires)| DateTime::<Utc>::from_timestamp(expires, 0)));
+    Ok(data)
+}
+
+async fn invoice_pdf_response(db: &Database, invoice: &Invoice) -> Result<Response, StatusCode> {
+    let stored = match &invoice.storage_key {
+        Some(storage_key) => storage::read_private_file(storage_key).await.ok(),
+        None => None,
+    };
+    let pdf = match stored {
+        Some(pdf) => pdf,
+        None => render_pdf(&load_document(db, invoice).await?)?,
+    };
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, "application/pdf".to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"invoice-{}.pdf\"", invoice.invoice_number),
+            ),
+        ],
+        pdf,
+    )
+        .into_response())
+}
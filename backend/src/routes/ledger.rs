@@ -0,0 +1,387 @@
+use std::collections::{BTreeMap, HashMap};
+
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::Json,
+    routing::get,
+    Router,
+};
+use chrono::{Duration, NaiveDate, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use uuid::Uuid;
+
+use crate::{
+    auth::Claims,
+    database::Database,
+    routes::{
+        fees::ensure_admin,
+        stripe::{stripe_json, stripe_secret, stripe_url},
+    },
+};
+
+/// Longest period one reconciliation report may cover.
+const MAX_RECONCILIATION_DAYS: i64 = 92;
+/// Stripe balance transactions fetched per report, 100 per page; the report says when it stopped.
+const MAX_BALANCE_TRANSACTION_PAGES: usize = 50;
+
+/// Why a journal entry was written for a ledger entry.
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum JournalKind {
+    /// The money of the ledger entry reached the platform's Stripe balance.
+    Settlement,
+    /// The money was taken back, e.g. by a lost dispute.
+    Reversal,
+}
+
+impl JournalKind {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Settlement => "SETTLEMENT",
+            Self::Reversal => "REVERSAL",
+        }
+    }
+
+    fn sign(self) -> i64 {
+        match self {
+            Self::Settlement => 1,
+            Self::Reversal => -1,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct ReconciliationQuery {
+    from: NaiveDate,
+    /// Inclusive; defaults to `from`.
+    to: Option<NaiveDate>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TrialBalanceQuery {
+    currency: Option<String>,
+}
+
+/// One money movement on the platform's Stripe balance.
+#[derive(Debug, Deserialize)]
+struct BalanceTransaction {
+    id: String,
+    amount: i64,
+    currency: String,
+    reporting_category: String,
+    /// The expanded charge, refund or dispute behind the movement.
+    source: Option<serde_json::Value>,
+}
+
+/// Money per currency and category, as seen by the ledger and by Stripe.
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct CategoryTotals {
+    ledger_cents: i64,
+    stripe_cents: i64,
+}
+
+pub fn ledger_routes() -> Router<Database> {
+    Router::new()
+        .route("/trial-balance", get(get_trial_balance))
+        .route("/reconciliation", get(get_reconciliation_report))
+}
+
+/// Write the journal of the given ledger entries: the gross and tax land on the balance of the
+/// provider that took the payment (Stripe or Coinbase), and are owed on to the creator (net), the
+/// platform (fee) and the tax authorities (tax). The postings of an entry sum to zero; entries
+/// already journaled for `kind` are skipped.
+pub(crate) async fn post_journal<'e, E>(
+    executor: E,
+    ledger_entry_ids: &[Uuid],
+    kind: JournalKind,
+) -> Result<(), sqlx::Error>
+where
+    E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+{
+    if ledger_entry_ids.is_empty() {
+        return Ok(());
+    }
+
+    sqlx::query(
+        r#"
+        WITH journaled AS (
+            INSERT INTO journal_entries (ledger_entry_id, kind, currency)
+            SELECT id, $2, currency FROM ledger_entries WHERE id = ANY($1)
+            ON CONFLICT (ledger_entry_id, kind) DO NOTHING
+            RETURNING id, ledger_entry_id
+        )
+        INSERT INTO journal_postings (journal_entry_id, account, creator_id, amount_cents)
+        SELECT j.id, p.account, p.creator_id, p.amount_cents * $3
+        FROM journaled j
+        JOIN ledger_entries l ON l.id = j.ledger_entry_id
+        CROSS JOIN LATERAL (
+            VALUES
+                (
+                    CASE l.payment_provider
+                        WHEN 'COINBASE' THEN 'COINBASE_BALANCE'
+                        ELSE 'STRIPE_BALANCE'
+                    END,
+                    NULL,
+                    l.gross_cents + l.tax_cents
+                ),
+                ('CREATOR_PAYABLE', l.creator_id, -l.net_cents),
+                ('PLATFORM_FEES', NULL, -l.fee_cents),
+                ('TAX_PAYABLE', NULL, -l.tax_cents)
+        ) AS p(account, creator_id, amount_cents)
+        WHERE p.amount_cents <> 0
+        "#,
+    )
+    .bind(ledger_entry_ids)
+    .bind(kind.as_str())
+    .bind(kind.sign())
+    .execute(executor)
+    .await?;
+
+    Ok(())
+}
+
+// Debits and credits per account; every currency's accounts must sum to zero
+async fn get_trial_balance(
+    State(db): State<Database>,
+    Query(params): Query<TrialBalanceQuery>,
+    claims: Claims,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    ensure_admin(&db, &claims.sub).await?;
+    let currency = params.currency.map(|currency| currency.trim().to_ascii_uppercase());
+
+    let rows = sqlx::query_as::<_, (String, String, i64, i64)>(
+        r#"
+        SELECT j.currency, p.account,
+               COALESCE(SUM(p.amount_cents) FILTER (WHERE p.amount_cents > 0), 0)::BIGINT,
+               COALESCE(-SUM(p.amount_cents) FILTER (WHERE p.amount_cents < 0), 0)::BIGINT
+        FROM journal_postings p
+        JOIN journal_entries j ON j.id = p.journal_entry_id
+        WHERE ($1::TEXT IS NULL OR j.currency = $1)
+        GROUP BY j.currency, p.account
+        ORDER BY j.currency, p.account
+        "#,
+    )
+    .bind(&currency)
+    .fetch_all(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to build trial balance: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    let mut imbalance = BTreeMap::<String, i64>::new();
+    let accounts: Vec<serde_json::Value> = rows
+        .into_iter()
+        .map(|(currency, account, debits, credits)| {
+            *imbalance.entry(currency.clone()).or_default() += debits - credits;
+            json!({
+                "currency": currency,
+                "account": account,
+                "debitCents": debits,
+                "creditCents": credits,
+                "balanceCents": debits - credits,
+            })
+        })
+        .collect();
+
+    Ok(Json(json!({
+        "success": true,
+        "data": {
+            "accounts": accounts,
+            "balanced": imbalance.values().all(|difference| *difference == 0),
+            "imbalanceCents": imbalance,
+        }
+    })))
+}
+
+// Compares what the journal says reached or left the Stripe balance with Stripe's own balance
+// transactions, per currency and per payment. Payments settled near the edges of the period can
+// be flagged when Stripe and the ledger date them on different sides of a boundary.
+async fn get_reconciliation_report(
+    State(db): State<Database>,
+    Query(params): Query<ReconciliationQuery>,
+    claims: Claims,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    ensure_admin(&db, &claims.sub).await?;
+    let to = params.to.unwrap_or(params.from);
+    if to < params.from || (to - params.from).num_days() >= MAX_RECONCILIATION_DAYS {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let start = Utc.from_utc_datetime(&params.from.and_hms_opt(0, 0, 0).unwrap_or_default());
+    let end = Utc.from_utc_datetime(&to.and_hms_opt(0, 0, 0).unwrap_or_default()) + Duration::days(1);
+
+    // What the journal moved on the Stripe balance, per payment intent and category
+    let ledger_rows = sqlx::query_as::<_, (Option<String>, String, String, i64)>(
+        r#"
+        SELECT l.stripe_payment_intent_id,
+               j.currency,
+               CASE
+                   WHEN j.kind = 'REVERSAL' THEN 'DISPUTE'
+                   WHEN l.source_type = 'REFUND' THEN 'REFUND'
+                   ELSE 'CHARGE'
+               END AS category,
+               SUM(p.amount_cents)::BIGINT
+        FROM journal_postings p
+        JOIN journal_entries j ON j.id = p.journal_entry_id
+        JOIN ledger_entries l ON l.id = j.ledger_entry_id
+        WHERE p.account = 'STRIPE_BALANCE' AND j.created_at >= $1 AND j.created_at < $2
+        GROUP BY 1, 2, 3
+        "#,
+    )
+    .bind(start)
+    .bind(end)
+    .fetch_all(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to load journal for reconciliation: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    let (stripe_transactions, complete) = fetch_balance_transactions(start.timestamp(), end.timestamp()).await?;
+
+    let mut totals = BTreeMap::<(String, &'static str), CategoryTotals>::new();
+    let mut by_payment = HashMap::<(String, &'static str), (i64, i64)>::new();
+    for (payment_intent_id, currency, category, amount) in ledger_rows {
+        let category = match category.as_str() {
+            "REFUND" => "REFUND",
+            "DISPUTE" => "DISPUTE",
+            _ => "CHARGE",
+        };
+        totals.entry((currency, category)).or_default().ledger_cents += amount;
+        if let Some(payment_intent_id) = payment_intent_id {
+            by_payment.entry((payment_intent_id, category)).or_default().0 += amount;
+        }
+    }
+
+    let mut unmatched_stripe = Vec::new();
+    for transaction in &stripe_transactions {
+        let category = match transaction.reporting_category.as_str() {
+            "charge" => "CHARGE",
+            "refund" | "refund_failure" => "REFUND",
+            "dispute" | "dispute_reversal" => "DISPUTE",
+            // Fees, payouts, transfers and the like never go through the creator ledger
+            _ => continue,
+        };
+        totals
+            .entry((transaction.currency.to_ascii_uppercase(), category))
+            .or_default()
+            .stripe_cents += transaction.amount;
+
+        let payment_intent_id = transaction
+            .source
+            .as_ref()
+            .and_then(|source| source.get("payment_intent"))
+            .and_then(|payment_intent| payment_intent.as_str())
+            .map(str::to_string);
+        match payment_intent_id {
+            Some(payment_intent_id) => by_payment.entry((payment_intent_id, category)).or_default().1 += transaction.amount,
+            None => unmatched_stripe.push(json!({
+                "balanceTransactionId": transaction.id,
+                "category": category,
+                "currency": transaction.currency.to_ascii_uppercase(),
+                "amountCents": transaction.amount,
+            })),
+        }
+    }
+
+    let summary: Vec<serde_json::Value> = totals
+        .into_iter()
+        .map(|((currency, category), totals)| {
+            json!({
+                "currency": currency,
+                "category": category,
+                "ledgerCents": totals.ledger_cents,
+                "stripeCents": totals.stripe_cents,
+                "differenceCents": totals.ledger_cents - totals.stripe_cents,
+            })
+        })
+        .collect();
+
+    let mut discrepancies: Vec<serde_json::Value> = by_payment
+        .into_iter()
+        .filter(|(_, (ledger, stripe))| ledger != stripe)
+        .map(|((payment_intent_id, category), (ledger, stripe))| {
+            let issue = if ledger == 0 {
+                "MISSING_IN_LEDGER"
+            } else if stripe == 0 {
+                "MISSING_IN_STRIPE"
+            } else {
+                "AMOUNT_MISMATCH"
+            };
+            json!({
+                "paymentIntentId": payment_intent_id,
+                "category": category,
+                "issue": issue,
+                "ledgerCents": ledger,
+                "stripeCents": stripe,
+                "differenceCents": ledger - stripe,
+            })
+        })
+        .collect();
+    discrepancies.sort_by(|a, b| a["paymentIntentId"].as_str().cmp(&b["paymentIntentId"].as_str()));
+
+    Ok(Json(json!({
+        "success": true,
+        "data": {
+            "from": params.from,
+            "to": to,
+            "summary": summary,
+            "discrepancies": discrepancies,
+            "unmatchedStripeTransactions": unmatched_stripe,
+            "stripeTransactionsComplete": complete,
+        }
+    })))
+}
+
+/// Balance transactions created in `[start, end)`, with their source expanded to reach the
+/// payment intent. The flag is false when the page limit cut the list short.
+async fn fetch_balance_transactions(start: i64, end: i64) -> Result<(Vec<BalanceTransaction>, bool), StatusCode> {
+    let secret = stripe_secret()?;
+    let client = reqwest::Client::new();
+    let mut transactions = Vec::new();
+    let mut starting_after: Option<String> = None;
+
+    for _ in 0..MAX_BALANCE_TRANSACTION_PAGES {
+        let mut query = vec![
+            ("limit", "100".to_string()),
+            ("created[gte]", start.to_string()),
+            ("created[lt]", end.to_string()),
+            ("expand[]", "data.source".to_string()),
+        ];
+        if let Some(cursor) = &starting_after {
+            query.push(("starting_after", cursor.clone()));
+        }
+        let page = stripe_json(
+            client
+                .get(stripe_url("/v1/balance_transactions"))
+                .bearer_auth(&secret)
+                .query(&query)
+                .send()
+                .await,
+            "list balance transactions",
+        )
+        .await?;
+
+        let has_more = page.get("has_more").and_then(|value| value.as_bool()).unwrap_or(false);
+        let data: Vec<BalanceTransaction> = page
+            .get("data")
+            .cloned()
+            .map(serde_json::from_value)
+            .transpose()
+            .map_err(|e| {
+                tracing::error!("Failed to parse Stripe balance transactions: {}", e);
+                StatusCode::BAD_GATEWAY
+            })?
+            .unwrap_or_default();
+        starting_after = data.last().map(|transaction| transaction.id.clone());
+        transactions.extend(data);
+
+        if !has_more || starting_after.is_none() {
+            return Ok((transactions, true));
+        }
+    }
+
+    Ok((transactions, false))
+}
@@ -0,0 +1,514 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::Json,
+    routing::{get, post},
+    Router,
+};
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sha2::Sha256;
+use uuid::Uuid;
+
+use crate::{auth::Claims, config::Config, database::Database};
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Random bytes in a key, followed by [`SIGNATURE_BYTES`] of its signature.
+const KEY_BYTES: usize = 10;
+const SIGNATURE_BYTES: usize = 5;
+const GROUP_LEN: usize = 5;
+const MAX_INSTANCE_ID_LEN: usize = 255;
+
+/// A license key issued with a purchase.
+#[derive(Debug, Serialize, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+struct LicenseKey {
+    id: Uuid,
+    license_key: String,
+    purchase_id: Uuid,
+    product_id: Uuid,
+    variant_id: Option<Uuid>,
+    user_id: String,
+    activation_limit: i32,
+    revoked_at: Option<DateTime<Utc>>,
+    created_at: DateTime<Utc>,
+}
+
+/// A key as third-party apps see it, with what decides whether it is valid.
+#[derive(Debug, sqlx::FromRow)]
+struct KeyRecord {
+    id: Uuid,
+    product_id: Uuid,
+    product_name: String,
+    variant_id: Option<Uuid>,
+    variant_name: Option<String>,
+    activation_limit: i32,
+    revoked_at: Option<DateTime<Utc>>,
+    purchase_status: String,
+}
+
+impl KeyRecord {
+    /// `ACTIVE`, `REVOKED` by the creator, `REFUNDED`, or `INACTIVE` while the purchase is in
+    /// any other state.
+    fn status(&self) -> &'static str {
+        if self.revoked_at.is_some() {
+            return "REVOKED";
+        }
+        match self.purchase_status.to_ascii_uppercase().as_str() {
+            "COMPLETED" => "ACTIVE",
+            "REFUNDED" => "REFUNDED",
+            _ => "INACTIVE",
+        }
+    }
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+struct Activation {
+    id: Uuid,
+    instance_id: String,
+    label: Option<String>,
+    activated_at: DateTime<Utc>,
+    last_validated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+struct OwnedLicense {
+    id: Uuid,
+    license_key: String,
+    purchase_id: Uuid,
+    product_id: Uuid,
+    product_name: String,
+    variant_id: Option<Uuid>,
+    variant_name: Option<String>,
+    activation_limit: i32,
+    activations: i64,
+    revoked_at: Option<DateTime<Utc>>,
+    created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ValidateLicenseRequest {
+    license_key: String,
+    /// Rejects keys of other products when given.
+    product_id: Option<Uuid>,
+    /// Reports whether this machine is activated, and refreshes when it last checked in.
+    instance_id: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ActivateLicenseRequest {
+    license_key: String,
+    product_id: Option<Uuid>,
+    /// Identifies the machine or installation, chosen by the calling app.
+    instance_id: String,
+    label: Option<String>,
+}
+
+pub fn license_routes() -> Router<Database> {
+    Router::new()
+        .route("/me", get(list_my_licenses))
+        .route("/validate", post(validate_license))
+        .route("/activate", post(activate_license))
+        .route("/deactivate", post(deactivate_license))
+        .route("/:id/revoke", post(revoke_license))
+}
+
+fn key_mac(random: &[u8]) -> HmacSha256 {
+    let secret = Config::from_env().map(|config| config.jwt_secret).unwrap_or_default();
+    let mut mac =
+        HmacSha256::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(b"license-key:");
+    mac.update(random);
+    mac
+}
+
+fn grouped(hex: &str) -> String {
+    hex.as_bytes()
+        .chunks(GROUP_LEN)
+        .map(|group| String::from_utf8_lossy(group).into_owned())
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+/// A new key such as `3F9A1-C07B2-...`: random bytes and the start of their signature, in hex
+/// groups of five.
+fn generate_key() -> String {
+    let seed = Uuid::new_v4();
+    let random = &seed.as_bytes()[..KEY_BYTES];
+    let signature = key_mac(random).finalize().into_bytes();
+    grouped(&hex::encode_upper([random, &signature[..SIGNATURE_BYTES]].concat()))
+}
+
+/// The stored form of a key as typed by a buyer, or None when its signature does not match, so
+/// made-up keys are turned away without a lookup.
+fn normalize_key(key: &str) -> Option<String> {
+    let compact = key
+        .chars()
+        .filter(|c| !c.is_whitespace() && *c != '-')
+        .collect::<String>()
+        .to_ascii_uppercase();
+    let bytes = hex::decode(&compact).ok()?;
+    if bytes.len() != KEY_BYTES + SIGNATURE_BYTES {
+        return None;
+    }
+    let (random, provided) = bytes.split_at(KEY_BYTES);
+    key_mac(random).verify_truncated_left(provided).ok()?;
+    Some(grouped(&compact))
+}
+
+/// Issue the key of a completed purchase of a product sold with license keys. A purchase keeps
+/// the key it was first given; purchases of other products get none.
+pub(crate) async fn issue_license_key(db: &Database, purchase_id: Uuid) -> Result<(), StatusCode> {
+    sqlx::query(
+        r#"
+        INSERT INTO license_keys (license_key, purchase_id, product_id, variant_id, user_id, activation_limit)
+        SELECT $2, p.id, p.product_id, p.variant_id, p.user_id,
+               COALESCE(v.activation_limit, pr.license_activation_limit)
+        FROM purchases p
+        JOIN products pr ON pr.id = p.product_id
+        LEFT JOIN product_variants v ON v.id = p.variant_id
+        WHERE p.id = $1 AND p.status = 'COMPLETED' AND pr.license_keys
+        ON CONFLICT (purchase_id) DO NOTHING
+        "#,
+    )
+    .bind(purchase_id)
+    .bind(generate_key())
+    .execute(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to issue license key for purchase {}: {}", purchase_id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    Ok(())
+}
+
+/// Issue keys for every purchase completed by a checkout session or payment intent. Failures
+/// are only logged: the buyer's license list issues anything missed here.
+pub(crate) async fn issue_license_keys(db: &Database, stripe_reference: &str) {
+    let purchase_ids = match sqlx::query_scalar::<_, Uuid>(
+        r#"
+        SELECT p.id FROM purchases p
+        JOIN products pr ON pr.id = p.product_id
+        WHERE p.status = 'COMPLETED' AND pr.license_keys
+          AND (p.stripe_checkout_session_id = $1 OR p.stripe_payment_intent_id = $1)
+        "#,
+    )
+    .bind(stripe_reference)
+    .fetch_all(&db.pool)
+    .await
+    {
+        Ok(ids) => ids,
+        Err(e) => {
+            tracing::warn!("Failed to load purchases paid by {}: {}", stripe_reference, e);
+            return;
+        }
+    };
+
+    for purchase_id in purchase_ids {
+        if issue_license_key(db, purchase_id).await.is_err() {
+            tracing::warn!("Failed to issue license key for purchase {}", purchase_id);
+        }
+    }
+}
+
+async fn find_key(db: &Database, license_key: &str) -> Result<Option<KeyRecord>, StatusCode> {
+    let Some(license_key) = normalize_key(license_key) else {
+        return Ok(None);
+    };
+    sqlx::query_as::<_, KeyRecord>(
+        r#"
+        SELECT
+            lk.id,
+            lk.product_id,
+            pr.name AS product_name,
+            lk.variant_id,
+            v.name AS variant_name,
+            lk.activation_limit,
+            lk.revoked_at,
+            p.status AS purchase_status
+        FROM license_keys lk
+        JOIN purchases p ON p.id = lk.purchase_id
+        JOIN products pr ON pr.id = lk.product_id
+        LEFT JOIN product_variants v ON v.id = lk.variant_id
+        WHERE lk.license_key = $1
+        "#,
+    )
+    .bind(license_key)
+    .fetch_optional(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to look up license key: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })
+}
+
+async fn count_activations(db: &Database, license_key_id: Uuid) -> Result<i64, StatusCode> {
+    sqlx::query_scalar("SELECT COUNT(*) FROM license_activations WHERE license_key_id = $1")
+        .bind(license_key_id)
+        .fetch_one(&db.pool)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
+}
+
+fn instance_id(value: &str) -> Result<&str, StatusCode> {
+    let value = value.trim();
+    if value.is_empty() || value.len() > MAX_INSTANCE_ID_LEN {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    Ok(value)
+}
+
+// Keys of purchases that completed before the key was issued are filled in here
+async fn list_my_licenses(
+    State(db): State<Database>,
+    claims: Claims,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let missing = sqlx::query_scalar::<_, Uuid>(
+        r#"
+        SELECT p.id FROM purchases p
+        JOIN products pr ON pr.id = p.product_id
+        WHERE p.user_id = $1 AND p.status = 'COMPLETED' AND pr.license_keys
+          AND NOT EXISTS (SELECT 1 FROM license_keys lk WHERE lk.purchase_id = p.id)
+        "#,
+    )
+    .bind(&claims.sub)
+    .fetch_all(&db.pool)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    for purchase_id in missing {
+        issue_license_key(&db, purchase_id).await?;
+    }
+
+    let licenses = sqlx::query_as::<_, OwnedLicense>(
+        r#"
+        SELECT
+            lk.id,
+            lk.license_key,
+            lk.purchase_id,
+            lk.product_id,
+            pr.name AS product_name,
+            lk.variant_id,
+            v.name AS variant_name,
+            lk.activation_limit,
+            (SELECT COUNT(*) FROM license_activations a WHERE a.license_key_id = lk.id) AS activations,
+            lk.revoked_at,
+            lk.created_at
+        FROM license_keys lk
+        JOIN products pr ON pr.id = lk.product_id
+        LEFT JOIN product_variants v ON v.id = lk.variant_id
+        WHERE lk.user_id = $1
+        ORDER BY lk.created_at DESC
+        "#,
+    )
+    .bind(&claims.sub)
+    .fetch_all(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to list licenses for {}: {}", claims.sub, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    Ok(Json(json!({ "success": true, "data": licenses })))
+}
+
+/// Called by third-party apps. Unknown keys are reported as invalid rather than as an error so
+/// apps can handle every answer the same way.
+async fn validate_license(
+    State(db): State<Database>,
+    Json(payload): Json<ValidateLicenseRequest>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let record = find_key(&db, &payload.license_key)
+        .await?
+        .filter(|record| payload.product_id.is_none_or(|id| id == record.product_id));
+    let Some(record) = record else {
+        return Ok(Json(json!({
+            "success": true,
+            "data": { "valid": false, "status": "INVALID" }
+        })));
+    };
+
+    let instance_activated = match payload.instance_id.as_deref() {
+        Some(instance) => Some(
+            sqlx::query(
+                r#"
+                UPDATE license_activations SET last_validated_at = NOW()
+                WHERE license_key_id = $1 AND instance_id = $2
+                "#,
+            )
+            .bind(record.id)
+            .bind(instance_id(instance)?)
+            .execute(&db.pool)
+            .await
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+            .rows_affected()
+                > 0,
+        ),
+        None => None,
+    };
+    let activations = count_activations(&db, record.id).await?;
+    let status = record.status();
+
+    Ok(Json(json!({
+        "success": true,
+        "data": {
+            "valid": status == "ACTIVE" && instance_activated.unwrap_or(true),
+            "status": status,
+            "productId": record.product_id,
+            "productName": record.product_name,
+            "variantId": record.variant_id,
+            "variantName": record.variant_name,
+            "activationLimit": record.activation_limit,
+            "activations": activations,
+            "instanceActivated": instance_activated
+        }
+    })))
+}
+
+/// Activate a key on one more machine. Activating a machine again only refreshes it; the key
+/// row is locked while counting so parallel activations cannot pass the limit.
+async fn activate_license(
+    State(db): State<Database>,
+    Json(payload): Json<ActivateLicenseRequest>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let instance = instance_id(&payload.instance_id)?;
+    let record = find_key(&db, &payload.license_key)
+        .await?
+        .filter(|record| payload.product_id.is_none_or(|id| id == record.product_id))
+        .ok_or(StatusCode::NOT_FOUND)?;
+    if record.status() != "ACTIVE" {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    let mut tx = db.pool.begin().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    sqlx::query("SELECT id FROM license_keys WHERE id = $1 FOR UPDATE")
+        .bind(record.id)
+        .execute(&mut tx)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let existing = sqlx::query_as::<_, Activation>(
+        r#"
+        UPDATE license_activations
+        SET last_validated_at = NOW(), label = COALESCE($3, label)
+        WHERE license_key_id = $1 AND instance_id = $2
+        RETURNING id, instance_id, label, activated_at, last_validated_at
+        "#,
+    )
+    .bind(record.id)
+    .bind(instance)
+    .bind(&payload.label)
+    .fetch_optional(&mut tx)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let activation = match existing {
+        Some(activation) => activation,
+        None => {
+            let used = sqlx::query_scalar::<_, i64>(
+                "SELECT COUNT(*) FROM license_activations WHERE license_key_id = $1",
+            )
+            .bind(record.id)
+            .fetch_one(&mut tx)
+            .await
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+            if used >= i64::from(record.activation_limit) {
+                return Err(StatusCode::CONFLICT);
+            }
+            sqlx::query_as::<_, Activation>(
+                r#"
+                INSERT INTO license_activations (license_key_id, instance_id, label)
+                VALUES ($1, $2, $3)
+                RETURNING id, instance_id, label, activated_at, last_validated_at
+                "#,
+            )
+            .bind(record.id)
+            .bind(instance)
+            .bind(&payload.label)
+            .fetch_one(&mut tx)
+            .await
+            .map_err(|e| {
+                tracing::error!("Failed to activate license {}: {}", record.id, e);
+                StatusCode::INTERNAL_SERVER_ERROR
+            })?
+        }
+    };
+    tx.commit().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let activations = count_activations(&db, record.id).await?;
+    Ok(Json(json!({
+        "success": true,
+        "data": {
+            "activation": activation,
+            "activationLimit": record.activation_limit,
+            "activations": activations
+        }
+    })))
+}
+
+/// Free a machine's seat, e.g. when the app is uninstalled. Works on revoked keys too.
+async fn deactivate_license(
+    State(db): State<Database>,
+    Json(payload): Json<ActivateLicenseRequest>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let instance = instance_id(&payload.instance_id)?;
+    let record = find_key(&db, &payload.license_key)
+        .await?
+        .filter(|record| payload.product_id.is_none_or(|id| id == record.product_id))
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    let removed = sqlx::query(
+        "DELETE FROM license_activations WHERE license_key_id = $1 AND instance_id = $2",
+    )
+    .bind(record.id)
+    .bind(instance)
+    .execute(&db.pool)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    .rows_affected();
+    if removed == 0 {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    let activations = count_activations(&db, record.id).await?;
+    Ok(Json(json!({
+        "success": true,
+        "data": {
+            "activationLimit": record.activation_limit,
+            "activations": activations
+        }
+    })))
+}
+
+// The product's creator can revoke a key, e.g. one that was shared publicly
+async fn revoke_license(
+    State(db): State<Database>,
+    Path(id): Path<Uuid>,
+    claims: Claims,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let license = sqlx::query_as::<_, LicenseKey>(
+        r#"
+        UPDATE license_keys lk SET revoked_at = COALESCE(lk.revoked_at, NOW())
+        FROM products pr
+        WHERE lk.id = $1 AND pr.id = lk.product_id AND pr.user_id = $2
+        RETURNING lk.*
+        "#,
+    )
+    .bind(id)
+    .bind(&claims.sub)
+    .fetch_optional(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to revoke license {}: {}", id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?
+    .ok_or(StatusCode::NOT_FOUND)?;
+
+    Ok(Json(json!({ "success": true, "data": license })))
+}
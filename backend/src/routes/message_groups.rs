@@ -0,0 +1,383 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::Json,
+    routing::{delete, get, post},
+    Router,
+};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use uuid::Uuid;
+
+use crate::{auth::Claims, database::Database, messaging, routes::polls::ensure_creator};
+
+const MAX_TITLE_CHARS: usize = 100;
+/// Longest a member can be muted for at a time; leave the duration out to mute until unmuted.
+const MAX_MUTE_MINUTES: i64 = 30 * 24 * 60;
+
+/// A group conversation as its creator manages it.
+#[derive(Debug, Serialize, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+struct Group {
+    id: Uuid,
+    title: Option<String>,
+    /// `None` when every member is in it.
+    tier_id: Option<String>,
+    member_count: i64,
+    created_at: DateTime<Utc>,
+    last_message_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+struct GroupMember {
+    user_id: String,
+    name: Option<String>,
+    username: Option<String>,
+    avatar: Option<String>,
+    joined_at: DateTime<Utc>,
+    muted: bool,
+    /// `None` while muted means muted until unmuted.
+    muted_until: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CreateGroupRequest {
+    title: String,
+    /// Only members of this tier; every member when left out.
+    tier_id: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct MuteRequest {
+    /// Minutes to mute for; indefinitely when left out.
+    duration_minutes: Option<i64>,
+}
+
+pub fn group_routes() -> Router<Database> {
+    Router::new()
+        .route("/", get(list_groups).post(create_group))
+        .route("/:id", delete(delete_group))
+        .route("/:id/members", get(list_members))
+        .route("/:id/members/:user_id", delete(remove_member))
+        .route("/:id/members/:user_id/mute", post(mute_member).delete(unmute_member))
+        .route("/:id/bans/:user_id", delete(unban_member))
+}
+
+async fn list_groups(
+    State(db): State<Database>,
+    claims: Claims,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let groups = sqlx::query_as::<_, Group>(
+        r#"
+        SELECT c.id, c.title, c.tier_id, c.created_at, c.last_message_at,
+               (SELECT COUNT(*) FROM conversation_participants p
+                WHERE p.conversation_id = c.id) AS member_count
+        FROM conversations c
+        WHERE c.creator_id = $1
+        ORDER BY c.created_at
+        "#,
+    )
+    .bind(&claims.sub)
+    .fetch_all(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to list groups of {}: {}", claims.sub, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    Ok(Json(json!({ "success": true, "data": groups })))
+}
+
+/// Open a group for every member, or for one tier's members, and fill it with them straight
+/// away. Each tier gets at most one group.
+async fn create_group(
+    State(db): State<Database>,
+    claims: Claims,
+    Json(payload): Json<CreateGroupRequest>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    ensure_creator(&db, &claims.sub).await?;
+
+    let title = payload.title.trim();
+    if title.is_empty() || title.chars().count() > MAX_TITLE_CHARS {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let tier_id = payload
+        .tier_id
+        .as_deref()
+        .map(str::trim)
+        .filter(|tier_id| !tier_id.is_empty());
+
+    let mut tx = db.pool.begin().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let id = sqlx::query_scalar::<_, Uuid>(
+        r#"
+        INSERT INTO conversations (creator_id, title, tier_id)
+        VALUES ($1, $2, $3)
+        ON CONFLICT DO NOTHING
+        RETURNING id
+        "#,
+    )
+    .bind(&claims.sub)
+    .bind(title)
+    .bind(tier_id)
+    .fetch_optional(&mut tx)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to create group for {}: {}", claims.sub, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?
+    .ok_or(StatusCode::CONFLICT)?;
+    sqlx::query("INSERT INTO conversation_participants (conversation_id, user_id) VALUES ($1, $2)")
+        .bind(id)
+        .bind(&claims.sub)
+        .execute(&mut tx)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    tx.commit().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    messaging::sync_group_members(&db, Some(&claims.sub))
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to fill group {}: {:?}", id, e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    let group = load_group(&db, id, &claims.sub).await?;
+    Ok(Json(json!({ "success": true, "data": group })))
+}
+
+/// Delete the group along with its messages.
+async fn delete_group(
+    State(db): State<Database>,
+    Path(id): Path<Uuid>,
+    claims: Claims,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let deleted = sqlx::query("DELETE FROM conversations WHERE id = $1 AND creator_id = $2")
+        .bind(id)
+        .bind(&claims.sub)
+        .execute(&db.pool)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to delete group {}: {}", id, e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+    if deleted.rows_affected() == 0 {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    Ok(Json(json!({ "success": true })))
+}
+
+async fn list_members(
+    State(db): State<Database>,
+    Path(id): Path<Uuid>,
+    claims: Claims,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    ensure_group_owner(&db, id, &claims.sub).await?;
+    let members = sqlx::query_as::<_, GroupMember>(
+        r#"
+        SELECT p.user_id, COALESCE(u.display_name, u.name) AS name, u.username,
+               u.avatar_url AS avatar, p.joined_at,
+               p.muted_at IS NOT NULL AND COALESCE(p.muted_until > NOW(), TRUE) AS muted,
+               p.muted_until
+        FROM conversation_participants p
+        JOIN users u ON u.id = p.user_id
+        WHERE p.conversation_id = $1
+        ORDER BY p.joined_at
+        "#,
+    )
+    .bind(id)
+    .fetch_all(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to list members of group {}: {}", id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    let banned = sqlx::query_scalar::<_, String>(
+        "SELECT user_id FROM conversation_bans WHERE conversation_id = $1 ORDER BY banned_at",
+    )
+    .bind(id)
+    .fetch_all(&db.pool)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(json!({
+        "success": true,
+        "data": { "members": members, "bannedUserIds": banned }
+    })))
+}
+
+/// Take a member out of the group for good; they stay out while their membership lasts.
+async fn remove_member(
+    State(db): State<Database>,
+    Path((id, user_id)): Path<(Uuid, String)>,
+    claims: Claims,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    ensure_group_owner(&db, id, &claims.sub).await?;
+    if user_id == claims.sub {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let mut tx = db.pool.begin().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let removed = sqlx::query(
+        "DELETE FROM conversation_participants WHERE conversation_id = $1 AND user_id = $2",
+    )
+    .bind(id)
+    .bind(&user_id)
+    .execute(&mut tx)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to remove {} from group {}: {}", user_id, id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    if removed.rows_affected() == 0 {
+        return Err(StatusCode::NOT_FOUND);
+    }
+    sqlx::query(
+        r#"
+        INSERT INTO conversation_bans (conversation_id, user_id)
+        VALUES ($1, $2)
+        ON CONFLICT DO NOTHING
+        "#,
+    )
+    .bind(id)
+    .bind(&user_id)
+    .execute(&mut tx)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    tx.commit().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(json!({ "success": true })))
+}
+
+/// Let a removed member back in; they rejoin if their membership still covers the group.
+async fn unban_member(
+    State(db): State<Database>,
+    Path((id, user_id)): Path<(Uuid, String)>,
+    claims: Claims,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    ensure_group_owner(&db, id, &claims.sub).await?;
+    let unbanned =
+        sqlx::query("DELETE FROM conversation_bans WHERE conversation_id = $1 AND user_id = $2")
+            .bind(id)
+            .bind(&user_id)
+            .execute(&db.pool)
+            .await
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    if unbanned.rows_affected() == 0 {
+        return Err(StatusCode::NOT_FOUND);
+    }
+    messaging::sync_group_members(&db, Some(&claims.sub))
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to sync group {}: {:?}", id, e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    Ok(Json(json!({ "success": true })))
+}
+
+/// Stop a member from writing in the group, for a while or until unmuted.
+async fn mute_member(
+    State(db): State<Database>,
+    Path((id, user_id)): Path<(Uuid, String)>,
+    claims: Claims,
+    Json(payload): Json<MuteRequest>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    ensure_group_owner(&db, id, &claims.sub).await?;
+    if user_id == claims.sub {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let muted_until = match payload.duration_minutes {
+        Some(minutes) if !(1..=MAX_MUTE_MINUTES).contains(&minutes) => {
+            return Err(StatusCode::BAD_REQUEST)
+        }
+        Some(minutes) => Some(Utc::now() + Duration::minutes(minutes)),
+        None => None,
+    };
+
+    let updated = sqlx::query(
+        r#"
+        UPDATE conversation_participants SET muted_at = NOW(), muted_until = $3
+        WHERE conversation_id = $1 AND user_id = $2
+        "#,
+    )
+    .bind(id)
+    .bind(&user_id)
+    .bind(muted_until)
+    .execute(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to mute {} in group {}: {}", user_id, id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    if updated.rows_affected() == 0 {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    Ok(Json(json!({
+        "success": true,
+        "data": { "userId": user_id, "mutedUntil": muted_until }
+    })))
+}
+
+async fn unmute_member(
+    State(db): State<Database>,
+    Path((id, user_id)): Path<(Uuid, String)>,
+    claims: Claims,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    ensure_group_owner(&db, id, &claims.sub).await?;
+    let updated = sqlx::query(
+        r#"
+        UPDATE conversation_participants SET muted_at = NULL, muted_until = NULL
+        WHERE conversation_id = $1 AND user_id = $2
+        "#,
+    )
+    .bind(id)
+    .bind(&user_id)
+    .execute(&db.pool)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    if updated.rows_affected() == 0 {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    Ok(Json(json!({ "success": true })))
+}
+
+/// Groups are only managed by the creator who runs them; anyone else gets a 404.
+async fn ensure_group_owner(db: &Database, id: Uuid, user_id: &str) -> Result<(), StatusCode> {
+    let owned = sqlx::query_scalar::<_, bool>(
+        "SELECT EXISTS(SELECT 1 FROM conversations WHERE id = $1 AND creator_id = $2)",
+    )
+    .bind(id)
+    .bind(user_id)
+    .fetch_one(&db.pool)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    if !owned {
+        return Err(StatusCode::NOT_FOUND);
+    }
+    Ok(())
+}
+
+async fn load_group(db: &Database, id: Uuid, creator_id: &str) -> Result<Group, StatusCode> {
+    sqlx::query_as::<_, Group>(
+        r#"
+        SELECT c.id, c.title, c.tier_id, c.created_at, c.last_message_at,
+               (SELECT COUNT(*) FROM conversation_participants p
+                WHERE p.conversation_id = c.id) AS member_count
+        FROM conversations c
+        WHERE c.id = $1 AND c.creator_id = $2
+        "#,
+    )
+    .bind(id)
+    .bind(creator_id)
+    .fetch_optional(&db.pool)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    .ok_or(StatusCode::NOT_FOUND)
+}
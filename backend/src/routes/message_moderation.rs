@@ -0,0 +1,481 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::Json,
+    routing::{delete, get, post},
+    Router,
+};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use uuid::Uuid;
+
+use crate::{
+    auth::Claims,
+    database::Database,
+    messaging::{normalize_words, DirectMessage, FilterAction},
+    routes::fees::ensure_admin,
+};
+
+const MAX_WORD_CHARS: usize = 100;
+/// Messages shown with a reported conversation, newest first.
+const REVIEW_MESSAGES: i64 = 100;
+
+/// A conversation in the moderation queue, with its reports rolled up.
+#[derive(Debug, Serialize, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+struct ReportedConversation {
+    conversation_id: Uuid,
+    report_count: i64,
+    reasons: Vec<String>,
+    reported_user_ids: Vec<String>,
+    first_reported_at: DateTime<Utc>,
+    last_reported_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+struct MessageReport {
+    id: Uuid,
+    conversation_id: Uuid,
+    message_id: Option<Uuid>,
+    reported_user_id: Option<String>,
+    /// `None` for messages flagged by the blocked-words filter.
+    reporter_id: Option<String>,
+    /// `SPAM`, `HARASSMENT`, `SCAM`, `INAPPROPRIATE`, `OTHER` or `AUTO_FILTER`.
+    reason: String,
+    details: Option<String>,
+    /// `OPEN`, `RESOLVED` or `DISMISSED`.
+    status: String,
+    resolution_note: Option<String>,
+    resolved_by: Option<String>,
+    resolved_at: Option<DateTime<Utc>>,
+    created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+struct ReviewParticipant {
+    user_id: String,
+    name: Option<String>,
+    username: Option<String>,
+    messaging_suspended_at: Option<DateTime<Utc>>,
+    messaging_suspended_until: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+struct BlockedWord {
+    word: String,
+    action: String,
+    created_by: Option<String>,
+    created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+struct Suspension {
+    user_id: String,
+    name: Option<String>,
+    username: Option<String>,
+    messaging_suspended_at: DateTime<Utc>,
+    /// `None` when suspended until lifted.
+    messaging_suspended_until: Option<DateTime<Utc>>,
+    messaging_suspension_reason: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ReportQueueQuery {
+    status: Option<String>,
+    page: Option<u32>,
+    limit: Option<u32>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum ResolutionStatus {
+    /// Acted on, e.g. by suspending the sender.
+    Resolved,
+    /// Nothing wrong found.
+    Dismissed,
+}
+
+#[derive(Debug, Deserialize)]
+struct ResolveRequest {
+    status: ResolutionStatus,
+    note: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct BlockedWordRequest {
+    word: String,
+    action: FilterAction,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SuspendRequest {
+    /// Hours to suspend for; until lifted when left out.
+    duration_hours: Option<i64>,
+    reason: Option<String>,
+}
+
+pub fn message_moderation_routes() -> Router<Database> {
+    Router::new()
+        .route("/reports", get(list_reported_conversations))
+        .route("/reports/:conversation_id", get(get_reported_conversation))
+        .route("/reports/:conversation_id/resolve", post(resolve_reports))
+        .route("/blocked-words", get(list_blocked_words).post(add_blocked_word))
+        .route("/blocked-words/:word", delete(remove_blocked_word))
+        .route("/suspensions", get(list_suspensions))
+        .route("/suspensions/:user_id", post(suspend_sender).delete(lift_suspension))
+}
+
+// Conversations waiting longest come first
+async fn list_reported_conversations(
+    State(db): State<Database>,
+    Query(params): Query<ReportQueueQuery>,
+    claims: Claims,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    ensure_admin(&db, &claims.sub).await?;
+    let page = params.page.unwrap_or(1).max(1);
+    let limit = params.limit.unwrap_or(20).clamp(1, 100);
+    let offset = ((page - 1) * limit) as i64;
+    let status = params
+        .status
+        .map(|status| status.trim().to_ascii_uppercase())
+        .unwrap_or_else(|| "OPEN".to_string());
+
+    let conversations = sqlx::query_as::<_, ReportedConversation>(
+        r#"
+        SELECT conversation_id, COUNT(*) AS report_count,
+               ARRAY_AGG(DISTINCT reason) AS reasons,
+               COALESCE(ARRAY_AGG(DISTINCT reported_user_id::TEXT)
+                   FILTER (WHERE reported_user_id IS NOT NULL), '{}') AS reported_user_ids,
+               MIN(created_at) AS first_reported_at, MAX(created_at) AS last_reported_at
+        FROM message_reports
+        WHERE status = $1
+        GROUP BY conversation_id
+        ORDER BY MIN(created_at)
+        LIMIT $2 OFFSET $3
+        "#,
+    )
+    .bind(&status)
+    .bind(limit as i64)
+    .bind(offset)
+    .fetch_all(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to list reported conversations: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    let total = sqlx::query_scalar::<_, i64>(
+        "SELECT COUNT(DISTINCT conversation_id) FROM message_reports WHERE status = $1",
+    )
+    .bind(&status)
+    .fetch_one(&db.pool)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(json!({
+        "success": true,
+        "data": conversations,
+        "pagination": {
+            "page": page,
+            "limit": limit,
+            "total": total,
+            "pages": ((total as f64) / (limit as f64)).ceil() as u32,
+        }
+    })))
+}
+
+/// A reported conversation for review: every report on it, its participants and latest messages.
+async fn get_reported_conversation(
+    State(db): State<Database>,
+    Path(conversation_id): Path<Uuid>,
+    claims: Claims,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    ensure_admin(&db, &claims.sub).await?;
+
+    let reports = sqlx::query_as::<_, MessageReport>(
+        "SELECT * FROM message_reports WHERE conversation_id = $1 ORDER BY created_at DESC",
+    )
+    .bind(conversation_id)
+    .fetch_all(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to load reports of {}: {}", conversation_id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    if reports.is_empty() {
+        return Err(StatusCode::NOT_FOUND);
+    }
+    let participants = sqlx::query_as::<_, ReviewParticipant>(
+        r#"
+        SELECT p.user_id, COALESCE(u.display_name, u.name) AS name, u.username,
+               u.messaging_suspended_at, u.messaging_suspended_until
+        FROM conversation_participants p
+        JOIN users u ON u.id = p.user_id
+        WHERE p.conversation_id = $1
+        ORDER BY p.joined_at
+        "#,
+    )
+    .bind(conversation_id)
+    .fetch_all(&db.pool)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let messages = sqlx::query_as::<_, DirectMessage>(
+        r#"
+        SELECT id, conversation_id, sender_id, body, created_at
+        FROM messages
+        WHERE conversation_id = $1
+        ORDER BY created_at DESC, id DESC
+        LIMIT $2
+        "#,
+    )
+    .bind(conversation_id)
+    .bind(REVIEW_MESSAGES)
+    .fetch_all(&db.pool)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(json!({
+        "success": true,
+        "data": {
+            "conversationId": conversation_id,
+            "reports": reports,
+            "participants": participants,
+            "messages": messages,
+        }
+    })))
+}
+
+/// Close every open report on a conversation.
+async fn resolve_reports(
+    State(db): State<Database>,
+    Path(conversation_id): Path<Uuid>,
+    claims: Claims,
+    Json(payload): Json<ResolveRequest>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    ensure_admin(&db, &claims.sub).await?;
+    let status = match payload.status {
+        ResolutionStatus::Resolved => "RESOLVED",
+        ResolutionStatus::Dismissed => "DISMISSED",
+    };
+    let note = payload
+        .note
+        .as_deref()
+        .map(str::trim)
+        .filter(|note| !note.is_empty());
+
+    let closed = sqlx::query(
+        r#"
+        UPDATE message_reports
+        SET status = $2, resolution_note = $3, resolved_by = $4, resolved_at = NOW()
+        WHERE conversation_id = $1 AND status = 'OPEN'
+        "#,
+    )
+    .bind(conversation_id)
+    .bind(status)
+    .bind(note)
+    .bind(&claims.sub)
+    .execute(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to resolve reports of {}: {}", conversation_id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?
+    .rows_affected();
+    if closed == 0 {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    Ok(Json(json!({
+        "success": true,
+        "data": { "conversationId": conversation_id, "status": status, "closed": closed }
+    })))
+}
+
+async fn list_blocked_words(
+    State(db): State<Database>,
+    claims: Claims,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    ensure_admin(&db, &claims.sub).await?;
+    let words = sqlx::query_as::<_, BlockedWord>("SELECT * FROM blocked_words ORDER BY word")
+        .fetch_all(&db.pool)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to list blocked words: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+    Ok(Json(json!({ "success": true, "data": words })))
+}
+
+/// Add a word or phrase to the filter, or change what it does. Messages are matched on whole
+/// words, so entries are stored lowercase without punctuation.
+async fn add_blocked_word(
+    State(db): State<Database>,
+    claims: Claims,
+    Json(payload): Json<BlockedWordRequest>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    ensure_admin(&db, &claims.sub).await?;
+    let word = normalize_words(&payload.word).trim().to_string();
+    if word.is_empty() || word.chars().count() > MAX_WORD_CHARS {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let word = sqlx::query_as::<_, BlockedWord>(
+        r#"
+        INSERT INTO blocked_words (word, action, created_by) VALUES ($1, $2, $3)
+        ON CONFLICT (word) DO UPDATE SET action = EXCLUDED.action
+        RETURNING *
+        "#,
+    )
+    .bind(&word)
+    .bind(payload.action.as_str())
+    .bind(&claims.sub)
+    .fetch_one(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to add blocked word: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    Ok(Json(json!({ "success": true, "data": word })))
+}
+
+async fn remove_blocked_word(
+    State(db): State<Database>,
+    Path(word): Path<String>,
+    claims: Claims,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    ensure_admin(&db, &claims.sub).await?;
+    let word = normalize_words(&word).trim().to_string();
+    let removed = sqlx::query("DELETE FROM blocked_words WHERE word = $1")
+        .bind(&word)
+        .execute(&db.pool)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .rows_affected();
+    if removed == 0 {
+        return Err(StatusCode::NOT_FOUND);
+    }
+    Ok(Json(json!({ "success": true, "data": { "word": word } })))
+}
+
+async fn list_suspensions(
+    State(db): State<Database>,
+    claims: Claims,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    ensure_admin(&db, &claims.sub).await?;
+    let suspensions = sqlx::query_as::<_, Suspension>(
+        r#"
+        SELECT id AS user_id, COALESCE(display_name, name) AS name, username,
+               messaging_suspended_at, messaging_suspended_until, messaging_suspension_reason
+        FROM users
+        WHERE messaging_suspended_at IS NOT NULL
+          AND COALESCE(messaging_suspended_until > NOW(), TRUE)
+        ORDER BY messaging_suspended_at DESC
+        "#,
+    )
+    .fetch_all(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to list messaging suspensions: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    Ok(Json(json!({ "success": true, "data": suspensions })))
+}
+
+/// Stop a user from sending messages. Open reports against them are resolved along the way.
+async fn suspend_sender(
+    State(db): State<Database>,
+    Path(user_id): Path<String>,
+    claims: Claims,
+    Json(payload): Json<SuspendRequest>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    ensure_admin(&db, &claims.sub).await?;
+    if user_id == claims.sub || payload.duration_hours.is_some_and(|hours| hours <= 0) {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let until = payload
+        .duration_hours
+        .map(|hours| Utc::now() + Duration::hours(hours));
+    let reason = payload
+        .reason
+        .as_deref()
+        .map(str::trim)
+        .filter(|reason| !reason.is_empty());
+
+    let mut tx = db.pool.begin().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let suspended = sqlx::query(
+        r#"
+        UPDATE users
+        SET messaging_suspended_at = NOW(), messaging_suspended_until = $2,
+            messaging_suspension_reason = $3
+        WHERE id = $1
+        "#,
+    )
+    .bind(&user_id)
+    .bind(until)
+    .bind(reason)
+    .execute(&mut tx)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to suspend {} from messaging: {}", user_id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?
+    .rows_affected();
+    if suspended == 0 {
+        return Err(StatusCode::NOT_FOUND);
+    }
+    let resolved = sqlx::query(
+        r#"
+        UPDATE message_reports
+        SET status = 'RESOLVED', resolution_note = COALESCE($2, 'Sender suspended'),
+            resolved_by = $3, resolved_at = NOW()
+        WHERE reported_user_id = $1 AND status = 'OPEN'
+        "#,
+    )
+    .bind(&user_id)
+    .bind(reason)
+    .bind(&claims.sub)
+    .execute(&mut tx)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    .rows_affected();
+    tx.commit().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(json!({
+        "success": true,
+        "data": {
+            "userId": user_id,
+            "suspendedUntil": until,
+            "resolvedReports": resolved
+        }
+    })))
+}
+
+async fn lift_suspension(
+    State(db): State<Database>,
+    Path(user_id): Path<String>,
+    claims: Claims,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    ensure_admin(&db, &claims.sub).await?;
+    let lifted = sqlx::query(
+        r#"
+        UPDATE users
+        SET messaging_suspended_at = NULL, messaging_suspended_until = NULL,
+            messaging_suspension_reason = NULL
+        WHERE id = $1 AND messaging_suspended_at IS NOT NULL
+        "#,
+    )
+    .bind(&user_id)
+    .execute(&db.pool)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    .rows_affected();
+    if lifted == 0 {
+        return Err(StatusCode::NOT_FOUND);
+    }
+    Ok(Json(json!({ "success": true, "data": { "userId": user_id } })))
+}
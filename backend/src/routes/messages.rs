@@ -0,0 +1,1148 @@
+use axum::{
+    extract::{Multipart, Path, Query, State},
+    http::StatusCode,
+    response::Json,
+    routing::{get, post},
+    Router,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use uuid::Uuid;
+
+use crate::{
+    auth::Claims,
+    database::Database,
+    messaging::{self, DirectMessage, DmPolicy, FilterAction, MessageAttachment, RequestStatus},
+    realtime::publish_counters,
+    routes::{
+        dm_sequences::sequence_routes, message_groups::group_routes,
+        priority_messages::priority_routes,
+    },
+    storage, thumbnails,
+};
+
+pub(crate) const MAX_MESSAGE_CHARS: usize = 5_000;
+const MAX_REPORT_DETAILS_CHARS: usize = 1_000;
+const MAX_ATTACHMENTS_PER_MESSAGE: usize = 10;
+const ATTACHMENT_MAX_BYTES: usize = 25 * 1024 * 1024;
+const ATTACHMENT_URL_TTL_SECONDS: i64 = 15 * 60;
+/// Longest side of image previews, in pixels.
+const THUMBNAIL_MAX_SIDE: u32 = 320;
+/// What may be sent in a message.
+const ATTACHMENT_TYPES: &[&str] = &[
+    "image/jpeg",
+    "image/png",
+    "image/gif",
+    "image/webp",
+    "application/pdf",
+    "application/zip",
+    "text/plain",
+    "audio/mpeg",
+    "video/mp4",
+];
+
+/// Someone in a conversation, with how far they got through its messages.
+#[derive(Debug, Serialize, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+struct Participant {
+    #[serde(skip)]
+    conversation_id: Uuid,
+    user_id: String,
+    name: Option<String>,
+    username: Option<String>,
+    avatar: Option<String>,
+    last_delivered_at: Option<DateTime<Utc>>,
+    last_read_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, sqlx::FromRow)]
+struct ConversationRow {
+    id: Uuid,
+    created_at: DateTime<Utc>,
+    last_message_at: Option<DateTime<Utc>>,
+    unread_count: i64,
+    last_message_id: Option<Uuid>,
+    last_message_sender_id: Option<String>,
+    last_message_body: Option<String>,
+    request_status: Option<String>,
+    /// Set on group conversations only.
+    creator_id: Option<String>,
+    title: Option<String>,
+    tier_id: Option<String>,
+}
+
+/// A message matching a search, with the matching words marked up in `snippet`.
+#[derive(Debug, Serialize, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+struct MessageSearchRow {
+    id: Uuid,
+    conversation_id: Uuid,
+    sender_id: String,
+    body: String,
+    created_at: DateTime<Utc>,
+    snippet: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct StartConversationRequest {
+    recipient_id: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SendMessageRequest {
+    #[serde(default)]
+    body: String,
+    /// Uploads to send with the message; the body may be empty when there are some.
+    #[serde(default)]
+    attachment_ids: Vec<Uuid>,
+}
+
+/// Why a participant reports a message or conversation.
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum ReportReason {
+    Spam,
+    Harassment,
+    Scam,
+    Inappropriate,
+    Other,
+}
+
+impl ReportReason {
+    fn as_str(self) -> &'static str {
+        match self {
+            ReportReason::Spam => "SPAM",
+            ReportReason::Harassment => "HARASSMENT",
+            ReportReason::Scam => "SCAM",
+            ReportReason::Inappropriate => "INAPPROPRIATE",
+            ReportReason::Other => "OTHER",
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ReportRequest {
+    /// The message reported; the whole conversation when left out.
+    message_id: Option<Uuid>,
+    reason: ReportReason,
+    details: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct DmSettingsRequest {
+    policy: DmPolicy,
+}
+
+#[derive(Debug, Deserialize)]
+struct MessageQuery {
+    page: Option<u32>,
+    limit: Option<u32>,
+}
+
+#[derive(Debug, Deserialize)]
+struct MessageCursorQuery {
+    /// Only messages older than this one.
+    before: Option<Uuid>,
+    limit: Option<u32>,
+}
+
+#[derive(Debug, Deserialize)]
+struct MessageSearchQuery {
+    q: String,
+    before: Option<Uuid>,
+    limit: Option<u32>,
+}
+
+pub fn message_routes() -> Router<Database> {
+    Router::new()
+        .route("/conversations", get(list_conversations).post(start_conversation))
+        .route("/requests", get(list_requests))
+        .route("/search", get(search_messages))
+        .route("/settings", get(get_dm_settings).put(update_dm_settings))
+        .route("/conversations/:id", get(get_conversation))
+        .route(
+            "/conversations/:id/messages",
+            get(list_messages).post(send_message),
+        )
+        .route("/conversations/:id/read", post(mark_conversation_read))
+        .route("/conversations/:id/accept", post(accept_request))
+        .route("/conversations/:id/decline", post(decline_request))
+        .route("/conversations/:id/reports", post(report_conversation))
+        .route("/conversations/:id/attachments", post(upload_attachments))
+        .route(
+            "/conversations/:id/attachments/:attachment_id",
+            get(get_attachment),
+        )
+        .nest("/groups", group_routes())
+        .nest("/priority", priority_routes())
+        .nest("/sequences", sequence_routes())
+}
+
+/// The inbox: conversations the user hasn't got as a pending or declined request.
+async fn list_conversations(
+    State(db): State<Database>,
+    Query(params): Query<MessageQuery>,
+    claims: Claims,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    list_inbox(&db, &claims.sub, params, false).await
+}
+
+/// Message requests: conversations started by people the user's DM policy doesn't let straight
+/// through, waiting to be accepted or declined.
+async fn list_requests(
+    State(db): State<Database>,
+    Query(params): Query<MessageQuery>,
+    claims: Claims,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    list_inbox(&db, &claims.sub, params, true).await
+}
+
+async fn list_inbox(
+    db: &Database,
+    user_id: &str,
+    params: MessageQuery,
+    requests: bool,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let page = params.page.unwrap_or(1).max(1);
+    let limit = params.limit.unwrap_or(20).clamp(1, 100);
+    let offset = ((page - 1) * limit) as i64;
+
+    let rows = sqlx::query_as::<_, ConversationRow>(
+        r#"
+        SELECT c.id, c.created_at, c.last_message_at,
+               (SELECT COUNT(*) FROM messages m
+                WHERE m.conversation_id = c.id AND m.sender_id <> $1
+                  AND m.created_at > COALESCE(p.last_read_at, '-infinity')) AS unread_count,
+               lm.id AS last_message_id, lm.sender_id AS last_message_sender_id,
+               lm.body AS last_message_body, p.request_status, c.creator_id, c.title, c.tier_id
+        FROM conversation_participants p
+        JOIN conversations c ON c.id = p.conversation_id
+        LEFT JOIN LATERAL (
+            SELECT id, sender_id, body FROM messages
+            WHERE conversation_id = c.id
+            ORDER BY created_at DESC
+            LIMIT 1
+        ) lm ON TRUE
+        WHERE p.user_id = $1
+          AND (COALESCE(p.request_status, 'ACCEPTED') = 'ACCEPTED') <> $4
+        ORDER BY COALESCE(c.last_message_at, c.created_at) DESC
+        LIMIT $2 OFFSET $3
+        "#,
+    )
+    .bind(user_id)
+    .bind(limit as i64)
+    .bind(offset)
+    .bind(requests)
+    .fetch_all(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to list conversations of {}: {}", user_id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    let total = sqlx::query_scalar::<_, i64>(
+        r#"
+        SELECT COUNT(*) FROM conversation_participants
+        WHERE user_id = $1 AND (COALESCE(request_status, 'ACCEPTED') = 'ACCEPTED') <> $2
+        "#,
+    )
+    .bind(user_id)
+    .bind(requests)
+    .fetch_one(&db.pool)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let ids: Vec<Uuid> = rows.iter().map(|row| row.id).collect();
+    let mut participants = load_participants(db, &ids).await?;
+    let conversations: Vec<serde_json::Value> = rows
+        .into_iter()
+        .map(|row| {
+            let (members, rest) = participants
+                .drain(..)
+                .partition(|participant| participant.conversation_id == row.id);
+            participants = rest;
+            conversation_json(row, members)
+        })
+        .collect();
+
+    Ok(Json(json!({
+        "success": true,
+        "data": conversations,
+        "pagination": {
+            "page": page,
+            "limit": limit,
+            "total": total,
+            "pages": ((total as f64) / (limit as f64)).ceil() as u32,
+        }
+    })))
+}
+
+/// Open the direct conversation with another user, creating it the first time.
+async fn start_conversation(
+    State(db): State<Database>,
+    claims: Claims,
+    Json(payload): Json<StartConversationRequest>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let recipient_id = payload.recipient_id.trim();
+    if recipient_id.is_empty() || recipient_id == claims.sub {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let recipient_exists =
+        sqlx::query_scalar::<_, bool>("SELECT EXISTS(SELECT 1 FROM users WHERE id = $1)")
+            .bind(recipient_id)
+            .fetch_one(&db.pool)
+            .await
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    if !recipient_exists {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    let conversation_id = messaging::open_direct_conversation(&db, &claims.sub, recipient_id)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to start conversation with {}: {}", recipient_id, e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    conversation_response(&db, conversation_id, &claims.sub).await
+}
+
+async fn get_conversation(
+    State(db): State<Database>,
+    Path(id): Path<Uuid>,
+    claims: Claims,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    ensure_participant(&db, id, &claims.sub).await?;
+    conversation_response(&db, id, &claims.sub).await
+}
+
+/// Messages newest first, a page at a time: pass the oldest message id seen as `before` for the
+/// page before it. Fetching the newest page counts as receiving it.
+async fn list_messages(
+    State(db): State<Database>,
+    Path(id): Path<Uuid>,
+    Query(params): Query<MessageCursorQuery>,
+    claims: Claims,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    ensure_participant(&db, id, &claims.sub).await?;
+    let limit = params.limit.unwrap_or(50).clamp(1, 100);
+
+    // One extra row tells whether there are older messages
+    let mut messages = sqlx::query_as::<_, DirectMessage>(
+        r#"
+        SELECT id, conversation_id, sender_id, body, created_at
+        FROM messages
+        WHERE conversation_id = $1
+          AND ($2::UUID IS NULL OR (created_at, id) < (
+              SELECT created_at, id FROM messages WHERE id = $2 AND conversation_id = $1
+          ))
+        ORDER BY created_at DESC, id DESC
+        LIMIT $3
+        "#,
+    )
+    .bind(id)
+    .bind(params.before)
+    .bind(limit as i64 + 1)
+    .fetch_all(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to list messages of {}: {}", id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    let has_more = messages.len() > limit as usize;
+    messages.truncate(limit as usize);
+
+    if let Some(newest) = messages.first().filter(|_| params.before.is_none()) {
+        messaging::mark_delivered(&db, id, &claims.sub, newest.created_at).await;
+    }
+
+    let next_cursor = messages.last().filter(|_| has_more).map(|message| message.id);
+    let messages = with_attachments(&db, messages).await?;
+
+    Ok(Json(json!({
+        "success": true,
+        "data": messages,
+        "pagination": {
+            "limit": limit,
+            "hasMore": has_more,
+            "nextCursor": next_cursor,
+        }
+    })))
+}
+
+/// Full-text search over every conversation the user is in, newest match first and paged the
+/// same way as a conversation's messages.
+async fn search_messages(
+    State(db): State<Database>,
+    Query(params): Query<MessageSearchQuery>,
+    claims: Claims,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let query = params.q.trim();
+    if query.is_empty() {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let limit = params.limit.unwrap_or(20).clamp(1, 50);
+
+    let mut results = sqlx::query_as::<_, MessageSearchRow>(
+        r#"
+        SELECT m.id, m.conversation_id, m.sender_id, m.body, m.created_at,
+               ts_headline('simple', m.body, websearch_to_tsquery('simple', $2),
+                           'MaxFragments=1, MaxWords=20, MinWords=5') AS snippet
+        FROM messages m
+        JOIN conversation_participants p
+          ON p.conversation_id = m.conversation_id AND p.user_id = $1
+        WHERE m.search_vector @@ websearch_to_tsquery('simple', $2)
+          AND ($3::UUID IS NULL OR (m.created_at, m.id) < (
+              SELECT created_at, id FROM messages WHERE id = $3
+          ))
+        ORDER BY m.created_at DESC, m.id DESC
+        LIMIT $4
+        "#,
+    )
+    .bind(&claims.sub)
+    .bind(query)
+    .bind(params.before)
+    .bind(limit as i64 + 1)
+    .fetch_all(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to search messages of {}: {}", claims.sub, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    let has_more = results.len() > limit as usize;
+    results.truncate(limit as usize);
+    let next_cursor = results.last().filter(|_| has_more).map(|result| result.id);
+
+    Ok(Json(json!({
+        "success": true,
+        "data": results,
+        "pagination": {
+            "limit": limit,
+            "hasMore": has_more,
+            "nextCursor": next_cursor,
+        }
+    })))
+}
+
+async fn send_message(
+    State(db): State<Database>,
+    Path(id): Path<Uuid>,
+    claims: Claims,
+    Json(payload): Json<SendMessageRequest>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let body = payload.body.trim();
+    let mut attachment_ids = payload.attachment_ids;
+    attachment_ids.sort_unstable();
+    attachment_ids.dedup();
+    if (body.is_empty() && attachment_ids.is_empty())
+        || body.chars().count() > MAX_MESSAGE_CHARS
+        || attachment_ids.len() > MAX_ATTACHMENTS_PER_MESSAGE
+    {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    ensure_participant(&db, id, &claims.sub).await?;
+    ensure_not_muted(&db, id, &claims.sub).await?;
+    let flagged_word = screen_outgoing(&db, &claims.sub, body).await?;
+    let requested = request_recipients(&db, id, &claims.sub).await?;
+
+    let mut tx = db.pool.begin().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let message = sqlx::query_as::<_, DirectMessage>(
+        r#"
+        INSERT INTO messages (conversation_id, sender_id, body)
+        VALUES ($1, $2, $3)
+        RETURNING id, conversation_id, sender_id, body, created_at
+        "#,
+    )
+    .bind(id)
+    .bind(&claims.sub)
+    .bind(body)
+    .fetch_one(&mut tx)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to send message in {}: {}", id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    // Only the sender's own uploads to this conversation that haven't been sent yet
+    let attachments = sqlx::query_as::<_, MessageAttachment>(
+        r#"
+        UPDATE message_attachments SET message_id = $1
+        WHERE id = ANY($2) AND conversation_id = $3 AND uploader_id = $4 AND message_id IS NULL
+        RETURNING id, message_id, file_name, content_type, size_bytes, width, height,
+                  thumbnail_key IS NOT NULL AS has_thumbnail
+        "#,
+    )
+    .bind(message.id)
+    .bind(&attachment_ids)
+    .bind(id)
+    .bind(&claims.sub)
+    .fetch_all(&mut tx)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    if attachments.len() != attachment_ids.len() {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    sqlx::query("UPDATE conversations SET last_message_at = $2 WHERE id = $1")
+        .bind(id)
+        .bind(message.created_at)
+        .execute(&mut tx)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    sqlx::query(
+        r#"
+        UPDATE conversation_participants SET request_status = $3
+        WHERE conversation_id = $1 AND user_id = ANY($2) AND request_status IS NULL
+        "#,
+    )
+    .bind(id)
+    .bind(&requested)
+    .bind(RequestStatus::Pending.as_str())
+    .execute(&mut tx)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    // Senders have read what they wrote, and replying to a request accepts it
+    sqlx::query(
+        r#"
+        UPDATE conversation_participants
+        SET last_read_at = $3, last_delivered_at = $3,
+            request_status = CASE WHEN request_status IS NULL THEN NULL ELSE $4 END
+        WHERE conversation_id = $1 AND user_id = $2
+        "#,
+    )
+    .bind(id)
+    .bind(&claims.sub)
+    .bind(message.created_at)
+    .bind(RequestStatus::Accepted.as_str())
+    .execute(&mut tx)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    // Any reply answers the paid messages waiting on this sender
+    sqlx::query(
+        r#"
+        UPDATE priority_messages SET status = 'ANSWERED', answered_at = $3, updated_at = NOW()
+        WHERE conversation_id = $1 AND creator_id = $2 AND status = 'AWAITING_REPLY'
+        "#,
+    )
+    .bind(id)
+    .bind(&claims.sub)
+    .bind(message.created_at)
+    .execute(&mut tx)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    tx.commit().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let sender_name = sqlx::query_scalar::<_, Option<String>>(
+        "SELECT COALESCE(display_name, name, username) FROM users WHERE id = $1",
+    )
+    .bind(&claims.sub)
+    .fetch_one(&db.pool)
+    .await
+    .ok()
+    .flatten()
+    .unwrap_or_else(|| "Someone".to_string());
+    messaging::deliver_message(&db, &message, &attachments, &sender_name).await;
+    if let Some(word) = flagged_word {
+        messaging::flag_message(&db, &message, &word).await;
+    }
+
+    let mut data = json!(message);
+    data["attachments"] = json!(attachments);
+    data["isRequest"] = json!(!requested.is_empty());
+    Ok(Json(json!({
+        "success": true,
+        "data": data
+    })))
+}
+
+async fn mark_conversation_read(
+    State(db): State<Database>,
+    Path(id): Path<Uuid>,
+    claims: Claims,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    ensure_participant(&db, id, &claims.sub).await?;
+    let read_up_to = messaging::mark_read(&db, id, &claims.sub)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to mark {} read for {}: {}", id, claims.sub, e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    Ok(Json(json!({
+        "success": true,
+        "data": { "lastReadAt": read_up_to }
+    })))
+}
+
+async fn accept_request(
+    State(db): State<Database>,
+    Path(id): Path<Uuid>,
+    claims: Claims,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    answer_request(&db, id, &claims.sub, RequestStatus::Accepted).await
+}
+
+/// Declined conversations stay out of the inbox and their sender can't message again; accepting
+/// later still works.
+async fn decline_request(
+    State(db): State<Database>,
+    Path(id): Path<Uuid>,
+    claims: Claims,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    answer_request(&db, id, &claims.sub, RequestStatus::Declined).await
+}
+
+async fn answer_request(
+    db: &Database,
+    id: Uuid,
+    user_id: &str,
+    status: RequestStatus,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    ensure_participant(db, id, user_id).await?;
+    let updated = sqlx::query(
+        r#"
+        UPDATE conversation_participants SET request_status = $3
+        WHERE conversation_id = $1 AND user_id = $2 AND request_status IS NOT NULL
+        "#,
+    )
+    .bind(id)
+    .bind(user_id)
+    .bind(status.as_str())
+    .execute(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to answer message request {} for {}: {}", id, user_id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    // Only conversations that came in as requests can be accepted or declined
+    if updated.rows_affected() == 0 {
+        return Err(StatusCode::CONFLICT);
+    }
+    publish_counters(db, user_id).await;
+
+    conversation_response(db, id, user_id).await
+}
+
+/// Report a message, or the whole conversation, to the admins. In a direct conversation the other
+/// participant is the one reported when no message is given.
+async fn report_conversation(
+    State(db): State<Database>,
+    Path(id): Path<Uuid>,
+    claims: Claims,
+    Json(payload): Json<ReportRequest>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    ensure_participant(&db, id, &claims.sub).await?;
+    let details = payload
+        .details
+        .as_deref()
+        .map(str::trim)
+        .filter(|details| !details.is_empty());
+    if details.is_some_and(|details| details.chars().count() > MAX_REPORT_DETAILS_CHARS) {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let reported_user_id = match payload.message_id {
+        Some(message_id) => {
+            let sender_id = sqlx::query_scalar::<_, String>(
+                "SELECT sender_id FROM messages WHERE id = $1 AND conversation_id = $2",
+            )
+            .bind(message_id)
+            .bind(id)
+            .fetch_optional(&db.pool)
+            .await
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+            .ok_or(StatusCode::NOT_FOUND)?;
+            if sender_id == claims.sub {
+                return Err(StatusCode::BAD_REQUEST);
+            }
+            Some(sender_id)
+        }
+        None => sqlx::query_scalar::<_, String>(
+            r#"
+            SELECT p.user_id FROM conversation_participants p
+            JOIN conversations c ON c.id = p.conversation_id
+            WHERE p.conversation_id = $1 AND p.user_id <> $2 AND c.direct_key IS NOT NULL
+            "#,
+        )
+        .bind(id)
+        .bind(&claims.sub)
+        .fetch_optional(&db.pool)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?,
+    };
+
+    let report_id = sqlx::query_scalar::<_, Uuid>(
+        r#"
+        INSERT INTO message_reports
+            (conversation_id, message_id, reported_user_id, reporter_id, reason, details)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        ON CONFLICT DO NOTHING
+        RETURNING id
+        "#,
+    )
+    .bind(id)
+    .bind(payload.message_id)
+    .bind(&reported_user_id)
+    .bind(&claims.sub)
+    .bind(payload.reason.as_str())
+    .bind(details)
+    .fetch_optional(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to report conversation {}: {}", id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?
+    // Already reported this message
+    .ok_or(StatusCode::CONFLICT)?;
+
+    Ok(Json(json!({
+        "success": true,
+        "data": { "reportId": report_id, "status": "OPEN" }
+    })))
+}
+
+async fn get_dm_settings(
+    State(db): State<Database>,
+    claims: Claims,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let policy = messaging::dm_policy(&db, &claims.sub).await.map_err(|e| {
+        tracing::error!("Failed to load DM settings of {}: {}", claims.sub, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    Ok(Json(json!({ "success": true, "data": { "policy": policy } })))
+}
+
+/// Who may message the user straight to their inbox. Conversations already accepted or in the
+/// inbox stay where they are.
+async fn update_dm_settings(
+    State(db): State<Database>,
+    claims: Claims,
+    Json(payload): Json<DmSettingsRequest>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    sqlx::query(
+        r#"
+        INSERT INTO dm_settings (user_id, policy)
+        VALUES ($1, $2)
+        ON CONFLICT (user_id) DO UPDATE SET policy = EXCLUDED.policy, updated_at = NOW()
+        "#,
+    )
+    .bind(&claims.sub)
+    .bind(payload.policy.as_str())
+    .execute(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to update DM settings of {}: {}", claims.sub, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    Ok(Json(json!({ "success": true, "data": { "policy": payload.policy } })))
+}
+
+/// Upload files to send in the conversation; send them by passing their ids with a message.
+async fn upload_attachments(
+    State(db): State<Database>,
+    Path(id): Path<Uuid>,
+    claims: Claims,
+    mut multipart: Multipart,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    ensure_participant(&db, id, &claims.sub).await?;
+
+    let mut attachments = Vec::new();
+    while let Some(mut field) = multipart
+        .next_field()
+        .await
+        .map_err(|_| StatusCode::BAD_REQUEST)?
+    {
+        let file_name = match field.file_name() {
+            Some(name) if !name.trim().is_empty() => std::path::Path::new(name)
+                .file_name()
+                .and_then(|name| name.to_str())
+                .unwrap_or("attachment")
+                .to_string(),
+            _ => continue,
+        };
+        if attachments.len() == MAX_ATTACHMENTS_PER_MESSAGE {
+            return Err(StatusCode::BAD_REQUEST);
+        }
+        let content_type = field
+            .content_type()
+            .map(|mime| mime.to_string())
+            .unwrap_or_default();
+        if !ATTACHMENT_TYPES.contains(&content_type.as_str()) {
+            return Err(StatusCode::UNSUPPORTED_MEDIA_TYPE);
+        }
+
+        let mut bytes: Vec<u8> = Vec::new();
+        while let Some(chunk) = field.chunk().await.map_err(|_| StatusCode::BAD_REQUEST)? {
+            if bytes.len() + chunk.len() > ATTACHMENT_MAX_BYTES {
+                return Err(StatusCode::PAYLOAD_TOO_LARGE);
+            }
+            bytes.extend_from_slice(&chunk);
+        }
+        if bytes.is_empty() {
+            return Err(StatusCode::BAD_REQUEST);
+        }
+        if !matches_signature(&content_type, &bytes) {
+            return Err(StatusCode::UNSUPPORTED_MEDIA_TYPE);
+        }
+
+        attachments
+            .push(store_attachment(&db, id, &claims.sub, file_name, content_type, bytes).await?);
+    }
+
+    if attachments.is_empty() {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    Ok(Json(json!({
+        "success": true,
+        "data": attachments
+    })))
+}
+
+async fn store_attachment(
+    db: &Database,
+    conversation_id: Uuid,
+    user_id: &str,
+    file_name: String,
+    content_type: String,
+    bytes: Vec<u8>,
+) -> Result<MessageAttachment, StatusCode> {
+    let size_bytes = bytes.len() as i64;
+    let mime_type = content_type.clone();
+    let (bytes, dimensions, thumbnail) = tokio::task::spawn_blocking(move || {
+        let dimensions = thumbnails::dimensions(&mime_type, &bytes);
+        let thumbnail = thumbnails::generate(&mime_type, &bytes, THUMBNAIL_MAX_SIDE);
+        (bytes, dimensions, thumbnail)
+    })
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let folder = format!("messages/{}", conversation_id);
+    let stored = storage::store_private_file(&folder, Some(&file_name), &content_type, bytes)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to store attachment in {}: {:?}", conversation_id, e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+    // A missing preview isn't worth failing the upload over
+    let thumbnail_key = match thumbnail {
+        Some(thumbnail) => {
+            storage::store_private_file(&folder, Some("thumbnail.png"), "image/png", thumbnail.png)
+                .await
+                .map_err(|e| tracing::warn!("Failed to store thumbnail: {:?}", e))
+                .ok()
+                .map(|stored| stored.storage_key)
+        }
+        None => None,
+    };
+
+    sqlx::query_as::<_, MessageAttachment>(
+        r#"
+        INSERT INTO message_attachments
+            (conversation_id, uploader_id, file_name, content_type, size_bytes, storage_key,
+             thumbnail_key, width, height)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+        RETURNING id, message_id, file_name, content_type, size_bytes, width, height,
+                  thumbnail_key IS NOT NULL AS has_thumbnail
+        "#,
+    )
+    .bind(conversation_id)
+    .bind(user_id)
+    .bind(&file_name)
+    .bind(&content_type)
+    .bind(size_bytes)
+    .bind(&stored.storage_key)
+    .bind(thumbnail_key)
+    .bind(dimensions.map(|(width, _)| width as i32))
+    .bind(dimensions.map(|(_, height)| height as i32))
+    .fetch_one(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to save attachment in {}: {}", conversation_id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })
+}
+
+/// Short-lived signed URLs for an attachment and its preview. Attachments not sent yet are only
+/// visible to whoever uploaded them.
+async fn get_attachment(
+    State(db): State<Database>,
+    Path((id, attachment_id)): Path<(Uuid, Uuid)>,
+    claims: Claims,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    ensure_participant(&db, id, &claims.sub).await?;
+    let (file_name, content_type, storage_key, thumbnail_key) =
+        sqlx::query_as::<_, (String, String, String, Option<String>)>(
+            r#"
+            SELECT file_name, content_type, storage_key, thumbnail_key
+            FROM message_attachments
+            WHERE id = $1 AND conversation_id = $2
+              AND (message_id IS NOT NULL OR uploader_id = $3)
+            "#,
+        )
+        .bind(attachment_id)
+        .bind(id)
+        .bind(&claims.sub)
+        .fetch_optional(&db.pool)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    let sign = |storage_key: String| async move {
+        storage::create_signed_url(&storage_key, ATTACHMENT_URL_TTL_SECONDS)
+            .await
+            .map_err(|e| {
+                tracing::error!("Failed to sign attachment {}: {:?}", attachment_id, e);
+                StatusCode::INTERNAL_SERVER_ERROR
+            })
+    };
+    let (url, expires) = sign(storage_key).await?;
+    let thumbnail_url = match thumbnail_key {
+        Some(thumbnail_key) => Some(sign(thumbnail_key).await?.0),
+        None => None,
+    };
+
+    Ok(Json(json!({
+        "success": true,
+        "data": {
+            "url": url,
+            "thumbnailUrl": thumbnail_url,
+            "fileName": file_name,
+            "contentType": content_type,
+            "expiresAt": DateTime::<Utc>::from_timestamp(expires, 0)
+        }
+    })))
+}
+
+/// Whether the file starts the way files of its declared type do, so nothing else passes as an
+/// image or document. Plain text, audio and video aren't checked.
+fn matches_signature(content_type: &str, bytes: &[u8]) -> bool {
+    match content_type {
+        "image/jpeg" => bytes.starts_with(&[0xFF, 0xD8, 0xFF]),
+        "image/png" => bytes.starts_with(b"\x89PNG\r\n\x1a\n"),
+        "image/gif" => bytes.starts_with(b"GIF87a") || bytes.starts_with(b"GIF89a"),
+        "image/webp" => bytes.starts_with(b"RIFF") && bytes.get(8..12) == Some(&b"WEBP"[..]),
+        "application/pdf" => bytes.starts_with(b"%PDF-"),
+        "application/zip" => bytes.starts_with(b"PK\x03\x04"),
+        _ => true,
+    }
+}
+
+/// Messages as JSON with the files sent with each.
+async fn with_attachments(
+    db: &Database,
+    messages: Vec<DirectMessage>,
+) -> Result<Vec<serde_json::Value>, StatusCode> {
+    let message_ids: Vec<Uuid> = messages.iter().map(|message| message.id).collect();
+    let attachments = sqlx::query_as::<_, MessageAttachment>(
+        r#"
+        SELECT id, message_id, file_name, content_type, size_bytes, width, height,
+               thumbnail_key IS NOT NULL AS has_thumbnail
+        FROM message_attachments
+        WHERE message_id = ANY($1)
+        ORDER BY created_at
+        "#,
+    )
+    .bind(&message_ids)
+    .fetch_all(&db.pool)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(messages
+        .into_iter()
+        .map(|message| {
+            let files: Vec<&MessageAttachment> = attachments
+                .iter()
+                .filter(|attachment| attachment.message_id == Some(message.id))
+                .collect();
+            let mut value = json!(message);
+            value["attachments"] = json!(files);
+            value
+        })
+        .collect())
+}
+
+/// The other participants whose DM policy doesn't let `sender_id` through, so the message lands in
+/// their requests. Anyone who wrote in the conversation themselves, or already has it in their
+/// inbox, gets it directly; a recipient who declined it can't be messaged at all. Group
+/// conversations are never requests.
+async fn request_recipients(
+    db: &Database,
+    id: Uuid,
+    sender_id: &str,
+) -> Result<Vec<String>, StatusCode> {
+    let others = sqlx::query_as::<_, (String, Option<String>, bool)>(
+        r#"
+        SELECT p.user_id, p.request_status,
+               EXISTS(
+                   SELECT 1 FROM messages m
+                   WHERE m.conversation_id = p.conversation_id AND m.sender_id = p.user_id
+               ) AS has_written
+        FROM conversation_participants p
+        JOIN conversations c ON c.id = p.conversation_id
+        WHERE p.conversation_id = $1 AND p.user_id <> $2 AND c.creator_id IS NULL
+        "#,
+    )
+    .bind(id)
+    .bind(sender_id)
+    .fetch_all(&db.pool)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let mut requested = Vec::new();
+    for (user_id, status, has_written) in others {
+        match status.as_deref().and_then(RequestStatus::parse) {
+            Some(RequestStatus::Declined) => return Err(StatusCode::FORBIDDEN),
+            Some(RequestStatus::Pending) => requested.push(user_id),
+            Some(RequestStatus::Accepted) => {}
+            None if has_written => {}
+            None => {
+                let allowed = messaging::may_message(db, sender_id, &user_id)
+                    .await
+                    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+                if !allowed {
+                    requested.push(user_id);
+                }
+            }
+        }
+    }
+    Ok(requested)
+}
+
+/// Conversations are only visible to the people in them, so anyone else gets a 404.
+async fn ensure_participant(db: &Database, id: Uuid, user_id: &str) -> Result<(), StatusCode> {
+    match messaging::is_participant(db, id, user_id).await {
+        Ok(true) => Ok(()),
+        Ok(false) => Err(StatusCode::NOT_FOUND),
+        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+    }
+}
+
+/// Members a group's creator muted can read along but not write.
+async fn ensure_not_muted(db: &Database, id: Uuid, user_id: &str) -> Result<(), StatusCode> {
+    let muted = sqlx::query_scalar::<_, bool>(
+        r#"
+        SELECT EXISTS(
+            SELECT 1 FROM conversation_participants
+            WHERE conversation_id = $1 AND user_id = $2
+              AND muted_at IS NOT NULL AND COALESCE(muted_until > NOW(), TRUE)
+        )
+        "#,
+    )
+    .bind(id)
+    .bind(user_id)
+    .fetch_one(&db.pool)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    if muted {
+        return Err(StatusCode::FORBIDDEN);
+    }
+    Ok(())
+}
+
+/// Check a message before it is sent: suspended senders can't send anything and blocked words
+/// are refused. Returns the word to flag the message for when the filter lets it through.
+pub(crate) async fn screen_outgoing(
+    db: &Database,
+    sender_id: &str,
+    body: &str,
+) -> Result<Option<String>, StatusCode> {
+    let suspended = messaging::messaging_suspended(db, sender_id)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    if suspended {
+        return Err(StatusCode::FORBIDDEN);
+    }
+    let verdict = messaging::screen_message(db, body).await.map_err(|e| {
+        tracing::error!("Failed to screen message from {}: {}", sender_id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    match verdict {
+        Some((FilterAction::Block, _)) => Err(StatusCode::UNPROCESSABLE_ENTITY),
+        Some((FilterAction::Flag, word)) => Ok(Some(word)),
+        None => Ok(None),
+    }
+}
+
+async fn load_participants(db: &Database, ids: &[Uuid]) -> Result<Vec<Participant>, StatusCode> {
+    sqlx::query_as::<_, Participant>(
+        r#"
+        SELECT p.conversation_id, p.user_id, COALESCE(u.display_name, u.name) AS name,
+               u.username, u.avatar_url AS avatar, p.last_delivered_at, p.last_read_at
+        FROM conversation_participants p
+        JOIN users u ON u.id = p.user_id
+        WHERE p.conversation_id = ANY($1)
+        ORDER BY p.joined_at
+        "#,
+    )
+    .bind(ids)
+    .fetch_all(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to load conversation participants: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })
+}
+
+async fn conversation_response(
+    db: &Database,
+    id: Uuid,
+    user_id: &str,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let row = sqlx::query_as::<_, ConversationRow>(
+        r#"
+        SELECT c.id, c.created_at, c.last_message_at,
+               (SELECT COUNT(*) FROM messages m
+                WHERE m.conversation_id = c.id AND m.sender_id <> p.user_id
+                  AND m.created_at > COALESCE(p.last_read_at, '-infinity')) AS unread_count,
+               lm.id AS last_message_id, lm.sender_id AS last_message_sender_id,
+               lm.body AS last_message_body, p.request_status, c.creator_id, c.title, c.tier_id
+        FROM conversations c
+        JOIN conversation_participants p ON p.conversation_id = c.id AND p.user_id = $2
+        LEFT JOIN LATERAL (
+            SELECT id, sender_id, body FROM messages
+            WHERE conversation_id = c.id
+            ORDER BY created_at DESC
+            LIMIT 1
+        ) lm ON TRUE
+        WHERE c.id = $1
+        "#,
+    )
+    .bind(id)
+    .bind(user_id)
+    .fetch_optional(&db.pool)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    .ok_or(StatusCode::NOT_FOUND)?;
+    let participants = load_participants(db, &[id]).await?;
+
+    Ok(Json(json!({
+        "success": true,
+        "data": conversation_json(row, participants)
+    })))
+}
+
+fn conversation_json(row: ConversationRow, participants: Vec<Participant>) -> serde_json::Value {
+    let last_message = row.last_message_id.map(|id| {
+        json!({
+            "id": id,
+            "senderId": row.last_message_sender_id,
+            "body": row.last_message_body,
+            "createdAt": row.last_message_at,
+        })
+    });
+    json!({
+        "id": row.id,
+        "isGroup": row.creator_id.is_some(),
+        "title": row.title,
+        "creatorId": row.creator_id,
+        "tierId": row.tier_id,
+        "participants": participants,
+        "lastMessage": last_message,
+        "unreadCount": row.unread_count,
+        "requestStatus": row.request_status,
+        "createdAt": row.created_at,
+        "lastMessageAt": row.last_message_at,
+    })
+}
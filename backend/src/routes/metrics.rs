@@ -0,0 +1,205 @@
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::Json,
+    routing::get,
+    Router,
+};
+use chrono::NaiveDate;
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::{
+    auth::Claims,
+    database::Database,
+    routes::{analytics::RangeQuery, fees::ensure_admin},
+};
+
+/// Visitors seen this many days up to the end of a period count as monthly active.
+const MAU_WINDOW_DAYS: i32 = 30;
+
+#[derive(Debug, Deserialize)]
+pub struct MetricsQuery {
+    /// `day` (the default) or `month`.
+    pub interval: Option<String>,
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+    /// GMV and fees in other currencies are left out. USD unless given.
+    pub currency: Option<String>,
+}
+
+pub fn metrics_routes() -> Router<Database> {
+    Router::new().route("/", get(get_platform_metrics))
+}
+
+fn ratio(part: i64, whole: i64) -> f64 {
+    if whole > 0 {
+        part as f64 / whole as f64
+    } else {
+        0.0
+    }
+}
+
+// Platform-wide money, activity and payment health per day or month. GMV is what buyers paid
+// in settled ledger entries and the take rate the platform's share of it; active creators are
+// those with a settled sale in the period. DAU and MAU count the distinct visitors in tracked
+// analytics events, so they only reach back as far as raw events are kept. Payment attempts
+// are the donations and purchases that reached Stripe; an attempt failed when it was declined
+// or cancelled and never completed.
+async fn get_platform_metrics(
+    State(db): State<Database>,
+    claims: Claims,
+    Query(query): Query<MetricsQuery>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    ensure_admin(&db, &claims.sub).await?;
+    let range = RangeQuery {
+        interval: query.interval.clone(),
+        from: query.from,
+        to: query.to,
+        format: None,
+    };
+    let (interval, from, to) = range.resolve()?;
+    let first_period = interval.truncate(from);
+    let step = format!("1 {}", interval.as_str());
+    let currency = query.currency.as_deref().unwrap_or("USD").to_ascii_uppercase();
+
+    let money = sqlx::query_as::<_, (NaiveDate, i64, i64, i64, i64)>(
+        r#"
+        SELECT
+            p.period::DATE,
+            COALESCE(SUM(le.gross_cents) FILTER (WHERE UPPER(le.currency) = $4), 0)::BIGINT,
+            COALESCE(SUM(le.fee_cents) FILTER (WHERE UPPER(le.currency) = $4), 0)::BIGINT,
+            COUNT(le.id),
+            COUNT(DISTINCT le.creator_id)
+        FROM generate_series($1::DATE, $2::DATE, $3::INTERVAL) AS p(period)
+        LEFT JOIN ledger_entries le
+            ON le.status IN ('SETTLED', 'DISPUTED')
+           AND le.created_at >= p.period::TIMESTAMP AT TIME ZONE 'UTC'
+           AND le.created_at < (p.period + $3::INTERVAL)::TIMESTAMP AT TIME ZONE 'UTC'
+        GROUP BY p.period
+        ORDER BY p.period
+        "#,
+    )
+    .bind(first_period)
+    .bind(to)
+    .bind(&step)
+    .bind(&currency)
+    .fetch_all(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to load platform GMV: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    let activity = sqlx::query_as::<_, (NaiveDate, f64, i64)>(
+        r#"
+        WITH daily AS (
+            SELECT (occurred_at AT TIME ZONE 'UTC')::DATE AS day, COUNT(DISTINCT visitor_id) AS visitors
+            FROM analytics_events
+            WHERE occurred_at >= $1::DATE::TIMESTAMP AT TIME ZONE 'UTC'
+              AND occurred_at < ($2::DATE + $3::INTERVAL)::TIMESTAMP AT TIME ZONE 'UTC'
+            GROUP BY 1
+        )
+        SELECT
+            p.period::DATE,
+            (COALESCE((SELECT SUM(d.visitors) FROM daily d
+                       WHERE d.day >= p.period AND d.day < p.period + $3::INTERVAL), 0)
+             / ((p.period + $3::INTERVAL)::DATE - p.period::DATE))::FLOAT8,
+            (SELECT COUNT(DISTINCT ae.visitor_id) FROM analytics_events ae
+             WHERE ae.occurred_at < (p.period + $3::INTERVAL)::TIMESTAMP AT TIME ZONE 'UTC'
+               AND ae.occurred_at >= (p.period + $3::INTERVAL)::TIMESTAMP AT TIME ZONE 'UTC' - make_interval(days => $4))
+        FROM generate_series($1::DATE, $2::DATE, $3::INTERVAL) AS p(period)
+        ORDER BY p.period
+        "#,
+    )
+    .bind(first_period)
+    .bind(to)
+    .bind(&step)
+    .bind(MAU_WINDOW_DAYS)
+    .fetch_all(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to load platform activity: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    let payments = sqlx::query_as::<_, (NaiveDate, i64, i64, i64)>(
+        r#"
+        SELECT
+            p.period::DATE,
+            COUNT(a.status),
+            COUNT(*) FILTER (WHERE a.status = 'COMPLETED'),
+            COUNT(*) FILTER (WHERE a.status <> 'COMPLETED' AND (a.payment_error IS NOT NULL OR a.status = 'CANCELLED'))
+        FROM generate_series($1::DATE, $2::DATE, $3::INTERVAL) AS p(period)
+        LEFT JOIN (
+            SELECT created_at, status, payment_error FROM donations
+            WHERE stripe_payment_intent_id IS NOT NULL
+            UNION ALL
+            SELECT created_at, status, payment_error FROM purchases
+            WHERE stripe_payment_intent_id IS NOT NULL OR stripe_checkout_session_id IS NOT NULL
+        ) a ON a.created_at >= p.period::TIMESTAMP AT TIME ZONE 'UTC'
+           AND a.created_at < (p.period + $3::INTERVAL)::TIMESTAMP AT TIME ZONE 'UTC'
+        GROUP BY p.period
+        ORDER BY p.period
+        "#,
+    )
+    .bind(first_period)
+    .bind(to)
+    .bind(&step)
+    .fetch_all(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to load platform payment attempts: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    let (mut gmv_total, mut fee_total, mut attempts_total, mut failed_total) = (0, 0, 0, 0);
+    let series = money
+        .iter()
+        .zip(&activity)
+        .zip(&payments)
+        .map(|((money, activity), payments)| {
+            let (period, gmv_cents, fee_cents, transactions, active_creators) = *money;
+            let (_, dau, mau) = *activity;
+            let (_, attempts, completed, failed) = *payments;
+            gmv_total += gmv_cents;
+            fee_total += fee_cents;
+            attempts_total += attempts;
+            failed_total += failed;
+            json!({
+                "period": period,
+                "gmvCents": gmv_cents,
+                "feeCents": fee_cents,
+                "takeRate": ratio(fee_cents, gmv_cents),
+                "transactions": transactions,
+                "activeCreators": active_creators,
+                "dau": dau,
+                "mau": mau,
+                "stickiness": if mau > 0 { dau / mau as f64 } else { 0.0 },
+                "paymentAttempts": attempts,
+                "paymentsCompleted": completed,
+                "paymentsFailed": failed,
+                "paymentFailureRate": ratio(failed, attempts),
+            })
+        })
+        .collect::<Vec<_>>();
+
+    Ok(Json(json!({
+        "success": true,
+        "data": {
+            "interval": interval.as_str(),
+            "from": from,
+            "to": to,
+            "currency": currency,
+            "totals": {
+                "gmvCents": gmv_total,
+                "feeCents": fee_total,
+                "takeRate": ratio(fee_total, gmv_total),
+                "paymentAttempts": attempts_total,
+                "paymentsFailed": failed_total,
+                "paymentFailureRate": ratio(failed_total, attempts_total),
+            },
+            "series": series,
+        }
+    })))
+}
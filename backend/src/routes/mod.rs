@@ -1,15 +1,67 @@
 pub mod analytics;
+pub mod announcements;
 pub mod articles;
 pub mod auth;
+pub mod bookmarks;
+pub mod campaign_experiments;
+pub mod campaign_referrals;
 pub mod campaigns;
+pub mod cart;
+pub mod coinbase;
+pub mod cohorts;
 pub mod creators;
+pub mod event_attendees;
+pub mod event_cohosts;
+pub mod event_feedback;
+pub mod event_invites;
+pub mod event_stream;
+pub mod event_tickets;
 pub mod events;
 pub mod feed;
+pub mod feed_mutes;
+pub mod feed_telemetry;
+pub mod following;
+pub mod discounts;
+pub mod disputes;
+pub mod dm_sequences;
+pub mod emails;
+pub mod explore;
+pub mod fees;
+pub mod inventory;
+pub mod invoices;
+pub mod ledger;
+pub mod licenses;
+pub mod message_groups;
+pub mod message_moderation;
+pub mod messages;
+pub mod metrics;
+pub mod notifications;
+pub mod payments;
+pub mod podcast_analytics;
+pub mod podcast_imports;
+pub mod podcast_transcripts;
 pub mod podcasts;
+pub mod poll_templates;
+pub mod polls;
 pub mod posts;
+pub mod priority_messages;
+pub mod product_downloads;
+pub mod product_variants;
+pub mod product_versions;
 pub mod products;
 pub mod purchases;
+pub mod referral_fraud;
 pub mod referrals;
 pub mod search;
+pub mod series;
+pub mod shipping;
+pub mod storefront;
+pub mod stripe;
+pub mod subscriptions;
+pub mod tax;
+pub mod taxonomy;
+pub mod tracking;
 pub mod uploads;
 pub mod users;
+pub mod withdrawals;
+pub mod ws;
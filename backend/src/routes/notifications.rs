@@ -0,0 +1,379 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::{Html, Json},
+    routing::{get, post},
+    Router,
+};
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use uuid::Uuid;
+
+use crate::{
+    auth::Claims,
+    database::Database,
+    notification_service::{
+        load_preferences, verify_digest_unsubscribe_token, DigestFrequency, NotificationChannel,
+        NotificationKind,
+    },
+    pagination::{parse_cursor, Cursor},
+    realtime::publish_counters,
+};
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+struct Notification {
+    id: Uuid,
+    kind: String,
+    payload: serde_json::Value,
+    read_at: Option<DateTime<Utc>>,
+    created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct NotificationQuery {
+    page: Option<u32>,
+    /// `nextCursor` of the previous page; takes the place of `page`.
+    cursor: Option<String>,
+    limit: Option<u32>,
+    #[serde(default)]
+    unread_only: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct DigestSettingsRequest {
+    frequency: DigestFrequency,
+}
+
+/// Channels to switch for one category; channels left out keep their setting.
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ChannelPreferences {
+    in_app: Option<bool>,
+    email: Option<bool>,
+    push: Option<bool>,
+}
+
+#[derive(Debug, Deserialize)]
+struct PreferencesRequest {
+    /// Keyed by category, e.g. `COMMENT`.
+    preferences: HashMap<String, ChannelPreferences>,
+}
+
+#[derive(Debug, Deserialize)]
+struct UnsubscribeQuery {
+    token: String,
+}
+
+pub fn notification_routes() -> Router<Database> {
+    Router::new()
+        .route("/", get(list_notifications))
+        .route("/preferences", get(get_preferences).patch(update_preferences))
+        .route("/digest", get(get_digest_settings).put(update_digest_settings))
+        // Public: digest emails link here, and mail clients POST for one-click unsubscribe
+        .route(
+            "/digest/unsubscribe",
+            get(unsubscribe_from_digest).post(unsubscribe_from_digest),
+        )
+        .route("/unread-count", get(get_unread_count))
+        .route("/read-all", post(mark_all_read))
+        .route("/:id/read", post(mark_read))
+}
+
+async fn list_notifications(
+    State(db): State<Database>,
+    Query(params): Query<NotificationQuery>,
+    claims: Claims,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let cursor = parse_cursor(params.cursor.as_deref())?;
+    let cursor_id = cursor.as_ref().map(Cursor::uuid).transpose()?;
+    let page = params.page.unwrap_or(1).max(1);
+    let limit = params.limit.unwrap_or(20).clamp(1, 100);
+    let offset = if cursor.is_some() { 0 } else { ((page - 1) * limit) as i64 };
+
+    // One extra row tells whether there is a next page
+    let mut notifications = sqlx::query_as::<_, Notification>(
+        r#"
+        SELECT id, kind, payload, read_at, created_at FROM notifications
+        WHERE user_id = $1 AND (NOT $2 OR read_at IS NULL)
+          AND ($5::TIMESTAMPTZ IS NULL OR (created_at, id) < ($5, $6))
+        ORDER BY created_at DESC, id DESC
+        LIMIT $3 OFFSET $4
+        "#,
+    )
+    .bind(&claims.sub)
+    .bind(params.unread_only)
+    .bind(limit as i64 + 1)
+    .bind(offset)
+    .bind(cursor.as_ref().map(|cursor| cursor.at))
+    .bind(cursor_id)
+    .fetch_all(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to list notifications of {}: {}", claims.sub, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    let has_more = notifications.len() > limit as usize;
+    notifications.truncate(limit as usize);
+    let next_cursor = notifications
+        .last()
+        .filter(|_| has_more)
+        .map(|notification| Cursor::new(notification.created_at, notification.id).encode());
+    let (total, unread_count) = sqlx::query_as::<_, (i64, i64)>(
+        r#"
+        SELECT COUNT(*) FILTER (WHERE NOT $2 OR read_at IS NULL),
+               COUNT(*) FILTER (WHERE read_at IS NULL)
+        FROM notifications
+        WHERE user_id = $1
+        "#,
+    )
+    .bind(&claims.sub)
+    .bind(params.unread_only)
+    .fetch_one(&db.pool)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(json!({
+        "success": true,
+        "data": {
+            "notifications": notifications,
+            "unreadCount": unread_count,
+        },
+        "pagination": {
+            "page": page,
+            "limit": limit,
+            "total": total,
+            "pages": ((total as f64) / (limit as f64)).ceil() as u32,
+            "hasMore": has_more,
+            "nextCursor": next_cursor,
+        }
+    })))
+}
+
+async fn get_unread_count(
+    State(db): State<Database>,
+    claims: Claims,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let unread_count = unread_count(&db, &claims.sub).await?;
+    Ok(Json(json!({ "success": true, "data": { "unreadCount": unread_count } })))
+}
+
+async fn mark_read(
+    State(db): State<Database>,
+    Path(id): Path<Uuid>,
+    claims: Claims,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let found = sqlx::query(
+        r#"
+        UPDATE notifications SET read_at = COALESCE(read_at, NOW())
+        WHERE id = $1 AND user_id = $2
+        "#,
+    )
+    .bind(id)
+    .bind(&claims.sub)
+    .execute(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to mark notification {} read: {}", id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?
+    .rows_affected();
+    if found == 0 {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    publish_counters(&db, &claims.sub).await;
+    let unread_count = unread_count(&db, &claims.sub).await?;
+    Ok(Json(json!({ "success": true, "data": { "id": id, "unreadCount": unread_count } })))
+}
+
+async fn mark_all_read(
+    State(db): State<Database>,
+    claims: Claims,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let marked = sqlx::query(
+        "UPDATE notifications SET read_at = NOW() WHERE user_id = $1 AND read_at IS NULL",
+    )
+    .bind(&claims.sub)
+    .execute(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to mark notifications of {} read: {}", claims.sub, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?
+    .rows_affected();
+    publish_counters(&db, &claims.sub).await;
+
+    Ok(Json(json!({ "success": true, "data": { "marked": marked, "unreadCount": 0 } })))
+}
+
+async fn get_preferences(
+    State(db): State<Database>,
+    claims: Claims,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    preferences_response(&db, &claims.sub).await
+}
+
+async fn update_preferences(
+    State(db): State<Database>,
+    claims: Claims,
+    Json(payload): Json<PreferencesRequest>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let mut changes = Vec::new();
+    for (category, channels) in &payload.preferences {
+        let kind = NotificationKind::parse(category).ok_or(StatusCode::BAD_REQUEST)?;
+        for (channel, enabled) in [
+            (NotificationChannel::InApp, channels.in_app),
+            (NotificationChannel::Email, channels.email),
+            (NotificationChannel::Push, channels.push),
+        ] {
+            if let Some(enabled) = enabled {
+                changes.push((kind, channel, enabled));
+            }
+        }
+    }
+
+    let mut tx = db.pool.begin().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    for (kind, channel, enabled) in changes {
+        sqlx::query(
+            r#"
+            INSERT INTO notification_preferences (user_id, category, channel, enabled)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT (user_id, category, channel)
+            DO UPDATE SET enabled = EXCLUDED.enabled, updated_at = NOW()
+            "#,
+        )
+        .bind(&claims.sub)
+        .bind(kind.as_str())
+        .bind(channel.as_str())
+        .bind(enabled)
+        .execute(&mut tx)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to update notification preferences of {}: {}", claims.sub, e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+    }
+    tx.commit().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    preferences_response(&db, &claims.sub).await
+}
+
+/// The full matrix, `{ CATEGORY: { inApp, email, push } }`, defaults included.
+async fn preferences_response(
+    db: &Database,
+    user_id: &str,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let preferences = load_preferences(db, user_id).await.map_err(|e| {
+        tracing::error!("Failed to load notification preferences of {}: {}", user_id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    let matrix: serde_json::Map<String, serde_json::Value> = NotificationKind::ALL
+        .into_iter()
+        .map(|kind| {
+            let channels: serde_json::Map<String, serde_json::Value> = NotificationChannel::ALL
+                .into_iter()
+                .map(|channel| {
+                    let enabled = preferences.get(&(kind, channel)).copied().unwrap_or(true);
+                    (channel.key().to_string(), json!(enabled))
+                })
+                .collect();
+            (kind.as_str().to_string(), serde_json::Value::Object(channels))
+        })
+        .collect();
+
+    Ok(Json(json!({ "success": true, "data": { "preferences": matrix } })))
+}
+
+async fn get_digest_settings(
+    State(db): State<Database>,
+    claims: Claims,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let settings = sqlx::query_as::<_, (String, Option<DateTime<Utc>>)>(
+        "SELECT frequency, last_sent_at FROM digest_settings WHERE user_id = $1",
+    )
+    .bind(&claims.sub)
+    .fetch_optional(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to load digest settings of {}: {}", claims.sub, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    let (frequency, last_sent_at) = match settings {
+        Some((frequency, last_sent_at)) => {
+            (DigestFrequency::parse(&frequency).unwrap_or_default(), last_sent_at)
+        }
+        None => (DigestFrequency::default(), None),
+    };
+
+    Ok(Json(json!({
+        "success": true,
+        "data": { "frequency": frequency, "lastSentAt": last_sent_at }
+    })))
+}
+
+async fn update_digest_settings(
+    State(db): State<Database>,
+    claims: Claims,
+    Json(payload): Json<DigestSettingsRequest>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    set_digest_frequency(&db, &claims.sub, payload.frequency).await?;
+    Ok(Json(json!({ "success": true, "data": { "frequency": payload.frequency } })))
+}
+
+// Answers with a page rather than JSON, since people land here from their inbox
+async fn unsubscribe_from_digest(
+    State(db): State<Database>,
+    Query(params): Query<UnsubscribeQuery>,
+) -> Result<Html<&'static str>, StatusCode> {
+    let user_id =
+        verify_digest_unsubscribe_token(&params.token).ok_or(StatusCode::UNAUTHORIZED)?;
+    set_digest_frequency(&db, &user_id, DigestFrequency::Off).await?;
+
+    Ok(Html(
+        "<!DOCTYPE html><html><head><meta charset=\"utf-8\"><title>Unsubscribed</title></head>\
+         <body><p>You will no longer get Fundify digest emails. You can turn them back on in \
+         your settings.</p></body></html>",
+    ))
+}
+
+async fn set_digest_frequency(
+    db: &Database,
+    user_id: &str,
+    frequency: DigestFrequency,
+) -> Result<(), StatusCode> {
+    sqlx::query(
+        r#"
+        INSERT INTO digest_settings (user_id, frequency)
+        VALUES ($1, $2)
+        ON CONFLICT (user_id) DO UPDATE SET frequency = EXCLUDED.frequency, updated_at = NOW()
+        "#,
+    )
+    .bind(user_id)
+    .bind(frequency.as_str())
+    .execute(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to update digest settings of {}: {}", user_id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    Ok(())
+}
+
+async fn unread_count(db: &Database, user_id: &str) -> Result<i64, StatusCode> {
+    sqlx::query_scalar::<_, i64>(
+        "SELECT COUNT(*) FROM notifications WHERE user_id = $1 AND read_at IS NULL",
+    )
+    .bind(user_id)
+    .fetch_one(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to count unread notifications of {}: {}", user_id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })
+}
@@ -0,0 +1,804 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::Json,
+    routing::{get, post},
+    Router,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use uuid::Uuid;
+
+use crate::{
+    auth::Claims,
+    database::Database,
+    routes::{
+        fees::ensure_admin,
+        ledger::{post_journal, JournalKind},
+        stripe::{
+            fetch_payment_intent, record_payment_intent_status, stripe_json, stripe_secret, stripe_url,
+        },
+    },
+};
+
+/// Ledger sources a creator can refund; subscriptions are refunded through the billing portal.
+const REFUNDABLE_SOURCES: [&str; 5] =
+    ["DONATION", "EVENT_TICKET", "POST_UNLOCK", "PRIORITY_MESSAGE", "PURCHASE"];
+
+/// A payment a creator received, as recorded in the ledger.
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct Payment {
+    pub id: Uuid,
+    pub product_type: String,
+    /// `DONATION`, `EVENT_TICKET`, `POST_UNLOCK`, `PRIORITY_MESSAGE` or `PURCHASE`.
+    pub source_type: String,
+    pub source_id: String,
+    pub creator_id: String,
+    pub payer_id: Option<String>,
+    pub currency: String,
+    pub gross_cents: i64,
+    pub fee_cents: i64,
+    pub net_cents: i64,
+    pub tax_cents: i64,
+    pub status: String,
+    /// Price refunded so far, excluding the tax refunded with it.
+    pub refunded_cents: i64,
+    pub stripe_account_id: Option<String>,
+    pub stripe_payment_intent_id: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Money returned to a buyer for all or part of a payment.
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct Refund {
+    pub id: Uuid,
+    pub ledger_entry_id: Uuid,
+    pub creator_id: String,
+    pub buyer_id: Option<String>,
+    /// Part of the price refunded.
+    pub amount_cents: i64,
+    /// Tax refunded on top of `amount_cents`.
+    pub tax_cents: i64,
+    /// Platform fee handed back with the refund.
+    pub fee_cents: i64,
+    pub currency: String,
+    pub reason: String,
+    pub note: Option<String>,
+    /// `PENDING` while Stripe is called, then `SUCCEEDED` or `FAILED`.
+    pub status: String,
+    pub stripe_refund_id: Option<String>,
+    pub failure_reason: Option<String>,
+    pub created_by: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub(crate) enum RefundReason {
+    Duplicate,
+    Fraudulent,
+    RequestedByCustomer,
+    ProductNotDelivered,
+    EventCancelled,
+    /// Paid for but could not be delivered, e.g. an event that sold out during checkout.
+    FulfillmentFailed,
+    /// A priority message the creator didn't reply to in time.
+    ResponseOverdue,
+    Other,
+}
+
+impl RefundReason {
+    const ALL: [Self; 8] = [
+        Self::Duplicate,
+        Self::Fraudulent,
+        Self::RequestedByCustomer,
+        Self::ProductNotDelivered,
+        Self::EventCancelled,
+        Self::FulfillmentFailed,
+        Self::ResponseOverdue,
+        Self::Other,
+    ];
+
+    fn parse(reason: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|candidate| candidate.as_str() == reason)
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Duplicate => "DUPLICATE",
+            Self::Fraudulent => "FRAUDULENT",
+            Self::RequestedByCustomer => "REQUESTED_BY_CUSTOMER",
+            Self::ProductNotDelivered => "PRODUCT_NOT_DELIVERED",
+            Self::EventCancelled => "EVENT_CANCELLED",
+            Self::FulfillmentFailed => "FULFILLMENT_FAILED",
+            Self::ResponseOverdue => "RESPONSE_OVERDUE",
+            Self::Other => "OTHER",
+        }
+    }
+
+    /// Stripe only knows three reasons; the rest are kept on our side.
+    fn stripe_reason(self) -> Option<&'static str> {
+        match self {
+            Self::Duplicate => Some("duplicate"),
+            Self::Fraudulent => Some("fraudulent"),
+            Self::RequestedByCustomer => Some("requested_by_customer"),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RefundRequest {
+    /// Part of the price to refund, in cents; the whole remainder when omitted.
+    amount: Option<i64>,
+    reason: RefundReason,
+    note: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PaymentsQuery {
+    source_type: Option<String>,
+    page: Option<u32>,
+    limit: Option<u32>,
+}
+
+pub fn payment_routes() -> Router<Database> {
+    Router::new()
+        .route("/", get(list_payments))
+        .route("/:id", get(get_payment))
+        .route("/:id/status", get(get_payment_status))
+        .route("/:id/refund", post(refund_payment))
+}
+
+const PAYMENT_COLUMNS: &str = r#"
+    l.id, l.product_type, l.source_type, l.source_id, l.creator_id, l.payer_id, l.currency,
+    l.gross_cents, l.fee_cents, l.net_cents, l.tax_cents, l.status,
+    COALESCE((
+        SELECT SUM(r.amount_cents) FROM refunds r
+        WHERE r.ledger_entry_id = l.id AND r.status <> 'FAILED'
+    ), 0)::BIGINT AS refunded_cents,
+    l.stripe_account_id, l.stripe_payment_intent_id, l.created_at
+"#;
+
+async fn list_payments(
+    State(db): State<Database>,
+    Query(params): Query<PaymentsQuery>,
+    claims: Claims,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let page = params.page.unwrap_or(1).max(1);
+    let limit = params.limit.unwrap_or(20).clamp(1, 100);
+    let offset = ((page - 1) * limit) as i64;
+    let source_type = params.source_type.map(|value| value.trim().to_ascii_uppercase());
+
+    let payments = sqlx::query_as::<_, Payment>(&format!(
+        r#"
+        SELECT {}
+        FROM ledger_entries l
+        WHERE l.creator_id = $1 AND l.source_type = ANY($2)
+          AND ($3::TEXT IS NULL OR l.source_type = $3)
+        ORDER BY l.created_at DESC
+        LIMIT $4 OFFSET $5
+        "#,
+        PAYMENT_COLUMNS
+    ))
+    .bind(&claims.sub)
+    .bind(&REFUNDABLE_SOURCES[..])
+    .bind(&source_type)
+    .bind(limit as i64)
+    .bind(offset)
+    .fetch_all(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to list payments of {}: {}", claims.sub, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    let total = sqlx::query_scalar::<_, i64>(
+        r#"
+        SELECT COUNT(*) FROM ledger_entries
+        WHERE creator_id = $1 AND source_type = ANY($2) AND ($3::TEXT IS NULL OR source_type = $3)
+        "#,
+    )
+    .bind(&claims.sub)
+    .bind(&REFUNDABLE_SOURCES[..])
+    .bind(&source_type)
+    .fetch_one(&db.pool)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(json!({
+        "success": true,
+        "data": payments,
+        "pagination": {
+            "page": page,
+            "limit": limit,
+            "total": total,
+            "pages": ((total as f64) / (limit as f64)).ceil() as u32,
+        }
+    })))
+}
+
+async fn get_payment(
+    State(db): State<Database>,
+    Path(id): Path<Uuid>,
+    claims: Claims,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let payment = load_payment(&db, id).await?;
+    if payment.creator_id != claims.sub {
+        ensure_admin(&db, &claims.sub).await?;
+    }
+
+    let refunds = sqlx::query_as::<_, Refund>(
+        "SELECT * FROM refunds WHERE ledger_entry_id = $1 ORDER BY created_at DESC",
+    )
+    .bind(id)
+    .fetch_all(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to load refunds of payment {}: {}", id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    Ok(Json(json!({
+        "success": true,
+        "data": {
+            "payment": payment,
+            "refunds": refunds,
+            "refundableCents": payment.gross_cents - payment.refunded_cents,
+        }
+    })))
+}
+
+// Polled by the payment page while the buyer completes 3D Secure. It only reports: the payment is
+// fulfilled by the payment_intent.succeeded webhook alone.
+async fn get_payment_status(
+    State(db): State<Database>,
+    Path(id): Path<Uuid>,
+    claims: Claims,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let payment = load_payment(&db, id).await?;
+    let is_payer = payment.payer_id.as_deref() == Some(claims.sub.as_str());
+    if !is_payer && payment.creator_id != claims.sub {
+        ensure_admin(&db, &claims.sub).await?;
+    }
+
+    let stored = sqlx::query_as::<_, (Option<String>, Option<String>)>(
+        r#"
+        SELECT payment_intent_status, payment_error FROM donations
+        WHERE id::TEXT = $1 AND $2 = 'DONATION'
+        UNION ALL
+        SELECT payment_intent_status, payment_error FROM purchases
+        WHERE id::TEXT = $1 AND $2 = 'PURCHASE'
+        "#,
+    )
+    .bind(&payment.source_id)
+    .bind(&payment.source_type)
+    .fetch_optional(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to load status of payment {}: {}", id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    let (mut intent_status, mut payment_error) = stored.unwrap_or_default();
+    let mut next_action = None;
+    let mut client_secret = None;
+
+    // Until the webhook settles the payment Stripe is asked directly, which also gives the buyer
+    // what they need to authenticate again or retry with another card
+    if let (true, Some(payment_intent_id)) =
+        (payment.status == "PENDING", &payment.stripe_payment_intent_id)
+    {
+        let payment_intent = fetch_payment_intent(payment_intent_id).await?;
+        intent_status = payment_intent
+            .get("status")
+            .and_then(|status| status.as_str())
+            .map(str::to_string);
+        payment_error = payment_intent
+            .pointer("/last_payment_error/message")
+            .and_then(|message| message.as_str())
+            .map(str::to_string);
+        if let Some(status) = &intent_status {
+            record_payment_intent_status(&db, payment_intent_id, status, payment_error.as_deref())
+                .await?;
+        }
+        next_action = payment_intent
+            .pointer("/next_action/type")
+            .and_then(|action| action.as_str())
+            .map(str::to_string);
+        if is_payer
+            && matches!(intent_status.as_deref(), Some("requires_action" | "requires_payment_method"))
+        {
+            client_secret = payment_intent.get("client_secret").cloned();
+        }
+    }
+    // Settled, disputed and reversed payments all went through
+    let fulfilled = payment.status != "PENDING";
+    let intent_status = intent_status.or_else(|| fulfilled.then(|| "succeeded".to_string()));
+
+    Ok(Json(json!({
+        "success": true,
+        "data": {
+            "paymentId": payment.id,
+            "status": intent_status,
+            "requiresAction": intent_status.as_deref() == Some("requires_action"),
+            "nextAction": next_action,
+            "clientSecret": client_secret,
+            "error": payment_error,
+            "fulfilled": fulfilled,
+            "ledgerStatus": payment.status,
+        }
+    })))
+}
+
+async fn refund_payment(
+    State(db): State<Database>,
+    Path(id): Path<Uuid>,
+    claims: Claims,
+    Json(payload): Json<RefundRequest>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let payment = load_payment(&db, id).await?;
+    if payment.creator_id != claims.sub {
+        ensure_admin(&db, &claims.sub).await?;
+    }
+
+    let refund =
+        refund_ledger_entry(&db, id, payload.amount, payload.reason, payload.note, &claims.sub).await?;
+
+    Ok(Json(json!({
+        "success": true,
+        "data": refund
+    })))
+}
+
+/// Refund `amount_cents` of a settled payment, or all that is left of it, and book the refund.
+///
+/// The refund is reserved against the payment before Stripe is called, so two partial refunds
+/// cannot both take the same remainder; a Stripe failure marks it FAILED and releases it.
+pub(crate) async fn refund_ledger_entry(
+    db: &Database,
+    id: Uuid,
+    amount_cents: Option<i64>,
+    reason: RefundReason,
+    note: Option<String>,
+    refunded_by: &str,
+) -> Result<Refund, StatusCode> {
+    let mut tx = db.pool.begin().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let payment = sqlx::query_as::<_, Payment>(&format!(
+        "SELECT {} FROM ledger_entries l WHERE l.id = $1 FOR UPDATE",
+        PAYMENT_COLUMNS
+    ))
+    .bind(id)
+    .fetch_optional(&mut tx)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to load payment {}: {}", id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?
+    .ok_or(StatusCode::NOT_FOUND)?;
+    if !REFUNDABLE_SOURCES.contains(&payment.source_type.as_str()) {
+        return Err(StatusCode::UNPROCESSABLE_ENTITY);
+    }
+    // Disputed payments are settled through the dispute, and pending ones have not been paid
+    if payment.status != "SETTLED" {
+        return Err(StatusCode::CONFLICT);
+    }
+    if payment.stripe_payment_intent_id.is_none() {
+        return Err(StatusCode::UNPROCESSABLE_ENTITY);
+    }
+
+    let (refunded_tax, refunded_fee) = sqlx::query_as::<_, (i64, i64)>(
+        r#"
+        SELECT COALESCE(SUM(tax_cents), 0)::BIGINT, COALESCE(SUM(fee_cents), 0)::BIGINT
+        FROM refunds
+        WHERE ledger_entry_id = $1 AND status <> 'FAILED'
+        "#,
+    )
+    .bind(id)
+    .fetch_one(&mut tx)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let refundable = payment.gross_cents - payment.refunded_cents;
+    let amount = amount_cents.unwrap_or(refundable);
+    if amount <= 0 || amount > refundable {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    // The last refund takes whatever is left, so rounding never leaves a cent behind
+    let (tax_cents, fee_cents) = if amount == refundable {
+        (payment.tax_cents - refunded_tax, payment.fee_cents - refunded_fee)
+    } else {
+        (
+            proportion(payment.tax_cents, amount, payment.gross_cents),
+            proportion(payment.fee_cents, amount, payment.gross_cents),
+        )
+    };
+
+    let note = note
+        .map(|note| note.trim().to_string())
+        .filter(|note| !note.is_empty());
+    let refund = sqlx::query_as::<_, Refund>(
+        r#"
+        INSERT INTO refunds (
+            ledger_entry_id, creator_id, buyer_id, amount_cents, tax_cents, fee_cents,
+            currency, reason, note, created_by
+        )
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+        RETURNING *
+        "#,
+    )
+    .bind(id)
+    .bind(&payment.creator_id)
+    .bind(&payment.payer_id)
+    .bind(amount)
+    .bind(tax_cents)
+    .bind(fee_cents)
+    .bind(&payment.currency)
+    .bind(reason.as_str())
+    .bind(&note)
+    .bind(refunded_by)
+    .fetch_one(&mut tx)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to reserve refund of payment {}: {}", id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    tx.commit().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    submit_refund(db, &payment, refund).await
+}
+
+/// Refund the whole of a payment whose purchase could not be delivered, e.g. a ticket for an
+/// event that sold out while the buyer paid. A retried webhook finds the refund an earlier attempt
+/// reserved and finishes that one instead of reserving another, which the already refunded
+/// payment would reject.
+pub(crate) async fn refund_unfulfilled(
+    db: &Database,
+    ledger_entry_id: Uuid,
+    reason: &str,
+) -> Result<(), StatusCode> {
+    let earlier = sqlx::query_as::<_, (Uuid, String)>(
+        r#"
+        SELECT id, status FROM refunds
+        WHERE ledger_entry_id = $1 AND reason = $2 AND status <> 'FAILED'
+        ORDER BY created_at DESC
+        LIMIT 1
+        "#,
+    )
+    .bind(ledger_entry_id)
+    .bind(RefundReason::FulfillmentFailed.as_str())
+    .fetch_optional(&db.pool)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    match earlier {
+        Some((refund_id, status)) if status == "PENDING" => {
+            resume_refund(db, refund_id).await?;
+        }
+        Some(_) => {}
+        None => {
+            refund_ledger_entry(
+                db,
+                ledger_entry_id,
+                None,
+                RefundReason::FulfillmentFailed,
+                Some(reason.to_string()),
+                "SYSTEM",
+            )
+            .await?;
+        }
+    }
+    Ok(())
+}
+
+/// Finish a refund left PENDING by a call that failed after reserving it, e.g. a webhook whose
+/// Stripe refund went through but whose booking did not. Stripe is asked again under the same
+/// idempotency key, so it returns the refund it already made instead of paying out twice.
+async fn resume_refund(db: &Database, refund_id: Uuid) -> Result<Refund, StatusCode> {
+    let refund = sqlx::query_as::<_, Refund>("SELECT * FROM refunds WHERE id = $1")
+        .bind(refund_id)
+        .fetch_optional(&db.pool)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to load refund {}: {}", refund_id, e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?
+        .ok_or(StatusCode::NOT_FOUND)?;
+    if refund.status != "PENDING" {
+        return Ok(refund);
+    }
+
+    // `refunded_cents` already counts this refund's reservation; book it as if it didn't yet
+    let mut payment = load_payment(db, refund.ledger_entry_id).await?;
+    payment.refunded_cents -= refund.amount_cents;
+    submit_refund(db, &payment, refund).await
+}
+
+/// Send a reserved refund to Stripe and book it. `payment` is the refunded payment as it stood
+/// before this refund was reserved.
+async fn submit_refund(db: &Database, payment: &Payment, refund: Refund) -> Result<Refund, StatusCode> {
+    let payment_intent_id = payment
+        .stripe_payment_intent_id
+        .clone()
+        .ok_or(StatusCode::UNPROCESSABLE_ENTITY)?;
+    let mut form_data = vec![
+        ("payment_intent".to_string(), payment_intent_id),
+        ("amount".to_string(), (refund.amount_cents + refund.tax_cents).to_string()),
+        ("metadata[refund_id]".to_string(), refund.id.to_string()),
+        ("metadata[source_type]".to_string(), payment.source_type.clone()),
+        ("metadata[source_id]".to_string(), payment.source_id.clone()),
+    ];
+    if let Some(reason) = RefundReason::parse(&refund.reason).and_then(RefundReason::stripe_reason) {
+        form_data.push(("reason".to_string(), reason.to_string()));
+    }
+    // Destination charges pull the creator's share back and return the platform's fee in proportion
+    if payment.stripe_account_id.is_some() {
+        form_data.push(("reverse_transfer".to_string(), "true".to_string()));
+        form_data.push(("refund_application_fee".to_string(), "true".to_string()));
+    }
+
+    let stripe_refund = stripe_json(
+        reqwest::Client::new()
+            .post(stripe_url("/v1/refunds"))
+            .bearer_auth(stripe_secret()?)
+            .header("Idempotency-Key", format!("refund-{}", refund.id))
+            .form(&form_data)
+            .send()
+            .await,
+        "create refund",
+    )
+    .await;
+    let stripe_refund_id = match stripe_refund
+        .as_ref()
+        .ok()
+        .and_then(|refund| refund.get("id"))
+        .and_then(|id| id.as_str())
+    {
+        Some(stripe_refund_id) => stripe_refund_id.to_string(),
+        None => {
+            mark_failed(db, refund.id, "Stripe rejected the refund").await;
+            return Err(StatusCode::BAD_GATEWAY);
+        }
+    };
+
+    let refund_id = refund.id;
+    match complete_refund(db, payment, refund, &stripe_refund_id).await? {
+        Some(refund) => {
+            notify_buyer(db, &refund).await;
+            Ok(refund)
+        }
+        // Booked, and the buyer told, by whichever call got there first
+        None => sqlx::query_as::<_, Refund>("SELECT * FROM refunds WHERE id = $1")
+            .bind(refund_id)
+            .fetch_one(&db.pool)
+            .await
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR),
+    }
+}
+
+/// `part / whole` of `total`, rounded to the nearest cent.
+fn proportion(total: i64, part: i64, whole: i64) -> i64 {
+    if whole <= 0 {
+        return 0;
+    }
+    (total * part + whole / 2) / whole
+}
+
+async fn load_payment(db: &Database, id: Uuid) -> Result<Payment, StatusCode> {
+    sqlx::query_as::<_, Payment>(&format!(
+        "SELECT {} FROM ledger_entries l WHERE l.id = $1",
+        PAYMENT_COLUMNS
+    ))
+    .bind(id)
+    .fetch_optional(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to load payment {}: {}", id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?
+    .ok_or(StatusCode::NOT_FOUND)
+}
+
+async fn mark_failed(db: &Database, refund_id: Uuid, reason: &str) {
+    if let Err(e) = sqlx::query(
+        "UPDATE refunds SET status = 'FAILED', failure_reason = $2, updated_at = NOW() WHERE id = $1",
+    )
+    .bind(refund_id)
+    .bind(reason)
+    .execute(&db.pool)
+    .await
+    {
+        tracing::error!("Failed to mark refund {} failed: {}", refund_id, e);
+    }
+}
+
+// Stripe has already returned the money, so the books must follow: a negative ledger entry and
+// tax line cancel the refunded share, and a fully refunded sale loses what it unlocked. Returns
+// None, booking nothing, when the refund is no longer PENDING because another call completed it.
+async fn complete_refund(
+    db: &Database,
+    payment: &Payment,
+    refund: Refund,
+    stripe_refund_id: &str,
+) -> Result<Option<Refund>, StatusCode> {
+    let db_error = |e: sqlx::Error| {
+        tracing::error!("Failed to record refund {}: {}", refund.id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    };
+    let mut tx = db.pool.begin().await.map_err(db_error)?;
+
+    let completed = sqlx::query_as::<_, Refund>(
+        r#"
+        UPDATE refunds
+        SET status = 'SUCCEEDED', stripe_refund_id = $2, updated_at = NOW()
+        WHERE id = $1 AND status = 'PENDING'
+        RETURNING *
+        "#,
+    )
+    .bind(refund.id)
+    .bind(stripe_refund_id)
+    .fetch_optional(&mut tx)
+    .await
+    .map_err(db_error)?;
+    let Some(completed) = completed else {
+        return Ok(None);
+    };
+
+    let adjustment = sqlx::query_scalar::<_, Uuid>(
+        r#"
+        INSERT INTO ledger_entries (
+            product_type, source_type, source_id, creator_id, payer_id, currency,
+            gross_cents, fee_cents, net_cents, tax_cents, status, stripe_account_id,
+            stripe_payment_intent_id, settled_at
+        )
+        VALUES ($1, 'REFUND', $2, $3, $4, $5, $6, $7, $8, $9, 'SETTLED', $10, $11, NOW())
+        RETURNING id
+        "#,
+    )
+    .bind(&payment.product_type)
+    .bind(refund.id.to_string())
+    .bind(&payment.creator_id)
+    .bind(&payment.payer_id)
+    .bind(&payment.currency)
+    .bind(-refund.amount_cents)
+    .bind(-refund.fee_cents)
+    .bind(-(refund.amount_cents - refund.fee_cents))
+    .bind(-refund.tax_cents)
+    .bind(&payment.stripe_account_id)
+    .bind(&payment.stripe_payment_intent_id)
+    .fetch_one(&mut tx)
+    .await
+    .map_err(db_error)?;
+    post_journal(&mut tx, &[adjustment], JournalKind::Settlement)
+        .await
+        .map_err(db_error)?;
+
+    if refund.tax_cents > 0 {
+        sqlx::query(
+            r#"
+            INSERT INTO tax_lines (
+                source_type, source_id, creator_id, buyer_id, country, region, tax_name,
+                rate_basis_points, taxable_cents, tax_cents, currency, evidence, status,
+                stripe_payment_intent_id, settled_at
+            )
+            SELECT 'REFUND', $3, creator_id, buyer_id, country, region, tax_name,
+                   rate_basis_points, -$4, -$5, currency, evidence, 'SETTLED',
+                   stripe_payment_intent_id, NOW()
+            FROM tax_lines
+            WHERE source_type = $1 AND source_id = $2 AND status = 'SETTLED' AND tax_cents > 0
+            LIMIT 1
+            "#,
+        )
+        .bind(&payment.source_type)
+        .bind(&payment.source_id)
+        .bind(refund.id.to_string())
+        .bind(refund.amount_cents)
+        .bind(refund.tax_cents)
+        .execute(&mut tx)
+        .await
+        .map_err(db_error)?;
+    }
+
+    let fully_refunded = payment.refunded_cents + refund.amount_cents >= payment.gross_cents;
+    match payment.source_type.as_str() {
+        // Campaign totals drop by every refund, partial or not
+        "DONATION" => {
+            sqlx::query(
+                r#"
+                WITH refunded AS (
+                    UPDATE donations
+                    SET status = CASE WHEN $3 THEN 'REFUNDED' ELSE 'PARTIALLY_REFUNDED' END,
+                        updated_at = NOW()
+                    WHERE id::TEXT = $1
+                    RETURNING campaign_id
+                )
+                UPDATE campaigns c
+                SET current_amount = GREATEST(COALESCE(c.current_amount, 0) - $2, 0), updated_at = NOW()
+                FROM refunded
+                WHERE c.id = refunded.campaign_id
+                "#,
+            )
+            .bind(&payment.source_id)
+            .bind(refund.amount_cents as f64 / 100.0)
+            .bind(fully_refunded)
+            .execute(&mut tx)
+            .await
+            .map_err(db_error)?;
+        }
+        "PURCHASE" if fully_refunded => {
+            sqlx::query("UPDATE purchases SET status = 'REFUNDED' WHERE id::TEXT = $1")
+                .bind(&payment.source_id)
+                .execute(&mut tx)
+                .await
+                .map_err(db_error)?;
+        }
+        "POST_UNLOCK" if fully_refunded => {
+            sqlx::query("UPDATE post_unlocks SET status = 'REFUNDED', updated_at = NOW() WHERE id::TEXT = $1")
+                .bind(&payment.source_id)
+                .execute(&mut tx)
+                .await
+                .map_err(db_error)?;
+        }
+        "PRIORITY_MESSAGE" if fully_refunded => {
+            sqlx::query(
+                "UPDATE priority_messages SET status = 'REFUNDED', updated_at = NOW() WHERE id::TEXT = $1",
+            )
+            .bind(&payment.source_id)
+            .execute(&mut tx)
+            .await
+            .map_err(db_error)?;
+        }
+        "EVENT_TICKET" if fully_refunded => {
+            sqlx::query(
+                r#"
+                UPDATE event_rsvps
+                SET status = 'NOT_GOING', is_paid = FALSE, updated_at = NOW()
+                WHERE event_id = $1 AND user_id = $2
+                "#,
+            )
+            .bind(&payment.source_id)
+            .bind(&payment.payer_id)
+            .execute(&mut tx)
+            .await
+            .map_err(db_error)?;
+        }
+        _ => {}
+    }
+
+    tx.commit().await.map_err(db_error)?;
+    Ok(Some(completed))
+}
+
+async fn notify_buyer(db: &Database, refund: &Refund) {
+    let (Some(amqp), Some(buyer_id)) = (&db.amqp, &refund.buyer_id) else {
+        return;
+    };
+    let email = sqlx::query_scalar::<_, Option<String>>("SELECT email FROM users WHERE id = $1")
+        .bind(buyer_id)
+        .fetch_optional(&db.pool)
+        .await
+        .ok()
+        .flatten()
+        .flatten();
+    let Some(email) = email else {
+        return;
+    };
+
+    if let Err(e) = amqp
+        .send_refund_email(
+            refund.id.to_string(),
+            buyer_id.clone(),
+            email,
+            refund.amount_cents + refund.tax_cents,
+            refund.currency.clone(),
+            refund.reason.clone(),
+        )
+        .await
+    {
+        tracing::warn!("Failed to queue refund email for {}: {}", buyer_id, e);
+    }
+}
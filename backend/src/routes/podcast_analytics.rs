@@ -0,0 +1,395 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::{HeaderMap, StatusCode},
+    response::Json,
+};
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use serde::Deserialize;
+use serde_json::json;
+use sqlx::Row;
+use uuid::Uuid;
+
+use crate::{
+    auth::Claims,
+    database::Database,
+    middleware::optional_auth::MaybeClaims,
+    post_views,
+    routes::{analytics::RangeQuery, podcasts::ensure_podcast_owner},
+};
+
+const MAX_BATCH_PINGS: usize = 100;
+/// Pings reported later than this after they happened are dropped.
+const MAX_PING_AGE_HOURS: i64 = 24;
+/// A listen that reached this share of the episode counts as completed.
+const COMPLETION_THRESHOLD: f64 = 0.95;
+/// Points on the drop-off curve are this many percent of the episode apart.
+const DROP_OFF_STEP_PERCENT: i32 = 5;
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ListenPing {
+    episode_id: Uuid,
+    /// Generated by the player for each playback of an episode, so one listener's replays
+    /// count as separate plays.
+    session_id: Uuid,
+    /// Playback position in seconds.
+    position: f64,
+    /// The player reached the end of the episode.
+    #[serde(default)]
+    completed: bool,
+    occurred_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct ListenPingBatch {
+    pings: Vec<ListenPing>,
+}
+
+/// Batched progress pings from the player. Each playback session keeps the furthest position
+/// it reached, which is what completion and drop-off are measured on. Pings for unknown or
+/// unpublished episodes, from too long ago, or for a session started by another listener are
+/// skipped.
+pub(crate) async fn record_listens(
+    State(db): State<Database>,
+    MaybeClaims(claims): MaybeClaims,
+    headers: HeaderMap,
+    Json(payload): Json<ListenPingBatch>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    if payload.pings.len() > MAX_BATCH_PINGS {
+        return Err(StatusCode::PAYLOAD_TOO_LARGE);
+    }
+
+    let now = Utc::now();
+    let oldest = now - Duration::hours(MAX_PING_AGE_HOURS);
+    let pings: Vec<&ListenPing> = payload
+        .pings
+        .iter()
+        .filter(|ping| ping.position.is_finite() && ping.position >= 0.0)
+        .filter(|ping| ping.occurred_at.unwrap_or(now) >= oldest)
+        .collect();
+    if pings.is_empty() {
+        return Ok(Json(json!({
+            "success": true,
+            "data": { "accepted": 0, "skipped": payload.pings.len() }
+        })));
+    }
+
+    let listener_key = post_views::viewer_key(claims.as_ref().map(|claims| claims.sub.as_str()), &headers);
+    let session_ids: Vec<Uuid> = pings.iter().map(|ping| ping.session_id).collect();
+    let episode_ids: Vec<Uuid> = pings.iter().map(|ping| ping.episode_id).collect();
+    let positions: Vec<f64> = pings.iter().map(|ping| ping.position).collect();
+    let completed: Vec<bool> = pings.iter().map(|ping| ping.completed).collect();
+    let occurred_at: Vec<DateTime<Utc>> = pings
+        .iter()
+        .map(|ping| ping.occurred_at.unwrap_or(now).min(now))
+        .collect();
+
+    // Positions past the end of the episode are capped at its length, and a batch can hold
+    // several pings of one session, so they are folded together before the upsert.
+    let accepted = sqlx::query(
+        r#"
+        INSERT INTO podcast_listens (
+            session_id, episode_id, listener_key, started_at, last_ping_at, max_position, completed
+        )
+        SELECT p.session_id, p.episode_id, $1, MIN(p.occurred_at), MAX(p.occurred_at),
+               MAX(LEAST(p.position, COALESCE(NULLIF(e.duration, 0)::FLOAT8, p.position))),
+               BOOL_OR(p.completed OR (e.duration > 0 AND p.position >= e.duration * $7))
+        FROM UNNEST($2::UUID[], $3::UUID[], $4::FLOAT8[], $5::BOOLEAN[], $6::TIMESTAMPTZ[])
+            AS p(session_id, episode_id, position, completed, occurred_at)
+        JOIN podcast_episodes e ON e.id = p.episode_id AND COALESCE(e.status, 'PUBLISHED') = 'PUBLISHED'
+        GROUP BY p.session_id, p.episode_id
+        ON CONFLICT (session_id, episode_id) DO UPDATE
+        SET last_ping_at = GREATEST(podcast_listens.last_ping_at, EXCLUDED.last_ping_at),
+            max_position = GREATEST(podcast_listens.max_position, EXCLUDED.max_position),
+            completed = podcast_listens.completed OR EXCLUDED.completed
+        WHERE podcast_listens.listener_key = EXCLUDED.listener_key
+        "#,
+    )
+    .bind(&listener_key)
+    .bind(&session_ids)
+    .bind(&episode_ids)
+    .bind(&positions)
+    .bind(&completed)
+    .bind(&occurred_at)
+    .bind(COMPLETION_THRESHOLD)
+    .execute(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to record podcast listens: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?
+    .rows_affected();
+
+    Ok(Json(json!({
+        "success": true,
+        "data": {
+            "accepted": pings.len(),
+            "sessions": accepted,
+            "skipped": payload.pings.len() - pings.len()
+        }
+    })))
+}
+
+fn completion_rate(completions: i64, plays: i64) -> f64 {
+    if plays > 0 {
+        (completions as f64 / plays as f64 * 10000.0).round() / 10000.0
+    } else {
+        0.0
+    }
+}
+
+// Listening stats of a podcast over a range: plays, unique listeners and completions per
+// episode, with totals and a series per day or month. Visible to the podcast's creator only.
+pub(crate) async fn get_podcast_insights(
+    State(db): State<Database>,
+    Path(podcast_id): Path<Uuid>,
+    Query(range): Query<RangeQuery>,
+    claims: Claims,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    ensure_podcast_owner(&db, podcast_id, &claims.sub).await?;
+    let (interval, from, to) = range.resolve()?;
+    let step = format!("1 {}", interval.as_str());
+    let db_error = |e: sqlx::Error| {
+        tracing::error!("Failed to load listening insights of podcast {}: {}", podcast_id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    };
+
+    let episodes = sqlx::query(
+        r#"
+        SELECT
+            e.id, e.title, e.episode_number, e.duration, e.published_at,
+            COUNT(l.session_id) AS plays,
+            COUNT(DISTINCT l.listener_key) AS listeners,
+            COUNT(l.session_id) FILTER (WHERE l.completed) AS completions,
+            AVG(CASE WHEN l.completed THEN 1.0
+                     WHEN e.duration > 0 THEN LEAST(l.max_position / e.duration, 1.0)
+                END)::FLOAT8 AS average_listened
+        FROM podcast_episodes e
+        LEFT JOIN podcast_listens l
+            ON l.episode_id = e.id
+           AND l.started_at >= $2::DATE::TIMESTAMP AT TIME ZONE 'UTC'
+           AND l.started_at < ($3::DATE + 1)::TIMESTAMP AT TIME ZONE 'UTC'
+        WHERE e.podcast_id = $1
+        GROUP BY e.id
+        ORDER BY plays DESC, COALESCE(e.published_at, e.created_at) DESC
+        "#,
+    )
+    .bind(podcast_id)
+    .bind(from)
+    .bind(to)
+    .fetch_all(&db.pool)
+    .await
+    .map_err(db_error)?;
+
+    let totals = sqlx::query_as::<_, (i64, i64, i64)>(
+        r#"
+        SELECT COUNT(*), COUNT(DISTINCT l.listener_key), COUNT(*) FILTER (WHERE l.completed)
+        FROM podcast_listens l
+        JOIN podcast_episodes e ON e.id = l.episode_id
+        WHERE e.podcast_id = $1
+          AND l.started_at >= $2::DATE::TIMESTAMP AT TIME ZONE 'UTC'
+          AND l.started_at < ($3::DATE + 1)::TIMESTAMP AT TIME ZONE 'UTC'
+        "#,
+    )
+    .bind(podcast_id)
+    .bind(from)
+    .bind(to)
+    .fetch_one(&db.pool)
+    .await
+    .map_err(db_error)?;
+
+    let series = sqlx::query_as::<_, (NaiveDate, i64, i64)>(
+        r#"
+        SELECT p.period::DATE, COUNT(l.session_id), COUNT(DISTINCT l.listener_key)
+        FROM generate_series($2::DATE, $3::DATE, $4::INTERVAL) AS p(period)
+        LEFT JOIN podcast_listens l
+            ON l.episode_id IN (SELECT id FROM podcast_episodes WHERE podcast_id = $1)
+           AND l.started_at >= GREATEST(p.period, $5::DATE)::TIMESTAMP AT TIME ZONE 'UTC'
+           AND l.started_at < LEAST(p.period + $4::INTERVAL, $3::DATE + 1)::TIMESTAMP AT TIME ZONE 'UTC'
+        GROUP BY p.period
+        ORDER BY p.period
+        "#,
+    )
+    .bind(podcast_id)
+    .bind(interval.truncate(from))
+    .bind(to)
+    .bind(&step)
+    .bind(from)
+    .fetch_all(&db.pool)
+    .await
+    .map_err(db_error)?;
+
+    let (plays, listeners, completions) = totals;
+    let episodes = episodes
+        .iter()
+        .map(|row| {
+            let plays: i64 = row.get("plays");
+            let completions: i64 = row.get("completions");
+            json!({
+                "id": row.get::<Uuid, _>("id"),
+                "title": row.get::<String, _>("title"),
+                "episodeNumber": row.try_get::<Option<i32>, _>("episode_number").unwrap_or(None),
+                "duration": row.try_get::<Option<i32>, _>("duration").unwrap_or(None),
+                "publishedAt": row.try_get::<Option<DateTime<Utc>>, _>("published_at").unwrap_or(None),
+                "plays": plays,
+                "listeners": row.get::<i64, _>("listeners"),
+                "completions": completions,
+                "completionRate": completion_rate(completions, plays),
+                "averageListened": row.get::<Option<f64>, _>("average_listened").unwrap_or(0.0),
+            })
+        })
+        .collect::<Vec<_>>();
+
+    Ok(Json(json!({
+        "success": true,
+        "data": {
+            "podcastId": podcast_id,
+            "interval": interval.as_str(),
+            "from": from,
+            "to": to,
+            "plays": plays,
+            "listeners": listeners,
+            "completions": completions,
+            "completionRate": completion_rate(completions, plays),
+            "episodes": episodes,
+            "series": series
+                .iter()
+                .map(|(period, plays, listeners)| json!({
+                    "period": period,
+                    "plays": plays,
+                    "listeners": listeners,
+                }))
+                .collect::<Vec<_>>(),
+        }
+    })))
+}
+
+// One episode's listening stats over a range, with its drop-off curve: the share of plays
+// still listening at every few percent of the episode.
+pub(crate) async fn get_episode_insights(
+    State(db): State<Database>,
+    Path((podcast_id, episode_id)): Path<(Uuid, Uuid)>,
+    Query(range): Query<RangeQuery>,
+    claims: Claims,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    ensure_podcast_owner(&db, podcast_id, &claims.sub).await?;
+    let (interval, from, to) = range.resolve()?;
+    let step = format!("1 {}", interval.as_str());
+    let db_error = |e: sqlx::Error| {
+        tracing::error!("Failed to load listening insights of episode {}: {}", episode_id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    };
+
+    let duration = sqlx::query_scalar::<_, Option<i32>>(
+        "SELECT duration FROM podcast_episodes WHERE id = $1 AND podcast_id = $2",
+    )
+    .bind(episode_id)
+    .bind(podcast_id)
+    .fetch_optional(&db.pool)
+    .await
+    .map_err(db_error)?
+    .ok_or(StatusCode::NOT_FOUND)?;
+
+    let (plays, listeners, completions) = sqlx::query_as::<_, (i64, i64, i64)>(
+        r#"
+        SELECT COUNT(*), COUNT(DISTINCT listener_key), COUNT(*) FILTER (WHERE completed)
+        FROM podcast_listens
+        WHERE episode_id = $1
+          AND started_at >= $2::DATE::TIMESTAMP AT TIME ZONE 'UTC'
+          AND started_at < ($3::DATE + 1)::TIMESTAMP AT TIME ZONE 'UTC'
+        "#,
+    )
+    .bind(episode_id)
+    .bind(from)
+    .bind(to)
+    .fetch_one(&db.pool)
+    .await
+    .map_err(db_error)?;
+
+    // Episodes added by URL may have no duration; the furthest anyone got stands in for it
+    let drop_off = sqlx::query_as::<_, (i32, i64)>(
+        r#"
+        WITH listens AS (
+            SELECT max_position, completed
+            FROM podcast_listens
+            WHERE episode_id = $1
+              AND started_at >= $2::DATE::TIMESTAMP AT TIME ZONE 'UTC'
+              AND started_at < ($3::DATE + 1)::TIMESTAMP AT TIME ZONE 'UTC'
+        ),
+        length AS (
+            SELECT COALESCE(NULLIF($4::INT, 0)::FLOAT8, (SELECT MAX(max_position) FROM listens)) AS seconds
+        )
+        SELECT b.percent,
+               COUNT(l.max_position) FILTER (
+                   WHERE l.completed OR l.max_position >= length.seconds * b.percent / 100.0
+               )
+        FROM generate_series(0, 100, $5) AS b(percent)
+        CROSS JOIN length
+        LEFT JOIN listens l ON TRUE
+        GROUP BY b.percent
+        ORDER BY b.percent
+        "#,
+    )
+    .bind(episode_id)
+    .bind(from)
+    .bind(to)
+    .bind(duration)
+    .bind(DROP_OFF_STEP_PERCENT)
+    .fetch_all(&db.pool)
+    .await
+    .map_err(db_error)?;
+
+    let series = sqlx::query_as::<_, (NaiveDate, i64, i64, i64)>(
+        r#"
+        SELECT p.period::DATE, COUNT(l.session_id), COUNT(DISTINCT l.listener_key),
+               COUNT(l.session_id) FILTER (WHERE l.completed)
+        FROM generate_series($2::DATE, $3::DATE, $4::INTERVAL) AS p(period)
+        LEFT JOIN podcast_listens l
+            ON l.episode_id = $1
+           AND l.started_at >= GREATEST(p.period, $5::DATE)::TIMESTAMP AT TIME ZONE 'UTC'
+           AND l.started_at < LEAST(p.period + $4::INTERVAL, $3::DATE + 1)::TIMESTAMP AT TIME ZONE 'UTC'
+        GROUP BY p.period
+        ORDER BY p.period
+        "#,
+    )
+    .bind(episode_id)
+    .bind(interval.truncate(from))
+    .bind(to)
+    .bind(&step)
+    .bind(from)
+    .fetch_all(&db.pool)
+    .await
+    .map_err(db_error)?;
+
+    Ok(Json(json!({
+        "success": true,
+        "data": {
+            "episodeId": episode_id,
+            "interval": interval.as_str(),
+            "from": from,
+            "to": to,
+            "duration": duration,
+            "plays": plays,
+            "listeners": listeners,
+            "completions": completions,
+            "completionRate": completion_rate(completions, plays),
+            "dropOff": drop_off
+                .iter()
+                .map(|(percent, listening)| json!({
+                    "percent": percent,
+                    "listening": listening,
+                    "share": completion_rate(*listening, plays),
+                }))
+                .collect::<Vec<_>>(),
+            "series": series
+                .iter()
+                .map(|(period, plays, listeners, completions)| json!({
+                    "period": period,
+                    "plays": plays,
+                    "listeners": listeners,
+                    "completions": completions,
+                }))
+                .collect::<Vec<_>>(),
+        }
+    })))
+}
@@ -0,0 +1,238 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::Json,
+};
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use serde_json::json;
+use sqlx::Row;
+use uuid::Uuid;
+
+use crate::{auth::Claims, database::Database, jobs, routes::podcasts::ensure_podcast_owner};
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ImportPodcastRequest {
+    pub feed_url: String,
+    /// Check the feed for new episodes from time to time. Defaults to true.
+    pub auto_sync: Option<bool>,
+    /// Copy episode audio into our storage instead of playing it from the old host.
+    #[serde(default)]
+    pub mirror_audio: bool,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct FeedSyncSettings {
+    pub enabled: Option<bool>,
+    /// Applies to episodes synced from now on.
+    pub mirror_audio: Option<bool>,
+}
+
+async fn queue_import(db: &Database, import_id: Uuid) -> Result<(), StatusCode> {
+    match &db.amqp {
+        Some(amqp) => {
+            if let Err(e) = amqp.send_podcast_import_job(import_id.to_string()).await {
+                tracing::error!("Failed to queue podcast import {}: {:?}", import_id, e);
+                let _ = sqlx::query(
+                    "UPDATE podcast_imports SET status = 'FAILED', error = 'Could not queue import', updated_at = NOW() WHERE id = $1",
+                )
+                .bind(import_id)
+                .execute(&db.pool)
+                .await;
+                return Err(StatusCode::SERVICE_UNAVAILABLE);
+            }
+        }
+        None => {
+            // Without CloudAMQP the import still runs in the background of this process.
+            let db = db.clone();
+            tokio::spawn(async move {
+                let _ = jobs::podcast_import::run_import(&db, &import_id.to_string()).await;
+            });
+        }
+    }
+    Ok(())
+}
+
+// Queue an import of an existing show from its RSS feed. The show is created from the feed's
+// channel; importing the same feed again adds only episodes that are new.
+pub(crate) async fn import_podcast(
+    State(db): State<Database>,
+    claims: Claims,
+    Json(payload): Json<ImportPodcastRequest>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let is_creator = sqlx::query_scalar::<_, bool>("SELECT is_creator FROM users WHERE id = $1")
+        .bind(&claims.sub)
+        .fetch_one(&db.pool)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    if !is_creator {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    let feed_url = payload.feed_url.trim();
+    if !feed_url.starts_with("http://") && !feed_url.starts_with("https://") {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let import_id = sqlx::query_scalar::<_, Uuid>(
+        r#"
+        INSERT INTO podcast_imports (user_id, feed_url, auto_sync, mirror_audio)
+        VALUES ($1, $2, $3, $4)
+        RETURNING id
+        "#,
+    )
+    .bind(&claims.sub)
+    .bind(feed_url)
+    .bind(payload.auto_sync.unwrap_or(true))
+    .bind(payload.mirror_audio)
+    .fetch_one(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to create podcast import for {}: {}", claims.sub, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    queue_import(&db, import_id).await?;
+
+    Ok(Json(json!({
+        "success": true,
+        "data": {
+            "id": import_id,
+            "feedUrl": feed_url,
+            "status": "QUEUED"
+        }
+    })))
+}
+
+// Progress of one of the creator's podcast imports or syncs
+pub(crate) async fn get_podcast_import(
+    State(db): State<Database>,
+    Path(import_id): Path<Uuid>,
+    claims: Claims,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let row = sqlx::query(
+        r#"
+        SELECT id, podcast_id, feed_url, auto_sync, mirror_audio, status, total_items,
+               processed_items, imported_episodes, skipped_items, error, created_at, completed_at
+        FROM podcast_imports
+        WHERE id = $1 AND user_id = $2
+        "#,
+    )
+    .bind(import_id)
+    .bind(&claims.sub)
+    .fetch_optional(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to load podcast import {}: {}", import_id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?
+    .ok_or(StatusCode::NOT_FOUND)?;
+
+    let total_items: i32 = row.get("total_items");
+    let processed_items: i32 = row.get("processed_items");
+    let status: String = row.get("status");
+    let progress = if status == "COMPLETED" {
+        100.0
+    } else if total_items > 0 {
+        (processed_items as f64 / total_items as f64 * 1000.0).round() / 10.0
+    } else {
+        0.0
+    };
+
+    Ok(Json(json!({
+        "success": true,
+        "data": {
+            "id": row.get::<Uuid, _>("id"),
+            "podcastId": row.get::<Option<Uuid>, _>("podcast_id"),
+            "feedUrl": row.get::<String, _>("feed_url"),
+            "autoSync": row.get::<bool, _>("auto_sync"),
+            "mirrorAudio": row.get::<bool, _>("mirror_audio"),
+            "status": status,
+            "totalItems": total_items,
+            "processedItems": processed_items,
+            "importedEpisodes": row.get::<i32, _>("imported_episodes"),
+            "skippedItems": row.get::<i32, _>("skipped_items"),
+            "progress": progress,
+            "error": row.get::<Option<String>, _>("error"),
+            "createdAt": row.get::<DateTime<Utc>, _>("created_at"),
+            "completedAt": row.get::<Option<DateTime<Utc>>, _>("completed_at"),
+        }
+    })))
+}
+
+/// Fetch an imported podcast's feed now instead of waiting for the periodic sync.
+pub(crate) async fn sync_podcast_now(
+    State(db): State<Database>,
+    Path(podcast_id): Path<Uuid>,
+    claims: Claims,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    ensure_podcast_owner(&db, podcast_id, &claims.sub).await?;
+    let import_id = sqlx::query_scalar::<_, Uuid>(
+        r#"
+        INSERT INTO podcast_imports (user_id, podcast_id, feed_url, auto_sync, mirror_audio)
+        SELECT creator_id, id, external_feed_url, feed_sync_enabled, mirror_audio
+        FROM podcasts
+        WHERE id = $1 AND external_feed_url IS NOT NULL
+        RETURNING id
+        "#,
+    )
+    .bind(podcast_id)
+    .fetch_optional(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to create sync of podcast {}: {}", podcast_id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?
+    .ok_or(StatusCode::BAD_REQUEST)?;
+    queue_import(&db, import_id).await?;
+
+    Ok(Json(json!({
+        "success": true,
+        "data": {
+            "id": import_id,
+            "podcastId": podcast_id,
+            "status": "QUEUED"
+        }
+    })))
+}
+
+pub(crate) async fn update_feed_sync(
+    State(db): State<Database>,
+    Path(podcast_id): Path<Uuid>,
+    claims: Claims,
+    Json(payload): Json<FeedSyncSettings>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    ensure_podcast_owner(&db, podcast_id, &claims.sub).await?;
+    let row = sqlx::query(
+        r#"
+        UPDATE podcasts
+        SET feed_sync_enabled = COALESCE($2, feed_sync_enabled),
+            mirror_audio = COALESCE($3, mirror_audio),
+            updated_at = NOW()
+        WHERE id = $1 AND external_feed_url IS NOT NULL
+        RETURNING external_feed_url, feed_sync_enabled, mirror_audio, feed_synced_at, feed_sync_error
+        "#,
+    )
+    .bind(podcast_id)
+    .bind(payload.enabled)
+    .bind(payload.mirror_audio)
+    .fetch_optional(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to update feed sync of podcast {}: {}", podcast_id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?
+    .ok_or(StatusCode::BAD_REQUEST)?;
+
+    Ok(Json(json!({
+        "success": true,
+        "data": {
+            "podcastId": podcast_id,
+            "feedUrl": row.get::<Option<String>, _>("external_feed_url"),
+            "enabled": row.get::<bool, _>("feed_sync_enabled"),
+            "mirrorAudio": row.get::<bool, _>("mirror_audio"),
+            "lastSyncedAt": row.get::<Option<DateTime<Utc>>, _>("feed_synced_at"),
+            "lastError": row.get::<Option<String>, _>("feed_sync_error"),
+        }
+    })))
+}
@@ -0,0 +1,299 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::{header, StatusCode},
+    response::{IntoResponse, Json, Response},
+};
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use serde_json::json;
+use uuid::Uuid;
+
+use crate::{
+    auth::Claims,
+    config::TranscriptionConfig,
+    database::Database,
+    jobs,
+    middleware::optional_auth::MaybeClaims,
+    routes::podcasts::ensure_podcast_owner,
+    transcription::{self, Segment},
+};
+
+/// Largest transcript file a creator can upload.
+const MAX_TRANSCRIPT_BYTES: usize = 2 * 1024 * 1024;
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct TranscriptQuery {
+    /// `json` (the default), `vtt`, `srt` or `text`.
+    pub format: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct SaveTranscriptRequest {
+    /// Ask the speech-to-text provider for a transcript instead of uploading one.
+    #[serde(default)]
+    pub auto: bool,
+    pub content: Option<String>,
+    /// `vtt`, `srt` or `text`; detected from the content when left out.
+    pub format: Option<String>,
+    pub language: Option<String>,
+}
+
+#[derive(Debug, sqlx::FromRow)]
+struct TranscriptRow {
+    source: String,
+    status: String,
+    provider: Option<String>,
+    language: Option<String>,
+    text: Option<String>,
+    segments: Option<serde_json::Value>,
+    error: Option<String>,
+    updated_at: DateTime<Utc>,
+    episode_status: Option<String>,
+    creator_id: String,
+}
+
+async fn ensure_episode(db: &Database, podcast_id: Uuid, episode_id: Uuid) -> Result<(), StatusCode> {
+    sqlx::query_scalar::<_, Uuid>("SELECT id FROM podcast_episodes WHERE id = $1 AND podcast_id = $2")
+        .bind(episode_id)
+        .bind(podcast_id)
+        .fetch_optional(&db.pool)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to load episode {}: {}", episode_id, e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?
+        .map(|_| ())
+        .ok_or(StatusCode::NOT_FOUND)
+}
+
+/// An episode's transcript. Listeners get ready transcripts of published episodes; the
+/// podcast's creator also sees pending and failed ones.
+pub(crate) async fn get_transcript(
+    State(db): State<Database>,
+    Path((podcast_id, episode_id)): Path<(Uuid, Uuid)>,
+    Query(query): Query<TranscriptQuery>,
+    MaybeClaims(claims): MaybeClaims,
+) -> Result<Response, StatusCode> {
+    let transcript = sqlx::query_as::<_, TranscriptRow>(
+        r#"
+        SELECT t.source, t.status, t.provider, t.language, t.text, t.segments, t.error, t.updated_at,
+               e.status AS episode_status, p.creator_id
+        FROM podcast_transcripts t
+        JOIN podcast_episodes e ON e.id = t.episode_id
+        JOIN podcasts p ON p.id = e.podcast_id
+        WHERE t.episode_id = $1 AND e.podcast_id = $2
+        "#,
+    )
+    .bind(episode_id)
+    .bind(podcast_id)
+    .fetch_optional(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to load transcript of episode {}: {}", episode_id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?
+    .ok_or(StatusCode::NOT_FOUND)?;
+
+    let is_owner = claims.is_some_and(|claims| claims.sub == transcript.creator_id);
+    let is_public = transcript.status == "READY"
+        && transcript.episode_status.as_deref().unwrap_or("PUBLISHED") == "PUBLISHED";
+    if !is_owner && !is_public {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    let segments = transcript
+        .segments
+        .clone()
+        .and_then(|segments| serde_json::from_value::<Vec<Segment>>(segments).ok())
+        .unwrap_or_default();
+    let (content_type, body) = match query.format.as_deref().unwrap_or("json") {
+        "json" => {
+            return Ok(Json(json!({
+                "success": true,
+                "data": {
+                    "episodeId": episode_id,
+                    "source": transcript.source,
+                    "status": transcript.status,
+                    "provider": transcript.provider,
+                    "language": transcript.language,
+                    "text": transcript.text,
+                    "segments": segments,
+                    "error": if is_owner { transcript.error } else { None },
+                    "updatedAt": transcript.updated_at,
+                }
+            }))
+            .into_response())
+        }
+        _ if transcript.status != "READY" => return Err(StatusCode::NOT_FOUND),
+        "text" => ("text/plain; charset=utf-8", transcript.text.unwrap_or_default()),
+        // Plain text transcripts carry no timing to build captions from
+        "vtt" | "srt" if segments.is_empty() => return Err(StatusCode::NOT_FOUND),
+        "vtt" => ("text/vtt; charset=utf-8", transcription::render_vtt(&segments)),
+        "srt" => ("application/x-subrip; charset=utf-8", transcription::render_srt(&segments)),
+        _ => return Err(StatusCode::BAD_REQUEST),
+    };
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, content_type),
+            (header::CACHE_CONTROL, "public, max-age=300"),
+        ],
+        body,
+    )
+        .into_response())
+}
+
+/// Upload a transcript (WebVTT, SubRip or plain text) or request one from the speech-to-text
+/// provider. Either replaces the episode's current transcript.
+pub(crate) async fn save_transcript(
+    State(db): State<Database>,
+    Path((podcast_id, episode_id)): Path<(Uuid, Uuid)>,
+    claims: Claims,
+    Json(payload): Json<SaveTranscriptRequest>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    ensure_podcast_owner(&db, podcast_id, &claims.sub).await?;
+    ensure_episode(&db, podcast_id, episode_id).await?;
+    let language = payload
+        .language
+        .as_deref()
+        .map(str::trim)
+        .filter(|language| !language.is_empty());
+    let db_error = |e: sqlx::Error| {
+        tracing::error!("Failed to save transcript of episode {}: {}", episode_id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    };
+
+    if payload.auto {
+        match transcription::provider(&TranscriptionConfig::from_env()) {
+            Ok(Some(_)) => {}
+            Ok(None) => return Err(StatusCode::SERVICE_UNAVAILABLE),
+            Err(e) => {
+                tracing::error!("Transcription provider is misconfigured: {:?}", e);
+                return Err(StatusCode::SERVICE_UNAVAILABLE);
+            }
+        }
+        sqlx::query(
+            r#"
+            INSERT INTO podcast_transcripts (episode_id, source, status, language)
+            VALUES ($1, 'AUTO', 'PENDING', $2)
+            ON CONFLICT (episode_id) DO UPDATE
+            SET source = 'AUTO', status = 'PENDING', provider = NULL, language = EXCLUDED.language,
+                text = NULL, segments = NULL, error = NULL, updated_at = NOW()
+            "#,
+        )
+        .bind(episode_id)
+        .bind(language)
+        .execute(&db.pool)
+        .await
+        .map_err(db_error)?;
+        queue_transcription(&db, episode_id).await?;
+
+        return Ok(Json(json!({
+            "success": true,
+            "data": { "episodeId": episode_id, "source": "AUTO", "status": "PENDING" }
+        })));
+    }
+
+    let content = payload.content.as_deref().map(str::trim).unwrap_or_default();
+    if content.is_empty() {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    if content.len() > MAX_TRANSCRIPT_BYTES {
+        return Err(StatusCode::PAYLOAD_TOO_LARGE);
+    }
+    let format = payload
+        .format
+        .as_deref()
+        .map(|format| format.trim().to_ascii_lowercase())
+        .unwrap_or_else(|| if content.contains("-->") { "vtt" } else { "text" }.to_string());
+    let (text, segments) = match format.as_str() {
+        "vtt" | "srt" => {
+            let segments = transcription::parse_cues(content).ok_or(StatusCode::BAD_REQUEST)?;
+            let text = segments
+                .iter()
+                .map(|segment| segment.text.as_str())
+                .collect::<Vec<_>>()
+                .join(" ");
+            (text, segments)
+        }
+        "text" => (content.to_string(), Vec::new()),
+        _ => return Err(StatusCode::BAD_REQUEST),
+    };
+
+    sqlx::query(
+        r#"
+        INSERT INTO podcast_transcripts (episode_id, source, status, language, text, segments)
+        VALUES ($1, 'UPLOAD', 'READY', $2, $3, $4)
+        ON CONFLICT (episode_id) DO UPDATE
+        SET source = 'UPLOAD', status = 'READY', provider = NULL, language = EXCLUDED.language,
+            text = EXCLUDED.text, segments = EXCLUDED.segments, error = NULL, updated_at = NOW()
+        "#,
+    )
+    .bind(episode_id)
+    .bind(language)
+    .bind(&text)
+    .bind(json!(segments))
+    .execute(&db.pool)
+    .await
+    .map_err(db_error)?;
+
+    Ok(Json(json!({
+        "success": true,
+        "data": {
+            "episodeId": episode_id,
+            "source": "UPLOAD",
+            "status": "READY",
+            "language": language,
+            "segments": segments.len(),
+        }
+    })))
+}
+
+pub(crate) async fn delete_transcript(
+    State(db): State<Database>,
+    Path((podcast_id, episode_id)): Path<(Uuid, Uuid)>,
+    claims: Claims,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    ensure_podcast_owner(&db, podcast_id, &claims.sub).await?;
+    ensure_episode(&db, podcast_id, episode_id).await?;
+    sqlx::query("DELETE FROM podcast_transcripts WHERE episode_id = $1")
+        .bind(episode_id)
+        .execute(&db.pool)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to delete transcript of episode {}: {}", episode_id, e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    Ok(Json(json!({
+        "success": true,
+        "message": "Transcript deleted"
+    })))
+}
+
+// Episodes whose audio is still being processed are transcribed by the processing job instead,
+// so queueing them early is harmless.
+async fn queue_transcription(db: &Database, episode_id: Uuid) -> Result<(), StatusCode> {
+    match &db.amqp {
+        Some(amqp) => {
+            if let Err(e) = amqp.send_episode_transcript_job(episode_id.to_string()).await {
+                tracing::error!("Failed to queue transcript of episode {}: {:?}", episode_id, e);
+                let _ = sqlx::query(
+                    "UPDATE podcast_transcripts SET status = 'FAILED', error = 'Could not queue transcription' WHERE episode_id = $1",
+                )
+                .bind(episode_id)
+                .execute(&db.pool)
+                .await;
+                return Err(StatusCode::SERVICE_UNAVAILABLE);
+            }
+        }
+        None => {
+            let db = db.clone();
+            tokio::spawn(async move {
+                let _ = jobs::podcast_transcript::transcribe_episode(&db, &episode_id.to_string()).await;
+            });
+        }
+    }
+    Ok(())
+}
@@ -1,8 +1,8 @@
 use axum::{
     extract::{Path, Query, State},
-    http::StatusCode,
-    response::Json,
-    routing::{get, post},
+    http::{header, StatusCode},
+    response::{IntoResponse, Json, Response},
+    routing::{get, patch, post},
     Router,
 };
 use chrono::{DateTime, Utc};
@@ -11,7 +11,22 @@ use serde_json::json;
 use sqlx::{postgres::PgRow, Postgres, QueryBuilder, Row};
 use uuid::Uuid;
 
-use crate::{auth::Claims, database::Database};
+use crate::{
+    auth::Claims,
+    database::Database,
+    email_service::{api_link, app_link},
+    jobs,
+    middleware::optional_auth::MaybeClaims,
+    podcast_feed::{self, FeedEpisode, FeedShow, FeedTranscript},
+    routes::{
+        podcast_analytics::{get_episode_insights, get_podcast_insights, record_listens},
+        podcast_imports::{get_podcast_import, import_podcast, sync_podcast_now, update_feed_sync},
+        podcast_transcripts::{delete_transcript, get_transcript, save_transcript},
+    },
+};
+
+const MAX_CHAPTER_MARKERS: usize = 100;
+const EPISODE_STATUSES: [&str; 3] = ["DRAFT", "PUBLISHED", "ARCHIVED"];
 
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -26,7 +41,9 @@ struct PodcastQuery {
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
 struct PodcastEpisodesQuery {
+    /// Only honored for the podcast's creator.
     pub include_drafts: Option<bool>,
+    pub season: Option<i32>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -60,6 +77,73 @@ struct CreateEpisodeRequest {
     pub spotify_episode_url: Option<String>,
     #[serde(alias = "publishedAt")]
     pub published_at: Option<String>,
+    pub season_number: Option<i32>,
+    /// Keep the episode a draft until then; a future `publishedAt` does the same.
+    pub scheduled_for: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct UploadEpisodeRequest {
+    pub title: String,
+    pub description: Option<String>,
+    pub episode_number: Option<i32>,
+    /// Where the uploads API stored the audio.
+    pub audio_url: String,
+    /// Chapter starts; chapters embedded in the file are used when there are none.
+    #[serde(default)]
+    pub markers: Vec<ChapterMarker>,
+    /// Publish once processing succeeds; kept as a draft otherwise. Defaults to true.
+    pub publish: Option<bool>,
+    pub season_number: Option<i32>,
+    /// Publish at this time instead, if processing has finished by then.
+    pub scheduled_for: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct UpdateEpisodeRequest {
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub season_number: Option<i32>,
+    pub episode_number: Option<i32>,
+    /// `PUBLISHED` releases the episode now; `DRAFT` also cancels a scheduled release.
+    pub status: Option<String>,
+    pub scheduled_for: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChapterMarker {
+    /// Seconds, or a `HH:MM:SS` / `MM:SS` timestamp.
+    pub start: MarkerStart,
+    pub title: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum MarkerStart {
+    Seconds(f64),
+    Timestamp(String),
+}
+
+impl MarkerStart {
+    fn seconds(&self) -> Option<f64> {
+        match self {
+            MarkerStart::Seconds(seconds) => Some(*seconds),
+            MarkerStart::Timestamp(timestamp) => {
+                let mut seconds = 0.0;
+                let parts = timestamp.trim().split(':').collect::<Vec<_>>();
+                if parts.len() > 3 {
+                    return None;
+                }
+                for part in parts {
+                    seconds = seconds * 60.0 + part.parse::<f64>().ok()?;
+                }
+                Some(seconds)
+            }
+        }
+        .filter(|seconds| seconds.is_finite() && *seconds >= 0.0)
+    }
 }
 
 #[derive(Debug, Serialize)]
@@ -126,6 +210,7 @@ struct PodcastEpisodeResponse {
     pub id: Uuid,
     pub title: String,
     pub description: Option<String>,
+    pub season_number: Option<i32>,
     #[serde(alias = "episodeNumber")]
     pub episode_number: Option<i32>,
     pub duration: Option<i32>,
@@ -136,10 +221,19 @@ struct PodcastEpisodeResponse {
     pub spotify_episode_url: Option<String>,
     #[serde(alias = "publishedAt")]
     pub published_at: Option<DateTime<Utc>>,
+    /// When a draft is due to be published.
+    pub scheduled_for: Option<DateTime<Utc>>,
     #[serde(alias = "createdAt")]
     pub created_at: DateTime<Utc>,
     #[serde(alias = "updatedAt")]
     pub updated_at: DateTime<Utc>,
+    /// `PENDING`, `PROCESSING`, `READY` or `FAILED` for uploaded episodes; `None` for episodes
+    /// added by URL.
+    pub processing_status: Option<String>,
+    pub processing_error: Option<String>,
+    pub bitrate_kbps: Option<i32>,
+    pub loudness_lufs: Option<f64>,
+    pub chapters: Option<serde_json::Value>,
 }
 
 impl PodcastEpisodeResponse {
@@ -148,6 +242,7 @@ impl PodcastEpisodeResponse {
             id: row.get("id"),
             title: row.get("title"),
             description: row.try_get("description").unwrap_or(None),
+            season_number: row.try_get("season_number").unwrap_or(None),
             episode_number: row.try_get("episode_number").unwrap_or(None),
             duration: row.try_get("duration").unwrap_or(None),
             status: row
@@ -157,8 +252,14 @@ impl PodcastEpisodeResponse {
             audio_url: row.get("audio_url"),
             spotify_episode_url: row.try_get("spotify_episode_url").unwrap_or(None),
             published_at: row.try_get("published_at").unwrap_or(None),
+            scheduled_for: row.try_get("scheduled_for").unwrap_or(None),
             created_at: row.get("created_at"),
             updated_at: row.get("updated_at"),
+            processing_status: row.try_get("processing_status").unwrap_or(None),
+            processing_error: row.try_get("processing_error").unwrap_or(None),
+            bitrate_kbps: row.try_get("bitrate_kbps").unwrap_or(None),
+            loudness_lufs: row.try_get("loudness_lufs").unwrap_or(None),
+            chapters: row.try_get("chapters").unwrap_or(None),
         }
     }
 }
@@ -166,10 +267,34 @@ impl PodcastEpisodeResponse {
 pub fn podcast_routes() -> Router<Database> {
     Router::new()
         .route("/", get(get_podcasts).post(create_podcast))
+        .route("/listens", post(record_listens))
+        .route("/import", post(import_podcast))
+        .route("/imports/:import_id", get(get_podcast_import))
+        .route("/:podcast_id/sync", post(sync_podcast_now).put(update_feed_sync))
+        .route("/:podcast_id/insights", get(get_podcast_insights))
         .route(
             "/:podcast_id/episodes",
             get(get_podcast_episodes).post(create_podcast_episode),
         )
+        .route("/:podcast_id/seasons", get(get_podcast_seasons))
+        .route("/:podcast_id/episodes/upload", post(upload_podcast_episode))
+        .route(
+            "/:podcast_id/episodes/:episode_id",
+            patch(update_podcast_episode),
+        )
+        .route(
+            "/:podcast_id/episodes/:episode_id/process",
+            post(reprocess_podcast_episode),
+        )
+        .route(
+            "/:podcast_id/episodes/:episode_id/transcript",
+            get(get_transcript).post(save_transcript).delete(delete_transcript),
+        )
+        .route(
+            "/:podcast_id/episodes/:episode_id/insights",
+            get(get_episode_insights),
+        )
+        .route("/:podcast_id/rss", get(get_podcast_feed))
 }
 
 async fn get_podcasts(
@@ -280,12 +405,20 @@ async fn get_podcasts(
     })))
 }
 
+// Published episodes, newest season and episode first. The creator can include drafts, which
+// also lists episodes scheduled for release.
 async fn get_podcast_episodes(
     State(db): State<Database>,
     Path(podcast_id): Path<Uuid>,
     Query(params): Query<PodcastEpisodesQuery>,
+    MaybeClaims(claims): MaybeClaims,
 ) -> Result<Json<serde_json::Value>, StatusCode> {
-    let include_drafts = params.include_drafts.unwrap_or(false);
+    let include_drafts = match claims {
+        Some(claims) if params.include_drafts.unwrap_or(false) => {
+            ensure_podcast_owner(&db, podcast_id, &claims.sub).await.is_ok()
+        }
+        _ => false,
+    };
 
     let mut query_builder = QueryBuilder::<Postgres>::new(
         r#"
@@ -293,14 +426,21 @@ async fn get_podcast_episodes(
             pe.id,
             pe.title,
             pe.description,
+            pe.season_number,
             pe.episode_number,
             pe.duration,
             pe.status,
             pe.audio_url,
             pe.spotify_episode_url,
             pe.published_at,
+            pe.scheduled_for,
             pe.created_at,
-            pe.updated_at
+            pe.updated_at,
+            pe.processing_status,
+            pe.processing_error,
+            pe.bitrate_kbps,
+            pe.loudness_lufs,
+            pe.chapters
         FROM podcast_episodes pe
         WHERE pe.podcast_id = 
         "#,
@@ -311,8 +451,14 @@ async fn get_podcast_episodes(
     if !include_drafts {
         query_builder.push(" AND pe.status = 'PUBLISHED'");
     }
+    if let Some(season) = params.season {
+        query_builder.push(" AND pe.season_number = ");
+        query_builder.push_bind(season);
+    }
 
-    query_builder.push(" ORDER BY COALESCE(pe.episode_number, 0) DESC, pe.created_at DESC");
+    query_builder.push(
+        " ORDER BY COALESCE(pe.season_number, 0) DESC, COALESCE(pe.episode_number, 0) DESC, pe.created_at DESC",
+    );
 
     let rows = query_builder
         .build()
@@ -465,12 +611,19 @@ async fn create_podcast_episode(
         return Err(StatusCode::FORBIDDEN);
     }
 
-    let episode_number = payload
-        .episode_number
-        .as_ref()
-        .and_then(|value| value.trim().parse::<i32>().ok());
+    let episode_number = episode_number_in_season(
+        &db,
+        podcast_id,
+        payload.season_number,
+        payload
+            .episode_number
+            .as_ref()
+            .and_then(|value| value.trim().parse::<i32>().ok()),
+        None,
+    )
+    .await?;
 
-    let published_at = match payload.published_at.as_ref() {
+    let mut published_at = match payload.published_at.as_ref() {
         Some(raw) => Some(
             DateTime::parse_from_rfc3339(raw)
                 .map_err(|_| StatusCode::BAD_REQUEST)?
@@ -479,6 +632,20 @@ async fn create_podcast_episode(
         None => None,
     };
 
+    let mut status = episode_status(payload.status.as_deref())?.unwrap_or("PUBLISHED");
+    let now = Utc::now();
+    if payload.scheduled_for.is_some_and(|at| at <= now) {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let mut scheduled_for = payload.scheduled_for;
+    if status == "PUBLISHED" && published_at.is_some_and(|at| at > now) {
+        scheduled_for = scheduled_for.or(published_at);
+    }
+    if scheduled_for.is_some() {
+        status = "DRAFT";
+        published_at = None;
+    }
+
     let query = r#"
         INSERT INTO podcast_episodes (
             id,
@@ -491,6 +658,8 @@ async fn create_podcast_episode(
             status,
             spotify_episode_url,
             published_at,
+            season_number,
+            scheduled_for,
             created_at,
             updated_at
         )
@@ -505,6 +674,8 @@ async fn create_podcast_episode(
             $8,
             $9,
             $10,
+            $11,
+            $12,
             NOW(),
             NOW()
         )
@@ -512,12 +683,14 @@ async fn create_podcast_episode(
             id,
             title,
             description,
+            season_number,
             episode_number,
             duration,
             status,
             audio_url,
             spotify_episode_url,
             published_at,
+            scheduled_for,
             created_at,
             updated_at
     "#;
@@ -532,14 +705,11 @@ async fn create_podcast_episode(
         .bind(episode_number)
         .bind(payload.duration)
         .bind(&payload.audio_url)
-        .bind(
-            payload
-                .status
-                .clone()
-                .unwrap_or_else(|| "PUBLISHED".to_string()),
-        )
+        .bind(status)
         .bind(payload.spotify_episode_url.clone())
         .bind(published_at)
+        .bind(payload.season_number)
+        .bind(scheduled_for)
         .fetch_one(&db.pool)
         .await
         .map_err(|e| {
@@ -554,3 +724,545 @@ async fn create_podcast_episode(
         }
     })))
 }
+
+pub(crate) async fn ensure_podcast_owner(db: &Database, podcast_id: Uuid, user_id: &str) -> Result<(), StatusCode> {
+    let owner = sqlx::query_scalar::<_, String>("SELECT creator_id FROM podcasts WHERE id = $1")
+        .bind(podcast_id)
+        .fetch_optional(&db.pool)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to load podcast owner: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?
+        .ok_or(StatusCode::NOT_FOUND)?;
+    if owner != user_id {
+        return Err(StatusCode::FORBIDDEN);
+    }
+    Ok(())
+}
+
+/// Validate an episode status from a request, accepting any letter case.
+fn episode_status(status: Option<&str>) -> Result<Option<&'static str>, StatusCode> {
+    let Some(status) = status else {
+        return Ok(None);
+    };
+    let status = status.trim().to_ascii_uppercase();
+    EPISODE_STATUSES
+        .into_iter()
+        .find(|known| *known == status)
+        .map(Some)
+        .ok_or(StatusCode::BAD_REQUEST)
+}
+
+/// Check a requested episode number against the other episodes of its season, or pick the one
+/// after the season's last episode when none was requested. Episodes without a season count as
+/// one season of their own.
+async fn episode_number_in_season(
+    db: &Database,
+    podcast_id: Uuid,
+    season_number: Option<i32>,
+    requested: Option<i32>,
+    episode_id: Option<Uuid>,
+) -> Result<i32, StatusCode> {
+    if season_number.is_some_and(|season| season < 1) || requested.is_some_and(|number| number < 1) {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let db_error = |e: sqlx::Error| {
+        tracing::error!("Failed to check episode numbers of podcast {}: {}", podcast_id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    };
+
+    let Some(number) = requested else {
+        return sqlx::query_scalar::<_, i32>(
+            r#"
+            SELECT COALESCE(MAX(episode_number), 0) + 1
+            FROM podcast_episodes
+            WHERE podcast_id = $1 AND season_number IS NOT DISTINCT FROM $2
+            "#,
+        )
+        .bind(podcast_id)
+        .bind(season_number)
+        .fetch_one(&db.pool)
+        .await
+        .map_err(db_error);
+    };
+    let taken = sqlx::query_scalar::<_, bool>(
+        r#"
+        SELECT EXISTS (
+            SELECT 1 FROM podcast_episodes
+            WHERE podcast_id = $1
+              AND season_number IS NOT DISTINCT FROM $2
+              AND episode_number = $3
+              AND id IS DISTINCT FROM $4
+        )
+        "#,
+    )
+    .bind(podcast_id)
+    .bind(season_number)
+    .bind(number)
+    .bind(episode_id)
+    .fetch_one(&db.pool)
+    .await
+    .map_err(db_error)?;
+    if taken {
+        return Err(StatusCode::CONFLICT);
+    }
+    Ok(number)
+}
+
+/// Hand an uploaded episode to the media worker. Without CloudAMQP it is processed in the
+/// background of this process instead.
+async fn queue_episode_processing(db: &Database, episode_id: Uuid) -> Result<(), StatusCode> {
+    match &db.amqp {
+        Some(amqp) => {
+            if let Err(e) = amqp.send_episode_audio_job(episode_id.to_string()).await {
+                tracing::error!("Failed to queue processing of episode {}: {:?}", episode_id, e);
+                let _ = sqlx::query(
+                    "UPDATE podcast_episodes SET processing_status = 'FAILED', processing_error = 'Could not queue processing' WHERE id = $1",
+                )
+                .bind(episode_id)
+                .execute(&db.pool)
+                .await;
+                return Err(StatusCode::SERVICE_UNAVAILABLE);
+            }
+        }
+        None => {
+            let db = db.clone();
+            tokio::spawn(async move {
+                let _ = jobs::podcast_audio::process_episode_audio(&db, &episode_id.to_string()).await;
+            });
+        }
+    }
+    Ok(())
+}
+
+// Uploaded episodes start as drafts and are only published once their audio has been probed,
+// loudness-normalized and chaptered; see `jobs::podcast_audio`.
+async fn upload_podcast_episode(
+    State(db): State<Database>,
+    Path(podcast_id): Path<Uuid>,
+    claims: Claims,
+    Json(payload): Json<UploadEpisodeRequest>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    ensure_podcast_owner(&db, podcast_id, &claims.sub).await?;
+
+    let title = payload.title.trim();
+    let audio_url = payload.audio_url.trim();
+    let from_uploads = audio_url.starts_with("/uploads/")
+        || audio_url.starts_with("https://")
+        || audio_url.starts_with("http://");
+    if title.is_empty() || !from_uploads || payload.markers.len() > MAX_CHAPTER_MARKERS {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let markers = payload
+        .markers
+        .iter()
+        .map(|marker| {
+            let start = marker.start.seconds().ok_or(StatusCode::BAD_REQUEST)?;
+            Ok(json!({
+                "start": start,
+                "title": marker.title.as_deref().map(str::trim).unwrap_or_default(),
+            }))
+        })
+        .collect::<Result<Vec<_>, StatusCode>>()?;
+    if payload.scheduled_for.is_some_and(|at| at <= Utc::now()) {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let episode_number = episode_number_in_season(
+        &db,
+        podcast_id,
+        payload.season_number,
+        payload.episode_number,
+        None,
+    )
+    .await?;
+
+    let row = sqlx::query(
+        r#"
+        INSERT INTO podcast_episodes (
+            podcast_id, title, description, episode_number, audio_url, status,
+            processing_status, publish_when_ready, chapter_markers, season_number, scheduled_for
+        )
+        VALUES ($1, $2, $3, $4, $5, 'DRAFT', 'PENDING', $6, $7, $8, $9)
+        RETURNING
+            id,
+            title,
+            description,
+            season_number,
+            episode_number,
+            duration,
+            status,
+            audio_url,
+            spotify_episode_url,
+            published_at,
+            scheduled_for,
+            created_at,
+            updated_at,
+            processing_status,
+            processing_error,
+            bitrate_kbps,
+            loudness_lufs,
+            chapters
+        "#,
+    )
+    .bind(podcast_id)
+    .bind(title)
+    .bind(&payload.description)
+    .bind(episode_number)
+    .bind(audio_url)
+    // Scheduling an episode means publishing it
+    .bind(payload.publish.unwrap_or(true) || payload.scheduled_for.is_some())
+    .bind(json!(markers))
+    .bind(payload.season_number)
+    .bind(payload.scheduled_for)
+    .fetch_one(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to create uploaded podcast episode: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    let episode = PodcastEpisodeResponse::from_row(&row);
+    queue_episode_processing(&db, episode.id).await?;
+
+    Ok(Json(json!({
+        "success": true,
+        "data": {
+            "episode": episode
+        }
+    })))
+}
+
+/// Edit an episode's details, its place in the podcast's seasons, or when it is released.
+async fn update_podcast_episode(
+    State(db): State<Database>,
+    Path((podcast_id, episode_id)): Path<(Uuid, Uuid)>,
+    claims: Claims,
+    Json(payload): Json<UpdateEpisodeRequest>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    ensure_podcast_owner(&db, podcast_id, &claims.sub).await?;
+    let db_error = |e: sqlx::Error| {
+        tracing::error!("Failed to update episode {}: {}", episode_id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    };
+
+    let current = sqlx::query(
+        "SELECT season_number, episode_number, processing_status FROM podcast_episodes WHERE id = $1 AND podcast_id = $2",
+    )
+    .bind(episode_id)
+    .bind(podcast_id)
+    .fetch_optional(&db.pool)
+    .await
+    .map_err(db_error)?
+    .ok_or(StatusCode::NOT_FOUND)?;
+
+    let title = payload.title.as_deref().map(str::trim);
+    if title.is_some_and(str::is_empty) {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let status = episode_status(payload.status.as_deref())?;
+    if let Some(scheduled_for) = payload.scheduled_for {
+        if scheduled_for <= Utc::now() || status.is_some_and(|status| status != "DRAFT") {
+            return Err(StatusCode::BAD_REQUEST);
+        }
+    }
+    let processing_status: Option<String> = current.get("processing_status");
+    if status == Some("PUBLISHED") && processing_status.as_deref().unwrap_or("READY") != "READY" {
+        return Err(StatusCode::CONFLICT);
+    }
+
+    let mut season_number: Option<i32> = current.get("season_number");
+    let mut episode_number: Option<i32> = current.get("episode_number");
+    if payload.season_number.is_some() || payload.episode_number.is_some() {
+        season_number = payload.season_number.or(season_number);
+        episode_number = Some(
+            episode_number_in_season(
+                &db,
+                podcast_id,
+                season_number,
+                payload.episode_number.or(episode_number),
+                Some(episode_id),
+            )
+            .await?,
+        );
+    }
+
+    let row = sqlx::query(
+        r#"
+        UPDATE podcast_episodes
+        SET title = COALESCE($3, title),
+            description = COALESCE($4, description),
+            season_number = $5,
+            episode_number = $6,
+            status = CASE WHEN $8::timestamptz IS NOT NULL THEN 'DRAFT' ELSE COALESCE($7, status) END,
+            published_at = CASE
+                WHEN $8 IS NOT NULL THEN NULL
+                WHEN $7 = 'PUBLISHED' AND status <> 'PUBLISHED' THEN NOW()
+                ELSE published_at
+            END,
+            scheduled_for = CASE WHEN $8 IS NOT NULL THEN $8 WHEN $7 IS NOT NULL THEN NULL ELSE scheduled_for END,
+            updated_at = NOW()
+        WHERE id = $1 AND podcast_id = $2
+        RETURNING
+            id,
+            title,
+            description,
+            season_number,
+            episode_number,
+            duration,
+            status,
+            audio_url,
+            spotify_episode_url,
+            published_at,
+            scheduled_for,
+            created_at,
+            updated_at,
+            processing_status,
+            processing_error,
+            bitrate_kbps,
+            loudness_lufs,
+            chapters
+        "#,
+    )
+    .bind(episode_id)
+    .bind(podcast_id)
+    .bind(title)
+    .bind(&payload.description)
+    .bind(season_number)
+    .bind(episode_number)
+    .bind(status)
+    .bind(payload.scheduled_for)
+    .fetch_one(&db.pool)
+    .await
+    .map_err(db_error)?;
+
+    Ok(Json(json!({
+        "success": true,
+        "data": {
+            "episode": PodcastEpisodeResponse::from_row(&row)
+        }
+    })))
+}
+
+// Seasons of a podcast with their published episode counts. The creator also sees drafts,
+// scheduled episodes and the number the next episode of each season would get.
+async fn get_podcast_seasons(
+    State(db): State<Database>,
+    Path(podcast_id): Path<Uuid>,
+    MaybeClaims(claims): MaybeClaims,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let is_owner = match claims {
+        Some(claims) => ensure_podcast_owner(&db, podcast_id, &claims.sub).await.is_ok(),
+        None => false,
+    };
+    let rows = sqlx::query(
+        r#"
+        SELECT season_number,
+               COUNT(*) FILTER (WHERE status = 'PUBLISHED') AS published,
+               COUNT(*) FILTER (WHERE status = 'DRAFT' AND scheduled_for IS NULL) AS drafts,
+               COUNT(*) FILTER (WHERE status = 'DRAFT' AND scheduled_for IS NOT NULL) AS scheduled,
+               MIN(published_at) FILTER (WHERE status = 'PUBLISHED') AS first_published_at,
+               MAX(published_at) FILTER (WHERE status = 'PUBLISHED') AS last_published_at,
+               COALESCE(MAX(episode_number), 0) + 1 AS next_episode_number
+        FROM podcast_episodes
+        WHERE podcast_id = $1
+        GROUP BY season_number
+        ORDER BY season_number NULLS FIRST
+        "#,
+    )
+    .bind(podcast_id)
+    .fetch_all(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to load seasons of podcast {}: {}", podcast_id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    let seasons = rows
+        .iter()
+        .filter(|row| is_owner || row.get::<i64, _>("published") > 0)
+        .map(|row| {
+            let mut season = json!({
+                "season": row.get::<Option<i32>, _>("season_number"),
+                "episodes": row.get::<i64, _>("published"),
+                "firstPublishedAt": row.get::<Option<DateTime<Utc>>, _>("first_published_at"),
+                "lastPublishedAt": row.get::<Option<DateTime<Utc>>, _>("last_published_at"),
+            });
+            if is_owner {
+                season["drafts"] = json!(row.get::<i64, _>("drafts"));
+                season["scheduled"] = json!(row.get::<i64, _>("scheduled"));
+                season["nextEpisodeNumber"] = json!(row.get::<i32, _>("next_episode_number"));
+            }
+            season
+        })
+        .collect::<Vec<_>>();
+
+    Ok(Json(json!({
+        "success": true,
+        "data": {
+            "seasons": seasons
+        }
+    })))
+}
+
+/// Retry processing of an uploaded episode that failed.
+async fn reprocess_podcast_episode(
+    State(db): State<Database>,
+    Path((podcast_id, episode_id)): Path<(Uuid, Uuid)>,
+    claims: Claims,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    ensure_podcast_owner(&db, podcast_id, &claims.sub).await?;
+
+    let restarted = sqlx::query(
+        r#"
+        UPDATE podcast_episodes
+        SET processing_status = 'PENDING', processing_error = NULL, updated_at = NOW()
+        WHERE id = $1 AND podcast_id = $2 AND processing_status = 'FAILED'
+        "#,
+    )
+    .bind(episode_id)
+    .bind(podcast_id)
+    .execute(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to restart processing of episode {}: {}", episode_id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?
+    .rows_affected();
+    if restarted == 0 {
+        return Err(StatusCode::CONFLICT);
+    }
+    queue_episode_processing(&db, episode_id).await?;
+
+    Ok(Json(json!({
+        "success": true,
+        "data": { "id": episode_id, "processingStatus": "PENDING" }
+    })))
+}
+
+/// RSS language codes for the language names podcasts are created with.
+fn language_code(language: &str) -> Option<String> {
+    let language = language.trim();
+    let code = match language.to_ascii_lowercase().as_str() {
+        "english" => "en",
+        "spanish" => "es",
+        "french" => "fr",
+        "german" => "de",
+        "italian" => "it",
+        "portuguese" => "pt",
+        "dutch" => "nl",
+        "turkish" => "tr",
+        "russian" => "ru",
+        "arabic" => "ar",
+        "hindi" => "hi",
+        "japanese" => "ja",
+        "korean" => "ko",
+        "chinese" => "zh",
+        // Already a code, such as `en` or `pt-BR`
+        _ if language.len() <= 5 && !language.is_empty() => return Some(language.to_string()),
+        _ => return None,
+    };
+    Some(code.to_string())
+}
+
+// Public RSS feed of a published podcast, listing its published episodes with a
+// `<podcast:transcript>` for every ready transcript.
+async fn get_podcast_feed(
+    State(db): State<Database>,
+    Path(podcast_id): Path<Uuid>,
+) -> Result<Response, StatusCode> {
+    let db_error = |e: sqlx::Error| {
+        tracing::error!("Failed to build feed of podcast {}: {}", podcast_id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    };
+    let show = sqlx::query(
+        r#"
+        SELECT p.title, p.description, p.category, p.language, p.cover_image,
+               COALESCE(u.display_name, u.username) AS author
+        FROM podcasts p
+        JOIN users u ON u.id = p.creator_id
+        WHERE p.id = $1 AND COALESCE(p.status, 'PUBLISHED') = 'PUBLISHED'
+        "#,
+    )
+    .bind(podcast_id)
+    .fetch_optional(&db.pool)
+    .await
+    .map_err(db_error)?
+    .ok_or(StatusCode::NOT_FOUND)?;
+
+    let rows = sqlx::query(
+        r#"
+        SELECT e.id, e.title, e.description, e.audio_url, e.duration, e.season_number, e.episode_number,
+               COALESCE(e.published_at, e.created_at) AS published_at,
+               t.language AS transcript_language,
+               COALESCE(jsonb_array_length(t.segments), 0) > 0 AS has_cues,
+               t.episode_id IS NOT NULL AS has_transcript
+        FROM podcast_episodes e
+        LEFT JOIN podcast_transcripts t ON t.episode_id = e.id AND t.status = 'READY'
+        WHERE e.podcast_id = $1 AND COALESCE(e.status, 'PUBLISHED') = 'PUBLISHED'
+        ORDER BY COALESCE(e.published_at, e.created_at) DESC
+        "#,
+    )
+    .bind(podcast_id)
+    .fetch_all(&db.pool)
+    .await
+    .map_err(db_error)?;
+
+    let episodes = rows
+        .iter()
+        .map(|row| {
+            let id: Uuid = row.get("id");
+            let language: Option<String> = row.try_get("transcript_language").unwrap_or(None);
+            let transcript = |format: &str, mime_type| FeedTranscript {
+                url: api_link(&format!(
+                    "/api/podcasts/{}/episodes/{}/transcript?format={}",
+                    podcast_id, id, format
+                )),
+                mime_type,
+                language: language.clone(),
+            };
+            let mut transcripts = Vec::new();
+            if row.get::<bool, _>("has_cues") {
+                transcripts.push(transcript("vtt", "text/vtt"));
+                transcripts.push(transcript("srt", "application/x-subrip"));
+            }
+            if row.get::<bool, _>("has_transcript") {
+                transcripts.push(transcript("text", "text/plain"));
+            }
+            FeedEpisode {
+                id: id.to_string(),
+                title: row.get("title"),
+                description: row.try_get("description").unwrap_or(None),
+                audio_url: row.get("audio_url"),
+                duration: row.try_get("duration").unwrap_or(None),
+                season_number: row.try_get("season_number").unwrap_or(None),
+                episode_number: row.try_get("episode_number").unwrap_or(None),
+                published_at: row.get("published_at"),
+                transcripts,
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let show = FeedShow {
+        title: show.get("title"),
+        description: show.try_get("description").unwrap_or(None),
+        link: app_link(&format!("/podcasts/{}", podcast_id)),
+        feed_url: api_link(&format!("/api/podcasts/{}/rss", podcast_id)),
+        author: show.try_get("author").unwrap_or(None),
+        category: show.try_get("category").unwrap_or(None),
+        language: show
+            .try_get::<Option<String>, _>("language")
+            .unwrap_or(None)
+            .as_deref()
+            .and_then(language_code),
+        cover_image: show.try_get("cover_image").unwrap_or(None),
+    };
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, "application/rss+xml; charset=utf-8"),
+            (header::CACHE_CONTROL, "public, max-age=300"),
+        ],
+        podcast_feed::render_feed(&show, &episodes),
+    )
+        .into_response())
+}
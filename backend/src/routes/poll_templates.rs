@@ -0,0 +1,326 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::Json,
+    routing::{get, post, put},
+    Router,
+};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use uuid::Uuid;
+
+use crate::{
+    auth::Claims,
+    database::Database,
+    routes::polls::{
+        clean_tier_ids, ensure_creator, insert_poll, prepare_questions, CreatePollRequest,
+        QuestionInput,
+    },
+};
+
+/// Longest a poll created from a template stays open.
+const MAX_TEMPLATE_DURATION_MINUTES: i32 = 60 * 24 * 90;
+
+/// A saved set of questions and settings that a creator turns into a new poll on demand.
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct PollTemplate {
+    pub id: Uuid,
+    pub creator_id: String,
+    pub name: String,
+    pub title: Option<String>,
+    pub description: Option<String>,
+    /// Validated questions, stored in the shape `POST /api/polls` accepts.
+    pub questions: serde_json::Value,
+    pub is_public: bool,
+    pub is_anonymous: bool,
+    pub is_quiz: bool,
+    pub allowed_tier_ids: Vec<String>,
+    /// How long polls created from the template stay open; `None` keeps them open until closed.
+    pub duration_minutes: Option<i32>,
+    pub use_count: i32,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// A template takes the same body as a new poll, plus a name to find it by.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SaveTemplateRequest {
+    name: String,
+    #[serde(flatten)]
+    poll: CreatePollRequest,
+}
+
+/// Per-use overrides; anything left out comes from the template.
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct InstantiateTemplateRequest {
+    title: Option<String>,
+    description: Option<String>,
+    #[serde(alias = "expiresAt")]
+    closes_at: Option<String>,
+    post_id: Option<Uuid>,
+    duration_minutes: Option<i32>,
+}
+
+pub fn template_routes() -> Router<Database> {
+    Router::new()
+        .route("/templates", get(list_templates).post(create_template))
+        .route("/templates/:template_id", put(update_template).delete(delete_template))
+        .route("/from-template/:template_id", post(create_poll_from_template))
+}
+
+async fn list_templates(
+    State(db): State<Database>,
+    claims: Claims,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let templates = sqlx::query_as::<_, PollTemplate>(
+        r#"
+        SELECT * FROM poll_templates
+        WHERE creator_id = $1
+        ORDER BY COALESCE(last_used_at, created_at) DESC
+        "#,
+    )
+    .bind(&claims.sub)
+    .fetch_all(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to list poll templates of {}: {}", claims.sub, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    Ok(Json(json!({
+        "success": true,
+        "data": templates
+    })))
+}
+
+async fn create_template(
+    State(db): State<Database>,
+    claims: Claims,
+    Json(payload): Json<SaveTemplateRequest>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    ensure_creator(&db, &claims.sub).await?;
+    let fields = TemplateFields::parse(payload)?;
+
+    let template = sqlx::query_as::<_, PollTemplate>(
+        r#"
+        INSERT INTO poll_templates (
+            creator_id, name, title, description, questions, is_public, is_anonymous,
+            allowed_tier_ids, duration_minutes, is_quiz
+        )
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+        RETURNING *
+        "#,
+    )
+    .bind(&claims.sub)
+    .bind(&fields.name)
+    .bind(&fields.title)
+    .bind(&fields.description)
+    .bind(&fields.questions)
+    .bind(fields.is_public)
+    .bind(fields.is_anonymous)
+    .bind(&fields.allowed_tier_ids)
+    .bind(fields.duration_minutes)
+    .bind(fields.is_quiz)
+    .fetch_one(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to create poll template: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    Ok(Json(json!({
+        "success": true,
+        "data": template
+    })))
+}
+
+// Saving replaces the whole template, the same way it was created
+async fn update_template(
+    State(db): State<Database>,
+    Path(template_id): Path<Uuid>,
+    claims: Claims,
+    Json(payload): Json<SaveTemplateRequest>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let fields = TemplateFields::parse(payload)?;
+
+    let template = sqlx::query_as::<_, PollTemplate>(
+        r#"
+        UPDATE poll_templates
+        SET name = $3, title = $4, description = $5, questions = $6, is_public = $7,
+            is_anonymous = $8, allowed_tier_ids = $9, duration_minutes = $10, is_quiz = $11,
+            updated_at = NOW()
+        WHERE id = $1 AND creator_id = $2
+        RETURNING *
+        "#,
+    )
+    .bind(template_id)
+    .bind(&claims.sub)
+    .bind(&fields.name)
+    .bind(&fields.title)
+    .bind(&fields.description)
+    .bind(&fields.questions)
+    .bind(fields.is_public)
+    .bind(fields.is_anonymous)
+    .bind(&fields.allowed_tier_ids)
+    .bind(fields.duration_minutes)
+    .bind(fields.is_quiz)
+    .fetch_optional(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to update poll template {}: {}", template_id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?
+    .ok_or(StatusCode::NOT_FOUND)?;
+
+    Ok(Json(json!({
+        "success": true,
+        "data": template
+    })))
+}
+
+async fn delete_template(
+    State(db): State<Database>,
+    Path(template_id): Path<Uuid>,
+    claims: Claims,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let result = sqlx::query("DELETE FROM poll_templates WHERE id = $1 AND creator_id = $2")
+        .bind(template_id)
+        .bind(&claims.sub)
+        .execute(&db.pool)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to delete poll template {}: {}", template_id, e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+    if result.rows_affected() == 0 {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    Ok(Json(json!({
+        "success": true,
+        "message": "Template deleted"
+    })))
+}
+
+// Polls made from a template are ordinary polls; later edits to the template don't touch them
+async fn create_poll_from_template(
+    State(db): State<Database>,
+    Path(template_id): Path<Uuid>,
+    claims: Claims,
+    payload: Option<Json<InstantiateTemplateRequest>>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let Json(payload) = payload.unwrap_or_default();
+    ensure_creator(&db, &claims.sub).await?;
+
+    let template = sqlx::query_as::<_, PollTemplate>(
+        "SELECT * FROM poll_templates WHERE id = $1 AND creator_id = $2",
+    )
+    .bind(template_id)
+    .bind(&claims.sub)
+    .fetch_optional(&db.pool)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    .ok_or(StatusCode::NOT_FOUND)?;
+
+    let questions: Vec<QuestionInput> =
+        serde_json::from_value(template.questions.clone()).map_err(|e| {
+            tracing::error!("Poll template {} has unreadable questions: {}", template_id, e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    // Polls on a post count their duration from when the post goes live; standalone polls
+    // from now. An explicit closing time wins over the template's duration.
+    let duration_minutes = payload.duration_minutes.or(template.duration_minutes);
+    let closes_at = match (payload.closes_at, payload.post_id, duration_minutes) {
+        (Some(closes_at), _, _) => Some(closes_at),
+        (None, None, Some(minutes)) => {
+            Some((Utc::now() + Duration::minutes(minutes as i64)).to_rfc3339())
+        }
+        (None, _, _) => None,
+    };
+    let request = CreatePollRequest {
+        title: payload.title.or(template.title),
+        description: payload.description.or(template.description),
+        questions: Some(questions),
+        is_public: Some(template.is_public),
+        is_anonymous: template.is_anonymous,
+        is_quiz: template.is_quiz,
+        allowed_tier_ids: template.allowed_tier_ids,
+        duration_minutes: duration_minutes.filter(|_| payload.post_id.is_some() && closes_at.is_none()),
+        closes_at,
+        post_id: payload.post_id,
+        ..Default::default()
+    };
+    let data = insert_poll(&db, &claims.sub, request).await?;
+
+    if let Err(e) = sqlx::query(
+        "UPDATE poll_templates SET use_count = use_count + 1, last_used_at = NOW() WHERE id = $1",
+    )
+    .bind(template_id)
+    .execute(&db.pool)
+    .await
+    {
+        tracing::warn!("Failed to record use of poll template {}: {}", template_id, e);
+    }
+
+    Ok(Json(json!({
+        "success": true,
+        "data": data
+    })))
+}
+
+struct TemplateFields {
+    name: String,
+    title: Option<String>,
+    description: Option<String>,
+    questions: serde_json::Value,
+    is_public: bool,
+    is_anonymous: bool,
+    is_quiz: bool,
+    allowed_tier_ids: Vec<String>,
+    duration_minutes: Option<i32>,
+}
+
+impl TemplateFields {
+    /// Validate a template with the same rules as a new poll.
+    fn parse(payload: SaveTemplateRequest) -> Result<Self, StatusCode> {
+        let name = payload.name.trim().to_string();
+        if name.is_empty() {
+            return Err(StatusCode::BAD_REQUEST);
+        }
+        if payload
+            .poll
+            .duration_minutes
+            .is_some_and(|minutes| !(1..=MAX_TEMPLATE_DURATION_MINUTES).contains(&minutes))
+        {
+            return Err(StatusCode::BAD_REQUEST);
+        }
+
+        let questions: Vec<QuestionInput> = prepare_questions(&payload.poll)?
+            .iter()
+            .map(|question| question.to_input())
+            .collect();
+        let trimmed = |value: Option<String>| {
+            value
+                .map(|value| value.trim().to_string())
+                .filter(|value| !value.is_empty())
+        };
+
+        Ok(Self {
+            name,
+            title: trimmed(payload.poll.title),
+            description: trimmed(payload.poll.description),
+            questions: json!(questions),
+            is_public: payload.poll.is_public.unwrap_or(true),
+            is_anonymous: payload.poll.is_anonymous,
+            is_quiz: payload.poll.is_quiz,
+            allowed_tier_ids: clean_tier_ids(payload.poll.allowed_tier_ids),
+            duration_minutes: payload.poll.duration_minutes,
+        })
+    }
+}
@@ -0,0 +1,1800 @@
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet};
+use std::convert::Infallible;
+
+use axum::{
+    extract::{Path, Query, State},
+    http::{header, StatusCode},
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        IntoResponse, Json, Response,
+    },
+    routing::{get, post, put},
+    Router,
+};
+use chrono::{DateTime, Utc};
+use futures_util::{stream, Stream, StreamExt};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sha2::Sha256;
+use sqlx::Row;
+use uuid::Uuid;
+
+use crate::{
+    auth::Claims, config::Config, database::Database, middleware::optional_auth::MaybeClaims,
+    routes::poll_templates::template_routes,
+};
+
+type HmacSha256 = Hmac<Sha256>;
+
+const MAX_QUESTIONS: usize = 20;
+const MIN_OPTIONS: usize = 2;
+const MAX_OPTIONS: usize = 10;
+/// Longest a post-attached poll may stay open after its post goes live.
+const MAX_DURATION_MINUTES: i32 = 60 * 24 * 90;
+
+#[derive(Debug, Clone, sqlx::FromRow)]
+struct Poll {
+    id: Uuid,
+    creator_id: String,
+    title: String,
+    description: Option<String>,
+    is_public: bool,
+    /// Anonymous votes keep only a keyed hash of the voter, never the user id.
+    is_anonymous: bool,
+    /// When not empty, only active subscribers on one of these tiers may vote.
+    allowed_tier_ids: Vec<String>,
+    /// Quiz polls have correct options and score every voter.
+    is_quiz: bool,
+    closes_at: Option<DateTime<Utc>>,
+    results_published_at: Option<DateTime<Utc>>,
+    /// `DRAFT` until the post it is attached to goes live, then `OPEN`.
+    status: String,
+    post_id: Option<Uuid>,
+    /// For post-attached polls, how long voting stays open once the post is published.
+    duration_minutes: Option<i32>,
+    created_at: DateTime<Utc>,
+    updated_at: DateTime<Utc>,
+    creator_name: Option<String>,
+    creator_avatar: Option<String>,
+}
+
+impl Poll {
+    fn is_draft(&self) -> bool {
+        self.status == "DRAFT"
+    }
+
+    /// A poll takes votes from the moment it opens until it closes or its results are published.
+    fn is_open(&self, now: DateTime<Utc>) -> bool {
+        !self.is_draft()
+            && self.results_published_at.is_none()
+            && self.closes_at.is_none_or(|closes_at| closes_at > now)
+    }
+}
+
+const POLL_SELECT: &str = r#"
+    SELECT p.id, p.creator_id, p.title, p.description, p.is_public, p.is_anonymous,
+           COALESCE(p.allowed_tier_ids, '{}') AS allowed_tier_ids, p.is_quiz, p.closes_at,
+           p.results_published_at, p.status, p.post_id, p.duration_minutes,
+           p.created_at, p.updated_at,
+           COALESCE(u.display_name, u.username) AS creator_name, u.avatar_url AS creator_avatar
+    FROM polls p
+    LEFT JOIN users u ON u.id = p.creator_id
+"#;
+
+/// How a question is answered. Multiple-choice ballots pick up to `max_selections` options;
+/// ranked ballots list up to that many options in order of preference.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum QuestionType {
+    Single,
+    Multiple,
+    Ranked,
+}
+
+impl QuestionType {
+    fn parse(raw: &str) -> Option<Self> {
+        match raw.trim().to_uppercase().as_str() {
+            "SINGLE" | "SINGLE_CHOICE" => Some(Self::Single),
+            "MULTIPLE" | "MULTIPLE_CHOICE" => Some(Self::Multiple),
+            "RANKED" | "RANKED_CHOICE" => Some(Self::Ranked),
+            _ => None,
+        }
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Single => "SINGLE",
+            Self::Multiple => "MULTIPLE",
+            Self::Ranked => "RANKED",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct QuestionInput {
+    pub prompt: String,
+    pub options: Vec<String>,
+    #[serde(default)]
+    pub allow_multiple: bool,
+    #[serde(alias = "type")]
+    pub question_type: Option<String>,
+    pub max_selections: Option<i32>,
+    /// Indexes into `options` of the correct answers, for quiz polls.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub correct_options: Vec<usize>,
+}
+
+pub(crate) struct NewQuestion {
+    prompt: String,
+    options: Vec<String>,
+    question_type: QuestionType,
+    max_selections: Option<i32>,
+    correct_options: Vec<usize>,
+}
+
+impl NewQuestion {
+    /// The validated question in the shape clients submit it.
+    pub(crate) fn to_input(&self) -> QuestionInput {
+        QuestionInput {
+            prompt: self.prompt.clone(),
+            options: self.options.clone(),
+            allow_multiple: self.question_type != QuestionType::Single,
+            question_type: Some(self.question_type.as_str().to_string()),
+            max_selections: self.max_selections,
+            correct_options: self.correct_options.clone(),
+        }
+    }
+}
+
+/// Either `questions` or the single-question shorthand `question` + `options`.
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct CreatePollRequest {
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub questions: Option<Vec<QuestionInput>>,
+    pub question: Option<String>,
+    pub options: Option<Vec<String>>,
+    #[serde(default)]
+    pub multiple_choice: bool,
+    #[serde(default)]
+    pub correct_options: Vec<usize>,
+    #[serde(default)]
+    pub is_quiz: bool,
+    #[serde(alias = "expiresAt")]
+    pub closes_at: Option<String>,
+    pub is_public: Option<bool>,
+    #[serde(default)]
+    pub is_anonymous: bool,
+    #[serde(default, alias = "tierIds")]
+    pub allowed_tier_ids: Vec<String>,
+    /// Attach the poll to a post; it stays a draft until a scheduled post is published.
+    pub post_id: Option<Uuid>,
+    pub duration_minutes: Option<i32>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PollSettingsRequest {
+    is_anonymous: Option<bool>,
+    #[serde(alias = "tierIds")]
+    allowed_tier_ids: Option<Vec<String>>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AnswerInput {
+    question_id: Uuid,
+    option_ids: Vec<Uuid>,
+}
+
+/// Either one answer per question, or `optionIndex` for the first question.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct VoteRequest {
+    answers: Option<Vec<AnswerInput>>,
+    option_index: Option<usize>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct VoterQuery {
+    page: Option<u32>,
+    limit: Option<u32>,
+}
+
+pub fn poll_routes() -> Router<Database> {
+    Router::new()
+        .route("/", post(create_poll))
+        .route("/creator/:creator_id", get(get_creator_polls))
+        .route("/post/:post_id", get(get_post_polls))
+        .route("/:id", get(get_poll).delete(delete_poll))
+        .route("/:id/vote", post(vote_poll))
+        .route("/:id/settings", put(update_poll_settings))
+        .route("/:id/results/publish", post(publish_results))
+        .route("/:id/results/stream", get(stream_results))
+        .route("/:id/export", get(export_responses))
+        .route("/:id/scores", get(get_quiz_scores))
+        .route("/:id/options/:option_id/voters", get(list_option_voters))
+        .merge(template_routes())
+}
+
+async fn create_poll(
+    State(db): State<Database>,
+    claims: Claims,
+    Json(payload): Json<CreatePollRequest>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    ensure_creator(&db, &claims.sub).await?;
+    let data = insert_poll(&db, &claims.sub, payload).await?;
+
+    Ok(Json(json!({
+        "success": true,
+        "data": data
+    })))
+}
+
+pub(crate) async fn ensure_creator(db: &Database, user_id: &str) -> Result<(), StatusCode> {
+    let is_creator = sqlx::query_scalar::<_, Option<bool>>("SELECT is_creator FROM users WHERE id = $1")
+        .bind(user_id)
+        .fetch_optional(&db.pool)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .flatten()
+        .unwrap_or(false);
+    if !is_creator {
+        return Err(StatusCode::FORBIDDEN);
+    }
+    Ok(())
+}
+
+/// Normalize and validate the questions of a create request.
+pub(crate) fn prepare_questions(payload: &CreatePollRequest) -> Result<Vec<NewQuestion>, StatusCode> {
+    let questions = match (&payload.questions, &payload.question, &payload.options) {
+        (Some(questions), _, _) => questions.clone(),
+        (None, Some(prompt), Some(options)) => vec![QuestionInput {
+            prompt: prompt.clone(),
+            options: options.clone(),
+            allow_multiple: payload.multiple_choice,
+            question_type: None,
+            max_selections: None,
+            correct_options: payload.correct_options.clone(),
+        }],
+        _ => return Err(StatusCode::BAD_REQUEST),
+    };
+    let questions: Vec<NewQuestion> = questions
+        .into_iter()
+        .map(|question| {
+            let question_type = match question.question_type.as_deref() {
+                Some(raw) => QuestionType::parse(raw).ok_or(StatusCode::BAD_REQUEST)?,
+                None if question.allow_multiple => QuestionType::Multiple,
+                None => QuestionType::Single,
+            };
+            if question.correct_options.iter().any(|index| *index >= question.options.len()) {
+                return Err(StatusCode::BAD_REQUEST);
+            }
+            // Blank options are dropped, so correct answers are re-indexed against what is kept.
+            let mut options = Vec::new();
+            let mut correct_options = Vec::new();
+            for (index, option) in question.options.iter().enumerate() {
+                let option = option.trim();
+                if option.is_empty() {
+                    continue;
+                }
+                if question.correct_options.contains(&index) {
+                    correct_options.push(options.len());
+                }
+                options.push(option.to_string());
+            }
+            Ok(NewQuestion {
+                prompt: question.prompt.trim().to_string(),
+                options,
+                question_type,
+                max_selections: question.max_selections,
+                correct_options,
+            })
+        })
+        .collect::<Result<_, StatusCode>>()?;
+    if questions.is_empty()
+        || questions.len() > MAX_QUESTIONS
+        || questions.iter().any(|question| {
+            question.prompt.is_empty()
+                || !(MIN_OPTIONS..=MAX_OPTIONS).contains(&question.options.len())
+                || question.max_selections.is_some_and(|max| {
+                    max < 1
+                        || max as usize > question.options.len()
+                        || (question.question_type == QuestionType::Single && max != 1)
+                })
+                || !quiz_answers_valid(question, payload.is_quiz)
+        })
+    {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    Ok(questions)
+}
+
+/// Quiz questions need at least one correct option, exactly one when single choice, and cannot
+/// be ranked; questions outside quizzes have none.
+fn quiz_answers_valid(question: &NewQuestion, is_quiz: bool) -> bool {
+    if !is_quiz {
+        return question.correct_options.is_empty();
+    }
+    match question.question_type {
+        QuestionType::Single => question.correct_options.len() == 1,
+        QuestionType::Multiple => !question.correct_options.is_empty(),
+        QuestionType::Ranked => false,
+    }
+}
+
+/// Create a poll with its questions for `creator_id` and return it as the creator sees it.
+pub(crate) async fn insert_poll(
+    db: &Database,
+    creator_id: &str,
+    payload: CreatePollRequest,
+) -> Result<serde_json::Value, StatusCode> {
+    let questions = prepare_questions(&payload)?;
+
+    let now = Utc::now();
+    let closes_at = match payload.closes_at.as_deref().filter(|raw| !raw.trim().is_empty()) {
+        Some(raw) => {
+            let closes_at = DateTime::parse_from_rfc3339(raw.trim())
+                .map_err(|_| StatusCode::BAD_REQUEST)?
+                .with_timezone(&Utc);
+            if closes_at <= now {
+                return Err(StatusCode::BAD_REQUEST);
+            }
+            Some(closes_at)
+        }
+        None => None,
+    };
+
+    // A poll on a post that is not live yet waits as a draft; the publishing worker opens it
+    // together with the post and starts the duration from there.
+    let post_live_at = match payload.post_id {
+        Some(post_id) => {
+            let post = sqlx::query(
+                r#"
+                SELECT user_id,
+                       COALESCE(published, TRUE) AS published,
+                       COALESCE(published_at, created_at) AS live_at
+                FROM posts
+                WHERE id = $1
+                "#,
+            )
+            .bind(post_id)
+            .fetch_optional(&db.pool)
+            .await
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+            .ok_or(StatusCode::NOT_FOUND)?;
+            if post.get::<String, _>("user_id") != creator_id {
+                return Err(StatusCode::FORBIDDEN);
+            }
+            let live_at: Option<DateTime<Utc>> = post.get("live_at");
+            Some(live_at.filter(|_| post.get::<bool, _>("published")))
+        }
+        None => None,
+    };
+    if payload.duration_minutes.is_some()
+        && (post_live_at.is_none()
+            || closes_at.is_some()
+            || payload
+                .duration_minutes
+                .is_some_and(|minutes| !(1..=MAX_DURATION_MINUTES).contains(&minutes)))
+    {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let is_draft = post_live_at.is_some_and(|live_at| live_at.is_none_or(|live_at| live_at > now));
+    if let (Some(Some(live_at)), Some(closes_at)) = (post_live_at, closes_at) {
+        if closes_at <= live_at {
+            return Err(StatusCode::BAD_REQUEST);
+        }
+    }
+    let closes_at = match payload.duration_minutes {
+        Some(minutes) if !is_draft => Some(now + chrono::Duration::minutes(minutes as i64)),
+        _ => closes_at,
+    };
+    // Single-question polls are titled by their question.
+    let title = payload
+        .title
+        .map(|title| title.trim().to_string())
+        .filter(|title| !title.is_empty())
+        .unwrap_or_else(|| questions[0].prompt.clone());
+
+    let mut tx = db.pool.begin().await.map_err(|e| {
+        tracing::error!("Failed to start poll transaction: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    let poll_id = sqlx::query_scalar::<_, Uuid>(
+        r#"
+        INSERT INTO polls (
+            creator_id, title, description, is_public, is_anonymous, allowed_tier_ids, closes_at,
+            status, post_id, duration_minutes, is_quiz
+        )
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
+        RETURNING id
+        "#,
+    )
+    .bind(creator_id)
+    .bind(&title)
+    .bind(payload.description.as_deref().map(str::trim).filter(|d| !d.is_empty()))
+    .bind(payload.is_public.unwrap_or(true))
+    .bind(payload.is_anonymous)
+    .bind(clean_tier_ids(payload.allowed_tier_ids))
+    .bind(closes_at)
+    .bind(if is_draft { "DRAFT" } else { "OPEN" })
+    .bind(payload.post_id)
+    .bind(payload.duration_minutes)
+    .bind(payload.is_quiz)
+    .fetch_one(&mut tx)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to create poll: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    for (position, question) in questions.iter().enumerate() {
+        // Without an explicit limit, multiple-choice and ranked ballots may use every option.
+        let max_selections = match question.question_type {
+            QuestionType::Single => 1,
+            _ => question.max_selections.unwrap_or(question.options.len() as i32),
+        };
+        let question_id = sqlx::query_scalar::<_, Uuid>(
+            r#"
+            INSERT INTO poll_questions (
+                poll_id, position, prompt, allow_multiple, question_type, max_selections
+            )
+            VALUES ($1, $2, $3, $4, $5, $6)
+            RETURNING id
+            "#,
+        )
+        .bind(poll_id)
+        .bind(position as i32)
+        .bind(&question.prompt)
+        .bind(question.question_type != QuestionType::Single)
+        .bind(question.question_type.as_str())
+        .bind(max_selections)
+        .fetch_one(&mut tx)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to create question for poll {}: {}", poll_id, e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+        for (option_position, label) in question.options.iter().enumerate() {
+            sqlx::query(
+                r#"
+                INSERT INTO poll_options (question_id, position, label, is_correct)
+                VALUES ($1, $2, $3, $4)
+                "#,
+            )
+            .bind(question_id)
+            .bind(option_position as i32)
+            .bind(label)
+            .bind(question.correct_options.contains(&option_position))
+            .execute(&mut tx)
+            .await
+            .map_err(|e| {
+                tracing::error!("Failed to create option for poll {}: {}", poll_id, e);
+                StatusCode::INTERNAL_SERVER_ERROR
+            })?;
+        }
+    }
+
+    tx.commit().await.map_err(|e| {
+        tracing::error!("Failed to commit poll {}: {}", poll_id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    let poll = load_poll(db, poll_id).await?.ok_or(StatusCode::INTERNAL_SERVER_ERROR)?;
+    let data = polls_json(db, &[poll], Some(creator_id)).await?;
+
+    Ok(data.into_iter().next().unwrap_or_default())
+}
+
+async fn get_poll(
+    State(db): State<Database>,
+    Path(poll_id): Path<Uuid>,
+    MaybeClaims(maybe_claims): MaybeClaims,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let viewer_id = maybe_claims.map(|claims| claims.sub);
+    let poll = load_poll(&db, poll_id).await?.ok_or(StatusCode::NOT_FOUND)?;
+    if !can_view(&db, &poll, viewer_id.as_deref()).await? {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    let data = polls_json(&db, &[poll], viewer_id.as_deref()).await?;
+
+    Ok(Json(json!({
+        "success": true,
+        "data": data.into_iter().next()
+    })))
+}
+
+// Private polls are only listed for the creator and their active subscribers
+async fn get_creator_polls(
+    State(db): State<Database>,
+    Path(creator_id): Path<String>,
+    MaybeClaims(maybe_claims): MaybeClaims,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let viewer_id = maybe_claims.map(|claims| claims.sub);
+    let is_owner = viewer_id.as_deref() == Some(creator_id.as_str());
+    let include_private = match viewer_id.as_deref() {
+        Some(viewer_id) => is_owner || is_subscriber(&db, viewer_id, &creator_id).await?,
+        None => false,
+    };
+
+    let polls = sqlx::query_as::<_, Poll>(&format!(
+        r#"{} WHERE p.creator_id = $1 AND (p.is_public OR $2) AND (p.status <> 'DRAFT' OR $3)
+           ORDER BY p.created_at DESC LIMIT 100"#,
+        POLL_SELECT
+    ))
+    .bind(&creator_id)
+    .bind(include_private)
+    .bind(is_owner)
+    .fetch_all(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to list polls of {}: {}", creator_id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    let data = polls_json(&db, &polls, viewer_id.as_deref()).await?;
+
+    Ok(Json(json!({
+        "success": true,
+        "data": data
+    })))
+}
+
+async fn get_post_polls(
+    State(db): State<Database>,
+    Path(post_id): Path<Uuid>,
+    MaybeClaims(maybe_claims): MaybeClaims,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let viewer_id = maybe_claims.map(|claims| claims.sub);
+    let polls = sqlx::query_as::<_, Poll>(&format!(
+        "{} WHERE p.post_id = $1 ORDER BY p.created_at ASC",
+        POLL_SELECT
+    ))
+    .bind(post_id)
+    .fetch_all(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to list polls of post {}: {}", post_id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    let mut visible = Vec::with_capacity(polls.len());
+    for poll in polls {
+        if can_view(&db, &poll, viewer_id.as_deref()).await? {
+            visible.push(poll);
+        }
+    }
+    let data = polls_json(&db, &visible, viewer_id.as_deref()).await?;
+
+    Ok(Json(json!({
+        "success": true,
+        "data": data
+    })))
+}
+
+async fn delete_poll(
+    State(db): State<Database>,
+    Path(poll_id): Path<Uuid>,
+    claims: Claims,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let result = sqlx::query("DELETE FROM polls WHERE id = $1 AND creator_id = $2")
+        .bind(poll_id)
+        .bind(&claims.sub)
+        .execute(&db.pool)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to delete poll {}: {}", poll_id, e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    if result.rows_affected() == 0 {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    Ok(Json(json!({
+        "success": true,
+        "message": "Poll deleted"
+    })))
+}
+
+// Votes are final: each question can be answered once, and only while the poll is open
+async fn vote_poll(
+    State(db): State<Database>,
+    Path(poll_id): Path<Uuid>,
+    claims: Claims,
+    Json(payload): Json<VoteRequest>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let poll = load_poll(&db, poll_id).await?.ok_or(StatusCode::NOT_FOUND)?;
+    if !can_view(&db, &poll, Some(&claims.sub)).await? {
+        return Err(StatusCode::NOT_FOUND);
+    }
+    if !poll.is_open(Utc::now()) {
+        return Err(StatusCode::FORBIDDEN);
+    }
+    if !poll.allowed_tier_ids.is_empty() && poll.creator_id != claims.sub {
+        let tiers = subscriber_tiers(&db, &claims.sub, std::slice::from_ref(&poll.creator_id)).await?;
+        if !tier_allowed(&poll, tiers.get(&poll.creator_id)) {
+            return Err(StatusCode::FORBIDDEN);
+        }
+    }
+    let voter = Voter::new(&poll, &claims.sub);
+
+    let option_rows = sqlx::query(
+        r#"
+        SELECT q.id AS question_id, q.position AS question_position, q.question_type,
+               q.max_selections, o.id AS option_id
+        FROM poll_questions q
+        JOIN poll_options o ON o.question_id = q.id
+        WHERE q.poll_id = $1
+        ORDER BY q.position, o.position
+        "#,
+    )
+    .bind(poll_id)
+    .fetch_all(&db.pool)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let mut questions: Vec<(Uuid, QuestionType, Option<i32>, Vec<Uuid>)> = Vec::new();
+    for row in &option_rows {
+        let question_id: Uuid = row.get("question_id");
+        if questions.last().map(|(id, _, _, _)| *id) != Some(question_id) {
+            let question_type = QuestionType::parse(&row.get::<String, _>("question_type"))
+                .unwrap_or(QuestionType::Single);
+            questions.push((question_id, question_type, row.get("max_selections"), Vec::new()));
+        }
+        if let Some((_, _, _, options)) = questions.last_mut() {
+            options.push(row.get("option_id"));
+        }
+    }
+
+    let answers = match (payload.answers, payload.option_index) {
+        (Some(answers), _) => answers,
+        (None, Some(index)) => {
+            let (question_id, _, _, options) = questions.first().ok_or(StatusCode::BAD_REQUEST)?;
+            vec![AnswerInput {
+                question_id: *question_id,
+                option_ids: vec![*options.get(index).ok_or(StatusCode::BAD_REQUEST)?],
+            }]
+        }
+        (None, None) => return Err(StatusCode::BAD_REQUEST),
+    };
+
+    // Ranked ballots list their options in order of preference.
+    let mut ranked_questions = HashSet::new();
+    let mut seen_questions = HashSet::new();
+    for answer in &answers {
+        let (_, question_type, max_selections, options) = questions
+            .iter()
+            .find(|(id, _, _, _)| *id == answer.question_id)
+            .ok_or(StatusCode::BAD_REQUEST)?;
+        let max_selections = match question_type {
+            QuestionType::Single => 1,
+            _ => max_selections.map_or(options.len(), |max| max.max(1) as usize),
+        };
+        let distinct: HashSet<&Uuid> = answer.option_ids.iter().collect();
+        if !seen_questions.insert(answer.question_id)
+            || distinct.is_empty()
+            || distinct.len() != answer.option_ids.len()
+            || distinct.len() > max_selections
+            || distinct.iter().any(|option_id| !options.contains(option_id))
+        {
+            return Err(StatusCode::BAD_REQUEST);
+        }
+        if *question_type == QuestionType::Ranked {
+            ranked_questions.insert(answer.question_id);
+        }
+    }
+
+    let mut tx = db.pool.begin().await.map_err(|e| {
+        tracing::error!("Failed to start vote transaction: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    // Serialize concurrent ballots from the same voter so a question cannot be answered twice.
+    sqlx::query("SELECT pg_advisory_xact_lock(hashtext($1))")
+        .bind(format!("poll:{}:{}", poll_id, voter.key()))
+        .execute(&mut tx)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let question_ids: Vec<Uuid> = answers.iter().map(|answer| answer.question_id).collect();
+    let already_answered = sqlx::query_scalar::<_, bool>(
+        r#"
+        SELECT EXISTS(
+            SELECT 1 FROM poll_votes
+            WHERE COALESCE(user_id, voter_hash) = $1 AND question_id = ANY($2)
+        )
+        "#,
+    )
+    .bind(voter.key())
+    .bind(&question_ids)
+    .fetch_one(&mut tx)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    if already_answered {
+        return Err(StatusCode::CONFLICT);
+    }
+
+    for answer in &answers {
+        let ranked = ranked_questions.contains(&answer.question_id);
+        for (index, option_id) in answer.option_ids.iter().enumerate() {
+            sqlx::query(
+                r#"
+                INSERT INTO poll_votes (poll_id, question_id, option_id, user_id, voter_hash, rank)
+                VALUES ($1, $2, $3, $4, $5, $6)
+                "#,
+            )
+            .bind(poll_id)
+            .bind(answer.question_id)
+            .bind(option_id)
+            .bind(voter.user_id)
+            .bind(voter.hash.as_deref())
+            .bind(ranked.then_some(index as i32 + 1))
+            .execute(&mut tx)
+            .await
+            .map_err(|e| {
+                tracing::error!("Failed to record vote on poll {}: {}", poll_id, e);
+                StatusCode::INTERNAL_SERVER_ERROR
+            })?;
+        }
+    }
+
+    tx.commit().await.map_err(|e| {
+        tracing::error!("Failed to commit vote on poll {}: {}", poll_id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    broadcast_results(&db, &poll).await;
+
+    let data = polls_json(&db, &[poll], Some(&claims.sub)).await?;
+
+    Ok(Json(json!({
+        "success": true,
+        "data": data.into_iter().next()
+    })))
+}
+
+// Publishing closes the poll for good and tells everyone who voted
+async fn publish_results(
+    State(db): State<Database>,
+    Path(poll_id): Path<Uuid>,
+    claims: Claims,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let published = sqlx::query_scalar::<_, Uuid>(
+        r#"
+        UPDATE polls
+        SET results_published_at = NOW(),
+            closes_at = LEAST(COALESCE(closes_at, NOW()), NOW()),
+            updated_at = NOW()
+        WHERE id = $1 AND creator_id = $2 AND results_published_at IS NULL AND status <> 'DRAFT'
+        RETURNING id
+        "#,
+    )
+    .bind(poll_id)
+    .bind(&claims.sub)
+    .fetch_optional(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to publish results of poll {}: {}", poll_id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    let poll = load_poll(&db, poll_id).await?.ok_or(StatusCode::NOT_FOUND)?;
+    if poll.creator_id != claims.sub {
+        return Err(StatusCode::FORBIDDEN);
+    }
+    if published.is_none() {
+        return Err(StatusCode::CONFLICT);
+    }
+    broadcast_results(&db, &poll).await;
+
+    // Anonymous ballots cannot be traced back to a user, so nobody is notified for them.
+    let voters = sqlx::query_scalar::<_, String>(
+        "SELECT DISTINCT user_id FROM poll_votes WHERE poll_id = $1 AND user_id IS NOT NULL",
+    )
+    .bind(poll_id)
+    .fetch_all(&db.pool)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    if let Some(amqp) = &db.amqp {
+        for voter_id in &voters {
+            if let Err(e) = amqp
+                .send_poll_results_notification(poll_id.to_string(), voter_id.clone(), poll.title.clone())
+                .await
+            {
+                tracing::warn!("Failed to notify {} about poll {} results: {}", voter_id, poll_id, e);
+            }
+        }
+    }
+
+    let data = polls_json(&db, &[poll], Some(&claims.sub)).await?;
+
+    Ok(Json(json!({
+        "success": true,
+        "data": data.into_iter().next(),
+        "notifiedVoters": voters.len()
+    })))
+}
+
+// Live tallies for overlays during streams and events. The current results are sent straight
+// away, then every vote is pushed through Redis so all API instances fan out the same updates.
+async fn stream_results(
+    State(db): State<Database>,
+    Path(poll_id): Path<Uuid>,
+    MaybeClaims(maybe_claims): MaybeClaims,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, StatusCode> {
+    let viewer_id = maybe_claims.map(|claims| claims.sub);
+    let poll = load_poll(&db, poll_id).await?.ok_or(StatusCode::NOT_FOUND)?;
+    if !can_view(&db, &poll, viewer_id.as_deref()).await? {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    let redis = db.redis.as_ref().ok_or(StatusCode::SERVICE_UNAVAILABLE)?;
+    // Subscribe before taking the snapshot so no vote falls in between
+    let updates = redis
+        .subscribe(&results_channel(poll_id))
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to subscribe to results of poll {}: {}", poll_id, e);
+            StatusCode::SERVICE_UNAVAILABLE
+        })?;
+
+    let snapshot = polls_json(&db, &[poll], None)
+        .await?
+        .into_iter()
+        .next()
+        .unwrap_or_default()
+        .to_string();
+
+    let events = stream::once(async move { snapshot })
+        .chain(updates)
+        .map(|payload| Ok(Event::default().event("results").data(payload)));
+
+    Ok(Sse::new(events).keep_alive(KeepAlive::default()))
+}
+
+// One row per respondent and question. Anonymous polls only ever stored voter hashes, so their
+// respondents are numbered instead of named.
+async fn export_responses(
+    State(db): State<Database>,
+    Path(poll_id): Path<Uuid>,
+    claims: Claims,
+) -> Result<Response, StatusCode> {
+    let poll = load_poll(&db, poll_id).await?.ok_or(StatusCode::NOT_FOUND)?;
+    if poll.creator_id != claims.sub {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    let rows = sqlx::query(
+        r#"
+        SELECT COALESCE(v.user_id, v.voter_hash) AS voter, v.user_id, v.question_id,
+               q.prompt, o.label, v.created_at, u.display_name, u.username
+        FROM poll_votes v
+        JOIN poll_questions q ON q.id = v.question_id
+        JOIN poll_options o ON o.id = v.option_id
+        LEFT JOIN users u ON u.id = v.user_id
+        WHERE v.poll_id = $1
+        ORDER BY MIN(v.created_at) OVER (PARTITION BY COALESCE(v.user_id, v.voter_hash)),
+                 voter, q.position, COALESCE(v.rank, 0), o.position
+        "#,
+    )
+    .bind(poll_id)
+    .fetch_all(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to export responses of poll {}: {}", poll_id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    let write_error = |e: csv::Error| {
+        tracing::error!("Failed to write poll CSV: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    };
+
+    writer
+        .write_record(["Respondent", "Name", "Username", "Question", "Answer", "Voted at"])
+        .map_err(write_error)?;
+
+    // Consecutive rows of the same respondent and question form one answer; ranked choices
+    // are already in order of preference.
+    let mut respondents = 0;
+    let mut index = 0;
+    while index < rows.len() {
+        let voter: String = rows[index].get("voter");
+        let question_id: Uuid = rows[index].get("question_id");
+        if index == 0 || rows[index - 1].get::<String, _>("voter") != voter {
+            respondents += 1;
+        }
+
+        let mut labels = Vec::new();
+        let start = index;
+        while index < rows.len()
+            && rows[index].get::<String, _>("voter") == voter
+            && rows[index].get::<Uuid, _>("question_id") == question_id
+        {
+            labels.push(rows[index].get::<String, _>("label"));
+            index += 1;
+        }
+
+        let row = &rows[start];
+        let user_id: Option<String> = row.get("user_id");
+        let (respondent, name, username) = match user_id {
+            Some(user_id) if !poll.is_anonymous => (
+                user_id,
+                row.get::<Option<String>, _>("display_name").unwrap_or_default(),
+                row.get::<Option<String>, _>("username").unwrap_or_default(),
+            ),
+            _ => (format!("Anonymous #{}", respondents), String::new(), String::new()),
+        };
+        writer
+            .write_record([
+                respondent,
+                name,
+                username,
+                row.get::<String, _>("prompt"),
+                labels.join("; "),
+                row.get::<DateTime<Utc>, _>("created_at").to_rfc3339(),
+            ])
+            .map_err(write_error)?;
+    }
+
+    let body = writer.into_inner().map_err(|e| {
+        tracing::error!("Failed to finish poll CSV: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, "text/csv; charset=utf-8".to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"poll-{}-responses.csv\"", poll_id),
+            ),
+        ],
+        body,
+    )
+        .into_response())
+}
+
+// A question counts as correct when the voter picked exactly its correct options
+async fn get_quiz_scores(
+    State(db): State<Database>,
+    Path(poll_id): Path<Uuid>,
+    claims: Claims,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let poll = load_poll(&db, poll_id).await?.ok_or(StatusCode::NOT_FOUND)?;
+    if poll.creator_id != claims.sub {
+        return Err(StatusCode::FORBIDDEN);
+    }
+    if !poll.is_quiz {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let questions = sqlx::query(
+        "SELECT id, prompt FROM poll_questions WHERE poll_id = $1 ORDER BY position",
+    )
+    .bind(poll_id)
+    .fetch_all(&db.pool)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let answers = sqlx::query(
+        r#"
+        SELECT COALESCE(v.user_id, v.voter_hash) AS voter, v.question_id,
+               BOOL_AND(o.is_correct) AND COUNT(*) = (
+                   SELECT COUNT(*) FROM poll_options c
+                   WHERE c.question_id = v.question_id AND c.is_correct
+               ) AS correct
+        FROM poll_votes v
+        JOIN poll_options o ON o.id = v.option_id
+        WHERE v.poll_id = $1
+        GROUP BY 1, 2
+        "#,
+    )
+    .bind(poll_id)
+    .fetch_all(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to score quiz {}: {}", poll_id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    let mut scores: HashMap<String, usize> = HashMap::new();
+    let mut question_results: HashMap<Uuid, (i64, i64)> = HashMap::new();
+    for row in &answers {
+        let correct = row.try_get::<Option<bool>, _>("correct").unwrap_or(None).unwrap_or(false);
+        let score = scores.entry(row.get("voter")).or_default();
+        let (answered, answered_correctly) = question_results.entry(row.get("question_id")).or_default();
+        *answered += 1;
+        if correct {
+            *score += 1;
+            *answered_correctly += 1;
+        }
+    }
+
+    let max_score = questions.len();
+    let mut distribution = vec![0i64; max_score + 1];
+    for score in scores.values() {
+        distribution[(*score).min(max_score)] += 1;
+    }
+    let respondents = scores.len();
+    let average = (respondents > 0)
+        .then(|| scores.values().sum::<usize>() as f64 / respondents as f64)
+        .map(|average| (average * 100.0).round() / 100.0);
+
+    let questions: Vec<serde_json::Value> = questions
+        .iter()
+        .map(|row| {
+            let question_id: Uuid = row.get("id");
+            let (answered, answered_correctly) =
+                question_results.get(&question_id).copied().unwrap_or_default();
+            json!({
+                "id": question_id,
+                "prompt": row.get::<String, _>("prompt"),
+                "answered": answered,
+                "answeredCorrectly": answered_correctly,
+                "correctRate": (answered > 0).then(|| answered_correctly as f64 / answered as f64),
+            })
+        })
+        .collect();
+
+    Ok(Json(json!({
+        "success": true,
+        "data": {
+            "respondents": respondents,
+            "maxScore": max_score,
+            "averageScore": average,
+            "distribution": distribution
+                .iter()
+                .enumerate()
+                .map(|(score, count)| json!({ "score": score, "count": count }))
+                .collect::<Vec<_>>(),
+            "questions": questions,
+        }
+    })))
+}
+
+// Who picked an option, so creators can follow up with them. Never available for anonymous polls.
+async fn list_option_voters(
+    State(db): State<Database>,
+    Path((poll_id, option_id)): Path<(Uuid, Uuid)>,
+    Query(params): Query<VoterQuery>,
+    claims: Claims,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let poll = load_poll(&db, poll_id).await?.ok_or(StatusCode::NOT_FOUND)?;
+    if poll.creator_id != claims.sub {
+        return Err(StatusCode::FORBIDDEN);
+    }
+    if poll.is_anonymous {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    let option = sqlx::query(
+        r#"
+        SELECT o.label, q.id AS question_id, q.prompt
+        FROM poll_options o
+        JOIN poll_questions q ON q.id = o.question_id
+        WHERE o.id = $1 AND q.poll_id = $2
+        "#,
+    )
+    .bind(option_id)
+    .bind(poll_id)
+    .fetch_optional(&db.pool)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    .ok_or(StatusCode::NOT_FOUND)?;
+
+    let page = params.page.unwrap_or(1).max(1);
+    let limit = params.limit.unwrap_or(50).clamp(1, 200);
+    let offset = ((page - 1) * limit) as i64;
+
+    let rows = sqlx::query(
+        r#"
+        SELECT v.user_id, v.rank, v.created_at, u.display_name, u.username, u.avatar_url,
+               COUNT(*) OVER() AS total_count
+        FROM poll_votes v
+        LEFT JOIN users u ON u.id = v.user_id
+        WHERE v.option_id = $1 AND v.user_id IS NOT NULL
+        ORDER BY v.created_at ASC
+        LIMIT $2 OFFSET $3
+        "#,
+    )
+    .bind(option_id)
+    .bind(limit as i64)
+    .bind(offset)
+    .fetch_all(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to list voters of poll option {}: {}", option_id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    let total: i64 = rows.first().map(|row| row.get("total_count")).unwrap_or(0);
+    let voters: Vec<serde_json::Value> = rows
+        .iter()
+        .map(|row| {
+            json!({
+                "id": row.get::<String, _>("user_id"),
+                "name": row.try_get::<Option<String>, _>("display_name").unwrap_or(None),
+                "username": row.try_get::<Option<String>, _>("username").unwrap_or(None),
+                "avatar": row.try_get::<Option<String>, _>("avatar_url").unwrap_or(None),
+                "rank": row.get::<Option<i32>, _>("rank"),
+                "votedAt": row.get::<DateTime<Utc>, _>("created_at"),
+            })
+        })
+        .collect();
+
+    Ok(Json(json!({
+        "success": true,
+        "data": {
+            "option": {
+                "id": option_id,
+                "label": option.get::<String, _>("label"),
+                "questionId": option.get::<Uuid, _>("question_id"),
+                "question": option.get::<String, _>("prompt"),
+            },
+            "voters": voters,
+        },
+        "pagination": {
+            "page": page,
+            "limit": limit,
+            "total": total,
+            "pages": ((total as f64) / (limit as f64)).ceil() as u32,
+        }
+    })))
+}
+
+// Tiers can change at any time; anonymity only before the first vote, so earlier voters keep
+// the privacy they were promised
+async fn update_poll_settings(
+    State(db): State<Database>,
+    Path(poll_id): Path<Uuid>,
+    claims: Claims,
+    Json(payload): Json<PollSettingsRequest>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let poll = load_poll(&db, poll_id).await?.ok_or(StatusCode::NOT_FOUND)?;
+    if poll.creator_id != claims.sub {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    if payload.is_anonymous.is_some_and(|is_anonymous| is_anonymous != poll.is_anonymous) {
+        let has_votes = sqlx::query_scalar::<_, bool>(
+            "SELECT EXISTS(SELECT 1 FROM poll_votes WHERE poll_id = $1)",
+        )
+        .bind(poll_id)
+        .fetch_one(&db.pool)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        if has_votes {
+            return Err(StatusCode::CONFLICT);
+        }
+    }
+
+    sqlx::query(
+        r#"
+        UPDATE polls
+        SET is_anonymous = COALESCE($2, is_anonymous),
+            allowed_tier_ids = COALESCE($3, allowed_tier_ids),
+            updated_at = NOW()
+        WHERE id = $1
+        "#,
+    )
+    .bind(poll_id)
+    .bind(payload.is_anonymous)
+    .bind(payload.allowed_tier_ids.map(clean_tier_ids))
+    .execute(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to update settings of poll {}: {}", poll_id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    let poll = load_poll(&db, poll_id).await?.ok_or(StatusCode::NOT_FOUND)?;
+    let data = polls_json(&db, &[poll], Some(&claims.sub)).await?;
+
+    Ok(Json(json!({
+        "success": true,
+        "data": data.into_iter().next()
+    })))
+}
+
+fn results_channel(poll_id: Uuid) -> String {
+    format!("poll:{}:results", poll_id)
+}
+
+/// Push the current tallies to live result streams. Only aggregate counts go out, so the same
+/// payload serves every subscriber; failures are logged and never fail the vote.
+async fn broadcast_results(db: &Database, poll: &Poll) {
+    let Some(redis) = &db.redis else {
+        return;
+    };
+
+    let payload = match polls_json(db, std::slice::from_ref(poll), None).await {
+        Ok(data) => data.into_iter().next().unwrap_or_default().to_string(),
+        Err(_) => return,
+    };
+
+    let mut redis = redis.clone();
+    if let Err(e) = redis.publish(&results_channel(poll.id), &payload).await {
+        tracing::warn!("Failed to broadcast results of poll {}: {}", poll.id, e);
+    }
+}
+
+pub(crate) fn clean_tier_ids(tier_ids: Vec<String>) -> Vec<String> {
+    let mut cleaned: Vec<String> = tier_ids
+        .into_iter()
+        .map(|tier_id| tier_id.trim().to_string())
+        .filter(|tier_id| !tier_id.is_empty())
+        .collect();
+    cleaned.sort();
+    cleaned.dedup();
+    cleaned
+}
+
+/// Who a ballot belongs to. Anonymous polls store only `hash`, which still dedupes ballots
+/// because the same user always hashes to the same value for a given poll.
+struct Voter<'a> {
+    user_id: Option<&'a str>,
+    hash: Option<String>,
+}
+
+impl<'a> Voter<'a> {
+    fn new(poll: &Poll, user_id: &'a str) -> Self {
+        if poll.is_anonymous {
+            Voter {
+                user_id: None,
+                hash: Some(anonymous_voter_hash(poll.id, user_id)),
+            }
+        } else {
+            Voter {
+                user_id: Some(user_id),
+                hash: None,
+            }
+        }
+    }
+
+    /// Matches `COALESCE(user_id, voter_hash)` on stored votes.
+    fn key(&self) -> &str {
+        self.user_id.or(self.hash.as_deref()).unwrap_or_default()
+    }
+}
+
+/// Keyed with the server secret and scoped to the poll, so the hash cannot be reversed by
+/// trying user ids or linked across polls.
+fn anonymous_voter_hash(poll_id: Uuid, user_id: &str) -> String {
+    let secret = Config::from_env()
+        .map(|config| config.jwt_secret)
+        .unwrap_or_default();
+    let mut mac =
+        HmacSha256::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(poll_id.as_bytes());
+    mac.update(user_id.as_bytes());
+    hex::encode(mac.finalize().into_bytes())
+}
+
+/// Tier of the viewer's active subscription to each of the given creators.
+async fn subscriber_tiers(
+    db: &Database,
+    user_id: &str,
+    creator_ids: &[String],
+) -> Result<HashMap<String, Option<String>>, StatusCode> {
+    let rows = sqlx::query(
+        r#"
+        SELECT creator_id, tier_id
+        FROM subscriptions
+        WHERE user_id = $1 AND UPPER(status) = 'ACTIVE' AND creator_id = ANY($2)
+        "#,
+    )
+    .bind(user_id)
+    .bind(creator_ids)
+    .fetch_all(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to load subscription tiers of {}: {}", user_id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    Ok(rows
+        .iter()
+        .map(|row| (row.get("creator_id"), row.try_get("tier_id").unwrap_or(None)))
+        .collect())
+}
+
+/// `subscription` is the viewer's subscription to the poll's creator, if any.
+fn tier_allowed(poll: &Poll, subscription: Option<&Option<String>>) -> bool {
+    poll.allowed_tier_ids.is_empty()
+        || subscription
+            .and_then(|tier_id| tier_id.as_ref())
+            .is_some_and(|tier_id| poll.allowed_tier_ids.contains(tier_id))
+}
+
+async fn load_poll(db: &Database, poll_id: Uuid) -> Result<Option<Poll>, StatusCode> {
+    sqlx::query_as::<_, Poll>(&format!("{} WHERE p.id = $1", POLL_SELECT))
+        .bind(poll_id)
+        .fetch_optional(&db.pool)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to load poll {}: {}", poll_id, e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })
+}
+
+async fn is_subscriber(db: &Database, user_id: &str, creator_id: &str) -> Result<bool, StatusCode> {
+    sqlx::query_scalar::<_, bool>(
+        r#"
+        SELECT EXISTS(
+            SELECT 1 FROM subscriptions
+            WHERE user_id = $1 AND creator_id = $2 AND UPPER(status) = 'ACTIVE'
+        )
+        "#,
+    )
+    .bind(user_id)
+    .bind(creator_id)
+    .fetch_one(&db.pool)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
+}
+
+async fn can_view(db: &Database, poll: &Poll, viewer_id: Option<&str>) -> Result<bool, StatusCode> {
+    match viewer_id {
+        Some(viewer_id) if viewer_id == poll.creator_id => Ok(true),
+        _ if poll.is_draft() => Ok(false),
+        _ if poll.is_public => Ok(true),
+        Some(viewer_id) => is_subscriber(db, viewer_id, &poll.creator_id).await,
+        None => Ok(false),
+    }
+}
+
+/// Build poll payloads with questions, tallies and the viewer's own answers. The first
+/// question is also flattened into `question`/`options`/`voteCounts` for single-question
+/// clients.
+async fn polls_json(
+    db: &Database,
+    polls: &[Poll],
+    viewer_id: Option<&str>,
+) -> Result<Vec<serde_json::Value>, StatusCode> {
+    let poll_ids: Vec<Uuid> = polls.iter().map(|poll| poll.id).collect();
+    if poll_ids.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let option_rows = sqlx::query(
+        r#"
+        SELECT q.poll_id, q.id AS question_id, q.prompt, q.allow_multiple, q.question_type,
+               q.max_selections,
+               (
+                   SELECT COUNT(DISTINCT COALESCE(v.user_id, v.voter_hash)) FROM poll_votes v
+                   WHERE v.question_id = q.id
+               )::BIGINT AS voters,
+               o.id AS option_id, o.label, o.is_correct,
+               (
+                   SELECT COUNT(*) FROM poll_votes v
+                   WHERE v.option_id = o.id AND COALESCE(v.rank, 1) = 1
+               )::BIGINT AS votes
+        FROM poll_questions q
+        JOIN poll_options o ON o.question_id = q.id
+        WHERE q.poll_id = ANY($1)
+        ORDER BY q.poll_id, q.position, o.position
+        "#,
+    )
+    .bind(&poll_ids)
+    .fetch_all(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to load poll questions: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    // The viewer's rank for each option they picked; zero outside ranked questions.
+    let viewer_votes: HashMap<Uuid, i32> = match viewer_id {
+        Some(viewer_id) => {
+            let hashes: Vec<String> = polls
+                .iter()
+                .filter(|poll| poll.is_anonymous)
+                .map(|poll| anonymous_voter_hash(poll.id, viewer_id))
+                .collect();
+            sqlx::query_as::<_, (Uuid, i32)>(
+                r#"
+                SELECT option_id, COALESCE(rank, 0) FROM poll_votes
+                WHERE poll_id = ANY($2) AND (user_id = $1 OR voter_hash = ANY($3))
+                "#,
+            )
+            .bind(viewer_id)
+            .bind(&poll_ids)
+            .bind(&hashes)
+            .fetch_all(&db.pool)
+            .await
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+            .into_iter()
+            .collect()
+        }
+        None => HashMap::new(),
+    };
+
+    let viewer_tiers = match viewer_id {
+        Some(viewer_id) if polls.iter().any(|poll| !poll.allowed_tier_ids.is_empty()) => {
+            let creator_ids: Vec<String> = polls.iter().map(|poll| poll.creator_id.clone()).collect();
+            subscriber_tiers(db, viewer_id, &creator_ids).await?
+        }
+        _ => HashMap::new(),
+    };
+
+    let quiz_polls: HashSet<Uuid> = polls.iter().filter(|poll| poll.is_quiz).map(|poll| poll.id).collect();
+    let mut questions_by_poll: HashMap<Uuid, Vec<serde_json::Value>> = HashMap::new();
+    let mut ranked_options: HashMap<Uuid, (Uuid, Vec<Uuid>)> = HashMap::new();
+    for row in &option_rows {
+        let poll_id: Uuid = row.get("poll_id");
+        let question_id: Uuid = row.get("question_id");
+        let option_id: Uuid = row.get("option_id");
+        let question_type: String = row.get("question_type");
+        let questions = questions_by_poll.entry(poll_id).or_default();
+
+        if QuestionType::parse(&question_type) == Some(QuestionType::Ranked) {
+            ranked_options
+                .entry(question_id)
+                .or_insert_with(|| (poll_id, Vec::new()))
+                .1
+                .push(option_id);
+        }
+        if questions.last().and_then(|q| q["id"].as_str()) != Some(question_id.to_string().as_str()) {
+            questions.push(json!({
+                "id": question_id,
+                "prompt": row.get::<String, _>("prompt"),
+                "type": question_type,
+                "maxSelections": row.get::<Option<i32>, _>("max_selections"),
+                "allowMultiple": row.get::<bool, _>("allow_multiple"),
+                "totalVoters": row.get::<i64, _>("voters"),
+                "options": [],
+                "userVotedOptionIds": [],
+            }));
+        }
+        let Some(question) = questions.last_mut() else {
+            continue;
+        };
+        if let Some(options) = question["options"].as_array_mut() {
+            let mut option = json!({
+                "id": option_id,
+                "label": row.get::<String, _>("label"),
+                "votes": row.get::<i64, _>("votes"),
+            });
+            if quiz_polls.contains(&poll_id) {
+                option["isCorrect"] = json!(row.get::<bool, _>("is_correct"));
+            }
+            options.push(option);
+        }
+        if viewer_votes.contains_key(&option_id) {
+            if let Some(voted) = question["userVotedOptionIds"].as_array_mut() {
+                voted.push(json!(option_id));
+            }
+        }
+    }
+
+    // Ranked questions report first preferences as `votes`, plus the instant-runoff rounds.
+    if !ranked_options.is_empty() {
+        let question_ids: Vec<Uuid> = ranked_options.keys().copied().collect();
+        let ballot_rows = sqlx::query(
+            r#"
+            SELECT question_id, COALESCE(user_id, voter_hash) AS voter, option_id
+            FROM poll_votes
+            WHERE question_id = ANY($1) AND rank IS NOT NULL
+            ORDER BY question_id, voter, rank
+            "#,
+        )
+        .bind(&question_ids)
+        .fetch_all(&db.pool)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to load ranked ballots: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+        let mut ballots: HashMap<Uuid, Vec<Vec<Uuid>>> = HashMap::new();
+        let mut current: Option<(Uuid, String)> = None;
+        for row in &ballot_rows {
+            let key = (row.get::<Uuid, _>("question_id"), row.get::<String, _>("voter"));
+            let question_ballots = ballots.entry(key.0).or_default();
+            if current.as_ref() != Some(&key) || question_ballots.is_empty() {
+                question_ballots.push(Vec::new());
+            }
+            if let Some(ballot) = question_ballots.last_mut() {
+                ballot.push(row.get("option_id"));
+            }
+            current = Some(key);
+        }
+
+        for (question_id, (poll_id, options)) in &ranked_options {
+            let Some(question) = questions_by_poll
+                .get_mut(poll_id)
+                .and_then(|questions| questions.iter_mut().find(|q| q["id"] == json!(question_id)))
+            else {
+                continue;
+            };
+            let mut ranking: Vec<&Uuid> = options
+                .iter()
+                .filter(|option_id| viewer_votes.contains_key(option_id))
+                .collect();
+            ranking.sort_by_key(|option_id| viewer_votes.get(option_id).copied().unwrap_or(0));
+            question["userVotedOptionIds"] = json!(ranking);
+            question["runoff"] = instant_runoff(
+                options,
+                ballots.get(question_id).map(Vec::as_slice).unwrap_or_default(),
+            );
+        }
+    }
+
+    let now = Utc::now();
+    Ok(polls
+        .iter()
+        .map(|poll| {
+            let mut questions = questions_by_poll.remove(&poll.id).unwrap_or_default();
+            let has_voted = questions.iter().any(|question| {
+                question["userVotedOptionIds"]
+                    .as_array()
+                    .is_some_and(|voted| !voted.is_empty())
+            });
+            let all_answered = questions.iter().all(|question| {
+                question["userVotedOptionIds"]
+                    .as_array()
+                    .is_some_and(|voted| !voted.is_empty())
+            });
+
+            // Correct answers stay hidden until the viewer has answered everything or the
+            // poll has closed; the creator always sees them.
+            let quiz = poll.is_quiz.then(|| {
+                let reveal = viewer_id == Some(poll.creator_id.as_str())
+                    || !poll.is_open(now)
+                    || all_answered;
+                let graded: Vec<Option<bool>> = questions
+                    .iter_mut()
+                    .map(|question| grade_question(question, reveal))
+                    .collect();
+                let score = graded.iter().filter(|correct| **correct == Some(true)).count();
+                json!({
+                    "answersRevealed": reveal,
+                    "score": (reveal && has_voted).then_some(score),
+                    "maxScore": questions.len(),
+                })
+            });
+
+            let first = questions.first();
+            let first_options: Vec<serde_json::Value> = first
+                .and_then(|question| question["options"].as_array().cloned())
+                .unwrap_or_default();
+            let vote_counts: serde_json::Map<String, serde_json::Value> = first_options
+                .iter()
+                .enumerate()
+                .map(|(index, option)| (index.to_string(), option["votes"].clone()))
+                .collect();
+            let user_voted_index = first_options.iter().position(|option| {
+                first
+                    .and_then(|question| question["userVotedOptionIds"].as_array())
+                    .is_some_and(|voted| voted.contains(&option["id"]))
+            });
+            let ineligible_reason = match viewer_id {
+                _ if poll.is_draft() => Some("NOT_OPEN"),
+                _ if !poll.is_open(now) => Some("CLOSED"),
+                None => Some("SIGN_IN_REQUIRED"),
+                Some(viewer_id)
+                    if viewer_id != poll.creator_id
+                        && !tier_allowed(poll, viewer_tiers.get(&poll.creator_id)) =>
+                {
+                    Some("TIER_REQUIRED")
+                }
+                Some(_) if all_answered => Some("ALREADY_VOTED"),
+                Some(_) => None,
+            };
+
+            json!({
+                "id": poll.id,
+                "title": poll.title,
+                "description": poll.description,
+                "questions": questions,
+                "question": first.map(|question| question["prompt"].clone()),
+                "options": first_options.iter().map(|option| option["label"].clone()).collect::<Vec<_>>(),
+                "voteCounts": vote_counts,
+                "totalVotes": first.map(|question| question["totalVoters"].clone()).unwrap_or(json!(0)),
+                "multipleChoice": first.and_then(|question| question["allowMultiple"].as_bool()).unwrap_or(false),
+                "hasVoted": has_voted,
+                "userVotedIndex": user_voted_index,
+                "isPublic": poll.is_public,
+                "isAnonymous": poll.is_anonymous,
+                "isQuiz": poll.is_quiz,
+                "quiz": quiz,
+                "allowedTierIds": poll.allowed_tier_ids,
+                "eligibility": {
+                    "canVote": ineligible_reason.is_none(),
+                    "reason": ineligible_reason,
+                },
+                "status": poll.status,
+                "postId": poll.post_id,
+                "durationMinutes": poll.duration_minutes,
+                "isActive": poll.is_open(now),
+                "closesAt": poll.closes_at,
+                "expiresAt": poll.closes_at,
+                "resultsPublished": poll.results_published_at.is_some(),
+                "resultsPublishedAt": poll.results_published_at,
+                "createdAt": poll.created_at,
+                "updatedAt": poll.updated_at,
+                "creator": {
+                    "id": poll.creator_id,
+                    "name": poll.creator_name,
+                    "avatar": poll.creator_avatar,
+                },
+            })
+        })
+        .collect())
+}
+
+/// Strip a quiz question's correct options unless they may be shown, and report whether the
+/// viewer's answer matched them exactly (`None` when unanswered or not revealed).
+fn grade_question(question: &mut serde_json::Value, reveal: bool) -> Option<bool> {
+    let mut correct: Vec<String> = Vec::new();
+    if let Some(options) = question["options"].as_array_mut() {
+        for option in options {
+            if option["isCorrect"].as_bool() == Some(true) {
+                correct.extend(option["id"].as_str().map(str::to_string));
+            }
+            if !reveal {
+                if let Some(option) = option.as_object_mut() {
+                    option.remove("isCorrect");
+                }
+            }
+        }
+    }
+
+    let mut voted: Vec<String> = question["userVotedOptionIds"]
+        .as_array()
+        .map(|voted| voted.iter().filter_map(|id| id.as_str().map(str::to_string)).collect())
+        .unwrap_or_default();
+    if !reveal || voted.is_empty() {
+        return None;
+    }
+    voted.sort();
+    correct.sort();
+    let answered_correctly = voted == correct;
+    question["answeredCorrectly"] = json!(answered_correctly);
+    Some(answered_correctly)
+}
+
+/// Instant-runoff count over ranked ballots. Each round a ballot counts for its highest ranked
+/// option still in the race, and the weakest option is dropped until one holds a majority of
+/// the ballots that are not exhausted. A tie for last place drops the option with fewer first
+/// preferences, then the one listed later; when every remaining option is tied the count
+/// ends without a winner.
+fn instant_runoff(options: &[Uuid], ballots: &[Vec<Uuid>]) -> serde_json::Value {
+    let mut first_preferences: HashMap<Uuid, usize> = HashMap::new();
+    for ballot in ballots {
+        if let Some(option_id) = ballot.first() {
+            *first_preferences.entry(*option_id).or_default() += 1;
+        }
+    }
+
+    let mut remaining: Vec<Uuid> = options.to_vec();
+    let mut rounds = Vec::new();
+    let mut winner = None;
+    let mut tied = Vec::new();
+    while !remaining.is_empty() && !ballots.is_empty() {
+        let mut tallies: HashMap<Uuid, usize> = remaining.iter().map(|id| (*id, 0)).collect();
+        let mut exhausted = 0;
+        for ballot in ballots {
+            match ballot.iter().find(|option_id| tallies.contains_key(option_id)) {
+                Some(option_id) => *tallies.entry(*option_id).or_default() += 1,
+                None => exhausted += 1,
+            }
+        }
+        let continuing = ballots.len() - exhausted;
+        let round_tallies: Vec<serde_json::Value> = remaining
+            .iter()
+            .map(|option_id| json!({ "optionId": option_id, "votes": tallies[option_id] }))
+            .collect();
+
+        let leader = remaining
+            .iter()
+            .copied()
+            .max_by_key(|option_id| (tallies[option_id], Reverse(position_of(options, option_id))));
+        let lowest = remaining.iter().map(|option_id| tallies[option_id]).min().unwrap_or(0);
+        let highest = leader.map(|option_id| tallies[&option_id]).unwrap_or(0);
+
+        let eliminated = if continuing > 0 && (highest * 2 > continuing || remaining.len() == 1) {
+            winner = leader;
+            None
+        } else if continuing == 0 || lowest == highest {
+            tied = remaining.clone();
+            None
+        } else {
+            remaining.iter().copied().min_by_key(|option_id| {
+                (
+                    tallies[option_id],
+                    first_preferences.get(option_id).copied().unwrap_or(0),
+                    Reverse(position_of(options, option_id)),
+                )
+            })
+        };
+
+        rounds.push(json!({
+            "round": rounds.len() + 1,
+            "tallies": round_tallies,
+            "continuingBallots": continuing,
+            "exhaustedBallots": exhausted,
+            "eliminatedOptionId": eliminated,
+        }));
+        match eliminated {
+            Some(option_id) => remaining.retain(|id| *id != option_id),
+            None => break,
+        }
+    }
+
+    json!({
+        "ballots": ballots.len(),
+        "rounds": rounds,
+        "winnerOptionId": winner,
+        "tiedOptionIds": tied,
+    })
+}
+
+fn position_of(options: &[Uuid], option_id: &Uuid) -> usize {
+    options.iter().position(|id| id == option_id).unwrap_or(usize::MAX)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options(count: usize) -> Vec<Uuid> {
+        (0..count).map(|_| Uuid::new_v4()).collect()
+    }
+
+    #[test]
+    fn majority_of_first_preferences_wins_outright() {
+        let [a, b, c] = options(3)[..] else { unreachable!() };
+        let ballots = vec![vec![a, b], vec![a, c], vec![b, a]];
+        let result = instant_runoff(&[a, b, c], &ballots);
+        assert_eq!(result["winnerOptionId"], json!(a));
+        assert_eq!(result["rounds"].as_array().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn eliminated_ballots_transfer_to_their_next_preference() {
+        let [a, b, c] = options(3)[..] else { unreachable!() };
+        let ballots = vec![
+            vec![a],
+            vec![a],
+            vec![b, c],
+            vec![c, b],
+            vec![c, b],
+        ];
+        let result = instant_runoff(&[a, b, c], &ballots);
+        // B is dropped first, and its ballot gives C three of five
+        assert_eq!(result["rounds"][0]["eliminatedOptionId"], json!(b));
+        assert_eq!(result["winnerOptionId"], json!(c));
+    }
+
+    #[test]
+    fn exhausted_ballots_do_not_count_toward_the_majority() {
+        let [a, b, c] = options(3)[..] else { unreachable!() };
+        let ballots = vec![vec![a], vec![a], vec![b], vec![c]];
+        let result = instant_runoff(&[a, b, c], &ballots);
+        let last = result["rounds"].as_array().unwrap().last().unwrap().clone();
+        assert_eq!(result["winnerOptionId"], json!(a));
+        assert_eq!(last["exhaustedBallots"], json!(1));
+        assert_eq!(last["continuingBallots"], json!(3));
+    }
+
+    #[test]
+    fn a_tie_between_every_remaining_option_has_no_winner() {
+        let [a, b] = options(2)[..] else { unreachable!() };
+        let ballots = vec![vec![a, b], vec![b, a]];
+        let result = instant_runoff(&[a, b], &ballots);
+        assert_eq!(result["winnerOptionId"], json!(null));
+        assert_eq!(result["tiedOptionIds"], json!([a, b]));
+    }
+}
@@ -1,21 +1,41 @@
 use axum::{
-    extract::{Path, Query, State},
-    http::StatusCode,
+    extract::{Multipart, Path, Query, State},
+    http::{HeaderMap, StatusCode},
     response::Json,
     routing::{delete, get, post, put},
     Router,
 };
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, Datelike, NaiveDate, Utc};
 use serde::{Deserialize, Serialize};
 use serde_json::json;
 use sqlx::Row;
+use std::collections::{BTreeMap, HashSet};
+use tracing::error;
 use uuid::Uuid;
 
-use crate::{auth::Claims, database::Database, models::CreatePostRequest};
+use crate::{
+    auth::Claims, database::Database, feed_timeline, jobs, middleware::optional_auth::MaybeClaims,
+    models::CreatePostRequest,
+    notification_service::{notify_comment, CommentActivity},
+    pagination::{parse_cursor, Cursor},
+    post_views,
+    routes::{
+        fees::{quote_platform_fee, settle_ledger_entries, LedgerSource, ProductType},
+        purchases::extract_payment_intent_id,
+        stripe::{stripe_secret, stripe_url},
+    },
+    storage,
+};
+
+const ATTACHMENT_MAX_BYTES: usize = 100 * 1024 * 1024;
+const ATTACHMENT_URL_TTL_SECONDS: i64 = 15 * 60;
+const IMPORT_MAX_BYTES: usize = 200 * 1024 * 1024;
 
 #[derive(Debug, Deserialize)]
 pub struct PostQuery {
     pub page: Option<u32>,
+    /// `nextCursor` of the previous page; takes the place of `page`.
+    pub cursor: Option<String>,
     pub limit: Option<u32>,
     pub user_id: Option<String>,
     pub current_user_id: Option<String>,
@@ -33,6 +53,14 @@ struct PostRecord {
     video_url: Option<String>,
     audio_url: Option<String>,
     is_premium: bool,
+    unlock_price: Option<f64>,
+    unlock_currency: Option<String>,
+    published: Option<bool>,
+    published_at: Option<DateTime<Utc>>,
+    audio_duration_seconds: Option<f64>,
+    audio_waveform: Option<serde_json::Value>,
+    audio_processing_status: Option<String>,
+    campaign_id: Option<Uuid>,
     created_at: DateTime<Utc>,
     updated_at: DateTime<Utc>,
     author_name: Option<String>,
@@ -66,13 +94,25 @@ struct CreatorPostResponse {
     video_url: Option<String>,
     audio_url: Option<String>,
     #[serde(default)]
+    audio_duration: Option<f64>,
+    #[serde(default)]
+    audio_waveform: Option<serde_json::Value>,
+    #[serde(default)]
+    audio_processing_status: Option<String>,
+    #[serde(default)]
     attachments: Option<serde_json::Value>,
+    #[serde(default)]
+    campaign_id: Option<Uuid>,
     is_public: bool,
     #[serde(default)]
     minimum_tier_id: Option<String>,
     like_count: i64,
     comment_count: i64,
     is_liked: bool,
+    #[serde(default)]
+    unlock_price: Option<f64>,
+    #[serde(default)]
+    unlock_currency: Option<String>,
     published: bool,
     published_at: Option<DateTime<Utc>>,
     created_at: DateTime<Utc>,
@@ -86,6 +126,9 @@ pub fn post_routes() -> Router<Database> {
         .route("/", get(get_posts).post(create_post))
         .route("/creator/:user_id", get(get_posts_by_creator))
         .route("/my-posts", get(get_my_posts))
+        .route("/calendar", get(get_content_calendar))
+        .route("/import", post(import_posts))
+        .route("/import/:import_id", get(get_post_import))
         .route("/:id", get(get_post_by_id))
         .route("/:id", put(update_post))
         .route("/:id", delete(delete_post))
@@ -93,6 +136,137 @@ pub fn post_routes() -> Router<Database> {
         .route("/:id/unlike", post(unlike_post))
         .route("/:id/comments", get(get_post_comments).post(add_post_comment))
         .route("/:id/comments/:comment_id", delete(delete_post_comment))
+        .route(
+            "/:id/attachments",
+            get(list_post_attachments).post(upload_post_attachments),
+        )
+        .route(
+            "/:id/attachments/:attachment_id",
+            delete(delete_post_attachment),
+        )
+        .route(
+            "/:id/attachments/:attachment_id/download",
+            get(get_attachment_download),
+        )
+        .route("/:id/insights", get(get_post_insights))
+        .route("/:id/unlock", post(unlock_post))
+        .route("/unlocks/confirm", post(confirm_post_unlock))
+}
+
+#[derive(Debug, Deserialize)]
+struct CalendarQuery {
+    /// Month to render, formatted as `YYYY-MM`. Defaults to the current month.
+    month: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct CalendarEntry {
+    id: Uuid,
+    #[serde(rename = "type")]
+    entry_type: &'static str,
+    title: String,
+    scheduled_for: DateTime<Utc>,
+    status: &'static str,
+    link: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ConfirmPostUnlockRequest {
+    session_id: String,
+}
+
+/// What the current viewer may see beyond public posts: their own posts,
+/// posts from creators they actively subscribe to, and individually unlocked posts.
+struct ViewerAccess {
+    viewer_id: Option<String>,
+    subscribed_creators: HashSet<String>,
+    unlocked_posts: HashSet<Uuid>,
+}
+
+impl ViewerAccess {
+    async fn load(
+        db: &Database,
+        viewer_id: Option<String>,
+        posts: &[CreatorPostResponse],
+    ) -> Result<Self, StatusCode> {
+        let mut access = ViewerAccess {
+            viewer_id,
+            subscribed_creators: HashSet::new(),
+            unlocked_posts: HashSet::new(),
+        };
+
+        let viewer_id = match access.viewer_id.clone() {
+            Some(viewer_id) => viewer_id,
+            None => return Ok(access),
+        };
+
+        let creator_ids: Vec<String> = posts
+            .iter()
+            .map(|post| post.author.id.clone())
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .collect();
+        let post_ids: Vec<Uuid> = posts.iter().map(|post| post.id).collect();
+
+        if creator_ids.is_empty() {
+            return Ok(access);
+        }
+
+        access.subscribed_creators = sqlx::query_scalar::<_, String>(
+            r#"
+            SELECT creator_id
+            FROM subscriptions
+            WHERE user_id = $1 AND UPPER(status) = 'ACTIVE' AND creator_id = ANY($2)
+            "#,
+        )
+        .bind(&viewer_id)
+        .bind(&creator_ids)
+        .fetch_all(&db.pool)
+        .await
+        .map_err(|e| {
+            error!("Failed to load subscriptions for {}: {:?}", viewer_id, e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?
+        .into_iter()
+        .collect();
+
+        access.unlocked_posts = sqlx::query_scalar::<_, Uuid>(
+            r#"
+            SELECT post_id
+            FROM post_unlocks
+            WHERE user_id = $1 AND status = 'COMPLETED' AND post_id = ANY($2)
+            "#,
+        )
+        .bind(&viewer_id)
+        .bind(&post_ids)
+        .fetch_all(&db.pool)
+        .await
+        .map_err(|e| {
+            error!("Failed to load post unlocks for {}: {:?}", viewer_id, e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?
+        .into_iter()
+        .collect();
+
+        Ok(access)
+    }
+
+    fn can_view(&self, post: &CreatorPostResponse) -> bool {
+        post.is_public
+            || self.viewer_id.as_deref() == Some(post.author.id.as_str())
+            || self.subscribed_creators.contains(&post.author.id)
+            || self.unlocked_posts.contains(&post.id)
+    }
+
+    fn apply(&self, posts: &mut [CreatorPostResponse]) {
+        for post in posts.iter_mut() {
+            if !self.can_view(post) {
+                restrict_post(post);
+            }
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -115,33 +289,63 @@ struct PaginationInfo {
     limit: u32,
     total: usize,
     pages: u32,
+    #[serde(default, rename = "hasMore")]
+    has_more: bool,
+    #[serde(default, rename = "nextCursor")]
+    next_cursor: Option<String>,
+}
+
+/// Drop the extra row fetched past `limit` and point at the page after it.
+fn next_page(posts: &mut Vec<PostRecord>, limit: u32) -> (bool, Option<String>) {
+    let has_more = posts.len() > limit as usize;
+    posts.truncate(limit as usize);
+    let next_cursor = posts
+        .last()
+        .filter(|_| has_more)
+        .map(|post| Cursor::new(post.created_at, post.id).encode());
+    (has_more, next_cursor)
 }
 
 async fn get_posts(
     State(db): State<Database>,
+    MaybeClaims(maybe_claims): MaybeClaims,
     Query(params): Query<PostQuery>,
 ) -> Result<Json<PostsResponse>, StatusCode> {
+    let viewer_id = maybe_claims.map(|claims| claims.sub);
+    let cursor = parse_cursor(params.cursor.as_deref())?;
+    let cursor_id = cursor.as_ref().map(Cursor::uuid).transpose()?;
+    let cursor_at = cursor.as_ref().map(|cursor| cursor.at);
     let page = params.page.unwrap_or(1);
     let limit = params.limit.unwrap_or(20);
-    let offset = (page - 1) * limit;
+    let offset = if cursor.is_some() { 0 } else { (page - 1) * limit };
 
     // Try cache first
-    let cache_key = format!("posts:list:{}:{}:{}", page, limit, params.user_id.as_deref().unwrap_or("all"));
+    let cache_key = format!(
+        "posts:list:{}:{}:{}:{}",
+        page,
+        limit,
+        params.user_id.as_deref().unwrap_or("all"),
+        params.cursor.as_deref().unwrap_or("")
+    );
     if let Some(redis) = &db.redis {
         let mut redis_clone = redis.clone();
         if let Ok(Some(cached)) = redis_clone.get(&cache_key).await {
             tracing::debug!("Cache HIT for posts list: {}", cache_key);
-            if let Ok(cached_value) = serde_json::from_str::<PostsResponse>(&cached) {
+            if let Ok(mut cached_value) = serde_json::from_str::<PostsResponse>(&cached) {
+                ViewerAccess::load(&db, viewer_id, &cached_value.data.posts)
+                    .await?
+                    .apply(&mut cached_value.data.posts);
                 return Ok(Json(cached_value));
             }
         }
         tracing::debug!("Cache MISS for posts list: {}", cache_key);
     }
 
-    let limit_i64 = limit as i64;
+    // One extra row tells whether there is a next page
+    let limit_i64 = limit as i64 + 1;
     let offset_i64 = offset as i64;
 
-    let (posts, total) = if let Some(user_id) = params.user_id.clone() {
+    let (mut posts, total) = if let Some(user_id) = params.user_id.clone() {
         let posts = sqlx::query_as::<_, PostRecord>(
             r#"
             SELECT
@@ -155,6 +359,14 @@ async fn get_posts(
                 p.video_url,
                 p.audio_url,
                 p.is_premium,
+                p.unlock_price,
+                p.unlock_currency,
+                p.published,
+                p.published_at,
+                p.audio_duration_seconds,
+                p.audio_waveform,
+                p.audio_processing_status,
+                cu.campaign_id,
                 p.created_at,
                 p.updated_at,
                 u.name as author_name,
@@ -166,11 +378,15 @@ async fn get_posts(
                 CASE WHEN ul.user_id IS NOT NULL THEN true ELSE false END as user_liked
             FROM posts p
             LEFT JOIN users u ON p.user_id = u.id
+            LEFT JOIN campaign_updates cu ON cu.post_id = p.id
             LEFT JOIN (SELECT post_id, COUNT(*) as like_count FROM post_likes GROUP BY post_id) l ON l.post_id = p.id
             LEFT JOIN (SELECT post_id, COUNT(*) as comment_count FROM post_comments GROUP BY post_id) c ON c.post_id = p.id
             LEFT JOIN post_likes ul ON ul.post_id = p.id AND ul.user_id = $4
             WHERE p.user_id = $1
-            ORDER BY p.created_at DESC
+              AND COALESCE(p.published, TRUE)
+              AND COALESCE(p.published_at, p.created_at) <= NOW()
+              AND ($5::TIMESTAMPTZ IS NULL OR (p.created_at, p.id) < ($5, $6))
+            ORDER BY p.created_at DESC, p.id DESC
             LIMIT $2 OFFSET $3
             "#,
         )
@@ -178,6 +394,8 @@ async fn get_posts(
         .bind(limit_i64)
         .bind(offset_i64)
         .bind(params.current_user_id.as_ref().unwrap_or(&"".to_string()))
+        .bind(cursor_at)
+        .bind(cursor_id)
         .fetch_all(&db.pool)
         .await
         .map_err(|e| {
@@ -185,14 +403,22 @@ async fn get_posts(
             StatusCode::INTERNAL_SERVER_ERROR
         })?;
 
-        let total = sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM posts WHERE user_id = $1")
-            .bind(&user_id)
-            .fetch_one(&db.pool)
-            .await
-            .map_err(|e| {
-                eprintln!("Error counting posts: {:?}", e);
-                StatusCode::INTERNAL_SERVER_ERROR
-            })?;
+        let total = sqlx::query_scalar::<_, i64>(
+            r#"
+            SELECT COUNT(*)
+            FROM posts p
+            WHERE p.user_id = $1
+              AND COALESCE(p.published, TRUE)
+              AND COALESCE(p.published_at, p.created_at) <= NOW()
+            "#,
+        )
+        .bind(&user_id)
+        .fetch_one(&db.pool)
+        .await
+        .map_err(|e| {
+            eprintln!("Error counting posts: {:?}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
 
         (posts, total as usize)
     } else {
@@ -209,6 +435,14 @@ async fn get_posts(
                 p.video_url,
                 p.audio_url,
                 p.is_premium,
+                p.unlock_price,
+                p.unlock_currency,
+                p.published,
+                p.published_at,
+                p.audio_duration_seconds,
+                p.audio_waveform,
+                p.audio_processing_status,
+                cu.campaign_id,
                 p.created_at,
                 p.updated_at,
                 u.name as author_name,
@@ -220,16 +454,22 @@ async fn get_posts(
                 CASE WHEN ul.user_id IS NOT NULL THEN true ELSE false END as user_liked
             FROM posts p
             LEFT JOIN users u ON p.user_id = u.id
+            LEFT JOIN campaign_updates cu ON cu.post_id = p.id
             LEFT JOIN (SELECT post_id, COUNT(*) as like_count FROM post_likes GROUP BY post_id) l ON l.post_id = p.id
             LEFT JOIN (SELECT post_id, COUNT(*) as comment_count FROM post_comments GROUP BY post_id) c ON c.post_id = p.id
             LEFT JOIN post_likes ul ON ul.post_id = p.id AND ul.user_id = $3
-            ORDER BY p.created_at DESC
+            WHERE COALESCE(p.published, TRUE)
+              AND COALESCE(p.published_at, p.created_at) <= NOW()
+              AND ($4::TIMESTAMPTZ IS NULL OR (p.created_at, p.id) < ($4, $5))
+            ORDER BY p.created_at DESC, p.id DESC
             LIMIT $1 OFFSET $2
             "#,
         )
         .bind(limit_i64)
         .bind(offset_i64)
         .bind(params.current_user_id.as_ref().unwrap_or(&"".to_string()))
+        .bind(cursor_at)
+        .bind(cursor_id)
         .fetch_all(&db.pool)
         .await
         .map_err(|e| {
@@ -237,32 +477,45 @@ async fn get_posts(
             StatusCode::INTERNAL_SERVER_ERROR
         })?;
 
-        let total = sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM posts")
-            .fetch_one(&db.pool)
-            .await
-            .map_err(|e| {
-                eprintln!("Error counting posts: {:?}", e);
-                StatusCode::INTERNAL_SERVER_ERROR
-            })?;
+        let total = sqlx::query_scalar::<_, i64>(
+            r#"
+            SELECT COUNT(*)
+            FROM posts p
+            WHERE COALESCE(p.published, TRUE)
+              AND COALESCE(p.published_at, p.created_at) <= NOW()
+            "#,
+        )
+        .fetch_one(&db.pool)
+        .await
+        .map_err(|e| {
+            eprintln!("Error counting posts: {:?}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
 
         (posts, total as usize)
     };
 
-    let response = PostsResponse {
+    let (has_more, next_cursor) = next_page(&mut posts, limit);
+    let mut posts: Vec<CreatorPostResponse> = posts.into_iter().map(map_post).collect();
+    load_post_attachments(&db, &mut posts).await?;
+
+    let mut response = PostsResponse {
         success: true,
         data: PostsData {
-            posts: posts.into_iter().map(map_post).collect(),
+            posts,
             pagination: PaginationInfo {
                 page,
                 limit,
                 total,
                 pages: calculate_total_pages(total, limit),
+                has_more,
+                next_cursor,
             },
             has_subscription: false,
         },
     };
 
-    // Cache the response
+    // Cache the response before viewer-specific access is applied
     if let Some(redis) = &db.redis {
         let mut redis_clone = redis.clone();
         if let Ok(response_str) = serde_json::to_string(&response) {
@@ -270,19 +523,28 @@ async fn get_posts(
         }
     }
 
+    ViewerAccess::load(&db, viewer_id, &response.data.posts)
+        .await?
+        .apply(&mut response.data.posts);
+
     Ok(Json(response))
 }
 
 async fn get_posts_by_creator(
     State(db): State<Database>,
     Path(user_id): Path<String>,
+    MaybeClaims(maybe_claims): MaybeClaims,
     Query(params): Query<PostQuery>,
 ) -> Result<Json<PostsResponse>, StatusCode> {
+    let cursor = parse_cursor(params.cursor.as_deref())?;
+    let cursor_id = cursor.as_ref().map(Cursor::uuid).transpose()?;
     let page = params.page.unwrap_or(1);
     let limit = params.limit.unwrap_or(20);
-    let offset = (page - 1) * limit;
+    let offset = if cursor.is_some() { 0 } else { (page - 1) * limit };
+    let viewer_id = maybe_claims.map(|claims| claims.sub);
+    let is_owner = viewer_id.as_deref() == Some(user_id.as_str());
 
-    let posts = sqlx::query_as::<_, PostRecord>(
+    let mut posts = sqlx::query_as::<_, PostRecord>(
         r#"
         SELECT
             p.id,
@@ -295,6 +557,14 @@ async fn get_posts_by_creator(
             p.video_url,
             p.audio_url,
             p.is_premium,
+            p.unlock_price,
+            p.unlock_currency,
+            p.published,
+            p.published_at,
+            p.audio_duration_seconds,
+            p.audio_waveform,
+            p.audio_processing_status,
+            cu.campaign_id,
             p.created_at,
             p.updated_at,
             u.name as author_name,
@@ -306,18 +576,24 @@ async fn get_posts_by_creator(
             CASE WHEN ul.user_id IS NOT NULL THEN true ELSE false END as user_liked
         FROM posts p
         LEFT JOIN users u ON p.user_id = u.id
+        LEFT JOIN campaign_updates cu ON cu.post_id = p.id
         LEFT JOIN (SELECT post_id, COUNT(*) as like_count FROM post_likes GROUP BY post_id) l ON l.post_id = p.id
         LEFT JOIN (SELECT post_id, COUNT(*) as comment_count FROM post_comments GROUP BY post_id) c ON c.post_id = p.id
         LEFT JOIN post_likes ul ON ul.post_id = p.id AND ul.user_id = $4
         WHERE p.user_id = $1
-        ORDER BY p.created_at DESC
+          AND ($5 OR (COALESCE(p.published, TRUE) AND COALESCE(p.published_at, p.created_at) <= NOW()))
+          AND ($6::TIMESTAMPTZ IS NULL OR (p.created_at, p.id) < ($6, $7))
+        ORDER BY p.created_at DESC, p.id DESC
         LIMIT $2 OFFSET $3
         "#,
     )
     .bind(&user_id)
-    .bind(limit as i64)
+    .bind(limit as i64 + 1)
     .bind(offset as i64)
     .bind(params.current_user_id.as_ref().unwrap_or(&"".to_string()))
+    .bind(is_owner)
+    .bind(cursor.as_ref().map(|cursor| cursor.at))
+    .bind(cursor_id)
     .fetch_all(&db.pool)
     .await
     .map_err(|e| {
@@ -325,26 +601,42 @@ async fn get_posts_by_creator(
         StatusCode::INTERNAL_SERVER_ERROR
     })?;
 
-    let total_count = sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM posts WHERE user_id = $1")
-        .bind(&user_id)
-        .fetch_one(&db.pool)
-        .await
-        .map_err(|e| {
-            eprintln!("Error counting posts: {:?}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?;
+    let total_count = sqlx::query_scalar::<_, i64>(
+        r#"
+        SELECT COUNT(*)
+        FROM posts p
+        WHERE p.user_id = $1
+          AND ($2 OR (COALESCE(p.published, TRUE) AND COALESCE(p.published_at, p.created_at) <= NOW()))
+        "#,
+    )
+    .bind(&user_id)
+    .bind(is_owner)
+    .fetch_one(&db.pool)
+    .await
+    .map_err(|e| {
+        eprintln!("Error counting posts: {:?}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    let (has_more, next_cursor) = next_page(&mut posts, limit);
+    let mut posts: Vec<CreatorPostResponse> = posts.into_iter().map(map_post).collect();
+    load_post_attachments(&db, &mut posts).await?;
+    let access = ViewerAccess::load(&db, viewer_id, &posts).await?;
+    access.apply(&mut posts);
 
     let response = PostsResponse {
         success: true,
         data: PostsData {
-            posts: posts.into_iter().map(map_post).collect(),
+            posts,
             pagination: PaginationInfo {
                 page,
                 limit,
                 total: total_count as usize,
                 pages: calculate_total_pages(total_count as usize, limit),
+                has_more,
+                next_cursor,
             },
-            has_subscription: false,
+            has_subscription: access.subscribed_creators.contains(&user_id),
         },
     };
     Ok(Json(response))
@@ -355,12 +647,14 @@ async fn get_my_posts(
     claims: Claims,
     Query(params): Query<PostQuery>,
 ) -> Result<Json<PostsResponse>, StatusCode> {
+    let cursor = parse_cursor(params.cursor.as_deref())?;
+    let cursor_id = cursor.as_ref().map(Cursor::uuid).transpose()?;
     let page = params.page.unwrap_or(1);
     let limit = params.limit.unwrap_or(20);
-    let offset = (page - 1) * limit;
+    let offset = if cursor.is_some() { 0 } else { (page - 1) * limit };
     let user_id = claims.sub;
 
-    let posts = sqlx::query_as::<_, PostRecord>(
+    let mut posts = sqlx::query_as::<_, PostRecord>(
         r#"
         SELECT
             p.id,
@@ -373,6 +667,14 @@ async fn get_my_posts(
             p.video_url,
             p.audio_url,
             p.is_premium,
+            p.unlock_price,
+            p.unlock_currency,
+            p.published,
+            p.published_at,
+            p.audio_duration_seconds,
+            p.audio_waveform,
+            p.audio_processing_status,
+            cu.campaign_id,
             p.created_at,
             p.updated_at,
             u.name as author_name,
@@ -384,17 +686,21 @@ async fn get_my_posts(
             CASE WHEN ul.user_id IS NOT NULL THEN true ELSE false END as user_liked
         FROM posts p
         LEFT JOIN users u ON p.user_id = u.id
+        LEFT JOIN campaign_updates cu ON cu.post_id = p.id
         LEFT JOIN (SELECT post_id, COUNT(*) as like_count FROM post_likes GROUP BY post_id) l ON l.post_id = p.id
         LEFT JOIN (SELECT post_id, COUNT(*) as comment_count FROM post_comments GROUP BY post_id) c ON c.post_id = p.id
         LEFT JOIN post_likes ul ON ul.post_id = p.id AND ul.user_id = $1
         WHERE p.user_id = $1
-        ORDER BY p.created_at DESC
+          AND ($4::TIMESTAMPTZ IS NULL OR (p.created_at, p.id) < ($4, $5))
+        ORDER BY p.created_at DESC, p.id DESC
         LIMIT $2 OFFSET $3
         "#,
     )
     .bind(&user_id)
-    .bind(limit as i64)
+    .bind(limit as i64 + 1)
     .bind(offset as i64)
+    .bind(cursor.as_ref().map(|cursor| cursor.at))
+    .bind(cursor_id)
     .fetch_all(&db.pool)
     .await
     .map_err(|e| {
@@ -411,15 +717,21 @@ async fn get_my_posts(
             StatusCode::INTERNAL_SERVER_ERROR
         })?;
 
+    let (has_more, next_cursor) = next_page(&mut posts, limit);
+    let mut posts: Vec<CreatorPostResponse> = posts.into_iter().map(map_post).collect();
+    load_post_attachments(&db, &mut posts).await?;
+
     let response = PostsResponse {
         success: true,
         data: PostsData {
-            posts: posts.into_iter().map(map_post).collect(),
+            posts,
             pagination: PaginationInfo {
                 page,
                 limit,
                 total: total_count as usize,
                 pages: calculate_total_pages(total_count as usize, limit),
+                has_more,
+                next_cursor,
             },
             has_subscription: false,
         },
@@ -484,11 +796,22 @@ async fn create_post(
 
     let is_public = payload.is_public.unwrap_or(true);
     let is_premium = payload.is_premium.unwrap_or(!is_public);
+    let (unlock_price, unlock_currency) = normalize_unlock_price(&payload);
+
+    if let Some(campaign_id) = payload.campaign_id {
+        ensure_campaign_owner(&db, campaign_id, &user_id).await?;
+    }
+
+    let mut tx = db
+        .pool
+        .begin()
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
     let post_id = sqlx::query_scalar::<_, Uuid>(
         r#"
-        INSERT INTO posts (user_id, title, content, media_url, media_type, is_premium, image_urls, video_url, audio_url)
-        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+        INSERT INTO posts (user_id, title, content, media_url, media_type, is_premium, image_urls, video_url, audio_url, unlock_price, unlock_currency, published, published_at)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, COALESCE($13, NOW()))
         RETURNING id
         "#,
     )
@@ -501,30 +824,69 @@ async fn create_post(
     .bind(image_urls.clone())
     .bind(video_url.clone())
     .bind(audio_url.clone())
-    .fetch_one(&db.pool)
+    .bind(unlock_price)
+    .bind(&unlock_currency)
+    .bind(payload.published.unwrap_or(true))
+    .bind(payload.published_at)
+    .fetch_one(&mut tx)
     .await
     .map_err(|e| {
         eprintln!("Error creating post: {:?}", e);
         StatusCode::INTERNAL_SERVER_ERROR
     })?;
 
-    let post = fetch_post_with_author(&db, post_id).await?;
+    sync_campaign_update(&mut tx, post_id, payload.campaign_id).await?;
+
+    tx.commit()
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let mut post = fetch_post_with_author(&db, post_id).await?;
+    enqueue_audio_processing(&db, &mut post).await;
+    if post.published.unwrap_or(true) {
+        feed_timeline::publish(&db, &post.user_id, format!("post-{}", post.id), post.created_at);
+    }
+    let mut posts = vec![map_post(post)];
+    load_post_attachments(&db, &mut posts).await?;
 
     Ok(Json(json!({
         "success": true,
-        "data": map_post(post)
+        "data": posts.pop()
     })))
 }
 
 async fn get_post_by_id(
     State(db): State<Database>,
     Path(id): Path<Uuid>,
+    MaybeClaims(maybe_claims): MaybeClaims,
+    headers: HeaderMap,
 ) -> Result<Json<serde_json::Value>, StatusCode> {
+    let viewer_id = maybe_claims.map(|claims| claims.sub);
     let post = fetch_post_with_author(&db, id).await?;
+    let is_author = viewer_id.as_deref() == Some(post.user_id.as_str());
+
+    if !is_post_visible(&post) && !is_author {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    let mut posts = vec![map_post(post)];
+    load_post_attachments(&db, &mut posts).await?;
+    let access = ViewerAccess::load(&db, viewer_id.clone(), &posts).await?;
+
+    if !is_author {
+        let viewer_key = post_views::viewer_key(viewer_id.as_deref(), &headers);
+        let is_subscriber = access.subscribed_creators.contains(&posts[0].author.id);
+        let db = db.clone();
+        tokio::spawn(async move {
+            post_views::record_view(&db, id, &viewer_key, is_subscriber).await;
+        });
+    }
+
+    access.apply(&mut posts);
 
     Ok(Json(json!({
         "success": true,
-        "data": map_post(post)
+        "data": posts.pop()
     })))
 }
 
@@ -582,11 +944,26 @@ async fn update_post(
 
     let is_public = payload.is_public.unwrap_or(true);
     let is_premium = payload.is_premium.unwrap_or(!is_public);
+    let (unlock_price, unlock_currency) = normalize_unlock_price(&payload);
+
+    if let Some(campaign_id) = payload.campaign_id {
+        ensure_campaign_owner(&db, campaign_id, &user_id).await?;
+    }
+
+    let mut tx = db
+        .pool
+        .begin()
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
     let post_id = sqlx::query_scalar::<_, Uuid>(
         r#"
         UPDATE posts 
-        SET title = $2, content = $3, media_url = $4, media_type = $5, is_premium = $6, image_urls = $7, video_url = $8, audio_url = $9, updated_at = NOW()
+        SET title = $2, content = $3, media_url = $4, media_type = $5, is_premium = $6, image_urls = $7, video_url = $8, audio_url = $9, unlock_price = $10, unlock_currency = $11, published = COALESCE($12, published), published_at = COALESCE($13, published_at),
+            audio_duration_seconds = CASE WHEN audio_url IS DISTINCT FROM $9 THEN NULL ELSE audio_duration_seconds END,
+            audio_waveform = CASE WHEN audio_url IS DISTINCT FROM $9 THEN NULL ELSE audio_waveform END,
+            audio_processing_status = CASE WHEN audio_url IS DISTINCT FROM $9 THEN NULL ELSE audio_processing_status END,
+            updated_at = NOW()
         WHERE id = $1
         RETURNING id
         "#
@@ -600,15 +977,34 @@ async fn update_post(
     .bind(image_urls.clone())
     .bind(video_url.clone())
     .bind(audio_url.clone())
-    .fetch_one(&db.pool)
+    .bind(unlock_price)
+    .bind(&unlock_currency)
+    .bind(payload.published)
+    .bind(payload.published_at)
+    .fetch_one(&mut tx)
     .await
     .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
-    let post = fetch_post_with_author(&db, post_id).await?;
+    sync_campaign_update(&mut tx, post_id, payload.campaign_id).await?;
+
+    tx.commit()
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let mut post = fetch_post_with_author(&db, post_id).await?;
+    enqueue_audio_processing(&db, &mut post).await;
+    let feed_key = format!("post-{}", post.id);
+    if post.published.unwrap_or(true) {
+        feed_timeline::publish(&db, &post.user_id, feed_key, post.created_at);
+    } else {
+        feed_timeline::retract(&db, &post.user_id, feed_key);
+    }
+    let mut posts = vec![map_post(post)];
+    load_post_attachments(&db, &mut posts).await?;
 
     Ok(Json(json!({
         "success": true,
-        "data": map_post(post)
+        "data": posts.pop()
     })))
 }
 
@@ -632,11 +1028,26 @@ async fn delete_post(
         return Err(StatusCode::FORBIDDEN);
     }
 
+    let attachment_keys = sqlx::query_scalar::<_, String>(
+        "SELECT storage_key FROM post_attachments WHERE post_id = $1",
+    )
+    .bind(id)
+    .fetch_all(&db.pool)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
     sqlx::query("DELETE FROM posts WHERE id = $1")
         .bind(id)
         .execute(&db.pool)
         .await
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    feed_timeline::retract(&db, &user_id, format!("post-{}", id));
+
+    for storage_key in attachment_keys {
+        if let Err(e) = storage::delete_private_file(&storage_key).await {
+            error!("Failed to delete attachment file {}: {:?}", storage_key, e);
+        }
+    }
 
     Ok(Json(json!({
         "success": true,
@@ -644,80 +1055,526 @@ async fn delete_post(
     })))
 }
 
-fn calculate_total_pages(total: usize, limit: u32) -> u32 {
-    if total == 0 || limit == 0 {
-        0
-    } else {
-        ((total as f64) / (limit as f64)).ceil() as u32
-    }
-}
+// View and engagement stats for a single post, visible to its author only
+async fn get_post_insights(
+    State(db): State<Database>,
+    Path(id): Path<Uuid>,
+    claims: Claims,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let author_id = sqlx::query_scalar::<_, String>("SELECT user_id FROM posts WHERE id = $1")
+        .bind(id)
+        .fetch_optional(&db.pool)
+        .await
+        .map_err(|e| {
+            error!("Failed to load post {} for insights: {:?}", id, e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?
+        .ok_or(StatusCode::NOT_FOUND)?;
 
-fn normalize_media_type(media_type: String) -> String {
-    let lowered = media_type.trim().to_ascii_lowercase();
-    if lowered == "image" || lowered.starts_with("image/") {
-        "image".to_string()
-    } else if lowered == "video" || lowered.starts_with("video/") {
-        "video".to_string()
-    } else if lowered == "audio" || lowered.starts_with("audio/") {
-        "audio".to_string()
-    } else {
-        lowered
+    if author_id != claims.sub {
+        return Err(StatusCode::FORBIDDEN);
     }
-}
 
-fn matches_media_type(media_type: Option<&str>, target: &str) -> bool {
-    media_type
-        .map(|mt| {
-            let lowered = mt.trim().to_ascii_lowercase();
-            if lowered == "mixed" {
-                return matches!(target, "image" | "video" | "audio");
-            }
-            lowered == target || lowered.starts_with(&format!("{}/", target))
-        })
-        .unwrap_or(false)
-}
+    let stats = sqlx::query(
+        r#"
+        SELECT
+            COALESCE(SUM(views), 0)::BIGINT AS views,
+            COALESCE(SUM(subscriber_views), 0)::BIGINT AS subscriber_views,
+            COALESCE(SUM(non_subscriber_views), 0)::BIGINT AS non_subscriber_views,
+            (SELECT COUNT(*) FROM post_viewers WHERE post_id = $1)::BIGINT AS unique_viewers,
+            (SELECT COUNT(*) FROM post_likes WHERE post_id = $1)::BIGINT AS likes,
+            (SELECT COUNT(*) FROM post_comments WHERE post_id = $1)::BIGINT AS comments
+        FROM post_view_stats
+        WHERE post_id = $1
+        "#,
+    )
+    .bind(id)
+    .fetch_one(&db.pool)
+    .await
+    .map_err(|e| {
+        error!("Failed to load insights for post {}: {:?}", id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
 
-fn sanitize_url(input: Option<String>) -> Option<String> {
-    input.and_then(|value| {
-        let trimmed = value.trim();
-        if trimmed.is_empty() {
-            None
+    let hourly = sqlx::query(
+        r#"
+        SELECT hour, views, unique_viewers
+        FROM post_view_stats
+        WHERE post_id = $1
+        ORDER BY hour DESC
+        LIMIT 168
+        "#,
+    )
+    .bind(id)
+    .fetch_all(&db.pool)
+    .await
+    .map_err(|e| {
+        error!("Failed to load hourly views for post {}: {:?}", id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    // Views still buffered in Redis have not reached the stats table yet.
+    let (pending_views, pending_subscriber, pending_non_subscriber) =
+        post_views::pending_counts(&db, id).await;
+
+    let views = stats.get::<i64, _>("views") + pending_views;
+    let unique_viewers: i64 = stats.get("unique_viewers");
+    let likes: i64 = stats.get("likes");
+    let comments: i64 = stats.get("comments");
+    let rate = |count: i64| {
+        if unique_viewers > 0 {
+            (count as f64 / unique_viewers as f64 * 10000.0).round() / 10000.0
         } else {
-            Some(trimmed.to_string())
+            0.0
         }
-    })
-}
+    };
 
-fn sanitize_urls(input: Option<Vec<String>>) -> Option<Vec<String>> {
-    input
-        .map(|values| {
-            values
-                .into_iter()
-                .filter_map(|value| {
-                    let trimmed = value.trim();
-                    if trimmed.is_empty() {
-                        None
-                    } else {
-                        Some(trimmed.to_string())
-                    }
-                })
-                .collect::<Vec<String>>()
+    let hourly: Vec<serde_json::Value> = hourly
+        .iter()
+        .rev()
+        .map(|row| {
+            json!({
+                "hour": row.get::<DateTime<Utc>, _>("hour"),
+                "views": row.get::<i64, _>("views"),
+                "uniqueViewers": row.get::<i64, _>("unique_viewers"),
+            })
         })
-        .filter(|values| !values.is_empty())
+        .collect();
+
+    Ok(Json(json!({
+        "success": true,
+        "data": {
+            "postId": id,
+            "views": views,
+            "uniqueViewers": unique_viewers,
+            "likes": likes,
+            "comments": comments,
+            "conversion": {
+                "likeRate": rate(likes),
+                "commentRate": rate(comments),
+            },
+            "breakdown": {
+                "subscriberViews": stats.get::<i64, _>("subscriber_views") + pending_subscriber,
+                "nonSubscriberViews": stats.get::<i64, _>("non_subscriber_views") + pending_non_subscriber,
+            },
+            "hourly": hourly,
+        }
+    })))
 }
 
-fn map_post(record: PostRecord) -> CreatorPostResponse {
-    let PostRecord {
-        id,
-        user_id,
-        title,
-        content,
-        media_url,
-        media_type,
-        image_urls,
-        video_url,
+// Accept a Patreon CSV/ZIP export and queue it for background import
+async fn import_posts(
+    State(db): State<Database>,
+    claims: Claims,
+    mut multipart: Multipart,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let mut upload: Option<(String, Vec<u8>)> = None;
+
+    while let Some(mut field) = multipart
+        .next_field()
+        .await
+        .map_err(|_| StatusCode::BAD_REQUEST)?
+    {
+        if field.name() != Some("file") {
+            continue;
+        }
+
+        let filename = field
+            .file_name()
+            .and_then(|name| std::path::Path::new(name).file_name())
+            .and_then(|name| name.to_str())
+            .unwrap_or("patreon-export.csv")
+            .to_string();
+
+        let mut bytes: Vec<u8> = Vec::new();
+        while let Some(chunk) = field.chunk().await.map_err(|_| StatusCode::BAD_REQUEST)? {
+            if bytes.len() + chunk.len() > IMPORT_MAX_BYTES {
+                return Err(StatusCode::PAYLOAD_TOO_LARGE);
+            }
+            bytes.extend_from_slice(&chunk);
+        }
+
+        upload = Some((filename, bytes));
+    }
+
+    let (filename, bytes) = upload.ok_or(StatusCode::BAD_REQUEST)?;
+    let lower_name = filename.to_ascii_lowercase();
+    if bytes.is_empty() || !(lower_name.ends_with(".csv") || lower_name.ends_with(".zip")) {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let content_type = if lower_name.ends_with(".zip") {
+        "application/zip"
+    } else {
+        "text/csv"
+    };
+    let stored = storage::store_private_file("imports", Some(&filename), content_type, bytes)
+        .await
+        .map_err(|e| {
+            error!("Failed to store post import for {}: {:?}", claims.sub, e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    let import_id = sqlx::query_scalar::<_, Uuid>(
+        r#"
+        INSERT INTO post_imports (user_id, source, filename, storage_key)
+        VALUES ($1, 'patreon', $2, $3)
+        RETURNING id
+        "#,
+    )
+    .bind(&claims.sub)
+    .bind(&filename)
+    .bind(&stored.storage_key)
+    .fetch_one(&db.pool)
+    .await
+    .map_err(|e| {
+        error!("Failed to create post import for {}: {:?}", claims.sub, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    match &db.amqp {
+        Some(amqp) => {
+            if let Err(e) = amqp.send_post_import_job(import_id.to_string()).await {
+                error!("Failed to queue post import {}: {:?}", import_id, e);
+                let _ = sqlx::query(
+                    "UPDATE post_imports SET status = 'FAILED', error = 'Could not queue import', updated_at = NOW() WHERE id = $1",
+                )
+                .bind(import_id)
+                .execute(&db.pool)
+                .await;
+                return Err(StatusCode::SERVICE_UNAVAILABLE);
+            }
+        }
+        None => {
+            // Without CloudAMQP the import still runs in the background of this process.
+            let db = db.clone();
+            tokio::spawn(async move {
+                let _ = jobs::patreon_import::run_import(&db, &import_id.to_string()).await;
+            });
+        }
+    }
+
+    Ok(Json(json!({
+        "success": true,
+        "data": {
+            "id": import_id,
+            "status": "QUEUED"
+        }
+    })))
+}
+
+// Progress of one of the creator's post imports
+async fn get_post_import(
+    State(db): State<Database>,
+    Path(import_id): Path<Uuid>,
+    claims: Claims,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let row = sqlx::query(
+        r#"
+        SELECT id, source, filename, status, total_rows, processed_rows, imported_posts,
+               skipped_rows, error, created_at, completed_at
+        FROM post_imports
+        WHERE id = $1 AND user_id = $2
+        "#,
+    )
+    .bind(import_id)
+    .bind(&claims.sub)
+    .fetch_optional(&db.pool)
+    .await
+    .map_err(|e| {
+        error!("Failed to load post import {}: {:?}", import_id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?
+    .ok_or(StatusCode::NOT_FOUND)?;
+
+    let total_rows: i32 = row.get("total_rows");
+    let processed_rows: i32 = row.get("processed_rows");
+    let status: String = row.get("status");
+    let progress = if status == "COMPLETED" {
+        100.0
+    } else if total_rows > 0 {
+        (processed_rows as f64 / total_rows as f64 * 1000.0).round() / 10.0
+    } else {
+        0.0
+    };
+
+    Ok(Json(json!({
+        "success": true,
+        "data": {
+            "id": row.get::<Uuid, _>("id"),
+            "source": row.get::<String, _>("source"),
+            "filename": row.get::<Option<String>, _>("filename"),
+            "status": status,
+            "totalRows": total_rows,
+            "processedRows": processed_rows,
+            "importedPosts": row.get::<i32, _>("imported_posts"),
+            "skippedRows": row.get::<i32, _>("skipped_rows"),
+            "progress": progress,
+            "error": row.get::<Option<String>, _>("error"),
+            "createdAt": row.get::<DateTime<Utc>, _>("created_at"),
+            "completedAt": row.get::<Option<DateTime<Utc>>, _>("completed_at"),
+        }
+    })))
+}
+
+// Unified publishing calendar of the creator's posts, articles and events for one month
+async fn get_content_calendar(
+    State(db): State<Database>,
+    claims: Claims,
+    Query(params): Query<CalendarQuery>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let (month_start, month_end) = parse_calendar_month(params.month.as_deref())?;
+    let user_id = claims.sub;
+    let now = Utc::now();
+
+    let post_rows = sqlx::query(
+        r#"
+        SELECT id, title, COALESCE(published, TRUE) AS published,
+               COALESCE(published_at, created_at) AS scheduled_for
+        FROM posts
+        WHERE user_id = $1
+          AND COALESCE(published_at, created_at) >= $2
+          AND COALESCE(published_at, created_at) < $3
+        "#,
+    )
+    .bind(&user_id)
+    .bind(month_start)
+    .bind(month_end)
+    .fetch_all(&db.pool)
+    .await
+    .map_err(|e| {
+        error!("Failed to load calendar posts for {}: {:?}", user_id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    let article_rows = sqlx::query(
+        r#"
+        SELECT id, title, slug, published_at IS NOT NULL AS published,
+               COALESCE(published_at, created_at) AS scheduled_for
+        FROM articles
+        WHERE author_id = $1
+          AND COALESCE(published_at, created_at) >= $2
+          AND COALESCE(published_at, created_at) < $3
+        "#,
+    )
+    .bind(&user_id)
+    .bind(month_start)
+    .bind(month_end)
+    .fetch_all(&db.pool)
+    .await
+    .map_err(|e| {
+        error!("Failed to load calendar articles for {}: {:?}", user_id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    let event_rows = sqlx::query(
+        r#"
+        SELECT id, title, UPPER(COALESCE(status, 'DRAFT')) <> 'DRAFT' AS published,
+               start_time AS scheduled_for
+        FROM events
+        WHERE host_id = $1
+          AND start_time >= $2
+          AND start_time < $3
+        "#,
+    )
+    .bind(&user_id)
+    .bind(month_start)
+    .bind(month_end)
+    .fetch_all(&db.pool)
+    .await
+    .map_err(|e| {
+        error!("Failed to load calendar events for {}: {:?}", user_id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    let calendar_status = |published: bool, scheduled_for: DateTime<Utc>| {
+        if !published {
+            "draft"
+        } else if scheduled_for > now {
+            "scheduled"
+        } else {
+            "published"
+        }
+    };
+
+    let mut entries: Vec<CalendarEntry> = Vec::new();
+
+    for row in post_rows {
+        let scheduled_for: DateTime<Utc> = row.get("scheduled_for");
+        entries.push(CalendarEntry {
+            id: row.get("id"),
+            entry_type: "post",
+            title: row.get("title"),
+            scheduled_for,
+            status: calendar_status(row.get("published"), scheduled_for),
+            link: None,
+        });
+    }
+
+    for row in article_rows {
+        let scheduled_for: DateTime<Utc> = row.get("scheduled_for");
+        let slug: String = row.get("slug");
+        entries.push(CalendarEntry {
+            id: row.get("id"),
+            entry_type: "article",
+            title: row.get("title"),
+            scheduled_for,
+            status: calendar_status(row.get("published"), scheduled_for),
+            link: Some(format!("/blog/{}", slug)),
+        });
+    }
+
+    for row in event_rows {
+        let id: Uuid = row.get("id");
+        let scheduled_for: DateTime<Utc> = row.get("scheduled_for");
+        entries.push(CalendarEntry {
+            id,
+            entry_type: "event",
+            title: row.get("title"),
+            scheduled_for,
+            status: calendar_status(row.get("published"), scheduled_for),
+            link: Some(format!("/events/{}", id)),
+        });
+    }
+
+    entries.sort_by_key(|entry| entry.scheduled_for);
+
+    let mut days: BTreeMap<String, Vec<&CalendarEntry>> = BTreeMap::new();
+    for entry in &entries {
+        days.entry(entry.scheduled_for.format("%Y-%m-%d").to_string())
+            .or_default()
+            .push(entry);
+    }
+
+    Ok(Json(json!({
+        "success": true,
+        "data": {
+            "month": month_start.format("%Y-%m").to_string(),
+            "start": month_start,
+            "end": month_end,
+            "days": days,
+            "entries": entries,
+            "counts": {
+                "posts": entries.iter().filter(|entry| entry.entry_type == "post").count(),
+                "articles": entries.iter().filter(|entry| entry.entry_type == "article").count(),
+                "events": entries.iter().filter(|entry| entry.entry_type == "event").count()
+            }
+        }
+    })))
+}
+
+fn parse_calendar_month(month: Option<&str>) -> Result<(DateTime<Utc>, DateTime<Utc>), StatusCode> {
+    let today = Utc::now().date_naive();
+    let (year, month) = match month.map(str::trim).filter(|value| !value.is_empty()) {
+        Some(value) => {
+            let (year, month) = value.split_once('-').ok_or(StatusCode::BAD_REQUEST)?;
+            (
+                year.parse::<i32>().map_err(|_| StatusCode::BAD_REQUEST)?,
+                month.parse::<u32>().map_err(|_| StatusCode::BAD_REQUEST)?,
+            )
+        }
+        None => (today.year(), today.month()),
+    };
+
+    let start = NaiveDate::from_ymd_opt(year, month, 1).ok_or(StatusCode::BAD_REQUEST)?;
+    let end = if month == 12 {
+        NaiveDate::from_ymd_opt(year + 1, 1, 1)
+    } else {
+        NaiveDate::from_ymd_opt(year, month + 1, 1)
+    }
+    .ok_or(StatusCode::BAD_REQUEST)?;
+
+    let to_utc = |date: NaiveDate| {
+        date.and_hms_opt(0, 0, 0)
+            .map(|datetime| datetime.and_utc())
+            .ok_or(StatusCode::BAD_REQUEST)
+    };
+
+    Ok((to_utc(start)?, to_utc(end)?))
+}
+
+fn calculate_total_pages(total: usize, limit: u32) -> u32 {
+    if total == 0 || limit == 0 {
+        0
+    } else {
+        ((total as f64) / (limit as f64)).ceil() as u32
+    }
+}
+
+fn normalize_media_type(media_type: String) -> String {
+    let lowered = media_type.trim().to_ascii_lowercase();
+    if lowered == "image" || lowered.starts_with("image/") {
+        "image".to_string()
+    } else if lowered == "video" || lowered.starts_with("video/") {
+        "video".to_string()
+    } else if lowered == "audio" || lowered.starts_with("audio/") {
+        "audio".to_string()
+    } else {
+        lowered
+    }
+}
+
+fn matches_media_type(media_type: Option<&str>, target: &str) -> bool {
+    media_type
+        .map(|mt| {
+            let lowered = mt.trim().to_ascii_lowercase();
+            if lowered == "mixed" {
+                return matches!(target, "image" | "video" | "audio");
+            }
+            lowered == target || lowered.starts_with(&format!("{}/", target))
+        })
+        .unwrap_or(false)
+}
+
+fn sanitize_url(input: Option<String>) -> Option<String> {
+    input.and_then(|value| {
+        let trimmed = value.trim();
+        if trimmed.is_empty() {
+            None
+        } else {
+            Some(trimmed.to_string())
+        }
+    })
+}
+
+fn sanitize_urls(input: Option<Vec<String>>) -> Option<Vec<String>> {
+    input
+        .map(|values| {
+            values
+                .into_iter()
+                .filter_map(|value| {
+                    let trimmed = value.trim();
+                    if trimmed.is_empty() {
+                        None
+                    } else {
+                        Some(trimmed.to_string())
+                    }
+                })
+                .collect::<Vec<String>>()
+        })
+        .filter(|values| !values.is_empty())
+}
+
+fn map_post(record: PostRecord) -> CreatorPostResponse {
+    let PostRecord {
+        id,
+        user_id,
+        title,
+        content,
+        media_url,
+        media_type,
+        image_urls,
+        video_url,
         audio_url,
         is_premium,
+        unlock_price,
+        unlock_currency,
+        published,
+        published_at,
+        audio_duration_seconds,
+        audio_waveform,
+        audio_processing_status,
+        campaign_id,
         created_at,
         updated_at,
         author_name,
@@ -761,14 +1618,20 @@ fn map_post(record: PostRecord) -> CreatorPostResponse {
         images,
         video_url,
         audio_url,
+        audio_duration: audio_duration_seconds,
+        audio_waveform,
+        audio_processing_status,
         attachments: None,
+        campaign_id,
         is_public: !is_premium,
         minimum_tier_id: None,
         like_count: like_count.unwrap_or(0),
         comment_count: comment_count.unwrap_or(0),
         is_liked: user_liked.unwrap_or(false),
-        published: true,
-        published_at: Some(created_at),
+        unlock_price: unlock_price.filter(|_| is_premium),
+        unlock_currency: unlock_currency.filter(|_| is_premium),
+        published: published.unwrap_or(true),
+        published_at: Some(published_at.unwrap_or(created_at)),
         created_at,
         updated_at,
         author: CreatorPostAuthor {
@@ -782,6 +1645,199 @@ fn map_post(record: PostRecord) -> CreatorPostResponse {
     }
 }
 
+/// Drafts and posts scheduled for the future are only visible to their author.
+fn is_post_visible(record: &PostRecord) -> bool {
+    record.published.unwrap_or(true) && record.published_at.unwrap_or(record.created_at) <= Utc::now()
+}
+
+fn normalize_unlock_price(payload: &CreatePostRequest) -> (Option<f64>, String) {
+    let price = payload
+        .unlock_price
+        .filter(|price| price.is_finite() && *price > 0.0);
+    let currency = payload
+        .unlock_currency
+        .as_deref()
+        .map(str::trim)
+        .filter(|currency| currency.len() == 3)
+        .map(str::to_ascii_uppercase)
+        .unwrap_or_else(|| "USD".to_string());
+    (price, currency)
+}
+
+/// Strips the body and media from a premium post the viewer has no access to,
+/// leaving the excerpt and unlock price so clients can render a paywall.
+fn restrict_post(post: &mut CreatorPostResponse) {
+    post.content.clear();
+    post.images.clear();
+    post.video_url = None;
+    post.audio_url = None;
+    post.audio_waveform = None;
+    post.has_access = false;
+}
+
+async fn ensure_campaign_owner(
+    db: &Database,
+    campaign_id: Uuid,
+    user_id: &str,
+) -> Result<(), StatusCode> {
+    let creator_id = sqlx::query_scalar::<_, String>("SELECT creator_id FROM campaigns WHERE id = $1")
+        .bind(campaign_id)
+        .fetch_optional(&db.pool)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::BAD_REQUEST)?;
+
+    if creator_id != user_id {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    Ok(())
+}
+
+/// Keep the campaign update cross-posted from a post in sync with it. When `campaign_id`
+/// is given the post is (re)linked to that campaign; otherwise an existing link is refreshed.
+async fn sync_campaign_update(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    post_id: Uuid,
+    campaign_id: Option<Uuid>,
+) -> Result<(), StatusCode> {
+    let result = if let Some(campaign_id) = campaign_id {
+        sqlx::query(
+            r#"
+            INSERT INTO campaign_updates (campaign_id, author_id, post_id, title, content, published_at)
+            SELECT $2, p.user_id, p.id, p.title, COALESCE(p.content, ''), COALESCE(p.published_at, p.created_at)
+            FROM posts p
+            WHERE p.id = $1
+            ON CONFLICT (post_id) DO UPDATE
+            SET campaign_id = EXCLUDED.campaign_id,
+                title = EXCLUDED.title,
+                content = EXCLUDED.content,
+                published_at = EXCLUDED.published_at,
+                updated_at = NOW()
+            "#,
+        )
+        .bind(post_id)
+        .bind(campaign_id)
+        .execute(&mut *tx)
+        .await
+    } else {
+        sqlx::query(
+            r#"
+            UPDATE campaign_updates cu
+            SET title = p.title,
+                content = COALESCE(p.content, ''),
+                published_at = COALESCE(p.published_at, p.created_at),
+                updated_at = NOW()
+            FROM posts p
+            WHERE cu.post_id = p.id AND p.id = $1
+            "#,
+        )
+        .bind(post_id)
+        .execute(&mut *tx)
+        .await
+    };
+
+    result.map(|_| ()).map_err(|e| {
+        error!("Failed to sync campaign update for post {}: {:?}", post_id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })
+}
+
+/// Queue waveform extraction for a post whose audio has not been processed yet.
+async fn enqueue_audio_processing(db: &Database, post: &mut PostRecord) {
+    let (amqp, audio_url) = match (&db.amqp, &post.audio_url) {
+        (Some(amqp), Some(audio_url)) if post.audio_processing_status.is_none() => {
+            (amqp, audio_url.clone())
+        }
+        _ => return,
+    };
+
+    // Mark as pending before publishing so a fast worker's READY status is not overwritten.
+    if let Err(e) = sqlx::query("UPDATE posts SET audio_processing_status = 'PENDING' WHERE id = $1")
+        .bind(post.id)
+        .execute(&db.pool)
+        .await
+    {
+        error!("Failed to mark audio processing for post {}: {:?}", post.id, e);
+        return;
+    }
+
+    if let Err(e) = amqp
+        .send_audio_waveform_job(post.id.to_string(), audio_url)
+        .await
+    {
+        error!("Failed to queue audio processing for post {}: {:?}", post.id, e);
+        let _ = sqlx::query("UPDATE posts SET audio_processing_status = NULL WHERE id = $1")
+            .bind(post.id)
+            .execute(&db.pool)
+            .await;
+        return;
+    }
+
+    post.audio_processing_status = Some("PENDING".to_string());
+}
+
+fn attachment_json(row: &sqlx::postgres::PgRow) -> serde_json::Value {
+    let id: Uuid = row.get("id");
+    let post_id: Uuid = row.get("post_id");
+
+    json!({
+        "id": id,
+        "postId": post_id,
+        "uploadId": row.get::<Uuid, _>("upload_id"),
+        "filename": row.get::<String, _>("filename"),
+        "size": row.get::<i64, _>("size_bytes"),
+        "mimeType": row.get::<String, _>("mime_type"),
+        "createdAt": row.get::<DateTime<Utc>, _>("created_at"),
+        "downloadUrl": format!("/api/posts/{}/attachments/{}/download", post_id, id)
+    })
+}
+
+/// Fill in `attachments` for each post. Metadata is always included; downloads are
+/// gated separately through signed URLs.
+async fn load_post_attachments(
+    db: &Database,
+    posts: &mut [CreatorPostResponse],
+) -> Result<(), StatusCode> {
+    if posts.is_empty() {
+        return Ok(());
+    }
+
+    let post_ids: Vec<Uuid> = posts.iter().map(|post| post.id).collect();
+    let rows = sqlx::query(
+        r#"
+        SELECT id, post_id, upload_id, filename, size_bytes, mime_type, created_at
+        FROM post_attachments
+        WHERE post_id = ANY($1)
+        ORDER BY created_at ASC
+        "#,
+    )
+    .bind(&post_ids)
+    .fetch_all(&db.pool)
+    .await
+    .map_err(|e| {
+        error!("Failed to load post attachments: {:?}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    let mut by_post: std::collections::HashMap<Uuid, Vec<serde_json::Value>> =
+        std::collections::HashMap::new();
+    for row in &rows {
+        by_post
+            .entry(row.get("post_id"))
+            .or_default()
+            .push(attachment_json(row));
+    }
+
+    for post in posts.iter_mut() {
+        post.attachments = Some(serde_json::Value::Array(
+            by_post.remove(&post.id).unwrap_or_default(),
+        ));
+    }
+
+    Ok(())
+}
+
 fn generate_excerpt(content: &str) -> Option<String> {
     let trimmed = content.trim();
     if trimmed.is_empty() {
@@ -809,6 +1865,14 @@ async fn fetch_post_with_author(db: &Database, post_id: Uuid) -> Result<PostReco
             p.video_url,
             p.audio_url,
             p.is_premium,
+            p.unlock_price,
+            p.unlock_currency,
+            p.published,
+            p.published_at,
+            p.audio_duration_seconds,
+            p.audio_waveform,
+            p.audio_processing_status,
+            cu.campaign_id,
             p.created_at,
             p.updated_at,
             u.name as author_name,
@@ -816,9 +1880,11 @@ async fn fetch_post_with_author(db: &Database, post_id: Uuid) -> Result<PostReco
             u.avatar as author_avatar,
             u.is_creator as author_is_creator,
             COALESCE(l.like_count, 0) as like_count,
-            COALESCE(c.comment_count, 0) as comment_count
+            COALESCE(c.comment_count, 0) as comment_count,
+            false as user_liked
         FROM posts p
         LEFT JOIN users u ON p.user_id = u.id
+        LEFT JOIN campaign_updates cu ON cu.post_id = p.id
         LEFT JOIN (SELECT post_id, COUNT(*) as like_count FROM post_likes GROUP BY post_id) l ON l.post_id = p.id
         LEFT JOIN (SELECT post_id, COUNT(*) as comment_count FROM post_comments GROUP BY post_id) c ON c.post_id = p.id
         WHERE p.id = $1
@@ -926,6 +1992,7 @@ async fn get_post_comments(
             pc.id,
             pc.user_id,
             pc.content,
+            pc.parent_id,
             pc.created_at,
             u.username,
             u.avatar_url
@@ -947,6 +2014,7 @@ async fn get_post_comments(
                 "id": row.try_get::<Uuid, _>("id").unwrap(),
                 "userId": row.try_get::<String, _>("user_id").unwrap(),
                 "content": row.try_get::<String, _>("content").unwrap(),
+                "parentId": row.try_get::<Option<Uuid>, _>("parent_id").ok().flatten(),
                 "createdAt": row.try_get::<chrono::DateTime<chrono::Utc>, _>("created_at").unwrap(),
                 "user": {
                     "username": row.try_get::<Option<String>, _>("username").ok().flatten(),
@@ -972,18 +2040,41 @@ async fn add_post_comment(
     let content = payload["content"]
         .as_str()
         .ok_or(StatusCode::BAD_REQUEST)?;
+    let parent_id = match payload["parentId"].as_str() {
+        Some(parent_id) => Some(Uuid::parse_str(parent_id).map_err(|_| StatusCode::BAD_REQUEST)?),
+        None => None,
+    };
+
+    if let Some(parent_id) = parent_id {
+        let parent_on_post = sqlx::query_scalar::<_, bool>(
+            "SELECT EXISTS(SELECT 1 FROM post_comments WHERE id = $1 AND post_id = $2)",
+        )
+        .bind(parent_id)
+        .bind(id)
+        .fetch_one(&db.pool)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+        if !parent_on_post {
+            return Err(StatusCode::BAD_REQUEST);
+        }
+    }
 
     // Insert comment and get the full comment data with user info
     let comment = sqlx::query(
         r#"
-        INSERT INTO post_comments (post_id, user_id, content, created_at)
-        VALUES ($1, $2, $3, NOW())
-        RETURNING id, user_id, content, created_at
+        INSERT INTO post_comments (post_id, user_id, content, parent_id, created_at)
+        VALUES ($1, $2, $3, $4, NOW())
+        RETURNING id, user_id, content, created_at,
+            (SELECT user_id FROM posts WHERE id = $1) AS post_owner_id,
+            (SELECT title FROM posts WHERE id = $1) AS post_title,
+            (SELECT user_id FROM post_comments WHERE id = $4) AS parent_author_id
         "#
     )
     .bind(id)
     .bind(&claims.sub)
     .bind(content)
+    .bind(parent_id)
     .fetch_one(&db.pool)
     .await
     .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
@@ -1001,12 +2092,38 @@ async fn add_post_comment(
     .await
     .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
+    let commenter = user
+        .try_get::<Option<String>, _>("name")
+        .ok()
+        .flatten()
+        .or_else(|| user.try_get::<Option<String>, _>("username").ok().flatten())
+        .unwrap_or_else(|| "Someone".to_string());
+    let post_owner_id = comment.try_get::<Option<String>, _>("post_owner_id").ok().flatten();
+    let parent_author_id = comment.try_get::<Option<String>, _>("parent_author_id").ok().flatten();
+    let post_title = comment.try_get::<Option<String>, _>("post_title").ok().flatten();
+    notify_comment(
+        &db,
+        CommentActivity {
+            comment_id: comment.try_get::<Uuid, _>("id").unwrap(),
+            commenter_id: &claims.sub,
+            commenter_name: &commenter,
+            content,
+            owner_id: post_owner_id.as_deref(),
+            parent_author_id: parent_author_id.as_deref(),
+            title: post_title.as_deref().unwrap_or("a post"),
+            link: Some(format!("/posts/{}", id)),
+            ids: json!({ "postId": id, "parentId": parent_id }),
+        },
+    )
+    .await;
+
     Ok(Json(json!({
         "success": true,
         "data": {
             "id": comment.try_get::<Uuid, _>("id").unwrap(),
             "userId": comment.try_get::<String, _>("user_id").unwrap(),
             "content": comment.try_get::<String, _>("content").unwrap(),
+            "parentId": parent_id,
             "createdAt": comment.try_get::<chrono::DateTime<chrono::Utc>, _>("created_at").unwrap(),
             "user": {
                 "username": user.try_get::<Option<String>, _>("username").ok().flatten(),
@@ -1036,3 +2153,520 @@ async fn delete_post_comment(
         "success": true
     })))
 }
+
+// Start a one-time Stripe checkout to unlock a single premium post
+async fn unlock_post(
+    State(db): State<Database>,
+    Path(id): Path<Uuid>,
+    claims: Claims,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let post = fetch_post_with_author(&db, id).await?;
+
+    if !is_post_visible(&post) {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    if post.user_id == claims.sub {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let price = match post.unlock_price {
+        Some(price) if post.is_premium && price > 0.0 => price,
+        _ => return Err(StatusCode::BAD_REQUEST),
+    };
+    let currency = post
+        .unlock_currency
+        .clone()
+        .unwrap_or_else(|| "USD".to_string());
+
+    let already_unlocked = sqlx::query_scalar::<_, bool>(
+        "SELECT EXISTS(SELECT 1 FROM post_unlocks WHERE post_id = $1 AND user_id = $2 AND status = 'COMPLETED')",
+    )
+    .bind(id)
+    .bind(&claims.sub)
+    .fetch_one(&db.pool)
+    .await
+    .map_err(|e| {
+        error!("Failed to check post unlock for {}: {:?}", id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    if already_unlocked {
+        return Ok(Json(json!({
+            "success": true,
+            "data": {
+                "postId": id,
+                "status": "COMPLETED"
+            }
+        })));
+    }
+
+    let stripe_secret = stripe_secret()?;
+
+    let frontend_url =
+        std::env::var("FRONTEND_URL").unwrap_or_else(|_| "http://localhost:3000".to_string());
+    let success_url = format!(
+        "{}/posts/{}?unlock_session_id={{CHECKOUT_SESSION_ID}}",
+        frontend_url, id
+    );
+    let cancel_url = format!("{}/posts/{}?cancelled=true", frontend_url, id);
+
+    let amount_cents = (price * 100.0).round() as i64;
+    if amount_cents <= 0 {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let platform_fee =
+        quote_platform_fee(&db, ProductType::Product, &post.user_id, amount_cents).await?;
+
+    let mut form_data = vec![
+        ("mode".to_string(), "payment".to_string()),
+        ("success_url".to_string(), success_url),
+        ("cancel_url".to_string(), cancel_url),
+        (
+            "line_items[0][price_data][currency]".to_string(),
+            currency.to_lowercase(),
+        ),
+        (
+            "line_items[0][price_data][product_data][name]".to_string(),
+            post.title.clone(),
+        ),
+        (
+            "line_items[0][price_data][unit_amount]".to_string(),
+            amount_cents.to_string(),
+        ),
+        ("line_items[0][quantity]".to_string(), "1".to_string()),
+        ("payment_method_types[0]".to_string(), "card".to_string()),
+        ("metadata[user_id]".to_string(), claims.sub.clone()),
+        ("metadata[post_id]".to_string(), id.to_string()),
+        ("metadata[type]".to_string(), "post_unlock".to_string()),
+    ];
+    form_data.extend(platform_fee.checkout_params());
+
+    let client = reqwest::Client::new();
+    let response = client
+        .post(stripe_url("/v1/checkout/sessions"))
+        .header("Authorization", format!("Bearer {}", stripe_secret))
+        .form(&form_data)
+        .send()
+        .await
+        .map_err(|e| {
+            error!("Failed to create Stripe checkout session: {:?}", e);
+            StatusCode::BAD_GATEWAY
+        })?;
+
+    let status = response.status();
+    if !status.is_success() {
+        let body = response.text().await.unwrap_or_default();
+        error!(
+            "Stripe checkout session creation failed with status {}: {}",
+            status, body
+        );
+        return Err(StatusCode::BAD_GATEWAY);
+    }
+
+    let session: serde_json::Value = response.json().await.map_err(|e| {
+        error!("Failed to parse Stripe checkout session response: {:?}", e);
+        StatusCode::BAD_GATEWAY
+    })?;
+
+    let checkout_url = session
+        .get("url")
+        .and_then(|value| value.as_str())
+        .map(str::to_string)
+        .ok_or(StatusCode::BAD_GATEWAY)?;
+
+    let session_id = session
+        .get("id")
+        .and_then(|value| value.as_str())
+        .map(str::to_string)
+        .ok_or(StatusCode::BAD_GATEWAY)?;
+
+    let payment_intent_id = extract_payment_intent_id(&session);
+
+    let unlock = sqlx::query(
+        r#"
+        INSERT INTO post_unlocks (
+            post_id,
+            user_id,
+            amount,
+            currency,
+            status,
+            stripe_checkout_session_id,
+            stripe_payment_intent_id
+        )
+        VALUES ($1, $2, $3, $4, 'PENDING', $5, $6)
+        ON CONFLICT (post_id, user_id) DO UPDATE
+        SET amount = EXCLUDED.amount,
+            currency = EXCLUDED.currency,
+            status = 'PENDING',
+            stripe_checkout_session_id = EXCLUDED.stripe_checkout_session_id,
+            stripe_payment_intent_id = EXCLUDED.stripe_payment_intent_id,
+            updated_at = NOW()
+        RETURNING id, status
+        "#,
+    )
+    .bind(id)
+    .bind(&claims.sub)
+    .bind(price)
+    .bind(&currency)
+    .bind(&session_id)
+    .bind(payment_intent_id.clone())
+    .fetch_one(&db.pool)
+    .await
+    .map_err(|e| {
+        error!("Failed to store post unlock: {:?}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    platform_fee
+        .record(
+            &db,
+            LedgerSource {
+                source_type: "POST_UNLOCK",
+                source_id: unlock
+                    .try_get::<Uuid, _>("id")
+                    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+                    .to_string(),
+                payer_id: &claims.sub,
+                currency: &currency,
+                stripe_checkout_session_id: Some(&session_id),
+                stripe_payment_intent_id: payment_intent_id.as_deref(),
+            },
+        )
+        .await?;
+
+    Ok(Json(json!({
+        "success": true,
+        "data": {
+            "unlockId": unlock.try_get::<Uuid, _>("id").ok(),
+            "postId": id,
+            "status": unlock.try_get::<String, _>("status").ok(),
+            "checkoutUrl": checkout_url,
+            "amount": price,
+            "currency": currency,
+            "stripeSessionId": session_id,
+            "stripePaymentIntentId": payment_intent_id
+        }
+    })))
+}
+
+// Confirm a post unlock after the Stripe checkout redirect
+async fn confirm_post_unlock(
+    State(db): State<Database>,
+    claims: Claims,
+    Json(payload): Json<ConfirmPostUnlockRequest>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    if payload.session_id.trim().is_empty() {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let unlock = sqlx::query(
+        r#"
+        SELECT id, post_id, amount, currency, status
+        FROM post_unlocks
+        WHERE stripe_checkout_session_id = $1 AND user_id = $2
+        LIMIT 1
+        "#,
+    )
+    .bind(&payload.session_id)
+    .bind(&claims.sub)
+    .fetch_optional(&db.pool)
+    .await
+    .map_err(|e| {
+        error!(
+            "Failed to load post unlock for session {}: {:?}",
+            payload.session_id, e
+        );
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?
+    .ok_or(StatusCode::NOT_FOUND)?;
+
+    let unlock_id: Uuid = unlock
+        .try_get("id")
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let post_id: Uuid = unlock
+        .try_get("post_id")
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let mut status: String = unlock
+        .try_get("status")
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    if status != "COMPLETED" {
+        let stripe_secret = stripe_secret()?;
+
+        let client = reqwest::Client::new();
+        let response = client
+            .get(stripe_url(&format!("/v1/checkout/sessions/{}", payload.session_id)))
+            .header("Authorization", format!("Bearer {}", stripe_secret))
+            .query(&[("expand[]", "payment_intent")])
+            .send()
+            .await
+            .map_err(|e| {
+                error!(
+                    "Failed to contact Stripe for session {}: {:?}",
+                    payload.session_id, e
+                );
+                StatusCode::BAD_GATEWAY
+            })?;
+
+        if !response.status().is_success() {
+            let body = response.text().await.unwrap_or_default();
+            error!(
+                "Stripe returned error for session {}: {}",
+                payload.session_id, body
+            );
+            return Err(StatusCode::BAD_GATEWAY);
+        }
+
+        let session: serde_json::Value = response.json().await.map_err(|e| {
+            error!(
+                "Failed to parse Stripe session {} response: {:?}",
+                payload.session_id, e
+            );
+            StatusCode::BAD_GATEWAY
+        })?;
+
+        let payment_status = session
+            .get("payment_status")
+            .and_then(|value| value.as_str())
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+
+        if payment_status == "paid" || payment_status == "complete" {
+            let payment_intent_id = extract_payment_intent_id(&session);
+            status = sqlx::query_scalar::<_, String>(
+                r#"
+                UPDATE post_unlocks
+                SET status = 'COMPLETED',
+                    stripe_payment_intent_id = COALESCE($1, stripe_payment_intent_id),
+                    updated_at = NOW()
+                WHERE id = $2
+                RETURNING status
+                "#,
+            )
+            .bind(payment_intent_id.clone())
+            .bind(unlock_id)
+            .fetch_one(&db.pool)
+            .await
+            .map_err(|e| {
+                error!(
+                    "Failed to update post unlock {} after Stripe confirmation: {:?}",
+                    unlock_id, e
+                );
+                StatusCode::INTERNAL_SERVER_ERROR
+            })?;
+            settle_ledger_entries(&db, &payload.session_id, payment_intent_id.as_deref()).await?;
+        }
+    }
+
+    Ok(Json(json!({
+        "success": true,
+        "data": {
+            "unlockId": unlock_id,
+            "postId": post_id,
+            "status": status,
+            "amount": unlock.try_get::<f64, _>("amount").ok(),
+            "currency": unlock.try_get::<Option<String>, _>("currency").ok().flatten(),
+            "hasAccess": status == "COMPLETED"
+        }
+    })))
+}
+
+// List attachment metadata for a post
+async fn list_post_attachments(
+    State(db): State<Database>,
+    Path(id): Path<Uuid>,
+    MaybeClaims(maybe_claims): MaybeClaims,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let post = fetch_post_with_author(&db, id).await?;
+    let viewer_id = maybe_claims.map(|claims| claims.sub);
+
+    if !is_post_visible(&post) && viewer_id.as_deref() != Some(post.user_id.as_str()) {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    let mut posts = vec![map_post(post)];
+    load_post_attachments(&db, &mut posts).await?;
+
+    Ok(Json(json!({
+        "success": true,
+        "data": posts.pop().and_then(|post| post.attachments).unwrap_or_else(|| json!([]))
+    })))
+}
+
+// Upload one or more files via multipart and attach them to a post
+async fn upload_post_attachments(
+    State(db): State<Database>,
+    Path(id): Path<Uuid>,
+    claims: Claims,
+    mut multipart: Multipart,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let owns_post = sqlx::query_scalar::<_, bool>(
+        "SELECT EXISTS(SELECT 1 FROM posts WHERE id = $1 AND user_id = $2)",
+    )
+    .bind(id)
+    .bind(&claims.sub)
+    .fetch_one(&db.pool)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    if !owns_post {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    let mut attachments = Vec::new();
+
+    while let Some(mut field) = multipart
+        .next_field()
+        .await
+        .map_err(|_| StatusCode::BAD_REQUEST)?
+    {
+        let filename = match field.file_name() {
+            Some(name) if !name.trim().is_empty() => std::path::Path::new(name)
+                .file_name()
+                .and_then(|name| name.to_str())
+                .unwrap_or("attachment")
+                .to_string(),
+            _ => continue,
+        };
+        let mime_type = field
+            .content_type()
+            .map(|mime| mime.to_string())
+            .unwrap_or_else(|| "application/octet-stream".to_string());
+
+        let mut bytes: Vec<u8> = Vec::new();
+        while let Some(chunk) = field.chunk().await.map_err(|_| StatusCode::BAD_REQUEST)? {
+            if bytes.len() + chunk.len() > ATTACHMENT_MAX_BYTES {
+                return Err(StatusCode::PAYLOAD_TOO_LARGE);
+            }
+            bytes.extend_from_slice(&chunk);
+        }
+
+        if bytes.is_empty() {
+            return Err(StatusCode::BAD_REQUEST);
+        }
+
+        let size_bytes = bytes.len() as i64;
+        let stored = storage::store_private_file("attachments", Some(&filename), &mime_type, bytes)
+            .await
+            .map_err(|e| {
+                error!("Failed to store attachment for post {}: {:?}", id, e);
+                StatusCode::INTERNAL_SERVER_ERROR
+            })?;
+
+        let row = sqlx::query(
+            r#"
+            INSERT INTO post_attachments (post_id, user_id, upload_id, filename, size_bytes, mime_type, storage_key)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            RETURNING id, post_id, upload_id, filename, size_bytes, mime_type, created_at
+            "#,
+        )
+        .bind(id)
+        .bind(&claims.sub)
+        .bind(stored.upload_id)
+        .bind(&filename)
+        .bind(size_bytes)
+        .bind(&mime_type)
+        .bind(&stored.storage_key)
+        .fetch_one(&db.pool)
+        .await
+        .map_err(|e| {
+            error!("Failed to save attachment for post {}: {:?}", id, e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+        attachments.push(attachment_json(&row));
+    }
+
+    if attachments.is_empty() {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    Ok(Json(json!({
+        "success": true,
+        "data": attachments
+    })))
+}
+
+// Remove an attachment from a post
+async fn delete_post_attachment(
+    State(db): State<Database>,
+    Path((id, attachment_id)): Path<(Uuid, Uuid)>,
+    claims: Claims,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let storage_key = sqlx::query_scalar::<_, String>(
+        r#"
+        DELETE FROM post_attachments
+        WHERE id = $1 AND post_id = $2 AND user_id = $3
+        RETURNING storage_key
+        "#,
+    )
+    .bind(attachment_id)
+    .bind(id)
+    .bind(&claims.sub)
+    .fetch_optional(&db.pool)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    .ok_or(StatusCode::NOT_FOUND)?;
+
+    if let Err(e) = storage::delete_private_file(&storage_key).await {
+        error!("Failed to delete attachment file {}: {:?}", storage_key, e);
+    }
+
+    Ok(Json(json!({
+        "success": true,
+        "message": "Attachment deleted successfully"
+    })))
+}
+
+// Issue a short-lived signed download URL for viewers with access to the post
+async fn get_attachment_download(
+    State(db): State<Database>,
+    Path((id, attachment_id)): Path<(Uuid, Uuid)>,
+    MaybeClaims(maybe_claims): MaybeClaims,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let viewer_id = maybe_claims.map(|claims| claims.sub);
+    let post = fetch_post_with_author(&db, id).await?;
+
+    if !is_post_visible(&post) && viewer_id.as_deref() != Some(post.user_id.as_str()) {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    let posts = vec![map_post(post)];
+    let access = ViewerAccess::load(&db, viewer_id.clone(), &posts).await?;
+    if !access.can_view(&posts[0]) {
+        return Err(if viewer_id.is_some() {
+            StatusCode::FORBIDDEN
+        } else {
+            StatusCode::UNAUTHORIZED
+        });
+    }
+
+    let attachment = sqlx::query(
+        "SELECT filename, mime_type, storage_key FROM post_attachments WHERE id = $1 AND post_id = $2",
+    )
+    .bind(attachment_id)
+    .bind(id)
+    .fetch_optional(&db.pool)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    .ok_or(StatusCode::NOT_FOUND)?;
+
+    let storage_key: String = attachment.get("storage_key");
+    let (url, expires) = storage::create_signed_url(&storage_key, ATTACHMENT_URL_TTL_SECONDS)
+        .await
+        .map_err(|e| {
+            error!("Failed to sign attachment {}: {:?}", attachment_id, e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    Ok(Json(json!({
+        "success": true,
+        "data": {
+            "url": url,
+            "filename": attachment.get::<String, _>("filename"),
+            "mimeType": attachment.get::<String, _>("mime_type"),
+            "expiresAt": chrono::DateTime::<Utc>::from_timestamp(expires, 0)
+        }
+    })))
+}
@@ -0,0 +1,585 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::Json,
+    routing::get,
+    Router,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use uuid::Uuid;
+
+use crate::{
+    auth::Claims,
+    database::Database,
+    messaging::{self, DirectMessage, FilterAction, RequestStatus},
+    notification_service::{notify, NotificationKind},
+    routes::{
+        fees::{quote_platform_fee, LedgerSource, ProductType},
+        messages::{screen_outgoing, MAX_MESSAGE_CHARS},
+        payments::{refund_ledger_entry, RefundReason},
+        polls::ensure_creator,
+        stripe::{create_payment_intent, PaymentIntentSpec},
+    },
+};
+
+const MIN_PRICE_CENTS: i64 = 100;
+const MAX_PRICE_CENTS: i64 = 100_000;
+/// Longest a creator can take to reply, a week.
+const MAX_RESPONSE_HOURS: i32 = 7 * 24;
+const STATUSES: [&str; 7] = [
+    "PENDING",
+    "AWAITING_REPLY",
+    "ANSWERED",
+    "OVERDUE",
+    "REFUNDED",
+    "EXPIRED",
+    "CANCELLED",
+];
+
+/// What a creator charges for a priority message; `price_cents` is `None` when they don't take
+/// them.
+#[derive(Debug, Serialize, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+struct PrioritySettings {
+    price_cents: Option<i64>,
+    currency: String,
+    response_hours: i32,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+struct PriorityMessage {
+    id: Uuid,
+    conversation_id: Uuid,
+    message_id: Option<Uuid>,
+    sender_id: String,
+    creator_id: String,
+    /// The other side of the message: the sender in the paid inbox, the creator in sent ones.
+    name: Option<String>,
+    username: Option<String>,
+    avatar: Option<String>,
+    body: String,
+    amount_cents: i64,
+    currency: String,
+    status: String,
+    paid_at: Option<DateTime<Utc>>,
+    reply_due_at: Option<DateTime<Utc>>,
+    answered_at: Option<DateTime<Utc>>,
+    created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PrioritySettingsRequest {
+    /// Leave out to stop taking priority messages.
+    price_cents: Option<i64>,
+    currency: Option<String>,
+    response_hours: Option<i32>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SendPriorityMessageRequest {
+    recipient_id: String,
+    body: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct PriorityMessageQuery {
+    status: Option<String>,
+    page: Option<u32>,
+    limit: Option<u32>,
+}
+
+pub fn priority_routes() -> Router<Database> {
+    Router::new()
+        .route("/", get(list_received).post(send_priority_message))
+        .route("/sent", get(list_sent))
+        .route("/settings", get(get_settings).put(update_settings))
+        .route("/creators/:creator_id", get(get_creator_settings))
+}
+
+async fn load_settings(db: &Database, user_id: &str) -> Result<PrioritySettings, StatusCode> {
+    let settings = sqlx::query_as::<_, PrioritySettings>(
+        r#"
+        SELECT priority_price_cents AS price_cents, priority_currency AS currency,
+               priority_response_hours AS response_hours
+        FROM dm_settings WHERE user_id = $1
+        "#,
+    )
+    .bind(user_id)
+    .fetch_optional(&db.pool)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    Ok(settings.unwrap_or(PrioritySettings {
+        price_cents: None,
+        currency: "USD".to_string(),
+        response_hours: 48,
+    }))
+}
+
+async fn get_settings(
+    State(db): State<Database>,
+    claims: Claims,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let settings = load_settings(&db, &claims.sub).await?;
+    Ok(Json(json!({ "success": true, "data": settings })))
+}
+
+/// What fans see before paying: the price and how long the creator has to reply.
+async fn get_creator_settings(
+    State(db): State<Database>,
+    Path(creator_id): Path<String>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let settings = load_settings(&db, &creator_id).await?;
+    if settings.price_cents.is_none() {
+        return Err(StatusCode::NOT_FOUND);
+    }
+    Ok(Json(json!({ "success": true, "data": settings })))
+}
+
+async fn update_settings(
+    State(db): State<Database>,
+    claims: Claims,
+    Json(payload): Json<PrioritySettingsRequest>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    ensure_creator(&db, &claims.sub).await?;
+    let currency = payload
+        .currency
+        .as_deref()
+        .map(str::trim)
+        .unwrap_or("USD")
+        .to_uppercase();
+    let response_hours = payload.response_hours.unwrap_or(48);
+    if payload
+        .price_cents
+        .is_some_and(|price| !(MIN_PRICE_CENTS..=MAX_PRICE_CENTS).contains(&price))
+        || currency.len() != 3
+        || !currency.chars().all(|c| c.is_ascii_alphabetic())
+        || !(1..=MAX_RESPONSE_HOURS).contains(&response_hours)
+    {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    // Messages already paid for keep the deadline they were sent with
+    let settings = sqlx::query_as::<_, PrioritySettings>(
+        r#"
+        INSERT INTO dm_settings (user_id, priority_price_cents, priority_currency, priority_response_hours)
+        VALUES ($1, $2, $3, $4)
+        ON CONFLICT (user_id) DO UPDATE
+        SET priority_price_cents = EXCLUDED.priority_price_cents,
+            priority_currency = EXCLUDED.priority_currency,
+            priority_response_hours = EXCLUDED.priority_response_hours,
+            updated_at = NOW()
+        RETURNING priority_price_cents AS price_cents, priority_currency AS currency,
+                  priority_response_hours AS response_hours
+        "#,
+    )
+    .bind(&claims.sub)
+    .bind(payload.price_cents)
+    .bind(&currency)
+    .bind(response_hours)
+    .fetch_one(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to update priority settings of {}: {}", claims.sub, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    Ok(Json(json!({ "success": true, "data": settings })))
+}
+
+/// Pay for a message to a creator. The message is posted to their conversation when the payment
+/// succeeds, with a reply due within the creator's response window.
+async fn send_priority_message(
+    State(db): State<Database>,
+    claims: Claims,
+    Json(payload): Json<SendPriorityMessageRequest>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let creator_id = payload.recipient_id.trim();
+    let body = payload.body.trim();
+    if creator_id.is_empty()
+        || creator_id == claims.sub
+        || body.is_empty()
+        || body.chars().count() > MAX_MESSAGE_CHARS
+    {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    // Flagged words are checked again once the message is posted
+    screen_outgoing(&db, &claims.sub, body).await?;
+    let settings = load_settings(&db, creator_id).await?;
+    let amount_cents = settings.price_cents.ok_or(StatusCode::NOT_FOUND)?;
+
+    let conversation_id = messaging::open_direct_conversation(&db, &claims.sub, creator_id)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to open conversation with {}: {}", creator_id, e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+    // Paying doesn't get around a declined request
+    let declined = sqlx::query_scalar::<_, bool>(
+        r#"
+        SELECT EXISTS(
+            SELECT 1 FROM conversation_participants
+            WHERE conversation_id = $1 AND user_id = $2 AND request_status = $3
+        )
+        "#,
+    )
+    .bind(conversation_id)
+    .bind(creator_id)
+    .bind(RequestStatus::Declined.as_str())
+    .fetch_one(&db.pool)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    if declined {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    let platform_fee = quote_platform_fee(&db, ProductType::Message, creator_id, amount_cents).await?;
+    let priority_message_id = sqlx::query_scalar::<_, Uuid>(
+        r#"
+        INSERT INTO priority_messages
+            (conversation_id, sender_id, creator_id, body, amount_cents, currency, response_hours)
+        VALUES ($1, $2, $3, $4, $5, $6, $7)
+        RETURNING id
+        "#,
+    )
+    .bind(conversation_id)
+    .bind(&claims.sub)
+    .bind(creator_id)
+    .bind(body)
+    .bind(amount_cents)
+    .bind(&settings.currency)
+    .bind(settings.response_hours)
+    .fetch_one(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to store priority message to {}: {}", creator_id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    let payment_intent = create_payment_intent(PaymentIntentSpec {
+        amount_cents,
+        currency: &settings.currency,
+        description: "Priority message",
+        metadata: vec![
+            ("user_id", claims.sub.clone()),
+            ("priority_message_id", priority_message_id.to_string()),
+        ],
+        platform_fee: &platform_fee,
+    })
+    .await?;
+    let payment_intent_id = payment_intent
+        .get("id")
+        .and_then(|value| value.as_str())
+        .ok_or(StatusCode::BAD_GATEWAY)?;
+    sqlx::query("UPDATE priority_messages SET stripe_payment_intent_id = $2 WHERE id = $1")
+        .bind(priority_message_id)
+        .bind(payment_intent_id)
+        .execute(&db.pool)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let payment_id = platform_fee
+        .record(
+            &db,
+            LedgerSource {
+                source_type: "PRIORITY_MESSAGE",
+                source_id: priority_message_id.to_string(),
+                payer_id: &claims.sub,
+                currency: &settings.currency,
+                stripe_checkout_session_id: None,
+                stripe_payment_intent_id: Some(payment_intent_id),
+            },
+        )
+        .await?;
+
+    Ok(Json(json!({
+        "success": true,
+        "data": {
+            "priorityMessageId": priority_message_id,
+            "paymentId": payment_id,
+            "conversationId": conversation_id,
+            "status": "PENDING",
+            "amountCents": amount_cents,
+            "currency": settings.currency,
+            "responseHours": settings.response_hours,
+            "clientSecret": payment_intent.get("client_secret"),
+            "stripePaymentIntentId": payment_intent_id
+        }
+    })))
+}
+
+/// The paid inbox: priority messages sent to the caller, with those waiting on a reply first.
+async fn list_received(
+    State(db): State<Database>,
+    Query(params): Query<PriorityMessageQuery>,
+    claims: Claims,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    list_priority_messages(&db, &claims.sub, params, true).await
+}
+
+async fn list_sent(
+    State(db): State<Database>,
+    Query(params): Query<PriorityMessageQuery>,
+    claims: Claims,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    list_priority_messages(&db, &claims.sub, params, false).await
+}
+
+async fn list_priority_messages(
+    db: &Database,
+    user_id: &str,
+    params: PriorityMessageQuery,
+    received: bool,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let status = params.status.as_deref().map(str::to_uppercase);
+    if status.as_deref().is_some_and(|status| !STATUSES.contains(&status)) {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let page = params.page.unwrap_or(1).max(1);
+    let limit = params.limit.unwrap_or(20).clamp(1, 100);
+    let offset = ((page - 1) * limit) as i64;
+
+    // Unpaid messages are the sender's business only
+    let (owner_column, other_column) = if received {
+        ("creator_id", "sender_id")
+    } else {
+        ("sender_id", "creator_id")
+    };
+    let filter = format!(
+        "pm.{} = $1 AND ($2::TEXT IS NULL OR pm.status = $2) AND ($3 OR pm.status NOT IN ('PENDING', 'CANCELLED'))",
+        owner_column
+    );
+    let messages = sqlx::query_as::<_, PriorityMessage>(&format!(
+        r#"
+        SELECT pm.id, pm.conversation_id, pm.message_id, pm.sender_id, pm.creator_id,
+               COALESCE(u.display_name, u.name) AS name, u.username, u.avatar_url AS avatar,
+               pm.body, pm.amount_cents, pm.currency, pm.status, pm.paid_at, pm.reply_due_at,
+               pm.answered_at, pm.created_at
+        FROM priority_messages pm
+        JOIN users u ON u.id = pm.{}
+        WHERE {}
+        ORDER BY (pm.status = 'AWAITING_REPLY') DESC,
+                 CASE WHEN pm.status = 'AWAITING_REPLY' THEN pm.reply_due_at END ASC,
+                 pm.created_at DESC
+        LIMIT $4 OFFSET $5
+        "#,
+        other_column, filter
+    ))
+    .bind(user_id)
+    .bind(&status)
+    .bind(!received)
+    .bind(limit as i64)
+    .bind(offset)
+    .fetch_all(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to list priority messages of {}: {}", user_id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    let total = sqlx::query_scalar::<_, i64>(&format!(
+        "SELECT COUNT(*) FROM priority_messages pm WHERE {}",
+        filter
+    ))
+    .bind(user_id)
+    .bind(&status)
+    .bind(!received)
+    .fetch_one(&db.pool)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(json!({
+        "success": true,
+        "data": messages,
+        "pagination": {
+            "page": page,
+            "limit": limit,
+            "total": total,
+            "pages": ((total as f64) / (limit as f64)).ceil() as u32,
+        }
+    })))
+}
+
+/// Post a priority message once its payment succeeds and start the creator's reply window.
+/// Paying for a message accepts it into the creator's inbox if it was a pending request.
+pub(crate) async fn complete_priority_message(
+    db: &Database,
+    payment_intent_id: &str,
+) -> Result<(), StatusCode> {
+    let db_error = |e: sqlx::Error| {
+        tracing::error!("Failed to complete priority message {}: {}", payment_intent_id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    };
+    let mut tx = db.pool.begin().await.map_err(db_error)?;
+    let pending = sqlx::query_as::<_, (Uuid, Uuid, String, String, String)>(
+        r#"
+        SELECT id, conversation_id, sender_id, creator_id, body FROM priority_messages
+        WHERE stripe_payment_intent_id = $1 AND status = 'PENDING'
+        FOR UPDATE
+        "#,
+    )
+    .bind(payment_intent_id)
+    .fetch_optional(&mut tx)
+    .await
+    .map_err(db_error)?;
+    // Already posted on an earlier delivery of the event
+    let Some((id, conversation_id, sender_id, creator_id, body)) = pending else {
+        return Ok(());
+    };
+
+    let message = sqlx::query_as::<_, DirectMessage>(
+        r#"
+        INSERT INTO messages (conversation_id, sender_id, body)
+        VALUES ($1, $2, $3)
+        RETURNING id, conversation_id, sender_id, body, created_at
+        "#,
+    )
+    .bind(conversation_id)
+    .bind(&sender_id)
+    .bind(&body)
+    .fetch_one(&mut tx)
+    .await
+    .map_err(db_error)?;
+    let reply_due_at = sqlx::query_scalar::<_, DateTime<Utc>>(
+        r#"
+        UPDATE priority_messages
+        SET status = 'AWAITING_REPLY', message_id = $2, paid_at = NOW(),
+            payment_intent_status = 'succeeded', payment_error = NULL,
+            reply_due_at = NOW() + make_interval(hours => response_hours), updated_at = NOW()
+        WHERE id = $1
+        RETURNING reply_due_at
+        "#,
+    )
+    .bind(id)
+    .bind(message.id)
+    .fetch_one(&mut tx)
+    .await
+    .map_err(db_error)?;
+    sqlx::query("UPDATE conversations SET last_message_at = $2 WHERE id = $1")
+        .bind(conversation_id)
+        .bind(message.created_at)
+        .execute(&mut tx)
+        .await
+        .map_err(db_error)?;
+    sqlx::query(
+        r#"
+        UPDATE conversation_participants
+        SET last_read_at = $3, last_delivered_at = $3
+        WHERE conversation_id = $1 AND user_id = $2
+        "#,
+    )
+    .bind(conversation_id)
+    .bind(&sender_id)
+    .bind(message.created_at)
+    .execute(&mut tx)
+    .await
+    .map_err(db_error)?;
+    sqlx::query(
+        r#"
+        UPDATE conversation_participants SET request_status = $3
+        WHERE conversation_id = $1 AND user_id = $2 AND request_status = $4
+        "#,
+    )
+    .bind(conversation_id)
+    .bind(&creator_id)
+    .bind(RequestStatus::Accepted.as_str())
+    .bind(RequestStatus::Pending.as_str())
+    .execute(&mut tx)
+    .await
+    .map_err(db_error)?;
+    tx.commit().await.map_err(db_error)?;
+
+    let sender_name = sqlx::query_scalar::<_, Option<String>>(
+        "SELECT COALESCE(display_name, name, username) FROM users WHERE id = $1",
+    )
+    .bind(&sender_id)
+    .fetch_one(&db.pool)
+    .await
+    .ok()
+    .flatten()
+    .unwrap_or_else(|| "Someone".to_string());
+    messaging::deliver_message(db, &message, &[], &sender_name).await;
+    if let Ok(Some((FilterAction::Flag, word))) = messaging::screen_message(db, &message.body).await {
+        messaging::flag_message(db, &message, &word).await;
+    }
+    notify(
+        db,
+        &creator_id,
+        NotificationKind::Message,
+        json!({
+            "message": format!("{} sent you a priority message", sender_name),
+            "link": format!("/messages/{}", conversation_id),
+            "conversationId": conversation_id,
+            "messageId": message.id,
+            "priorityMessageId": id,
+            "replyDueAt": reply_due_at,
+        }),
+    )
+    .await;
+    Ok(())
+}
+
+/// Refund priority messages whose reply window closed without an answer. A refund that fails
+/// for good closes the message as `EXPIRED`; anything else is tried again on the next run.
+pub(crate) async fn refund_overdue_priority_messages(db: &Database) -> anyhow::Result<()> {
+    sqlx::query(
+        r#"
+        UPDATE priority_messages SET status = 'OVERDUE', updated_at = NOW()
+        WHERE status = 'AWAITING_REPLY' AND reply_due_at <= NOW()
+        "#,
+    )
+    .execute(&db.pool)
+    .await?;
+
+    let overdue = sqlx::query_as::<_, (Uuid, Option<Uuid>)>(
+        r#"
+        SELECT pm.id, l.id FROM priority_messages pm
+        LEFT JOIN ledger_entries l
+            ON l.source_type = 'PRIORITY_MESSAGE' AND l.source_id = pm.id::TEXT
+        WHERE pm.status = 'OVERDUE'
+        ORDER BY pm.reply_due_at
+        LIMIT 100
+        "#,
+    )
+    .fetch_all(&db.pool)
+    .await?;
+    for (id, ledger_entry_id) in overdue {
+        let refunded = match ledger_entry_id {
+            Some(ledger_entry_id) => {
+                refund_ledger_entry(
+                    db,
+                    ledger_entry_id,
+                    None,
+                    RefundReason::ResponseOverdue,
+                    Some("The creator didn't reply in time".to_string()),
+                    "SYSTEM",
+                )
+                .await
+            }
+            None => Err(StatusCode::NOT_FOUND),
+        };
+        match refunded {
+            Ok(_) => {}
+            Err(
+                status @ (StatusCode::CONFLICT
+                | StatusCode::UNPROCESSABLE_ENTITY
+                | StatusCode::NOT_FOUND),
+            ) => {
+                tracing::warn!("Priority message {} could not be refunded ({})", id, status);
+                sqlx::query(
+                    "UPDATE priority_messages SET status = 'EXPIRED', updated_at = NOW() WHERE id = $1 AND status = 'OVERDUE'",
+                )
+                .bind(id)
+                .execute(&db.pool)
+                .await?;
+            }
+            Err(status) => {
+                tracing::warn!("Refund of priority message {} failed ({}); will retry", id, status);
+            }
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,461 @@
+use std::path::{Component, PathBuf};
+
+use axum::{
+    body::Body,
+    extract::{Path, Query, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Json, Response},
+    routing::get,
+    Router,
+};
+use hmac::{Hmac, Mac};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sha2::Sha256;
+use tokio_util::io::ReaderStream;
+use uuid::Uuid;
+
+use crate::{
+    auth::Claims,
+    config::Config,
+    database::Database,
+    models::{Product, Purchase},
+    remote_fetch,
+    storage::{self, PrivateFileLocation},
+    routes::{
+        product_variants::{apply_variant, ProductVariant},
+        product_versions::find_version,
+    },
+};
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// How long a download link works once issued.
+pub(crate) const DOWNLOAD_LINK_TTL_SECONDS: i64 = 300;
+/// How long a file on a creator's host may take to stream through.
+const REMOTE_FILE_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(60 * 60);
+
+/// What a signed download link grants: one product file, through a purchase or to the creator.
+pub(crate) struct DownloadGrant {
+    pub product_id: Uuid,
+    /// None for the creator's own downloads, which are not limited.
+    pub purchase_id: Option<Uuid>,
+    pub variant_id: Option<Uuid>,
+    /// An earlier version of the file; the current file when empty.
+    pub version_id: Option<Uuid>,
+    pub expires: i64,
+}
+
+#[derive(Debug, Deserialize)]
+struct FileQuery {
+    token: String,
+}
+
+/// A logged download, as the creator sees it.
+#[derive(Debug, Serialize, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+struct DownloadLogEntry {
+    id: Uuid,
+    purchase_id: Option<Uuid>,
+    variant_id: Option<Uuid>,
+    version_id: Option<Uuid>,
+    user_id: String,
+    username: Option<String>,
+    ip_address: Option<String>,
+    user_agent: Option<String>,
+    created_at: DateTime<Utc>,
+}
+
+pub fn product_file_routes() -> Router<Database> {
+    Router::new()
+        .route("/:id/file", get(serve_product_file))
+        .route("/:id/downloads", get(list_product_downloads))
+}
+
+impl DownloadGrant {
+    fn mac(&self) -> HmacSha256 {
+        let secret = Config::from_env().map(|config| config.jwt_secret).unwrap_or_default();
+        let mut mac =
+            HmacSha256::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+        mac.update(
+            format!(
+                "product-download:{}:{}:{}:{}:{}",
+                self.product_id,
+                self.purchase_id.map(|id| id.to_string()).unwrap_or_default(),
+                self.variant_id.map(|id| id.to_string()).unwrap_or_default(),
+                self.version_id.map(|id| id.to_string()).unwrap_or_default(),
+                self.expires
+            )
+            .as_bytes(),
+        );
+        mac
+    }
+
+    /// A relative URL, like the other signed private-file links.
+    pub(crate) fn url(&self) -> String {
+        format!(
+            "/api/products/{}/file?token={}.{}.{}.{}.{}",
+            self.product_id,
+            self.purchase_id.map(|id| id.to_string()).unwrap_or_else(|| "owner".to_string()),
+            self.variant_id.map(|id| id.to_string()).unwrap_or_else(|| "none".to_string()),
+            self.version_id.map(|id| id.to_string()).unwrap_or_else(|| "current".to_string()),
+            self.expires,
+            hex::encode(self.mac().finalize().into_bytes())
+        )
+    }
+
+    /// The grant behind an unexpired token with a matching signature, along with the signature.
+    fn verify(product_id: Uuid, token: &str) -> Option<(Self, String)> {
+        let mut parts = token.split('.');
+        let (purchase, variant, version, expires, signature) = (
+            parts.next()?,
+            parts.next()?,
+            parts.next()?,
+            parts.next()?,
+            parts.next()?,
+        );
+        if parts.next().is_some() {
+            return None;
+        }
+        let grant = DownloadGrant {
+            product_id,
+            purchase_id: match purchase {
+                "owner" => None,
+                id => Some(Uuid::parse_str(id).ok()?),
+            },
+            variant_id: match variant {
+                "none" => None,
+                id => Some(Uuid::parse_str(id).ok()?),
+            },
+            version_id: match version {
+                "current" => None,
+                id => Some(Uuid::parse_str(id).ok()?),
+            },
+            expires: expires.parse().ok()?,
+        };
+        if grant.expires < chrono::Utc::now().timestamp() {
+            return None;
+        }
+
+        grant.mac().verify_slice(&hex::decode(signature).ok()?).ok()?;
+        Some((grant, signature.to_string()))
+    }
+}
+
+/// Downloads made so far through a purchase, one per link used.
+pub(crate) async fn downloads_used(db: &Database, purchase_id: Uuid) -> Result<i64, StatusCode> {
+    sqlx::query_scalar("SELECT COUNT(*) FROM product_downloads WHERE purchase_id = $1")
+        .bind(purchase_id)
+        .fetch_one(&db.pool)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
+}
+
+/// Log the first use of a link, counting it against the purchase's download limit. The purchase
+/// row is locked while counting so parallel downloads cannot pass the limit; reopening a link
+/// that was already counted, e.g. to resume, is free.
+async fn record_download(
+    db: &Database,
+    product: &Product,
+    grant: &DownloadGrant,
+    signature: &str,
+    user_id: &str,
+    headers: &HeaderMap,
+) -> Result<(), StatusCode> {
+    let ip_address = headers
+        .get("x-forwarded-for")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.split(',').next())
+        .or_else(|| headers.get("x-real-ip").and_then(|value| value.to_str().ok()))
+        .map(|ip| ip.trim().chars().take(64).collect::<String>());
+    let user_agent = headers
+        .get(header::USER_AGENT)
+        .and_then(|value| value.to_str().ok());
+
+    let mut tx = db.pool.begin().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    if let Some(purchase_id) = grant.purchase_id {
+        sqlx::query("SELECT id FROM purchases WHERE id = $1 FOR UPDATE")
+            .bind(purchase_id)
+            .execute(&mut tx)
+            .await
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        let (used, counted) = sqlx::query_as::<_, (i64, bool)>(
+            r#"
+            SELECT COUNT(*), COALESCE(BOOL_OR(link_signature = $2), FALSE)
+            FROM product_downloads WHERE purchase_id = $1
+            "#,
+        )
+        .bind(purchase_id)
+        .bind(signature)
+        .fetch_one(&mut tx)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        if counted {
+            return Ok(());
+        }
+        if product.download_limit.is_some_and(|limit| used >= i64::from(limit)) {
+            return Err(StatusCode::FORBIDDEN);
+        }
+    }
+
+    sqlx::query(
+        r#"
+        INSERT INTO product_downloads (
+            product_id, purchase_id, variant_id, version_id, user_id, link_signature, ip_address,
+            user_agent
+        )
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+        ON CONFLICT (link_signature) DO NOTHING
+        "#,
+    )
+    .bind(product.id)
+    .bind(grant.purchase_id)
+    .bind(grant.variant_id)
+    .bind(grant.version_id)
+    .bind(user_id)
+    .bind(signature)
+    .bind(ip_address)
+    .bind(user_agent)
+    .execute(&mut tx)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to log download of product {}: {}", product.id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    tx.commit().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
+}
+
+/// Whether a product file URL can be served through signed links only: a file in private
+/// storage or on the creator's own host. Files under the public `/uploads` mount cannot.
+pub(crate) fn is_servable_file_url(url: &str) -> bool {
+    let url = url.trim();
+    url.is_empty()
+        || storage::private_storage_key(url).is_some()
+        || url.starts_with("https://")
+        || url.starts_with("http://")
+}
+
+/// Stream a product file from where its URL points: private storage for `private://` keys,
+/// otherwise the creator's host over HTTP, as long as that host is on the public internet.
+async fn open_file(url: &str) -> Result<(Body, Option<String>, Option<u64>), StatusCode> {
+    if let Some(storage_key) = storage::private_storage_key(url) {
+        let location = storage::locate_private_file(storage_key, DOWNLOAD_LINK_TTL_SECONDS)
+            .await
+            .map_err(|e| {
+                tracing::error!("Failed to locate product file {}: {:?}", storage_key, e);
+                StatusCode::NOT_FOUND
+            })?;
+        match location {
+            PrivateFileLocation::Local(path) => return open_local_file(&path).await,
+            PrivateFileLocation::Remote(signed_url) => {
+                let response = reqwest::Client::new()
+                    .get(&signed_url)
+                    .send()
+                    .await
+                    .and_then(|response| response.error_for_status())
+                    .map_err(|e| {
+                        tracing::error!("Failed to fetch product file {}: {}", storage_key, e);
+                        StatusCode::BAD_GATEWAY
+                    })?;
+                return Ok(stream_response(response));
+            }
+        }
+    }
+
+    if url.starts_with("http://") || url.starts_with("https://") {
+        let response = remote_fetch::get(url, REMOTE_FILE_TIMEOUT).await.map_err(|e| {
+            tracing::error!("Failed to fetch product file: {:?}", e);
+            StatusCode::BAD_GATEWAY
+        })?;
+        return Ok(stream_response(response));
+    }
+
+    // Files saved under the public uploads mount before product files were stored privately
+    let relative = url.strip_prefix("/uploads/").ok_or(StatusCode::NOT_FOUND)?;
+    let relative = std::path::Path::new(relative);
+    if relative
+        .components()
+        .any(|component| !matches!(component, Component::Normal(_)))
+    {
+        return Err(StatusCode::NOT_FOUND);
+    }
+    let upload_root =
+        PathBuf::from(std::env::var("UPLOAD_DIR").unwrap_or_else(|_| "uploads".to_string()));
+    open_local_file(&upload_root.join(relative)).await
+}
+
+fn stream_response(response: reqwest::Response) -> (Body, Option<String>, Option<u64>) {
+    let content_type = response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string);
+    let length = response.content_length();
+    let chunks = futures_util::stream::unfold(Some(response), |response| async move {
+        let mut response = response?;
+        match response.chunk().await {
+            Ok(Some(chunk)) => Some((Ok(chunk), Some(response))),
+            Ok(None) => None,
+            Err(e) => Some((Err(e), None)),
+        }
+    });
+    (Body::from_stream(chunks), content_type, length)
+}
+
+async fn open_local_file(
+    path: &std::path::Path,
+) -> Result<(Body, Option<String>, Option<u64>), StatusCode> {
+    let file = tokio::fs::File::open(path)
+        .await
+        .map_err(|_| StatusCode::NOT_FOUND)?;
+    let length = file.metadata().await.ok().map(|metadata| metadata.len());
+    Ok((Body::from_stream(ReaderStream::new(file)), None, length))
+}
+
+/// The last segment of a file URL, for naming the download.
+pub(crate) fn file_name_of(url: &str) -> String {
+    url.split(['?', '#'])
+        .next()
+        .and_then(|path| path.split('/').next_back())
+        .filter(|name| !name.is_empty())
+        .unwrap_or("download")
+        .to_string()
+}
+
+// The signed link is the credential; it is re-checked against the purchase so a refund ends
+// access even to links issued before it.
+async fn serve_product_file(
+    State(db): State<Database>,
+    Path(id): Path<Uuid>,
+    Query(params): Query<FileQuery>,
+    headers: HeaderMap,
+) -> Result<Response, StatusCode> {
+    let (grant, signature) =
+        DownloadGrant::verify(id, &params.token).ok_or(StatusCode::FORBIDDEN)?;
+    let mut product = sqlx::query_as::<_, Product>("SELECT * FROM products WHERE id = $1")
+        .bind(id)
+        .fetch_optional(&db.pool)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    let user_id = match grant.purchase_id {
+        Some(purchase_id) => {
+            sqlx::query_as::<_, Purchase>(
+                "SELECT * FROM purchases WHERE id = $1 AND product_id = $2 AND UPPER(status) = 'COMPLETED'",
+            )
+            .bind(purchase_id)
+            .bind(id)
+            .fetch_optional(&db.pool)
+            .await
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+            .ok_or(StatusCode::FORBIDDEN)?
+            .user_id
+        }
+        None => product.user_id.clone(),
+    };
+    if grant.purchase_id.is_some() && product.is_preorder() {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    if let Some(variant_id) = grant.variant_id {
+        let variant = sqlx::query_as::<_, ProductVariant>(
+            "SELECT * FROM product_variants WHERE id = $1 AND product_id = $2",
+        )
+        .bind(variant_id)
+        .bind(id)
+        .fetch_optional(&db.pool)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+        apply_variant(&mut product, &variant);
+    }
+    let (download_url, file_name) = match grant.version_id {
+        Some(version_id) => {
+            let version = find_version(&db, id, version_id).await?;
+            (version.file_url, version.file_name)
+        }
+        None => (
+            product
+                .download_url
+                .clone()
+                .filter(|url| !url.trim().is_empty())
+                .ok_or(StatusCode::NOT_FOUND)?,
+            None,
+        ),
+    };
+
+    record_download(&db, &product, &grant, &signature, &user_id, &headers).await?;
+    let (body, content_type, length) = open_file(&download_url).await?;
+
+    let file_name = file_name
+        .unwrap_or_else(|| file_name_of(&download_url))
+        .replace('"', "");
+    let mut response = (
+        [
+            (
+                header::CONTENT_TYPE,
+                content_type.unwrap_or_else(|| "application/octet-stream".to_string()),
+            ),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"{}\"", file_name),
+            ),
+            (header::CACHE_CONTROL, "private, no-store".to_string()),
+        ],
+        body,
+    )
+        .into_response();
+    if let Some(length) = length {
+        response.headers_mut().insert(header::CONTENT_LENGTH, length.into());
+    }
+    Ok(response)
+}
+
+// The creator's log of the latest downloads of a product
+async fn list_product_downloads(
+    State(db): State<Database>,
+    Path(id): Path<Uuid>,
+    claims: Claims,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let owner = sqlx::query_scalar::<_, String>("SELECT user_id FROM products WHERE id = $1")
+        .bind(id)
+        .fetch_optional(&db.pool)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+    if owner != claims.sub {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    let downloads = sqlx::query_as::<_, DownloadLogEntry>(
+        r#"
+        SELECT d.id, d.purchase_id, d.variant_id, d.version_id, d.user_id, u.username, d.ip_address,
+               d.user_agent, d.created_at
+        FROM product_downloads d
+        LEFT JOIN users u ON u.id = d.user_id
+        WHERE d.product_id = $1
+        ORDER BY d.created_at DESC
+        LIMIT 100
+        "#,
+    )
+    .bind(id)
+    .fetch_all(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to list downloads of product {}: {}", id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    let total = sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM product_downloads WHERE product_id = $1")
+        .bind(id)
+        .fetch_one(&db.pool)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(json!({
+        "success": true,
+        "data": downloads,
+        "total": total
+    })))
+}
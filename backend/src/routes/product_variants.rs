@@ -0,0 +1,271 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::Json,
+    routing::{get, put},
+    Router,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use uuid::Uuid;
+
+use crate::{
+    auth::Claims, database::Database, middleware::optional_auth::MaybeClaims, models::Product,
+    routes::product_downloads::is_servable_file_url,
+};
+
+/// One purchasable version of a product, such as a personal or a commercial license, with its
+/// own price and file.
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct ProductVariant {
+    pub id: Uuid,
+    pub product_id: Uuid,
+    pub name: String,
+    pub description: Option<String>,
+    pub price: f64,
+    /// Falls back to the product's file when empty. Only handed out as signed links.
+    #[serde(skip_serializing)]
+    pub download_url: Option<String>,
+    pub position: i32,
+    /// Overrides the product's license activation limit, e.g. more seats for a team license.
+    pub activation_limit: Option<i32>,
+    /// Retired variants can no longer be bought, but their buyers keep their downloads.
+    pub is_active: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct VariantRequest {
+    name: String,
+    description: Option<String>,
+    price: f64,
+    #[serde(alias = "fileUrl")]
+    download_url: Option<String>,
+    position: Option<i32>,
+    is_active: Option<bool>,
+    activation_limit: Option<i32>,
+}
+
+pub fn variant_routes() -> Router<Database> {
+    Router::new()
+        .route("/:id/variants", get(list_variants).post(create_variant))
+        .route(
+            "/:id/variants/:variant_id",
+            put(update_variant).delete(retire_variant),
+        )
+}
+
+/// Variants buyers can choose from, in display order.
+pub(crate) async fn active_variants(
+    db: &Database,
+    product_id: Uuid,
+) -> Result<Vec<ProductVariant>, StatusCode> {
+    sqlx::query_as::<_, ProductVariant>(
+        r#"
+        SELECT * FROM product_variants
+        WHERE product_id = $1 AND is_active
+        ORDER BY position, created_at
+        "#,
+    )
+    .bind(product_id)
+    .fetch_all(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to load variants of product {}: {}", product_id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })
+}
+
+/// The variant a buyer is purchasing. An explicit choice must be an active variant of the
+/// product; without one, a product that has variants sells its first.
+pub(crate) async fn select_variant(
+    db: &Database,
+    product_id: Uuid,
+    variant_id: Option<Uuid>,
+) -> Result<Option<ProductVariant>, StatusCode> {
+    let variants = active_variants(db, product_id).await?;
+    match variant_id {
+        Some(variant_id) => variants
+            .into_iter()
+            .find(|variant| variant.id == variant_id)
+            .map(Some)
+            .ok_or(StatusCode::NOT_FOUND),
+        None => Ok(variants.into_iter().next()),
+    }
+}
+
+/// Price, name and file of the product as sold in the given variant.
+pub(crate) fn apply_variant(product: &mut Product, variant: &ProductVariant) {
+    product.name = format!("{} ({})", product.name, variant.name);
+    product.price = variant.price;
+    if let Some(download_url) = variant
+        .download_url
+        .as_ref()
+        .filter(|url| !url.trim().is_empty())
+    {
+        product.download_url = Some(download_url.clone());
+    }
+}
+
+async fn load_own_product(db: &Database, product_id: Uuid, user_id: &str) -> Result<(), StatusCode> {
+    let owner = sqlx::query_scalar::<_, String>("SELECT user_id FROM products WHERE id = $1")
+        .bind(product_id)
+        .fetch_optional(&db.pool)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+    if owner != user_id {
+        return Err(StatusCode::FORBIDDEN);
+    }
+    Ok(())
+}
+
+fn validate(payload: &VariantRequest) -> Result<(), StatusCode> {
+    if payload.name.trim().is_empty()
+        || !payload.price.is_finite()
+        || payload.price < 0.0
+        || payload.activation_limit.is_some_and(|limit| limit < 1)
+        || payload.download_url.as_deref().is_some_and(|url| !is_servable_file_url(url))
+    {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    Ok(())
+}
+
+// The creator also sees retired variants
+async fn list_variants(
+    State(db): State<Database>,
+    Path(product_id): Path<Uuid>,
+    MaybeClaims(claims): MaybeClaims,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let owner = sqlx::query_scalar::<_, String>("SELECT user_id FROM products WHERE id = $1")
+        .bind(product_id)
+        .fetch_optional(&db.pool)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    let variants = if claims.is_some_and(|claims| claims.sub == owner) {
+        sqlx::query_as::<_, ProductVariant>(
+            "SELECT * FROM product_variants WHERE product_id = $1 ORDER BY position, created_at",
+        )
+        .bind(product_id)
+        .fetch_all(&db.pool)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    } else {
+        active_variants(&db, product_id).await?
+    };
+
+    Ok(Json(json!({ "success": true, "data": variants })))
+}
+
+async fn create_variant(
+    State(db): State<Database>,
+    Path(product_id): Path<Uuid>,
+    claims: Claims,
+    Json(payload): Json<VariantRequest>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    validate(&payload)?;
+    load_own_product(&db, product_id, &claims.sub).await?;
+
+    // New variants go last unless placed explicitly
+    let variant = sqlx::query_as::<_, ProductVariant>(
+        r#"
+        INSERT INTO product_variants (
+            product_id, name, description, price, download_url, position, is_active, activation_limit
+        )
+        VALUES (
+            $1, $2, $3, $4, $5,
+            COALESCE($6, (SELECT COALESCE(MAX(position) + 1, 0) FROM product_variants WHERE product_id = $1)),
+            COALESCE($7, TRUE),
+            $8
+        )
+        RETURNING *
+        "#,
+    )
+    .bind(product_id)
+    .bind(payload.name.trim())
+    .bind(&payload.description)
+    .bind(payload.price)
+    .bind(&payload.download_url)
+    .bind(payload.position)
+    .bind(payload.is_active)
+    .bind(payload.activation_limit)
+    .fetch_one(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to create variant of product {}: {}", product_id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    Ok(Json(json!({ "success": true, "data": variant })))
+}
+
+async fn update_variant(
+    State(db): State<Database>,
+    Path((product_id, variant_id)): Path<(Uuid, Uuid)>,
+    claims: Claims,
+    Json(payload): Json<VariantRequest>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    validate(&payload)?;
+    load_own_product(&db, product_id, &claims.sub).await?;
+
+    let variant = sqlx::query_as::<_, ProductVariant>(
+        r#"
+        UPDATE product_variants
+        SET name = $3, description = $4, price = $5, download_url = COALESCE($6, download_url),
+            position = COALESCE($7, position), is_active = COALESCE($8, is_active),
+            activation_limit = $9, updated_at = NOW()
+        WHERE id = $1 AND product_id = $2
+        RETURNING *
+        "#,
+    )
+    .bind(variant_id)
+    .bind(product_id)
+    .bind(payload.name.trim())
+    .bind(&payload.description)
+    .bind(payload.price)
+    .bind(&payload.download_url)
+    .bind(payload.position)
+    .bind(payload.is_active)
+    .bind(payload.activation_limit)
+    .fetch_optional(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to update variant {}: {}", variant_id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?
+    .ok_or(StatusCode::NOT_FOUND)?;
+
+    Ok(Json(json!({ "success": true, "data": variant })))
+}
+
+// Variants are retired rather than deleted so earlier buyers keep the file they paid for
+async fn retire_variant(
+    State(db): State<Database>,
+    Path((product_id, variant_id)): Path<(Uuid, Uuid)>,
+    claims: Claims,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    load_own_product(&db, product_id, &claims.sub).await?;
+
+    let variant = sqlx::query_as::<_, ProductVariant>(
+        r#"
+        UPDATE product_variants SET is_active = FALSE, updated_at = NOW()
+        WHERE id = $1 AND product_id = $2
+        RETURNING *
+        "#,
+    )
+    .bind(variant_id)
+    .bind(product_id)
+    .fetch_optional(&db.pool)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    .ok_or(StatusCode::NOT_FOUND)?;
+
+    Ok(Json(json!({ "success": true, "data": variant })))
+}
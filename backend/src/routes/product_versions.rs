@@ -0,0 +1,283 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::Json,
+    routing::get,
+    Router,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use uuid::Uuid;
+
+use crate::{
+    auth::Claims,
+    database::Database,
+    models::Product,
+    notification_service::{notify, NotificationKind},
+    routes::{product_downloads::is_servable_file_url, product_variants::ProductVariant},
+};
+
+/// A released file of a product, or of one variant when the variant has its own file.
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct ProductVersion {
+    pub id: Uuid,
+    pub product_id: Uuid,
+    pub variant_id: Option<Uuid>,
+    pub version_number: i32,
+    /// Only handed out through signed download links.
+    #[serde(skip)]
+    pub file_url: String,
+    pub file_name: Option<String>,
+    pub release_notes: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl ProductVersion {
+    /// Whether a buyer of `variant_id` may download this version. Versions of the product's own
+    /// file are open to every buyer.
+    pub(crate) fn available_to(&self, variant_id: Option<Uuid>) -> bool {
+        self.variant_id.is_none_or(|id| Some(id) == variant_id)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CreateVersionRequest {
+    #[serde(alias = "downloadUrl")]
+    file_url: String,
+    file_name: Option<String>,
+    release_notes: Option<String>,
+    /// The variant whose file this replaces; the product's own file when empty.
+    variant_id: Option<Uuid>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct VersionQuery {
+    variant_id: Option<Uuid>,
+}
+
+pub fn product_version_routes() -> Router<Database> {
+    Router::new().route("/:id/versions", get(list_versions).post(create_version))
+}
+
+/// A version of the product, to download instead of the current file.
+pub(crate) async fn find_version(
+    db: &Database,
+    product_id: Uuid,
+    version_id: Uuid,
+) -> Result<ProductVersion, StatusCode> {
+    sqlx::query_as::<_, ProductVersion>(
+        "SELECT * FROM product_versions WHERE id = $1 AND product_id = $2",
+    )
+    .bind(version_id)
+    .bind(product_id)
+    .fetch_optional(&db.pool)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    .ok_or(StatusCode::NOT_FOUND)
+}
+
+// Newest first. Passing a variant narrows the history to what its buyers can download.
+async fn list_versions(
+    State(db): State<Database>,
+    Path(id): Path<Uuid>,
+    Query(params): Query<VersionQuery>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let versions = sqlx::query_as::<_, ProductVersion>(
+        r#"
+        SELECT * FROM product_versions
+        WHERE product_id = $1
+          AND ($2::UUID IS NULL OR variant_id IS NULL OR variant_id = $2)
+        ORDER BY version_number DESC
+        "#,
+    )
+    .bind(id)
+    .bind(params.variant_id)
+    .fetch_all(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to list versions of product {}: {}", id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    Ok(Json(json!({ "success": true, "data": versions })))
+}
+
+// Replaces the file buyers download and keeps the old one in the history. The first upload also
+// records the file the product shipped with, so earlier buyers can still get it.
+async fn create_version(
+    State(db): State<Database>,
+    Path(id): Path<Uuid>,
+    claims: Claims,
+    Json(payload): Json<CreateVersionRequest>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let file_url = payload.file_url.trim();
+    if file_url.is_empty() || !is_servable_file_url(file_url) {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let mut tx = db.pool.begin().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    // Locking the product keeps version numbers in order when two uploads race
+    let product = sqlx::query_as::<_, Product>("SELECT * FROM products WHERE id = $1 FOR UPDATE")
+        .bind(id)
+        .fetch_optional(&mut tx)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+    if product.user_id != claims.sub {
+        return Err(StatusCode::FORBIDDEN);
+    }
+    if !product.is_digital {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let (current_file, shipped_at) = match payload.variant_id {
+        Some(variant_id) => {
+            let variant = sqlx::query_as::<_, ProductVariant>(
+                "SELECT * FROM product_variants WHERE id = $1 AND product_id = $2",
+            )
+            .bind(variant_id)
+            .bind(id)
+            .fetch_optional(&mut tx)
+            .await
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+            .ok_or(StatusCode::NOT_FOUND)?;
+            (variant.download_url, variant.created_at)
+        }
+        None => (product.download_url.clone(), product.created_at),
+    };
+
+    let (latest, has_history) = sqlx::query_as::<_, (i32, bool)>(
+        r#"
+        SELECT COALESCE(MAX(version_number), 0),
+               COALESCE(BOOL_OR(variant_id IS NOT DISTINCT FROM $2), FALSE)
+        FROM product_versions WHERE product_id = $1
+        "#,
+    )
+    .bind(id)
+    .bind(payload.variant_id)
+    .fetch_one(&mut tx)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let mut version_number = latest + 1;
+    if let Some(current_file) = current_file.filter(|url| !url.trim().is_empty() && !has_history) {
+        sqlx::query(
+            r#"
+            INSERT INTO product_versions (product_id, variant_id, version_number, file_url, created_at)
+            VALUES ($1, $2, $3, $4, $5)
+            "#,
+        )
+        .bind(id)
+        .bind(payload.variant_id)
+        .bind(version_number)
+        .bind(current_file)
+        .bind(shipped_at)
+        .execute(&mut tx)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        version_number += 1;
+    }
+
+    let version = sqlx::query_as::<_, ProductVersion>(
+        r#"
+        INSERT INTO product_versions (product_id, variant_id, version_number, file_url, file_name, release_notes)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        RETURNING *
+        "#,
+    )
+    .bind(id)
+    .bind(payload.variant_id)
+    .bind(version_number)
+    .bind(file_url)
+    .bind(payload.file_name.as_deref().map(str::trim).filter(|name| !name.is_empty()))
+    .bind(payload.release_notes.as_deref().map(str::trim).filter(|notes| !notes.is_empty()))
+    .fetch_one(&mut tx)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to create version of product {}: {}", id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    let updated = match payload.variant_id {
+        Some(variant_id) => sqlx::query(
+            "UPDATE product_variants SET download_url = $2, updated_at = NOW() WHERE id = $1",
+        )
+        .bind(variant_id)
+        .bind(file_url),
+        None => sqlx::query("UPDATE products SET download_url = $2, updated_at = NOW() WHERE id = $1")
+            .bind(id)
+            .bind(file_url),
+    };
+    updated
+        .execute(&mut tx)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    tx.commit().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    // Pre-order buyers hear about the file when it is released instead
+    if !product.is_preorder() {
+        let notified_db = db.clone();
+        let notified_version = version.clone();
+        tokio::spawn(async move {
+            notify_buyers(&notified_db, &product, &notified_version).await;
+        });
+    }
+
+    Ok(Json(json!({ "success": true, "data": version })))
+}
+
+/// Tell everyone who bought the file a version replaces that an update is available. A new
+/// product file reaches buyers of every variant that does not ship its own.
+async fn notify_buyers(db: &Database, product: &Product, version: &ProductVersion) {
+    let buyers = sqlx::query_scalar::<_, String>(
+        r#"
+        SELECT DISTINCT p.user_id
+        FROM purchases p
+        LEFT JOIN product_variants v ON v.id = p.variant_id
+        WHERE p.product_id = $1
+          AND UPPER(p.status) = 'COMPLETED'
+          AND p.user_id <> $2
+          AND CASE
+                WHEN $3::UUID IS NULL THEN NULLIF(TRIM(v.download_url), '') IS NULL
+                ELSE p.variant_id = $3
+              END
+        "#,
+    )
+    .bind(product.id)
+    .bind(&product.user_id)
+    .bind(version.variant_id)
+    .fetch_all(&db.pool)
+    .await;
+    let buyers = match buyers {
+        Ok(buyers) => buyers,
+        Err(e) => {
+            tracing::warn!("Failed to load buyers of product {}: {}", product.id, e);
+            return;
+        }
+    };
+
+    for user_id in buyers {
+        notify(
+            db,
+            &user_id,
+            NotificationKind::ProductUpdate,
+            json!({
+                "message": format!(
+                    "{} has an update available (version {})",
+                    product.name, version.version_number
+                ),
+                "link": format!("/products/{}", product.id),
+                "productId": product.id,
+                "variantId": version.variant_id,
+                "versionId": version.id,
+                "versionNumber": version.version_number,
+                "releaseNotes": version.release_notes,
+            }),
+        )
+        .await;
+    }
+}
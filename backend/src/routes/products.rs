@@ -1,6 +1,6 @@
 use axum::{
     extract::{Path, Query, State},
-    http::StatusCode,
+    http::{HeaderMap, StatusCode},
     response::Json,
     routing::{delete, get, post, put},
     Router,
@@ -14,14 +14,47 @@ use crate::{
     auth::Claims,
     database::Database,
     models::{CreateProductRequest, Product, Purchase},
+    routes::{
+        fees::{quote_platform_fee, LedgerSource, ProductType},
+        discounts::{apply_sale_prices, claim_discount, discount_product, release_discount},
+        inventory::{attach_hold, consume_stock, hold_stock, release_hold, CHECKOUT_SESSION_MINUTES},
+        licenses::issue_license_key,
+        product_downloads::{
+            downloads_used, file_name_of, is_servable_file_url, product_file_routes, DownloadGrant,
+            DOWNLOAD_LINK_TTL_SECONDS,
+        },
+        product_variants::{active_variants, apply_variant, select_variant, variant_routes, ProductVariant},
+        product_versions::{find_version, product_version_routes},
+        shipping::{quote_shipping, ShippingAddress, ShippingQuote},
+        stripe::{create_payment_intent, stripe_secret, stripe_url, track_checkout, PaymentIntentSpec},
+        tax::{quote_tax, resolve_buyer_location, BuyerLocation, TaxQuote},
+    },
 };
 
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DownloadQuery {
+    /// Which of several purchased variants to download; the latest purchase otherwise.
+    pub variant_id: Option<Uuid>,
+    /// An earlier version of the file from the version history.
+    pub version_id: Option<Uuid>,
+}
+
+/// A product with the variants buyers can choose from.
+#[derive(Debug, Serialize)]
+pub struct ProductDetail {
+    #[serde(flatten)]
+    product: Product,
+    variants: Vec<ProductVariant>,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct ProductQuery {
     pub page: Option<u32>,
     pub limit: Option<u32>,
     pub user_id: Option<String>,
-    pub creatorId: Option<String>,
+    #[serde(rename = "creatorId")]
+    pub creator_id: Option<String>,
 }
 
 pub fn product_routes() -> Router<Database> {
@@ -34,7 +67,11 @@ pub fn product_routes() -> Router<Database> {
         .route("/:id", put(update_product))
         .route("/:id", delete(delete_product))
         .route("/:id/purchase", post(purchase_product))
+        .route("/:id/payment-intent", post(create_product_payment_intent))
         .route("/:id/download", get(get_product_download))
+        .merge(product_file_routes())
+        .merge(variant_routes())
+        .merge(product_version_routes())
 }
 
 async fn get_products(
@@ -47,9 +84,9 @@ async fn get_products(
     let limit_i64 = limit as i64;
     let offset_i64 = offset as i64;
 
-    let products = if let Some(creator_id) = params.creatorId.clone() {
+    let mut products = if let Some(creator_id) = params.creator_id.clone() {
         sqlx::query_as::<_, Product>(
-            "SELECT * FROM products WHERE user_id = $1 ORDER BY created_at DESC LIMIT $2 OFFSET $3",
+            "SELECT * FROM products WHERE user_id = $1 AND is_active ORDER BY created_at DESC LIMIT $2 OFFSET $3",
         )
         .bind(&creator_id)
         .bind(limit_i64)
@@ -58,7 +95,7 @@ async fn get_products(
         .await
     } else if let Some(user_id) = params.user_id.clone() {
         sqlx::query_as::<_, Product>(
-            "SELECT * FROM products WHERE user_id = $1 ORDER BY created_at DESC LIMIT $2 OFFSET $3",
+            "SELECT * FROM products WHERE user_id = $1 AND is_active ORDER BY created_at DESC LIMIT $2 OFFSET $3",
         )
         .bind(&user_id)
         .bind(limit_i64)
@@ -67,7 +104,7 @@ async fn get_products(
         .await
     } else {
         sqlx::query_as::<_, Product>(
-            "SELECT * FROM products ORDER BY created_at DESC LIMIT $1 OFFSET $2",
+            "SELECT * FROM products WHERE is_active ORDER BY created_at DESC LIMIT $1 OFFSET $2",
         )
         .bind(limit_i64)
         .bind(offset_i64)
@@ -78,6 +115,7 @@ async fn get_products(
         eprintln!("Error fetching products: {:?}", e);
         StatusCode::INTERNAL_SERVER_ERROR
     })?;
+    apply_sale_prices(&db, &mut products).await?;
 
     Ok(Json(products))
 }
@@ -89,7 +127,12 @@ async fn create_product(
 ) -> Result<Json<serde_json::Value>, StatusCode> {
     let user_id = claims.sub;
 
-    if payload.name.trim().is_empty() {
+    if payload.name.trim().is_empty()
+        || payload.license_activation_limit.is_some_and(|limit| limit < 1)
+        || payload.download_limit.is_some_and(|limit| limit < 1)
+        || payload.stock.is_some_and(|stock| stock < 0)
+        || payload.download_url.as_deref().is_some_and(|url| !is_servable_file_url(url))
+    {
         return Err(StatusCode::BAD_REQUEST);
     }
 
@@ -100,26 +143,34 @@ async fn create_product(
 
     let is_digital = payload
         .is_digital
-        .unwrap_or_else(|| match payload.product_type.as_deref() {
-            Some(product_type) if product_type.eq_ignore_ascii_case("physical") => false,
-            _ => true,
+        .unwrap_or_else(|| {
+            !matches!(payload.product_type.as_deref(), Some(product_type) if product_type.eq_ignore_ascii_case("physical"))
         });
 
     let product = sqlx::query_as::<_, Product>(
         r#"
-        INSERT INTO products (user_id, name, description, price, currency, image_url, is_digital, download_url)
-        VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+        INSERT INTO products (
+            user_id, name, description, price, currency, image_url, is_digital, download_url,
+            license_keys, license_activation_limit, download_limit, release_at, stock, is_active
+        )
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, COALESCE($9, FALSE), COALESCE($10, 3), $11, $12, $13, COALESCE($14, TRUE))
         RETURNING *
         "#
     )
     .bind(&user_id)
     .bind(&payload.name)
     .bind(&payload.description)
-    .bind(&payload.price)
+    .bind(payload.price)
     .bind(currency)
     .bind(&payload.image_url)
     .bind(is_digital)
     .bind(&payload.download_url)
+    .bind(payload.license_keys)
+    .bind(payload.license_activation_limit)
+    .bind(payload.download_limit)
+    .bind(payload.release_at)
+    .bind(payload.stock)
+    .bind(payload.is_active)
     .fetch_one(&db.pool)
     .await
     .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
@@ -151,14 +202,16 @@ async fn get_my_products(
 async fn get_product_by_id(
     State(db): State<Database>,
     Path(id): Path<Uuid>,
-) -> Result<Json<Product>, StatusCode> {
-    let product = sqlx::query_as::<_, Product>("SELECT * FROM products WHERE id = $1")
+) -> Result<Json<ProductDetail>, StatusCode> {
+    let mut product = sqlx::query_as::<_, Product>("SELECT * FROM products WHERE id = $1")
         .bind(id)
         .fetch_one(&db.pool)
         .await
         .map_err(|_| StatusCode::NOT_FOUND)?;
+    apply_sale_prices(&db, std::slice::from_mut(&mut product)).await?;
+    let variants = active_variants(&db, id).await?;
 
-    Ok(Json(product))
+    Ok(Json(ProductDetail { product, variants }))
 }
 
 async fn update_product(
@@ -182,7 +235,12 @@ async fn update_product(
         return Err(StatusCode::FORBIDDEN);
     }
 
-    if payload.name.trim().is_empty() {
+    if payload.name.trim().is_empty()
+        || payload.license_activation_limit.is_some_and(|limit| limit < 1)
+        || payload.download_limit.is_some_and(|limit| limit < 1)
+        || payload.stock.is_some_and(|stock| stock < 0)
+        || payload.download_url.as_deref().is_some_and(|url| !is_servable_file_url(url))
+    {
         return Err(StatusCode::BAD_REQUEST);
     }
 
@@ -193,15 +251,23 @@ async fn update_product(
 
     let is_digital = payload
         .is_digital
-        .unwrap_or_else(|| match payload.product_type.as_deref() {
-            Some(product_type) if product_type.eq_ignore_ascii_case("physical") => false,
-            _ => true,
+        .unwrap_or_else(|| {
+            !matches!(payload.product_type.as_deref(), Some(product_type) if product_type.eq_ignore_ascii_case("physical"))
         });
 
     let product = sqlx::query_as::<_, Product>(
         r#"
         UPDATE products 
-        SET name = $2, description = $3, price = $4, currency = $5, image_url = $6, is_digital = $7, download_url = $8, updated_at = NOW()
+        SET name = $2, description = $3, price = $4, currency = $5, image_url = $6, is_digital = $7, download_url = COALESCE($8, download_url),
+            license_keys = COALESCE($9, license_keys),
+            license_activation_limit = COALESCE($10, license_activation_limit),
+            download_limit = $11,
+            release_at = $12,
+            -- Moving the release back into the future makes it a pre-order again
+            released_at = CASE WHEN $12 > NOW() THEN NULL ELSE released_at END,
+            stock = $13,
+            is_active = COALESCE($14, is_active),
+            updated_at = NOW()
         WHERE id = $1
         RETURNING *
         "#
@@ -209,11 +275,17 @@ async fn update_product(
     .bind(id)
     .bind(&payload.name)
     .bind(&payload.description)
-    .bind(&payload.price)
+    .bind(payload.price)
     .bind(currency)
     .bind(&payload.image_url)
     .bind(is_digital)
     .bind(&payload.download_url)
+    .bind(payload.license_keys)
+    .bind(payload.license_activation_limit)
+    .bind(payload.download_limit)
+    .bind(payload.release_at)
+    .bind(payload.stock)
+    .bind(payload.is_active)
     .fetch_one(&db.pool)
     .await
     .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
@@ -253,44 +325,110 @@ async fn delete_product(
     Ok(StatusCode::NO_CONTENT)
 }
 
-#[derive(Debug, Deserialize)]
+#[allow(dead_code)]
+#[derive(Debug, Default, Deserialize)]
 #[serde(rename_all = "camelCase")]
 struct PurchaseProductRequest {
     payment_method: Option<String>,
     transaction_id: Option<String>,
+    /// ISO country of the buyer's billing address, the primary evidence for sales tax.
+    billing_country: Option<String>,
+    billing_region: Option<String>,
+    /// The variant chosen on the product page.
+    variant_id: Option<Uuid>,
+    /// A creator's discount code; running sales apply without one.
+    discount_code: Option<String>,
+    /// Required for physical products.
+    shipping_address: Option<ShippingAddress>,
+}
+
+/// Digital products are taxed where the buyer is; physical goods are left to the creator.
+async fn quote_product_tax(
+    db: &Database,
+    product: &Product,
+    payload: &PurchaseProductRequest,
+    headers: &HeaderMap,
+    amount_cents: i64,
+) -> Result<TaxQuote, StatusCode> {
+    if !product.is_digital {
+        return Ok(TaxQuote::none(BuyerLocation::default(), amount_cents));
+    }
+    let location = resolve_buyer_location(
+        payload.billing_country.as_deref(),
+        payload.billing_region.as_deref(),
+        headers,
+    );
+    quote_tax(db, location, amount_cents).await
 }
 
 async fn purchase_product(
     State(db): State<Database>,
     Path(id): Path<Uuid>,
     claims: Claims,
-    Json(_payload): Json<PurchaseProductRequest>,
+    headers: HeaderMap,
+    Json(payload): Json<PurchaseProductRequest>,
 ) -> Result<Json<serde_json::Value>, StatusCode> {
-    let product = sqlx::query_as::<_, Product>("SELECT * FROM products WHERE id = $1")
+    let mut product = sqlx::query_as::<_, Product>("SELECT * FROM products WHERE id = $1 AND is_active")
         .bind(id)
         .fetch_one(&db.pool)
         .await
         .map_err(|_| StatusCode::NOT_FOUND)?;
+    let variant = select_variant(&db, id, payload.variant_id).await?;
+    if let Some(variant) = &variant {
+        apply_variant(&mut product, variant);
+    }
+    let variant_id = variant.as_ref().map(|variant| variant.id);
+    let discount = discount_product(&db, &mut product, payload.discount_code.as_deref()).await?;
+    let discount_id = discount.as_ref().map(|discount| discount.id);
+    let discount_amount = discount
+        .as_ref()
+        .map(|discount| discount.discount_cents as f64 / 100.0);
+    let shipping = quote_shipping(
+        &db,
+        &product,
+        payload.shipping_address.as_ref(),
+        (product.price * 100.0).round() as i64,
+    )
+    .await?;
+    let shipping_cents = shipping.as_ref().map(|shipping| shipping.shipping_cents).unwrap_or(0);
+
+    let hold_id = hold_stock(&db, id).await?;
 
+    // Free physical products ship at the creator's cost
     if product.price <= 0.0 {
+        if let Some(discount_id) = discount_id {
+            claim_discount(&db, discount_id).await?;
+        }
         let purchase = sqlx::query_as::<_, Purchase>(
             r#"
-            INSERT INTO purchases (user_id, product_id, amount, currency, status)
-            VALUES ($1, $2, $3, $4, $5)
+            INSERT INTO purchases (
+                user_id, product_id, variant_id, amount, currency, status, discount_code_id, discount_amount,
+                shipping_address, shipping_zone_id, fulfillment_status
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
             RETURNING *
             "#,
         )
         .bind(&claims.sub)
         .bind(id)
+        .bind(variant_id)
         .bind(product.price)
         .bind(&product.currency)
         .bind("COMPLETED")
+        .bind(discount_id)
+        .bind(discount_amount)
+        .bind(shipping.as_ref().map(ShippingQuote::address_json))
+        .bind(shipping.as_ref().map(|shipping| shipping.zone_id))
+        .bind(shipping.as_ref().map(|_| "PENDING"))
         .fetch_one(&db.pool)
         .await
         .map_err(|error| {
             error!("Failed to create free purchase: {:?}", error);
             StatusCode::INTERNAL_SERVER_ERROR
         })?;
+        issue_license_key(&db, purchase.id).await?;
+        attach_hold(&db, hold_id, purchase.id).await?;
+        consume_stock(&db, &purchase.id.to_string()).await?;
 
         return Ok(Json(json!({
             "success": true,
@@ -298,18 +436,16 @@ async fn purchase_product(
                 "purchaseId": purchase.id,
                 "status": purchase.status,
                 "productId": purchase.product_id,
+                "variantId": purchase.variant_id,
+                "releaseAt": product.release_at.filter(|_| product.is_preorder()),
                 "amount": purchase.amount,
+                "discountAmount": purchase.discount_amount,
                 "currency": purchase.currency,
             }
         })));
     }
 
-    let stripe_secret =
-        std::env::var("STRIPE_SECRET_KEY").map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
-
-    if stripe_secret.trim().is_empty() {
-        return Err(StatusCode::INTERNAL_SERVER_ERROR);
-    }
+    let stripe_secret = stripe_secret()?;
 
     let frontend_url =
         std::env::var("FRONTEND_URL").unwrap_or_else(|_| "http://localhost:3000".to_string());
@@ -323,6 +459,11 @@ async fn purchase_product(
     if amount_cents <= 0 {
         return Err(StatusCode::BAD_REQUEST);
     }
+    let tax = quote_product_tax(&db, &product, &payload, &headers, amount_cents).await?;
+    // Shipping is paid out to the creator along with the price
+    let platform_fee = quote_platform_fee(&db, ProductType::Product, &product.user_id, amount_cents + shipping_cents)
+        .await?
+        .with_tax(tax.tax_cents);
 
     let mut form_data = vec![
         ("mode".to_string(), "payment".to_string()),
@@ -341,10 +482,15 @@ async fn purchase_product(
             amount_cents.to_string(),
         ),
         ("line_items[0][quantity]".to_string(), "1".to_string()),
-        ("payment_method_types[0]".to_string(), "card".to_string()),
         ("metadata[user_id]".to_string(), claims.sub.clone()),
         ("metadata[product_id]".to_string(), product.id.to_string()),
     ];
+    if let Some(variant_id) = variant_id {
+        form_data.push(("metadata[variant_id]".to_string(), variant_id.to_string()));
+    }
+    if let Some(discount_id) = discount_id {
+        form_data.push(("metadata[discount_code_id]".to_string(), discount_id.to_string()));
+    }
 
     if let Some(description) = &product.description {
         if !description.trim().is_empty() {
@@ -363,10 +509,51 @@ async fn purchase_product(
             ));
         }
     }
+    if tax.tax_cents > 0 {
+        form_data.extend([
+            (
+                "line_items[1][price_data][currency]".to_string(),
+                product.currency.to_lowercase(),
+            ),
+            (
+                "line_items[1][price_data][product_data][name]".to_string(),
+                tax.label(),
+            ),
+            (
+                "line_items[1][price_data][unit_amount]".to_string(),
+                tax.tax_cents.to_string(),
+            ),
+            ("line_items[1][quantity]".to_string(), "1".to_string()),
+        ]);
+    }
+    if shipping_cents > 0 {
+        let line = if tax.tax_cents > 0 { 2 } else { 1 };
+        form_data.extend([
+            (
+                format!("line_items[{}][price_data][currency]", line),
+                product.currency.to_lowercase(),
+            ),
+            (
+                format!("line_items[{}][price_data][product_data][name]", line),
+                "Shipping".to_string(),
+            ),
+            (
+                format!("line_items[{}][price_data][unit_amount]", line),
+                shipping_cents.to_string(),
+            ),
+            (format!("line_items[{}][quantity]", line), "1".to_string()),
+        ]);
+    }
+    form_data.extend(platform_fee.checkout_params());
+    // A limited product's session lapses before the unit held for it
+    if hold_id.is_some() {
+        let expires_at = chrono::Utc::now() + chrono::Duration::minutes(CHECKOUT_SESSION_MINUTES);
+        form_data.push(("expires_at".to_string(), expires_at.timestamp().to_string()));
+    }
 
     let client = reqwest::Client::new();
     let response = client
-        .post("https://api.stripe.com/v1/checkout/sessions")
+        .post(stripe_url("/v1/checkout/sessions"))
         .header("Authorization", format!("Bearer {}", stripe_secret))
         .form(&form_data)
         .send()
@@ -411,34 +598,66 @@ async fn purchase_product(
         .and_then(|value| value.as_str())
         .map(str::to_string);
 
+    // A use is only counted once checkout exists; losing the race leaves the session unused
+    if let Some(discount_id) = discount_id {
+        claim_discount(&db, discount_id).await?;
+    }
+
     let purchase = sqlx::query_as::<_, Purchase>(
         r#"
         INSERT INTO purchases (
             user_id,
             product_id,
+            variant_id,
             stripe_payment_intent_id,
             stripe_checkout_session_id,
             amount,
             currency,
-            status
+            status,
+            discount_code_id,
+            discount_amount,
+            shipping_cents,
+            shipping_address,
+            shipping_zone_id,
+            fulfillment_status
         )
-        VALUES ($1, $2, $3, $4, $5, $6, $7)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14)
         RETURNING *
         "#,
     )
     .bind(&claims.sub)
     .bind(id)
+    .bind(variant_id)
     .bind(payment_intent_id.clone())
     .bind(Some(session_id.clone()))
     .bind(product.price)
     .bind(&product.currency)
     .bind("PENDING")
+    .bind(discount_id)
+    .bind(discount_amount)
+    .bind(shipping_cents)
+    .bind(shipping.as_ref().map(ShippingQuote::address_json))
+    .bind(shipping.as_ref().map(|shipping| shipping.zone_id))
+    .bind(shipping.as_ref().map(|_| "PENDING"))
     .fetch_one(&db.pool)
     .await
     .map_err(|error| {
         error!("Failed to store purchase record: {:?}", error);
         StatusCode::INTERNAL_SERVER_ERROR
     })?;
+    attach_hold(&db, hold_id, purchase.id).await?;
+    track_checkout(&db, &session).await;
+
+    let source = LedgerSource {
+        source_type: "PURCHASE",
+        source_id: purchase.id.to_string(),
+        payer_id: &claims.sub,
+        currency: &product.currency,
+        stripe_checkout_session_id: Some(&session_id),
+        stripe_payment_intent_id: payment_intent_id.as_deref(),
+    };
+    tax.record(&db, &product.user_id, &source).await?;
+    platform_fee.record(&db, source).await?;
 
     Ok(Json(json!({
         "success": true,
@@ -447,7 +666,12 @@ async fn purchase_product(
             "status": purchase.status,
             "checkoutUrl": checkout_url,
             "productId": purchase.product_id,
+            "variantId": purchase.variant_id,
+            "releaseAt": product.release_at.filter(|_| product.is_preorder()),
             "amount": purchase.amount,
+            "discountAmount": purchase.discount_amount,
+            "shippingCents": shipping_cents,
+            "tax": tax,
             "currency": purchase.currency,
             "stripeSessionId": session_id,
             "stripePaymentIntentId": payment_intent_id
@@ -455,12 +679,163 @@ async fn purchase_product(
     })))
 }
 
+// In-page alternative to the hosted checkout, used by wallet buttons on the product page; the
+// purchase completes on the payment_intent.succeeded webhook. Free products use /purchase.
+async fn create_product_payment_intent(
+    State(db): State<Database>,
+    Path(id): Path<Uuid>,
+    claims: Claims,
+    headers: HeaderMap,
+    payload: Option<Json<PurchaseProductRequest>>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let Json(payload) = payload.unwrap_or_default();
+    let mut product = sqlx::query_as::<_, Product>("SELECT * FROM products WHERE id = $1 AND is_active")
+        .bind(id)
+        .fetch_one(&db.pool)
+        .await
+        .map_err(|_| StatusCode::NOT_FOUND)?;
+    let variant = select_variant(&db, id, payload.variant_id).await?;
+    if let Some(variant) = &variant {
+        apply_variant(&mut product, variant);
+    }
+    let variant_id = variant.as_ref().map(|variant| variant.id);
+    let discount = discount_product(&db, &mut product, payload.discount_code.as_deref()).await?;
+    let discount_id = discount.as_ref().map(|discount| discount.id);
+    let discount_amount = discount
+        .as_ref()
+        .map(|discount| discount.discount_cents as f64 / 100.0);
+    let shipping = quote_shipping(
+        &db,
+        &product,
+        payload.shipping_address.as_ref(),
+        (product.price * 100.0).round() as i64,
+    )
+    .await?;
+    let shipping_cents = shipping.as_ref().map(|shipping| shipping.shipping_cents).unwrap_or(0);
+
+    let amount_cents = (product.price * 100.0).round() as i64;
+    if amount_cents <= 0 {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let tax = quote_product_tax(&db, &product, &payload, &headers, amount_cents).await?;
+    // Shipping is paid out to the creator along with the price
+    let platform_fee = quote_platform_fee(&db, ProductType::Product, &product.user_id, amount_cents + shipping_cents)
+        .await?
+        .with_tax(tax.tax_cents);
+
+    let mut metadata = vec![
+        ("user_id", claims.sub.clone()),
+        ("product_id", product.id.to_string()),
+    ];
+    if let Some(variant_id) = variant_id {
+        metadata.push(("variant_id", variant_id.to_string()));
+    }
+    if let Some(discount_id) = discount_id {
+        metadata.push(("discount_code_id", discount_id.to_string()));
+    }
+    let hold_id = hold_stock(&db, id).await?;
+    // The use is claimed before paying can start; an intent that never gets created gives it and
+    // the held unit back
+    if let Some(discount_id) = discount_id {
+        if let Err(status) = claim_discount(&db, discount_id).await {
+            release_hold(&db, hold_id).await;
+            return Err(status);
+        }
+    }
+    let payment_intent = match create_payment_intent(PaymentIntentSpec {
+        amount_cents: amount_cents + tax.tax_cents + shipping_cents,
+        currency: &product.currency,
+        description: &product.name,
+        metadata,
+        platform_fee: &platform_fee,
+    })
+    .await
+    {
+        Ok(payment_intent) if payment_intent.get("id").is_some_and(|id| id.is_string()) => payment_intent,
+        result => {
+            if let Some(discount_id) = discount_id {
+                release_discount(&db, discount_id).await;
+            }
+            release_hold(&db, hold_id).await;
+            return Err(result.err().unwrap_or(StatusCode::BAD_GATEWAY));
+        }
+    };
+    let payment_intent_id = payment_intent
+        .get("id")
+        .and_then(|value| value.as_str())
+        .ok_or(StatusCode::BAD_GATEWAY)?;
+
+    let purchase = sqlx::query_as::<_, Purchase>(
+        r#"
+        INSERT INTO purchases (
+            user_id, product_id, variant_id, stripe_payment_intent_id, amount, currency, status,
+            discount_code_id, discount_amount, shipping_cents, shipping_address, shipping_zone_id,
+            fulfillment_status
+        )
+        VALUES ($1, $2, $3, $4, $5, $6, 'PENDING', $7, $8, $9, $10, $11, $12)
+        RETURNING *
+        "#,
+    )
+    .bind(&claims.sub)
+    .bind(id)
+    .bind(variant_id)
+    .bind(payment_intent_id)
+    .bind(product.price)
+    .bind(&product.currency)
+    .bind(discount_id)
+    .bind(discount_amount)
+    .bind(shipping_cents)
+    .bind(shipping.as_ref().map(ShippingQuote::address_json))
+    .bind(shipping.as_ref().map(|shipping| shipping.zone_id))
+    .bind(shipping.as_ref().map(|_| "PENDING"))
+    .fetch_one(&db.pool)
+    .await
+    .map_err(|error| {
+        error!("Failed to store purchase record: {:?}", error);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    attach_hold(&db, hold_id, purchase.id).await?;
+
+    let source = LedgerSource {
+        source_type: "PURCHASE",
+        source_id: purchase.id.to_string(),
+        payer_id: &claims.sub,
+        currency: &product.currency,
+        stripe_checkout_session_id: None,
+        stripe_payment_intent_id: Some(payment_intent_id),
+    };
+    tax.record(&db, &product.user_id, &source).await?;
+    let payment_id = platform_fee.record(&db, source).await?;
+
+    Ok(Json(json!({
+        "success": true,
+        "data": {
+            "purchaseId": purchase.id,
+            "paymentId": payment_id,
+            "status": purchase.status,
+            "productId": purchase.product_id,
+            "variantId": purchase.variant_id,
+            "releaseAt": product.release_at.filter(|_| product.is_preorder()),
+            "amount": purchase.amount,
+            "discountAmount": purchase.discount_amount,
+            "shippingCents": shipping_cents,
+            "tax": tax,
+            "currency": purchase.currency,
+            "clientSecret": payment_intent.get("client_secret"),
+            "stripePaymentIntentId": payment_intent_id
+        }
+    })))
+}
+
+// A short-lived signed link to the file rather than its address. Buyers get the file of the
+// variant they bought; the creator can fetch any variant's file.
 async fn get_product_download(
     State(db): State<Database>,
     Path(id): Path<Uuid>,
+    Query(params): Query<DownloadQuery>,
     claims: Claims,
 ) -> Result<Json<serde_json::Value>, StatusCode> {
-    let product = sqlx::query_as::<_, Product>("SELECT * FROM products WHERE id = $1")
+    let mut product = sqlx::query_as::<_, Product>("SELECT * FROM products WHERE id = $1")
         .bind(id)
         .fetch_one(&db.pool)
         .await
@@ -470,15 +845,22 @@ async fn get_product_download(
         return Err(StatusCode::BAD_REQUEST);
     }
 
-    let download_url = product
-        .download_url
-        .clone()
-        .filter(|url| !url.trim().is_empty())
-        .ok_or(StatusCode::NOT_FOUND)?;
-
     let is_owner = product.user_id == claims.sub;
+    // Pre-order buyers get the file once it is released; the creator can check it beforehand
+    if !is_owner && product.is_preorder() {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    let version = match params.version_id {
+        Some(version_id) => Some(find_version(&db, id, version_id).await?),
+        None => None,
+    };
 
-    if !is_owner {
+    // The creator downloading an old version of a variant's file gets it as that variant
+    let (purchase_id, variant_id) = if is_owner {
+        let version_variant = version.as_ref().and_then(|version| version.variant_id);
+        (None, version_variant.or(params.variant_id))
+    } else {
         let purchase = sqlx::query_as::<_, Purchase>(
             r#"
             SELECT *
@@ -486,39 +868,98 @@ async fn get_product_download(
             WHERE product_id = $1
               AND user_id = $2
               AND UPPER(status) = 'COMPLETED'
+              AND ($3::UUID IS NULL OR variant_id = $3)
             ORDER BY created_at DESC
             LIMIT 1
             "#,
         )
         .bind(id)
         .bind(&claims.sub)
+        .bind(params.variant_id)
         .fetch_optional(&db.pool)
         .await
         .map_err(|error| {
             error!("Failed to validate purchase for download: {:?}", error);
             StatusCode::INTERNAL_SERVER_ERROR
-        })?;
+        })?
+        .ok_or(StatusCode::FORBIDDEN)?;
+        (Some(purchase.id), purchase.variant_id)
+    };
+
+    // Retired variants still serve the buyers who bought them
+    let variant = match variant_id {
+        Some(variant_id) => Some(
+            sqlx::query_as::<_, ProductVariant>(
+                "SELECT * FROM product_variants WHERE id = $1 AND product_id = $2",
+            )
+            .bind(variant_id)
+            .bind(id)
+            .fetch_optional(&db.pool)
+            .await
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+            .ok_or(StatusCode::NOT_FOUND)?,
+        ),
+        None => None,
+    };
+    if let Some(variant) = &variant {
+        apply_variant(&mut product, variant);
+    }
+    if version
+        .as_ref()
+        .is_some_and(|version| !version.available_to(variant_id))
+    {
+        return Err(StatusCode::FORBIDDEN);
+    }
 
-        if purchase.is_none() {
-            return Err(StatusCode::FORBIDDEN);
-        }
+    let (download_url, version_file_name) = match &version {
+        Some(version) => (version.file_url.clone(), version.file_name.clone()),
+        None => (
+            product
+                .download_url
+                .clone()
+                .filter(|url| !url.trim().is_empty())
+                .ok_or(StatusCode::NOT_FOUND)?,
+            None,
+        ),
+    };
+
+    let downloads = match purchase_id {
+        Some(purchase_id) => Some(downloads_used(&db, purchase_id).await?),
+        None => None,
+    };
+    let remaining = product
+        .download_limit
+        .zip(downloads)
+        .map(|(limit, used)| (i64::from(limit) - used).max(0));
+    if remaining == Some(0) {
+        return Err(StatusCode::FORBIDDEN);
     }
 
-    let file_name = download_url
-        .split('/')
-        .last()
-        .unwrap_or("download")
-        .to_string();
+    let grant = DownloadGrant {
+        product_id: id,
+        purchase_id,
+        variant_id: variant.as_ref().map(|variant| variant.id),
+        version_id: version.as_ref().map(|version| version.id),
+        expires: chrono::Utc::now().timestamp() + DOWNLOAD_LINK_TTL_SECONDS,
+    };
+    let file_name = version_file_name.unwrap_or_else(|| file_name_of(&download_url));
 
     Ok(Json(json!({
         "success": true,
         "data": {
-            "fileUrl": download_url,
-            "fileName": file_name
+            "fileUrl": grant.url(),
+            "fileName": file_name,
+            "expiresAt": grant.expires,
+            "variantId": grant.variant_id,
+            "versionId": grant.version_id,
+            "downloadLimit": product.download_limit,
+            "downloadsUsed": downloads,
+            "downloadsRemaining": remaining
         }
     })))
 }
 
+#[allow(dead_code)]
 #[derive(Debug, Serialize)]
 struct ProductMeta {
     types: Vec<TypeCount>,
@@ -527,17 +968,22 @@ struct ProductMeta {
 }
 
 #[derive(Debug, Serialize, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
 struct TypeCount {
     r#type: String,
     count: i64,
+    min_price: f64,
+    max_price: f64,
 }
 
+#[allow(dead_code)]
 #[derive(Debug, Serialize)]
 struct PriceRange {
     min: f64,
     max: f64,
 }
 
+#[allow(dead_code)]
 #[derive(Debug, Serialize)]
 struct ProductStats {
     total_products: i64,
@@ -546,37 +992,69 @@ struct ProductStats {
     total_revenue: f64,
 }
 
+/// How long the catalog facets and collections are cached.
+const CATALOG_CACHE_SECONDS: usize = 300;
+
+// Facets for the catalog filters, over products on sale. Revenue counts completed purchases.
 async fn get_products_meta(
     State(db): State<Database>,
 ) -> Result<Json<serde_json::Value>, StatusCode> {
-    // Get product types and counts
+    let cache_key = "products:meta";
+    if let Some(redis) = &db.redis {
+        let mut redis_clone = redis.clone();
+        if let Ok(Some(cached)) = redis_clone.get(cache_key).await {
+            if let Ok(cached_value) = serde_json::from_str::<serde_json::Value>(&cached) {
+                return Ok(Json(cached_value));
+            }
+        }
+    }
+
     let types = sqlx::query_as::<_, TypeCount>(
-        "SELECT 'DIGITAL' as type, COUNT(*) as count FROM products WHERE is_digital = true",
+        r#"
+        SELECT CASE WHEN is_digital THEN 'DIGITAL' ELSE 'PHYSICAL' END AS type,
+               COUNT(*) AS count,
+               COALESCE(MIN(price), 0) AS min_price,
+               COALESCE(MAX(price), 0) AS max_price
+        FROM products
+        WHERE is_active
+        GROUP BY is_digital
+        ORDER BY is_digital DESC
+        "#,
     )
     .fetch_all(&db.pool)
     .await
-    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    .map_err(|e| {
+        tracing::error!("Failed to load product types: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
 
-    // Get price range
     let price_range = sqlx::query_as::<_, (Option<f64>, Option<f64>)>(
-        "SELECT MIN(price), MAX(price) FROM products",
+        "SELECT MIN(price), MAX(price) FROM products WHERE is_active",
     )
     .fetch_one(&db.pool)
     .await
     .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
-    // Get stats
+    // Featured products are the ones creators feature on their storefronts
     let stats = sqlx::query_as::<_, (i64, i64, i64, f64)>(
-        "SELECT 
-            COUNT(*) as total_products,
-            COUNT(CASE WHEN is_digital = true THEN 1 END) as featured_count,
-            COUNT(DISTINCT user_id) as creator_count,
-            COALESCE(SUM(price), 0) as total_revenue
-         FROM products",
+        r#"
+        SELECT
+            COUNT(*),
+            COUNT(*) FILTER (
+                WHERE EXISTS (SELECT 1 FROM storefront_settings s WHERE s.featured_product_id = p.id)
+            ),
+            COUNT(DISTINCT p.user_id),
+            (SELECT COALESCE(SUM(amount), 0) FROM purchases WHERE status = 'COMPLETED')
+        FROM products p
+        WHERE p.is_active
+        "#,
     )
     .fetch_one(&db.pool)
     .await
-    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    .map_err(|e| {
+        tracing::error!("Failed to load product stats: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
 
     let response = serde_json::json!({
         "success": true,
@@ -595,9 +1073,16 @@ async fn get_products_meta(
         }
     });
 
+    if let Some(redis) = &db.redis {
+        let mut redis_clone = redis.clone();
+        let _ = redis_clone
+            .set_ex(cache_key, &response.to_string(), CATALOG_CACHE_SECONDS)
+            .await;
+    }
     Ok(Json(response))
 }
 
+#[allow(dead_code)]
 #[derive(Debug, Serialize)]
 struct ProductCollections {
     featured: Vec<Product>,
@@ -608,27 +1093,65 @@ struct ProductCollections {
 async fn get_products_collections(
     State(db): State<Database>,
 ) -> Result<Json<serde_json::Value>, StatusCode> {
-    // Get featured products (digital products)
-    let featured = sqlx::query_as::<_, Product>(
-        "SELECT * FROM products WHERE is_digital = true ORDER BY created_at DESC LIMIT 6",
+    let cache_key = "products:collections";
+    if let Some(redis) = &db.redis {
+        let mut redis_clone = redis.clone();
+        if let Ok(Some(cached)) = redis_clone.get(cache_key).await {
+            if let Ok(cached_value) = serde_json::from_str::<serde_json::Value>(&cached) {
+                return Ok(Json(cached_value));
+            }
+        }
+    }
+
+    // Storefront picks first, then the newest digital products
+    let mut featured = sqlx::query_as::<_, Product>(
+        r#"
+        SELECT * FROM products p
+        WHERE p.is_active
+          AND (p.is_digital OR EXISTS (SELECT 1 FROM storefront_settings s WHERE s.featured_product_id = p.id))
+        ORDER BY EXISTS (SELECT 1 FROM storefront_settings s WHERE s.featured_product_id = p.id) DESC,
+                 p.created_at DESC
+        LIMIT 6
+        "#,
     )
     .fetch_all(&db.pool)
     .await
     .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
-    // Get top selling products (by price, as we don't have sales data)
-    let top_selling =
-        sqlx::query_as::<_, Product>("SELECT * FROM products ORDER BY price DESC LIMIT 6")
-            .fetch_all(&db.pool)
-            .await
-            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    // Most completed purchases over the last 30 days
+    let mut top_selling = sqlx::query_as::<_, Product>(
+        r#"
+        SELECT p.* FROM products p
+        JOIN (
+            SELECT product_id, COUNT(*) AS sales FROM purchases
+            WHERE status = 'COMPLETED' AND created_at > NOW() - INTERVAL '30 days'
+            GROUP BY product_id
+        ) s ON s.product_id = p.id
+        WHERE p.is_active
+        ORDER BY s.sales DESC, p.created_at DESC
+        LIMIT 6
+        "#,
+    )
+    .fetch_all(&db.pool)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
-    // Get new arrivals
-    let new_arrivals =
-        sqlx::query_as::<_, Product>("SELECT * FROM products ORDER BY created_at DESC LIMIT 6")
-            .fetch_all(&db.pool)
-            .await
-            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    // Pre-orders arrive on their release date
+    let mut new_arrivals = sqlx::query_as::<_, Product>(
+        r#"
+        SELECT * FROM products
+        WHERE is_active AND (release_at IS NULL OR release_at <= NOW())
+        ORDER BY COALESCE(release_at, created_at) DESC
+        LIMIT 6
+        "#,
+    )
+    .fetch_all(&db.pool)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    for products in [&mut featured, &mut top_selling, &mut new_arrivals] {
+        apply_sale_prices(&db, products).await?;
+    }
 
     let response = serde_json::json!({
         "success": true,
@@ -639,5 +1162,11 @@ async fn get_products_collections(
         }
     });
 
+    if let Some(redis) = &db.redis {
+        let mut redis_clone = redis.clone();
+        let _ = redis_clone
+            .set_ex(cache_key, &response.to_string(), CATALOG_CACHE_SECONDS)
+            .await;
+    }
     Ok(Json(response))
 }
@@ -11,14 +11,28 @@ use sqlx::{postgres::PgRow, Row};
 use tracing::error;
 use uuid::Uuid;
 
-use crate::{auth::Claims, database::Database, models::Purchase};
+use crate::{
+    auth::Claims,
+    database::Database,
+    models::Purchase,
+    routes::{
+        fees::settle_ledger_entries,
+        invoices::{issue_purchase_invoice, purchase_invoice_routes},
+        inventory::consume_stock,
+        licenses::issue_license_key,
+        stripe::{stripe_secret, stripe_url},
+    },
+};
 
 const PURCHASE_WITH_PRODUCT_QUERY: &str = r#"
     SELECT
         p.id,
         p.product_id,
+        p.variant_id,
+        v.name AS variant_name,
         p.user_id,
         p.amount,
+        p.discount_amount,
         p.currency,
         p.status,
         p.stripe_payment_intent_id,
@@ -30,16 +44,23 @@ const PURCHASE_WITH_PRODUCT_QUERY: &str = r#"
         pr.currency AS product_currency,
         pr.image_url AS product_image_url,
         pr.is_digital AS product_is_digital,
-        pr.download_url AS product_download_url,
+        -- Pre-ordered files stay hidden until release
+        CASE
+            WHEN pr.release_at > NOW() THEN NULL
+            ELSE COALESCE(NULLIF(v.download_url, ''), pr.download_url)
+        END AS product_download_url,
+        pr.release_at AS product_release_at,
         pr.user_id AS product_creator_id
     FROM purchases p
     JOIN products pr ON pr.id = p.product_id
+    LEFT JOIN product_variants v ON v.id = p.variant_id
 "#;
 
 pub fn purchase_routes() -> Router<Database> {
     Router::new()
         .route("/me", get(get_my_purchases))
         .route("/confirm", post(confirm_purchase))
+        .merge(purchase_invoice_routes())
 }
 
 #[derive(Debug, Deserialize)]
@@ -147,19 +168,11 @@ async fn confirm_purchase(
     })?
     .ok_or(StatusCode::NOT_FOUND)?;
 
-    let stripe_secret =
-        std::env::var("STRIPE_SECRET_KEY").map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
-
-    if stripe_secret.trim().is_empty() {
-        return Err(StatusCode::INTERNAL_SERVER_ERROR);
-    }
+    let stripe_secret = stripe_secret()?;
 
     let client = reqwest::Client::new();
     let response = client
-        .get(format!(
-            "https://api.stripe.com/v1/checkout/sessions/{}",
-            payload.session_id
-        ))
+        .get(stripe_url(&format!("/v1/checkout/sessions/{}", payload.session_id)))
         .header("Authorization", format!("Bearer {}", stripe_secret))
         .query(&[("expand[]", "payment_intent")])
         .send()
@@ -218,6 +231,16 @@ async fn confirm_purchase(
             );
             StatusCode::INTERNAL_SERVER_ERROR
         })?;
+        settle_ledger_entries(&db, &payload.session_id, payment_intent_id.as_deref()).await?;
+        if issue_purchase_invoice(&db, purchase.id).await.is_err() {
+            tracing::warn!("Failed to invoice purchase {}", purchase.id);
+        }
+        if issue_license_key(&db, purchase.id).await.is_err() {
+            tracing::warn!("Failed to issue license key for purchase {}", purchase.id);
+        }
+        if consume_stock(&db, &purchase.id.to_string()).await.is_err() {
+            tracing::warn!("Failed to take purchase {} off stock", purchase.id);
+        }
     }
 
     let purchase_json = load_purchase_with_product(&db, purchase.id).await?;
@@ -228,7 +251,7 @@ async fn confirm_purchase(
     })))
 }
 
-fn extract_payment_intent_id(session: &serde_json::Value) -> Option<String> {
+pub(crate) fn extract_payment_intent_id(session: &serde_json::Value) -> Option<String> {
     match session.get("payment_intent") {
         Some(serde_json::Value::String(id)) => Some(id.clone()),
         Some(serde_json::Value::Object(obj)) => obj
@@ -244,12 +267,21 @@ fn map_purchase_row(row: PgRow) -> Result<serde_json::Value, StatusCode> {
     let product_id: Uuid = row
         .try_get("product_id")
         .map_err(|err| map_row_error("product_id", err))?;
+    let variant_id: Option<Uuid> = row
+        .try_get("variant_id")
+        .map_err(|err| map_row_error("variant_id", err))?;
+    let variant_name: Option<String> = row
+        .try_get("variant_name")
+        .map_err(|err| map_row_error("variant_name", err))?;
     let user_id: String = row
         .try_get("user_id")
         .map_err(|err| map_row_error("user_id", err))?;
     let amount: f64 = row
         .try_get("amount")
         .map_err(|err| map_row_error("amount", err))?;
+    let discount_amount: Option<f64> = row
+        .try_get("discount_amount")
+        .map_err(|err| map_row_error("discount_amount", err))?;
     let currency: String = row
         .try_get("currency")
         .map_err(|err| map_row_error("currency", err))?;
@@ -290,6 +322,10 @@ fn map_purchase_row(row: PgRow) -> Result<serde_json::Value, StatusCode> {
     let product_creator_id: String = row
         .try_get("product_creator_id")
         .map_err(|err| map_row_error("product_creator_id", err))?;
+    let product_release_at: Option<chrono::DateTime<chrono::Utc>> = row
+        .try_get("product_release_at")
+        .map_err(|err| map_row_error("product_release_at", err))?;
+    let preorder = product_release_at.is_some_and(|release_at| release_at > chrono::Utc::now());
 
     let product_json = json!({
         "id": product_id,
@@ -300,9 +336,10 @@ fn map_purchase_row(row: PgRow) -> Result<serde_json::Value, StatusCode> {
         "currency": product_currency,
         "image_url": product_image_url,
         "coverImage": product_image_url,
-        "download_url": product_download_url,
-        "fileUrl": product_download_url,
+        // The file itself is only handed out as a signed link by the download endpoint
+        "hasFile": product_download_url.is_some_and(|url| !url.trim().is_empty()),
         "is_digital": product_is_digital,
+        "releaseAt": product_release_at,
         "user_id": product_creator_id.clone(),
         "creatorId": product_creator_id,
     });
@@ -310,10 +347,14 @@ fn map_purchase_row(row: PgRow) -> Result<serde_json::Value, StatusCode> {
     Ok(json!({
         "id": id,
         "productId": product_id,
+        "variantId": variant_id,
+        "variantName": variant_name,
         "userId": user_id,
         "amount": amount,
+        "discountAmount": discount_amount,
         "currency": currency,
         "status": status,
+        "preorder": preorder,
         "stripePaymentIntentId": stripe_payment_intent_id,
         "stripeCheckoutSessionId": stripe_checkout_session_id,
         "purchasedAt": created_at,
@@ -0,0 +1,314 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::Json,
+    routing::{get, post},
+    Router,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use uuid::Uuid;
+
+use crate::{auth::Claims, database::Database, routes::fees::ensure_admin};
+
+/// More referrals than this through one code within the window below look scripted.
+const BURST_REFERRALS: i64 = 10;
+const BURST_WINDOW_MINUTES: i32 = 60;
+/// More referred accounts than this from one network for the same referrer look like one person.
+const SAME_NETWORK_REFERRALS: i64 = 2;
+
+/// What a referral was flagged for, as stored in `referral_fraud_flags.signals`.
+const SELF_REFERRAL: &str = "SELF_REFERRAL";
+const SAME_CARD: &str = "SAME_CARD";
+const BURST: &str = "BURST";
+
+/// Review state of a flagged referral.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum FlagStatus {
+    /// Waiting for an admin; rewards stay held.
+    Open,
+    /// Looked fine on review; rewards are credited.
+    Cleared,
+    /// Fraud; rewards are revoked and none are credited.
+    Confirmed,
+}
+
+impl FlagStatus {
+    fn parse(value: &str) -> Option<Self> {
+        match value {
+            "OPEN" => Some(FlagStatus::Open),
+            "CLEARED" => Some(FlagStatus::Cleared),
+            "CONFIRMED" => Some(FlagStatus::Confirmed),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+struct FlaggedReferral {
+    id: Uuid,
+    referral_id: Uuid,
+    signals: Vec<String>,
+    /// `OPEN`, `CLEARED` or `CONFIRMED`.
+    status: String,
+    code: String,
+    referrer_id: String,
+    referrer_username: Option<String>,
+    referred_user_id: String,
+    referred_username: Option<String>,
+    referred_at: DateTime<Utc>,
+    reward_type: Option<String>,
+    reward_amount_cents: Option<i64>,
+    reward_months: Option<i32>,
+    reward_status: Option<String>,
+    review_note: Option<String>,
+    reviewed_by: Option<String>,
+    reviewed_at: Option<DateTime<Utc>>,
+    created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+struct FlagQueueQuery {
+    status: Option<String>,
+    page: Option<u32>,
+    limit: Option<u32>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum ReviewDecision {
+    /// Not fraud: release the held reward.
+    Clear,
+    /// Fraud: claw back the reward.
+    Confirm,
+}
+
+#[derive(Debug, Deserialize)]
+struct ReviewRequest {
+    decision: ReviewDecision,
+    note: Option<String>,
+}
+
+pub fn referral_fraud_routes() -> Router<Database> {
+    Router::new()
+        .route("/", get(list_flags))
+        .route("/:id/review", post(review_flag))
+}
+
+/// Run the fraud checks on a referral and flag it when any fire. A referral flagged before keeps
+/// its review state, so a cleared referral is not put back in the queue for the same signals;
+/// new signals reopen it. Returns the review state, `None` when it was never flagged.
+pub(crate) async fn screen_referral(db: &Database, referral_id: Uuid) -> Result<Option<FlagStatus>, sqlx::Error> {
+    let (self_referral, same_card, burst) = sqlx::query_as::<_, (bool, bool, bool)>(
+        r#"
+        SELECT
+            (referred.signup_ip_hash IS NOT NULL AND referred.signup_ip_hash = referrer.signup_ip_hash)
+            OR (r.ip_hash IS NOT NULL AND r.ip_hash = referrer.signup_ip_hash)
+            OR (referred.email IS NOT NULL AND referrer.email IS NOT NULL
+                AND regexp_replace(LOWER(split_part(referred.email, '@', 1)), '\+.*$|\.', '', 'g')
+                    = regexp_replace(LOWER(split_part(referrer.email, '@', 1)), '\+.*$|\.', '', 'g')
+                AND LOWER(split_part(referred.email, '@', 2)) = LOWER(split_part(referrer.email, '@', 2))),
+            EXISTS (
+                SELECT 1
+                FROM payment_card_fingerprints mine
+                JOIN payment_card_fingerprints other ON other.fingerprint = mine.fingerprint
+                WHERE mine.user_id = r.referred_user_id
+                  AND (other.user_id = r.referrer_id
+                       OR other.user_id IN (
+                           SELECT o.referred_user_id FROM referrals o
+                           WHERE o.referrer_id = r.referrer_id AND o.id <> r.id
+                       ))
+            ),
+            (SELECT COUNT(*) FROM referrals o
+             WHERE o.code_id = r.code_id
+               AND o.created_at BETWEEN r.created_at - make_interval(mins => $2)
+                                    AND r.created_at + make_interval(mins => $2)) > $3
+            OR (r.ip_hash IS NOT NULL AND (
+                SELECT COUNT(*) FROM referrals o
+                WHERE o.referrer_id = r.referrer_id AND o.ip_hash = r.ip_hash) > $4)
+        FROM referrals r
+        JOIN users referred ON referred.id = r.referred_user_id
+        JOIN users referrer ON referrer.id = r.referrer_id
+        WHERE r.id = $1
+        "#,
+    )
+    .bind(referral_id)
+    .bind(BURST_WINDOW_MINUTES)
+    .bind(BURST_REFERRALS)
+    .bind(SAME_NETWORK_REFERRALS)
+    .fetch_optional(&db.pool)
+    .await?
+    .unwrap_or((false, false, false));
+
+    let signals = [(self_referral, SELF_REFERRAL), (same_card, SAME_CARD), (burst, BURST)]
+        .into_iter()
+        .filter_map(|(fired, signal)| fired.then_some(signal))
+        .collect::<Vec<_>>();
+
+    let status = if signals.is_empty() {
+        sqlx::query_scalar::<_, String>("SELECT status FROM referral_fraud_flags WHERE referral_id = $1")
+            .bind(referral_id)
+            .fetch_optional(&db.pool)
+            .await?
+    } else {
+        let status = sqlx::query_scalar::<_, String>(
+            r#"
+            INSERT INTO referral_fraud_flags (referral_id, signals)
+            VALUES ($1, $2)
+            ON CONFLICT (referral_id) DO UPDATE
+            SET signals = ARRAY(SELECT DISTINCT UNNEST(referral_fraud_flags.signals || EXCLUDED.signals)),
+                status = CASE
+                    WHEN referral_fraud_flags.status = 'CLEARED'
+                         AND NOT (EXCLUDED.signals <@ referral_fraud_flags.signals) THEN 'OPEN'
+                    ELSE referral_fraud_flags.status
+                END,
+                updated_at = NOW()
+            RETURNING status
+            "#,
+        )
+        .bind(referral_id)
+        .bind(&signals)
+        .fetch_one(&db.pool)
+        .await?;
+        tracing::warn!("Referral {} flagged: {}", referral_id, signals.join(", "));
+        Some(status)
+    };
+    Ok(status.as_deref().and_then(FlagStatus::parse))
+}
+
+// Flagged referrals waiting longest come first
+async fn list_flags(
+    State(db): State<Database>,
+    Query(params): Query<FlagQueueQuery>,
+    claims: Claims,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    ensure_admin(&db, &claims.sub).await?;
+    let page = params.page.unwrap_or(1).max(1);
+    let limit = params.limit.unwrap_or(20).clamp(1, 100);
+    let offset = ((page - 1) * limit) as i64;
+    let status = params
+        .status
+        .map(|status| status.trim().to_ascii_uppercase())
+        .unwrap_or_else(|| "OPEN".to_string());
+
+    let flags = sqlx::query_as::<_, FlaggedReferral>(
+        r#"
+        SELECT f.id, f.referral_id, f.signals, f.status, c.code,
+               r.referrer_id, referrer.username AS referrer_username,
+               r.referred_user_id, referred.username AS referred_username,
+               r.created_at AS referred_at,
+               rw.reward_type, rw.amount_cents AS reward_amount_cents, rw.months AS reward_months,
+               rw.status AS reward_status,
+               f.review_note, f.reviewed_by, f.reviewed_at, f.created_at
+        FROM referral_fraud_flags f
+        JOIN referrals r ON r.id = f.referral_id
+        JOIN referral_codes c ON c.id = r.code_id
+        JOIN users referrer ON referrer.id = r.referrer_id
+        JOIN users referred ON referred.id = r.referred_user_id
+        LEFT JOIN referral_rewards rw ON rw.referral_id = r.id
+        WHERE f.status = $1
+        ORDER BY f.created_at
+        LIMIT $2 OFFSET $3
+        "#,
+    )
+    .bind(&status)
+    .bind(limit as i64)
+    .bind(offset)
+    .fetch_all(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to list flagged referrals: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    let total = sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM referral_fraud_flags WHERE status = $1")
+        .bind(&status)
+        .fetch_one(&db.pool)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(json!({
+        "success": true,
+        "data": flags,
+        "pagination": {
+            "page": page,
+            "limit": limit,
+            "total": total,
+            "pages": ((total as f64) / (limit as f64)).ceil() as u32,
+        }
+    })))
+}
+
+/// Decide a flagged referral. Clearing credits a held reward; confirming fraud revokes the
+/// reward, credited or not, which takes it back out of the referrer's balance.
+async fn review_flag(
+    State(db): State<Database>,
+    Path(id): Path<Uuid>,
+    claims: Claims,
+    Json(payload): Json<ReviewRequest>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    ensure_admin(&db, &claims.sub).await?;
+    let (status, reward_from, reward_to) = match payload.decision {
+        ReviewDecision::Clear => ("CLEARED", vec!["HELD"], "CREDITED"),
+        ReviewDecision::Confirm => ("CONFIRMED", vec!["HELD", "CREDITED"], "REVOKED"),
+    };
+    let note = payload
+        .note
+        .as_deref()
+        .map(str::trim)
+        .filter(|note| !note.is_empty());
+    let db_error = |e: sqlx::Error| {
+        tracing::error!("Failed to review flagged referral {}: {}", id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    };
+
+    let mut tx = db.pool.begin().await.map_err(db_error)?;
+    let referral_id = sqlx::query_scalar::<_, Uuid>(
+        r#"
+        UPDATE referral_fraud_flags
+        SET status = $2, review_note = $3, reviewed_by = $4, reviewed_at = NOW(), updated_at = NOW()
+        WHERE id = $1
+        RETURNING referral_id
+        "#,
+    )
+    .bind(id)
+    .bind(status)
+    .bind(note)
+    .bind(&claims.sub)
+    .fetch_optional(&mut tx)
+    .await
+    .map_err(db_error)?
+    .ok_or(StatusCode::NOT_FOUND)?;
+    let reward = sqlx::query_as::<_, (Uuid, String, i64, i32)>(
+        r#"
+        UPDATE referral_rewards
+        SET status = $3
+        WHERE referral_id = $1 AND status = ANY($2)
+        RETURNING id, reward_type, amount_cents, months
+        "#,
+    )
+    .bind(referral_id)
+    .bind(&reward_from)
+    .bind(reward_to)
+    .fetch_optional(&mut tx)
+    .await
+    .map_err(db_error)?;
+    tx.commit().await.map_err(db_error)?;
+
+    Ok(Json(json!({
+        "success": true,
+        "data": {
+            "id": id,
+            "referralId": referral_id,
+            "status": status,
+            "reward": reward.map(|(reward_id, reward_type, amount_cents, months)| json!({
+                "id": reward_id,
+                "rewardType": reward_type,
+                "amountCents": amount_cents,
+                "months": months,
+                "status": reward_to,
+            })),
+        }
+    })))
+}
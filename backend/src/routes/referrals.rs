@@ -1,8 +1,8 @@
 use axum::{
-    extract::{Json, Path, State},
+    extract::{Json, Path, Query, State},
     http::StatusCode,
     response::Json as ResponseJson,
-    routing::{get, patch, post},
+    routing::{get, patch},
     Router,
 };
 use serde::{Deserialize, Serialize};
@@ -10,7 +10,37 @@ use serde_json::json;
 use sqlx::{postgres::PgRow, FromRow, Row};
 use uuid::Uuid;
 
-use crate::{auth::Claims, database::Database};
+use crate::{
+    auth::Claims,
+    database::Database,
+    routes::{
+        analytics::RangeQuery,
+        referral_fraud::{screen_referral, FlagStatus},
+    },
+};
+
+/// Reward types: free months of membership, or site credit in cents.
+const SUBSCRIPTION_CREDIT: &str = "SUBSCRIPTION_CREDIT";
+const SITE_CREDIT: &str = "SITE_CREDIT";
+/// When the referrer is rewarded: once the referred user signs up, or once they first pay.
+const REWARD_ON: [&str; 2] = ["SIGNUP", "FIRST_PAYMENT"];
+const MAX_REWARD_MONTHS: i32 = 12;
+
+/// What a referred user paid for, as stored in `referral_conversions.event_type`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ReferralEvent {
+    Subscription,
+    Donation,
+}
+
+impl ReferralEvent {
+    fn as_str(self) -> &'static str {
+        match self {
+            ReferralEvent::Subscription => "SUBSCRIPTION",
+            ReferralEvent::Donation => "DONATION",
+        }
+    }
+}
 
 #[derive(Debug, Serialize)]
 struct ReferralCodeResponse {
@@ -19,6 +49,12 @@ struct ReferralCodeResponse {
     description: Option<String>,
     #[serde(rename = "rewardType")]
     reward_type: String,
+    #[serde(rename = "rewardAmountCents")]
+    reward_amount_cents: Option<i32>,
+    #[serde(rename = "rewardMonths")]
+    reward_months: i32,
+    #[serde(rename = "rewardOn")]
+    reward_on: String,
     #[serde(rename = "usageLimit")]
     usage_limit: Option<i32>,
     #[serde(rename = "usageCount")]
@@ -40,6 +76,9 @@ impl<'r> FromRow<'r, PgRow> for ReferralCodeResponse {
             code: row.try_get("code")?,
             description: row.try_get("description")?,
             reward_type: row.try_get("reward_type")?,
+            reward_amount_cents: row.try_get("reward_amount_cents")?,
+            reward_months: row.try_get("reward_months")?,
+            reward_on: row.try_get("reward_on")?,
             usage_limit: row.try_get("usage_limit")?,
             usage_count: row.try_get("usage_count")?,
             expires_at: row.try_get("expires_at")?,
@@ -58,6 +97,9 @@ struct CreateReferralCodeInput {
     usage_limit: Option<i32>,
     expires_at: Option<chrono::DateTime<chrono::Utc>>,
     reward_type: Option<String>,
+    reward_amount_cents: Option<i32>,
+    reward_months: Option<i32>,
+    reward_on: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -68,12 +110,17 @@ struct UpdateReferralCodeInput {
     expires_at: Option<chrono::DateTime<chrono::Utc>>,
     is_active: Option<bool>,
     reward_type: Option<String>,
+    reward_amount_cents: Option<i32>,
+    reward_months: Option<i32>,
+    reward_on: Option<String>,
 }
 
 pub fn referral_routes() -> Router<Database> {
     Router::new()
         .route("/validate/:code", get(validate_code))
         .route("/", get(list_codes).post(create_code))
+        .route("/stats", get(get_referral_stats))
+        .route("/rewards", get(list_rewards))
         .route("/:id", patch(update_code))
 }
 
@@ -88,6 +135,9 @@ async fn list_codes(
             code,
             description,
             reward_type,
+            reward_amount_cents,
+            reward_months,
+            reward_on,
             usage_limit,
             usage_count,
             expires_at,
@@ -115,19 +165,29 @@ async fn create_code(
     claims: Claims,
     Json(payload): Json<CreateReferralCodeInput>,
 ) -> Result<ResponseJson<serde_json::Value>, StatusCode> {
-    let code = payload.code.unwrap_or_else(|| generate_referral_code());
+    let code = payload.code.unwrap_or_else(generate_referral_code);
     let reward_type = payload
         .reward_type
-        .unwrap_or_else(|| "SUBSCRIPTION_CREDIT".to_string());
+        .unwrap_or_else(|| SUBSCRIPTION_CREDIT.to_string());
+    validate_reward(
+        Some(&reward_type),
+        payload.reward_amount_cents,
+        payload.reward_months,
+        payload.reward_on.as_deref(),
+    )?;
+    if reward_type == SITE_CREDIT && payload.reward_amount_cents.is_none() {
+        return Err(StatusCode::BAD_REQUEST);
+    }
 
     let record = sqlx::query_as::<_, ReferralCodeResponse>(
         r#"
         INSERT INTO referral_codes (
-            id, creator_id, code, description, reward_type, usage_limit, usage_count, expires_at, is_active, created_at, updated_at
+            id, creator_id, code, description, reward_type, reward_amount_cents, reward_months, reward_on,
+            usage_limit, usage_count, expires_at, is_active, created_at, updated_at
         ) VALUES (
-            $1, $2, $3, $4, $5, $6, 0, $7, TRUE, NOW(), NOW()
+            $1, $2, $3, $4, $5, $8, COALESCE($9, 1), COALESCE($10, 'FIRST_PAYMENT'), $6, 0, $7, TRUE, NOW(), NOW()
         )
-        RETURNING id, code, description, reward_type, usage_limit, usage_count, expires_at, is_active, created_at, updated_at
+        RETURNING id, code, description, reward_type, reward_amount_cents, reward_months, reward_on, usage_limit, usage_count, expires_at, is_active, created_at, updated_at
         "#,
     )
     .bind(Uuid::new_v4())
@@ -137,6 +197,9 @@ async fn create_code(
     .bind(reward_type)
     .bind(payload.usage_limit)
     .bind(payload.expires_at)
+    .bind(payload.reward_amount_cents)
+    .bind(payload.reward_months)
+    .bind(&payload.reward_on)
     .fetch_one(&db.pool)
     .await
     .map_err(|e| match &e {
@@ -158,6 +221,12 @@ async fn update_code(
     claims: Claims,
     Json(payload): Json<UpdateReferralCodeInput>,
 ) -> Result<ResponseJson<serde_json::Value>, StatusCode> {
+    validate_reward(
+        payload.reward_type.as_deref(),
+        payload.reward_amount_cents,
+        payload.reward_months,
+        payload.reward_on.as_deref(),
+    )?;
     let record = sqlx::query_as::<_, ReferralCodeResponse>(
         r#"
         UPDATE referral_codes
@@ -167,9 +236,12 @@ async fn update_code(
             expires_at = COALESCE($5, expires_at),
             is_active = COALESCE($6, is_active),
             reward_type = COALESCE($7, reward_type),
+            reward_amount_cents = COALESCE($8, reward_amount_cents),
+            reward_months = COALESCE($9, reward_months),
+            reward_on = COALESCE($10, reward_on),
             updated_at = NOW()
         WHERE id = $1 AND creator_id = $2
-        RETURNING id, code, description, reward_type, usage_limit, usage_count, expires_at, is_active, created_at, updated_at
+        RETURNING id, code, description, reward_type, reward_amount_cents, reward_months, reward_on, usage_limit, usage_count, expires_at, is_active, created_at, updated_at
         "#,
     )
     .bind(id)
@@ -179,6 +251,9 @@ async fn update_code(
     .bind(payload.expires_at)
     .bind(payload.is_active)
     .bind(payload.reward_type.clone())
+    .bind(payload.reward_amount_cents)
+    .bind(payload.reward_months)
+    .bind(&payload.reward_on)
     .fetch_one(&db.pool)
     .await
     .map_err(|e| match e {
@@ -253,6 +328,409 @@ async fn validate_code(
     })))
 }
 
+#[derive(Debug, Deserialize)]
+struct ReferralStatsQuery {
+    /// `day` (the default) or `month`.
+    interval: Option<String>,
+    from: Option<chrono::NaiveDate>,
+    to: Option<chrono::NaiveDate>,
+    /// Revenue in other currencies is left out. USD unless given.
+    currency: Option<String>,
+}
+
+fn validate_reward(
+    reward_type: Option<&str>,
+    amount_cents: Option<i32>,
+    months: Option<i32>,
+    reward_on: Option<&str>,
+) -> Result<(), StatusCode> {
+    let valid = reward_type.is_none_or(|reward_type| [SUBSCRIPTION_CREDIT, SITE_CREDIT].contains(&reward_type))
+        && amount_cents.is_none_or(|amount| amount > 0)
+        && months.is_none_or(|months| (1..=MAX_REWARD_MONTHS).contains(&months))
+        && reward_on.is_none_or(|reward_on| REWARD_ON.contains(&reward_on));
+    if valid {
+        Ok(())
+    } else {
+        Err(StatusCode::BAD_REQUEST)
+    }
+}
+
+/// Attribute a user to the owner of a referral code, if the code is usable and the user has not
+/// been referred before. Counts towards the code's usage limit, and credits the owner right away
+/// when the code rewards signups. Failures are logged, never surfaced: a bad code must not stop
+/// a signup or donation.
+pub(crate) async fn attribute_referral(
+    db: &Database,
+    code: &str,
+    user_id: &str,
+    source: &str,
+    ip_hash: Option<&str>,
+) {
+    let code = code.trim();
+    if code.is_empty() {
+        return;
+    }
+    let referral_id = sqlx::query_scalar::<_, Uuid>(
+        r#"
+        WITH code AS (
+            UPDATE referral_codes
+            SET usage_count = COALESCE(usage_count, 0) + 1, updated_at = NOW()
+            WHERE LOWER(code) = LOWER($1)
+              AND COALESCE(is_active, TRUE)
+              AND (expires_at IS NULL OR expires_at > NOW())
+              AND (usage_limit IS NULL OR COALESCE(usage_count, 0) < usage_limit)
+              AND creator_id <> $2
+              AND NOT EXISTS (SELECT 1 FROM referrals WHERE referred_user_id = $2)
+            RETURNING id, creator_id
+        )
+        INSERT INTO referrals (code_id, referrer_id, referred_user_id, source, ip_hash)
+        SELECT id, creator_id, $2, $3, $4 FROM code
+        ON CONFLICT (referred_user_id) DO NOTHING
+        RETURNING id
+        "#,
+    )
+    .bind(code)
+    .bind(user_id)
+    .bind(source)
+    .bind(ip_hash)
+    .fetch_optional(&db.pool)
+    .await;
+    match referral_id {
+        Ok(Some(referral_id)) => credit_reward(db, referral_id, "SIGNUP").await,
+        Ok(None) => {}
+        Err(e) => tracing::error!("Failed to attribute {} to referral code {}: {}", user_id, code, e),
+    }
+}
+
+/// Record a payment by a referred user against their referral, once per payment, and credit
+/// the referrer if the code rewards a first payment.
+pub(crate) async fn record_conversion(
+    db: &Database,
+    user_id: &str,
+    event: ReferralEvent,
+    source_id: &str,
+    amount_cents: i64,
+    currency: &str,
+) {
+    let referral_id = sqlx::query_scalar::<_, Uuid>(
+        r#"
+        INSERT INTO referral_conversions (referral_id, event_type, source_id, amount_cents, currency)
+        SELECT id, $2, $3, $4, UPPER($5) FROM referrals WHERE referred_user_id = $1
+        ON CONFLICT (event_type, source_id) DO NOTHING
+        RETURNING referral_id
+        "#,
+    )
+    .bind(user_id)
+    .bind(event.as_str())
+    .bind(source_id)
+    .bind(amount_cents)
+    .bind(currency)
+    .fetch_optional(&db.pool)
+    .await;
+    match referral_id {
+        Ok(Some(referral_id)) => credit_reward(db, referral_id, "FIRST_PAYMENT").await,
+        Ok(None) => {}
+        Err(e) => tracing::error!(
+            "Failed to record referral conversion {} {}: {}",
+            event.as_str(),
+            source_id,
+            e
+        ),
+    }
+}
+
+/// Record a completed donation against its donor's referral.
+pub(crate) async fn record_donation(db: &Database, donation_id: Uuid) {
+    let donation = sqlx::query_as::<_, (Option<String>, f64, String)>(
+        "SELECT donor_id, amount, currency FROM donations WHERE id = $1",
+    )
+    .bind(donation_id)
+    .fetch_optional(&db.pool)
+    .await;
+    match donation {
+        Ok(Some((Some(donor_id), amount, currency))) => {
+            record_conversion(
+                db,
+                &donor_id,
+                ReferralEvent::Donation,
+                &donation_id.to_string(),
+                (amount * 100.0).round() as i64,
+                &currency,
+            )
+            .await
+        }
+        Ok(_) => {}
+        Err(e) => tracing::error!("Failed to load donation {} for referrals: {}", donation_id, e),
+    }
+}
+
+/// Credit the referrer once per referred user, with the reward the code offers now, if the code
+/// rewards at this point. A site credit code without an amount credits nothing. The referral is
+/// screened for fraud first: a flagged one gets its reward held for review, and one confirmed as
+/// fraud gets nothing.
+async fn credit_reward(db: &Database, referral_id: Uuid, reward_on: &str) {
+    let status = match screen_referral(db, referral_id).await {
+        Ok(None | Some(FlagStatus::Cleared)) => "CREDITED",
+        Ok(Some(FlagStatus::Open)) => "HELD",
+        Ok(Some(FlagStatus::Confirmed)) => return,
+        Err(e) => {
+            tracing::error!("Failed to screen referral {}: {}", referral_id, e);
+            "HELD"
+        }
+    };
+    let credited = sqlx::query(
+        r#"
+        INSERT INTO referral_rewards (referral_id, user_id, reward_type, amount_cents, months, status)
+        SELECT r.id, r.referrer_id, c.reward_type,
+               CASE WHEN c.reward_type = $3 THEN COALESCE(c.reward_amount_cents, 0) ELSE 0 END,
+               CASE WHEN c.reward_type = $3 THEN 0 ELSE c.reward_months END,
+               $4
+        FROM referrals r
+        JOIN referral_codes c ON c.id = r.code_id
+        WHERE r.id = $1
+          AND c.reward_on = $2
+          AND (c.reward_type <> $3 OR COALESCE(c.reward_amount_cents, 0) > 0)
+        ON CONFLICT (referral_id) DO NOTHING
+        "#,
+    )
+    .bind(referral_id)
+    .bind(reward_on)
+    .bind(SITE_CREDIT)
+    .bind(status)
+    .execute(&db.pool)
+    .await;
+    if let Err(e) = credited {
+        tracing::error!("Failed to credit reward for referral {}: {}", referral_id, e);
+    }
+}
+
+// How the creator's codes perform over a range: users referred, how many of them went on to
+// pay, what they paid, and the rewards the creator earned, per code and per day or month.
+async fn get_referral_stats(
+    State(db): State<Database>,
+    claims: Claims,
+    Query(query): Query<ReferralStatsQuery>,
+) -> Result<ResponseJson<serde_json::Value>, StatusCode> {
+    let range = RangeQuery {
+        interval: query.interval.clone(),
+        from: query.from,
+        to: query.to,
+        format: None,
+    };
+    let (interval, from, to) = range.resolve()?;
+    let step = format!("1 {}", interval.as_str());
+    let currency = query.currency.as_deref().unwrap_or("USD").to_ascii_uppercase();
+
+    let codes = sqlx::query(
+        r#"
+        SELECT
+            c.id, c.code, c.reward_type, c.usage_count, c.is_active,
+            (SELECT COUNT(*) FROM referrals r
+             WHERE r.code_id = c.id
+               AND r.created_at >= $2::DATE::TIMESTAMP AT TIME ZONE 'UTC'
+               AND r.created_at < ($3::DATE + 1)::TIMESTAMP AT TIME ZONE 'UTC') AS referred,
+            COALESCE(conv.converted, 0) AS converted,
+            COALESCE(conv.donations, 0) AS donations,
+            COALESCE(conv.donation_cents, 0) AS donation_cents,
+            COALESCE(conv.subscriptions, 0) AS subscriptions,
+            COALESCE(conv.subscription_cents, 0) AS subscription_cents,
+            COALESCE(rw.credit_cents, 0) AS credit_cents,
+            COALESCE(rw.months, 0) AS months
+        FROM referral_codes c
+        LEFT JOIN LATERAL (
+            SELECT
+                COUNT(DISTINCT rc.referral_id) AS converted,
+                COUNT(*) FILTER (WHERE rc.event_type = 'DONATION') AS donations,
+                SUM(rc.amount_cents) FILTER (WHERE rc.event_type = 'DONATION' AND rc.currency = $4)::BIGINT AS donation_cents,
+                COUNT(*) FILTER (WHERE rc.event_type = 'SUBSCRIPTION') AS subscriptions,
+                SUM(rc.amount_cents) FILTER (WHERE rc.event_type = 'SUBSCRIPTION' AND rc.currency = $4)::BIGINT AS subscription_cents
+            FROM referral_conversions rc
+            JOIN referrals r ON r.id = rc.referral_id
+            WHERE r.code_id = c.id
+              AND rc.created_at >= $2::DATE::TIMESTAMP AT TIME ZONE 'UTC'
+              AND rc.created_at < ($3::DATE + 1)::TIMESTAMP AT TIME ZONE 'UTC'
+        ) conv ON TRUE
+        LEFT JOIN LATERAL (
+            SELECT SUM(rw.amount_cents)::BIGINT AS credit_cents, SUM(rw.months)::BIGINT AS months
+            FROM referral_rewards rw
+            JOIN referrals r ON r.id = rw.referral_id
+            WHERE r.code_id = c.id
+              AND rw.status = 'CREDITED'
+              AND rw.created_at >= $2::DATE::TIMESTAMP AT TIME ZONE 'UTC'
+              AND rw.created_at < ($3::DATE + 1)::TIMESTAMP AT TIME ZONE 'UTC'
+        ) rw ON TRUE
+        WHERE c.creator_id = $1
+        ORDER BY referred DESC, c.created_at DESC
+        "#,
+    )
+    .bind(&claims.sub)
+    .bind(from)
+    .bind(to)
+    .bind(&currency)
+    .fetch_all(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to load referral stats of {}: {}", claims.sub, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    let series = sqlx::query_as::<_, (chrono::NaiveDate, i64, i64, i64)>(
+        r#"
+        SELECT
+            p.period::DATE,
+            (SELECT COUNT(*) FROM referrals r
+             WHERE r.referrer_id = $4
+               AND r.created_at >= p.period::TIMESTAMP AT TIME ZONE 'UTC'
+               AND r.created_at < (p.period + $3::INTERVAL)::TIMESTAMP AT TIME ZONE 'UTC'),
+            (SELECT COUNT(*) FROM referral_conversions rc
+             JOIN referrals r ON r.id = rc.referral_id
+             WHERE r.referrer_id = $4
+               AND rc.created_at >= p.period::TIMESTAMP AT TIME ZONE 'UTC'
+               AND rc.created_at < (p.period + $3::INTERVAL)::TIMESTAMP AT TIME ZONE 'UTC'),
+            (SELECT COALESCE(SUM(rc.amount_cents), 0)::BIGINT FROM referral_conversions rc
+             JOIN referrals r ON r.id = rc.referral_id
+             WHERE r.referrer_id = $4
+               AND rc.currency = $5
+               AND rc.created_at >= p.period::TIMESTAMP AT TIME ZONE 'UTC'
+               AND rc.created_at < (p.period + $3::INTERVAL)::TIMESTAMP AT TIME ZONE 'UTC')
+        FROM generate_series($1::DATE, $2::DATE, $3::INTERVAL) AS p(period)
+        ORDER BY p.period
+        "#,
+    )
+    .bind(interval.truncate(from))
+    .bind(to)
+    .bind(&step)
+    .bind(&claims.sub)
+    .bind(&currency)
+    .fetch_all(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to load referral series of {}: {}", claims.sub, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    let (mut referred, mut converted, mut revenue_cents, mut credit_cents, mut months) = (0, 0, 0, 0, 0);
+    let codes = codes
+        .iter()
+        .map(|row| {
+            let code_referred: i64 = row.get("referred");
+            let code_converted: i64 = row.get("converted");
+            let donation_cents: i64 = row.get("donation_cents");
+            let subscription_cents: i64 = row.get("subscription_cents");
+            let code_credit_cents: i64 = row.get("credit_cents");
+            let code_months: i64 = row.get("months");
+            referred += code_referred;
+            converted += code_converted;
+            revenue_cents += donation_cents + subscription_cents;
+            credit_cents += code_credit_cents;
+            months += code_months;
+            json!({
+                "id": row.get::<Uuid, _>("id"),
+                "code": row.get::<String, _>("code"),
+                "rewardType": row.get::<String, _>("reward_type"),
+                "usageCount": row.get::<Option<i32>, _>("usage_count").unwrap_or(0),
+                "isActive": row.get::<Option<bool>, _>("is_active").unwrap_or(true),
+                "referred": code_referred,
+                "converted": code_converted,
+                "conversionRate": if code_referred > 0 { code_converted as f64 / code_referred as f64 } else { 0.0 },
+                "donations": row.get::<i64, _>("donations"),
+                "donationCents": donation_cents,
+                "subscriptions": row.get::<i64, _>("subscriptions"),
+                "subscriptionCents": subscription_cents,
+                "rewardCreditCents": code_credit_cents,
+                "rewardMonths": code_months,
+            })
+        })
+        .collect::<Vec<_>>();
+
+    Ok(ResponseJson(json!({
+        "success": true,
+        "data": {
+            "interval": interval.as_str(),
+            "from": from,
+            "to": to,
+            "currency": currency,
+            "totals": {
+                "referred": referred,
+                "converted": converted,
+                "conversionRate": if referred > 0 { converted as f64 / referred as f64 } else { 0.0 },
+                "revenueCents": revenue_cents,
+                "rewardCreditCents": credit_cents,
+                "rewardMonths": months,
+            },
+            "codes": codes,
+            "series": series
+                .into_iter()
+                .map(|(period, referred, conversions, revenue_cents)| json!({
+                    "period": period,
+                    "referred": referred,
+                    "conversions": conversions,
+                    "revenueCents": revenue_cents,
+                }))
+                .collect::<Vec<_>>(),
+        }
+    })))
+}
+
+// Rewards the user earned by referring others, newest first, with their site credit and free
+// months in total. Held and revoked rewards are listed but not counted.
+async fn list_rewards(
+    State(db): State<Database>,
+    claims: Claims,
+) -> Result<ResponseJson<serde_json::Value>, StatusCode> {
+    let rewards = sqlx::query(
+        r#"
+        SELECT rw.id, rw.reward_type, rw.amount_cents, rw.currency, rw.months, rw.status, rw.created_at,
+               c.code, u.display_name AS referred_name
+        FROM referral_rewards rw
+        JOIN referrals r ON r.id = rw.referral_id
+        JOIN referral_codes c ON c.id = r.code_id
+        JOIN users u ON u.id = r.referred_user_id
+        WHERE rw.user_id = $1
+        ORDER BY rw.created_at DESC
+        "#,
+    )
+    .bind(&claims.sub)
+    .fetch_all(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to load referral rewards of {}: {}", claims.sub, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    let (mut credit_cents, mut months) = (0i64, 0i64);
+    let rewards = rewards
+        .iter()
+        .map(|row| {
+            let status: String = row.get("status");
+            if status == "CREDITED" {
+                credit_cents += row.get::<i64, _>("amount_cents");
+                months += row.get::<i32, _>("months") as i64;
+            }
+            json!({
+                "id": row.get::<Uuid, _>("id"),
+                "rewardType": row.get::<String, _>("reward_type"),
+                "amountCents": row.get::<i64, _>("amount_cents"),
+                "currency": row.get::<String, _>("currency"),
+                "months": row.get::<i32, _>("months"),
+                "status": status,
+                "code": row.get::<String, _>("code"),
+                "referredName": row.get::<Option<String>, _>("referred_name"),
+                "createdAt": row.get::<chrono::DateTime<chrono::Utc>, _>("created_at"),
+            })
+        })
+        .collect::<Vec<_>>();
+
+    Ok(ResponseJson(json!({
+        "success": true,
+        "data": {
+            "creditCents": credit_cents,
+            "freeMonths": months,
+            "rewards": rewards,
+        }
+    })))
+}
+
 fn generate_referral_code() -> String {
     let mut raw = Uuid::new_v4()
         .to_string()
@@ -0,0 +1,463 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::Json,
+    routing::{get, put},
+    Router,
+};
+use serde::Deserialize;
+use serde_json::json;
+use sqlx::Row;
+use tracing::error;
+use uuid::Uuid;
+
+use crate::{
+    auth::Claims, database::Database, middleware::optional_auth::MaybeClaims,
+    routes::taxonomy::slugify,
+};
+
+#[derive(Debug, Deserialize)]
+struct SeriesQuery {
+    #[serde(rename = "authorId")]
+    author_id: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SeriesRequest {
+    title: String,
+    description: Option<String>,
+    slug: Option<String>,
+    #[serde(alias = "articleIds")]
+    article_ids: Option<Vec<Uuid>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SeriesArticlesRequest {
+    #[serde(alias = "articleIds")]
+    article_ids: Vec<Uuid>,
+}
+
+pub fn series_routes() -> Router<Database> {
+    Router::new()
+        .route("/", get(list_series).post(create_series))
+        .route(
+            "/:id",
+            get(get_series).put(update_series).delete(delete_series),
+        )
+        .route("/:id/articles", put(set_series_articles))
+}
+
+async fn list_series(
+    State(db): State<Database>,
+    Query(params): Query<SeriesQuery>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let rows = sqlx::query(
+        r#"
+        SELECT s.id, s.title, s.slug, s.description, s.author_id, s.created_at,
+               COUNT(i.article_id) AS article_count
+        FROM article_series s
+        LEFT JOIN article_series_items i ON i.series_id = s.id
+        WHERE $1::TEXT IS NULL OR s.author_id = $1
+        GROUP BY s.id
+        ORDER BY s.created_at DESC
+        "#,
+    )
+    .bind(&params.author_id)
+    .fetch_all(&db.pool)
+    .await
+    .map_err(|e| {
+        error!("Failed to list article series: {:?}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    let series: Vec<serde_json::Value> = rows
+        .iter()
+        .map(|row| {
+            json!({
+                "id": row.get::<Uuid, _>("id"),
+                "title": row.get::<String, _>("title"),
+                "slug": row.get::<String, _>("slug"),
+                "description": row.get::<Option<String>, _>("description"),
+                "authorId": row.get::<String, _>("author_id"),
+                "articleCount": row.get::<i64, _>("article_count"),
+                "createdAt": row.get::<chrono::DateTime<chrono::Utc>, _>("created_at"),
+            })
+        })
+        .collect();
+
+    Ok(Json(json!({
+        "success": true,
+        "data": series
+    })))
+}
+
+async fn get_series(
+    State(db): State<Database>,
+    Path(id_or_slug): Path<String>,
+    MaybeClaims(maybe_claims): MaybeClaims,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let row = sqlx::query(
+        r#"
+        SELECT id, title, slug, description, author_id, created_at, updated_at
+        FROM article_series
+        WHERE id::TEXT = $1 OR slug = $1
+        "#,
+    )
+    .bind(&id_or_slug)
+    .fetch_optional(&db.pool)
+    .await
+    .map_err(|e| {
+        error!("Failed to load article series {}: {:?}", id_or_slug, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?
+    .ok_or(StatusCode::NOT_FOUND)?;
+
+    let series_id: Uuid = row.get("id");
+    let author_id: String = row.get("author_id");
+    let is_author = maybe_claims.map(|claims| claims.sub) == Some(author_id.clone());
+    let articles = load_table_of_contents(&db, series_id, is_author).await?;
+
+    Ok(Json(json!({
+        "success": true,
+        "data": {
+            "id": series_id,
+            "title": row.get::<String, _>("title"),
+            "slug": row.get::<String, _>("slug"),
+            "description": row.get::<Option<String>, _>("description"),
+            "authorId": author_id,
+            "articles": articles,
+            "createdAt": row.get::<chrono::DateTime<chrono::Utc>, _>("created_at"),
+            "updatedAt": row.get::<chrono::DateTime<chrono::Utc>, _>("updated_at"),
+        }
+    })))
+}
+
+async fn create_series(
+    State(db): State<Database>,
+    claims: Claims,
+    Json(payload): Json<SeriesRequest>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let title = payload.title.trim().to_string();
+    if title.is_empty() {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let slug = slugify(payload.slug.as_deref().unwrap_or(&title));
+    if slug.is_empty() {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let mut tx = db.pool.begin().await.map_err(|e| {
+        error!("Failed to start series transaction: {:?}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    let series_id = sqlx::query_scalar::<_, Uuid>(
+        r#"
+        INSERT INTO article_series (author_id, title, slug, description)
+        VALUES ($1, $2, $3, $4)
+        RETURNING id
+        "#,
+    )
+    .bind(&claims.sub)
+    .bind(&title)
+    .bind(&slug)
+    .bind(&payload.description)
+    .fetch_one(&mut tx)
+    .await
+    .map_err(|e| match &e {
+        sqlx::Error::Database(db_err)
+            if db_err.constraint() == Some("article_series_slug_key") =>
+        {
+            StatusCode::CONFLICT
+        }
+        _ => {
+            error!("Failed to create article series: {:?}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        }
+    })?;
+
+    if let Some(article_ids) = payload.article_ids {
+        replace_items(&mut tx, series_id, &claims.sub, &article_ids).await?;
+    }
+
+    tx.commit().await.map_err(|e| {
+        error!("Failed to commit article series {}: {:?}", series_id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    let articles = load_table_of_contents(&db, series_id, true).await?;
+
+    Ok(Json(json!({
+        "success": true,
+        "data": {
+            "id": series_id,
+            "title": title,
+            "slug": slug,
+            "description": payload.description,
+            "authorId": claims.sub,
+            "articles": articles,
+        }
+    })))
+}
+
+async fn update_series(
+    State(db): State<Database>,
+    Path(id): Path<Uuid>,
+    claims: Claims,
+    Json(payload): Json<SeriesRequest>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    ensure_series_owner(&db, id, &claims.sub).await?;
+
+    let title = payload.title.trim().to_string();
+    if title.is_empty() {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    sqlx::query(
+        r#"
+        UPDATE article_series
+        SET title = $2, description = $3, updated_at = NOW()
+        WHERE id = $1
+        "#,
+    )
+    .bind(id)
+    .bind(&title)
+    .bind(&payload.description)
+    .execute(&db.pool)
+    .await
+    .map_err(|e| {
+        error!("Failed to update article series {}: {:?}", id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    Ok(Json(json!({
+        "success": true,
+        "data": {
+            "id": id,
+            "title": title,
+            "description": payload.description,
+        }
+    })))
+}
+
+async fn delete_series(
+    State(db): State<Database>,
+    Path(id): Path<Uuid>,
+    claims: Claims,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    ensure_series_owner(&db, id, &claims.sub).await?;
+
+    sqlx::query("DELETE FROM article_series WHERE id = $1")
+        .bind(id)
+        .execute(&db.pool)
+        .await
+        .map_err(|e| {
+            error!("Failed to delete article series {}: {:?}", id, e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    Ok(Json(json!({
+        "success": true,
+        "message": "Series deleted"
+    })))
+}
+
+// Replace the series' articles with the given list, in reading order
+async fn set_series_articles(
+    State(db): State<Database>,
+    Path(id): Path<Uuid>,
+    claims: Claims,
+    Json(payload): Json<SeriesArticlesRequest>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    ensure_series_owner(&db, id, &claims.sub).await?;
+
+    let mut tx = db.pool.begin().await.map_err(|e| {
+        error!("Failed to start series transaction: {:?}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    replace_items(&mut tx, id, &claims.sub, &payload.article_ids).await?;
+    tx.commit().await.map_err(|e| {
+        error!("Failed to commit article series {}: {:?}", id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    let articles = load_table_of_contents(&db, id, true).await?;
+
+    Ok(Json(json!({
+        "success": true,
+        "data": {
+            "id": id,
+            "articles": articles,
+        }
+    })))
+}
+
+/// Series navigation for an article's detail page: table of contents plus previous/next links.
+pub(crate) async fn load_article_series(
+    db: &Database,
+    article_id: Uuid,
+    is_author: bool,
+) -> Result<Option<serde_json::Value>, StatusCode> {
+    let series = sqlx::query(
+        r#"
+        SELECT s.id, s.title, s.slug
+        FROM article_series_items i
+        JOIN article_series s ON s.id = i.series_id
+        WHERE i.article_id = $1
+        "#,
+    )
+    .bind(article_id)
+    .fetch_optional(&db.pool)
+    .await
+    .map_err(|e| {
+        error!("Failed to load series for article {}: {:?}", article_id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    let series = match series {
+        Some(series) => series,
+        None => return Ok(None),
+    };
+
+    let series_id: Uuid = series.get("id");
+    let articles = load_table_of_contents(db, series_id, is_author).await?;
+    let index = articles
+        .iter()
+        .position(|entry| entry["id"] == json!(article_id));
+    let previous = index
+        .and_then(|index| index.checked_sub(1))
+        .and_then(|index| articles.get(index))
+        .cloned();
+    let next = index
+        .and_then(|index| articles.get(index + 1))
+        .cloned();
+
+    Ok(Some(json!({
+        "id": series_id,
+        "title": series.get::<String, _>("title"),
+        "slug": series.get::<String, _>("slug"),
+        "position": index.map(|index| index + 1),
+        "total": articles.len(),
+        "previous": previous,
+        "next": next,
+        "tableOfContents": articles,
+    })))
+}
+
+/// Articles of a series in reading order. Readers only see published parts.
+async fn load_table_of_contents(
+    db: &Database,
+    series_id: Uuid,
+    include_unpublished: bool,
+) -> Result<Vec<serde_json::Value>, StatusCode> {
+    let rows = sqlx::query(
+        r#"
+        SELECT a.id, a.title, a.slug, a.published_at
+        FROM article_series_items i
+        JOIN articles a ON a.id = i.article_id
+        WHERE i.series_id = $1
+          AND ($2 OR a.published_at IS NULL OR a.published_at <= NOW())
+        ORDER BY i.position ASC
+        "#,
+    )
+    .bind(series_id)
+    .bind(include_unpublished)
+    .fetch_all(&db.pool)
+    .await
+    .map_err(|e| {
+        error!("Failed to load articles for series {}: {:?}", series_id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    Ok(rows
+        .iter()
+        .enumerate()
+        .map(|(index, row)| {
+            json!({
+                "id": row.get::<Uuid, _>("id"),
+                "title": row.get::<String, _>("title"),
+                "slug": row.get::<String, _>("slug"),
+                "position": index + 1,
+                "publishedAt": row.get::<Option<chrono::DateTime<chrono::Utc>>, _>("published_at"),
+            })
+        })
+        .collect())
+}
+
+async fn replace_items(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    series_id: Uuid,
+    author_id: &str,
+    article_ids: &[Uuid],
+) -> Result<(), StatusCode> {
+    let mut unique = article_ids.to_vec();
+    unique.sort();
+    unique.dedup();
+    if unique.len() != article_ids.len() {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let owned = sqlx::query_scalar::<_, i64>(
+        "SELECT COUNT(*) FROM articles WHERE id = ANY($1) AND author_id = $2",
+    )
+    .bind(article_ids)
+    .bind(author_id)
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    if owned != article_ids.len() as i64 {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    sqlx::query("DELETE FROM article_series_items WHERE series_id = $1")
+        .bind(series_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    for (position, article_id) in article_ids.iter().enumerate() {
+        // An article belongs to a single series; adding it here moves it out of any other.
+        sqlx::query(
+            r#"
+            INSERT INTO article_series_items (series_id, article_id, position)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (article_id) DO UPDATE
+            SET series_id = EXCLUDED.series_id, position = EXCLUDED.position
+            "#,
+        )
+        .bind(series_id)
+        .bind(article_id)
+        .bind(position as i32)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| {
+            error!("Failed to add article {} to series {}: {:?}", article_id, series_id, e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+    }
+
+    sqlx::query("UPDATE article_series SET updated_at = NOW() WHERE id = $1")
+        .bind(series_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(())
+}
+
+async fn ensure_series_owner(db: &Database, series_id: Uuid, user_id: &str) -> Result<(), StatusCode> {
+    let author_id =
+        sqlx::query_scalar::<_, String>("SELECT author_id FROM article_series WHERE id = $1")
+            .bind(series_id)
+            .fetch_optional(&db.pool)
+            .await
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+            .ok_or(StatusCode::NOT_FOUND)?;
+
+    if author_id == user_id {
+        Ok(())
+    } else {
+        Err(StatusCode::FORBIDDEN)
+    }
+}
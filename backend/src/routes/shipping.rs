@@ -0,0 +1,552 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::Json,
+    routing::{get, put},
+    Router,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use uuid::Uuid;
+
+use crate::{
+    auth::Claims,
+    database::Database,
+    models::Product,
+    notification_service::{notify, NotificationKind},
+};
+
+/// Where a physical order is shipped.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ShippingAddress {
+    pub name: String,
+    pub line1: String,
+    pub line2: Option<String>,
+    pub city: String,
+    pub region: Option<String>,
+    pub postal_code: Option<String>,
+    /// ISO 3166-1 alpha-2 code.
+    pub country: String,
+}
+
+impl ShippingAddress {
+    /// The address trimmed, with its country upper-cased, or 400 when a required line is missing.
+    fn normalized(&self) -> Result<Self, StatusCode> {
+        let required = |value: &str| {
+            let value = value.trim();
+            (!value.is_empty()).then(|| value.to_string()).ok_or(StatusCode::BAD_REQUEST)
+        };
+        let optional = |value: &Option<String>| {
+            value.as_deref().map(str::trim).filter(|value| !value.is_empty()).map(str::to_string)
+        };
+        Ok(ShippingAddress {
+            name: required(&self.name)?,
+            line1: required(&self.line1)?,
+            line2: optional(&self.line2),
+            city: required(&self.city)?,
+            region: optional(&self.region),
+            postal_code: optional(&self.postal_code),
+            country: parse_country(&self.country).ok_or(StatusCode::BAD_REQUEST)?,
+        })
+    }
+}
+
+fn parse_country(value: &str) -> Option<String> {
+    let country = value.trim().to_ascii_uppercase();
+    (country.len() == 2 && country.chars().all(|c| c.is_ascii_alphabetic())).then_some(country)
+}
+
+/// A creator's shipping rate for a set of countries. A zone without countries covers every
+/// country no other zone lists. Amounts are in the currency of the product shipped.
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct ShippingZone {
+    pub id: Uuid,
+    pub creator_id: String,
+    pub name: String,
+    pub countries: Vec<String>,
+    pub rate_cents: i64,
+    /// Orders of at least this much ship free.
+    pub free_over_cents: Option<i64>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl ShippingZone {
+    /// Shipping on an order of `amount_cents`.
+    fn cost_cents(&self, amount_cents: i64) -> i64 {
+        let free = self.free_over_cents.is_some_and(|threshold| amount_cents >= threshold);
+        if free {
+            0
+        } else {
+            self.rate_cents
+        }
+    }
+}
+
+/// Shipping for one order of a physical product.
+#[derive(Debug, Clone)]
+pub(crate) struct ShippingQuote {
+    pub zone_id: Uuid,
+    pub shipping_cents: i64,
+    pub address: ShippingAddress,
+}
+
+impl ShippingQuote {
+    pub(crate) fn address_json(&self) -> serde_json::Value {
+        json!(self.address)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum FulfillmentStatus {
+    /// Paid and waiting to be shipped.
+    Pending,
+    Shipped,
+    Delivered,
+}
+
+impl FulfillmentStatus {
+    fn as_str(self) -> &'static str {
+        match self {
+            FulfillmentStatus::Pending => "PENDING",
+            FulfillmentStatus::Shipped => "SHIPPED",
+            FulfillmentStatus::Delivered => "DELIVERED",
+        }
+    }
+
+    fn parse(value: &str) -> Option<Self> {
+        match value {
+            "PENDING" => Some(FulfillmentStatus::Pending),
+            "SHIPPED" => Some(FulfillmentStatus::Shipped),
+            "DELIVERED" => Some(FulfillmentStatus::Delivered),
+            _ => None,
+        }
+    }
+}
+
+/// A paid physical order, as the buyer and the creator see it.
+#[derive(Debug, Serialize, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+struct Order {
+    id: Uuid,
+    user_id: String,
+    product_id: Uuid,
+    product_name: String,
+    product_image_url: Option<String>,
+    creator_id: String,
+    variant_id: Option<Uuid>,
+    variant_name: Option<String>,
+    amount: f64,
+    shipping_cents: i64,
+    currency: String,
+    shipping_address: Option<serde_json::Value>,
+    fulfillment_status: Option<String>,
+    tracking_carrier: Option<String>,
+    tracking_number: Option<String>,
+    tracking_url: Option<String>,
+    shipped_at: Option<DateTime<Utc>>,
+    delivered_at: Option<DateTime<Utc>>,
+    created_at: DateTime<Utc>,
+}
+
+const ORDER_QUERY: &str = r#"
+    SELECT
+        p.id, p.user_id, p.product_id, pr.name AS product_name, pr.image_url AS product_image_url,
+        pr.user_id AS creator_id, p.variant_id, v.name AS variant_name, p.amount, p.shipping_cents,
+        p.currency, p.shipping_address, p.fulfillment_status, p.tracking_carrier, p.tracking_number,
+        p.tracking_url, p.shipped_at, p.delivered_at, p.created_at
+    FROM purchases p
+    JOIN products pr ON pr.id = p.product_id
+    LEFT JOIN product_variants v ON v.id = p.variant_id
+    WHERE p.fulfillment_status IS NOT NULL AND p.status = 'COMPLETED'
+"#;
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ZoneRequest {
+    name: String,
+    /// Leave empty for a zone covering the rest of the world.
+    #[serde(default)]
+    countries: Vec<String>,
+    rate_cents: i64,
+    free_over_cents: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ShippingQuoteQuery {
+    product_id: Uuid,
+    country: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct OrdersQuery {
+    status: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct UpdateFulfillmentRequest {
+    status: FulfillmentStatus,
+    tracking_carrier: Option<String>,
+    tracking_number: Option<String>,
+    tracking_url: Option<String>,
+}
+
+pub fn shipping_routes() -> Router<Database> {
+    Router::new()
+        .route("/zones", get(list_zones).post(create_zone))
+        .route("/zones/:id", put(update_zone).delete(delete_zone))
+        .route("/quote", get(get_shipping_quote))
+        .route("/orders", get(list_my_orders))
+        .route("/orders/:id", get(get_order))
+        .route("/sales", get(list_sales))
+        .route("/sales/:id", put(update_fulfillment))
+}
+
+/// Shipping for a physical product sent to `address`, or None for a digital one. Fails with 400
+/// when the address is missing and 422 when the creator does not ship to its country. Orders
+/// of at least a zone's threshold, measured on `amount_cents`, ship free.
+pub(crate) async fn quote_shipping(
+    db: &Database,
+    product: &Product,
+    address: Option<&ShippingAddress>,
+    amount_cents: i64,
+) -> Result<Option<ShippingQuote>, StatusCode> {
+    if product.is_digital {
+        return Ok(None);
+    }
+    let address = address.ok_or(StatusCode::BAD_REQUEST)?.normalized()?;
+    let zone = find_zone(db, &product.user_id, &address.country)
+        .await?
+        .ok_or(StatusCode::UNPROCESSABLE_ENTITY)?;
+    Ok(Some(ShippingQuote {
+        zone_id: zone.id,
+        shipping_cents: zone.cost_cents(amount_cents),
+        address,
+    }))
+}
+
+// A zone naming the country wins over the catch-all; the cheapest wins among overlapping zones
+async fn find_zone(db: &Database, creator_id: &str, country: &str) -> Result<Option<ShippingZone>, StatusCode> {
+    sqlx::query_as::<_, ShippingZone>(
+        r#"
+        SELECT * FROM shipping_zones
+        WHERE creator_id = $1 AND ($2 = ANY(countries) OR cardinality(countries) = 0)
+        ORDER BY cardinality(countries) = 0, rate_cents
+        LIMIT 1
+        "#,
+    )
+    .bind(creator_id)
+    .bind(country)
+    .fetch_optional(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to load shipping zones of {}: {}", creator_id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })
+}
+
+impl ZoneRequest {
+    fn validate(&self) -> Result<Vec<String>, StatusCode> {
+        let name = self.name.trim();
+        if name.is_empty() || name.len() > 100 || self.rate_cents < 0 {
+            return Err(StatusCode::BAD_REQUEST);
+        }
+        if self.free_over_cents.is_some_and(|threshold| threshold < 0) {
+            return Err(StatusCode::BAD_REQUEST);
+        }
+        let mut countries = self
+            .countries
+            .iter()
+            .map(|country| parse_country(country).ok_or(StatusCode::BAD_REQUEST))
+            .collect::<Result<Vec<_>, _>>()?;
+        countries.sort();
+        countries.dedup();
+        Ok(countries)
+    }
+}
+
+async fn list_zones(
+    State(db): State<Database>,
+    claims: Claims,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let zones = sqlx::query_as::<_, ShippingZone>(
+        "SELECT * FROM shipping_zones WHERE creator_id = $1 ORDER BY cardinality(countries) = 0, name",
+    )
+    .bind(&claims.sub)
+    .fetch_all(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to list shipping zones of {}: {}", claims.sub, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    Ok(Json(json!({ "success": true, "data": zones })))
+}
+
+async fn create_zone(
+    State(db): State<Database>,
+    claims: Claims,
+    Json(payload): Json<ZoneRequest>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let countries = payload.validate()?;
+    let zone = sqlx::query_as::<_, ShippingZone>(
+        r#"
+        INSERT INTO shipping_zones (creator_id, name, countries, rate_cents, free_over_cents)
+        VALUES ($1, $2, $3, $4, $5)
+        RETURNING *
+        "#,
+    )
+    .bind(&claims.sub)
+    .bind(payload.name.trim())
+    .bind(&countries)
+    .bind(payload.rate_cents)
+    .bind(payload.free_over_cents)
+    .fetch_one(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to create shipping zone for {}: {}", claims.sub, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    Ok(Json(json!({ "success": true, "data": zone })))
+}
+
+async fn update_zone(
+    State(db): State<Database>,
+    Path(id): Path<Uuid>,
+    claims: Claims,
+    Json(payload): Json<ZoneRequest>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let countries = payload.validate()?;
+    let zone = sqlx::query_as::<_, ShippingZone>(
+        r#"
+        UPDATE shipping_zones
+        SET name = $3, countries = $4, rate_cents = $5, free_over_cents = $6, updated_at = NOW()
+        WHERE id = $1 AND creator_id = $2
+        RETURNING *
+        "#,
+    )
+    .bind(id)
+    .bind(&claims.sub)
+    .bind(payload.name.trim())
+    .bind(&countries)
+    .bind(payload.rate_cents)
+    .bind(payload.free_over_cents)
+    .fetch_optional(&db.pool)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    .ok_or(StatusCode::NOT_FOUND)?;
+
+    Ok(Json(json!({ "success": true, "data": zone })))
+}
+
+async fn delete_zone(
+    State(db): State<Database>,
+    Path(id): Path<Uuid>,
+    claims: Claims,
+) -> Result<StatusCode, StatusCode> {
+    let deleted = sqlx::query("DELETE FROM shipping_zones WHERE id = $1 AND creator_id = $2")
+        .bind(id)
+        .bind(&claims.sub)
+        .execute(&db.pool)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .rows_affected();
+    if deleted == 0 {
+        return Err(StatusCode::NOT_FOUND);
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// What shipping a product to a country costs, shown on the product page before checkout
+async fn get_shipping_quote(
+    State(db): State<Database>,
+    Query(params): Query<ShippingQuoteQuery>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let product = sqlx::query_as::<_, Product>("SELECT * FROM products WHERE id = $1 AND is_active")
+        .bind(params.product_id)
+        .fetch_optional(&db.pool)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+    if product.is_digital {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let country = parse_country(&params.country).ok_or(StatusCode::BAD_REQUEST)?;
+    let zone = find_zone(&db, &product.user_id, &country).await?;
+    let amount_cents = (product.price * 100.0).round() as i64;
+
+    Ok(Json(json!({
+        "success": true,
+        "data": {
+            "ships": zone.is_some(),
+            "zone": zone.as_ref().map(|zone| &zone.name),
+            "shippingCents": zone.as_ref().map(|zone| zone.cost_cents(amount_cents)),
+            "freeOverCents": zone.as_ref().and_then(|zone| zone.free_over_cents),
+            "currency": product.currency,
+        }
+    })))
+}
+
+async fn list_my_orders(
+    State(db): State<Database>,
+    claims: Claims,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let orders = sqlx::query_as::<_, Order>(&format!(
+        "{} AND p.user_id = $1 ORDER BY p.created_at DESC",
+        ORDER_QUERY
+    ))
+    .bind(&claims.sub)
+    .fetch_all(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to list orders of {}: {}", claims.sub, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    Ok(Json(json!({ "success": true, "data": orders })))
+}
+
+// Tracking for one order, open to its buyer and the creator shipping it
+async fn get_order(
+    State(db): State<Database>,
+    Path(id): Path<Uuid>,
+    claims: Claims,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let order = sqlx::query_as::<_, Order>(&format!("{} AND p.id = $1", ORDER_QUERY))
+        .bind(id)
+        .fetch_optional(&db.pool)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+    if order.user_id != claims.sub && order.creator_id != claims.sub {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    Ok(Json(json!({ "success": true, "data": order })))
+}
+
+// Orders of the creator's physical products, oldest unshipped first
+async fn list_sales(
+    State(db): State<Database>,
+    Query(params): Query<OrdersQuery>,
+    claims: Claims,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let status = match params.status.as_deref() {
+        Some(status) => {
+            Some(FulfillmentStatus::parse(&status.to_ascii_uppercase()).ok_or(StatusCode::BAD_REQUEST)?)
+        }
+        None => None,
+    };
+    let orders = sqlx::query_as::<_, Order>(&format!(
+        r#"{} AND pr.user_id = $1 AND ($2::TEXT IS NULL OR p.fulfillment_status = $2)
+        ORDER BY p.fulfillment_status = 'PENDING' DESC, p.created_at"#,
+        ORDER_QUERY
+    ))
+    .bind(&claims.sub)
+    .bind(status.map(FulfillmentStatus::as_str))
+    .fetch_all(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to list orders to ship for {}: {}", claims.sub, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    Ok(Json(json!({ "success": true, "data": orders })))
+}
+
+// Orders only move forward: pending, shipped, delivered. Tracking can be corrected while shipped.
+async fn update_fulfillment(
+    State(db): State<Database>,
+    Path(id): Path<Uuid>,
+    claims: Claims,
+    Json(payload): Json<UpdateFulfillmentRequest>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let order = sqlx::query_as::<_, Order>(&format!("{} AND p.id = $1", ORDER_QUERY))
+        .bind(id)
+        .fetch_optional(&db.pool)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+    if order.creator_id != claims.sub {
+        return Err(StatusCode::FORBIDDEN);
+    }
+    let current = order
+        .fulfillment_status
+        .as_deref()
+        .and_then(FulfillmentStatus::parse)
+        .unwrap_or(FulfillmentStatus::Pending);
+    let rank = |status: FulfillmentStatus| status as u8;
+    if rank(payload.status) < rank(current) {
+        return Err(StatusCode::CONFLICT);
+    }
+
+    let trimmed = |value: &Option<String>| {
+        value.as_deref().map(str::trim).filter(|value| !value.is_empty()).map(str::to_string)
+    };
+    let updated = sqlx::query_as::<_, (String, Option<String>, Option<String>, Option<String>)>(
+        r#"
+        UPDATE purchases
+        SET fulfillment_status = $2,
+            tracking_carrier = COALESCE($3, tracking_carrier),
+            tracking_number = COALESCE($4, tracking_number),
+            tracking_url = COALESCE($5, tracking_url),
+            shipped_at = CASE WHEN $2 <> 'PENDING' THEN COALESCE(shipped_at, NOW()) ELSE shipped_at END,
+            delivered_at = CASE WHEN $2 = 'DELIVERED' THEN COALESCE(delivered_at, NOW()) ELSE delivered_at END
+        WHERE id = $1
+        RETURNING fulfillment_status, tracking_carrier, tracking_number, tracking_url
+        "#,
+    )
+    .bind(id)
+    .bind(payload.status.as_str())
+    .bind(trimmed(&payload.tracking_carrier))
+    .bind(trimmed(&payload.tracking_number))
+    .bind(trimmed(&payload.tracking_url))
+    .fetch_one(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to update fulfillment of order {}: {}", id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    let (status, tracking_carrier, tracking_number, tracking_url) = updated;
+
+    let message = match payload.status {
+        _ if payload.status == current => None,
+        FulfillmentStatus::Shipped => Some(format!("Your order of {} has shipped", order.product_name)),
+        FulfillmentStatus::Delivered => Some(format!("Your order of {} was delivered", order.product_name)),
+        FulfillmentStatus::Pending => None,
+    };
+    if let Some(message) = message {
+        notify(
+            &db,
+            &order.user_id,
+            NotificationKind::Order,
+            json!({
+                "message": message,
+                "link": format!("/orders/{}", order.id),
+                "purchaseId": order.id,
+                "productId": order.product_id,
+                "trackingNumber": tracking_number,
+                "trackingUrl": tracking_url,
+            }),
+        )
+        .await;
+    }
+
+    Ok(Json(json!({
+        "success": true,
+        "data": {
+            "id": id,
+            "fulfillmentStatus": status,
+            "trackingCarrier": tracking_carrier,
+            "trackingNumber": tracking_number,
+            "trackingUrl": tracking_url,
+        }
+    })))
+}
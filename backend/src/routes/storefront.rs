@@ -0,0 +1,462 @@
+use std::collections::HashMap;
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::Json,
+    routing::{get, post, put},
+    Router,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use uuid::Uuid;
+
+use crate::{
+    auth::Claims, database::Database, models::Product, routes::discounts::apply_sale_prices,
+};
+
+/// Most sections one storefront may have.
+const MAX_SECTIONS: i64 = 20;
+
+/// How a creator's storefront looks: the banner across the top and the product featured under it.
+#[derive(Debug, Clone, Default, Serialize, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct StorefrontSettings {
+    pub banner_image_url: Option<String>,
+    pub banner_title: Option<String>,
+    pub banner_subtitle: Option<String>,
+    pub banner_link_url: Option<String>,
+    pub featured_product_id: Option<Uuid>,
+    pub accent_color: Option<String>,
+}
+
+/// A titled group of hand-picked products, shown in the creator's order.
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct StorefrontSection {
+    pub id: Uuid,
+    pub title: String,
+    pub description: Option<String>,
+    pub product_ids: Vec<Uuid>,
+    pub position: i32,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SettingsRequest {
+    banner_image_url: Option<String>,
+    banner_title: Option<String>,
+    banner_subtitle: Option<String>,
+    banner_link_url: Option<String>,
+    featured_product_id: Option<Uuid>,
+    accent_color: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SectionRequest {
+    title: String,
+    description: Option<String>,
+    #[serde(default)]
+    product_ids: Vec<Uuid>,
+    position: Option<i32>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ProductOrderRequest {
+    product_ids: Vec<Uuid>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SectionOrderRequest {
+    section_ids: Vec<Uuid>,
+}
+
+pub fn storefront_routes() -> Router<Database> {
+    Router::new()
+        .route("/", get(get_my_storefront).put(update_settings))
+        .route("/product-order", put(update_product_order))
+        .route("/sections", post(create_section))
+        .route("/sections/order", put(update_section_order))
+        .route("/sections/:id", put(update_section).delete(delete_section))
+}
+
+/// Blank strings clear a field.
+fn cleaned(value: &Option<String>) -> Option<&str> {
+    value.as_deref().map(str::trim).filter(|value| !value.is_empty())
+}
+
+async fn load_settings(db: &Database, creator_id: &str) -> Result<StorefrontSettings, StatusCode> {
+    let settings = sqlx::query_as::<_, StorefrontSettings>(
+        r#"
+        SELECT banner_image_url, banner_title, banner_subtitle, banner_link_url,
+               featured_product_id, accent_color
+        FROM storefront_settings WHERE creator_id = $1
+        "#,
+    )
+    .bind(creator_id)
+    .fetch_optional(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to load storefront of {}: {}", creator_id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    Ok(settings.unwrap_or_default())
+}
+
+async fn load_sections(db: &Database, creator_id: &str) -> Result<Vec<StorefrontSection>, StatusCode> {
+    sqlx::query_as::<_, StorefrontSection>(
+        r#"
+        SELECT id, title, description, product_ids, position, created_at, updated_at
+        FROM storefront_sections WHERE creator_id = $1
+        ORDER BY position, created_at
+        "#,
+    )
+    .bind(creator_id)
+    .fetch_all(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to load storefront sections of {}: {}", creator_id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })
+}
+
+/// Fails with 400 unless every product is the creator's. Duplicates are dropped, keeping the
+/// first place a product was given.
+async fn own_products(db: &Database, creator_id: &str, product_ids: &[Uuid]) -> Result<Vec<Uuid>, StatusCode> {
+    let mut unique = Vec::with_capacity(product_ids.len());
+    for id in product_ids {
+        if !unique.contains(id) {
+            unique.push(*id);
+        }
+    }
+    if unique.is_empty() {
+        return Ok(unique);
+    }
+    let owned = sqlx::query_scalar::<_, i64>(
+        "SELECT COUNT(*) FROM products WHERE id = ANY($1) AND user_id = $2",
+    )
+    .bind(&unique)
+    .bind(creator_id)
+    .fetch_one(&db.pool)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    if owned != unique.len() as i64 {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    Ok(unique)
+}
+
+/// The creator's products in storefront order: placed products first, then the rest, newest
+/// first.
+async fn storefront_products(db: &Database, creator_id: &str) -> Result<Vec<Product>, StatusCode> {
+    let mut products = sqlx::query_as::<_, Product>(
+        r#"
+        SELECT * FROM products WHERE user_id = $1 AND is_active
+        ORDER BY storefront_position NULLS LAST, created_at DESC
+        "#,
+    )
+    .bind(creator_id)
+    .fetch_all(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to load storefront products of {}: {}", creator_id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    apply_sale_prices(db, &mut products).await?;
+    Ok(products)
+}
+
+// Settings and sections as the creator edits them
+async fn get_my_storefront(
+    State(db): State<Database>,
+    claims: Claims,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let settings = load_settings(&db, &claims.sub).await?;
+    let sections = load_sections(&db, &claims.sub).await?;
+
+    Ok(Json(json!({
+        "success": true,
+        "data": {
+            "settings": settings,
+            "sections": sections
+        }
+    })))
+}
+
+async fn update_settings(
+    State(db): State<Database>,
+    claims: Claims,
+    Json(payload): Json<SettingsRequest>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let accent_color = cleaned(&payload.accent_color);
+    let valid_color = accent_color.is_none_or(|color| {
+        color.len() <= 20 && color.chars().all(|c| c.is_ascii_alphanumeric() || c == '#')
+    });
+    if !valid_color || cleaned(&payload.banner_title).is_some_and(|title| title.len() > 200) {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    if let Some(product_id) = payload.featured_product_id {
+        own_products(&db, &claims.sub, &[product_id]).await?;
+    }
+
+    let settings = sqlx::query_as::<_, StorefrontSettings>(
+        r#"
+        INSERT INTO storefront_settings (
+            creator_id, banner_image_url, banner_title, banner_subtitle, banner_link_url,
+            featured_product_id, accent_color
+        )
+        VALUES ($1, $2, $3, $4, $5, $6, $7)
+        ON CONFLICT (creator_id) DO UPDATE
+        SET banner_image_url = EXCLUDED.banner_image_url,
+            banner_title = EXCLUDED.banner_title,
+            banner_subtitle = EXCLUDED.banner_subtitle,
+            banner_link_url = EXCLUDED.banner_link_url,
+            featured_product_id = EXCLUDED.featured_product_id,
+            accent_color = EXCLUDED.accent_color,
+            updated_at = NOW()
+        RETURNING banner_image_url, banner_title, banner_subtitle, banner_link_url,
+                  featured_product_id, accent_color
+        "#,
+    )
+    .bind(&claims.sub)
+    .bind(cleaned(&payload.banner_image_url))
+    .bind(cleaned(&payload.banner_title))
+    .bind(cleaned(&payload.banner_subtitle))
+    .bind(cleaned(&payload.banner_link_url))
+    .bind(payload.featured_product_id)
+    .bind(accent_color)
+    .fetch_one(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to save storefront of {}: {}", claims.sub, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    Ok(Json(json!({ "success": true, "data": settings })))
+}
+
+// Products listed are placed in that order; the rest go back to newest first
+async fn update_product_order(
+    State(db): State<Database>,
+    claims: Claims,
+    Json(payload): Json<ProductOrderRequest>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let product_ids = own_products(&db, &claims.sub, &payload.product_ids).await?;
+
+    sqlx::query(
+        r#"
+        UPDATE products p
+        SET storefront_position = placed.position
+        FROM (
+            SELECT pr.id, ordered.position::INT AS position
+            FROM products pr
+            LEFT JOIN UNNEST($2::UUID[]) WITH ORDINALITY AS ordered(id, position) ON ordered.id = pr.id
+            WHERE pr.user_id = $1
+        ) placed
+        WHERE p.id = placed.id
+        "#,
+    )
+    .bind(&claims.sub)
+    .bind(&product_ids)
+    .execute(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to order storefront of {}: {}", claims.sub, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    Ok(Json(json!({ "success": true, "data": { "productIds": product_ids } })))
+}
+
+async fn create_section(
+    State(db): State<Database>,
+    claims: Claims,
+    Json(payload): Json<SectionRequest>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let title = payload.title.trim();
+    if title.is_empty() || title.len() > 200 {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let product_ids = own_products(&db, &claims.sub, &payload.product_ids).await?;
+    let count = sqlx::query_scalar::<_, i64>(
+        "SELECT COUNT(*) FROM storefront_sections WHERE creator_id = $1",
+    )
+    .bind(&claims.sub)
+    .fetch_one(&db.pool)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    if count >= MAX_SECTIONS {
+        return Err(StatusCode::UNPROCESSABLE_ENTITY);
+    }
+
+    // New sections go last unless placed explicitly
+    let section = sqlx::query_as::<_, StorefrontSection>(
+        r#"
+        INSERT INTO storefront_sections (creator_id, title, description, product_ids, position)
+        VALUES (
+            $1, $2, $3, $4,
+            COALESCE($5, (SELECT COALESCE(MAX(position) + 1, 0) FROM storefront_sections WHERE creator_id = $1))
+        )
+        RETURNING id, title, description, product_ids, position, created_at, updated_at
+        "#,
+    )
+    .bind(&claims.sub)
+    .bind(title)
+    .bind(cleaned(&payload.description))
+    .bind(&product_ids)
+    .bind(payload.position)
+    .fetch_one(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to create storefront section for {}: {}", claims.sub, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    Ok(Json(json!({ "success": true, "data": section })))
+}
+
+async fn update_section(
+    State(db): State<Database>,
+    Path(id): Path<Uuid>,
+    claims: Claims,
+    Json(payload): Json<SectionRequest>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let title = payload.title.trim();
+    if title.is_empty() || title.len() > 200 {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let product_ids = own_products(&db, &claims.sub, &payload.product_ids).await?;
+
+    let section = sqlx::query_as::<_, StorefrontSection>(
+        r#"
+        UPDATE storefront_sections
+        SET title = $3, description = $4, product_ids = $5,
+            position = COALESCE($6, position), updated_at = NOW()
+        WHERE id = $1 AND creator_id = $2
+        RETURNING id, title, description, product_ids, position, created_at, updated_at
+        "#,
+    )
+    .bind(id)
+    .bind(&claims.sub)
+    .bind(title)
+    .bind(cleaned(&payload.description))
+    .bind(&product_ids)
+    .bind(payload.position)
+    .fetch_optional(&db.pool)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    .ok_or(StatusCode::NOT_FOUND)?;
+
+    Ok(Json(json!({ "success": true, "data": section })))
+}
+
+async fn delete_section(
+    State(db): State<Database>,
+    Path(id): Path<Uuid>,
+    claims: Claims,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let deleted = sqlx::query("DELETE FROM storefront_sections WHERE id = $1 AND creator_id = $2")
+        .bind(id)
+        .bind(&claims.sub)
+        .execute(&db.pool)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .rows_affected();
+    if deleted == 0 {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    Ok(Json(json!({ "success": true })))
+}
+
+async fn update_section_order(
+    State(db): State<Database>,
+    claims: Claims,
+    Json(payload): Json<SectionOrderRequest>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    sqlx::query(
+        r#"
+        UPDATE storefront_sections s
+        SET position = ordered.position::INT - 1, updated_at = NOW()
+        FROM UNNEST($2::UUID[]) WITH ORDINALITY AS ordered(id, position)
+        WHERE s.id = ordered.id AND s.creator_id = $1
+        "#,
+    )
+    .bind(&claims.sub)
+    .bind(&payload.section_ids)
+    .execute(&db.pool)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let sections = load_sections(&db, &claims.sub).await?;
+    Ok(Json(json!({ "success": true, "data": sections })))
+}
+
+// The whole storefront in one response: creator, customization, products in storefront order and
+// sections with their products resolved.
+pub(crate) async fn get_storefront(
+    State(db): State<Database>,
+    Path(username): Path<String>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let creator = sqlx::query_as::<_, (String, Option<String>, Option<String>, Option<String>)>(
+        r#"
+        SELECT id, COALESCE(display_name, name), avatar, bio
+        FROM users WHERE username = $1 AND is_creator = true
+        "#,
+    )
+    .bind(&username)
+    .fetch_optional(&db.pool)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    .ok_or(StatusCode::NOT_FOUND)?;
+    let (creator_id, name, avatar, bio) = creator;
+
+    let settings = load_settings(&db, &creator_id).await?;
+    let sections = load_sections(&db, &creator_id).await?;
+    let products = storefront_products(&db, &creator_id).await?;
+
+    let by_id: HashMap<Uuid, &Product> = products.iter().map(|product| (product.id, product)).collect();
+    let featured = settings
+        .featured_product_id
+        .and_then(|id| by_id.get(&id).copied());
+    let collections: Vec<serde_json::Value> = sections
+        .iter()
+        .map(|section| {
+            let items: Vec<&Product> = section
+                .product_ids
+                .iter()
+                .filter_map(|id| by_id.get(id).copied())
+                .collect();
+            json!({
+                "id": section.id,
+                "title": section.title,
+                "description": section.description,
+                "position": section.position,
+                "products": items
+            })
+        })
+        .collect();
+
+    Ok(Json(json!({
+        "success": true,
+        "data": {
+            "creator": {
+                "id": creator_id,
+                "username": username,
+                "name": name,
+                "avatar": avatar,
+                "bio": bio
+            },
+            "settings": settings,
+            "featuredProduct": featured,
+            "collections": collections,
+            "products": products
+        }
+    })))
+}
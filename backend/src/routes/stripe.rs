@@ -0,0 +1,1545 @@
+use std::collections::HashMap;
+
+use axum::{
+    body::Bytes,
+    extract::{Path, Query, State},
+    http::{HeaderMap, StatusCode},
+    response::Json,
+    routing::{delete, get, post},
+    Router,
+};
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sha2::Sha256;
+
+use crate::{
+    auth::Claims,
+    config::{payments_sandbox, payments_sandbox_url, Config, SANDBOX_SECRET_KEY},
+    database::Database,
+    email_service::{app_link, queue_email, EmailTemplate},
+    invoice_pdf::format_amount,
+    notification_service::{notify, NotificationKind},
+    payment_providers::{DonationPayment, PaymentProvider},
+    routes::{
+        campaigns::notify_donation,
+        cart::fulfill_cart,
+        disputes::{apply_dispute_update, DisputeUpdate},
+        events::seat_paid_attendee,
+        fees::{quote_platform_fee, settle_ledger_entries_on, LedgerSource, PlatformFee, ProductType},
+        invoices::{issue_invoice, issue_purchase_invoices, InvoiceSource},
+        inventory::consume_stock,
+        licenses::issue_license_keys,
+        payments::refund_unfulfilled,
+        priority_messages::complete_priority_message,
+        referrals::{record_conversion, record_donation, ReferralEvent},
+        withdrawals::apply_payout_update,
+    },
+};
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Stripe webhooks older than this are rejected to limit replays.
+const WEBHOOK_TOLERANCE_SECONDS: i64 = 300;
+
+/// A creator's Stripe Connect account as last reported by Stripe.
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct ConnectAccount {
+    pub user_id: String,
+    pub stripe_account_id: String,
+    /// `PENDING` until onboarding is submitted, then `IN_REVIEW`, `RESTRICTED` or `ENABLED`.
+    pub status: String,
+    pub charges_enabled: bool,
+    pub payouts_enabled: bool,
+    pub details_submitted: bool,
+    /// Requirements Stripe currently needs before the account can be (re-)enabled.
+    pub requirements_due: Vec<String>,
+    pub disabled_reason: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PaymentRequestQuery {
+    product_id: Option<uuid::Uuid>,
+    campaign: Option<String>,
+}
+
+/// A PaymentIntent for an in-page payment, confirmed by Stripe.js with a card or a wallet.
+pub(crate) struct PaymentIntentSpec<'a> {
+    pub amount_cents: i64,
+    pub currency: &'a str,
+    /// What the buyer pays for; shown on wallet sheets, receipts and card statements.
+    pub description: &'a str,
+    /// Stored under `metadata[...]` next to the merchant and creator.
+    pub metadata: Vec<(&'static str, String)>,
+    pub platform_fee: &'a PlatformFee,
+}
+
+/// The parts of a Stripe event envelope this service reads.
+#[derive(Debug, Deserialize)]
+struct StripeEvent {
+    id: String,
+    #[serde(rename = "type")]
+    event_type: String,
+    data: StripeEventData,
+}
+
+#[derive(Debug, Deserialize)]
+struct StripeEventData {
+    object: serde_json::Value,
+}
+
+#[derive(Debug, Deserialize)]
+struct StripeAccount {
+    id: String,
+    #[serde(default)]
+    charges_enabled: bool,
+    #[serde(default)]
+    payouts_enabled: bool,
+    #[serde(default)]
+    details_submitted: bool,
+    requirements: Option<AccountRequirements>,
+}
+
+#[derive(Debug, Deserialize)]
+struct AccountRequirements {
+    currently_due: Option<Vec<String>>,
+    disabled_reason: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CheckoutSession {
+    id: String,
+    payment_status: Option<String>,
+    payment_intent: Option<Expandable>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Payout {
+    id: String,
+    failure_message: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct PaymentIntent {
+    id: String,
+    /// `requires_action` while the buyer's bank asks for 3D Secure, then `processing`,
+    /// `succeeded`, or `requires_payment_method` when the attempt failed and can be retried.
+    status: String,
+    #[serde(default)]
+    amount: i64,
+    #[serde(default)]
+    metadata: HashMap<String, String>,
+    last_payment_error: Option<PaymentIntentError>,
+    payment_method: Option<Expandable>,
+}
+
+#[derive(Debug, Deserialize)]
+struct PaymentIntentError {
+    message: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct StripeInvoice {
+    id: String,
+    subscription: Option<Expandable>,
+    #[serde(default)]
+    amount_paid: i64,
+    currency: String,
+    description: Option<String>,
+    /// `subscription_create` for a member's first payment, `subscription_cycle` for renewals.
+    billing_reason: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct StripeDispute {
+    id: String,
+    charge: Option<Expandable>,
+    payment_intent: Option<Expandable>,
+    amount: i64,
+    currency: String,
+    reason: Option<String>,
+    status: String,
+    evidence_details: Option<DisputeEvidenceDetails>,
+}
+
+#[derive(Debug, Deserialize)]
+struct DisputeEvidenceDetails {
+    /// Unix time by which the creator's evidence must reach the card issuer.
+    due_by: Option<i64>,
+}
+
+/// A related object Stripe sends either as its id or, when expanded, in full.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum Expandable {
+    Id(String),
+    Object { id: String },
+}
+
+impl Expandable {
+    fn id(&self) -> &str {
+        match self {
+            Self::Id(id) | Self::Object { id } => id,
+        }
+    }
+}
+
+/// Webhook events this service acts on, decoded from the envelope's `data.object`.
+#[derive(Debug)]
+enum WebhookEvent {
+    AccountUpdated(StripeAccount),
+    CheckoutSessionCompleted(CheckoutSession),
+    PaymentIntentSucceeded(PaymentIntent),
+    /// Any state short of success: awaiting authentication, processing, failed or cancelled.
+    PaymentIntentUpdated(PaymentIntent),
+    InvoicePaid(StripeInvoice),
+    PayoutPaid(Payout),
+    PayoutFailed(Payout),
+    Dispute(StripeDispute),
+    Unhandled,
+}
+
+impl WebhookEvent {
+    fn parse(event: &StripeEvent) -> Result<Self, serde_json::Error> {
+        let object = event.data.object.clone();
+        Ok(match event.event_type.as_str() {
+            "account.updated" => Self::AccountUpdated(serde_json::from_value(object)?),
+            "checkout.session.completed" | "checkout.session.async_payment_succeeded" => {
+                Self::CheckoutSessionCompleted(serde_json::from_value(object)?)
+            }
+            "payment_intent.succeeded" => Self::PaymentIntentSucceeded(serde_json::from_value(object)?),
+            "payment_intent.requires_action"
+            | "payment_intent.processing"
+            | "payment_intent.payment_failed"
+            | "payment_intent.canceled" => Self::PaymentIntentUpdated(serde_json::from_value(object)?),
+            "invoice.paid" => Self::InvoicePaid(serde_json::from_value(object)?),
+            "payout.paid" => Self::PayoutPaid(serde_json::from_value(object)?),
+            "payout.failed" | "payout.canceled" => Self::PayoutFailed(serde_json::from_value(object)?),
+            "charge.dispute.created" | "charge.dispute.updated" | "charge.dispute.closed" => {
+                Self::Dispute(serde_json::from_value(object)?)
+            }
+            _ => Self::Unhandled,
+        })
+    }
+}
+
+pub fn stripe_routes() -> Router<Database> {
+    Router::new()
+        .route("/connect/account", post(create_connect_account))
+        .route("/connect/status", get(get_connect_status))
+        .route("/connect/onboarding-link", post(create_onboarding_link))
+        .route("/payment-methods", get(list_payment_methods))
+        .route("/payment-methods/setup-intent", post(create_setup_intent))
+        .route("/payment-methods/:payment_method_id", delete(detach_payment_method))
+        .route("/billing-portal", post(create_billing_portal_session))
+        .route("/payment-request-config", get(get_payment_request_config))
+        .route("/webhook", post(stripe_webhook))
+}
+
+// Creating twice returns the existing account so retries from the onboarding screen are safe
+async fn create_connect_account(
+    State(db): State<Database>,
+    claims: Claims,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    if let Some(account) = load_connect_account(&db, &claims.sub).await? {
+        let url = account_link_url(&account.stripe_account_id).await?;
+        return Ok(Json(json!({
+            "success": true,
+            "data": { "account": account, "onboardingUrl": url }
+        })));
+    }
+
+    let user = sqlx::query_as::<_, (Option<String>, Option<bool>)>(
+        "SELECT email, is_creator FROM users WHERE id = $1",
+    )
+    .bind(&claims.sub)
+    .fetch_optional(&db.pool)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    .ok_or(StatusCode::NOT_FOUND)?;
+    if user.1 != Some(true) {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    let mut params = vec![
+        ("type", "express".to_string()),
+        ("metadata[user_id]", claims.sub.clone()),
+        ("capabilities[card_payments][requested]", "true".to_string()),
+        ("capabilities[transfers][requested]", "true".to_string()),
+    ];
+    if let Some(email) = user.0.filter(|email| !email.trim().is_empty()) {
+        params.push(("email", email));
+    }
+
+    let response = reqwest::Client::new()
+        .post(stripe_url("/v1/accounts"))
+        .header("Authorization", format!("Bearer {}", stripe_secret()?))
+        .form(&params)
+        .send()
+        .await;
+    let stripe_account: StripeAccount =
+        serde_json::from_value(stripe_json(response, "create Connect account").await?)
+            .map_err(|_| StatusCode::BAD_GATEWAY)?;
+
+    // A concurrent request may have created one first; keep whichever was stored.
+    sqlx::query(
+        r#"
+        INSERT INTO creator_connect_accounts (user_id, stripe_account_id)
+        VALUES ($1, $2)
+        ON CONFLICT (user_id) DO NOTHING
+        "#,
+    )
+    .bind(&claims.sub)
+    .bind(&stripe_account.id)
+    .execute(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to store Connect account for {}: {}", claims.sub, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    let account = apply_account_update(&db, &stripe_account)
+        .await?
+        .ok_or(StatusCode::INTERNAL_SERVER_ERROR)?;
+    let url = account_link_url(&account.stripe_account_id).await?;
+
+    Ok(Json(json!({
+        "success": true,
+        "data": { "account": account, "onboardingUrl": url }
+    })))
+}
+
+// Webhooks keep the stored state current; this also pulls from Stripe in case one was missed
+async fn get_connect_status(
+    State(db): State<Database>,
+    claims: Claims,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let Some(account) = load_connect_account(&db, &claims.sub).await? else {
+        return Ok(Json(json!({
+            "success": true,
+            "data": { "status": "NOT_CONNECTED", "account": null }
+        })));
+    };
+
+    let response = reqwest::Client::new()
+        .get(stripe_url(&format!("/v1/accounts/{}", account.stripe_account_id)))
+        .header("Authorization", format!("Bearer {}", stripe_secret()?))
+        .send()
+        .await;
+    let stripe_account = stripe_json(response, "fetch Connect account")
+        .await
+        .ok()
+        .and_then(|stripe_account| serde_json::from_value::<StripeAccount>(stripe_account).ok());
+    let account = match stripe_account {
+        Some(stripe_account) => apply_account_update(&db, &stripe_account).await?.unwrap_or(account),
+        None => account,
+    };
+
+    Ok(Json(json!({
+        "success": true,
+        "data": { "status": account.status, "account": account }
+    })))
+}
+
+// Onboarding links are single-use and expire within minutes, so one is minted per request
+async fn create_onboarding_link(
+    State(db): State<Database>,
+    claims: Claims,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let account = load_connect_account(&db, &claims.sub)
+        .await?
+        .ok_or(StatusCode::NOT_FOUND)?;
+    let url = account_link_url(&account.stripe_account_id).await?;
+
+    Ok(Json(json!({
+        "success": true,
+        "data": { "url": url, "status": account.status }
+    })))
+}
+
+// Cards are saved on the client with Stripe.js using the returned secret, never through us
+async fn create_setup_intent(
+    State(db): State<Database>,
+    claims: Claims,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let customer_id = ensure_stripe_customer(&db, &claims.sub).await?;
+
+    let response = reqwest::Client::new()
+        .post(stripe_url("/v1/setup_intents"))
+        .header("Authorization", format!("Bearer {}", stripe_secret()?))
+        .form(&[
+            ("customer", customer_id),
+            ("usage", "off_session".to_string()),
+            ("automatic_payment_methods[enabled]", "true".to_string()),
+            ("metadata[user_id]", claims.sub.clone()),
+        ])
+        .send()
+        .await;
+    let setup_intent = stripe_json(response, "create setup intent").await?;
+
+    Ok(Json(json!({
+        "success": true,
+        "data": {
+            "id": setup_intent.get("id"),
+            "clientSecret": setup_intent.get("client_secret"),
+        }
+    })))
+}
+
+async fn list_payment_methods(
+    State(db): State<Database>,
+    claims: Claims,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let Some(customer_id) = load_stripe_customer(&db, &claims.sub).await? else {
+        return Ok(Json(json!({ "success": true, "data": [] })));
+    };
+    let secret = stripe_secret()?;
+    let client = reqwest::Client::new();
+
+    let customer = client
+        .get(stripe_url(&format!("/v1/customers/{}", customer_id)))
+        .header("Authorization", format!("Bearer {}", secret))
+        .send()
+        .await;
+    let customer = stripe_json(customer, "fetch customer").await?;
+    let default_method = customer
+        .pointer("/invoice_settings/default_payment_method")
+        .and_then(|v| v.as_str());
+
+    let response = client
+        .get(stripe_url(&format!("/v1/customers/{}/payment_methods", customer_id)))
+        .header("Authorization", format!("Bearer {}", secret))
+        .query(&[("limit", "100")])
+        .send()
+        .await;
+    let methods = stripe_json(response, "list payment methods").await?;
+
+    let methods: Vec<serde_json::Value> = methods
+        .get("data")
+        .and_then(|v| v.as_array())
+        .map(|methods| {
+            methods
+                .iter()
+                .map(|method| {
+                    let id = method.get("id").and_then(|v| v.as_str());
+                    json!({
+                        "id": id,
+                        "type": method.get("type"),
+                        "brand": method.pointer("/card/brand"),
+                        "last4": method.pointer("/card/last4"),
+                        "expMonth": method.pointer("/card/exp_month"),
+                        "expYear": method.pointer("/card/exp_year"),
+                        "wallet": method.pointer("/card/wallet/type"),
+                        "isDefault": id.is_some() && id == default_method,
+                    })
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    Ok(Json(json!({
+        "success": true,
+        "data": methods
+    })))
+}
+
+async fn detach_payment_method(
+    State(db): State<Database>,
+    Path(payment_method_id): Path<String>,
+    claims: Claims,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let customer_id = load_stripe_customer(&db, &claims.sub)
+        .await?
+        .ok_or(StatusCode::NOT_FOUND)?;
+    let secret = stripe_secret()?;
+    let client = reqwest::Client::new();
+
+    // Only methods attached to the caller's own customer can be detached
+    let response = client
+        .get(stripe_url(&format!("/v1/payment_methods/{}", payment_method_id)))
+        .header("Authorization", format!("Bearer {}", secret))
+        .send()
+        .await;
+    let method = stripe_json(response, "fetch payment method")
+        .await
+        .map_err(|_| StatusCode::NOT_FOUND)?;
+    if method.get("customer").and_then(|v| v.as_str()) != Some(customer_id.as_str()) {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    let response = client
+        .post(stripe_url(&format!("/v1/payment_methods/{}/detach", payment_method_id)))
+        .header("Authorization", format!("Bearer {}", secret))
+        .send()
+        .await;
+    stripe_json(response, "detach payment method").await?;
+
+    Ok(Json(json!({
+        "success": true,
+        "message": "Payment method removed"
+    })))
+}
+
+async fn create_billing_portal_session(
+    State(db): State<Database>,
+    claims: Claims,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let customer_id = ensure_stripe_customer(&db, &claims.sub).await?;
+    let frontend_url = Config::from_env()
+        .map(|config| config.frontend_url)
+        .unwrap_or_else(|_| "http://localhost:3000".to_string());
+
+    let response = reqwest::Client::new()
+        .post(stripe_url("/v1/billing_portal/sessions"))
+        .header("Authorization", format!("Bearer {}", stripe_secret()?))
+        .form(&[
+            ("customer", customer_id),
+            ("return_url", format!("{}/settings/billing", frontend_url.trim_end_matches('/'))),
+        ])
+        .send()
+        .await;
+    let session = stripe_json(response, "create billing portal session").await?;
+
+    Ok(Json(json!({
+        "success": true,
+        "data": { "url": session.get("url") }
+    })))
+}
+
+// Public: everything returned is shown to buyers anyway, and wallet buttons render before sign-in
+async fn get_payment_request_config(
+    State(db): State<Database>,
+    Query(params): Query<PaymentRequestQuery>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let config = Config::from_env().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    if config.stripe_publishable_key.trim().is_empty() {
+        return Err(StatusCode::SERVICE_UNAVAILABLE);
+    }
+
+    // Donations leave the amount to the donor, so campaigns have no fixed total
+    let (label, amount, currency) = match (params.product_id, params.campaign) {
+        (Some(product_id), _) => {
+            let (name, price, currency) = sqlx::query_as::<_, (String, f64, String)>(
+                "SELECT name, price, currency FROM products WHERE id = $1",
+            )
+            .bind(product_id)
+            .fetch_optional(&db.pool)
+            .await
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+            .ok_or(StatusCode::NOT_FOUND)?;
+            (name, Some((price * 100.0).round() as i64), currency)
+        }
+        (None, Some(slug)) => {
+            let title = sqlx::query_scalar::<_, String>("SELECT title FROM campaigns WHERE slug = $1")
+                .bind(&slug)
+                .fetch_optional(&db.pool)
+                .await
+                .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+                .ok_or(StatusCode::NOT_FOUND)?;
+            (title, None, "USD".to_string())
+        }
+        (None, None) => (merchant_name(), None, "USD".to_string()),
+    };
+
+    Ok(Json(json!({
+        "success": true,
+        "data": {
+            "publishableKey": config.stripe_publishable_key,
+            "country": std::env::var("STRIPE_ACCOUNT_COUNTRY").unwrap_or_else(|_| "US".to_string()),
+            "currency": currency.to_lowercase(),
+            "merchantName": merchant_name(),
+            "total": { "label": label, "amount": amount },
+            "requestPayerName": true,
+            "requestPayerEmail": true,
+            "wallets": ["applePay", "googlePay", "link"],
+        }
+    })))
+}
+
+/// Name buyers see on wallet sheets and in payment metadata.
+fn merchant_name() -> String {
+    std::env::var("STRIPE_MERCHANT_NAME")
+        .ok()
+        .filter(|name| !name.trim().is_empty())
+        .unwrap_or_else(|| "Fundify".to_string())
+}
+
+/// Card statements allow 22 characters of letters, digits and spaces after the account prefix.
+fn statement_descriptor_suffix(description: &str) -> Option<String> {
+    let suffix: String = description
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric() || *c == ' ')
+        .collect::<String>()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_ascii_uppercase()
+        .chars()
+        .take(22)
+        .collect();
+    let suffix = suffix.trim().to_string();
+    suffix.chars().any(|c| c.is_ascii_alphabetic()).then_some(suffix)
+}
+
+/// Stripe: in-page payments through PaymentIntents, confirmed by the payment_intent.succeeded
+/// webhook.
+pub(crate) struct Stripe;
+
+#[axum::async_trait]
+impl PaymentProvider for Stripe {
+    /// Create a PaymentIntent for the donation form or a wallet button and store the donation as
+    /// PENDING, with its platform fee in the ledger.
+    async fn create_donation(
+        &self,
+        db: &Database,
+        donation: DonationPayment<'_>,
+    ) -> Result<serde_json::Value, StatusCode> {
+        create_intent_donation(db, donation).await
+    }
+}
+
+async fn create_intent_donation(
+    db: &Database,
+    donation: DonationPayment<'_>,
+) -> Result<serde_json::Value, StatusCode> {
+    let platform_fee =
+        quote_platform_fee(db, ProductType::Donation, donation.creator_id, donation.amount_cents).await?;
+    let payment_intent = create_payment_intent(PaymentIntentSpec {
+        amount_cents: donation.amount_cents,
+        currency: donation.currency,
+        description: donation.title,
+        metadata: vec![
+            ("campaign_id", donation.campaign_id.to_string()),
+            ("user_id", donation.donor_id.to_string()),
+        ],
+        platform_fee: &platform_fee,
+    })
+    .await?;
+    let payment_intent_id = payment_intent
+        .get("id")
+        .and_then(|v| v.as_str())
+        .ok_or(StatusCode::BAD_GATEWAY)?;
+
+    let donation_id = sqlx::query_scalar::<_, uuid::Uuid>(
+        r#"
+        INSERT INTO donations (
+            campaign_id, donor_id, amount, currency, message, is_anonymous, stripe_payment_intent_id,
+            share_link_id
+        )
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+        RETURNING id
+        "#,
+    )
+    .bind(donation.campaign_id)
+    .bind(donation.donor_id)
+    .bind(donation.amount_cents as f64 / 100.0)
+    .bind(donation.currency)
+    .bind(&donation.message)
+    .bind(donation.is_anonymous)
+    .bind(payment_intent_id)
+    .bind(donation.share_link_id)
+    .fetch_one(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to store donation to campaign {}: {}", donation.campaign_id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    let payment_id = platform_fee
+        .record(
+            db,
+            LedgerSource {
+                source_type: "DONATION",
+                source_id: donation_id.to_string(),
+                payer_id: donation.donor_id,
+                currency: donation.currency,
+                stripe_checkout_session_id: None,
+                stripe_payment_intent_id: Some(payment_intent_id),
+            },
+        )
+        .await?;
+
+    Ok(json!({
+        "donationId": donation_id,
+        "paymentId": payment_id,
+        "campaignId": donation.campaign_id,
+        "amount": donation.amount_cents as f64 / 100.0,
+        "currency": donation.currency,
+        "status": "PENDING",
+        "clientSecret": payment_intent.get("client_secret"),
+        "stripePaymentIntentId": payment_intent_id
+    }))
+}
+
+/// Create a PaymentIntent with automatic payment methods, so Apple Pay, Google Pay and Link are
+/// offered wherever the buyer's device supports them.
+pub(crate) async fn create_payment_intent(spec: PaymentIntentSpec<'_>) -> Result<serde_json::Value, StatusCode> {
+    let mut params = vec![
+        ("amount".to_string(), spec.amount_cents.to_string()),
+        ("currency".to_string(), spec.currency.to_lowercase()),
+        ("description".to_string(), spec.description.to_string()),
+        ("automatic_payment_methods[enabled]".to_string(), "true".to_string()),
+        ("metadata[merchant]".to_string(), merchant_name()),
+        ("metadata[creator_id]".to_string(), spec.platform_fee.creator_id.clone()),
+        ("metadata[product_type]".to_string(), spec.platform_fee.product_type.as_str().to_string()),
+    ];
+    if let Some(suffix) = statement_descriptor_suffix(spec.description) {
+        params.push(("statement_descriptor_suffix".to_string(), suffix));
+    }
+    params.extend(
+        spec.metadata
+            .into_iter()
+            .map(|(key, value)| (format!("metadata[{}]", key), value)),
+    );
+    params.extend(spec.platform_fee.payment_intent_params());
+
+    let response = reqwest::Client::new()
+        .post(stripe_url("/v1/payment_intents"))
+        .header("Authorization", format!("Bearer {}", stripe_secret()?))
+        .form(&params)
+        .send()
+        .await;
+    stripe_json(response, "create payment intent").await
+}
+
+pub(crate) async fn fetch_payment_intent(payment_intent_id: &str) -> Result<serde_json::Value, StatusCode> {
+    let response = reqwest::Client::new()
+        .get(stripe_url(&format!("/v1/payment_intents/{}", payment_intent_id)))
+        .header("Authorization", format!("Bearer {}", stripe_secret()?))
+        .send()
+        .await;
+    stripe_json(response, "load payment intent").await
+}
+
+async fn load_stripe_customer(db: &Database, user_id: &str) -> Result<Option<String>, StatusCode> {
+    sqlx::query_scalar::<_, Option<String>>("SELECT stripe_customer_id FROM users WHERE id = $1")
+        .bind(user_id)
+        .fetch_optional(&db.pool)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to load Stripe customer of {}: {}", user_id, e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })
+        .map(Option::flatten)
+}
+
+/// The user's Stripe customer id, creating the customer the first time it is needed.
+pub(crate) async fn ensure_stripe_customer(db: &Database, user_id: &str) -> Result<String, StatusCode> {
+    if let Some(customer_id) = load_stripe_customer(db, user_id).await? {
+        return Ok(customer_id);
+    }
+
+    let user = sqlx::query_as::<_, (Option<String>, Option<String>, String)>(
+        "SELECT email, display_name, username FROM users WHERE id = $1",
+    )
+    .bind(user_id)
+    .fetch_optional(&db.pool)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    .ok_or(StatusCode::NOT_FOUND)?;
+
+    let mut params = vec![
+        ("name", user.1.unwrap_or(user.2)),
+        ("metadata[user_id]", user_id.to_string()),
+    ];
+    if let Some(email) = user.0 {
+        params.push(("email", email));
+    }
+    // The idempotency key keeps concurrent first requests from creating two customers
+    let response = reqwest::Client::new()
+        .post(stripe_url("/v1/customers"))
+        .header("Authorization", format!("Bearer {}", stripe_secret()?))
+        .header("Idempotency-Key", format!("customer-{}", user_id))
+        .form(&params)
+        .send()
+        .await;
+    let customer = stripe_json(response, "create customer").await?;
+    let customer_id = customer
+        .get("id")
+        .and_then(|v| v.as_str())
+        .ok_or(StatusCode::BAD_GATEWAY)?;
+
+    sqlx::query_scalar::<_, String>(
+        r#"
+        UPDATE users
+        SET stripe_customer_id = COALESCE(stripe_customer_id, $2), updated_at = NOW()
+        WHERE id = $1
+        RETURNING stripe_customer_id
+        "#,
+    )
+    .bind(user_id)
+    .bind(customer_id)
+    .fetch_one(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to store Stripe customer of {}: {}", user_id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })
+}
+
+/// Stripe webhook. Every event id is recorded in `stripe_events`, so retries of an event that
+/// was already processed are acknowledged without running it again. When a handler fails, only
+/// the event's `stripe_events` row is rolled back and Stripe retries the event. Handlers write
+/// through the pool rather than this transaction, so whatever they did before failing stays
+/// committed; each handler must therefore be safe to run again for the same event. Completing a
+/// payment's records and settling its ledger entries share one transaction, and refunds resume
+/// from their own PENDING row, so a retry never finds half of a step already done.
+async fn stripe_webhook(
+    State(db): State<Database>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let secret = Config::from_env()
+        .map(|config| config.stripe_webhook_secret)
+        .unwrap_or_default();
+    if secret.trim().is_empty() {
+        tracing::error!("STRIPE_WEBHOOK_SECRET is not configured");
+        return Err(StatusCode::SERVICE_UNAVAILABLE);
+    }
+
+    let signature = headers
+        .get("stripe-signature")
+        .and_then(|value| value.to_str().ok())
+        .ok_or(StatusCode::UNAUTHORIZED)?;
+    if !verify_stripe_signature(&secret, signature, &body) {
+        return Err(StatusCode::UNAUTHORIZED);
+    }
+
+    let event: StripeEvent = serde_json::from_slice(&body).map_err(|_| StatusCode::BAD_REQUEST)?;
+
+    let mut tx = db.pool.begin().await.map_err(|e| {
+        tracing::error!("Failed to start Stripe event transaction: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    // The row lock makes a concurrent delivery of the same event wait for this one to finish.
+    sqlx::query(
+        "INSERT INTO stripe_events (id, event_type) VALUES ($1, $2) ON CONFLICT (id) DO NOTHING",
+    )
+    .bind(&event.id)
+    .bind(&event.event_type)
+    .execute(&mut tx)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to record Stripe event {}: {}", event.id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    let processed_at = sqlx::query_scalar::<_, Option<DateTime<Utc>>>(
+        "SELECT processed_at FROM stripe_events WHERE id = $1 FOR UPDATE",
+    )
+    .bind(&event.id)
+    .fetch_one(&mut tx)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    if processed_at.is_some() {
+        tx.commit().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        return Ok(Json(json!({ "success": true, "duplicate": true })));
+    }
+
+    let webhook_event = WebhookEvent::parse(&event).map_err(|e| {
+        tracing::error!("Malformed Stripe {} event {}: {}", event.event_type, event.id, e);
+        StatusCode::BAD_REQUEST
+    })?;
+    let handled = dispatch_event(&db, webhook_event).await?;
+
+    sqlx::query("UPDATE stripe_events SET processed_at = NOW(), handled = $2 WHERE id = $1")
+        .bind(&event.id)
+        .bind(handled)
+        .execute(&mut tx)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    tx.commit().await.map_err(|e| {
+        tracing::error!("Failed to commit Stripe event {}: {}", event.id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    Ok(Json(json!({
+        "success": true,
+        "data": { "eventId": event.id, "handled": handled }
+    })))
+}
+
+/// Run the handler for an event; returns whether anything handled it.
+async fn dispatch_event(db: &Database, event: WebhookEvent) -> Result<bool, StatusCode> {
+    match event {
+        WebhookEvent::AccountUpdated(account) => {
+            apply_account_update(db, &account).await?;
+            Ok(true)
+        }
+        WebhookEvent::CheckoutSessionCompleted(session) => {
+            complete_checkout_session(db, &session).await?;
+            Ok(true)
+        }
+        WebhookEvent::PaymentIntentSucceeded(payment_intent) => {
+            complete_payment_intent(db, &payment_intent).await?;
+            Ok(true)
+        }
+        WebhookEvent::PaymentIntentUpdated(payment_intent) => {
+            let error = payment_intent
+                .last_payment_error
+                .as_ref()
+                .and_then(|error| error.message.as_deref());
+            record_payment_intent_status(db, &payment_intent.id, &payment_intent.status, error)
+                .await?;
+            notify_payment_attention(db, &payment_intent, error).await;
+            Ok(true)
+        }
+        WebhookEvent::InvoicePaid(invoice) => invoice_subscription_payment(db, &invoice).await,
+        WebhookEvent::PayoutPaid(payout) => {
+            apply_payout_update(db, &payout.id, true, None).await?;
+            Ok(true)
+        }
+        WebhookEvent::PayoutFailed(payout) => {
+            apply_payout_update(db, &payout.id, false, payout.failure_message.as_deref()).await?;
+            Ok(true)
+        }
+        WebhookEvent::Dispute(dispute) => {
+            apply_dispute_update(
+                db,
+                &DisputeUpdate {
+                    stripe_dispute_id: &dispute.id,
+                    stripe_charge_id: dispute.charge.as_ref().map(Expandable::id),
+                    stripe_payment_intent_id: dispute.payment_intent.as_ref().map(Expandable::id),
+                    amount_cents: dispute.amount,
+                    currency: &dispute.currency,
+                    reason: dispute.reason.as_deref(),
+                    stripe_status: &dispute.status,
+                    evidence_due_by: dispute
+                        .evidence_details
+                        .and_then(|details| details.due_by)
+                        .and_then(|due_by| DateTime::from_timestamp(due_by, 0)),
+                },
+            )
+            .await?;
+            Ok(true)
+        }
+        WebhookEvent::Unhandled => Ok(false),
+    }
+}
+
+/// Fulfil what an in-page PaymentIntent paid for: donations count toward their campaign,
+/// product purchases complete and ticket buyers are seated. This runs only on the final
+/// `succeeded` event, never on the buyer's word that the payment went through.
+async fn complete_payment_intent(db: &Database, payment_intent: &PaymentIntent) -> Result<(), StatusCode> {
+    let payment_intent_id = payment_intent.id.as_str();
+    let db_error = |e: sqlx::Error| {
+        tracing::error!("Failed to complete payment intent {}: {}", payment_intent_id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    };
+    // Completing the records and settling their ledger entries commit together: a failure rolls
+    // all of it back, so the retried event still finds the donations to notify about.
+    let mut tx = db.pool.begin().await.map_err(db_error)?;
+    let completed_donations = sqlx::query_scalar::<_, uuid::Uuid>(
+        r#"
+        WITH completed AS (
+            UPDATE donations
+            SET status = 'COMPLETED', payment_intent_status = 'succeeded', payment_error = NULL,
+                updated_at = NOW()
+            WHERE stripe_payment_intent_id = $1 AND status <> 'COMPLETED'
+            RETURNING id, campaign_id, amount
+        )
+        UPDATE campaigns c
+        SET current_amount = COALESCE(c.current_amount, 0) + completed.amount, updated_at = NOW()
+        FROM completed
+        WHERE c.id = completed.campaign_id
+        RETURNING completed.id
+        "#,
+    )
+    .bind(payment_intent_id)
+    .fetch_all(&mut tx)
+    .await
+    .map_err(db_error)?;
+    sqlx::query(
+        r#"
+        UPDATE purchases
+        SET status = 'COMPLETED', payment_intent_status = 'succeeded', payment_error = NULL
+        WHERE stripe_payment_intent_id = $1 AND status <> 'COMPLETED'
+        "#,
+    )
+    .bind(payment_intent_id)
+    .execute(&mut tx)
+    .await
+    .map_err(db_error)?;
+    settle_ledger_entries_on(&mut tx, payment_intent_id, None).await?;
+    tx.commit().await.map_err(db_error)?;
+    record_card_fingerprint(db, payment_intent).await;
+    for donation_id in completed_donations {
+        notify_donation(db, donation_id).await;
+        record_donation(db, donation_id).await;
+    }
+
+    if payment_intent.metadata.contains_key("event_id") {
+        let seated = seat_paid_attendee(
+            db,
+            payment_intent_id,
+            &payment_intent.metadata,
+            payment_intent.amount,
+        )
+        .await;
+        match seated {
+            // Left for Stripe to retry the event
+            Err(StatusCode::INTERNAL_SERVER_ERROR) => return Err(StatusCode::INTERNAL_SERVER_ERROR),
+            Err(status) => refund_unseated_ticket(db, payment_intent_id, status).await?,
+            Ok(()) => {}
+        }
+    }
+    if payment_intent.metadata.contains_key("priority_message_id") {
+        complete_priority_message(db, payment_intent_id).await?;
+    }
+    // Before invoicing, so a purchase refunded as sold out gets neither an invoice nor a key
+    consume_stock(db, payment_intent_id).await?;
+    issue_purchase_invoices(db, payment_intent_id).await;
+    issue_license_keys(db, payment_intent_id).await;
+    Ok(())
+}
+
+// The event sold out, was closed to the buyer or went away while they were authenticating the
+// payment, so the ticket cannot be honoured and the money goes back.
+async fn refund_unseated_ticket(
+    db: &Database,
+    payment_intent_id: &str,
+    status: StatusCode,
+) -> Result<(), StatusCode> {
+    let ledger_entry_id = sqlx::query_scalar::<_, uuid::Uuid>(
+        r#"
+        SELECT id FROM ledger_entries
+        WHERE stripe_payment_intent_id = $1 AND source_type = 'EVENT_TICKET'
+        "#,
+    )
+    .bind(payment_intent_id)
+    .fetch_optional(&db.pool)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let Some(ledger_entry_id) = ledger_entry_id else {
+        tracing::error!(
+            "Ticket payment {} could not be seated ({}) and has no ledger entry",
+            payment_intent_id,
+            status
+        );
+        return Ok(());
+    };
+
+    let reason = match status {
+        StatusCode::CONFLICT => "The event sold out before payment completed",
+        _ => "The ticket could not be issued",
+    };
+    refund_unfulfilled(db, ledger_entry_id, reason).await
+}
+
+/// Keep the latest PaymentIntent status on the donations, purchases and priority messages it pays
+/// for, so buyers polling after a 3D Secure challenge see where their payment stands. A cancelled
+/// intent will never be paid, so its records are closed; anything else waits for `succeeded`.
+pub(crate) async fn record_payment_intent_status(
+    db: &Database,
+    payment_intent_id: &str,
+    status: &str,
+    error: Option<&str>,
+) -> Result<(), StatusCode> {
+    for statement in [
+        r#"
+        UPDATE donations
+        SET payment_intent_status = $2, payment_error = $3,
+            status = CASE WHEN $2 = 'canceled' THEN 'CANCELLED' ELSE status END,
+            updated_at = NOW()
+        WHERE stripe_payment_intent_id = $1 AND status = 'PENDING'
+        "#,
+        r#"
+        UPDATE purchases
+        SET payment_intent_status = $2, payment_error = $3,
+            status = CASE WHEN $2 = 'canceled' THEN 'CANCELLED' ELSE status END
+        WHERE stripe_payment_intent_id = $1 AND status = 'PENDING'
+        "#,
+        r#"
+        UPDATE priority_messages
+        SET payment_intent_status = $2, payment_error = $3,
+            status = CASE WHEN $2 = 'canceled' THEN 'CANCELLED' ELSE status END,
+            updated_at = NOW()
+        WHERE stripe_payment_intent_id = $1 AND status = 'PENDING'
+        "#,
+    ] {
+        sqlx::query(statement)
+            .bind(payment_intent_id)
+            .bind(status)
+            .bind(error)
+            .execute(&db.pool)
+            .await
+            .map_err(|e| {
+                tracing::error!("Failed to record status of payment intent {}: {}", payment_intent_id, e);
+                StatusCode::INTERNAL_SERVER_ERROR
+            })?;
+    }
+    Ok(())
+}
+
+/// Tell the payer when an in-page payment needs them: their bank asks for authentication, or the
+/// card was declined and another payment method is needed.
+async fn notify_payment_attention(db: &Database, payment_intent: &PaymentIntent, error: Option<&str>) {
+    let message = match payment_intent.status.as_str() {
+        "requires_action" => "Your bank needs you to confirm your payment",
+        "requires_payment_method" => "Your payment failed; try another payment method",
+        _ => return,
+    };
+    let Some(user_id) = payment_intent.metadata.get("user_id") else {
+        return;
+    };
+    notify(
+        db,
+        user_id,
+        NotificationKind::Payment,
+        json!({
+            "message": message,
+            "paymentIntentId": payment_intent.id,
+            "status": payment_intent.status,
+            "error": error,
+        }),
+    )
+    .await;
+}
+
+/// Email the creator that someone just joined their membership.
+async fn email_new_subscriber(
+    db: &Database,
+    creator_id: &str,
+    subscriber_id: &str,
+    invoice: &StripeInvoice,
+) {
+    let names = sqlx::query_as::<_, (Option<String>, String)>(
+        r#"
+        SELECT creator.email, subscriber.name
+        FROM users creator, users subscriber
+        WHERE creator.id = $1 AND subscriber.id = $2
+        "#,
+    )
+    .bind(creator_id)
+    .bind(subscriber_id)
+    .fetch_optional(&db.pool)
+    .await;
+    let (email, subscriber_name) = match names {
+        Ok(Some((Some(email), subscriber_name))) => (email, subscriber_name),
+        Ok(_) => return,
+        Err(e) => {
+            tracing::warn!("Failed to look up new subscriber email for {}: {}", creator_id, e);
+            return;
+        }
+    };
+
+    queue_email(
+        db,
+        &email,
+        Some(creator_id),
+        EmailTemplate::NewSubscriber,
+        json!({
+            "subscriber_name": subscriber_name,
+            "amount": format_amount(invoice.amount_paid, &invoice.currency),
+            "link": app_link("/creator-dashboard/subscribers"),
+        }),
+    )
+    .await;
+}
+
+/// Issue our own invoice for a paid subscription renewal; returns false for Stripe invoices
+/// that don't belong to a known subscription.
+async fn invoice_subscription_payment(db: &Database, invoice: &StripeInvoice) -> Result<bool, StatusCode> {
+    let Some(stripe_subscription_id) = invoice.subscription.as_ref().map(Expandable::id) else {
+        return Ok(false);
+    };
+    if invoice.amount_paid <= 0 {
+        return Ok(false);
+    }
+    let subscription = sqlx::query_as::<_, (uuid::Uuid, String, String)>(
+        "SELECT id, user_id, creator_id FROM subscriptions WHERE stripe_subscription_id = $1",
+    )
+    .bind(stripe_subscription_id)
+    .fetch_optional(&db.pool)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let Some((subscription_id, user_id, creator_id)) = subscription else {
+        return Ok(false);
+    };
+
+    let is_new = invoice.billing_reason.as_deref() == Some("subscription_create");
+    notify(
+        db,
+        &creator_id,
+        NotificationKind::Subscription,
+        json!({
+            "message": if is_new { "You have a new member" } else { "A membership was renewed" },
+            "link": "/creator-dashboard/subscribers",
+            "subscriptionId": subscription_id,
+            "subscriberId": user_id,
+            "isNew": is_new,
+            "amountCents": invoice.amount_paid,
+            "currency": invoice.currency,
+        }),
+    )
+    .await;
+    if is_new {
+        email_new_subscriber(db, &creator_id, &user_id, invoice).await;
+        record_conversion(
+            db,
+            &user_id,
+            ReferralEvent::Subscription,
+            &invoice.id,
+            invoice.amount_paid,
+            &invoice.currency,
+        )
+        .await;
+    }
+
+    issue_invoice(
+        db,
+        InvoiceSource {
+            source_type: "SUBSCRIPTION",
+            source_id: invoice.id.clone(),
+            creator_id,
+            buyer_id: Some(user_id),
+            subscription_id: Some(subscription_id),
+            description: invoice
+                .description
+                .clone()
+                .unwrap_or_else(|| "Membership renewal".to_string()),
+            amount_cents: invoice.amount_paid,
+            currency: invoice.currency.clone(),
+        },
+    )
+    .await?;
+    Ok(true)
+}
+
+/// Remember the card behind a payment by someone who referred or was referred, so the referral
+/// fraud checks can tell when two accounts pay with the same card. Best effort.
+async fn record_card_fingerprint(db: &Database, payment_intent: &PaymentIntent) {
+    let (Some(user_id), Some(payment_method)) =
+        (payment_intent.metadata.get("user_id"), payment_intent.payment_method.as_ref())
+    else {
+        return;
+    };
+    let in_referrals = sqlx::query_scalar::<_, bool>(
+        "SELECT EXISTS (SELECT 1 FROM referrals WHERE referred_user_id = $1 OR referrer_id = $1)",
+    )
+    .bind(user_id)
+    .fetch_one(&db.pool)
+    .await
+    .unwrap_or(false);
+    if !in_referrals {
+        return;
+    }
+    let Ok(secret) = stripe_secret() else {
+        return;
+    };
+
+    let response = reqwest::Client::new()
+        .get(stripe_url(&format!("/v1/payment_methods/{}", payment_method.id())))
+        .header("Authorization", format!("Bearer {}", secret))
+        .send()
+        .await;
+    let Ok(method) = stripe_json(response, "fetch payment method").await else {
+        return;
+    };
+    let Some(fingerprint) = method.pointer("/card/fingerprint").and_then(|v| v.as_str()) else {
+        return;
+    };
+    let recorded = sqlx::query(
+        r#"
+        INSERT INTO payment_card_fingerprints (user_id, fingerprint)
+        VALUES ($1, $2)
+        ON CONFLICT (user_id, fingerprint) DO NOTHING
+        "#,
+    )
+    .bind(user_id)
+    .bind(fingerprint)
+    .execute(&db.pool)
+    .await;
+    if let Err(e) = recorded {
+        tracing::warn!("Failed to record card fingerprint of {}: {}", user_id, e);
+    }
+}
+
+/// Complete the purchases and post unlocks paid through a checkout session, for buyers who
+/// never returned to the confirmation page.
+async fn complete_checkout_session(db: &Database, session: &CheckoutSession) -> Result<(), StatusCode> {
+    let paid = matches!(
+        session.payment_status.as_deref().map(str::to_ascii_lowercase).as_deref(),
+        Some("paid") | Some("complete") | Some("no_payment_required")
+    );
+    if !paid {
+        return Ok(());
+    }
+    let payment_intent_id = session.payment_intent.as_ref().map(Expandable::id);
+    let db_error = |e: sqlx::Error| {
+        tracing::error!("Failed to complete checkout session {}: {}", session.id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    };
+
+    let mut tx = db.pool.begin().await.map_err(db_error)?;
+    for statement in [
+        r#"
+        UPDATE purchases
+        SET status = 'COMPLETED',
+            stripe_payment_intent_id = COALESCE($2, stripe_payment_intent_id)
+        WHERE stripe_checkout_session_id = $1 AND status <> 'COMPLETED'
+        "#,
+        r#"
+        UPDATE post_unlocks
+        SET status = 'COMPLETED',
+            stripe_payment_intent_id = COALESCE($2, stripe_payment_intent_id),
+            updated_at = NOW()
+        WHERE stripe_checkout_session_id = $1 AND status <> 'COMPLETED'
+        "#,
+    ] {
+        sqlx::query(statement)
+            .bind(&session.id)
+            .bind(payment_intent_id)
+            .execute(&mut tx)
+            .await
+            .map_err(db_error)?;
+    }
+    settle_ledger_entries_on(&mut tx, &session.id, payment_intent_id).await?;
+    tx.commit().await.map_err(db_error)?;
+    fulfill_cart(db, &session.id, payment_intent_id).await?;
+    consume_stock(db, &session.id).await?;
+    issue_purchase_invoices(db, &session.id).await;
+    issue_license_keys(db, &session.id).await;
+
+    Ok(())
+}
+
+/// The Connect account id of a creator whose account may receive payouts.
+pub(crate) async fn ensure_payouts_enabled(db: &Database, user_id: &str) -> Result<String, StatusCode> {
+    let account = load_connect_account(db, user_id)
+        .await?
+        .ok_or(StatusCode::FORBIDDEN)?;
+    if account.status != "ENABLED" {
+        return Err(StatusCode::FORBIDDEN);
+    }
+    Ok(account.stripe_account_id)
+}
+
+async fn load_connect_account(db: &Database, user_id: &str) -> Result<Option<ConnectAccount>, StatusCode> {
+    sqlx::query_as::<_, ConnectAccount>("SELECT * FROM creator_connect_accounts WHERE user_id = $1")
+        .bind(user_id)
+        .fetch_optional(&db.pool)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to load Connect account of {}: {}", user_id, e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })
+}
+
+/// Store the capability flags of a Stripe account and derive the onboarding status.
+/// Accounts that are not ours are ignored.
+async fn apply_account_update(
+    db: &Database,
+    account: &StripeAccount,
+) -> Result<Option<ConnectAccount>, StatusCode> {
+    let requirements_due = account
+        .requirements
+        .as_ref()
+        .and_then(|requirements| requirements.currently_due.clone())
+        .unwrap_or_default();
+    let disabled_reason = account
+        .requirements
+        .as_ref()
+        .and_then(|requirements| requirements.disabled_reason.clone());
+
+    let status = match () {
+        _ if account.charges_enabled && account.payouts_enabled => "ENABLED",
+        _ if !account.details_submitted => "PENDING",
+        _ if disabled_reason.is_some() || !requirements_due.is_empty() => "RESTRICTED",
+        _ => "IN_REVIEW",
+    };
+
+    sqlx::query_as::<_, ConnectAccount>(
+        r#"
+        UPDATE creator_connect_accounts
+        SET status = $2, charges_enabled = $3, payouts_enabled = $4, details_submitted = $5,
+            requirements_due = $6, disabled_reason = $7, updated_at = NOW()
+        WHERE stripe_account_id = $1
+        RETURNING *
+        "#,
+    )
+    .bind(&account.id)
+    .bind(status)
+    .bind(account.charges_enabled)
+    .bind(account.payouts_enabled)
+    .bind(account.details_submitted)
+    .bind(&requirements_due)
+    .bind(&disabled_reason)
+    .fetch_optional(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to update Connect account {}: {}", account.id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })
+}
+
+/// A fresh Stripe-hosted onboarding link that sends the creator back to the dashboard.
+async fn account_link_url(stripe_account_id: &str) -> Result<String, StatusCode> {
+    let frontend_url = Config::from_env()
+        .map(|config| config.frontend_url)
+        .unwrap_or_else(|_| "http://localhost:3000".to_string());
+    let frontend_url = frontend_url.trim_end_matches('/');
+
+    let response = reqwest::Client::new()
+        .post(stripe_url("/v1/account_links"))
+        .header("Authorization", format!("Bearer {}", stripe_secret()?))
+        .form(&[
+            ("account", stripe_account_id.to_string()),
+            ("type", "account_onboarding".to_string()),
+            ("refresh_url", format!("{}/creator-dashboard/settings?onboarding=refresh", frontend_url)),
+            ("return_url", format!("{}/creator-dashboard/settings?onboarding=return", frontend_url)),
+        ])
+        .send()
+        .await;
+    let link = stripe_json(response, "create account link").await?;
+
+    link.get("url")
+        .and_then(|v| v.as_str())
+        .map(str::to_string)
+        .ok_or(StatusCode::BAD_GATEWAY)
+}
+
+/// URL of a Stripe API path such as `/v1/refunds`, on the sandbox provider in sandbox mode.
+pub(crate) fn stripe_url(path: &str) -> String {
+    if payments_sandbox() {
+        return format!("{}{}", payments_sandbox_url(), path);
+    }
+    format!("https://api.stripe.com{}", path)
+}
+
+pub(crate) fn stripe_secret() -> Result<String, StatusCode> {
+    // Live keys never leave for the sandbox provider
+    if payments_sandbox() {
+        return Ok(SANDBOX_SECRET_KEY.to_string());
+    }
+    let secret = std::env::var("STRIPE_SECRET_KEY").unwrap_or_default();
+    if secret.trim().is_empty() {
+        tracing::error!("STRIPE_SECRET_KEY is not configured");
+        return Err(StatusCode::INTERNAL_SERVER_ERROR);
+    }
+    Ok(secret)
+}
+
+pub(crate) async fn stripe_json(
+    response: Result<reqwest::Response, reqwest::Error>,
+    action: &str,
+) -> Result<serde_json::Value, StatusCode> {
+    let response = response.map_err(|err| {
+        tracing::error!("Failed to {} with Stripe: {:?}", action, err);
+        StatusCode::BAD_GATEWAY
+    })?;
+
+    if !response.status().is_success() {
+        let body = response.text().await.unwrap_or_default();
+        tracing::error!("Stripe returned error for {}: {}", action, body);
+        return Err(StatusCode::BAD_GATEWAY);
+    }
+
+    response.json().await.map_err(|err| {
+        tracing::error!("Failed to parse Stripe response for {}: {:?}", action, err);
+        StatusCode::BAD_GATEWAY
+    })
+}
+
+/// Record where the pending purchases of a new checkout session can be resumed and when the
+/// session lapses, for the abandoned checkout job.
+pub(crate) async fn track_checkout(db: &Database, session: &serde_json::Value) {
+    let Some(session_id) = session.get("id").and_then(|id| id.as_str()) else {
+        return;
+    };
+    let expires_at = session
+        .get("expires_at")
+        .and_then(|expires_at| expires_at.as_i64())
+        .and_then(|expires_at| DateTime::<Utc>::from_timestamp(expires_at, 0));
+    let tracked = sqlx::query(
+        r#"
+        UPDATE purchases SET checkout_url = $2, checkout_expires_at = $3
+        WHERE stripe_checkout_session_id = $1 AND status = 'PENDING'
+        "#,
+    )
+    .bind(session_id)
+    .bind(session.get("url").and_then(|url| url.as_str()))
+    .bind(expires_at)
+    .execute(&db.pool)
+    .await;
+    if let Err(e) = tracked {
+        tracing::warn!("Failed to track checkout session {}: {}", session_id, e);
+    }
+}
+
+/// `Stripe-Signature: t=<unix>,v1=<hex hmac-sha256 of "<t>.<body>">`
+fn verify_stripe_signature(secret: &str, header: &str, body: &[u8]) -> bool {
+    let mut timestamp = None;
+    let mut signatures = Vec::new();
+    for part in header.split(',') {
+        match part.trim().split_once('=') {
+            Some(("t", value)) => timestamp = value.parse::<i64>().ok(),
+            Some(("v1", value)) => signatures.push(value.to_string()),
+            _ => {}
+        }
+    }
+
+    let Some(timestamp) = timestamp else {
+        return false;
+    };
+    if (Utc::now().timestamp() - timestamp).abs() > WEBHOOK_TOLERANCE_SECONDS {
+        return false;
+    }
+
+    signatures.iter().any(|signature| {
+        let Ok(expected) = hex::decode(signature) else {
+            return false;
+        };
+        let mut mac =
+            HmacSha256::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+        mac.update(timestamp.to_string().as_bytes());
+        mac.update(b".");
+        mac.update(body);
+        mac.verify_slice(&expected).is_ok()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECRET: &str = "whsec_test";
+    const BODY: &[u8] = br#"{"id":"evt_1","type":"payment_intent.succeeded"}"#;
+
+    fn signature_header(secret: &str, timestamp: i64, body: &[u8]) -> String {
+        let mut mac = HmacSha256::new_from_slice(secret.as_bytes()).unwrap();
+        mac.update(format!("{}.", timestamp).as_bytes());
+        mac.update(body);
+        format!("t={},v1={}", timestamp, hex::encode(mac.finalize().into_bytes()))
+    }
+
+    #[test]
+    fn accepts_a_valid_signature() {
+        let header = signature_header(SECRET, Utc::now().timestamp(), BODY);
+        assert!(verify_stripe_signature(SECRET, &header, BODY));
+        // Stripe sends one v1 per active secret while one is being rolled
+        let rolled = format!("{},v1=00ff", header);
+        assert!(verify_stripe_signature(SECRET, &rolled, BODY));
+    }
+
+    #[test]
+    fn rejects_a_tampered_body_or_wrong_secret() {
+        let header = signature_header(SECRET, Utc::now().timestamp(), BODY);
+        let tampered = br#"{"id":"evt_2","type":"payment_intent.succeeded"}"#;
+        assert!(!verify_stripe_signature(SECRET, &header, tampered));
+        assert!(!verify_stripe_signature("whsec_other", &header, BODY));
+        assert!(!verify_stripe_signature(SECRET, "v1=00ff", BODY));
+    }
+
+    #[test]
+    fn rejects_a_stale_timestamp() {
+        let stale = Utc::now().timestamp() - WEBHOOK_TOLERANCE_SECONDS - 1;
+        let header = signature_header(SECRET, stale, BODY);
+        assert!(!verify_stripe_signature(SECRET, &header, BODY));
+    }
+}
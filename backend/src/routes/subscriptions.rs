@@ -0,0 +1,21 @@
+use axum::{http::StatusCode, response::Json, routing::get, Router};
+
+use crate::{database::Database, routes::invoices::subscription_invoice_routes};
+
+pub fn subscription_routes() -> Router<Database> {
+    Router::new()
+        .route("/my-subscribers", get(get_my_subscribers))
+        .merge(subscription_invoice_routes())
+}
+
+async fn get_my_subscribers() -> Result<Json<serde_json::Value>, StatusCode> {
+    // Mock subscribers for now
+    let response = serde_json::json!({
+        "success": true,
+        "data": {
+            "subscriptions": []
+        }
+    });
+
+    Ok(Json(response))
+}
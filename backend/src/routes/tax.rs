@@ -0,0 +1,401 @@
+use axum::{
+    extract::{Query, State},
+    http::{HeaderMap, StatusCode},
+    response::Json,
+    routing::get,
+    Router,
+};
+use chrono::{Datelike, NaiveDate, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use uuid::Uuid;
+
+use crate::{auth::Claims, database::Database, routes::fees::LedgerSource};
+
+/// Geolocation headers set by the CDNs and proxies we run behind, most trusted first.
+const IP_COUNTRY_HEADERS: [&str; 4] = [
+    "cf-ipcountry",
+    "cloudfront-viewer-country",
+    "x-vercel-ip-country",
+    "x-country-code",
+];
+
+/// Where a buyer is for tax purposes, with the evidence it was derived from.
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct BuyerLocation {
+    /// ISO 3166-1 alpha-2 country the sale is taxed in, if any evidence was found.
+    pub country: Option<String>,
+    /// State or province, for countries taxing per region.
+    pub region: Option<String>,
+    pub billing_country: Option<String>,
+    pub ip_country: Option<String>,
+    pub ip_address: Option<String>,
+    /// The billing country and the IP location disagree; the billing country is used, but the
+    /// sale lacks the two matching pieces of evidence EU rules ask for.
+    pub evidence_conflict: bool,
+}
+
+/// Tax due on one sale.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct TaxQuote {
+    pub country: Option<String>,
+    pub region: Option<String>,
+    /// `VAT`, `GST`, `Sales tax`; `None` when no tax applies.
+    pub name: Option<String>,
+    pub rate_basis_points: i32,
+    pub taxable_cents: i64,
+    pub tax_cents: i64,
+    pub location: BuyerLocation,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TaxQuoteQuery {
+    product_id: Uuid,
+    country: Option<String>,
+    region: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct QuarterlyReportQuery {
+    year: Option<i32>,
+    quarter: Option<u32>,
+}
+
+pub fn tax_routes() -> Router<Database> {
+    Router::new()
+        .route("/quote", get(get_tax_quote))
+        .route("/reports/quarterly", get(get_quarterly_report))
+}
+
+// What checkout will add on top of the price, so buyers see it before paying
+async fn get_tax_quote(
+    State(db): State<Database>,
+    Query(params): Query<TaxQuoteQuery>,
+    headers: HeaderMap,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let (price, currency, is_digital) = sqlx::query_as::<_, (f64, String, bool)>(
+        "SELECT price, currency, is_digital FROM products WHERE id = $1",
+    )
+    .bind(params.product_id)
+    .fetch_optional(&db.pool)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    .ok_or(StatusCode::NOT_FOUND)?;
+
+    let price_cents = (price * 100.0).round() as i64;
+    let location = resolve_buyer_location(params.country.as_deref(), params.region.as_deref(), &headers);
+    let quote = if is_digital {
+        quote_tax(&db, location, price_cents).await?
+    } else {
+        TaxQuote::none(location, price_cents)
+    };
+
+    Ok(Json(json!({
+        "success": true,
+        "data": {
+            "tax": quote,
+            "currency": currency,
+            "totalCents": price_cents + quote.tax_cents,
+        }
+    })))
+}
+
+async fn get_quarterly_report(
+    State(db): State<Database>,
+    Query(params): Query<QuarterlyReportQuery>,
+    claims: Claims,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let today = Utc::now().date_naive();
+    let year = params.year.unwrap_or(today.year());
+    let quarter = params.quarter.unwrap_or((today.month() - 1) / 3 + 1);
+    if !(1..=4).contains(&quarter) {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let start = NaiveDate::from_ymd_opt(year, (quarter - 1) * 3 + 1, 1).ok_or(StatusCode::BAD_REQUEST)?;
+    let end = if quarter == 4 {
+        NaiveDate::from_ymd_opt(year + 1, 1, 1)
+    } else {
+        NaiveDate::from_ymd_opt(year, quarter * 3 + 1, 1)
+    }
+    .ok_or(StatusCode::BAD_REQUEST)?;
+    let start_at = Utc.from_utc_datetime(&start.and_hms_opt(0, 0, 0).unwrap_or_default());
+    let end_at = Utc.from_utc_datetime(&end.and_hms_opt(0, 0, 0).unwrap_or_default());
+
+    let rows = sqlx::query_as::<_, (Option<String>, Option<String>, Option<String>, i32, String, i64, i64, i64)>(
+        r#"
+        SELECT country, region, tax_name, rate_basis_points, currency,
+               COUNT(*)::BIGINT, SUM(taxable_cents)::BIGINT, SUM(tax_cents)::BIGINT
+        FROM tax_lines
+        WHERE creator_id = $1 AND status = 'SETTLED'
+          AND settled_at >= $2 AND settled_at < $3
+        GROUP BY country, region, tax_name, rate_basis_points, currency
+        ORDER BY country NULLS LAST, region NULLS FIRST, currency
+        "#,
+    )
+    .bind(&claims.sub)
+    .bind(start_at)
+    .bind(end_at)
+    .fetch_all(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to build tax report for {}: {}", claims.sub, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    let mut totals = std::collections::BTreeMap::<String, (i64, i64)>::new();
+    let lines: Vec<serde_json::Value> = rows
+        .into_iter()
+        .map(|(country, region, name, rate, currency, sales, taxable, tax)| {
+            let total = totals.entry(currency.clone()).or_default();
+            total.0 += taxable;
+            total.1 += tax;
+            json!({
+                "country": country,
+                "region": region,
+                "taxName": name,
+                "rateBasisPoints": rate,
+                "currency": currency,
+                "sales": sales,
+                "taxableCents": taxable,
+                "taxCents": tax,
+            })
+        })
+        .collect();
+    let totals: Vec<serde_json::Value> = totals
+        .into_iter()
+        .map(|(currency, (taxable, tax))| {
+            json!({ "currency": currency, "taxableCents": taxable, "taxCents": tax })
+        })
+        .collect();
+
+    Ok(Json(json!({
+        "success": true,
+        "data": {
+            "year": year,
+            "quarter": quarter,
+            "periodStart": start,
+            "periodEnd": end.pred_opt().unwrap_or(end),
+            "lines": lines,
+            "totals": totals,
+        }
+    })))
+}
+
+/// Locate the buyer from the billing country they gave and the country their IP resolves to.
+/// The billing country wins when both are known.
+pub(crate) fn resolve_buyer_location(
+    billing_country: Option<&str>,
+    billing_region: Option<&str>,
+    headers: &HeaderMap,
+) -> BuyerLocation {
+    let billing_country = billing_country.and_then(country_code);
+    let ip_country = ip_country(headers);
+    let ip_address = headers
+        .get("x-forwarded-for")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.split(',').next())
+        .or_else(|| headers.get("x-real-ip").and_then(|value| value.to_str().ok()))
+        .map(|ip| ip.trim().to_string())
+        .filter(|ip| !ip.is_empty());
+
+    let evidence_conflict = matches!((&billing_country, &ip_country), (Some(b), Some(i)) if b != i);
+    let country = billing_country.clone().or_else(|| ip_country.clone());
+    // A region only makes sense for the country it was given with
+    let region = billing_region
+        .filter(|_| billing_country.is_some())
+        .map(|region| region.trim().to_ascii_uppercase())
+        .filter(|region| !region.is_empty() && region.len() <= 10);
+
+    BuyerLocation {
+        country,
+        region,
+        billing_country,
+        ip_country,
+        ip_address,
+        evidence_conflict,
+    }
+}
+
+/// The country the request's IP resolves to, as reported by the CDN in front of the API.
+pub(crate) fn ip_country(headers: &HeaderMap) -> Option<String> {
+    IP_COUNTRY_HEADERS
+        .iter()
+        .filter_map(|name| headers.get(*name).and_then(|value| value.to_str().ok()))
+        .find_map(country_code)
+}
+
+/// Proxies send `XX` or `T1` (Tor) for unknown locations; those are not evidence.
+fn country_code(value: &str) -> Option<String> {
+    let code = value.trim().to_ascii_uppercase();
+    (code.len() == 2 && code.chars().all(|c| c.is_ascii_alphabetic()) && code != "XX").then_some(code)
+}
+
+/// Tax on a digital sale of `taxable_cents` to a buyer at `location`. A regional rate takes
+/// precedence over the country-wide one.
+pub(crate) async fn quote_tax(
+    db: &Database,
+    location: BuyerLocation,
+    taxable_cents: i64,
+) -> Result<TaxQuote, StatusCode> {
+    let Some(country) = location.country.clone() else {
+        return Ok(TaxQuote::none(location, taxable_cents));
+    };
+
+    let rate = sqlx::query_as::<_, (String, i32, String)>(
+        r#"
+        SELECT region_code, rate_basis_points, tax_name
+        FROM tax_rates
+        WHERE country_code = $1 AND (region_code = '' OR region_code = $2)
+        ORDER BY region_code DESC
+        LIMIT 1
+        "#,
+    )
+    .bind(&country)
+    .bind(location.region.as_deref().unwrap_or(""))
+    .fetch_optional(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to load tax rate for {}: {}", country, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    let Some((region, rate_basis_points, name)) = rate else {
+        return Ok(TaxQuote::none(location, taxable_cents));
+    };
+
+    Ok(TaxQuote {
+        country: Some(country),
+        region: Some(region).filter(|region| !region.is_empty()),
+        name: Some(name),
+        rate_basis_points,
+        taxable_cents,
+        tax_cents: tax_cents(taxable_cents, rate_basis_points),
+        location,
+    })
+}
+
+/// Tax at `rate_basis_points` on `taxable_cents`, rounded to the nearest cent.
+fn tax_cents(taxable_cents: i64, rate_basis_points: i32) -> i64 {
+    (taxable_cents * rate_basis_points as i64 + 5_000) / 10_000
+}
+
+impl TaxQuote {
+    pub(crate) fn none(location: BuyerLocation, taxable_cents: i64) -> Self {
+        Self {
+            country: location.country.clone(),
+            region: None,
+            name: None,
+            rate_basis_points: 0,
+            taxable_cents,
+            tax_cents: 0,
+            location,
+        }
+    }
+
+    /// Label for the tax line on checkout pages and receipts, e.g. `VAT (DE 19%)`.
+    pub(crate) fn label(&self) -> String {
+        let rate = self.rate_basis_points as f64 / 100.0;
+        let place = match (&self.country, &self.region) {
+            (Some(country), Some(region)) => format!("{}-{} ", country, region),
+            (Some(country), None) => format!("{} ", country),
+            _ => String::new(),
+        };
+        format!("{} ({}{}%)", self.name.as_deref().unwrap_or("Tax"), place, rate)
+    }
+
+    /// Record the tax line of a sale, kept apart from the ledger so it can be reported on and
+    /// remitted separately. Sales without tax are recorded too, as evidence of the location.
+    pub(crate) async fn record(
+        &self,
+        db: &Database,
+        creator_id: &str,
+        source: &LedgerSource<'_>,
+    ) -> Result<(), StatusCode> {
+        sqlx::query(
+            r#"
+            INSERT INTO tax_lines (
+                source_type, source_id, creator_id, buyer_id, country, region, tax_name,
+                rate_basis_points, taxable_cents, tax_cents, currency, evidence,
+                stripe_checkout_session_id, stripe_payment_intent_id
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14)
+            "#,
+        )
+        .bind(source.source_type)
+        .bind(&source.source_id)
+        .bind(creator_id)
+        .bind(source.payer_id)
+        .bind(&self.country)
+        .bind(&self.region)
+        .bind(&self.name)
+        .bind(self.rate_basis_points)
+        .bind(self.taxable_cents)
+        .bind(self.tax_cents)
+        .bind(source.currency.to_uppercase())
+        .bind(json!(self.location))
+        .bind(source.stripe_checkout_session_id)
+        .bind(source.stripe_payment_intent_id)
+        .execute(&db.pool)
+        .await
+        .map_err(|e| {
+            tracing::error!(
+                "Failed to record tax line for {} {}: {}",
+                source.source_type,
+                source.source_id,
+                e
+            );
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn location(country: Option<&str>) -> BuyerLocation {
+        resolve_buyer_location(country, None, &HeaderMap::new())
+    }
+
+    #[test]
+    fn tax_rounds_to_the_nearest_cent() {
+        // 19% of $9.99 is 189.81 cents, 7.25% of $9.99 is 72.4275
+        assert_eq!(tax_cents(999, 1_900), 190);
+        assert_eq!(tax_cents(999, 725), 72);
+        assert_eq!(tax_cents(1_000, 2_000), 200);
+        assert_eq!(tax_cents(999, 0), 0);
+    }
+
+    #[test]
+    fn billing_country_wins_over_ip_country() {
+        let mut headers = HeaderMap::new();
+        headers.insert("cf-ipcountry", "fr".parse().unwrap());
+        let location = resolve_buyer_location(Some("de"), Some("by"), &headers);
+        assert_eq!(location.country.as_deref(), Some("DE"));
+        assert_eq!(location.region.as_deref(), Some("BY"));
+        assert!(location.evidence_conflict);
+
+        let unknown = resolve_buyer_location(Some("XX"), Some("by"), &HeaderMap::new());
+        assert_eq!(unknown.country, None);
+        assert_eq!(unknown.region, None);
+    }
+
+    #[test]
+    fn label_names_the_rate_and_place() {
+        let quote = TaxQuote {
+            country: Some("DE".to_string()),
+            region: None,
+            name: Some("VAT".to_string()),
+            rate_basis_points: 1_900,
+            taxable_cents: 999,
+            tax_cents: 190,
+            location: location(Some("DE")),
+        };
+        assert_eq!(quote.label(), "VAT (DE 19%)");
+        assert_eq!(TaxQuote::none(location(None), 999).label(), "Tax (0%)");
+    }
+}
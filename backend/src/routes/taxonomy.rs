@@ -0,0 +1,418 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::Json,
+    routing::{get, post, put},
+    Router,
+};
+use serde::Deserialize;
+use serde_json::json;
+use sqlx::{postgres::PgRow, Row};
+use tracing::error;
+use uuid::Uuid;
+
+use crate::{auth::Claims, database::Database};
+
+/// Article categories and tags share the same shape and management rules.
+#[derive(Debug, Clone, Copy)]
+enum Taxonomy {
+    Category,
+    Tag,
+}
+
+impl Taxonomy {
+    fn table(self) -> &'static str {
+        match self {
+            Taxonomy::Category => "categories",
+            Taxonomy::Tag => "tags",
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            Taxonomy::Category => "category",
+            Taxonomy::Tag => "tag",
+        }
+    }
+
+    /// Constraint guarding unique slugs, used to report conflicts.
+    fn slug_constraint(self) -> &'static str {
+        match self {
+            Taxonomy::Category => "categories_slug_key",
+            Taxonomy::Tag => "tags_slug_key",
+        }
+    }
+
+    fn article_count_sql(self) -> &'static str {
+        match self {
+            Taxonomy::Category => "(SELECT COUNT(*) FROM articles a WHERE a.category_id = t.id)",
+            Taxonomy::Tag => "(SELECT COUNT(*) FROM article_tags at WHERE at.tag_id = t.id)",
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct TaxonomyRequest {
+    name: String,
+    slug: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct MergeRequest {
+    #[serde(alias = "targetId")]
+    target_id: Uuid,
+}
+
+pub fn category_routes() -> Router<Database> {
+    Router::new()
+        .route("/", get(list_categories).post(create_category))
+        .route("/:id", put(rename_category).delete(delete_category))
+        .route("/:id/merge", post(merge_category))
+}
+
+pub fn tag_routes() -> Router<Database> {
+    Router::new()
+        .route("/", get(list_tags).post(create_tag))
+        .route("/:id", put(rename_tag).delete(delete_tag))
+        .route("/:id/merge", post(merge_tag))
+}
+
+async fn list_categories(State(db): State<Database>) -> Result<Json<serde_json::Value>, StatusCode> {
+    list_terms(&db, Taxonomy::Category).await
+}
+
+async fn list_tags(State(db): State<Database>) -> Result<Json<serde_json::Value>, StatusCode> {
+    list_terms(&db, Taxonomy::Tag).await
+}
+
+async fn create_category(
+    State(db): State<Database>,
+    claims: Claims,
+    Json(payload): Json<TaxonomyRequest>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    create_term(&db, Taxonomy::Category, &claims.sub, payload).await
+}
+
+async fn create_tag(
+    State(db): State<Database>,
+    claims: Claims,
+    Json(payload): Json<TaxonomyRequest>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    create_term(&db, Taxonomy::Tag, &claims.sub, payload).await
+}
+
+async fn rename_category(
+    State(db): State<Database>,
+    Path(id): Path<Uuid>,
+    claims: Claims,
+    Json(payload): Json<TaxonomyRequest>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    rename_term(&db, Taxonomy::Category, id, &claims.sub, payload).await
+}
+
+async fn rename_tag(
+    State(db): State<Database>,
+    Path(id): Path<Uuid>,
+    claims: Claims,
+    Json(payload): Json<TaxonomyRequest>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    rename_term(&db, Taxonomy::Tag, id, &claims.sub, payload).await
+}
+
+async fn delete_category(
+    State(db): State<Database>,
+    Path(id): Path<Uuid>,
+    claims: Claims,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    delete_term(&db, Taxonomy::Category, id, &claims.sub).await
+}
+
+async fn delete_tag(
+    State(db): State<Database>,
+    Path(id): Path<Uuid>,
+    claims: Claims,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    delete_term(&db, Taxonomy::Tag, id, &claims.sub).await
+}
+
+async fn merge_category(
+    State(db): State<Database>,
+    Path(id): Path<Uuid>,
+    claims: Claims,
+    Json(payload): Json<MergeRequest>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    merge_terms(&db, Taxonomy::Category, id, payload.target_id, &claims.sub).await
+}
+
+async fn merge_tag(
+    State(db): State<Database>,
+    Path(id): Path<Uuid>,
+    claims: Claims,
+    Json(payload): Json<MergeRequest>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    merge_terms(&db, Taxonomy::Tag, id, payload.target_id, &claims.sub).await
+}
+
+async fn list_terms(db: &Database, kind: Taxonomy) -> Result<Json<serde_json::Value>, StatusCode> {
+    let rows = sqlx::query(&format!(
+        "SELECT t.id, t.name, t.slug, t.created_by, {} AS article_count FROM {} t ORDER BY t.name ASC",
+        kind.article_count_sql(),
+        kind.table()
+    ))
+    .fetch_all(&db.pool)
+    .await
+    .map_err(|e| {
+        error!("Failed to list {}: {:?}", kind.table(), e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    let terms: Vec<serde_json::Value> = rows.iter().map(term_json).collect();
+
+    Ok(Json(json!({
+        "success": true,
+        "data": terms
+    })))
+}
+
+async fn create_term(
+    db: &Database,
+    kind: Taxonomy,
+    user_id: &str,
+    payload: TaxonomyRequest,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let (is_creator, _) = load_permissions(db, user_id).await?;
+    if !is_creator {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    let (name, slug) = validate_term(payload)?;
+
+    let row = sqlx::query(&format!(
+        "INSERT INTO {} (name, slug, created_by) VALUES ($1, $2, $3) RETURNING id, name, slug, created_by, 0::BIGINT AS article_count",
+        kind.table()
+    ))
+    .bind(&name)
+    .bind(&slug)
+    .bind(user_id)
+    .fetch_one(&db.pool)
+    .await
+    .map_err(|e| map_write_error(kind, e))?;
+
+    Ok(Json(json!({
+        "success": true,
+        "data": term_json(&row)
+    })))
+}
+
+async fn rename_term(
+    db: &Database,
+    kind: Taxonomy,
+    id: Uuid,
+    user_id: &str,
+    payload: TaxonomyRequest,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    ensure_can_manage(db, kind, id, user_id).await?;
+    let (name, slug) = validate_term(payload)?;
+
+    let row = sqlx::query(&format!(
+        "UPDATE {table} t SET name = $2, slug = $3, updated_at = NOW() WHERE t.id = $1 \
+         RETURNING t.id, t.name, t.slug, t.created_by, {count} AS article_count",
+        table = kind.table(),
+        count = kind.article_count_sql()
+    ))
+    .bind(id)
+    .bind(&name)
+    .bind(&slug)
+    .fetch_one(&db.pool)
+    .await
+    .map_err(|e| map_write_error(kind, e))?;
+
+    Ok(Json(json!({
+        "success": true,
+        "data": term_json(&row)
+    })))
+}
+
+async fn delete_term(
+    db: &Database,
+    kind: Taxonomy,
+    id: Uuid,
+    user_id: &str,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    ensure_can_manage(db, kind, id, user_id).await?;
+
+    // Articles keep existing: categories are unset and tag links cascade away.
+    sqlx::query(&format!("DELETE FROM {} WHERE id = $1", kind.table()))
+        .bind(id)
+        .execute(&db.pool)
+        .await
+        .map_err(|e| {
+            error!("Failed to delete {} {}: {:?}", kind.label(), id, e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    Ok(Json(json!({
+        "success": true,
+        "message": format!("Deleted {}", kind.label())
+    })))
+}
+
+/// Fold `source_id` into `target_id`: articles are re-pointed to the target and the source is removed.
+async fn merge_terms(
+    db: &Database,
+    kind: Taxonomy,
+    source_id: Uuid,
+    target_id: Uuid,
+    user_id: &str,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    if source_id == target_id {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    ensure_can_manage(db, kind, source_id, user_id).await?;
+    ensure_can_manage(db, kind, target_id, user_id).await?;
+
+    let mut tx = db.pool.begin().await.map_err(|e| {
+        error!("Failed to start {} merge: {:?}", kind.label(), e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    let moved = match kind {
+        Taxonomy::Category => sqlx::query(
+            "UPDATE articles SET category_id = $2, updated_at = NOW() WHERE category_id = $1",
+        )
+        .bind(source_id)
+        .bind(target_id)
+        .execute(&mut tx)
+        .await
+        .map(|result| result.rows_affected()),
+        Taxonomy::Tag => sqlx::query(
+            r#"
+            INSERT INTO article_tags (article_id, tag_id)
+            SELECT article_id, $2 FROM article_tags WHERE tag_id = $1
+            ON CONFLICT (article_id, tag_id) DO NOTHING
+            "#,
+        )
+        .bind(source_id)
+        .bind(target_id)
+        .execute(&mut tx)
+        .await
+        .map(|result| result.rows_affected()),
+    }
+    .map_err(|e| {
+        error!("Failed to re-point articles from {} {}: {:?}", kind.label(), source_id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    sqlx::query(&format!("DELETE FROM {} WHERE id = $1", kind.table()))
+        .bind(source_id)
+        .execute(&mut tx)
+        .await
+        .map_err(|e| {
+            error!("Failed to remove merged {} {}: {:?}", kind.label(), source_id, e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    tx.commit().await.map_err(|e| {
+        error!("Failed to commit {} merge: {:?}", kind.label(), e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    Ok(Json(json!({
+        "success": true,
+        "data": {
+            "mergedId": source_id,
+            "targetId": target_id,
+            "articlesMoved": moved
+        }
+    })))
+}
+
+/// Admins manage every term; creators manage the terms they created.
+async fn ensure_can_manage(
+    db: &Database,
+    kind: Taxonomy,
+    id: Uuid,
+    user_id: &str,
+) -> Result<(), StatusCode> {
+    let created_by = sqlx::query_scalar::<_, Option<String>>(&format!(
+        "SELECT created_by FROM {} WHERE id = $1",
+        kind.table()
+    ))
+    .bind(id)
+    .fetch_optional(&db.pool)
+    .await
+    .map_err(|e| {
+        error!("Failed to load {} {}: {:?}", kind.label(), id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?
+    .ok_or(StatusCode::NOT_FOUND)?;
+
+    let (_, is_admin) = load_permissions(db, user_id).await?;
+    if is_admin || created_by.as_deref() == Some(user_id) {
+        Ok(())
+    } else {
+        Err(StatusCode::FORBIDDEN)
+    }
+}
+
+/// Returns `(can_create, is_admin)` for the user.
+async fn load_permissions(db: &Database, user_id: &str) -> Result<(bool, bool), StatusCode> {
+    let row = sqlx::query(
+        "SELECT COALESCE(is_creator, FALSE) AS is_creator, COALESCE(is_admin, FALSE) AS is_admin FROM users WHERE id = $1",
+    )
+    .bind(user_id)
+    .fetch_optional(&db.pool)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    .ok_or(StatusCode::UNAUTHORIZED)?;
+
+    let is_admin: bool = row.get("is_admin");
+    Ok((is_admin || row.get::<bool, _>("is_creator"), is_admin))
+}
+
+fn validate_term(payload: TaxonomyRequest) -> Result<(String, String), StatusCode> {
+    let name = payload.name.trim().to_string();
+    if name.is_empty() || name.len() > 100 {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let slug = slugify(payload.slug.as_deref().unwrap_or(&name));
+    if slug.is_empty() {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    Ok((name, slug))
+}
+
+pub(crate) fn slugify(value: &str) -> String {
+    value
+        .to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+fn map_write_error(kind: Taxonomy, e: sqlx::Error) -> StatusCode {
+    match &e {
+        sqlx::Error::Database(db_err) if db_err.constraint() == Some(kind.slug_constraint()) => {
+            StatusCode::CONFLICT
+        }
+        sqlx::Error::RowNotFound => StatusCode::NOT_FOUND,
+        _ => {
+            error!("Failed to save {}: {:?}", kind.label(), e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        }
+    }
+}
+
+fn term_json(row: &PgRow) -> serde_json::Value {
+    json!({
+        "id": row.get::<Uuid, _>("id"),
+        "name": row.get::<String, _>("name"),
+        "slug": row.get::<String, _>("slug"),
+        "createdBy": row.get::<Option<String>, _>("created_by"),
+        "articleCount": row.get::<i64, _>("article_count"),
+    })
+}
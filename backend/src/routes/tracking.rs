@@ -0,0 +1,282 @@
+use axum::{
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    response::Json,
+};
+use chrono::{DateTime, Datelike, Duration, Months, NaiveDate, Utc};
+use serde::Deserialize;
+use serde_json::json;
+use tracing::info;
+use uuid::Uuid;
+
+use crate::{database::Database, middleware::optional_auth::MaybeClaims, routes::tax::ip_country};
+
+const MAX_BATCH_EVENTS: usize = 100;
+/// Events reported later than this after they happened are dropped.
+const MAX_EVENT_AGE_HOURS: i64 = 24;
+/// Monthly partitions of raw events older than this are dropped; the daily rollups are kept.
+const RAW_RETENTION_MONTHS: u32 = 13;
+const MAX_PATH_LEN: usize = 512;
+const MAX_TARGET_LEN: usize = 128;
+const MAX_ANONYMOUS_ID_LEN: usize = 64;
+
+/// What a tracked event can be about, as stored in `analytics_events.subject_type`.
+pub(crate) const SUBJECT_TYPES: [(&str, i16); 3] = [("campaign", 1), ("product", 2), ("episode", 3)];
+/// Click target of the donate button on a campaign page.
+pub(crate) const DONATE_TARGET: &str = "donate";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum TrackedEvent {
+    PageView,
+    Click,
+}
+
+impl TrackedEvent {
+    pub(crate) fn code(self) -> i16 {
+        match self {
+            TrackedEvent::PageView => 1,
+            TrackedEvent::Click => 2,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TrackEvent {
+    #[serde(rename = "type")]
+    event_type: TrackedEvent,
+    /// The creator whose page the event happened on.
+    creator_id: String,
+    path: Option<String>,
+    /// What was clicked, e.g. `subscribe-button`.
+    target: Option<String>,
+    /// The campaign or product the page shows, e.g. `campaign-<uuid>`.
+    subject: Option<String>,
+    occurred_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct TrackBatch {
+    /// A random id the client keeps for signed-out visitors, so they count once per day.
+    anonymous_id: Option<String>,
+    events: Vec<TrackEvent>,
+}
+
+/// Per-creator totals of one day, from the rollups.
+#[derive(Debug, Default, Clone, Copy)]
+pub(crate) struct DailyTraffic {
+    pub page_views: i64,
+    pub unique_visitors: i64,
+    pub clicks: i64,
+}
+
+/// The stored type code of a subject type name.
+pub(crate) fn subject_code(subject_type: &str) -> Option<i16> {
+    SUBJECT_TYPES
+        .iter()
+        .find(|(name, _)| *name == subject_type)
+        .map(|(_, code)| *code)
+}
+
+/// Split a subject such as `campaign-<uuid>` into its type code and id.
+fn parse_subject(subject: &str) -> Option<(i16, Uuid)> {
+    let (subject_type, id) = subject.split_once('-')?;
+    Some((subject_code(subject_type)?, Uuid::parse_str(id).ok()?))
+}
+
+fn truncate(value: Option<&str>, max_len: usize) -> Option<String> {
+    let value = value?.trim();
+    if value.is_empty() {
+        return None;
+    }
+    Some(value.chars().take(max_len).collect())
+}
+
+/// Batched page views and clicks from the web client, signed in or not. Events for creators that
+/// do not exist are dropped on insert, and events from too long ago are skipped.
+pub(crate) async fn track_events(
+    State(db): State<Database>,
+    MaybeClaims(claims): MaybeClaims,
+    headers: HeaderMap,
+    Json(payload): Json<TrackBatch>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    if payload.events.len() > MAX_BATCH_EVENTS {
+        return Err(StatusCode::PAYLOAD_TOO_LARGE);
+    }
+
+    let visitor_id = match claims {
+        Some(claims) => Some(claims.sub),
+        None => truncate(payload.anonymous_id.as_deref(), MAX_ANONYMOUS_ID_LEN).map(|id| format!("anon:{}", id)),
+    };
+    let country = ip_country(&headers);
+    let now = Utc::now();
+    let oldest = now - Duration::hours(MAX_EVENT_AGE_HOURS);
+
+    let mut creator_ids = Vec::new();
+    let mut event_types = Vec::new();
+    let mut paths = Vec::new();
+    let mut targets = Vec::new();
+    let mut subject_types = Vec::new();
+    let mut subject_ids = Vec::new();
+    let mut occurred_at = Vec::new();
+    for event in &payload.events {
+        let at = event.occurred_at.unwrap_or(now).min(now);
+        if at < oldest || event.creator_id.is_empty() {
+            continue;
+        }
+        creator_ids.push(event.creator_id.clone());
+        event_types.push(event.event_type.code());
+        paths.push(truncate(event.path.as_deref(), MAX_PATH_LEN));
+        targets.push(truncate(event.target.as_deref(), MAX_TARGET_LEN));
+        // An unknown subject still counts towards the creator's traffic
+        let subject = event.subject.as_deref().and_then(parse_subject);
+        subject_types.push(subject.map(|(subject_type, _)| subject_type));
+        subject_ids.push(subject.map(|(_, id)| id));
+        occurred_at.push(at);
+    }
+
+    let accepted = if creator_ids.is_empty() {
+        0
+    } else {
+        sqlx::query(
+            r#"
+            INSERT INTO analytics_events (
+                creator_id, event_type, visitor_id, path, target, subject_type, subject_id, country, occurred_at
+            )
+            SELECT e.creator_id, e.event_type, $6, e.path, e.target, e.subject_type, e.subject_id, $7, e.occurred_at
+            FROM UNNEST($1::TEXT[], $2::SMALLINT[], $3::TEXT[], $4::TEXT[], $5::TIMESTAMPTZ[], $8::SMALLINT[], $9::UUID[])
+                AS e(creator_id, event_type, path, target, occurred_at, subject_type, subject_id)
+            JOIN users u ON u.id = e.creator_id
+            "#,
+        )
+        .bind(&creator_ids)
+        .bind(&event_types)
+        .bind(&paths)
+        .bind(&targets)
+        .bind(&occurred_at)
+        .bind(&visitor_id)
+        .bind(&country)
+        .bind(&subject_types)
+        .bind(&subject_ids)
+        .execute(&db.pool)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to record analytics events: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?
+        .rows_affected() as usize
+    };
+
+    Ok(Json(json!({
+        "success": true,
+        "data": {
+            "accepted": accepted,
+            "skipped": payload.events.len() - accepted
+        }
+    })))
+}
+
+/// Daily traffic of a creator between two dates, inclusive. Days without events are missing.
+pub(crate) async fn daily_traffic(
+    db: &Database,
+    creator_id: &str,
+    from: NaiveDate,
+    to: NaiveDate,
+) -> Result<Vec<(NaiveDate, DailyTraffic)>, sqlx::Error> {
+    let rows = sqlx::query_as::<_, (NaiveDate, i64, i64, i64)>(
+        r#"
+        SELECT day,
+               COALESCE(SUM(events) FILTER (WHERE event_type = $4), 0)::BIGINT,
+               COALESCE(SUM(unique_visitors) FILTER (WHERE event_type = $4), 0)::BIGINT,
+               COALESCE(SUM(events) FILTER (WHERE event_type = $5), 0)::BIGINT
+        FROM creator_daily_stats
+        WHERE creator_id = $1 AND day BETWEEN $2 AND $3
+        GROUP BY day
+        ORDER BY day
+        "#,
+    )
+    .bind(creator_id)
+    .bind(from)
+    .bind(to)
+    .bind(TrackedEvent::PageView.code())
+    .bind(TrackedEvent::Click.code())
+    .fetch_all(&db.pool)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|(day, page_views, unique_visitors, clicks)| {
+            (day, DailyTraffic { page_views, unique_visitors, clicks })
+        })
+        .collect())
+}
+
+/// Make sure the coming months have partitions, recount yesterday's and today's per-creator
+/// totals from the raw events, then drop months of raw events past retention. Recounting whole
+/// days keeps the rollup right however often it runs.
+pub async fn roll_up_creator_stats(db: &Database) -> anyhow::Result<()> {
+    sqlx::query("SELECT create_analytics_event_partitions()")
+        .execute(&db.pool)
+        .await?;
+
+    let rolled_up = sqlx::query(
+        r#"
+        INSERT INTO creator_daily_stats (day, creator_id, event_type, events, unique_visitors)
+        SELECT
+            (occurred_at AT TIME ZONE 'UTC')::DATE AS day,
+            creator_id,
+            event_type,
+            COUNT(*),
+            COUNT(DISTINCT visitor_id)
+        FROM analytics_events
+        WHERE occurred_at >= date_trunc('day', NOW() AT TIME ZONE 'UTC') AT TIME ZONE 'UTC' - INTERVAL '1 day'
+        GROUP BY 1, 2, 3
+        ON CONFLICT (creator_id, day, event_type) DO UPDATE
+        SET events = EXCLUDED.events,
+            unique_visitors = EXCLUDED.unique_visitors
+        "#,
+    )
+    .execute(&db.pool)
+    .await?
+    .rows_affected();
+
+    let today = Utc::now().date_naive();
+    let cutoff = today
+        .with_day(1)
+        .and_then(|month| month.checked_sub_months(Months::new(RAW_RETENTION_MONTHS)))
+        .unwrap_or(today);
+    let cutoff_suffix = format!("{:04}{:02}", cutoff.year(), cutoff.month());
+    let partitions = sqlx::query_scalar::<_, String>(
+        r#"
+        SELECT c.relname::TEXT
+        FROM pg_inherits i
+        JOIN pg_class c ON c.oid = i.inhrelid
+        WHERE i.inhparent = 'analytics_events'::regclass
+        "#,
+    )
+    .fetch_all(&db.pool)
+    .await?;
+
+    let mut dropped = 0;
+    for partition in partitions {
+        let expired = partition
+            .strip_prefix("analytics_events_")
+            .is_some_and(|suffix| suffix.len() == 6 && suffix.chars().all(|c| c.is_ascii_digit()) && suffix < cutoff_suffix.as_str());
+        if expired {
+            sqlx::query(&format!("DROP TABLE IF EXISTS {}", partition))
+                .execute(&db.pool)
+                .await?;
+            dropped += 1;
+        }
+    }
+
+    if rolled_up > 0 || dropped > 0 {
+        info!(
+            "Analytics events: {} daily rows rolled up, {} monthly partitions dropped",
+            rolled_up, dropped
+        );
+    }
+    Ok(())
+}
@@ -1,18 +1,19 @@
 use std::{env, path::PathBuf, time::SystemTime};
 
 use axum::{
-    extract::{Multipart, State},
-    http::StatusCode,
-    response::Json,
-    routing::post,
+    extract::{Multipart, Path, Query, State},
+    http::{header, StatusCode},
+    response::{IntoResponse, Json, Response},
+    routing::{get, post},
     Router,
 };
 use reqwest::{Client, StatusCode as ReqwestStatusCode};
+use serde::Deserialize;
 use serde_json::json;
 use tokio::{fs, io::AsyncWriteExt};
 use uuid::Uuid;
 
-use crate::{auth::Claims, config::Config, database::Database};
+use crate::{auth::Claims, config::Config, database::Database, storage};
 
 type UploadResponse = Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)>;
 
@@ -20,6 +21,15 @@ pub fn upload_routes() -> Router<Database> {
     Router::new()
         .route("/image", post(upload_image))
         .route("/video", post(upload_video))
+        .route("/audio", post(upload_audio))
+        .route("/product-file", post(upload_product_file))
+        .route("/private/*key", get(download_private_file))
+}
+
+#[derive(Debug, Deserialize)]
+struct SignedDownloadQuery {
+    expires: i64,
+    signature: String,
 }
 
 async fn upload_image(
@@ -38,6 +48,14 @@ async fn upload_video(
     handle_upload(multipart, "videos", &["video/"], 300 * 1024 * 1024).await
 }
 
+async fn upload_audio(
+    State(_db): State<Database>,
+    _claims: Claims,
+    multipart: Multipart,
+) -> UploadResponse {
+    handle_upload(multipart, "audio", &["audio/"], 500 * 1024 * 1024).await
+}
+
 async fn handle_upload(
     mut multipart: Multipart,
     folder: &str,
@@ -210,6 +228,111 @@ async fn handle_upload(
     })))
 }
 
+/// Largest file a product can ship with.
+const PRODUCT_FILE_MAX_BYTES: usize = 500 * 1024 * 1024;
+
+// Product files go to private storage. The returned `private://` URL is what products,
+// variants and versions store; buyers only ever get signed links to it.
+async fn upload_product_file(
+    State(_db): State<Database>,
+    _claims: Claims,
+    mut multipart: Multipart,
+) -> UploadResponse {
+    while let Some(mut field) = multipart
+        .next_field()
+        .await
+        .map_err(|_| json_error(StatusCode::BAD_REQUEST, "Invalid multipart payload"))?
+    {
+        let Some(file_name) = field
+            .file_name()
+            .and_then(|name| std::path::Path::new(name).file_name())
+            .and_then(|name| name.to_str())
+            .map(str::to_string)
+        else {
+            continue;
+        };
+        let content_type = field
+            .content_type()
+            .map(|mime| mime.to_string())
+            .unwrap_or_else(|| "application/octet-stream".to_string());
+
+        let mut bytes: Vec<u8> = Vec::new();
+        while let Some(chunk) = field
+            .chunk()
+            .await
+            .map_err(|_| json_error(StatusCode::BAD_REQUEST, "Could not read upload stream"))?
+        {
+            if bytes.len() + chunk.len() > PRODUCT_FILE_MAX_BYTES {
+                return Err(json_error(
+                    StatusCode::PAYLOAD_TOO_LARGE,
+                    "Uploaded file exceeds size limit",
+                ));
+            }
+            bytes.extend_from_slice(&chunk);
+        }
+        if bytes.is_empty() {
+            return Err(json_error(StatusCode::BAD_REQUEST, "Empty file upload"));
+        }
+
+        let size = bytes.len();
+        let stored = storage::store_private_file("products", Some(&file_name), &content_type, bytes)
+            .await
+            .map_err(|error| {
+                tracing::error!("Failed to store product file: {:?}", error);
+                json_error(StatusCode::INTERNAL_SERVER_ERROR, "Failed to save file")
+            })?;
+
+        return Ok(Json(json!({
+            "success": true,
+            "data": {
+                "url": storage::private_file_url(&stored.storage_key),
+                "fileName": file_name,
+                "size": size,
+                "contentType": content_type,
+            }
+        })));
+    }
+
+    Err(json_error(StatusCode::BAD_REQUEST, "No file found in upload payload"))
+}
+
+// Serve a privately stored file behind a locally signed, expiring URL
+async fn download_private_file(
+    Path(key): Path<String>,
+    Query(params): Query<SignedDownloadQuery>,
+) -> Result<Response, (StatusCode, Json<serde_json::Value>)> {
+    if !storage::verify_signed_url(&key, params.expires, &params.signature) {
+        return Err(json_error(
+            StatusCode::FORBIDDEN,
+            "Download link is invalid or has expired",
+        ));
+    }
+
+    let file_path = storage::private_file_path(&key)
+        .map_err(|_| json_error(StatusCode::BAD_REQUEST, "Invalid file path"))?;
+    let bytes = fs::read(&file_path)
+        .await
+        .map_err(|_| json_error(StatusCode::NOT_FOUND, "File not found"))?;
+
+    let file_name = file_path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or("download")
+        .to_string();
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, "application/octet-stream".to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"{}\"", file_name),
+            ),
+        ],
+        bytes,
+    )
+        .into_response())
+}
+
 fn json_error(status: StatusCode, message: &str) -> (StatusCode, Json<serde_json::Value>) {
     (
         status,
@@ -238,6 +361,11 @@ fn guess_extension(content_type: &str, original_name: Option<&str>) -> String {
         "video/mp4" => "mp4".to_string(),
         "video/quicktime" => "mov".to_string(),
         "video/webm" => "webm".to_string(),
+        "audio/mpeg" => "mp3".to_string(),
+        "audio/mp4" | "audio/x-m4a" => "m4a".to_string(),
+        "audio/wav" | "audio/x-wav" => "wav".to_string(),
+        "audio/ogg" => "ogg".to_string(),
+        "audio/flac" => "flac".to_string(),
         _ => "bin".to_string(),
     }
 }
@@ -1,15 +1,33 @@
 use axum::{
     extract::{Path, Query, State},
-    http::StatusCode,
-    response::Json,
-    routing::{delete, get, post, put},
+    http::{HeaderMap, StatusCode},
+    response::{Json, Response},
+    routing::{get, post, put},
     Router,
 };
 use serde::{Deserialize, Serialize};
 use serde_json::json;
+use sha2::{Digest, Sha256};
 use sqlx::Row;
+use uuid::Uuid;
+
+use crate::{
+    auth::Claims,
+    database::Database,
+    feed_timeline, ics,
+    middleware::optional_auth::MaybeClaims,
+    models::User,
+    routes::{
+        bookmarks::bookmark_routes,
+        events::{calendar_response, load_calendar_events},
+        following::following_routes,
+    },
+};
 
-use crate::{auth::Claims, database::Database, models::User};
+#[derive(Debug, Deserialize)]
+struct CalendarFeedParams {
+    token: Option<String>,
+}
 
 #[derive(Debug, Deserialize)]
 struct PaginationParams {
@@ -21,6 +39,13 @@ pub fn user_routes() -> Router<Database> {
     Router::new()
         .route("/me", get(get_current_user))
         .route("/me/campaigns", get(get_user_campaigns))
+        .route("/me/events.ics", get(get_my_events_calendar))
+        .route(
+            "/me/calendar-token",
+            post(create_calendar_token).delete(revoke_calendar_token),
+        )
+        .nest("/me/bookmarks", bookmark_routes())
+        .nest("/me/following", following_routes())
         .route("/become-creator", post(become_creator))
         .route("/:id", get(get_user_by_id))
         .route("/:id", put(update_user))
@@ -176,6 +201,10 @@ async fn follow_user(
     .await
     .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
+    if result.rows_affected() > 0 {
+        feed_timeline::reset(&db, &claims.sub).await;
+    }
+
     let follower_count =
         sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM follows WHERE following_id = $1")
             .bind(&id)
@@ -206,6 +235,10 @@ async fn unfollow_user(
         .await
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
+    if result.rows_affected() > 0 {
+        feed_timeline::reset(&db, &claims.sub).await;
+    }
+
     let follower_count =
         sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM follows WHERE following_id = $1")
             .bind(&id)
@@ -374,3 +407,92 @@ async fn become_creator(
 
     Ok(Json(response))
 }
+
+// Subscription feed of the user's events. Calendar apps cannot send a bearer token, so the
+// feed also accepts the per-user token issued by `POST /me/calendar-token`.
+async fn get_my_events_calendar(
+    State(db): State<Database>,
+    Query(params): Query<CalendarFeedParams>,
+    MaybeClaims(maybe_claims): MaybeClaims,
+) -> Result<Response, StatusCode> {
+    let user_id = match (maybe_claims, params.token) {
+        (Some(claims), _) => claims.sub,
+        (None, Some(token)) => sqlx::query_scalar::<_, String>(
+            "SELECT user_id FROM calendar_feed_tokens WHERE token_hash = $1",
+        )
+        .bind(hash_calendar_token(&token))
+        .fetch_optional(&db.pool)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::UNAUTHORIZED)?,
+        (None, None) => return Err(StatusCode::UNAUTHORIZED),
+    };
+
+    let events = load_calendar_events(&db, &user_id).await?;
+    let calendar = ics::render_calendar("Fundify events", &events);
+    Ok(calendar_response("fundify-events.ics", calendar))
+}
+
+// Issue a new calendar feed token, replacing any previous one
+async fn create_calendar_token(
+    State(db): State<Database>,
+    headers: HeaderMap,
+    claims: Claims,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let token = format!("{}{}", Uuid::new_v4().simple(), Uuid::new_v4().simple());
+
+    sqlx::query(
+        r#"
+        INSERT INTO calendar_feed_tokens (user_id, token_hash, created_at)
+        VALUES ($1, $2, NOW())
+        ON CONFLICT (user_id)
+        DO UPDATE SET token_hash = EXCLUDED.token_hash, created_at = NOW()
+        "#,
+    )
+    .bind(&claims.sub)
+    .bind(hash_calendar_token(&token))
+    .execute(&db.pool)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let host = headers
+        .get("x-forwarded-host")
+        .or_else(|| headers.get("host"))
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or("localhost:4000");
+    let scheme = headers
+        .get("x-forwarded-proto")
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or("https");
+    let path = format!("{}/api/users/me/events.ics?token={}", host, token);
+
+    Ok(Json(json!({
+        "success": true,
+        "data": {
+            "token": token,
+            "url": format!("{}://{}", scheme, path),
+            "webcalUrl": format!("webcal://{}", path)
+        }
+    })))
+}
+
+// Revoke the calendar feed token so existing subscriptions stop updating
+async fn revoke_calendar_token(
+    State(db): State<Database>,
+    claims: Claims,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    sqlx::query("DELETE FROM calendar_feed_tokens WHERE user_id = $1")
+        .bind(&claims.sub)
+        .execute(&db.pool)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(json!({
+        "success": true,
+        "message": "Calendar feed token revoked"
+    })))
+}
+
+fn hash_calendar_token(token: &str) -> String {
+    hex::encode(Sha256::digest(token.trim().as_bytes()))
+}
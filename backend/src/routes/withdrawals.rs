@@ -0,0 +1,574 @@
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::Json,
+    routing::get,
+    Router,
+};
+use chrono::{DateTime, Datelike, Duration, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use uuid::Uuid;
+
+use crate::{
+    auth::Claims,
+    database::Database,
+    notification_service::{notify, NotificationKind},
+    routes::stripe::{ensure_payouts_enabled, stripe_json, stripe_secret, stripe_url},
+};
+
+/// Smallest payout a creator can request, in the currency's minor unit.
+pub(crate) const MIN_WITHDRAWAL_CENTS: i64 = 1_000;
+/// Monthly payouts stop at the 28th so every month has the chosen day.
+const MAX_MONTHLY_PAYOUT_DAY: i16 = 28;
+
+/// A creator's request to be paid out, from the ledger through Stripe to their bank.
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct Withdrawal {
+    pub id: Uuid,
+    pub creator_id: String,
+    pub amount_cents: i64,
+    pub currency: String,
+    /// `PENDING` until Stripe reports the payout `PAID` or `FAILED`.
+    pub status: String,
+    pub stripe_account_id: String,
+    /// Part of the amount first moved from the platform balance to the creator's account.
+    pub transfer_cents: i64,
+    pub stripe_transfer_id: Option<String>,
+    pub stripe_payout_id: Option<String>,
+    pub failure_reason: Option<String>,
+    pub arrival_date: Option<DateTime<Utc>>,
+    pub paid_at: Option<DateTime<Utc>>,
+    /// Started by the creator's payout schedule rather than by hand.
+    pub scheduled: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// How a creator wants to be paid out: by hand, or automatically every week or month once the
+/// balance reaches a threshold.
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct PayoutSchedule {
+    pub creator_id: String,
+    /// `MANUAL`, `WEEKLY` or `MONTHLY`.
+    pub frequency: String,
+    /// Automatic payouts wait until the available balance reaches this amount.
+    pub minimum_cents: i64,
+    /// ISO weekday of weekly payouts, 1 for Monday to 7 for Sunday.
+    pub weekly_day: i16,
+    /// Day of the month of monthly payouts, 1 to 28.
+    pub monthly_day: i16,
+    pub next_payout_at: Option<DateTime<Utc>>,
+    pub last_payout_at: Option<DateTime<Utc>>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// What a creator can withdraw in one currency.
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct Balance {
+    pub currency: String,
+    /// Settled earnings not yet paid out or being paid out.
+    pub available_cents: i64,
+    /// Earnings from payments that have not been confirmed yet.
+    pub pending_cents: i64,
+    /// Earnings held back while a buyer disputes the payment.
+    pub disputed_cents: i64,
+    /// Settled earnings still held on the platform account rather than the creator's.
+    #[serde(skip)]
+    pub platform_held_cents: i64,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct WithdrawalRequest {
+    /// Amount in cents.
+    amount: i64,
+    currency: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PayoutScheduleRequest {
+    frequency: String,
+    minimum_cents: Option<i64>,
+    weekly_day: Option<i16>,
+    monthly_day: Option<i16>,
+}
+
+#[derive(Debug, Deserialize)]
+struct HistoryQuery {
+    page: Option<u32>,
+    limit: Option<u32>,
+}
+
+pub fn withdrawal_routes() -> Router<Database> {
+    Router::new()
+        .route("/", get(list_withdrawals).post(create_withdrawal))
+        .route("/balance", get(get_balance))
+        .route("/schedule", get(get_payout_schedule).put(update_payout_schedule))
+}
+
+async fn get_balance(
+    State(db): State<Database>,
+    claims: Claims,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let balances = load_balances(&db, &claims.sub).await?;
+
+    Ok(Json(json!({
+        "success": true,
+        "data": {
+            "balances": balances,
+            "minimumWithdrawalCents": MIN_WITHDRAWAL_CENTS,
+        }
+    })))
+}
+
+async fn list_withdrawals(
+    State(db): State<Database>,
+    Query(params): Query<HistoryQuery>,
+    claims: Claims,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let page = params.page.unwrap_or(1).max(1);
+    let limit = params.limit.unwrap_or(20).clamp(1, 100);
+    let offset = ((page - 1) * limit) as i64;
+
+    let withdrawals = sqlx::query_as::<_, Withdrawal>(
+        r#"
+        SELECT * FROM withdrawals
+        WHERE creator_id = $1
+        ORDER BY created_at DESC
+        LIMIT $2 OFFSET $3
+        "#,
+    )
+    .bind(&claims.sub)
+    .bind(limit as i64)
+    .bind(offset)
+    .fetch_all(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to list withdrawals of {}: {}", claims.sub, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    let total = sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM withdrawals WHERE creator_id = $1")
+        .bind(&claims.sub)
+        .fetch_one(&db.pool)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(json!({
+        "success": true,
+        "data": withdrawals,
+        "pagination": {
+            "page": page,
+            "limit": limit,
+            "total": total,
+            "pages": ((total as f64) / (limit as f64)).ceil() as u32,
+        }
+    })))
+}
+
+async fn create_withdrawal(
+    State(db): State<Database>,
+    claims: Claims,
+    Json(payload): Json<WithdrawalRequest>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    if payload.amount < MIN_WITHDRAWAL_CENTS {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let currency = payload
+        .currency
+        .as_deref()
+        .map(str::trim)
+        .filter(|currency| currency.len() == 3)
+        .map(str::to_ascii_uppercase)
+        .unwrap_or_else(|| "USD".to_string());
+
+    let withdrawal = initiate_withdrawal(&db, &claims.sub, &currency, Some(payload.amount), MIN_WITHDRAWAL_CENTS, false)
+        .await?
+        .ok_or(StatusCode::UNPROCESSABLE_ENTITY)?;
+
+    Ok(Json(json!({
+        "success": true,
+        "data": withdrawal
+    })))
+}
+
+async fn get_payout_schedule(
+    State(db): State<Database>,
+    claims: Claims,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let schedule = sqlx::query_as::<_, PayoutSchedule>(
+        "SELECT * FROM payout_schedules WHERE creator_id = $1",
+    )
+    .bind(&claims.sub)
+    .fetch_optional(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to load payout schedule of {}: {}", claims.sub, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?
+    .unwrap_or_else(|| PayoutSchedule {
+        creator_id: claims.sub.clone(),
+        frequency: "MANUAL".to_string(),
+        minimum_cents: MIN_WITHDRAWAL_CENTS,
+        weekly_day: 1,
+        monthly_day: 1,
+        next_payout_at: None,
+        last_payout_at: None,
+        updated_at: Utc::now(),
+    });
+
+    Ok(Json(json!({
+        "success": true,
+        "data": schedule
+    })))
+}
+
+async fn update_payout_schedule(
+    State(db): State<Database>,
+    claims: Claims,
+    Json(payload): Json<PayoutScheduleRequest>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let frequency = payload.frequency.trim().to_ascii_uppercase();
+    if !["MANUAL", "WEEKLY", "MONTHLY"].contains(&frequency.as_str()) {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let minimum_cents = payload.minimum_cents.unwrap_or(MIN_WITHDRAWAL_CENTS);
+    let weekly_day = payload.weekly_day.unwrap_or(1);
+    let monthly_day = payload.monthly_day.unwrap_or(1);
+    if minimum_cents < MIN_WITHDRAWAL_CENTS
+        || !(1..=7).contains(&weekly_day)
+        || !(1..=MAX_MONTHLY_PAYOUT_DAY).contains(&monthly_day)
+    {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    // Automatic payouts would only fail until the creator can be paid out
+    if frequency != "MANUAL" {
+        ensure_payouts_enabled(&db, &claims.sub).await?;
+    }
+
+    let next_payout_at = next_payout_after(&frequency, weekly_day, monthly_day, Utc::now());
+    let schedule = sqlx::query_as::<_, PayoutSchedule>(
+        r#"
+        INSERT INTO payout_schedules (creator_id, frequency, minimum_cents, weekly_day, monthly_day, next_payout_at)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        ON CONFLICT (creator_id) DO UPDATE
+        SET frequency = EXCLUDED.frequency,
+            minimum_cents = EXCLUDED.minimum_cents,
+            weekly_day = EXCLUDED.weekly_day,
+            monthly_day = EXCLUDED.monthly_day,
+            next_payout_at = EXCLUDED.next_payout_at,
+            updated_at = NOW()
+        RETURNING *
+        "#,
+    )
+    .bind(&claims.sub)
+    .bind(&frequency)
+    .bind(minimum_cents)
+    .bind(weekly_day)
+    .bind(monthly_day)
+    .bind(next_payout_at)
+    .fetch_one(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to update payout schedule of {}: {}", claims.sub, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    Ok(Json(json!({
+        "success": true,
+        "data": schedule
+    })))
+}
+
+/// Midnight UTC of the first payout day strictly after `after`; `None` for manual payouts.
+pub(crate) fn next_payout_after(
+    frequency: &str,
+    weekly_day: i16,
+    monthly_day: i16,
+    after: DateTime<Utc>,
+) -> Option<DateTime<Utc>> {
+    let today = after.date_naive();
+    let day = match frequency {
+        "WEEKLY" => {
+            let weekday = today.weekday().number_from_monday() as i64;
+            let days_ahead = (weekly_day as i64 - weekday).rem_euclid(7);
+            today + Duration::days(if days_ahead == 0 { 7 } else { days_ahead })
+        }
+        "MONTHLY" => {
+            let this_month = NaiveDate::from_ymd_opt(today.year(), today.month(), monthly_day as u32)?;
+            if this_month > today {
+                this_month
+            } else if today.month() == 12 {
+                NaiveDate::from_ymd_opt(today.year() + 1, 1, monthly_day as u32)?
+            } else {
+                NaiveDate::from_ymd_opt(today.year(), today.month() + 1, monthly_day as u32)?
+            }
+        }
+        _ => return None,
+    };
+    Some(day.and_hms_opt(0, 0, 0)?.and_utc())
+}
+
+/// Reserve a withdrawal against the balance in `currency` and pay it out. Without an amount the
+/// whole available balance is withdrawn, or nothing when it is below `minimum_cents`.
+///
+/// The withdrawal is reserved before Stripe is called, so two requests cannot both spend the
+/// same earnings; a Stripe failure marks it FAILED and releases the amount.
+pub(crate) async fn initiate_withdrawal(
+    db: &Database,
+    creator_id: &str,
+    currency: &str,
+    amount_cents: Option<i64>,
+    minimum_cents: i64,
+    scheduled: bool,
+) -> Result<Option<Withdrawal>, StatusCode> {
+    let stripe_account_id = ensure_payouts_enabled(db, creator_id).await?;
+
+    let mut tx = db.pool.begin().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    // Serializes withdrawals per creator while the balance is checked.
+    sqlx::query("SELECT user_id FROM creator_connect_accounts WHERE user_id = $1 FOR UPDATE")
+        .bind(creator_id)
+        .execute(&mut tx)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let Some(balance) = sqlx::query_as::<_, Balance>(BALANCE_QUERY)
+        .bind(creator_id)
+        .fetch_all(&mut tx)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to load balance of {}: {}", creator_id, e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?
+        .into_iter()
+        .find(|balance| balance.currency == currency)
+    else {
+        return Ok(None);
+    };
+    let amount_cents = match amount_cents {
+        Some(amount) if amount > balance.available_cents => return Err(StatusCode::UNPROCESSABLE_ENTITY),
+        Some(amount) => amount,
+        None if balance.available_cents < minimum_cents.max(MIN_WITHDRAWAL_CENTS) => return Ok(None),
+        None => balance.available_cents,
+    };
+
+    let transfer_cents = amount_cents.min(balance.platform_held_cents.max(0));
+    let withdrawal = sqlx::query_as::<_, Withdrawal>(
+        r#"
+        INSERT INTO withdrawals (creator_id, amount_cents, currency, stripe_account_id, transfer_cents, scheduled)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        RETURNING *
+        "#,
+    )
+    .bind(creator_id)
+    .bind(amount_cents)
+    .bind(currency)
+    .bind(&stripe_account_id)
+    .bind(transfer_cents)
+    .bind(scheduled)
+    .fetch_one(&mut tx)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to record withdrawal for {}: {}", creator_id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    tx.commit().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    match execute_withdrawal(db, withdrawal.clone()).await {
+        Ok(withdrawal) => Ok(Some(withdrawal)),
+        Err(reason) => {
+            tracing::error!("Withdrawal {} failed: {}", withdrawal.id, reason);
+            mark_failed(db, withdrawal.id, &reason).await?;
+            Err(StatusCode::BAD_GATEWAY)
+        }
+    }
+}
+
+/// Move any platform-held part of the withdrawal to the creator's account, then pay it out.
+async fn execute_withdrawal(db: &Database, mut withdrawal: Withdrawal) -> Result<Withdrawal, String> {
+    let secret = stripe_secret().map_err(|_| "Stripe is not configured".to_string())?;
+    let client = reqwest::Client::new();
+    let currency = withdrawal.currency.to_ascii_lowercase();
+
+    if withdrawal.transfer_cents > 0 {
+        let response = client
+            .post(stripe_url("/v1/transfers"))
+            .header("Authorization", format!("Bearer {}", secret))
+            .header("Idempotency-Key", format!("withdrawal-transfer-{}", withdrawal.id))
+            .form(&[
+                ("amount", withdrawal.transfer_cents.to_string()),
+                ("currency", currency.clone()),
+                ("destination", withdrawal.stripe_account_id.clone()),
+                ("metadata[withdrawal_id]", withdrawal.id.to_string()),
+            ])
+            .send()
+            .await;
+        let transfer = stripe_json(response, "create transfer")
+            .await
+            .map_err(|_| "Transfer to the creator's account failed".to_string())?;
+        withdrawal.stripe_transfer_id = transfer.get("id").and_then(|id| id.as_str()).map(str::to_string);
+        // Stored straight away: the transfer stands even if the payout below fails.
+        save_stripe_ids(db, &withdrawal).await.map_err(|_| "Failed to store transfer".to_string())?;
+    }
+
+    let response = client
+        .post(stripe_url("/v1/payouts"))
+        .header("Authorization", format!("Bearer {}", secret))
+        .header("Stripe-Account", &withdrawal.stripe_account_id)
+        .header("Idempotency-Key", format!("withdrawal-payout-{}", withdrawal.id))
+        .form(&[
+            ("amount", withdrawal.amount_cents.to_string()),
+            ("currency", currency),
+            ("metadata[withdrawal_id]", withdrawal.id.to_string()),
+            ("metadata[user_id]", withdrawal.creator_id.clone()),
+        ])
+        .send()
+        .await;
+    let payout = stripe_json(response, "create payout")
+        .await
+        .map_err(|_| "Payout to the creator's bank failed".to_string())?;
+    withdrawal.stripe_payout_id = payout.get("id").and_then(|id| id.as_str()).map(str::to_string);
+    withdrawal.arrival_date = payout
+        .get("arrival_date")
+        .and_then(|date| date.as_i64())
+        .and_then(|seconds| DateTime::from_timestamp(seconds, 0));
+
+    save_stripe_ids(db, &withdrawal)
+        .await
+        .map_err(|_| "Failed to store payout".to_string())
+}
+
+async fn save_stripe_ids(db: &Database, withdrawal: &Withdrawal) -> Result<Withdrawal, StatusCode> {
+    sqlx::query_as::<_, Withdrawal>(
+        r#"
+        UPDATE withdrawals
+        SET stripe_transfer_id = $2, stripe_payout_id = $3, arrival_date = $4, updated_at = NOW()
+        WHERE id = $1
+        RETURNING *
+        "#,
+    )
+    .bind(withdrawal.id)
+    .bind(&withdrawal.stripe_transfer_id)
+    .bind(&withdrawal.stripe_payout_id)
+    .bind(withdrawal.arrival_date)
+    .fetch_one(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to update withdrawal {}: {}", withdrawal.id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })
+}
+
+async fn mark_failed(db: &Database, withdrawal_id: Uuid, reason: &str) -> Result<(), StatusCode> {
+    sqlx::query(
+        "UPDATE withdrawals SET status = 'FAILED', failure_reason = $2, updated_at = NOW() WHERE id = $1",
+    )
+    .bind(withdrawal_id)
+    .bind(reason)
+    .execute(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to mark withdrawal {} failed: {}", withdrawal_id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    Ok(())
+}
+
+/// Apply a `payout.paid` / `payout.failed` webhook to the withdrawal it pays out.
+pub(crate) async fn apply_payout_update(
+    db: &Database,
+    payout_id: &str,
+    paid: bool,
+    failure_message: Option<&str>,
+) -> Result<(), StatusCode> {
+    let withdrawal = sqlx::query_as::<_, (Uuid, String, i64, String)>(
+        r#"
+        UPDATE withdrawals
+        SET status = CASE WHEN $2 THEN 'PAID' ELSE 'FAILED' END,
+            failure_reason = CASE WHEN $2 THEN NULL ELSE COALESCE($3, 'Payout failed') END,
+            paid_at = CASE WHEN $2 THEN NOW() ELSE NULL END,
+            updated_at = NOW()
+        WHERE stripe_payout_id = $1 AND status = 'PENDING'
+        RETURNING id, creator_id, amount_cents, currency
+        "#,
+    )
+    .bind(payout_id)
+    .bind(paid)
+    .bind(failure_message)
+    .fetch_optional(&db.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to apply payout {} to its withdrawal: {}", payout_id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    if let Some((withdrawal_id, creator_id, amount_cents, currency)) = withdrawal {
+        let amount = format!("{:.2} {}", amount_cents as f64 / 100.0, currency.to_uppercase());
+        notify(
+            db,
+            &creator_id,
+            NotificationKind::Payout,
+            json!({
+                "message": if paid {
+                    format!("Your {} payout has arrived", amount)
+                } else {
+                    format!("Your {} payout failed", amount)
+                },
+                "withdrawalId": withdrawal_id,
+                "paid": paid,
+                "amountCents": amount_cents,
+                "currency": currency,
+                "failureReason": failure_message,
+            }),
+        )
+        .await;
+    }
+    Ok(())
+}
+
+// Failed withdrawals no longer count against the balance; their transfers, if any, still moved
+// money off the platform account.
+const BALANCE_QUERY: &str = r#"
+    WITH earned AS (
+        SELECT currency,
+               COALESCE(SUM(net_cents) FILTER (WHERE status = 'SETTLED'), 0) AS settled,
+               COALESCE(SUM(net_cents) FILTER (WHERE status = 'PENDING'), 0) AS pending,
+               COALESCE(SUM(net_cents) FILTER (WHERE status = 'DISPUTED'), 0) AS disputed,
+               COALESCE(SUM(net_cents) FILTER (WHERE status = 'SETTLED' AND stripe_account_id IS NULL), 0)
+                   AS platform_held
+        FROM ledger_entries
+        WHERE creator_id = $1
+        GROUP BY currency
+    ),
+    withdrawn AS (
+        SELECT currency,
+               COALESCE(SUM(amount_cents) FILTER (WHERE status <> 'FAILED'), 0) AS reserved,
+               COALESCE(SUM(transfer_cents) FILTER (WHERE stripe_transfer_id IS NOT NULL OR status <> 'FAILED'), 0)
+                   AS transferred
+        FROM withdrawals
+        WHERE creator_id = $1
+        GROUP BY currency
+    )
+    SELECT e.currency,
+           (e.settled - COALESCE(w.reserved, 0))::BIGINT AS available_cents,
+           e.pending::BIGINT AS pending_cents,
+           e.disputed::BIGINT AS disputed_cents,
+           (e.platform_held - COALESCE(w.transferred, 0))::BIGINT AS platform_held_cents
+    FROM earned e
+    LEFT JOIN withdrawn w ON w.currency = e.currency
+    ORDER BY e.currency
+"#;
+
+pub(crate) async fn load_balances(db: &Database, creator_id: &str) -> Result<Vec<Balance>, StatusCode> {
+    sqlx::query_as::<_, Balance>(BALANCE_QUERY)
+        .bind(creator_id)
+        .fetch_all(&db.pool)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to load balance of {}: {}", creator_id, e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })
+}
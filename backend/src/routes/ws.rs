@@ -0,0 +1,120 @@
+use std::time::Duration;
+
+use axum::{
+    extract::{
+        ws::{Message, WebSocket, WebSocketUpgrade},
+        Query, State,
+    },
+    http::StatusCode,
+    response::Response,
+    routing::get,
+    Router,
+};
+use futures_util::{SinkExt, Stream, StreamExt};
+use serde::Deserialize;
+
+use crate::{
+    auth::{verify_jwt, Claims},
+    config::Config,
+    database::Database,
+    messaging,
+    middleware::optional_auth::MaybeClaims,
+    realtime::{live_counters, live_message, user_channel, LiveEvent},
+};
+
+/// Largest message taken from a client; sockets carry small control messages upstream.
+const MAX_MESSAGE_BYTES: usize = 64 * 1024;
+/// Pings keep proxies from closing idle sockets and reveal dead connections.
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Deserialize)]
+struct SocketQuery {
+    token: Option<String>,
+}
+
+pub fn ws_routes() -> Router<Database> {
+    Router::new().route("/ws", get(connect))
+}
+
+// Browsers cannot set headers on a WebSocket handshake, so the JWT may also come as `?token=`.
+// The socket gets the user's counters first, then every live update published for them.
+async fn connect(
+    State(db): State<Database>,
+    Query(params): Query<SocketQuery>,
+    MaybeClaims(maybe_claims): MaybeClaims,
+    upgrade: WebSocketUpgrade,
+) -> Result<Response, StatusCode> {
+    let claims = match (maybe_claims, params.token) {
+        (Some(claims), _) => claims,
+        (None, Some(token)) => {
+            let config = Config::from_env().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+            verify_jwt(&token, &config.jwt_secret).map_err(|_| StatusCode::UNAUTHORIZED)?
+        }
+        (None, None) => return Err(StatusCode::UNAUTHORIZED),
+    };
+
+    let redis = db.redis.as_ref().ok_or(StatusCode::SERVICE_UNAVAILABLE)?;
+    // Subscribe before reading the counters so no update falls in between
+    let updates = redis.subscribe(&user_channel(&claims.sub)).await.map_err(|e| {
+        tracing::error!("Failed to subscribe to live updates of {}: {}", claims.sub, e);
+        StatusCode::SERVICE_UNAVAILABLE
+    })?;
+    let counters = live_counters(&db, &claims.sub).await.map_err(|e| {
+        tracing::error!("Failed to count live counters of {}: {}", claims.sub, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    let hello = live_message(LiveEvent::Counters, counters);
+
+    Ok(upgrade
+        .max_message_size(MAX_MESSAGE_BYTES)
+        .on_upgrade(move |socket| run_socket(socket, db, claims, hello, updates)))
+}
+
+// The socket closes when the client leaves, the connection breaks, or the token expires.
+// Clients send typing indicators and receipts up the same socket; their pings are answered by
+// the socket itself.
+async fn run_socket(
+    socket: WebSocket,
+    db: Database,
+    claims: Claims,
+    hello: String,
+    updates: impl Stream<Item = String> + Send + 'static,
+) {
+    let (mut sender, mut receiver) = socket.split();
+    let user_id = claims.sub.clone();
+    let mut reading = tokio::spawn(async move {
+        while let Some(Ok(message)) = receiver.next().await {
+            match message {
+                Message::Text(text) => messaging::handle_client_event(&db, &user_id, &text).await,
+                Message::Close(_) => break,
+                _ => {}
+            }
+        }
+    });
+
+    let expires_in = (claims.exp as i64 - chrono::Utc::now().timestamp()).max(0) as u64;
+    let expiry = tokio::time::sleep(Duration::from_secs(expires_in));
+    let first_heartbeat = tokio::time::Instant::now() + HEARTBEAT_INTERVAL;
+    let mut heartbeat = tokio::time::interval_at(first_heartbeat, HEARTBEAT_INTERVAL);
+    tokio::pin!(expiry, updates);
+
+    if sender.send(Message::Text(hello)).await.is_ok() {
+        loop {
+            let outgoing = tokio::select! {
+                _ = &mut reading => break,
+                _ = &mut expiry => break,
+                update = updates.next() => match update {
+                    Some(update) => Message::Text(update),
+                    None => break,
+                },
+                _ = heartbeat.tick() => Message::Ping(Vec::new()),
+            };
+            if sender.send(outgoing).await.is_err() {
+                break;
+            }
+        }
+    }
+
+    reading.abort();
+    let _ = sender.send(Message::Close(None)).await;
+}
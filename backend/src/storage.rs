@@ -0,0 +1,374 @@
+use std::{
+    env,
+    path::{Component, Path, PathBuf},
+    time::SystemTime,
+};
+
+use anyhow::{anyhow, Result};
+use hmac::{Hmac, Mac};
+use reqwest::Client;
+use serde_json::json;
+use sha2::Sha256;
+use tokio::{fs, io::AsyncWriteExt};
+use uuid::Uuid;
+
+use crate::config::Config;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// A file written to private storage, addressed by its storage key (`folder/file_name`).
+#[derive(Debug, Clone)]
+pub struct StoredFile {
+    pub upload_id: Uuid,
+    pub storage_key: String,
+}
+
+/// Root directory for files that must not be served by the public `/uploads` mount.
+pub fn private_upload_root() -> PathBuf {
+    PathBuf::from(env::var("PRIVATE_UPLOAD_DIR").unwrap_or_else(|_| "private_uploads".to_string()))
+}
+
+fn supabase_configured(config: &Config) -> bool {
+    !config.supabase_url.is_empty() && !config.supabase_service_role_key.is_empty()
+}
+
+/// Store a file outside the public uploads mount. Files go to Supabase storage when it is
+/// configured and to `PRIVATE_UPLOAD_DIR` otherwise; either way they are only reachable
+/// through a signed URL.
+pub async fn store_private_file(
+    folder: &str,
+    original_name: Option<&str>,
+    content_type: &str,
+    bytes: Vec<u8>,
+) -> Result<StoredFile> {
+    let config = Config::from_env()?;
+    let upload_id = Uuid::new_v4();
+    let timestamp = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or_default();
+    let extension = original_name
+        .and_then(|name| Path::new(name).extension())
+        .and_then(|ext| ext.to_str())
+        .map(|ext| ext.to_ascii_lowercase())
+        .unwrap_or_else(|| "bin".to_string());
+    let storage_key = format!("{}/{}_{}.{}", folder, timestamp, upload_id, extension);
+
+    if !supabase_configured(&config) {
+        let file_path = private_file_path(&storage_key)?;
+        if let Some(parent) = file_path.parent() {
+            fs::create_dir_all(parent).await?;
+        }
+        let mut file = fs::File::create(&file_path).await?;
+        file.write_all(&bytes).await?;
+
+        return Ok(StoredFile {
+            upload_id,
+            storage_key,
+        });
+    }
+
+    let endpoint = format!(
+        "{}/storage/v1/object/{}/private/{}",
+        config.supabase_url.trim_end_matches('/'),
+        config.supabase_bucket,
+        storage_key
+    );
+
+    let response = Client::new()
+        .post(&endpoint)
+        .header(
+            "Authorization",
+            format!("Bearer {}", config.supabase_service_role_key),
+        )
+        .header("Content-Type", content_type)
+        .header("Content-Length", bytes.len())
+        .header("X-Upsert", "true")
+        .body(bytes)
+        .send()
+        .await?;
+
+    if !response.status().is_success() {
+        let status = response.status();
+        let body = response.text().await.unwrap_or_default();
+        return Err(anyhow!(
+            "Supabase private upload failed with status {}: {}",
+            status,
+            body
+        ));
+    }
+
+    Ok(StoredFile {
+        upload_id,
+        storage_key,
+    })
+}
+
+/// Store a file under the public uploads location and return its public URL. Mirrors the
+/// image/video upload endpoints: Supabase when configured, otherwise `UPLOAD_DIR`.
+pub async fn store_public_file(
+    folder: &str,
+    extension: &str,
+    content_type: &str,
+    bytes: Vec<u8>,
+) -> Result<String> {
+    let config = Config::from_env()?;
+    let timestamp = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or_default();
+    let file_name = format!("{}_{}.{}", timestamp, Uuid::new_v4(), extension);
+
+    if !supabase_configured(&config) {
+        let upload_root =
+            PathBuf::from(env::var("UPLOAD_DIR").unwrap_or_else(|_| "uploads".to_string()));
+        let target_dir = upload_root.join(folder);
+        fs::create_dir_all(&target_dir).await?;
+        let mut file = fs::File::create(target_dir.join(&file_name)).await?;
+        file.write_all(&bytes).await?;
+
+        return Ok(format!("/uploads/{}/{}", folder, file_name));
+    }
+
+    let storage_path = format!("{}/{}", folder, file_name);
+    let endpoint = format!(
+        "{}/storage/v1/object/{}/{}",
+        config.supabase_url.trim_end_matches('/'),
+        config.supabase_bucket,
+        storage_path
+    );
+
+    let response = Client::new()
+        .post(&endpoint)
+        .header(
+            "Authorization",
+            format!("Bearer {}", config.supabase_service_role_key),
+        )
+        .header("Content-Type", content_type)
+        .header("Content-Length", bytes.len())
+        .header("X-Upsert", "true")
+        .body(bytes)
+        .send()
+        .await?;
+
+    if !response.status().is_success() {
+        let status = response.status();
+        let body = response.text().await.unwrap_or_default();
+        return Err(anyhow!(
+            "Supabase upload failed with status {}: {}",
+            status,
+            body
+        ));
+    }
+
+    Ok(format!(
+        "{}/storage/v1/object/public/{}/{}",
+        config.supabase_url.trim_end_matches('/'),
+        config.supabase_bucket,
+        storage_path
+    ))
+}
+
+/// Remove a privately stored file. Missing files are not treated as errors.
+pub async fn delete_private_file(storage_key: &str) -> Result<()> {
+    let config = Config::from_env()?;
+
+    if !supabase_configured(&config) {
+        let file_path = private_file_path(storage_key)?;
+        if let Err(error) = fs::remove_file(&file_path).await {
+            if error.kind() != std::io::ErrorKind::NotFound {
+                return Err(error.into());
+            }
+        }
+        return Ok(());
+    }
+
+    let endpoint = format!(
+        "{}/storage/v1/object/{}/private/{}",
+        config.supabase_url.trim_end_matches('/'),
+        config.supabase_bucket,
+        storage_key
+    );
+
+    Client::new()
+        .delete(&endpoint)
+        .header(
+            "Authorization",
+            format!("Bearer {}", config.supabase_service_role_key),
+        )
+        .send()
+        .await?;
+
+    Ok(())
+}
+
+/// Read a privately stored file back into memory.
+pub async fn read_private_file(storage_key: &str) -> Result<Vec<u8>> {
+    let config = Config::from_env()?;
+
+    if !supabase_configured(&config) {
+        let file_path = private_file_path(storage_key)?;
+        return Ok(fs::read(&file_path).await?);
+    }
+
+    let endpoint = format!(
+        "{}/storage/v1/object/{}/private/{}",
+        config.supabase_url.trim_end_matches('/'),
+        config.supabase_bucket,
+        storage_key
+    );
+
+    let response = Client::new()
+        .get(&endpoint)
+        .header(
+            "Authorization",
+            format!("Bearer {}", config.supabase_service_role_key),
+        )
+        .send()
+        .await?;
+
+    if !response.status().is_success() {
+        let status = response.status();
+        let body = response.text().await.unwrap_or_default();
+        return Err(anyhow!(
+            "Supabase private download failed with status {}: {}",
+            status,
+            body
+        ));
+    }
+
+    Ok(response.bytes().await?.to_vec())
+}
+
+/// Create a download URL for a private file that stops working after `ttl_seconds`.
+/// Returns the URL together with its expiry as a unix timestamp.
+pub async fn create_signed_url(storage_key: &str, ttl_seconds: i64) -> Result<(String, i64)> {
+    let config = Config::from_env()?;
+    let expires = chrono::Utc::now().timestamp() + ttl_seconds;
+
+    if !supabase_configured(&config) {
+        let signature = sign(&config.jwt_secret, storage_key, expires);
+        let url = format!(
+            "/api/upload/private/{}?expires={}&signature={}",
+            storage_key, expires, signature
+        );
+        return Ok((url, expires));
+    }
+
+    let endpoint = format!(
+        "{}/storage/v1/object/sign/{}/private/{}",
+        config.supabase_url.trim_end_matches('/'),
+        config.supabase_bucket,
+        storage_key
+    );
+
+    let response = Client::new()
+        .post(&endpoint)
+        .header(
+            "Authorization",
+            format!("Bearer {}", config.supabase_service_role_key),
+        )
+        .json(&json!({ "expiresIn": ttl_seconds }))
+        .send()
+        .await?;
+
+    if !response.status().is_success() {
+        let status = response.status();
+        let body = response.text().await.unwrap_or_default();
+        return Err(anyhow!(
+            "Supabase signed URL request failed with status {}: {}",
+            status,
+            body
+        ));
+    }
+
+    let body: serde_json::Value = response.json().await?;
+    let signed_path = body
+        .get("signedURL")
+        .or_else(|| body.get("signedUrl"))
+        .and_then(|value| value.as_str())
+        .ok_or_else(|| anyhow!("Supabase signed URL response missing signedURL"))?;
+
+    Ok((
+        format!(
+            "{}/storage/v1{}",
+            config.supabase_url.trim_end_matches('/'),
+            signed_path
+        ),
+        expires,
+    ))
+}
+
+/// Check a locally signed URL produced by [`create_signed_url`].
+pub fn verify_signed_url(storage_key: &str, expires: i64, signature: &str) -> bool {
+    if expires < chrono::Utc::now().timestamp() {
+        return false;
+    }
+
+    let config = match Config::from_env() {
+        Ok(config) => config,
+        Err(_) => return false,
+    };
+
+    let provided = match hex::decode(signature) {
+        Ok(bytes) => bytes,
+        Err(_) => return false,
+    };
+
+    let mut mac = match HmacSha256::new_from_slice(config.jwt_secret.as_bytes()) {
+        Ok(mac) => mac,
+        Err(_) => return false,
+    };
+    mac.update(format!("{}:{}", storage_key, expires).as_bytes());
+    mac.verify_slice(&provided).is_ok()
+}
+
+/// Marks a stored file URL, such as a product's download URL, as a private storage key.
+const PRIVATE_URL_PREFIX: &str = "private://";
+
+/// Where a privately stored file can be read from.
+pub enum PrivateFileLocation {
+    Local(PathBuf),
+    /// A short-lived signed Supabase URL.
+    Remote(String),
+}
+
+/// The URL a privately stored file is recorded under, e.g. `private://products/…`.
+pub fn private_file_url(storage_key: &str) -> String {
+    format!("{}{}", PRIVATE_URL_PREFIX, storage_key)
+}
+
+/// The storage key behind a URL made by [`private_file_url`].
+pub fn private_storage_key(url: &str) -> Option<&str> {
+    url.strip_prefix(PRIVATE_URL_PREFIX)
+}
+
+/// Find a privately stored file for reading it on the server.
+pub async fn locate_private_file(storage_key: &str, ttl_seconds: i64) -> Result<PrivateFileLocation> {
+    let config = Config::from_env()?;
+    if !supabase_configured(&config) {
+        return Ok(PrivateFileLocation::Local(private_file_path(storage_key)?));
+    }
+    let (url, _) = create_signed_url(storage_key, ttl_seconds).await?;
+    Ok(PrivateFileLocation::Remote(url))
+}
+
+/// Resolve a storage key to a path under the private upload root, rejecting traversal.
+pub fn private_file_path(storage_key: &str) -> Result<PathBuf> {
+    let relative = Path::new(storage_key);
+    if relative
+        .components()
+        .any(|component| !matches!(component, Component::Normal(_)))
+    {
+        return Err(anyhow!("Invalid storage key"));
+    }
+
+    Ok(private_upload_root().join(relative))
+}
+
+fn sign(secret: &str, storage_key: &str, expires: i64) -> String {
+    let mut mac =
+        HmacSha256::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(format!("{}:{}", storage_key, expires).as_bytes());
+    hex::encode(mac.finalize().into_bytes())
+}
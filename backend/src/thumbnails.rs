@@ -0,0 +1,54 @@
+use std::io::Cursor;
+
+use image::{ImageFormat, ImageReader, Limits};
+
+// Thumbnails for uploaded images, always written as PNG. Other formats get no thumbnail; clients
+// show their own placeholder.
+
+/// Images with more pixels than this aren't decoded, so a small file can't claim a huge canvas.
+const MAX_PIXELS: u64 = 50_000_000;
+
+pub struct Thumbnail {
+    pub png: Vec<u8>,
+}
+
+fn format(content_type: &str) -> Option<ImageFormat> {
+    match content_type {
+        "image/png" => Some(ImageFormat::Png),
+        "image/jpeg" | "image/jpg" => Some(ImageFormat::Jpeg),
+        "image/gif" => Some(ImageFormat::Gif),
+        _ => None,
+    }
+}
+
+fn reader<'a>(content_type: &str, bytes: &'a [u8]) -> Option<ImageReader<Cursor<&'a [u8]>>> {
+    let mut reader = ImageReader::with_format(Cursor::new(bytes), format(content_type)?);
+    let mut limits = Limits::default();
+    limits.max_alloc = Some(MAX_PIXELS * 4);
+    reader.limits(limits);
+    Some(reader)
+}
+
+/// Width and height of a PNG, JPEG or GIF, read from its header.
+pub fn dimensions(content_type: &str, bytes: &[u8]) -> Option<(u32, u32)> {
+    reader(content_type, bytes)?.into_dimensions().ok()
+}
+
+/// A PNG of the image scaled to fit within `max_side` pixels each way; images already that
+/// small are re-encoded as they are. `None` when the format isn't supported or the file doesn't
+/// decode. Decoding is CPU-bound, so async callers should run this on a blocking thread.
+pub fn generate(content_type: &str, bytes: &[u8], max_side: u32) -> Option<Thumbnail> {
+    let (width, height) = dimensions(content_type, bytes)?;
+    if width as u64 * height as u64 > MAX_PIXELS {
+        return None;
+    }
+    let mut image = reader(content_type, bytes)?.decode().ok()?;
+    let max_side = max_side.max(1);
+    if width.max(height) > max_side {
+        image = image.thumbnail(max_side, max_side);
+    }
+
+    let mut png = Vec::new();
+    image.write_to(&mut Cursor::new(&mut png), ImageFormat::Png).ok()?;
+    Some(Thumbnail { png })
+}
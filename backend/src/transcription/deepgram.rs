@@ -0,0 +1,92 @@
+use anyhow::Context;
+
+use super::{Segment, SpeechToText, Transcript};
+use crate::config::TranscriptionConfig;
+
+const LISTEN_URL: &str = "https://api.deepgram.com/v1/listen";
+
+pub struct Deepgram {
+    api_key: String,
+    model: String,
+}
+
+impl Deepgram {
+    pub fn new(config: &TranscriptionConfig) -> anyhow::Result<Self> {
+        if config.deepgram_api_key.trim().is_empty() {
+            anyhow::bail!("DEEPGRAM_API_KEY is not set");
+        }
+        Ok(Deepgram {
+            api_key: config.deepgram_api_key.trim().to_string(),
+            model: config.deepgram_model.clone(),
+        })
+    }
+}
+
+#[axum::async_trait]
+impl SpeechToText for Deepgram {
+    fn name(&self) -> &'static str {
+        "deepgram"
+    }
+
+    // Utterances give the timed segments; the language is detected rather than taken from the
+    // podcast, whose language is free text.
+    async fn transcribe(&self, audio: Vec<u8>, content_type: &str) -> anyhow::Result<Transcript> {
+        let response = reqwest::Client::new()
+            .post(LISTEN_URL)
+            .query(&[
+                ("model", self.model.as_str()),
+                ("smart_format", "true"),
+                ("utterances", "true"),
+                ("detect_language", "true"),
+            ])
+            .header("Authorization", format!("Token {}", self.api_key))
+            .header("Content-Type", content_type)
+            .body(audio)
+            .send()
+            .await
+            .context("failed to reach Deepgram")?;
+        let status = response.status();
+        let body: serde_json::Value = response.json().await.context("Deepgram returned invalid JSON")?;
+        if !status.is_success() {
+            anyhow::bail!(
+                "Deepgram rejected the audio ({}): {}",
+                status,
+                body.get("err_msg").and_then(|message| message.as_str()).unwrap_or_default()
+            );
+        }
+
+        let channel = body.pointer("/results/channels/0");
+        let text = channel
+            .and_then(|channel| channel.pointer("/alternatives/0/transcript"))
+            .and_then(|text| text.as_str())
+            .unwrap_or_default()
+            .to_string();
+        let language = channel
+            .and_then(|channel| channel.get("detected_language"))
+            .and_then(|language| language.as_str())
+            .map(str::to_string);
+        let segments = body
+            .pointer("/results/utterances")
+            .and_then(|utterances| utterances.as_array())
+            .map(|utterances| {
+                utterances
+                    .iter()
+                    .filter_map(|utterance| {
+                        Some(Segment {
+                            start: utterance.get("start")?.as_f64()?,
+                            end: utterance.get("end")?.as_f64()?,
+                            text: utterance.get("transcript")?.as_str()?.trim().to_string(),
+                        })
+                    })
+                    .filter(|segment| !segment.text.is_empty())
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        Ok(Transcript {
+            text,
+            segments,
+            language,
+        })
+    }
+}
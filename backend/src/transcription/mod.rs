@@ -0,0 +1,140 @@
+use serde::{Deserialize, Serialize};
+
+use crate::config::TranscriptionConfig;
+
+mod deepgram;
+
+/// A stretch of speech and when it was said, in seconds from the start of the episode.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Segment {
+    pub start: f64,
+    pub end: f64,
+    pub text: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct Transcript {
+    pub text: String,
+    /// Empty for plain text transcripts, which carry no timing.
+    pub segments: Vec<Segment>,
+    pub language: Option<String>,
+}
+
+/// A speech-to-text service that can transcribe an episode's audio.
+#[axum::async_trait]
+pub trait SpeechToText: Send + Sync {
+    /// Stored with the transcript, so it is known which service produced it.
+    fn name(&self) -> &'static str;
+
+    async fn transcribe(&self, audio: Vec<u8>, content_type: &str) -> anyhow::Result<Transcript>;
+}
+
+/// The configured speech-to-text service; `None` when automatic transcripts are turned off.
+pub fn provider(config: &TranscriptionConfig) -> anyhow::Result<Option<Box<dyn SpeechToText>>> {
+    match config.provider.as_str() {
+        "deepgram" => Ok(Some(Box::new(deepgram::Deepgram::new(config)?))),
+        "none" | "" => Ok(None),
+        other => anyhow::bail!("Unknown TRANSCRIPTION_PROVIDER '{}'", other),
+    }
+}
+
+/// Read a WebVTT or SubRip file. Cue identifiers, settings and voice tags are dropped.
+pub fn parse_cues(content: &str) -> Option<Vec<Segment>> {
+    let content = content.replace("\r\n", "\n");
+    let mut segments = Vec::new();
+
+    for block in content.split("\n\n") {
+        let mut lines = block.lines().map(str::trim).skip_while(|line| !line.contains("-->"));
+        let Some(timing) = lines.next() else {
+            continue;
+        };
+        let (start, rest) = timing.split_once("-->")?;
+        let end = rest.split_whitespace().next()?;
+        let text = lines
+            .filter(|line| !line.is_empty())
+            .map(strip_tags)
+            .collect::<Vec<_>>()
+            .join(" ");
+        if text.is_empty() {
+            continue;
+        }
+        segments.push(Segment {
+            start: parse_timestamp(start.trim())?,
+            end: parse_timestamp(end)?,
+            text,
+        });
+    }
+
+    if segments.is_empty() {
+        None
+    } else {
+        Some(segments)
+    }
+}
+
+/// `HH:MM:SS.mmm`, `MM:SS.mmm`, or the SubRip `HH:MM:SS,mmm`.
+fn parse_timestamp(value: &str) -> Option<f64> {
+    let parts = value.replace(',', ".");
+    let parts = parts.split(':').collect::<Vec<_>>();
+    if !(2..=3).contains(&parts.len()) {
+        return None;
+    }
+    let mut seconds = 0.0;
+    for part in parts {
+        seconds = seconds * 60.0 + part.parse::<f64>().ok()?;
+    }
+    Some(seconds).filter(|seconds| seconds.is_finite() && *seconds >= 0.0)
+}
+
+fn strip_tags(line: &str) -> String {
+    let mut text = String::with_capacity(line.len());
+    let mut in_tag = false;
+    for c in line.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' if in_tag => in_tag = false,
+            _ if !in_tag => text.push(c),
+            _ => {}
+        }
+    }
+    text.trim().to_string()
+}
+
+fn format_timestamp(seconds: f64, decimal: char) -> String {
+    let millis = (seconds.max(0.0) * 1000.0).round() as u64;
+    format!(
+        "{:02}:{:02}:{:02}{}{:03}",
+        millis / 3_600_000,
+        millis / 60_000 % 60,
+        millis / 1000 % 60,
+        decimal,
+        millis % 1000
+    )
+}
+
+pub fn render_vtt(segments: &[Segment]) -> String {
+    let mut vtt = String::from("WEBVTT\n\n");
+    for segment in segments {
+        vtt.push_str(&format!(
+            "{} --> {}\n{}\n\n",
+            format_timestamp(segment.start, '.'),
+            format_timestamp(segment.end, '.'),
+            segment.text
+        ));
+    }
+    vtt
+}
+
+pub fn render_srt(segments: &[Segment]) -> String {
+    let mut srt = String::new();
+    for (index, segment) in segments.iter().enumerate() {
+        srt.push_str(&format!(
+            "{}\n{} --> {}\n{}\n\n",
+            index + 1,
+            format_timestamp(segment.start, ','),
+            format_timestamp(segment.end, ','),
+            segment.text
+        ));
+    }
+    srt
+}